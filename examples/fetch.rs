@@ -110,7 +110,7 @@ fn run(args: &Args) -> Result<(), git2::Error> {
     // commits. This may be needed even if there was no packfile to download,
     // which can happen e.g. when the branches have been changed but all the
     // needed objects are available locally.
-    try!(remote.update_tips(None, None));
+    try!(remote.update_tips(None));
 
     Ok(())
 }
@@ -57,11 +57,11 @@ fn main() {
     }
 
     let mut remote = r.find_remote("origin").unwrap();
-    remote.fetch(&["refs/heads/*:refs/heads/*"], None, None).unwrap();
+    remote.fetch(&["refs/heads/*:refs/heads/*"], None).unwrap();
     let b = r.find_branch("master", git2::BranchType::Local).unwrap();
     let id = b.get().target().unwrap();
     let obj = r.find_object(id, None).unwrap();
-    r.reset(&obj, git2::ResetType::Hard, None, None, None).unwrap();;
+    r.reset(&obj, git2::ResetType::Hard, None).unwrap();;
 
     assert!(File::open(&td2.path().join("bar")).is_ok());
 }
@@ -10,7 +10,7 @@ fn main() {
     register_dep("SSH2");
     register_dep("OPENSSL");
 
-    match pkg_config::Config::new().atleast_version("0.22.0").find("libgit2") {
+    match pkg_config::Config::new().atleast_version("1.8.0").find("libgit2") {
         Ok(_) => return,
         Err(..) => {}
     }
@@ -43,9 +43,11 @@ fn main() {
         "bench" | "release" => "Release",
         _ => "Debug",
     };
-    run(cmd.arg("-DTHREADSAFE=ON")
+    run(cmd.arg("-DUSE_THREADS=ON")
            .arg("-DBUILD_SHARED_LIBS=OFF")
-           .arg("-DBUILD_CLAR=OFF")
+           .arg("-DBUILD_TESTS=OFF")
+           .arg("-DBUILD_CLI=OFF")
+           .arg("-DUSE_SSH=ON")
            .arg(format!("-DCMAKE_BUILD_TYPE={}", profile))
            .arg(format!("-DCMAKE_INSTALL_PREFIX={}", dst.display()))
            .arg("-DBUILD_EXAMPLES=OFF")
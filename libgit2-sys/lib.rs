@@ -12,19 +12,32 @@ pub use git_branch_t::*;
 pub use git_error_code::*;
 pub use git_repository_state_t::*;
 pub use git_direction::*;
+pub use git_remote_autotag_option_t::*;
+pub use git_proxy_t::*;
+pub use git_remote_redirect_t::*;
 pub use git_clone_local_t::*;
 pub use git_remote_completion_type::*;
 pub use git_checkout_notify_t::*;
 pub use git_checkout_strategy_t::*;
 pub use git_reset_t::*;
+pub use git_merge_file_favor_t::*;
+pub use git_rebase_operation_t::*;
+pub use git_stash_apply_progress_t::*;
+pub use git_attr_value_t::*;
+pub use git_filter_mode_t::*;
+pub use git_apply_location_t::*;
 pub use git_otype::*;
 pub use git_filemode_t::*;
 pub use git_treewalk_mode::*;
+pub use git_tree_update_t::*;
 pub use git_config_level_t::*;
+pub use git_libgit2_opt_t::*;
+pub use git_trace_level_t::*;
 pub use git_submodule_update_t::*;
 pub use git_submodule_ignore_t::*;
 pub use git_credtype_t::*;
 pub use git_repository_init_flag_t::*;
+pub use git_repository_open_flag_t::*;
 pub use git_repository_init_mode_t::*;
 pub use git_index_add_option_t::*;
 pub use git_cert_t::*;
@@ -34,9 +47,12 @@ pub use git_status_show_t::*;
 pub use git_delta_t::*;
 pub use git_sort::*;
 pub use git_diff_format_t::*;
+pub use git_diff_binary_t::*;
 pub use git_diff_stats_format_t::*;
 pub use git_smart_service_t::*;
 pub use git_cert_ssh_t::*;
+pub use git_cert_ssh_raw_type_t::*;
+pub use git_fetch_prune_t::*;
 
 use libc::{c_int, c_char, c_uint, size_t, c_uchar, c_void, c_ushort};
 
@@ -46,14 +62,22 @@ pub const GIT_CLONE_OPTIONS_VERSION: c_uint = 1;
 pub const GIT_CHECKOUT_OPTIONS_VERSION: c_uint = 1;
 pub const GIT_REMOTE_CALLBACKS_VERSION: c_uint = 1;
 pub const GIT_STATUS_OPTIONS_VERSION: c_uint = 1;
+pub const GIT_PUSH_OPTIONS_VERSION: c_uint = 1;
+pub const GIT_PROXY_OPTIONS_VERSION: c_uint = 1;
+pub const GIT_INDEXER_OPTIONS_VERSION: c_uint = 1;
+pub const GIT_FETCH_OPTIONS_VERSION: c_uint = 1;
+pub const GIT_REMOTE_CREATE_OPTIONS_VERSION: c_uint = 1;
 
+pub enum git_blame {}
 pub enum git_blob {}
 pub enum git_branch_iterator {}
 pub enum git_commit {}
+pub enum git_annotated_commit {}
 pub enum git_config {}
 pub enum git_config_iterator {}
 pub enum git_index {}
 pub enum git_object {}
+pub enum git_rebase {}
 pub enum git_reference {}
 pub enum git_reference_iterator {}
 pub enum git_refspec {}
@@ -64,16 +88,38 @@ pub enum git_submodule {}
 pub enum git_tag {}
 pub enum git_tree {}
 pub enum git_tree_entry {}
+pub enum git_treebuilder {}
 pub enum git_push {}
 pub enum git_note {}
 pub enum git_note_iterator {}
 pub enum git_status_list {}
 pub enum git_pathspec {}
+pub enum git_filter_source {}
 pub enum git_pathspec_match_list {}
 pub enum git_diff {}
 pub enum git_diff_stats {}
+pub enum git_patch {}
 pub enum git_reflog {}
 pub enum git_reflog_entry {}
+pub enum git_worktree {}
+pub enum git_odb {}
+pub enum git_odb_backend {}
+pub enum git_odb_object {}
+pub enum git_refdb {}
+pub enum git_filter_list {}
+pub enum git_packbuilder {}
+pub enum git_indexer {}
+pub enum git_remote_connect_options {}
+pub enum git_fetch_negotiation {}
+
+#[repr(C)]
+pub struct git_indexer_options {
+    pub version: c_uint,
+    pub progress_cb: Option<git_transfer_progress_cb>,
+    pub progress_cb_payload: *mut c_void,
+    pub verify: c_uchar,
+}
+pub enum git_commit_graph_writer {}
 
 #[repr(C)]
 pub struct git_revspec {
@@ -94,6 +140,13 @@ pub struct git_oid {
     pub id: [u8; GIT_OID_RAWSZ],
 }
 
+#[repr(C)]
+#[derive(Copy)]
+pub struct git_oidarray {
+    pub ids: *mut git_oid,
+    pub count: size_t,
+}
+
 #[repr(C)]
 #[derive(Copy)]
 pub struct git_strarray {
@@ -113,12 +166,18 @@ pub struct git_signature {
 pub struct git_time {
     pub time: git_time_t,
     pub offset: c_int,
+    pub sign: c_char,
 }
 
 pub type git_off_t = i64;
 pub type git_time_t = i64;
 
 pub type git_revparse_mode_t = c_int;
+pub const GIT_FEATURE_THREADS: c_int = 1 << 0;
+pub const GIT_FEATURE_HTTPS: c_int = 1 << 1;
+pub const GIT_FEATURE_SSH: c_int = 1 << 2;
+pub const GIT_FEATURE_NSEC: c_int = 1 << 3;
+
 pub const GIT_REVPARSE_SINGLE: c_int = 1 << 0;
 pub const GIT_REVPARSE_RANGE: c_int = 1 << 1;
 pub const GIT_REVPARSE_MERGE_BASE: c_int = 1 << 2;
@@ -139,11 +198,26 @@ pub enum git_error_code {
     GIT_EUNMERGED = -10,
     GIT_ENONFASTFORWARD = -11,
     GIT_EINVALIDSPEC = -12,
-    GIT_EMERGECONFLICT = -13,
+    GIT_ECONFLICT = -13,
     GIT_ELOCKED = -14,
     GIT_EMODIFIED = -15,
+    GIT_EAUTH = -16,
+    GIT_ECERTIFICATE = -17,
+    GIT_EAPPLIED = -18,
+    GIT_EPEEL = -19,
+    GIT_EEOF = -20,
+    GIT_EINVALID = -21,
+    GIT_EUNCOMMITTED = -22,
+    GIT_EDIRECTORY = -23,
+    GIT_EMERGECONFLICT = -24,
     GIT_PASSTHROUGH = -30,
     GIT_ITEROVER = -31,
+    GIT_RETRY = -32,
+    GIT_EMISMATCH = -33,
+    GIT_EINDEXDIRTY = -34,
+    GIT_EAPPLYFAIL = -35,
+    GIT_EOWNER = -36,
+    GIT_TIMEOUT = -37,
 }
 
 #[repr(C)]
@@ -179,6 +253,13 @@ pub enum git_error_t {
     GITERR_CHERRYPICK,
     GITERR_DESCRIBE,
     GITERR_REBASE,
+    GITERR_FILESYSTEM,
+    GITERR_PATCH,
+    GITERR_WORKTREE,
+    GITERR_SHA,
+    GITERR_HTTP,
+    GITERR_INTERNAL,
+    GITERR_GRAFTS,
 }
 pub use git_error_t::*;
 
@@ -204,15 +285,75 @@ pub enum git_direction {
     GIT_DIRECTION_PUSH = 1,
 }
 
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_remote_autotag_option_t {
+    GIT_REMOTE_DOWNLOAD_TAGS_UNSPECIFIED = 0,
+    GIT_REMOTE_DOWNLOAD_TAGS_AUTO,
+    GIT_REMOTE_DOWNLOAD_TAGS_NONE,
+    GIT_REMOTE_DOWNLOAD_TAGS_ALL,
+}
+
+pub const GIT_REMOTE_UPDATE_FETCHHEAD: c_uint = 1 << 0;
+pub const GIT_REMOTE_UPDATE_REPORT_UNCHANGED: c_uint = 1 << 1;
+
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_proxy_t {
+    GIT_PROXY_NONE,
+    GIT_PROXY_AUTO,
+    GIT_PROXY_SPECIFIED,
+}
+
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_remote_redirect_t {
+    GIT_REMOTE_REDIRECT_NONE = 1 << 0,
+    GIT_REMOTE_REDIRECT_INITIAL = 1 << 1,
+    GIT_REMOTE_REDIRECT_ALL = 1 << 2,
+}
+
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_fetch_prune_t {
+    GIT_FETCH_PRUNE_UNSPECIFIED,
+    GIT_FETCH_PRUNE,
+    GIT_FETCH_NO_PRUNE,
+}
+
+#[repr(C)]
+pub struct git_fetch_options {
+    pub version: c_int,
+    pub callbacks: git_remote_callbacks,
+    pub prune: git_fetch_prune_t,
+    pub update_fetchhead: c_uint,
+    pub download_tags: git_remote_autotag_option_t,
+    pub proxy_opts: git_proxy_options,
+    pub depth: c_int,
+    pub follow_redirects: git_remote_redirect_t,
+    pub custom_headers: git_strarray,
+}
+
+#[repr(C)]
+pub struct git_remote_create_options {
+    pub version: c_uint,
+    pub repository: *mut git_repository,
+    pub name: *const c_char,
+    pub fetchspec: *const c_char,
+    pub flags: c_uint,
+}
+
+pub const GIT_REMOTE_CREATE_SKIP_INSTEADOF: c_uint = 1 << 0;
+pub const GIT_REMOTE_CREATE_SKIP_DEFAULT_FETCHSPEC: c_uint = 1 << 1;
+
 #[repr(C)]
 pub struct git_clone_options {
     pub version: c_uint,
     pub checkout_opts: git_checkout_options,
-    pub remote_callbacks: git_remote_callbacks,
+    pub fetch_opts: git_fetch_options,
     pub bare: c_int,
     pub local: git_clone_local_t,
     pub checkout_branch: *const c_char,
-    pub signature: *mut git_signature,
     pub repository_cb: Option<git_repository_create_cb>,
     pub repository_cb_payload: *mut c_void,
     pub remote_cb: Option<git_remote_create_cb>,
@@ -243,10 +384,21 @@ pub struct git_checkout_options {
     pub progress_payload: *mut c_void,
     pub paths: git_strarray,
     pub baseline: *mut git_tree,
+    pub baseline_index: *mut git_index,
     pub target_directory: *const c_char,
     pub ancestor_label: *const c_char,
     pub our_label: *const c_char,
     pub their_label: *const c_char,
+    pub perfdata_cb: Option<git_checkout_perfdata_cb>,
+    pub perfdata_payload: *mut c_void,
+}
+
+#[repr(C)]
+#[derive(Copy)]
+pub struct git_checkout_perfdata {
+    pub mkdir_calls: size_t,
+    pub stat_calls: size_t,
+    pub chmod_calls: size_t,
 }
 
 pub type git_checkout_notify_cb = extern fn(git_checkout_notify_t,
@@ -259,6 +411,8 @@ pub type git_checkout_progress_cb = extern fn(*const c_char,
                                               size_t,
                                               size_t,
                                               *mut c_void);
+pub type git_checkout_perfdata_cb = extern fn(*const git_checkout_perfdata,
+                                              *mut c_void);
 
 #[repr(C)]
 pub struct git_remote_callbacks {
@@ -278,9 +432,37 @@ pub struct git_remote_callbacks {
     pub push_update_reference: Option<extern fn(*const c_char,
                                                 *const c_char,
                                                 *mut c_void) -> c_int>,
+    pub push_negotiation: Option<git_push_negotiation>,
+    pub transport: Option<git_transport_cb>,
+    pub remote_ready: Option<git_remote_ready_cb>,
     pub payload: *mut c_void,
+    pub resolve_url: Option<git_url_resolve_cb>,
+    pub update_refs: Option<extern fn(*const c_char,
+                                      *const git_oid,
+                                      *const git_oid,
+                                      *mut git_refspec,
+                                      *mut c_void) -> c_int>,
 }
 
+#[repr(C)]
+pub struct git_push_update {
+    pub src_refname: *mut c_char,
+    pub dst_refname: *mut c_char,
+    pub src: git_oid,
+    pub dst: git_oid,
+}
+
+pub type git_push_negotiation = extern fn(*mut *const git_push_update,
+                                          size_t,
+                                          *mut c_void) -> c_int;
+pub type git_remote_ready_cb = extern fn(*mut git_remote,
+                                        c_int,
+                                        *mut c_void) -> c_int;
+pub type git_url_resolve_cb = extern fn(*mut git_buf,
+                                        *const c_char,
+                                        c_int,
+                                        *mut c_void) -> c_int;
+
 #[repr(C)]
 #[derive(Copy)]
 pub enum git_remote_completion_type {
@@ -298,6 +480,10 @@ pub type git_transfer_progress_cb = extern fn(*const git_transfer_progress,
                                               *mut c_void) -> c_int;
 pub type git_packbuilder_progress = extern fn(c_int, c_uint, c_uint,
                                               *mut c_void) -> c_int;
+pub type git_packbuilder_foreach_cb = extern fn(*mut c_void, size_t,
+                                                *mut c_void) -> c_int;
+pub const GIT_PACKBUILDER_ADDING_OBJECTS: c_int = 0;
+pub const GIT_PACKBUILDER_DELTAFICATION: c_int = 1;
 pub type git_push_transfer_progress = extern fn(c_uint, c_uint, size_t,
                                                 *mut c_void) -> c_int;
 pub type git_transport_certificate_check_cb = extern fn(*mut git_cert,
@@ -308,8 +494,10 @@ pub type git_transport_certificate_check_cb = extern fn(*mut git_cert,
 #[repr(C)]
 #[derive(Copy, PartialEq)]
 pub enum git_cert_t {
+    GIT_CERT_NONE,
     GIT_CERT_X509,
     GIT_CERT_HOSTKEY_LIBSSH2,
+    GIT_CERT_STRARRAY,
 }
 
 #[repr(C)]
@@ -323,6 +511,10 @@ pub struct git_cert_hostkey {
     pub kind: git_cert_ssh_t,
     pub hash_md5: [u8; 16],
     pub hash_sha1: [u8; 20],
+    pub hash_sha256: [u8; 32],
+    pub raw_type: git_cert_ssh_raw_type_t,
+    pub hostkey: *const c_char,
+    pub hostkey_len: size_t,
 }
 
 #[repr(C)]
@@ -337,6 +529,20 @@ pub struct git_cert_x509 {
 pub enum git_cert_ssh_t {
     GIT_CERT_SSH_MD5 = 1 << 0,
     GIT_CERT_SSH_SHA1 = 1 << 1,
+    GIT_CERT_SSH_SHA256 = 1 << 2,
+    GIT_CERT_SSH_RAW = 1 << 3,
+}
+
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_cert_ssh_raw_type_t {
+    GIT_CERT_SSH_RAW_TYPE_UNKNOWN = 0,
+    GIT_CERT_SSH_RAW_TYPE_RSA = 1,
+    GIT_CERT_SSH_RAW_TYPE_DSS = 2,
+    GIT_CERT_SSH_RAW_TYPE_KEY_ECDSA_256 = 3,
+    GIT_CERT_SSH_RAW_TYPE_KEY_ECDSA_384 = 4,
+    GIT_CERT_SSH_RAW_TYPE_KEY_ECDSA_521 = 5,
+    GIT_CERT_SSH_RAW_TYPE_KEY_ED25519 = 6,
 }
 
 #[repr(C)]
@@ -358,6 +564,7 @@ pub struct git_diff_file {
     pub size: git_off_t,
     pub flags: u32,
     pub mode: u16,
+    pub id_abbrev: u16,
 }
 
 pub type git_repository_create_cb = extern fn(*mut *mut git_repository,
@@ -401,6 +608,7 @@ pub enum git_status_t {
     GIT_STATUS_WT_UNREADABLE = (1 << 12),
 
     GIT_STATUS_IGNORED = (1 << 14),
+    GIT_STATUS_CONFLICTED = (1 << 15),
 }
 
 #[repr(C)]
@@ -446,6 +654,7 @@ pub enum git_delta_t {
     GIT_DELTA_UNTRACKED = 7,
     GIT_DELTA_TYPECHANGE = 8,
     GIT_DELTA_UNREADABLE = 9,
+    GIT_DELTA_CONFLICTED = 10,
 }
 
 #[repr(C)]
@@ -454,6 +663,8 @@ pub struct git_status_options {
     pub show: git_status_show_t,
     pub flags: c_uint,
     pub pathspec: git_strarray,
+    pub baseline: *mut git_tree,
+    pub rename_threshold: u16,
 }
 
 #[repr(C)]
@@ -478,8 +689,8 @@ pub struct git_status_entry {
 pub enum git_checkout_strategy_t {
     GIT_CHECKOUT_NONE = 0,
     GIT_CHECKOUT_SAFE = (1 << 0),
-    GIT_CHECKOUT_SAFE_CREATE = (1 << 1),
-    GIT_CHECKOUT_FORCE = (1 << 2),
+    GIT_CHECKOUT_FORCE = (1 << 1),
+    GIT_CHECKOUT_RECREATE_MISSING = (1 << 2),
     GIT_CHECKOUT_ALLOW_CONFLICTS = (1 << 4),
     GIT_CHECKOUT_REMOVE_UNTRACKED = (1 << 5),
     GIT_CHECKOUT_REMOVE_IGNORED = (1 << 6),
@@ -494,12 +705,371 @@ pub enum git_checkout_strategy_t {
     GIT_CHECKOUT_DONT_OVERWRITE_IGNORED = (1 << 19),
     GIT_CHECKOUT_CONFLICT_STYLE_MERGE = (1 << 20),
     GIT_CHECKOUT_CONFLICT_STYLE_DIFF3 = (1 << 21),
+    GIT_CHECKOUT_DONT_REMOVE_EXISTING = (1 << 22),
+    GIT_CHECKOUT_DONT_WRITE_INDEX = (1 << 23),
+    GIT_CHECKOUT_DRY_RUN = (1 << 24),
+    GIT_CHECKOUT_CONFLICT_STYLE_ZDIFF3 = (1 << 25),
 
     GIT_CHECKOUT_UPDATE_SUBMODULES = (1 << 16),
     GIT_CHECKOUT_UPDATE_SUBMODULES_IF_CHANGED = (1 << 17),
 
 }
 
+#[repr(C)]
+pub struct git_merge_options {
+    pub version: c_uint,
+    pub flags: u32,
+    pub rename_threshold: c_uint,
+    pub target_limit: c_uint,
+    pub metric: *mut git_diff_similarity_metric,
+    pub recursion_limit: c_uint,
+    pub default_driver: *const c_char,
+    pub file_favor: git_merge_file_favor_t,
+    pub file_flags: u32,
+}
+
+pub const GIT_MERGE_OPTIONS_VERSION: c_uint = 1;
+
+pub type git_merge_flag_t = u32;
+pub const GIT_MERGE_FIND_RENAMES: u32 = 1 << 0;
+pub const GIT_MERGE_FAIL_ON_CONFLICT: u32 = 1 << 1;
+pub const GIT_MERGE_SKIP_REUC: u32 = 1 << 2;
+pub const GIT_MERGE_NO_RECURSIVE: u32 = 1 << 3;
+
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_merge_file_favor_t {
+    GIT_MERGE_FILE_FAVOR_NORMAL = 0,
+    GIT_MERGE_FILE_FAVOR_OURS = 1,
+    GIT_MERGE_FILE_FAVOR_THEIRS = 2,
+    GIT_MERGE_FILE_FAVOR_UNION = 3,
+}
+
+pub type git_merge_analysis_t = u32;
+pub const GIT_MERGE_ANALYSIS_NONE: u32 = 0;
+pub const GIT_MERGE_ANALYSIS_NORMAL: u32 = 1 << 0;
+pub const GIT_MERGE_ANALYSIS_UP_TO_DATE: u32 = 1 << 1;
+pub const GIT_MERGE_ANALYSIS_FASTFORWARD: u32 = 1 << 2;
+pub const GIT_MERGE_ANALYSIS_UNBORN: u32 = 1 << 3;
+
+pub type git_merge_preference_t = u32;
+pub const GIT_MERGE_PREFERENCE_NONE: u32 = 0;
+pub const GIT_MERGE_PREFERENCE_NO_FASTFORWARD: u32 = 1 << 0;
+pub const GIT_MERGE_PREFERENCE_FASTFORWARD_ONLY: u32 = 1 << 1;
+
+pub type git_merge_file_flag_t = u32;
+pub const GIT_MERGE_FILE_DEFAULT: u32 = 0;
+pub const GIT_MERGE_FILE_STYLE_MERGE: u32 = 1 << 0;
+pub const GIT_MERGE_FILE_STYLE_DIFF3: u32 = 1 << 1;
+pub const GIT_MERGE_FILE_SIMPLIFY_ALNUM: u32 = 1 << 2;
+pub const GIT_MERGE_FILE_IGNORE_WHITESPACE: u32 = 1 << 3;
+pub const GIT_MERGE_FILE_IGNORE_WHITESPACE_CHANGE: u32 = 1 << 4;
+pub const GIT_MERGE_FILE_IGNORE_WHITESPACE_EOL: u32 = 1 << 5;
+pub const GIT_MERGE_FILE_DIFF_PATIENCE: u32 = 1 << 6;
+pub const GIT_MERGE_FILE_DIFF_MINIMAL: u32 = 1 << 7;
+pub const GIT_MERGE_FILE_STYLE_ZDIFF3: u32 = 1 << 8;
+pub const GIT_MERGE_FILE_ACCEPT_CONFLICTS: u32 = 1 << 9;
+
+#[repr(C)]
+pub struct git_merge_file_input {
+    pub version: c_uint,
+    pub ptr: *const c_char,
+    pub size: size_t,
+    pub path: *const c_char,
+    pub mode: c_uint,
+}
+
+pub const GIT_MERGE_FILE_INPUT_VERSION: c_uint = 1;
+
+#[repr(C)]
+pub struct git_merge_file_options {
+    pub version: c_uint,
+    pub ancestor_label: *const c_char,
+    pub our_label: *const c_char,
+    pub their_label: *const c_char,
+    pub favor: git_merge_file_favor_t,
+    pub flags: u32,
+    pub marker_size: c_ushort,
+}
+
+pub const GIT_MERGE_FILE_OPTIONS_VERSION: c_uint = 1;
+
+#[repr(C)]
+pub struct git_merge_file_result {
+    pub automergeable: c_uint,
+    pub path: *const c_char,
+    pub mode: c_uint,
+    pub ptr: *const c_char,
+    pub len: size_t,
+}
+
+#[repr(C)]
+pub struct git_email_create_options {
+    pub version: c_uint,
+    pub flags: u32,
+    pub diff_opts: git_diff_options,
+    pub diff_find_opts: git_diff_find_options,
+    pub subject_prefix: *const c_char,
+    pub start_number: size_t,
+    pub reroll_number: size_t,
+}
+
+pub const GIT_EMAIL_CREATE_OPTIONS_VERSION: c_uint = 1;
+pub const GIT_EMAIL_CREATE_DEFAULT: u32 = 0;
+pub const GIT_EMAIL_CREATE_OMIT_NUMBERS: u32 = 1 << 0;
+pub const GIT_EMAIL_CREATE_ALWAYS_NUMBER: u32 = 1 << 1;
+pub const GIT_EMAIL_CREATE_NO_RENAMES: u32 = 1 << 2;
+
+pub type git_commit_create_cb = extern fn(*mut git_oid,
+                                          *const git_signature,
+                                          *const git_signature,
+                                          *const c_char,
+                                          *const c_char,
+                                          *const git_tree,
+                                          size_t,
+                                          *const *const git_commit,
+                                          *mut c_void) -> c_int;
+
+pub type git_commit_signing_cb = extern fn(*mut git_buf,
+                                           *mut git_buf,
+                                           *const c_char,
+                                           *mut c_void) -> c_int;
+
+#[repr(C)]
+pub struct git_message_trailer {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+
+#[repr(C)]
+pub struct git_message_trailer_array {
+    pub trailers: *mut git_message_trailer,
+    pub count: size_t,
+    pub _trailer_block: *mut c_char,
+}
+
+#[repr(C)]
+pub struct git_rebase_options {
+    pub version: c_uint,
+    pub quiet: c_int,
+    pub inmemory: c_int,
+    pub rewrite_notes_ref: *const c_char,
+    pub merge_options: git_merge_options,
+    pub checkout_options: git_checkout_options,
+    pub commit_create_cb: Option<git_commit_create_cb>,
+    pub signing_cb: Option<git_commit_signing_cb>,
+    pub payload: *mut c_void,
+}
+
+pub const GIT_REBASE_OPTIONS_VERSION: c_uint = 1;
+pub const GIT_REBASE_NO_OPERATION: size_t = !0;
+
+#[repr(C)]
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum git_rebase_operation_t {
+    GIT_REBASE_OPERATION_PICK = 0,
+    GIT_REBASE_OPERATION_REWORD,
+    GIT_REBASE_OPERATION_EDIT,
+    GIT_REBASE_OPERATION_SQUASH,
+    GIT_REBASE_OPERATION_FIXUP,
+    GIT_REBASE_OPERATION_EXEC,
+}
+
+#[repr(C)]
+pub struct git_rebase_operation {
+    pub kind: git_rebase_operation_t,
+    pub id: git_oid,
+    pub exec: *const c_char,
+}
+
+pub const GIT_STASH_DEFAULT: u32 = 0;
+pub const GIT_STASH_KEEP_INDEX: u32 = 1 << 0;
+pub const GIT_STASH_INCLUDE_UNTRACKED: u32 = 1 << 1;
+pub const GIT_STASH_INCLUDE_IGNORED: u32 = 1 << 2;
+
+pub const GIT_STASH_APPLY_DEFAULT: u32 = 0;
+pub const GIT_STASH_APPLY_REINSTATE_INDEX: u32 = 1 << 0;
+
+#[repr(C)]
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum git_stash_apply_progress_t {
+    GIT_STASH_APPLY_PROGRESS_NONE = 0,
+    GIT_STASH_APPLY_PROGRESS_LOADING_STASH,
+    GIT_STASH_APPLY_PROGRESS_ANALYZE_INDEX,
+    GIT_STASH_APPLY_PROGRESS_ANALYZE_MODIFIED,
+    GIT_STASH_APPLY_PROGRESS_ANALYZE_UNTRACKED,
+    GIT_STASH_APPLY_PROGRESS_CHECKOUT_UNTRACKED,
+    GIT_STASH_APPLY_PROGRESS_CHECKOUT_MODIFIED,
+    GIT_STASH_APPLY_PROGRESS_DONE,
+}
+
+pub type git_stash_apply_progress_cb = extern fn(git_stash_apply_progress_t,
+                                                 *mut c_void) -> c_int;
+pub type git_stash_cb = extern fn(size_t,
+                                  *const c_char,
+                                  *const git_oid,
+                                  *mut c_void) -> c_int;
+
+pub type git_repository_mergehead_foreach_cb = extern fn(*const git_oid,
+                                                         *mut c_void) -> c_int;
+pub type git_repository_fetchhead_foreach_cb = extern fn(*const c_char,
+                                                         *const c_char,
+                                                         *const git_oid,
+                                                         c_uint,
+                                                         *mut c_void) -> c_int;
+
+#[repr(C)]
+pub struct git_stash_apply_options {
+    pub version: c_uint,
+    pub flags: u32,
+    pub checkout_options: git_checkout_options,
+    pub progress_cb: Option<git_stash_apply_progress_cb>,
+    pub progress_payload: *mut c_void,
+}
+
+pub const GIT_STASH_APPLY_OPTIONS_VERSION: c_uint = 1;
+
+#[repr(C)]
+pub struct git_worktree_add_options {
+    pub version: c_uint,
+    pub lock: c_int,
+    pub checkout_existing: c_int,
+    pub reference: *mut git_reference,
+    pub checkout_options: git_checkout_options,
+}
+
+pub const GIT_WORKTREE_ADD_OPTIONS_VERSION: c_uint = 1;
+
+pub const GIT_WORKTREE_PRUNE_VALID: u32 = 1 << 0;
+pub const GIT_WORKTREE_PRUNE_LOCKED: u32 = 1 << 1;
+pub const GIT_WORKTREE_PRUNE_WORKING_TREE: u32 = 1 << 2;
+
+#[repr(C)]
+pub struct git_worktree_prune_options {
+    pub version: c_uint,
+    pub flags: u32,
+}
+
+pub const GIT_WORKTREE_PRUNE_OPTIONS_VERSION: c_uint = 1;
+
+pub const GIT_ATTR_CHECK_FILE_THEN_INDEX: u32 = 0;
+pub const GIT_ATTR_CHECK_INDEX_THEN_FILE: u32 = 1;
+pub const GIT_ATTR_CHECK_INDEX_ONLY: u32 = 2;
+pub const GIT_ATTR_CHECK_NO_SYSTEM: u32 = 1 << 2;
+pub const GIT_ATTR_CHECK_INCLUDE_HEAD: u32 = 1 << 3;
+
+#[repr(C)]
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum git_attr_value_t {
+    GIT_ATTR_VALUE_UNSPECIFIED = 0,
+    GIT_ATTR_VALUE_TRUE,
+    GIT_ATTR_VALUE_FALSE,
+    GIT_ATTR_VALUE_STRING,
+}
+
+#[repr(C)]
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum git_filter_mode_t {
+    GIT_FILTER_TO_WORKTREE = 0,
+    GIT_FILTER_TO_ODB = 1,
+}
+
+pub type git_filter_init_fn = extern fn(*mut git_filter) -> c_int;
+pub type git_filter_shutdown_fn = extern fn(*mut git_filter);
+pub type git_filter_check_fn = extern fn(*mut git_filter,
+                                         *mut *mut c_void,
+                                         *const git_filter_source,
+                                         *mut *const c_char) -> c_int;
+pub type git_filter_apply_fn = extern fn(*mut git_filter,
+                                         *mut *mut c_void,
+                                         *mut git_buf,
+                                         *const git_buf,
+                                         *const git_filter_source) -> c_int;
+pub type git_filter_cleanup_fn = extern fn(*mut git_filter, *mut c_void);
+
+#[repr(C)]
+pub struct git_filter {
+    pub version: c_uint,
+    pub attributes: *const c_char,
+    pub initialize: Option<git_filter_init_fn>,
+    pub shutdown: Option<git_filter_shutdown_fn>,
+    pub check: Option<git_filter_check_fn>,
+    pub apply: Option<git_filter_apply_fn>,
+    pub stream: *mut c_void,
+    pub cleanup: Option<git_filter_cleanup_fn>,
+}
+
+pub const GIT_FILTER_VERSION: c_uint = 1;
+pub const GIT_FILTER_DRIVER_PRIORITY: c_int = 200;
+
+pub const GIT_FILTER_DEFAULT: u32 = 0;
+pub const GIT_FILTER_ALLOW_UNSAFE: u32 = 1 << 0;
+
+#[repr(C)]
+pub struct git_writestream {
+    pub write: extern fn(*mut git_writestream, *const c_char, size_t) -> c_int,
+    pub close: extern fn(*mut git_writestream) -> c_int,
+    pub free: extern fn(*mut git_writestream),
+}
+
+pub type git_apply_delta_cb = extern fn(*const git_diff_delta,
+                                        *mut c_void) -> c_int;
+pub type git_apply_hunk_cb = extern fn(*const git_diff_hunk,
+                                       *mut c_void) -> c_int;
+
+#[repr(C)]
+pub struct git_apply_options {
+    pub version: c_uint,
+    pub delta_cb: Option<git_apply_delta_cb>,
+    pub hunk_cb: Option<git_apply_hunk_cb>,
+    pub payload: *mut c_void,
+    pub flags: c_uint,
+}
+
+pub const GIT_APPLY_OPTIONS_VERSION: c_uint = 1;
+pub const GIT_APPLY_CHECK: c_uint = 1 << 0;
+
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_apply_location_t {
+    GIT_APPLY_LOCATION_WORKDIR = 0,
+    GIT_APPLY_LOCATION_INDEX = 1,
+    GIT_APPLY_LOCATION_BOTH = 2,
+}
+
+#[repr(C)]
+#[derive(Copy)]
+pub struct git_blame_options {
+    pub version: c_uint,
+    pub flags: u32,
+    pub min_match_characters: u16,
+    pub newest_commit: git_oid,
+    pub oldest_commit: git_oid,
+    pub min_line: size_t,
+    pub max_line: size_t,
+}
+
+pub const GIT_BLAME_OPTIONS_VERSION: c_uint = 1;
+pub const GIT_BLAME_NORMAL: u32 = 0;
+pub const GIT_BLAME_TRACK_COPIES_SAME_FILE: u32 = 1 << 0;
+pub const GIT_BLAME_TRACK_COPIES_SAME_COMMIT_MOVES: u32 = 1 << 1;
+pub const GIT_BLAME_TRACK_COPIES_SAME_COMMIT_COPIES: u32 = 1 << 2;
+pub const GIT_BLAME_TRACK_COPIES_ANY_COMMIT_COPIES: u32 = 1 << 3;
+pub const GIT_BLAME_FIRST_PARENT: u32 = 1 << 4;
+
+#[repr(C)]
+pub struct git_blame_hunk {
+    pub lines_in_hunk: size_t,
+    pub final_commit_id: git_oid,
+    pub final_start_line_number: size_t,
+    pub final_signature: *mut git_signature,
+    pub orig_commit_id: git_oid,
+    pub orig_path: *const c_char,
+    pub orig_start_line_number: size_t,
+    pub orig_signature: *mut git_signature,
+    pub boundary: c_char,
+}
+
 #[repr(C)]
 #[derive(Copy)]
 pub enum git_reset_t {
@@ -552,6 +1122,24 @@ pub enum git_treewalk_mode {
 
 pub type git_treewalk_cb = extern fn(*const c_char, *const git_tree_entry,
                                      *mut c_void) -> c_int;
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_tree_update_t {
+    GIT_TREE_UPDATE_UPSERT = 0,
+    GIT_TREE_UPDATE_REMOVE = 1,
+}
+
+#[repr(C)]
+#[derive(Copy)]
+pub struct git_tree_update {
+    pub action: git_tree_update_t,
+    pub id: git_oid,
+    pub filemode: c_uint,
+    pub path: *const c_char,
+}
+
+pub type git_treebuilder_filter_cb = extern fn(*const git_tree_entry,
+                                               *mut c_void) -> c_int;
 
 #[repr(C)]
 #[derive(Copy)]
@@ -569,6 +1157,11 @@ pub enum git_branch_t {
     GIT_BRANCH_ALL = GIT_BRANCH_LOCAL as isize | GIT_BRANCH_REMOTE as isize,
 }
 
+pub const GIT_INDEXCAP_IGNORE_CASE: c_uint = 1;
+pub const GIT_INDEXCAP_NO_FILEMODE: c_uint = 2;
+pub const GIT_INDEXCAP_NO_SYMLINKS: c_uint = 4;
+pub const GIT_INDEXCAP_FROM_OWNER: c_int = -1;
+
 pub type git_index_matched_path_cb = extern fn(*const c_char, *const c_char,
                                                *mut c_void) -> c_int;
 
@@ -582,17 +1175,25 @@ pub struct git_index_entry {
     pub mode: c_uint,
     pub uid: c_uint,
     pub gid: c_uint,
-    pub file_size: git_off_t,
+    pub file_size: u32,
     pub id: git_oid,
     pub flags: c_ushort,
     pub flags_extended: c_ushort,
     pub path: *const c_char,
 }
 
+#[repr(C)]
+#[derive(Copy)]
+pub struct git_index_reuc_entry {
+    pub mode: [u32; 3],
+    pub oid: [git_oid; 3],
+    pub path: *mut c_char,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct git_index_time {
-    pub seconds: git_time_t,
+    pub seconds: i32,
     pub nanoseconds: c_uint,
 }
 
@@ -600,24 +1201,63 @@ pub struct git_index_time {
 pub struct git_config_entry {
     pub name: *const c_char,
     pub value: *const c_char,
+    pub backend_type: *const c_char,
+    pub origin_path: *const c_char,
+    pub include_depth: c_uint,
     pub level: git_config_level_t,
+    pub free: Option<extern fn(*mut git_config_entry)>,
 }
 
 #[repr(C)]
 #[derive(Copy)]
 pub enum git_config_level_t {
-    GIT_CONFIG_LEVEL_SYSTEM = 1,
-    GIT_CONFIG_LEVEL_XDG = 2,
-    GIT_CONFIG_LEVEL_GLOBAL = 3,
-    GIT_CONFIG_LEVEL_LOCAL = 4,
-    GIT_CONFIG_LEVEL_APP = 5,
+    GIT_CONFIG_LEVEL_PROGRAMDATA = 1,
+    GIT_CONFIG_LEVEL_SYSTEM = 2,
+    GIT_CONFIG_LEVEL_XDG = 3,
+    GIT_CONFIG_LEVEL_GLOBAL = 4,
+    GIT_CONFIG_LEVEL_LOCAL = 5,
+    GIT_CONFIG_LEVEL_WORKTREE = 6,
+    GIT_CONFIG_LEVEL_APP = 7,
     GIT_CONFIG_HIGHEST_LEVEL = -1,
 }
 
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_libgit2_opt_t {
+    GIT_OPT_GET_MWINDOW_SIZE,
+    GIT_OPT_SET_MWINDOW_SIZE,
+    GIT_OPT_GET_MWINDOW_MAPPED_LIMIT,
+    GIT_OPT_SET_MWINDOW_MAPPED_LIMIT,
+    GIT_OPT_GET_SEARCH_PATH,
+    GIT_OPT_SET_SEARCH_PATH,
+    GIT_OPT_SET_CACHE_OBJECT_LIMIT,
+    GIT_OPT_SET_CACHE_MAX_SIZE,
+    GIT_OPT_ENABLE_CACHING,
+    GIT_OPT_GET_CACHED_MEMORY,
+    GIT_OPT_GET_TEMPLATE_PATH,
+    GIT_OPT_SET_TEMPLATE_PATH,
+    GIT_OPT_SET_SSL_CERT_LOCATIONS,
+    GIT_OPT_SET_USER_AGENT,
+    GIT_OPT_ENABLE_STRICT_OBJECT_CREATION,
+}
+
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_trace_level_t {
+    GIT_TRACE_NONE = 0,
+    GIT_TRACE_FATAL = 1,
+    GIT_TRACE_ERROR = 2,
+    GIT_TRACE_WARN = 3,
+    GIT_TRACE_INFO = 4,
+    GIT_TRACE_DEBUG = 5,
+    GIT_TRACE_TRACE = 6,
+}
+
+pub type git_trace_callback = extern fn(git_trace_level_t, *const c_char);
+
 #[repr(C)]
 #[derive(Copy)]
 pub enum git_submodule_update_t {
-    GIT_SUBMODULE_UPDATE_RESET    = -1,
     GIT_SUBMODULE_UPDATE_CHECKOUT = 1,
     GIT_SUBMODULE_UPDATE_REBASE   = 2,
     GIT_SUBMODULE_UPDATE_MERGE    = 3,
@@ -628,14 +1268,12 @@ pub enum git_submodule_update_t {
 #[repr(C)]
 #[derive(Copy)]
 pub enum git_submodule_ignore_t {
-    GIT_SUBMODULE_IGNORE_RESET     = -1,
+    GIT_SUBMODULE_IGNORE_UNSPECIFIED = -1,
 
     GIT_SUBMODULE_IGNORE_NONE      = 1,
     GIT_SUBMODULE_IGNORE_UNTRACKED = 2,
     GIT_SUBMODULE_IGNORE_DIRTY     = 3,
     GIT_SUBMODULE_IGNORE_ALL       = 4,
-
-    GIT_SUBMODULE_IGNORE_DEFAULT   = 0
 }
 
 #[repr(C)]
@@ -652,6 +1290,8 @@ pub enum git_credtype_t {
     GIT_CREDTYPE_SSH_CUSTOM = 1 << 2,
     GIT_CREDTYPE_DEFAULT = 1 << 3,
     GIT_CREDTYPE_SSH_INTERACTIVE = 1 << 4,
+    GIT_CREDTYPE_USERNAME = 1 << 5,
+    GIT_CREDTYPE_SSH_MEMORY = 1 << 6,
 }
 
 pub type git_cred_ssh_interactive_callback = extern fn(
@@ -672,17 +1312,31 @@ pub type git_cred_sign_callback = extern fn(
     data: *const c_uchar,
     data_len: size_t,
     abstrakt: *mut *mut c_void,
-);
+) -> c_int;
 
 pub enum LIBSSH2_SESSION {}
 pub enum LIBSSH2_USERAUTH_KBDINT_PROMPT {}
 pub enum LIBSSH2_USERAUTH_KBDINT_RESPONSE {}
 
 #[repr(C)]
-#[derive(Copy)]
+pub struct git_proxy_options {
+    pub version: c_uint,
+    pub kind: git_proxy_t,
+    pub url: *const c_char,
+    pub credentials: Option<git_cred_acquire_cb>,
+    pub certificate_check: Option<git_transport_certificate_check_cb>,
+    pub payload: *mut c_void,
+}
+
+#[repr(C)]
 pub struct git_push_options {
     pub version: c_uint,
     pub pb_parallelism: c_uint,
+    pub callbacks: git_remote_callbacks,
+    pub proxy_opts: git_proxy_options,
+    pub follow_redirects: git_remote_redirect_t,
+    pub custom_headers: git_strarray,
+    pub remote_push_options: git_strarray,
 }
 
 pub type git_tag_foreach_cb = extern fn(name: *const c_char,
@@ -723,6 +1377,16 @@ pub enum git_repository_init_flag_t {
     GIT_REPOSITORY_INIT_EXTERNAL_TEMPLATE = (1 << 5),
 }
 
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_repository_open_flag_t {
+    GIT_REPOSITORY_OPEN_NO_SEARCH = (1 << 0),
+    GIT_REPOSITORY_OPEN_CROSS_FS  = (1 << 1),
+    GIT_REPOSITORY_OPEN_BARE      = (1 << 2),
+    GIT_REPOSITORY_OPEN_NO_DOTGIT = (1 << 3),
+    GIT_REPOSITORY_OPEN_FROM_ENV  = (1 << 4),
+}
+
 #[repr(C)]
 #[derive(Copy)]
 pub enum git_repository_init_mode_t {
@@ -764,6 +1428,17 @@ pub struct git_remote_head {
     pub symref_target: *mut c_char,
 }
 
+pub type git_revwalk_hide_cb = extern fn(*const git_oid, *mut c_void)
+                                         -> c_int;
+
+pub type git_reference_normalize_t = u32;
+pub const GIT_REF_FORMAT_NORMAL: u32 = 0;
+pub const GIT_REF_FORMAT_ALLOW_ONELEVEL: u32 = 1 << 0;
+pub const GIT_REF_FORMAT_REFSPEC_PATTERN: u32 = 1 << 1;
+pub const GIT_REF_FORMAT_REFSPEC_SHORTHAND: u32 = 1 << 2;
+
+pub const GIT_REFNAME_MAX: usize = 1024;
+
 pub type git_pathspec_flag_t = u32;
 pub const GIT_PATHSPEC_DEFAULT: u32 = 0;
 pub const GIT_PATHSPEC_IGNORE_CASE: u32 = 1 << 0;
@@ -782,6 +1457,32 @@ pub type git_diff_line_cb = extern fn(*const git_diff_delta,
                                       *const git_diff_hunk,
                                       *const git_diff_line,
                                       *mut c_void) -> c_int;
+pub type git_diff_binary_cb = extern fn(*const git_diff_delta,
+                                        *const git_diff_binary,
+                                        *mut c_void) -> c_int;
+
+#[repr(C)]
+pub struct git_diff_binary {
+    pub contains_data: c_uint,
+    pub old_file: git_diff_binary_file,
+    pub new_file: git_diff_binary_file,
+}
+
+#[repr(C)]
+pub struct git_diff_binary_file {
+    pub kind: git_diff_binary_t,
+    pub data: *const c_char,
+    pub datalen: size_t,
+    pub inflatedlen: size_t,
+}
+
+#[repr(C)]
+#[derive(Copy)]
+pub enum git_diff_binary_t {
+    GIT_DIFF_BINARY_NONE,
+    GIT_DIFF_BINARY_LITERAL,
+    GIT_DIFF_BINARY_DELTA,
+}
 
 #[repr(C)]
 pub struct git_diff_hunk {
@@ -794,6 +1495,11 @@ pub struct git_diff_hunk {
 }
 
 pub type git_diff_line_t = u8;
+pub const GIT_DIFF_FLAG_BINARY: u32 = 1 << 0;
+pub const GIT_DIFF_FLAG_NOT_BINARY: u32 = 1 << 1;
+pub const GIT_DIFF_FLAG_VALID_ID: u32 = 1 << 2;
+pub const GIT_DIFF_FLAG_EXISTS: u32 = 1 << 3;
+
 pub const GIT_DIFF_LINE_CONTEXT: u8 = ' ' as u8;
 pub const GIT_DIFF_LINE_ADDITION: u8 = '+' as u8;
 pub const GIT_DIFF_LINE_DELETION: u8 = '-' as u8;
@@ -821,8 +1527,9 @@ pub struct git_diff_options {
     pub flags: u32,
     pub ignore_submodules: git_submodule_ignore_t,
     pub pathspec: git_strarray,
-    pub notify_cb: git_diff_notify_cb,
-    pub notify_payload: *mut c_void,
+    pub notify_cb: Option<git_diff_notify_cb>,
+    pub progress_cb: Option<git_diff_progress_cb>,
+    pub payload: *mut c_void,
     pub context_lines: u32,
     pub interhunk_lines: u32,
     pub id_abbrev: u16,
@@ -853,6 +1560,10 @@ pub type git_diff_notify_cb = extern fn(*const git_diff,
                                         *const git_diff_delta,
                                         *const c_char,
                                         *mut c_void) -> c_int;
+pub type git_diff_progress_cb = extern fn(*const git_diff,
+                                          *const c_char,
+                                          *const c_char,
+                                          *mut c_void) -> c_int;
 
 pub type git_diff_options_t = u32;
 pub const GIT_DIFF_NORMAL: u32 = 0;
@@ -873,6 +1584,7 @@ pub const GIT_DIFF_ENABLE_FAST_UNTRACKED_DIRS: u32 = 1 << 14;
 pub const GIT_DIFF_UPDATE_INDEX: u32 = 1 << 15;
 pub const GIT_DIFF_INCLUDE_UNREADABLE: u32 = 1 << 16;
 pub const GIT_DIFF_INCLUDE_UNREADABLE_AS_UNTRACKED: u32 = 1 << 17;
+pub const GIT_DIFF_INDENT_HEURISTIC: u32 = 1 << 18;
 pub const GIT_DIFF_FORCE_TEXT: u32 = 1 << 20;
 pub const GIT_DIFF_FORCE_BINARY: u32 = 1 << 21;
 pub const GIT_DIFF_IGNORE_WHITESPACE: u32 = 1 << 22;
@@ -915,6 +1627,14 @@ pub struct git_diff_similarity_metric {
 
 pub const GIT_DIFF_FIND_OPTIONS_VERSION: c_uint = 1;
 
+#[repr(C)]
+#[derive(Copy)]
+pub struct git_diff_patchid_options {
+    pub version: c_uint,
+}
+
+pub const GIT_DIFF_PATCHID_OPTIONS_VERSION: c_uint = 1;
+
 pub const GIT_DIFF_FIND_BY_CONFIG: u32 = 0;
 pub const GIT_DIFF_FIND_RENAMES: u32 = 1 << 0;
 pub const GIT_DIFF_FIND_RENAMES_FROM_REWRITES: u32 = 1 << 1;
@@ -940,32 +1660,28 @@ pub type git_transport_cb = extern fn(out: *mut *mut git_transport,
 #[repr(C)]
 pub struct git_transport {
     pub version: c_uint,
-    pub set_callbacks: extern fn(*mut git_transport,
-                                 git_transport_message_cb,
-                                 git_transport_message_cb,
-                                 git_transport_certificate_check_cb,
-                                 *mut c_void) -> c_int,
     pub connect: extern fn(*mut git_transport,
                            *const c_char,
-                           git_cred_acquire_cb,
-                           *mut c_void,
-                           c_int, c_int) -> c_int,
+                           c_int,
+                           *const git_remote_connect_options) -> c_int,
+    pub set_connect_opts: extern fn(*mut git_transport,
+                                    *const git_remote_connect_options)
+                                    -> c_int,
+    pub capabilities: extern fn(*mut c_uint, *mut git_transport) -> c_int,
     pub ls: extern fn(*mut *mut *const git_remote_head,
                       *mut size_t,
                       *mut git_transport) -> c_int,
     pub push: extern fn(*mut git_transport, *mut git_push) -> c_int,
     pub negotiate_fetch: extern fn(*mut git_transport,
                                    *mut git_repository,
-                                   *const *const git_remote_head,
-                                   size_t) -> c_int,
+                                   *const git_fetch_negotiation) -> c_int,
+    pub shallow_roots: extern fn(*mut git_oidarray,
+                                 *mut git_transport) -> c_int,
     pub download_pack: extern fn(*mut git_transport,
                                  *mut git_repository,
-                                 *mut git_transfer_progress,
-                                 git_transfer_progress_cb,
-                                 *mut c_void) -> c_int,
+                                 *mut git_transfer_progress) -> c_int,
     pub is_connected: extern fn(*mut git_transport) -> c_int,
-    pub read_flags: extern fn(*mut git_transport, *mut c_int) -> c_int,
-    pub cancel: extern fn(*mut git_transport) -> c_int,
+    pub cancel: extern fn(*mut git_transport),
     pub close: extern fn(*mut git_transport) -> c_int,
     pub free: extern fn(*mut git_transport),
 }
@@ -1002,12 +1718,14 @@ pub struct git_smart_subtransport {
 }
 
 pub type git_smart_subtransport_cb = extern fn(*mut *mut git_smart_subtransport,
-                                               *mut git_transport) -> c_int;
+                                               *mut git_transport,
+                                               *mut c_void) -> c_int;
 
 #[repr(C)]
 pub struct git_smart_subtransport_definition {
     pub callback: git_smart_subtransport_cb,
     pub rpc: c_uint,
+    pub param: *mut c_void,
 }
 
 /// Initialize openssl for the libgit2 library
@@ -1135,12 +1853,27 @@ pub fn openssl_init() {}
 extern {
     // threads
     pub fn git_libgit2_init() -> c_int;
-    pub fn git_libgit2_shutdown();
+    pub fn git_libgit2_shutdown() -> c_int;
+    pub fn git_libgit2_version(major: *mut c_int,
+                               minor: *mut c_int,
+                               rev: *mut c_int) -> c_int;
+    pub fn git_libgit2_features() -> c_int;
+    pub fn git_libgit2_opts(option: c_int, ...) -> c_int;
+
+    // trace
+    pub fn git_trace_set(level: git_trace_level_t,
+                         cb: Option<git_trace_callback>) -> c_int;
 
     // repository
     pub fn git_repository_free(repo: *mut git_repository);
     pub fn git_repository_open(repo: *mut *mut git_repository,
                                path: *const c_char) -> c_int;
+    pub fn git_repository_open_ext(repo: *mut *mut git_repository,
+                                   path: *const c_char,
+                                   flags: c_uint,
+                                   ceiling_dirs: *const c_char) -> c_int;
+    pub fn git_repository_wrap_odb(out: *mut *mut git_repository,
+                                   odb: *mut git_odb) -> c_int;
     pub fn git_repository_init(repo: *mut *mut git_repository,
                                path: *const c_char,
                                is_bare: c_uint) -> c_int;
@@ -1154,18 +1887,52 @@ extern {
                                         -> *const c_char;
     pub fn git_repository_head(out: *mut *mut git_reference,
                                repo: *mut git_repository) -> c_int;
+    pub fn git_repository_set_head(repo: *mut git_repository,
+                                   refname: *const c_char) -> c_int;
+    pub fn git_repository_set_head_detached(repo: *mut git_repository,
+                                            commitish: *const git_oid)
+                                            -> c_int;
     pub fn git_repository_is_bare(repo: *mut git_repository) -> c_int;
     pub fn git_repository_is_empty(repo: *mut git_repository) -> c_int;
     pub fn git_repository_is_shallow(repo: *mut git_repository) -> c_int;
     pub fn git_repository_path(repo: *mut git_repository) -> *const c_char;
+    pub fn git_repository_commondir(repo: *mut git_repository)
+                                    -> *const c_char;
     pub fn git_repository_state(repo: *mut git_repository) -> c_int;
+    pub fn git_repository_state_cleanup(repo: *mut git_repository) -> c_int;
+    pub fn git_repository__cleanup(repo: *mut git_repository) -> c_int;
+    pub fn git_repository_mergehead_foreach(
+        repo: *mut git_repository,
+        callback: git_repository_mergehead_foreach_cb,
+        payload: *mut c_void) -> c_int;
+    pub fn git_repository_fetchhead_foreach(
+        repo: *mut git_repository,
+        callback: git_repository_fetchhead_foreach_cb,
+        payload: *mut c_void) -> c_int;
     pub fn git_repository_workdir(repo: *mut git_repository) -> *const c_char;
     pub fn git_repository_index(out: *mut *mut git_index,
                                 repo: *mut git_repository) -> c_int;
     pub fn git_repository_config(out: *mut *mut git_config,
                                  repo: *mut git_repository) -> c_int;
+    pub fn git_repository_odb(out: *mut *mut git_odb,
+                              repo: *mut git_repository) -> c_int;
+    pub fn git_repository_set_odb(repo: *mut git_repository,
+                                  odb: *mut git_odb) -> c_int;
+    pub fn git_repository_refdb(out: *mut *mut git_refdb,
+                                repo: *mut git_repository) -> c_int;
+    pub fn git_repository_set_config(repo: *mut git_repository,
+                                     config: *mut git_config) -> c_int;
+    pub fn git_repository_set_index(repo: *mut git_repository,
+                                    index: *mut git_index) -> c_int;
+    pub fn git_repository_set_refdb(repo: *mut git_repository,
+                                    refdb: *mut git_refdb) -> c_int;
     pub fn git_repository_config_snapshot(out: *mut *mut git_config,
                                           repo: *mut git_repository) -> c_int;
+    pub fn git_repository_hashfile(out: *mut git_oid,
+                                   repo: *mut git_repository,
+                                   path: *const c_char,
+                                   otype: git_otype,
+                                   as_path: *const c_char) -> c_int;
     pub fn git_repository_discover(out: *mut git_buf,
                                    start_path: *const c_char,
                                    across_fs: c_int,
@@ -1188,6 +1955,11 @@ extern {
                              repo: *mut git_repository,
                              id: *const git_oid,
                              kind: git_otype) -> c_int;
+    pub fn git_object_lookup_prefix(dest: *mut *mut git_object,
+                                    repo: *mut git_repository,
+                                    id: *const git_oid,
+                                    len: size_t,
+                                    kind: git_otype) -> c_int;
     pub fn git_object_type(obj: *const git_object) -> git_otype;
     pub fn git_object_peel(peeled: *mut *mut git_object,
                            object: *const git_object,
@@ -1199,7 +1971,7 @@ extern {
     pub fn git_object_typeisloose(kind: git_otype) -> c_int;
 
     // oid
-    pub fn git_oid_fromraw(out: *mut git_oid, raw: *const c_uchar);
+    pub fn git_oid_fromraw(out: *mut git_oid, raw: *const c_uchar) -> c_int;
     pub fn git_oid_fromstrn(out: *mut git_oid, str: *const c_char,
                             len: size_t) -> c_int;
     pub fn git_oid_tostr(out: *mut c_char, n: size_t,
@@ -1209,11 +1981,11 @@ extern {
     pub fn git_oid_streq(id: *const git_oid, str: *const c_char) -> c_int;
     pub fn git_oid_iszero(id: *const git_oid) -> c_int;
 
-    // giterr
-    pub fn giterr_last() -> *const git_error;
-    pub fn giterr_clear();
-    pub fn giterr_detach(cpy: *mut git_error) -> c_int;
-    pub fn giterr_set_str(error_class: c_int, string: *const c_char);
+    // error
+    pub fn git_error_last() -> *const git_error;
+    pub fn git_error_clear();
+    pub fn git_error_set_str(error_class: c_int,
+                             string: *const c_char) -> c_int;
 
     // remote
     pub fn git_remote_create(out: *mut *mut git_remote,
@@ -1225,8 +1997,14 @@ extern {
                              name: *const c_char) -> c_int;
     pub fn git_remote_create_anonymous(out: *mut *mut git_remote,
                                        repo: *mut git_repository,
+                                       url: *const c_char) -> c_int;
+    pub fn git_remote_create_with_opts(out: *mut *mut git_remote,
                                        url: *const c_char,
-                                       fetch: *const c_char) -> c_int;
+                                       opts: *const git_remote_create_options)
+                                       -> c_int;
+    pub fn git_remote_create_options_init(
+        opts: *mut git_remote_create_options,
+        version: c_uint) -> c_int;
     pub fn git_remote_delete(repo: *mut git_repository,
                              name: *const c_char) -> c_int;
     pub fn git_remote_free(remote: *mut git_remote);
@@ -1235,18 +2013,23 @@ extern {
     pub fn git_remote_refspec_count(remote: *const git_remote) -> size_t;
     pub fn git_remote_url(remote: *const git_remote) -> *const c_char;
     pub fn git_remote_connect(remote: *mut git_remote,
-                              dir: git_direction) -> c_int;
-    pub fn git_remote_connected(remote: *mut git_remote) -> c_int;
-    pub fn git_remote_disconnect(remote: *mut git_remote);
-    pub fn git_remote_save(remote: *const git_remote) -> c_int;
-    pub fn git_remote_add_fetch(remote: *mut git_remote,
+                              dir: git_direction,
+                              callbacks: *const git_remote_callbacks,
+                              proxy_opts: *const git_proxy_options,
+                              custom_headers: *const git_strarray) -> c_int;
+    pub fn git_remote_connected(remote: *const git_remote) -> c_int;
+    pub fn git_remote_disconnect(remote: *mut git_remote) -> c_int;
+    pub fn git_remote_add_fetch(repo: *mut git_repository,
+                                remote: *const c_char,
                                 refspec: *const c_char) -> c_int;
-    pub fn git_remote_add_push(remote: *mut git_remote,
+    pub fn git_remote_add_push(repo: *mut git_repository,
+                               remote: *const c_char,
                                refspec: *const c_char) -> c_int;
-    pub fn git_remote_clear_refspecs(remote: *mut git_remote);
+    pub fn git_remote_owner(remote: *const git_remote) -> *mut git_repository;
     pub fn git_remote_download(remote: *mut git_remote,
-                               refspecs: *const git_strarray) -> c_int;
-    pub fn git_remote_stop(remote: *mut git_remote);
+                               refspecs: *const git_strarray,
+                               opts: *const git_fetch_options) -> c_int;
+    pub fn git_remote_stop(remote: *mut git_remote) -> c_int;
     pub fn git_remote_dup(dest: *mut *mut git_remote,
                           source: *mut git_remote) -> c_int;
     pub fn git_remote_get_fetch_refspecs(array: *mut git_strarray,
@@ -1262,25 +2045,24 @@ extern {
                              new_name: *const c_char) -> c_int;
     pub fn git_remote_fetch(remote: *mut git_remote,
                             refspecs: *const git_strarray,
-                            signature: *const git_signature,
+                            opts: *const git_fetch_options,
                             reflog_message: *const c_char) -> c_int;
+    pub fn git_fetch_options_init(opts: *mut git_fetch_options,
+                                  version: c_uint) -> c_int;
     pub fn git_remote_update_tips(remote: *mut git_remote,
-                                  signature: *const git_signature,
+                                  callbacks: *const git_remote_callbacks,
+                                  update_flags: c_uint,
+                                  download_tags: git_remote_autotag_option_t,
                                   reflog_message: *const c_char) -> c_int;
-    pub fn git_remote_update_fetchhead(remote: *mut git_remote) -> c_int;
-    pub fn git_remote_set_url(remote: *mut git_remote,
+    pub fn git_remote_upload(remote: *mut git_remote,
+                             refspecs: *const git_strarray,
+                             opts: *const git_push_options) -> c_int;
+    pub fn git_remote_set_url(repo: *mut git_repository,
+                              remote: *const c_char,
                               url: *const c_char) -> c_int;
-    pub fn git_remote_set_pushurl(remote: *mut git_remote,
+    pub fn git_remote_set_pushurl(repo: *mut git_repository,
+                                  remote: *const c_char,
                                   pushurl: *const c_char) -> c_int;
-    pub fn git_remote_set_update_fetchhead(remote: *mut git_remote,
-                                           update: c_int);
-    pub fn git_remote_set_fetch_refspecs(remote: *mut git_remote,
-                                         array: *mut git_strarray) -> c_int;
-    pub fn git_remote_set_push_refspecs(remote: *mut git_remote,
-                                        array: *mut git_strarray) -> c_int;
-    pub fn git_remote_set_callbacks(remote: *mut git_remote,
-                                    callbacks: *const git_remote_callbacks)
-                                    -> c_int;
     pub fn git_remote_init_callbacks(opts: *mut git_remote_callbacks,
                                      version: c_uint) -> c_int;
     pub fn git_remote_stats(remote: *mut git_remote)
@@ -1302,6 +2084,7 @@ extern {
 
     // strarray
     pub fn git_strarray_free(array: *mut git_strarray);
+    pub fn git_oidarray_free(array: *mut git_oidarray);
 
     // signature
     pub fn git_signature_default(out: *mut *mut git_signature,
@@ -1335,6 +2118,59 @@ extern {
                                     repo: *mut git_repository,
                                     path: *const c_char) -> c_int;
 
+    // ignore
+    pub fn git_ignore_add_rule(repo: *mut git_repository,
+                               rules: *const c_char) -> c_int;
+    pub fn git_ignore_clear_internal_rules(repo: *mut git_repository) -> c_int;
+    pub fn git_ignore_path_is_ignored(ignored: *mut c_int,
+                                      repo: *mut git_repository,
+                                      path: *const c_char) -> c_int;
+
+    // attr
+    pub fn git_attr_get(value_out: *mut *const c_char,
+                        repo: *mut git_repository,
+                        flags: u32,
+                        path: *const c_char,
+                        name: *const c_char) -> c_int;
+    pub fn git_attr_value(attr: *const c_char) -> git_attr_value_t;
+    pub fn git_attr_cache_flush(repo: *mut git_repository) -> c_int;
+
+    // filter
+    pub fn git_filter_register(name: *const c_char,
+                               filter: *mut git_filter,
+                               priority: c_int) -> c_int;
+    pub fn git_filter_unregister(name: *const c_char) -> c_int;
+    pub fn git_filter_lookup(name: *const c_char) -> *mut git_filter;
+    pub fn git_filter_source_path(src: *const git_filter_source)
+                                  -> *const c_char;
+    pub fn git_filter_source_filemode(src: *const git_filter_source) -> u16;
+    pub fn git_filter_source_id(src: *const git_filter_source)
+                                -> *const git_oid;
+    pub fn git_filter_source_mode(src: *const git_filter_source)
+                                  -> git_filter_mode_t;
+    pub fn git_filter_source_flags(src: *const git_filter_source) -> u32;
+    pub fn git_filter_list_load(filters: *mut *mut git_filter_list,
+                                repo: *mut git_repository,
+                                blob: *mut git_blob,
+                                path: *const c_char,
+                                mode: git_filter_mode_t,
+                                flags: u32) -> c_int;
+    pub fn git_filter_list_free(filters: *mut git_filter_list);
+    pub fn git_filter_list_apply_to_file(out: *mut git_buf,
+                                         filters: *mut git_filter_list,
+                                         repo: *mut git_repository,
+                                         path: *const c_char) -> c_int;
+    pub fn git_filter_list_stream_data(filters: *mut git_filter_list,
+                                       data: *mut git_buf,
+                                       target: *mut git_writestream) -> c_int;
+    pub fn git_filter_list_stream_file(filters: *mut git_filter_list,
+                                       repo: *mut git_repository,
+                                       path: *const c_char,
+                                       target: *mut git_writestream) -> c_int;
+    pub fn git_filter_list_stream_blob(filters: *mut git_filter_list,
+                                       blob: *mut git_blob,
+                                       target: *mut git_writestream) -> c_int;
+
     // clone
     pub fn git_clone(out: *mut *mut git_repository,
                      url: *const c_char,
@@ -1345,14 +2181,17 @@ extern {
 
     // reset
     pub fn git_reset(repo: *mut git_repository,
-                     target: *mut git_object,
+                     target: *const git_object,
                      reset_type: git_reset_t,
-                     checkout_opts: *mut git_checkout_options,
-                     signature: *mut git_signature,
-                     log_message: *const c_char) -> c_int;
+                     checkout_opts: *const git_checkout_options) -> c_int;
     pub fn git_reset_default(repo: *mut git_repository,
                              target: *mut git_object,
                              pathspecs: *mut git_strarray) -> c_int;
+    pub fn git_reset_from_annotated(repo: *mut git_repository,
+                                    commit: *const git_annotated_commit,
+                                    reset_type: git_reset_t,
+                                    checkout_opts: *const git_checkout_options)
+                                    -> c_int;
 
     // reference
     pub fn git_reference_cmp(ref1: *const git_reference,
@@ -1364,10 +2203,18 @@ extern {
     pub fn git_reference_is_remote(r: *const git_reference) -> c_int;
     pub fn git_reference_is_tag(r: *const git_reference) -> c_int;
     pub fn git_reference_is_valid_name(name: *const c_char) -> c_int;
+    pub fn git_reference_peel(out: *mut *mut git_object,
+                              r: *mut git_reference,
+                              otype: git_otype) -> c_int;
+    pub fn git_reference_normalize_name(buffer_out: *mut c_char,
+                                        buffer_size: size_t,
+                                        name: *const c_char,
+                                        flags: c_uint) -> c_int;
     pub fn git_reference_lookup(out: *mut *mut git_reference,
                                 repo: *mut git_repository,
                                 name: *const c_char) -> c_int;
     pub fn git_reference_name(r: *const git_reference) -> *const c_char;
+    pub fn git_reference_owner(r: *const git_reference) -> *mut git_repository;
     pub fn git_reference_name_to_id(out: *mut git_oid,
                                     repo: *mut git_repository,
                                     name: *const c_char) -> c_int;
@@ -1375,7 +2222,6 @@ extern {
                                 r: *mut git_reference,
                                 new_name: *const c_char,
                                 force: c_int,
-                                sig: *const git_signature,
                                 log_message: *const c_char) -> c_int;
     pub fn git_reference_resolve(out: *mut *mut git_reference,
                                  r: *const git_reference) -> c_int;
@@ -1399,14 +2245,12 @@ extern {
                                 name: *const c_char,
                                 id: *const git_oid,
                                 force: c_int,
-                                sig: *const git_signature,
                                 log_message: *const c_char) -> c_int;
     pub fn git_reference_symbolic_create(out: *mut *mut git_reference,
                                          repo: *mut git_repository,
                                          name: *const c_char,
                                          target: *const c_char,
                                          force: c_int,
-                                         sig: *const git_signature,
                                          log_message: *const c_char) -> c_int;
 
     // submodules
@@ -1440,24 +2284,121 @@ extern {
     pub fn git_submodule_path(submodule: *mut git_submodule) -> *const c_char;
     pub fn git_submodule_reload(submodule: *mut git_submodule,
                                 force: c_int) -> c_int;
-    pub fn git_submodule_reload_all(repo: *mut git_repository,
-                                    force: c_int) -> c_int;
-    pub fn git_submodule_save(submodule: *mut git_submodule) -> c_int;
-    pub fn git_submodule_set_ignore(submodule: *mut git_submodule,
-                                    ignore: git_submodule_ignore_t)
-                                    -> git_submodule_ignore_t;
-    pub fn git_submodule_set_update(submodule: *mut git_submodule,
-                                    update: git_submodule_update_t)
-                                    -> git_submodule_update_t;
-    pub fn git_submodule_set_url(submodule: *mut git_submodule,
+    pub fn git_submodule_owner(submodule: *mut git_submodule)
+                               -> *mut git_repository;
+    pub fn git_submodule_set_ignore(repo: *mut git_repository,
+                                    name: *const c_char,
+                                    ignore: git_submodule_ignore_t) -> c_int;
+    pub fn git_submodule_set_update(repo: *mut git_repository,
+                                    name: *const c_char,
+                                    update: git_submodule_update_t) -> c_int;
+    pub fn git_submodule_set_url(repo: *mut git_repository,
+                                 name: *const c_char,
                                  url: *const c_char) -> c_int;
     pub fn git_submodule_sync(submodule: *mut git_submodule) -> c_int;
-    pub fn git_submodule_update(submodule: *mut git_submodule)
-                                -> git_submodule_update_t;
+    pub fn git_submodule_update_strategy(submodule: *mut git_submodule)
+                                         -> git_submodule_update_t;
     pub fn git_submodule_url(submodule: *mut git_submodule) -> *const c_char;
     pub fn git_submodule_wd_id(submodule: *mut git_submodule) -> *const git_oid;
     pub fn git_submodule_status(status: *mut c_uint,
-                                submodule: *mut git_submodule) -> c_int;
+                                repo: *mut git_repository,
+                                name: *const c_char,
+                                ignore: git_submodule_ignore_t) -> c_int;
+
+    // odb
+    pub fn git_odb_free(db: *mut git_odb);
+    pub fn git_odb_exists(db: *mut git_odb, id: *const git_oid) -> c_int;
+    pub fn git_odb_add_disk_alternate(odb: *mut git_odb,
+                                      path: *const c_char) -> c_int;
+    pub fn git_odb_new(out: *mut *mut git_odb) -> c_int;
+    pub fn git_odb_open(out: *mut *mut git_odb,
+                        objects_dir: *const c_char) -> c_int;
+    pub fn git_odb_add_backend(odb: *mut git_odb,
+                               backend: *mut git_odb_backend,
+                               priority: c_int) -> c_int;
+    pub fn git_odb_backend_one_pack(out: *mut *mut git_odb_backend,
+                                    index_file: *const c_char) -> c_int;
+    pub fn git_odb_read(out: *mut *mut git_odb_object,
+                        db: *mut git_odb,
+                        id: *const git_oid) -> c_int;
+    pub fn git_odb_object_free(object: *mut git_odb_object);
+    pub fn git_odb_object_id(object: *mut git_odb_object) -> *const git_oid;
+    pub fn git_odb_object_data(object: *mut git_odb_object) -> *const c_void;
+    pub fn git_odb_object_size(object: *mut git_odb_object) -> size_t;
+    pub fn git_odb_object_type(object: *mut git_odb_object) -> git_otype;
+
+    // mempack
+    pub fn git_mempack_new(out: *mut *mut git_odb_backend) -> c_int;
+    pub fn git_mempack_dump(pack: *mut git_buf,
+                            repo: *mut git_repository,
+                            backend: *mut git_odb_backend) -> c_int;
+    pub fn git_mempack_reset(backend: *mut git_odb_backend) -> c_int;
+
+    // refdb
+    pub fn git_refdb_compress(refdb: *mut git_refdb) -> c_int;
+    pub fn git_refdb_free(refdb: *mut git_refdb);
+    pub fn git_refdb_new(out: *mut *mut git_refdb,
+                         repo: *mut git_repository) -> c_int;
+
+    // packbuilder
+    pub fn git_packbuilder_new(out: *mut *mut git_packbuilder,
+                               repo: *mut git_repository) -> c_int;
+    pub fn git_packbuilder_free(pb: *mut git_packbuilder);
+    pub fn git_packbuilder_insert(pb: *mut git_packbuilder,
+                                  id: *const git_oid,
+                                  name: *const c_char) -> c_int;
+    pub fn git_packbuilder_insert_tree(pb: *mut git_packbuilder,
+                                       id: *const git_oid) -> c_int;
+    pub fn git_packbuilder_insert_commit(pb: *mut git_packbuilder,
+                                         id: *const git_oid) -> c_int;
+    pub fn git_packbuilder_insert_walk(pb: *mut git_packbuilder,
+                                       walk: *mut git_revwalk) -> c_int;
+    pub fn git_packbuilder_write(pb: *mut git_packbuilder,
+                                 path: *const c_char,
+                                 mode: c_uint,
+                                 progress_cb: Option<git_transfer_progress_cb>,
+                                 progress_cb_payload: *mut c_void) -> c_int;
+    pub fn git_packbuilder_hash(pb: *mut git_packbuilder) -> *const git_oid;
+    pub fn git_packbuilder_object_count(pb: *mut git_packbuilder) -> size_t;
+    pub fn git_packbuilder_written(pb: *mut git_packbuilder) -> size_t;
+    pub fn git_packbuilder_foreach(pb: *mut git_packbuilder,
+                                   cb: git_packbuilder_foreach_cb,
+                                   payload: *mut c_void) -> c_int;
+
+    // indexer
+    pub fn git_indexer_options_init(opts: *mut git_indexer_options,
+                                    version: c_uint) -> c_int;
+    pub fn git_indexer_new(out: *mut *mut git_indexer,
+                           path: *const c_char,
+                           mode: c_uint,
+                           odb: *mut git_odb,
+                           opts: *const git_indexer_options) -> c_int;
+    pub fn git_indexer_append(idx: *mut git_indexer,
+                              data: *const c_void,
+                              size: size_t,
+                              stats: *mut git_transfer_progress) -> c_int;
+    pub fn git_indexer_commit(idx: *mut git_indexer,
+                              stats: *mut git_transfer_progress) -> c_int;
+    pub fn git_indexer_hash(idx: *const git_indexer) -> *const git_oid;
+    pub fn git_indexer_free(idx: *mut git_indexer);
+
+    // blame
+    pub fn git_blame_init_options(opts: *mut git_blame_options,
+                                  version: c_uint) -> c_int;
+    pub fn git_blame_file(out: *mut *mut git_blame,
+                          repo: *mut git_repository,
+                          path: *const c_char,
+                          options: *mut git_blame_options) -> c_int;
+    pub fn git_blame_buffer(out: *mut *mut git_blame,
+                            reference: *mut git_blame,
+                            buffer: *const c_char,
+                            buffer_len: size_t) -> c_int;
+    pub fn git_blame_free(blame: *mut git_blame);
+    pub fn git_blame_get_hunk_count(blame: *mut git_blame) -> u32;
+    pub fn git_blame_get_hunk_byindex(blame: *mut git_blame,
+                                      index: u32) -> *const git_blame_hunk;
+    pub fn git_blame_get_hunk_byline(blame: *mut git_blame,
+                                     lineno: size_t) -> *const git_blame_hunk;
 
     // blob
     pub fn git_blob_free(blob: *mut git_blob);
@@ -1470,7 +2411,7 @@ extern {
                                   id: *const git_oid,
                                   len: size_t) -> c_int;
     pub fn git_blob_rawcontent(blob: *const git_blob) -> *const c_void;
-    pub fn git_blob_rawsize(blob: *const git_blob) -> git_off_t;
+    pub fn git_blob_rawsize(blob: *const git_blob) -> u64;
     pub fn git_blob_create_frombuffer(id: *mut git_oid,
                                       repo: *mut git_repository,
                                       buffer: *const c_void,
@@ -1515,6 +2456,33 @@ extern {
                          mode: git_treewalk_mode,
                          callback: git_treewalk_cb,
                          payload: *mut c_void) -> c_int;
+    pub fn git_tree_create_updated(out: *mut git_oid,
+                                   repo: *mut git_repository,
+                                   baseline: *mut git_tree,
+                                   nupdates: size_t,
+                                   updates: *const git_tree_update) -> c_int;
+
+    // treebuilder
+    pub fn git_treebuilder_new(out: *mut *mut git_treebuilder,
+                               repo: *mut git_repository,
+                               source: *const git_tree) -> c_int;
+    pub fn git_treebuilder_clear(bld: *mut git_treebuilder) -> c_int;
+    pub fn git_treebuilder_entrycount(bld: *mut git_treebuilder) -> size_t;
+    pub fn git_treebuilder_free(bld: *mut git_treebuilder);
+    pub fn git_treebuilder_get(bld: *mut git_treebuilder,
+                               filename: *const c_char) -> *const git_tree_entry;
+    pub fn git_treebuilder_insert(out: *mut *const git_tree_entry,
+                                  bld: *mut git_treebuilder,
+                                  filename: *const c_char,
+                                  id: *const git_oid,
+                                  filemode: c_uint) -> c_int;
+    pub fn git_treebuilder_remove(bld: *mut git_treebuilder,
+                                  filename: *const c_char) -> c_int;
+    pub fn git_treebuilder_filter(bld: *mut git_treebuilder,
+                                  filter: git_treebuilder_filter_cb,
+                                  payload: *mut c_void) -> c_int;
+    pub fn git_treebuilder_write(id: *mut git_oid,
+                                 bld: *mut git_treebuilder) -> c_int;
 
     // buf
     pub fn git_buf_free(buffer: *mut git_buf);
@@ -1530,6 +2498,10 @@ extern {
     pub fn git_commit_lookup(commit: *mut *mut git_commit,
                              repo: *mut git_repository,
                              id: *const git_oid) -> c_int;
+    pub fn git_commit_lookup_prefix(commit: *mut *mut git_commit,
+                                    repo: *mut git_repository,
+                                    id: *const git_oid,
+                                    len: size_t) -> c_int;
     pub fn git_commit_message(commit: *const git_commit) -> *const c_char;
     pub fn git_commit_message_encoding(commit: *const git_commit) -> *const c_char;
     pub fn git_commit_message_raw(commit: *const git_commit) -> *const c_char;
@@ -1567,17 +2539,38 @@ extern {
                              tree: *const git_tree,
                              parent_count: size_t,
                              parents: *const *const git_commit) -> c_int;
+    pub fn git_commit_create_buffer(out: *mut git_buf,
+                                    repo: *mut git_repository,
+                                    author: *const git_signature,
+                                    committer: *const git_signature,
+                                    message_encoding: *const c_char,
+                                    message: *const c_char,
+                                    tree: *const git_tree,
+                                    parent_count: size_t,
+                                    parents: *const *const git_commit)
+                                    -> c_int;
+    pub fn git_commit_create_with_signature(id: *mut git_oid,
+                                            repo: *mut git_repository,
+                                            commit_content: *const c_char,
+                                            signature: *const c_char,
+                                            signature_field: *const c_char)
+                                            -> c_int;
+    pub fn git_commit_extract_signature(signature: *mut git_buf,
+                                        signed_data: *mut git_buf,
+                                        repo: *mut git_repository,
+                                        commit_id: *mut git_oid,
+                                        field: *const c_char) -> c_int;
 
     // branch
     pub fn git_branch_create(out: *mut *mut git_reference,
                              repo: *mut git_repository,
                              branch_name: *const c_char,
                              target: *const git_commit,
-                             force: c_int,
-                             signature: *const git_signature,
-                             log_message: *const c_char) -> c_int;
+                             force: c_int) -> c_int;
     pub fn git_branch_delete(branch: *mut git_reference) -> c_int;
     pub fn git_branch_is_head(branch: *const git_reference) -> c_int;
+    pub fn git_branch_name_is_valid(valid: *mut c_int,
+                                    name: *const c_char) -> c_int;
     pub fn git_branch_iterator_free(iter: *mut git_branch_iterator);
     pub fn git_branch_iterator_new(iter: *mut *mut git_branch_iterator,
                                    repo: *mut git_repository,
@@ -1589,9 +2582,7 @@ extern {
     pub fn git_branch_move(out: *mut *mut git_reference,
                            branch: *mut git_reference,
                            new_branch_name: *const c_char,
-                           force: c_int,
-                           signature: *const git_signature,
-                           log_message: *const c_char) -> c_int;
+                           force: c_int) -> c_int;
     pub fn git_branch_name(out: *mut *const c_char,
                            branch: *const git_reference) -> c_int;
     pub fn git_branch_next(out: *mut *mut git_reference,
@@ -1601,10 +2592,27 @@ extern {
                                    upstream_name: *const c_char) -> c_int;
     pub fn git_branch_upstream(out: *mut *mut git_reference,
                                branch: *const git_reference) -> c_int;
+    pub fn git_branch_upstream_name(out: *mut git_buf,
+                                    repo: *mut git_repository,
+                                    refname: *const c_char) -> c_int;
+    pub fn git_branch_remote_name(out: *mut git_buf,
+                                  repo: *mut git_repository,
+                                  refname: *const c_char) -> c_int;
+    pub fn git_branch_upstream_remote(out: *mut git_buf,
+                                      repo: *mut git_repository,
+                                      refname: *const c_char) -> c_int;
+    pub fn git_branch_upstream_merge(out: *mut git_buf,
+                                     repo: *mut git_repository,
+                                     refname: *const c_char) -> c_int;
 
     // index
+    pub fn git_index_owner(index: *const git_index) -> *mut git_repository;
     pub fn git_index_add(index: *mut git_index,
                          entry: *const git_index_entry) -> c_int;
+    pub fn git_index_add_frombuffer(index: *mut git_index,
+                                    entry: *const git_index_entry,
+                                    buffer: *const c_void,
+                                    len: size_t) -> c_int;
     pub fn git_index_add_all(index: *mut git_index,
                              pathspec: *const git_strarray,
                              flags: c_uint,
@@ -1612,7 +2620,20 @@ extern {
                              payload: *mut c_void) -> c_int;
     pub fn git_index_add_bypath(index: *mut git_index,
                                 path: *const c_char) -> c_int;
+    pub fn git_index_caps(index: *const git_index) -> c_int;
+    pub fn git_index_checksum(index: *mut git_index) -> *const git_oid;
     pub fn git_index_clear(index: *mut git_index) -> c_int;
+    pub fn git_index_conflict_add(index: *mut git_index,
+                                  ancestor_entry: *const git_index_entry,
+                                  our_entry: *const git_index_entry,
+                                  their_entry: *const git_index_entry) -> c_int;
+    pub fn git_index_conflict_get(ancestor_out: *mut *const git_index_entry,
+                                  our_out: *mut *const git_index_entry,
+                                  their_out: *mut *const git_index_entry,
+                                  index: *mut git_index,
+                                  path: *const c_char) -> c_int;
+    pub fn git_index_conflict_remove(index: *mut git_index,
+                                     path: *const c_char) -> c_int;
     pub fn git_index_entry_stage(entry: *const git_index_entry) -> c_int;
     pub fn git_index_entrycount(entry: *const git_index) -> size_t;
     pub fn git_index_find(at_pos: *mut size_t,
@@ -1624,6 +2645,7 @@ extern {
     pub fn git_index_get_bypath(index: *mut git_index,
                                 path: *const c_char,
                                 stage: c_int) -> *const git_index_entry;
+    pub fn git_index_has_conflicts(index: *const git_index) -> c_int;
     pub fn git_index_new(index: *mut *mut git_index) -> c_int;
     pub fn git_index_open(index: *mut *mut git_index,
                           index_path: *const c_char) -> c_int;
@@ -1643,10 +2665,32 @@ extern {
     pub fn git_index_remove_directory(index: *mut git_index,
                                       dir: *const c_char,
                                       stage: c_int) -> c_int;
+    pub fn git_index_reuc_add(index: *mut git_index,
+                              path: *const c_char,
+                              ancestor_mode: c_int,
+                              ancestor_id: *const git_oid,
+                              our_mode: c_int,
+                              our_id: *const git_oid,
+                              their_mode: c_int,
+                              their_id: *const git_oid) -> c_int;
+    pub fn git_index_reuc_clear(index: *mut git_index);
+    pub fn git_index_reuc_entrycount(index: *mut git_index) -> size_t;
+    pub fn git_index_reuc_find(at_pos: *mut size_t,
+                               index: *mut git_index,
+                               path: *const c_char) -> c_int;
+    pub fn git_index_reuc_get_byindex(index: *mut git_index, n: size_t)
+                                      -> *const git_index_reuc_entry;
+    pub fn git_index_reuc_get_bypath(index: *mut git_index,
+                                     path: *const c_char)
+                                     -> *const git_index_reuc_entry;
+    pub fn git_index_reuc_remove(index: *mut git_index, n: size_t) -> c_int;
+    pub fn git_index_set_caps(index: *mut git_index, caps: c_int) -> c_int;
+    pub fn git_index_set_version(index: *mut git_index, v: c_uint) -> c_int;
     pub fn git_index_update_all(index: *mut git_index,
                                 pathspec: *const git_strarray,
                                 callback: Option<git_index_matched_path_cb>,
                                 payload: *mut c_void) -> c_int;
+    pub fn git_index_version(index: *mut git_index) -> c_uint;
     pub fn git_index_write(index: *mut git_index) -> c_int;
     pub fn git_index_write_tree(out: *mut git_oid,
                                 index: *mut git_index) -> c_int;
@@ -1658,6 +2702,7 @@ extern {
     pub fn git_config_add_file_ondisk(cfg: *mut git_config,
                                       path: *const c_char,
                                       level: git_config_level_t,
+                                      repo: *const git_repository,
                                       force: c_int) -> c_int;
     pub fn git_config_delete_entry(cfg: *mut git_config,
                                    name: *const c_char) -> c_int;
@@ -1671,7 +2716,8 @@ extern {
     pub fn git_config_get_bool(out: *mut c_int,
                                cfg: *const git_config,
                                name: *const c_char) -> c_int;
-    pub fn git_config_get_entry(out: *mut *const git_config_entry,
+    pub fn git_config_entry_free(entry: *mut git_config_entry);
+    pub fn git_config_get_entry(out: *mut *mut git_config_entry,
                                 cfg: *const git_config,
                                 name: *const c_char) -> c_int;
     pub fn git_config_get_int32(out: *mut i32,
@@ -1683,6 +2729,9 @@ extern {
     pub fn git_config_get_string(out: *mut *const c_char,
                                  cfg: *const git_config,
                                  name: *const c_char) -> c_int;
+    pub fn git_config_get_path(out: *mut git_buf,
+                               cfg: *const git_config,
+                               name: *const c_char) -> c_int;
     pub fn git_config_iterator_free(iter: *mut git_config_iterator);
     pub fn git_config_iterator_glob_new(out: *mut *mut git_config_iterator,
                                         cfg: *const git_config,
@@ -1751,24 +2800,8 @@ extern {
                                            password: *const c_char) -> c_int;
 
     // push
-    pub fn git_push_add_refspec(push: *mut git_push,
-                                refspec: *const c_char) -> c_int;
-    pub fn git_push_finish(push: *mut git_push) -> c_int;
-    pub fn git_push_free(push: *mut git_push);
     pub fn git_push_init_options(opts: *mut git_push_options,
                                  version: c_uint) -> c_int;
-    pub fn git_push_new(out: *mut *mut git_push,
-                        remote: *mut git_remote) -> c_int;
-    pub fn git_push_set_options(push: *mut git_push,
-                                opts: *const git_push_options) -> c_int;
-    pub fn git_push_update_tips(push: *mut git_push,
-                                signature: *const git_signature,
-                                reflog_message: *const c_char) -> c_int;
-    pub fn git_push_status_foreach(push: *mut git_push,
-                                   cb: extern fn(*const c_char,
-                                                 *const c_char,
-                                                 *mut c_void) -> c_int,
-                                   data: *mut c_void) -> c_int;
 
     // tags
     pub fn git_tag_annotation_create(oid: *mut git_oid,
@@ -1836,6 +2869,25 @@ extern {
 
     // notes
     pub fn git_note_author(note: *const git_note) -> *const git_signature;
+    pub fn git_note_commit_create(notes_commit_out: *mut git_oid,
+                                  notes_blob_out: *mut git_oid,
+                                  repo: *mut git_repository,
+                                  parent: *mut git_commit,
+                                  author: *const git_signature,
+                                  committer: *const git_signature,
+                                  oid: *const git_oid,
+                                  note: *const c_char,
+                                  allow_note_overwrite: c_int) -> c_int;
+    pub fn git_note_commit_read(out: *mut *mut git_note,
+                                repo: *mut git_repository,
+                                notes_commit: *mut git_commit,
+                                oid: *const git_oid) -> c_int;
+    pub fn git_note_commit_remove(notes_commit_out: *mut git_oid,
+                                  repo: *mut git_repository,
+                                  notes_commit: *mut git_commit,
+                                  author: *const git_signature,
+                                  committer: *const git_signature,
+                                  oid: *const git_oid) -> c_int;
     pub fn git_note_committer(note: *const git_note) -> *const git_signature;
     pub fn git_note_create(out: *mut git_oid,
                            repo: *mut git_repository,
@@ -1845,7 +2897,7 @@ extern {
                            oid: *const git_oid,
                            note: *const c_char,
                            force: c_int) -> c_int;
-    pub fn git_note_default_ref(out: *mut *const c_char,
+    pub fn git_note_default_ref(out: *mut git_buf,
                                 repo: *mut git_repository) -> c_int;
     pub fn git_note_free(note: *mut git_note);
     pub fn git_note_id(note: *const git_note) -> *const git_oid;
@@ -1867,14 +2919,26 @@ extern {
                            committer: *const git_signature,
                            oid: *const git_oid) -> c_int;
 
+    // commit graph
+    pub fn git_commit_graph_writer_new(out: *mut *mut git_commit_graph_writer,
+                                       objects_info_dir: *const c_char)
+                                       -> c_int;
+    pub fn git_commit_graph_writer_free(w: *mut git_commit_graph_writer);
+    pub fn git_commit_graph_writer_add_revwalk(w: *mut git_commit_graph_writer,
+                                               walk: *mut git_revwalk)
+                                               -> c_int;
+    pub fn git_commit_graph_writer_commit(w: *mut git_commit_graph_writer,
+                                          opts: *mut c_void) -> c_int;
+
     // revwalk
     pub fn git_revwalk_new(out: *mut *mut git_revwalk,
                            repo: *mut git_repository) -> c_int;
     pub fn git_revwalk_free(walk: *mut git_revwalk);
 
-    pub fn git_revwalk_reset(walk: *mut git_revwalk);
+    pub fn git_revwalk_reset(walk: *mut git_revwalk) -> c_int;
 
-    pub fn git_revwalk_sorting(walk: *mut git_revwalk, sort_mode: c_uint);
+    pub fn git_revwalk_sorting(walk: *mut git_revwalk,
+                               sort_mode: c_uint) -> c_int;
 
     pub fn git_revwalk_push_head(walk: *mut git_revwalk) -> c_int;
     pub fn git_revwalk_push(walk: *mut git_revwalk,
@@ -1885,7 +2949,7 @@ extern {
                                  glob: *const c_char) -> c_int;
     pub fn git_revwalk_push_range(walk: *mut git_revwalk,
                                   range: *const c_char) -> c_int;
-    pub fn git_revwalk_simplify_first_parent(walk: *mut git_revwalk);
+    pub fn git_revwalk_simplify_first_parent(walk: *mut git_revwalk) -> c_int;
 
     pub fn git_revwalk_hide_head(walk: *mut git_revwalk) -> c_int;
     pub fn git_revwalk_hide(walk: *mut git_revwalk,
@@ -1896,12 +2960,193 @@ extern {
                                  refname: *const c_char) -> c_int;
 
     pub fn git_revwalk_next(out: *mut git_oid, walk: *mut git_revwalk) -> c_int;
+    pub fn git_revwalk_add_hide_cb(walk: *mut git_revwalk,
+                                   hide_cb: Option<git_revwalk_hide_cb>,
+                                   payload: *mut c_void) -> c_int;
+
+    // annotated commit
+    pub fn git_annotated_commit_free(commit: *mut git_annotated_commit);
+    pub fn git_annotated_commit_from_fetchhead(
+        out: *mut *mut git_annotated_commit,
+        repo: *mut git_repository,
+        branch_name: *const c_char,
+        remote_url: *const c_char,
+        id: *const git_oid) -> c_int;
+    pub fn git_annotated_commit_from_ref(out: *mut *mut git_annotated_commit,
+                                         repo: *mut git_repository,
+                                         reference: *const git_reference)
+                                         -> c_int;
+    pub fn git_annotated_commit_from_revspec(
+        out: *mut *mut git_annotated_commit,
+        repo: *mut git_repository,
+        revspec: *const c_char) -> c_int;
+    pub fn git_annotated_commit_id(commit: *const git_annotated_commit)
+                                   -> *const git_oid;
+    pub fn git_annotated_commit_lookup(out: *mut *mut git_annotated_commit,
+                                       repo: *mut git_repository,
+                                       id: *const git_oid) -> c_int;
+    pub fn git_annotated_commit_ref(commit: *const git_annotated_commit)
+                                    -> *const c_char;
 
     // merge
+    pub fn git_merge(repo: *mut git_repository,
+                     their_heads: *mut *const git_annotated_commit,
+                     their_heads_len: size_t,
+                     merge_opts: *const git_merge_options,
+                     checkout_opts: *const git_checkout_options) -> c_int;
+    pub fn git_merge_analysis(analysis_out: *mut git_merge_analysis_t,
+                              pref_out: *mut git_merge_preference_t,
+                              repo: *mut git_repository,
+                              their_heads: *mut *const git_annotated_commit,
+                              their_heads_len: size_t) -> c_int;
     pub fn git_merge_base(out: *mut git_oid,
                           repo: *mut git_repository,
                           one: *const git_oid,
                           two: *const git_oid) -> c_int;
+    pub fn git_merge_base_many(out: *mut git_oid,
+                               repo: *mut git_repository,
+                               length: size_t,
+                               input_array: *const git_oid) -> c_int;
+    pub fn git_merge_base_octopus(out: *mut git_oid,
+                                  repo: *mut git_repository,
+                                  length: size_t,
+                                  input_array: *const git_oid) -> c_int;
+    pub fn git_merge_bases(out: *mut git_oidarray,
+                           repo: *mut git_repository,
+                           one: *const git_oid,
+                           two: *const git_oid) -> c_int;
+    pub fn git_merge_bases_many(out: *mut git_oidarray,
+                                repo: *mut git_repository,
+                                length: size_t,
+                                input_array: *const git_oid) -> c_int;
+    pub fn git_merge_file(out: *mut git_merge_file_result,
+                          ancestor: *const git_merge_file_input,
+                          ours: *const git_merge_file_input,
+                          theirs: *const git_merge_file_input,
+                          opts: *const git_merge_file_options) -> c_int;
+    pub fn git_merge_file_from_index(out: *mut git_merge_file_result,
+                                     repo: *mut git_repository,
+                                     ancestor: *const git_index_entry,
+                                     ours: *const git_index_entry,
+                                     theirs: *const git_index_entry,
+                                     opts: *const git_merge_file_options)
+                                     -> c_int;
+    pub fn git_merge_file_init_input(opts: *mut git_merge_file_input,
+                                     version: c_uint) -> c_int;
+    pub fn git_merge_file_init_options(opts: *mut git_merge_file_options,
+                                       version: c_uint) -> c_int;
+    pub fn git_merge_file_result_free(result: *mut git_merge_file_result);
+    pub fn git_merge_commits(out: *mut *mut git_index,
+                             repo: *mut git_repository,
+                             our_commit: *const git_commit,
+                             their_commit: *const git_commit,
+                             opts: *const git_merge_options) -> c_int;
+    pub fn git_merge_init_options(opts: *mut git_merge_options,
+                                  version: c_uint) -> c_int;
+    pub fn git_merge_trees(out: *mut *mut git_index,
+                           repo: *mut git_repository,
+                           ancestor_tree: *const git_tree,
+                           our_tree: *const git_tree,
+                           their_tree: *const git_tree,
+                           opts: *const git_merge_options) -> c_int;
+
+    // message
+    pub fn git_message_prettify(out: *mut git_buf,
+                                message: *const c_char,
+                                strip_comments: c_int,
+                                comment_char: c_char) -> c_int;
+    pub fn git_message_trailers(arr: *mut git_message_trailer_array,
+                                message: *const c_char) -> c_int;
+    pub fn git_message_trailer_array_free(arr: *mut git_message_trailer_array);
+
+    // rebase
+    pub fn git_rebase_init_options(opts: *mut git_rebase_options,
+                                   version: c_uint) -> c_int;
+    pub fn git_rebase_init(out: *mut *mut git_rebase,
+                           repo: *mut git_repository,
+                           branch: *const git_annotated_commit,
+                           upstream: *const git_annotated_commit,
+                           onto: *const git_annotated_commit,
+                           opts: *const git_rebase_options) -> c_int;
+    pub fn git_rebase_open(out: *mut *mut git_rebase,
+                           repo: *mut git_repository,
+                           opts: *const git_rebase_options) -> c_int;
+    pub fn git_rebase_orig_head_name(rebase: *mut git_rebase) -> *const c_char;
+    pub fn git_rebase_orig_head_id(rebase: *mut git_rebase) -> *const git_oid;
+    pub fn git_rebase_onto_name(rebase: *mut git_rebase) -> *const c_char;
+    pub fn git_rebase_onto_id(rebase: *mut git_rebase) -> *const git_oid;
+    pub fn git_rebase_operation_entrycount(rebase: *mut git_rebase) -> size_t;
+    pub fn git_rebase_operation_current(rebase: *mut git_rebase) -> size_t;
+    pub fn git_rebase_operation_byindex(rebase: *mut git_rebase,
+                                        idx: size_t)
+                                        -> *mut git_rebase_operation;
+    pub fn git_rebase_next(operation: *mut *mut git_rebase_operation,
+                           rebase: *mut git_rebase) -> c_int;
+    pub fn git_rebase_inmemory_index(index: *mut *mut git_index,
+                                     rebase: *mut git_rebase) -> c_int;
+    pub fn git_rebase_commit(id: *mut git_oid,
+                             rebase: *mut git_rebase,
+                             author: *const git_signature,
+                             committer: *const git_signature,
+                             message_encoding: *const c_char,
+                             message: *const c_char) -> c_int;
+    pub fn git_rebase_abort(rebase: *mut git_rebase) -> c_int;
+    pub fn git_rebase_finish(rebase: *mut git_rebase,
+                             signature: *const git_signature) -> c_int;
+    pub fn git_rebase_free(rebase: *mut git_rebase);
+
+    // stash
+    pub fn git_stash_save(out: *mut git_oid,
+                          repo: *mut git_repository,
+                          stasher: *const git_signature,
+                          message: *const c_char,
+                          flags: u32) -> c_int;
+    pub fn git_stash_apply_init_options(opts: *mut git_stash_apply_options,
+                                        version: c_uint) -> c_int;
+    pub fn git_stash_apply(repo: *mut git_repository,
+                           index: size_t,
+                           options: *const git_stash_apply_options) -> c_int;
+    pub fn git_stash_foreach(repo: *mut git_repository,
+                             callback: git_stash_cb,
+                             payload: *mut c_void) -> c_int;
+    pub fn git_stash_drop(repo: *mut git_repository, index: size_t) -> c_int;
+    pub fn git_stash_pop(repo: *mut git_repository,
+                         index: size_t,
+                         options: *const git_stash_apply_options) -> c_int;
+
+    // worktree
+    pub fn git_worktree_list(out: *mut git_strarray,
+                             repo: *mut git_repository) -> c_int;
+    pub fn git_worktree_lookup(out: *mut *mut git_worktree,
+                               repo: *mut git_repository,
+                               name: *const c_char) -> c_int;
+    pub fn git_worktree_open_from_repository(out: *mut *mut git_worktree,
+                                             repo: *mut git_repository)
+                                             -> c_int;
+    pub fn git_worktree_free(wt: *mut git_worktree);
+    pub fn git_worktree_validate(wt: *const git_worktree) -> c_int;
+    pub fn git_worktree_add_init_options(opts: *mut git_worktree_add_options,
+                                         version: c_uint) -> c_int;
+    pub fn git_worktree_add(out: *mut *mut git_worktree,
+                            repo: *mut git_repository,
+                            name: *const c_char,
+                            path: *const c_char,
+                            opts: *const git_worktree_add_options) -> c_int;
+    pub fn git_worktree_lock(wt: *mut git_worktree,
+                             reason: *const c_char) -> c_int;
+    pub fn git_worktree_unlock(wt: *mut git_worktree) -> c_int;
+    pub fn git_worktree_is_locked(reason: *mut git_buf,
+                                  wt: *const git_worktree) -> c_int;
+    pub fn git_worktree_name(wt: *const git_worktree) -> *const c_char;
+    pub fn git_worktree_path(wt: *const git_worktree) -> *const c_char;
+    pub fn git_worktree_prune_init_options(
+        opts: *mut git_worktree_prune_options,
+        version: c_uint) -> c_int;
+    pub fn git_worktree_is_prunable(wt: *mut git_worktree,
+                                    opts: *mut git_worktree_prune_options)
+                                    -> c_int;
+    pub fn git_worktree_prune(wt: *mut git_worktree,
+                              opts: *mut git_worktree_prune_options) -> c_int;
 
     // pathspec
     pub fn git_pathspec_free(ps: *mut git_pathspec);
@@ -1945,18 +3190,20 @@ extern {
                                    buffer_len: size_t,
                                    buffer_as_path: *const c_char,
                                    options: *const git_diff_options,
-                                   file_cb: git_diff_file_cb,
-                                   hunk_cb: git_diff_hunk_cb,
-                                   line_cb: git_diff_line_cb,
+                                   file_cb: Option<git_diff_file_cb>,
+                                   binary_cb: Option<git_diff_binary_cb>,
+                                   hunk_cb: Option<git_diff_hunk_cb>,
+                                   line_cb: Option<git_diff_line_cb>,
                                    payload: *mut c_void) -> c_int;
     pub fn git_diff_blobs(old_blob: *const git_blob,
                           old_as_path: *const c_char,
                           new_blob: *const git_blob,
                           new_as_path: *const c_char,
                           options: *const git_diff_options,
-                          file_cb: git_diff_file_cb,
-                          hunk_cb: git_diff_hunk_cb,
-                          line_cb: git_diff_line_cb,
+                          file_cb: Option<git_diff_file_cb>,
+                          binary_cb: Option<git_diff_binary_cb>,
+                          hunk_cb: Option<git_diff_hunk_cb>,
+                          line_cb: Option<git_diff_line_cb>,
                           payload: *mut c_void) -> c_int;
     pub fn git_diff_buffers(old_buffer: *const c_void,
                             old_len: size_t,
@@ -1965,24 +3212,32 @@ extern {
                             new_len: size_t,
                             new_as_path: *const c_char,
                             options: *const git_diff_options,
-                            file_cb: git_diff_file_cb,
-                            hunk_cb: git_diff_hunk_cb,
-                            line_cb: git_diff_line_cb,
+                            file_cb: Option<git_diff_file_cb>,
+                            binary_cb: Option<git_diff_binary_cb>,
+                            hunk_cb: Option<git_diff_hunk_cb>,
+                            line_cb: Option<git_diff_line_cb>,
                             payload: *mut c_void) -> c_int;
     pub fn git_diff_find_similar(diff: *mut git_diff,
                                  options: *const git_diff_find_options) -> c_int;
     pub fn git_diff_find_init_options(opts: *mut git_diff_find_options,
                                       version: c_uint) -> c_int;
     pub fn git_diff_foreach(diff: *mut git_diff,
-                            file_cb: git_diff_file_cb,
-                            hunk_cb: git_diff_hunk_cb,
-                            line_cb: git_diff_line_cb,
+                            file_cb: Option<git_diff_file_cb>,
+                            binary_cb: Option<git_diff_binary_cb>,
+                            hunk_cb: Option<git_diff_hunk_cb>,
+                            line_cb: Option<git_diff_line_cb>,
                             payload: *mut c_void) -> c_int;
     pub fn git_diff_free(diff: *mut git_diff);
+    pub fn git_diff_from_buffer(diff: *mut *mut git_diff,
+                                content: *const c_char,
+                                content_len: size_t) -> c_int;
     pub fn git_diff_get_delta(diff: *const git_diff,
                               idx: size_t) -> *const git_diff_delta;
     pub fn git_diff_get_stats(out: *mut *mut git_diff_stats,
                               diff: *mut git_diff) -> c_int;
+    pub fn git_diff_patchid(out: *mut git_oid,
+                            diff: *mut git_diff,
+                            opts: *mut git_diff_patchid_options) -> c_int;
     pub fn git_diff_index_to_workdir(diff: *mut *mut git_diff,
                                      repo: *mut git_repository,
                                      index: *mut git_index,
@@ -2028,6 +3283,89 @@ extern {
                                                opts: *const git_diff_options)
                                                -> c_int;
 
+    // email
+    pub fn git_email_create_from_commit(out: *mut git_buf,
+                                        commit: *mut git_commit,
+                                        opts: *const git_email_create_options)
+                                        -> c_int;
+    pub fn git_email_create_from_diff(out: *mut git_buf,
+                                      diff: *mut git_diff,
+                                      patch_idx: size_t,
+                                      patch_count: size_t,
+                                      commit_id: *const git_oid,
+                                      summary: *const c_char,
+                                      body: *const c_char,
+                                      author: *const git_signature,
+                                      opts: *const git_email_create_options)
+                                      -> c_int;
+
+    // apply
+    pub fn git_apply(repo: *mut git_repository,
+                     diff: *mut git_diff,
+                     location: git_apply_location_t,
+                     options: *const git_apply_options) -> c_int;
+    pub fn git_apply_options_init(opts: *mut git_apply_options,
+                                  version: c_uint) -> c_int;
+    pub fn git_apply_to_tree(out: *mut *mut git_index,
+                             repo: *mut git_repository,
+                             preimage: *mut git_tree,
+                             diff: *mut git_diff,
+                             options: *const git_apply_options) -> c_int;
+
+    // patch
+    pub fn git_patch_free(patch: *mut git_patch);
+    pub fn git_patch_from_blob_and_buffer(out: *mut *mut git_patch,
+                                          old_blob: *const git_blob,
+                                          old_as_path: *const c_char,
+                                          buffer: *const c_char,
+                                          buffer_len: size_t,
+                                          buffer_as_path: *const c_char,
+                                          opts: *const git_diff_options)
+                                          -> c_int;
+    pub fn git_patch_from_blobs(out: *mut *mut git_patch,
+                                old_blob: *const git_blob,
+                                old_as_path: *const c_char,
+                                new_blob: *const git_blob,
+                                new_as_path: *const c_char,
+                                opts: *const git_diff_options) -> c_int;
+    pub fn git_patch_from_buffers(out: *mut *mut git_patch,
+                                  old_buffer: *const c_void,
+                                  old_len: size_t,
+                                  old_as_path: *const c_char,
+                                  new_buffer: *const c_char,
+                                  new_len: size_t,
+                                  new_as_path: *const c_char,
+                                  opts: *const git_diff_options) -> c_int;
+    pub fn git_patch_from_diff(out: *mut *mut git_patch,
+                               diff: *mut git_diff,
+                               idx: size_t) -> c_int;
+    pub fn git_patch_get_delta(patch: *const git_patch)
+                               -> *const git_diff_delta;
+    pub fn git_patch_get_hunk(out: *mut *const git_diff_hunk,
+                              lines_in_hunk: *mut size_t,
+                              patch: *mut git_patch,
+                              hunk_idx: size_t) -> c_int;
+    pub fn git_patch_get_line_in_hunk(out: *mut *const git_diff_line,
+                                      patch: *mut git_patch,
+                                      hunk_idx: size_t,
+                                      line_of_hunk: size_t) -> c_int;
+    pub fn git_patch_line_stats(total_context: *mut size_t,
+                                total_additions: *mut size_t,
+                                total_deletions: *mut size_t,
+                                patch: *const git_patch) -> c_int;
+    pub fn git_patch_num_hunks(patch: *const git_patch) -> size_t;
+    pub fn git_patch_num_lines_in_hunk(patch: *const git_patch,
+                                       hunk_idx: size_t) -> c_int;
+    pub fn git_patch_print(patch: *mut git_patch,
+                           print_cb: git_diff_line_cb,
+                           payload: *mut c_void) -> c_int;
+    pub fn git_patch_size(patch: *mut git_patch,
+                          include_context: c_int,
+                          include_hunk_headers: c_int,
+                          include_file_headers: c_int) -> size_t;
+    pub fn git_patch_to_buf(buf: *mut git_buf,
+                            patch: *mut git_patch) -> c_int;
+
     pub fn git_graph_ahead_behind(ahead: *mut size_t, behind: *mut size_t,
                                   repo: *mut git_repository,
                                   local: *const git_oid, upstream: *const git_oid)
@@ -2072,6 +3410,9 @@ extern {
                                   cb: git_transport_cb,
                                   param: *mut c_void) -> c_int;
     pub fn git_transport_unregister(prefix: *const c_char) -> c_int;
+    pub fn git_transport_local(out: *mut *mut git_transport,
+                               owner: *mut git_remote,
+                               payload: *mut c_void) -> c_int;
     pub fn git_transport_smart(out: *mut *mut git_transport,
                                owner: *mut git_remote,
                                payload: *mut c_void) -> c_int;
@@ -2079,7 +3420,7 @@ extern {
 
 #[test]
 fn smoke() {
-    unsafe { git_threads_init(); }
+    unsafe { git_libgit2_init(); }
 }
 
 pub fn issue_14344_workaround() {
@@ -0,0 +1,182 @@
+use std::mem;
+use libc::{c_int, c_void};
+
+use {raw, panic, DiffDelta, DiffHunk};
+use util::Binding;
+
+/// Options to specify when applying a diff with `Repository::apply` or
+/// `Repository::apply_to_tree`.
+pub struct ApplyOptions<'cb> {
+    raw: raw::git_apply_options,
+    delta_cb: Option<Box<DeltaCb<'cb>>>,
+    hunk_cb: Option<Box<HunkCb<'cb>>>,
+}
+
+/// Callback invoked for each delta before it is applied.
+///
+/// Returning `false` skips the delta, `true` applies it.
+pub type DeltaCb<'a> = FnMut(Option<DiffDelta>) -> bool + 'a;
+
+/// Callback invoked for each hunk before it is applied.
+///
+/// Returning `false` skips the hunk, `true` applies it.
+pub type HunkCb<'a> = FnMut(Option<DiffHunk>) -> bool + 'a;
+
+impl<'cb> ApplyOptions<'cb> {
+    /// Creates a new set of empty options (zeroed).
+    pub fn new() -> ApplyOptions<'cb> {
+        let mut opts = ApplyOptions {
+            raw: unsafe { mem::zeroed() },
+            delta_cb: None,
+            hunk_cb: None,
+        };
+        assert_eq!(unsafe {
+            raw::git_apply_options_init(&mut opts.raw,
+                                        raw::GIT_APPLY_OPTIONS_VERSION)
+        }, 0);
+        opts
+    }
+
+    /// When applying a patch, callback that will be made per delta (file).
+    pub fn delta_callback<F>(&mut self, cb: F) -> &mut ApplyOptions<'cb>
+                             where F: FnMut(Option<DiffDelta>) -> bool + 'cb {
+        self.delta_cb = Some(Box::new(cb) as Box<DeltaCb<'cb>>);
+        self
+    }
+
+    /// When applying a patch, callback that will be made per hunk.
+    pub fn hunk_callback<F>(&mut self, cb: F) -> &mut ApplyOptions<'cb>
+                            where F: FnMut(Option<DiffHunk>) -> bool + 'cb {
+        self.hunk_cb = Some(Box::new(cb) as Box<HunkCb<'cb>>);
+        self
+    }
+
+    /// Don't actually make changes, just test that the patch applies.
+    pub fn check(&mut self, check: bool) -> &mut ApplyOptions<'cb> {
+        if check {
+            self.raw.flags |= raw::GIT_APPLY_CHECK;
+        } else {
+            self.raw.flags &= !raw::GIT_APPLY_CHECK;
+        }
+        self
+    }
+
+    /// Pointer to a raw git_apply_options structure configured with the
+    /// callbacks registered so far.
+    ///
+    /// This function is unsafe as the pointer is only valid so long as this
+    /// structure is not moved, modified, or used elsewhere.
+    pub unsafe fn raw(&mut self) -> *const raw::git_apply_options {
+        if self.delta_cb.is_some() {
+            let f: raw::git_apply_delta_cb = delta_cb;
+            self.raw.delta_cb = Some(f);
+        }
+        if self.hunk_cb.is_some() {
+            let f: raw::git_apply_hunk_cb = hunk_cb;
+            self.raw.hunk_cb = Some(f);
+        }
+        self.raw.payload = self as *mut _ as *mut _;
+        &self.raw as *const _
+    }
+}
+
+extern fn delta_cb(delta: *const raw::git_diff_delta,
+                   data: *mut c_void) -> c_int {
+    unsafe {
+        let payload = &mut *(data as *mut ApplyOptions);
+        let callback = match payload.delta_cb {
+            Some(ref mut c) => c,
+            None => return 0,
+        };
+        let delta = Binding::from_raw_opt(delta as *mut _);
+        match panic::wrap(|| callback(delta)) {
+            Some(true) => 0,
+            Some(false) => 1,
+            None => -1,
+        }
+    }
+}
+
+extern fn hunk_cb(hunk: *const raw::git_diff_hunk,
+                  data: *mut c_void) -> c_int {
+    unsafe {
+        let payload = &mut *(data as *mut ApplyOptions);
+        let callback = match payload.hunk_cb {
+            Some(ref mut c) => c,
+            None => return 0,
+        };
+        let hunk = Binding::from_raw_opt(hunk);
+        match panic::wrap(|| callback(hunk)) {
+            Some(true) => 0,
+            Some(false) => 1,
+            None => -1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::File;
+    use {ApplyLocation, ApplyOptions, Diff, DiffOptions, Repository};
+
+    fn setup() -> (::std::old_io::TempDir, Repository, Diff) {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join("foo")).write_str("foo\n").unwrap();
+        File::create(&td.path().join("bar")).write_str("bar\n").unwrap();
+        let diff = {
+            let mut opts = DiffOptions::new();
+            opts.include_untracked(true).show_untracked_content(true);
+            repo.diff_index_to_workdir(None, Some(&mut opts)).unwrap()
+        };
+        (td, repo, diff)
+    }
+
+    #[test]
+    fn apply_to_tree() {
+        let (_td, repo, diff) = setup();
+        assert_eq!(diff.deltas().len(), 2);
+        let head = repo.head().unwrap().target().unwrap();
+        let tree = repo.find_commit(head).unwrap().tree().unwrap();
+
+        let index = repo.apply_to_tree(&tree, &diff, None).unwrap();
+        assert_eq!(index.len(), 2);
+
+        let mut opts = ApplyOptions::new();
+        opts.delta_callback(|delta| {
+            delta.unwrap().new_file().path() == Some(Path::new("bar"))
+        });
+        let index = repo.apply_to_tree(&tree, &diff, Some(&mut opts)).unwrap();
+        assert_eq!(index.len(), 1);
+        assert!(index.get_path(&Path::new("bar"), 0).is_some());
+
+        let mut hunks = 0;
+        {
+            let mut opts = ApplyOptions::new();
+            opts.hunk_callback(|hunk| {
+                assert!(hunk.is_some());
+                hunks += 1;
+                false
+            });
+            let index = repo.apply_to_tree(&tree, &diff,
+                                           Some(&mut opts)).unwrap();
+            assert_eq!(index.len(), 2);
+        }
+        assert_eq!(hunks, 2);
+    }
+
+    #[test]
+    fn apply_to_index() {
+        let (_td, repo, diff) = setup();
+        let mut opts = ApplyOptions::new();
+        opts.check(true);
+        repo.apply(&diff, ApplyLocation::Index, Some(&mut opts)).unwrap();
+        assert_eq!(repo.index().unwrap().len(), 0);
+
+        repo.apply(&diff, ApplyLocation::Index, None).unwrap();
+        let index = repo.index().unwrap();
+        assert_eq!(index.len(), 2);
+
+        // the working directory already contains the changes
+        assert!(repo.apply(&diff, ApplyLocation::WorkDir, None).is_err());
+    }
+}
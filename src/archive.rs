@@ -0,0 +1,681 @@
+use std::mem;
+use std::old_io::{self, IoError, IoResult};
+
+use {raw, Error, Oid, Repository, Tree, Commit, ArchiveFormat, AttrValue};
+use {Index, ATTR_CHECK_INDEX_ONLY};
+use util::{self, io_error, Binding};
+
+const S_IFMT: i32 = 0o170000;
+const S_IFDIR: i32 = 0o040000;
+const S_IFLNK: i32 = 0o120000;
+const S_IFGITLINK: i32 = 0o160000;
+const TAR_MAX_SIZE: u64 = 0o77777777777;
+
+/// Options controlling how `Repository::archive` writes an archive.
+pub struct ArchiveOptions {
+    format: ArchiveFormat,
+    prefix: Vec<u8>,
+    commit: Option<Oid>,
+    mtime: Option<i64>,
+}
+
+// The state of an archive being written out.
+struct Archiver<'a> {
+    out: &'a mut (Writer + 'a),
+    format: ArchiveFormat,
+    mtime: i64,
+    offset: u64,
+    central: Vec<u8>,
+    entries: usize,
+}
+
+impl ArchiveOptions {
+    /// Creates a new default set of archive options, writing a tar archive
+    /// without any prefix.
+    pub fn new() -> ArchiveOptions {
+        ArchiveOptions {
+            format: ArchiveFormat::Tar,
+            prefix: Vec::new(),
+            commit: None,
+            mtime: None,
+        }
+    }
+
+    /// Set the format of the archive.
+    pub fn format(&mut self, format: ArchiveFormat) -> &mut ArchiveOptions {
+        self.format = format;
+        self
+    }
+
+    /// Set a prefix to prepend to every path in the archive, such as
+    /// `project-1.0/`.
+    ///
+    /// As with `git archive --prefix`, a directory prefix must end with a
+    /// slash.
+    pub fn prefix(&mut self, prefix: &str) -> &mut ArchiveOptions {
+        self.prefix = prefix.as_bytes().to_vec();
+        self
+    }
+
+    /// Set the commit the tree being archived belongs to.
+    ///
+    /// The commit is used to expand the placeholders of files with the
+    /// `export-subst` attribute, and its id is recorded in the archive like
+    /// `git archive` does, where `git get-tar-commit-id` can find it.
+    pub fn commit(&mut self, id: Oid) -> &mut ArchiveOptions {
+        self.commit = Some(id);
+        self
+    }
+
+    /// Set the modification time, in seconds since the epoch, of the
+    /// entries of the archive.
+    ///
+    /// Defaults to the commit time of the commit set with `commit`, if any,
+    /// or the epoch otherwise, so that archives are reproducible.
+    pub fn mtime(&mut self, mtime: i64) -> &mut ArchiveOptions {
+        self.mtime = Some(mtime);
+        self
+    }
+}
+
+pub fn archive(repo: &Repository, tree: &Tree, out: &mut Writer,
+               opts: &ArchiveOptions) -> Result<(), Error> {
+    let mut commit = match opts.commit {
+        Some(id) => Some(try!(repo.find_commit(id))),
+        None => None,
+    };
+    let mtime = opts.mtime.unwrap_or_else(|| {
+        commit.as_ref().map(|c| c.committer().when().seconds()).unwrap_or(0)
+    });
+    let mut archiver = Archiver {
+        out: out,
+        format: opts.format,
+        mtime: mtime,
+        offset: 0,
+        central: Vec::new(),
+        entries: 0,
+    };
+    let id = commit.as_ref().map(|c| c.id());
+    let attrs = try!(attr_repo(repo, tree));
+    try!(archiver.start(id).map_err(io_error));
+    if !opts.prefix.is_empty() && opts.prefix.ends_with(b"/") {
+        try!(archiver.add(opts.prefix.as_slice(), S_IFDIR, &[])
+                     .map_err(io_error));
+    }
+    try!(walk(repo, &attrs, tree, &[], opts, commit.as_mut(), &mut archiver));
+    archiver.finish(id).map_err(io_error)
+}
+
+// Open a separate handle on `repo` whose index holds the contents of `tree`,
+// so that attributes are read from the `.gitattributes` files of the tree
+// being archived, as `git archive` does, rather than from the working
+// directory or the real index.
+fn attr_repo(repo: &Repository, tree: &Tree) -> Result<Repository, Error> {
+    let ret = try!(Repository::open(&repo.path()));
+    let mut index = try!(Index::new());
+    try!(index.read_tree(tree));
+    unsafe {
+        try_call!(raw::git_repository_set_index(ret.raw(), index.raw()));
+    }
+    Ok(ret)
+}
+
+fn walk(repo: &Repository, attrs: &Repository, tree: &Tree, base: &[u8],
+        opts: &ArchiveOptions, mut commit: Option<&mut Commit>,
+        archiver: &mut Archiver) -> Result<(), Error> {
+    for entry in tree.iter() {
+        let mut path = base.to_vec();
+        path.push_all(entry.name_bytes());
+        if try!(attr(attrs, path.as_slice(), "export-ignore")) {
+            continue
+        }
+
+        let mut name = opts.prefix.clone();
+        name.push_all(path.as_slice());
+        let mode = entry.filemode_raw();
+        match mode & S_IFMT {
+            S_IFDIR => {
+                name.push(b'/');
+                try!(archiver.add(name.as_slice(), S_IFDIR, &[])
+                             .map_err(io_error));
+                let subtree = try!(repo.find_tree(entry.id()));
+                path.push(b'/');
+                try!(walk(repo, attrs, &subtree, path.as_slice(), opts,
+                          commit.as_mut().map(|c| &mut **c), archiver));
+            }
+            // submodules show up as empty directories
+            S_IFGITLINK => {
+                name.push(b'/');
+                try!(archiver.add(name.as_slice(), S_IFDIR, &[])
+                             .map_err(io_error));
+            }
+            _ => {
+                let blob = try!(repo.find_blob(entry.id()));
+                let subst = commit.is_some() && mode & S_IFMT != S_IFLNK &&
+                            try!(attr(attrs, path.as_slice(), "export-subst"));
+                let data = if subst {
+                    let commit = commit.as_mut().unwrap();
+                    substitute(blob.content(), &mut **commit)
+                } else {
+                    blob.content().to_vec()
+                };
+                try!(archiver.add(name.as_slice(), mode, data.as_slice())
+                             .map_err(io_error));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn attr(repo: &Repository, path: &[u8], name: &str) -> Result<bool, Error> {
+    let value = try!(repo.get_attr_bytes(&Path::new(path), name,
+                                         ATTR_CHECK_INDEX_ONLY));
+    let value = value.as_ref().map(|v| v.as_slice());
+    Ok(AttrValue::from_bytes(value) == AttrValue::True)
+}
+
+// Expand the `$Format:...$` placeholders of a file with the `export-subst`
+// attribute, using the pretty format placeholders of `git log`.
+fn substitute(data: &[u8], commit: &mut Commit) -> Vec<u8> {
+    let mut ret = Vec::new();
+    let mut rest = data;
+    loop {
+        let start = match find(rest, b"$Format:") {
+            Some(i) => i,
+            None => break,
+        };
+        let fmt = &rest[start + 8..];
+        let end = match fmt.iter().position(|b| *b == b'$') {
+            Some(i) => i,
+            None => break,
+        };
+        ret.push_all(&rest[..start]);
+        expand(&fmt[..end], commit, &mut ret);
+        rest = &fmt[end + 1..];
+    }
+    ret.push_all(rest);
+    ret
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if haystack.len() < needle.len() { return None }
+    range(0, haystack.len() - needle.len() + 1).find(|&i| {
+        &haystack[i..i + needle.len()] == needle
+    })
+}
+
+fn expand(fmt: &[u8], commit: &mut Commit, out: &mut Vec<u8>) {
+    let abbrev = |id: Oid| id.to_string()[..7].to_string();
+    let mut i = 0;
+    while i < fmt.len() {
+        if fmt[i] != b'%' || i + 1 == fmt.len() {
+            out.push(fmt[i]);
+            i += 1;
+            continue
+        }
+        let (value, len) = match (fmt[i + 1], fmt.get(i + 2).map(|b| *b)) {
+            (b'H', _) => (commit.id().to_string(), 2),
+            (b'h', _) => (abbrev(commit.id()), 2),
+            (b'T', _) => (commit.tree_id().to_string(), 2),
+            (b't', _) => (abbrev(commit.tree_id()), 2),
+            (b'P', _) => (commit.parent_ids().map(|id| id.to_string())
+                                .collect::<Vec<_>>().connect(" "), 2),
+            (b'p', _) => (commit.parent_ids().map(|id| abbrev(id))
+                                .collect::<Vec<_>>().connect(" "), 2),
+            (b'a', Some(b'n')) => (lossy(commit.author().name_bytes()), 3),
+            (b'a', Some(b'e')) => (lossy(commit.author().email_bytes()), 3),
+            (b'a', Some(b't')) => {
+                (commit.author().when().seconds().to_string(), 3)
+            }
+            (b'c', Some(b'n')) => (lossy(commit.committer().name_bytes()), 3),
+            (b'c', Some(b'e')) => (lossy(commit.committer().email_bytes()), 3),
+            (b'c', Some(b't')) => {
+                (commit.committer().when().seconds().to_string(), 3)
+            }
+            (b's', _) => (lossy(commit.summary_bytes().unwrap_or(&[])), 2),
+            (b'n', _) => ("\n".to_string(), 2),
+            (b'%', _) => ("%".to_string(), 2),
+            // unknown placeholders are left alone
+            _ => ("%".to_string(), 1),
+        };
+        out.push_all(value.as_bytes());
+        i += len;
+    }
+}
+
+fn lossy(data: &[u8]) -> String {
+    String::from_utf8_lossy(data).into_owned()
+}
+
+impl<'a> Archiver<'a> {
+    fn start(&mut self, commit: Option<Oid>) -> IoResult<()> {
+        match (self.format, commit) {
+            // record the commit in a global pax header, as git does
+            (ArchiveFormat::Tar, Some(id)) => {
+                let record = pax_record("comment", id.to_string().as_bytes());
+                self.tar_entry(b"pax_global_header", 0o666, b'g', &[],
+                               record.as_slice())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn add(&mut self, name: &[u8], mode: i32, data: &[u8]) -> IoResult<()> {
+        self.entries += 1;
+        match self.format {
+            ArchiveFormat::Tar => {
+                let (perm, kind, link, data) = match mode & S_IFMT {
+                    S_IFDIR => (0o775, b'5', &[][..], data),
+                    S_IFLNK => (0o777, b'2', data, &[][..]),
+                    _ if mode & 0o111 != 0 => (0o775, b'0', &[][..], data),
+                    _ => (0o664, b'0', &[][..], data),
+                };
+                self.tar_entry(name, perm, kind, link, data)
+            }
+            ArchiveFormat::Zip => self.zip_entry(name, mode, data),
+        }
+    }
+
+    fn finish(&mut self, commit: Option<Oid>) -> IoResult<()> {
+        match self.format {
+            ArchiveFormat::Tar => {
+                // two empty blocks, padded to the default blocking factor
+                // of 20 blocks
+                let end = self.offset + 1024;
+                let len = (end + 10239) / 10240 * 10240 - self.offset;
+                self.write(&vec![0; len as usize][..])
+            }
+            ArchiveFormat::Zip => {
+                let central = mem::replace(&mut self.central, Vec::new());
+                let start = self.offset;
+                try!(self.write(central.as_slice()));
+                let entries = self.entries as u64;
+                let size = central.len() as u64;
+
+                // archives which outgrow the fields of the end record get a
+                // zip64 end record and a locator pointing at it as well
+                let mut end = Vec::new();
+                if entries >= 0xffff || size >= 0xffffffff ||
+                   start >= 0xffffffff {
+                    let offset = self.offset;
+                    try!(end.write_le_u32(0x06064b50));
+                    try!(end.write_le_u64(44));
+                    try!(end.write_le_u16(3 << 8 | 45));
+                    try!(end.write_le_u16(45));
+                    try!(end.write_le_u32(0));
+                    try!(end.write_le_u32(0));
+                    try!(end.write_le_u64(entries));
+                    try!(end.write_le_u64(entries));
+                    try!(end.write_le_u64(size));
+                    try!(end.write_le_u64(start));
+                    try!(end.write_le_u32(0x07064b50));
+                    try!(end.write_le_u32(0));
+                    try!(end.write_le_u64(offset));
+                    try!(end.write_le_u32(1));
+                }
+
+                let comment = commit.map(|id| id.to_string())
+                                    .unwrap_or(String::new());
+                let entries = if entries >= 0xffff { 0xffff } else { entries };
+                try!(end.write_le_u32(0x06054b50));
+                try!(end.write_le_u16(0));
+                try!(end.write_le_u16(0));
+                try!(end.write_le_u16(entries as u16));
+                try!(end.write_le_u16(entries as u16));
+                try!(end.write_le_u32(clamp32(size)));
+                try!(end.write_le_u32(clamp32(start)));
+                try!(end.write_le_u16(comment.len() as u16));
+                try!(end.write_all(comment.as_bytes()));
+                self.write(end.as_slice())
+            }
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> IoResult<()> {
+        self.offset += data.len() as u64;
+        self.out.write_all(data)
+    }
+
+    fn tar_entry(&mut self, name: &[u8], perm: u32, kind: u8, link: &[u8],
+                 data: &[u8]) -> IoResult<()> {
+        try!(self.tar_headers(name, perm, kind, link, data.len() as u64));
+        try!(self.write(data));
+        self.pad(data.len())
+    }
+
+    fn tar_headers(&mut self, name: &[u8], perm: u32, kind: u8, link: &[u8],
+                   size: u64) -> IoResult<()> {
+        // paths which do not fit into the header go into a pax header, as
+        // do sizes of 8 GiB and more, which the 11 octal digits of the
+        // header cannot hold
+        let mut pax = Vec::new();
+        if name.len() > 100 {
+            pax.push_all(pax_record("path", name).as_slice());
+        }
+        if link.len() > 100 {
+            pax.push_all(pax_record("linkpath", link).as_slice());
+        }
+        let size = if size > TAR_MAX_SIZE {
+            pax.push_all(pax_record("size", size.to_string().as_bytes())
+                             .as_slice());
+            0
+        } else {
+            size
+        };
+        if !pax.is_empty() {
+            let header = tar_header(b"pax_header", 0o666, pax.len() as u64,
+                                    self.mtime, b'x', &[]);
+            try!(self.write(&header));
+            try!(self.write(pax.as_slice()));
+            try!(self.pad(pax.len()));
+        }
+        let header = tar_header(name, perm, size, self.mtime, kind, link);
+        self.write(&header)
+    }
+
+    fn pad(&mut self, len: usize) -> IoResult<()> {
+        let pad = (512 - len % 512) % 512;
+        self.write(&vec![0; pad][..])
+    }
+
+    fn zip_entry(&mut self, name: &[u8], mode: i32, data: &[u8])
+                 -> IoResult<()> {
+        let (mode, data) = match mode & S_IFMT {
+            S_IFDIR => (0o040775, &[][..]),
+            S_IFLNK => (0o120777, data),
+            _ if mode & 0o111 != 0 => (0o100775, data),
+            _ => (0o100664, data),
+        };
+        if name.len() > 0xffff {
+            return Err(IoError {
+                kind: old_io::InvalidInput,
+                desc: "path is too long for a zip archive",
+                detail: None,
+            })
+        }
+        // bit 11 marks names which are utf-8 rather than cp437
+        let flags = if name.iter().any(|b| *b >= 0x80) { 0x800 } else { 0 };
+        let (time, date) = dos_time(self.mtime);
+        let crc = crc32(data);
+        let offset = self.offset;
+        let size = data.len() as u64;
+
+        // sizes and offsets which do not fit into 32 bits are stored in a
+        // zip64 extra field instead, with the fields themselves saturated
+        let big = size >= 0xffffffff;
+        let mut extra = Vec::new();
+        if big {
+            try!(extra.write_le_u16(1));
+            try!(extra.write_le_u16(16));
+            try!(extra.write_le_u64(size));
+            try!(extra.write_le_u64(size));
+        }
+        let version = if big || offset >= 0xffffffff { 45 } else { 10 };
+
+        let mut local = Vec::new();
+        try!(local.write_le_u32(0x04034b50));
+        try!(local.write_le_u16(version));
+        try!(local.write_le_u16(flags));
+        try!(local.write_le_u16(0));
+        try!(local.write_le_u16(time));
+        try!(local.write_le_u16(date));
+        try!(local.write_le_u32(crc));
+        try!(local.write_le_u32(clamp32(size)));
+        try!(local.write_le_u32(clamp32(size)));
+        try!(local.write_le_u16(name.len() as u16));
+        try!(local.write_le_u16(extra.len() as u16));
+        try!(local.write_all(name));
+        try!(local.write_all(extra.as_slice()));
+        try!(self.write(local.as_slice()));
+        try!(self.write(data));
+
+        if offset >= 0xffffffff {
+            if big {
+                try!(extra.write_le_u64(offset));
+                extra[2] = 24;
+            } else {
+                try!(extra.write_le_u16(1));
+                try!(extra.write_le_u16(8));
+                try!(extra.write_le_u64(offset));
+            }
+        }
+        let central = &mut self.central;
+        try!(central.write_le_u32(0x02014b50));
+        // made by unix, so that the external attributes hold the mode
+        try!(central.write_le_u16(3 << 8 | version));
+        try!(central.write_le_u16(version));
+        try!(central.write_le_u16(flags));
+        try!(central.write_le_u16(0));
+        try!(central.write_le_u16(time));
+        try!(central.write_le_u16(date));
+        try!(central.write_le_u32(crc));
+        try!(central.write_le_u32(clamp32(size)));
+        try!(central.write_le_u32(clamp32(size)));
+        try!(central.write_le_u16(name.len() as u16));
+        try!(central.write_le_u16(extra.len() as u16));
+        try!(central.write_le_u16(0));
+        try!(central.write_le_u16(0));
+        try!(central.write_le_u16(0));
+        let dos = if mode & S_IFMT as u32 == S_IFDIR as u32 { 0x10 } else { 0 };
+        try!(central.write_le_u32(mode << 16 | dos));
+        try!(central.write_le_u32(clamp32(offset)));
+        try!(central.write_all(name));
+        central.write_all(extra.as_slice())
+    }
+}
+
+fn clamp32(n: u64) -> u32 {
+    if n >= 0xffffffff { 0xffffffff } else { n as u32 }
+}
+
+fn tar_header(name: &[u8], perm: u32, size: u64, mtime: i64, kind: u8,
+              link: &[u8]) -> [u8; 512] {
+    let mut header = [0u8; 512];
+    {
+        let mut field = |at: usize, len: usize, data: &[u8]| {
+            for (dst, src) in header[at..at + len].iter_mut().zip(data.iter()) {
+                *dst = *src;
+            }
+        };
+        field(0, 100, name);
+        field(100, 8, format!("{:07o}", perm).as_bytes());
+        field(108, 8, b"0000000");
+        field(116, 8, b"0000000");
+        field(124, 12, format!("{:011o}", size).as_bytes());
+        field(136, 12, format!("{:011o}", if mtime < 0 { 0 } else { mtime })
+                           .as_bytes());
+        field(148, 8, b"        ");
+        field(156, 1, &[kind]);
+        field(157, 100, link);
+        field(257, 8, b"ustar\x0000");
+        field(265, 32, b"root");
+        field(297, 32, b"root");
+    }
+    let sum = header.iter().fold(0u32, |sum, b| sum + *b as u32);
+    for (dst, src) in header[148..156].iter_mut()
+                                      .zip(format!("{:06o}\0 ", sum).bytes()) {
+        *dst = src;
+    }
+    header
+}
+
+// A pax extended header record, which is prefixed with its own length.
+fn pax_record(key: &str, value: &[u8]) -> Vec<u8> {
+    let len = key.len() + value.len() + 3;
+    let mut total = len + 1;
+    loop {
+        let next = len + total.to_string().len();
+        if next == total { break }
+        total = next;
+    }
+    let mut ret = format!("{} {}=", total, key).into_bytes();
+    ret.push_all(value);
+    ret.push(b'\n');
+    ret
+}
+
+// Convert a time to the DOS format used by zip, clamped to the range it can
+// represent.
+fn dos_time(mtime: i64) -> (u16, u16) {
+    let min = util::days_from_civil(1980, 1, 1) * 86400;
+    let mtime = if mtime < min { min } else { mtime };
+    let (year, month, day) = util::civil_from_days(mtime / 86400);
+    if year > 2107 { return (0xbf7d, 0xff9f) }
+    let secs = mtime % 86400;
+    let time = (secs / 3600) << 11 | (secs / 60 % 60) << 5 | (secs % 60) / 2;
+    let date = (year - 1980) << 9 | month << 5 | day;
+    (time as u16, date as u16)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in data.iter() {
+        crc ^= *b as u32;
+        for _ in range(0, 8) {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::{BufReader, File};
+    use std::str;
+    use {ArchiveFormat, ArchiveOptions, Oid, Repository};
+    use super::{Archiver, S_IFDIR};
+
+    fn setup(repo: &Repository) -> Oid {
+        let blob = |data: &[u8]| repo.blob(data).unwrap();
+        let mut bin = repo.treebuilder(None).unwrap();
+        bin.insert("run", blob(b"#!/bin/sh\n"), 0o100755).unwrap();
+        let bin = bin.write().unwrap();
+        let attrs = blob(b"ignored export-ignore\nversion export-subst\n");
+        let mut root = repo.treebuilder(None).unwrap();
+        root.insert(".gitattributes", attrs, 0o100644).unwrap();
+        root.insert("a", blob(b"a\n"), 0o100644).unwrap();
+        root.insert("bin", bin, 0o040000).unwrap();
+        root.insert("ignored", blob(b"x\n"), 0o100644).unwrap();
+        root.insert("l", blob(b"a"), 0o120000).unwrap();
+        root.insert("version", blob(b"$Format:%H %an$\n"), 0o100644).unwrap();
+        let tree = repo.find_tree(root.write().unwrap()).unwrap();
+
+        // attributes come from the tree, not the working directory
+        File::create(&repo.workdir().unwrap().join(".gitattributes"))
+            .write_str("a export-ignore\n").unwrap();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let parent = repo.find_commit(head).unwrap();
+        repo.commit(None, &sig, &sig, "archive", &tree, &[&parent]).unwrap()
+    }
+
+    fn field(data: &[u8]) -> String {
+        let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+        String::from_utf8(data[..end].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn tar() {
+        let (_td, repo) = ::test::repo_init();
+        let id = setup(&repo);
+        let tree = repo.find_commit(id).unwrap().tree().unwrap();
+        let mut opts = ArchiveOptions::new();
+        opts.prefix("p/").commit(id);
+        let mut out = Vec::new();
+        repo.archive(&tree, &mut out, Some(&mut opts)).unwrap();
+        assert_eq!(out.len() % 10240, 0);
+
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        while out[pos] != 0 {
+            let header = &out[pos..pos + 512];
+            let size = header[124..135].iter().fold(0, |n, b| {
+                n * 8 + (*b - b'0') as usize
+            });
+            let data = out[pos + 512..pos + 512 + size].to_vec();
+            entries.push((field(&header[..100]), field(&header[100..108]),
+                          header[156], field(&header[157..257]), data));
+            pos += 512 + (size + 511) / 512 * 512;
+        }
+        let names = entries.iter().map(|e| e.0.as_slice())
+                           .collect::<Vec<_>>();
+        assert_eq!(names, vec!["pax_global_header", "p/", "p/.gitattributes",
+                               "p/a", "p/bin/", "p/bin/run", "p/l",
+                               "p/version"]);
+        assert_eq!(entries[0].4, format!("52 comment={}\n", id).into_bytes());
+        assert_eq!((entries[4].1.as_slice(), entries[4].2), ("0000775", b'5'));
+        assert_eq!(entries[3].1.as_slice(), "0000664");
+        assert_eq!(entries[5].1.as_slice(), "0000775");
+        assert_eq!((entries[6].2, entries[6].3.as_slice()), (b'2', "a"));
+        assert_eq!(str::from_utf8(entries[7].4.as_slice()).unwrap(),
+                   format!("{} name\n", id));
+    }
+
+    #[test]
+    fn zip() {
+        let (_td, repo) = ::test::repo_init();
+        let id = setup(&repo);
+        let tree = repo.find_commit(id).unwrap().tree().unwrap();
+        let mut opts = ArchiveOptions::new();
+        opts.format(ArchiveFormat::Zip).commit(id);
+        let mut out = Vec::new();
+        repo.archive(&tree, &mut out, Some(&mut opts)).unwrap();
+        assert!(out.starts_with(b"PK\x03\x04"));
+
+        // the end of central directory record carries the commit id
+        let end = &out[out.len() - 62..];
+        assert!(end.starts_with(b"PK\x05\x06"));
+        assert_eq!(end[10] as usize | (end[11] as usize) << 8, 6);
+        assert_eq!(&end[22..], id.to_string().as_bytes());
+
+        let mut out = Vec::new();
+        repo.archive(&tree, &mut out, None).unwrap();
+        assert!(!out.is_empty() && out.len() % 10240 == 0);
+    }
+
+    #[test]
+    fn tar_size() {
+        let mut out = Vec::new();
+        {
+            let mut archiver = Archiver {
+                out: &mut out, format: ArchiveFormat::Tar, mtime: 0,
+                offset: 0, central: Vec::new(), entries: 0,
+            };
+            archiver.tar_headers(b"big", 0o664, b'0', &[], 1 << 33).unwrap();
+        }
+        assert_eq!(out.len(), 1536);
+        assert_eq!(out[156], b'x');
+        assert!(out[512..].starts_with(b"19 size=8589934592\n"));
+        assert_eq!(&out[1024 + 124..1024 + 135], b"00000000000");
+    }
+
+    #[test]
+    fn zip64() {
+        let mut out = Vec::new();
+        {
+            let mut archiver = Archiver {
+                out: &mut out, format: ArchiveFormat::Zip, mtime: 0,
+                offset: 0, central: Vec::new(), entries: 0,
+            };
+            for _ in range(0, 0x10000) {
+                archiver.add(b"d/", S_IFDIR, &[]).unwrap();
+            }
+            archiver.finish(None).unwrap();
+        }
+
+        // the end record saturates the entry count, and the zip64 end record
+        // found through the locator holds the real one
+        let end = &out[out.len() - 22..];
+        assert!(end.starts_with(b"PK\x05\x06"));
+        assert_eq!(&end[8..12], b"\xff\xff\xff\xff");
+        let locator = &out[out.len() - 42..out.len() - 22];
+        assert!(locator.starts_with(b"PK\x06\x07"));
+        let at = BufReader::new(&locator[8..16]).read_le_u64().unwrap();
+        let end64 = &out[at as usize..out.len() - 42];
+        assert_eq!(end64.len(), 56);
+        assert!(end64.starts_with(b"PK\x06\x06"));
+        let mut r = BufReader::new(&end64[24..40]);
+        assert_eq!(r.read_le_u64().unwrap(), 0x10000);
+        assert_eq!(r.read_le_u64().unwrap(), 0x10000);
+    }
+}
@@ -0,0 +1,61 @@
+use std::str;
+
+use raw;
+
+// The contents of the special values libgit2 uses for the true, false and
+// unset states, which are recognized by their address rather than contents,
+// so that copies of them made by `Repository::get_attr` can be told apart.
+const TRUE: &'static [u8] = b"[internal]__TRUE__";
+const FALSE: &'static [u8] = b"[internal]__FALSE__";
+const UNSET: &'static [u8] = b"[internal]__UNSET__";
+
+/// The value of an attribute, as returned by `Repository::get_attr` and
+/// `Repository::get_attr_bytes` and interpreted with `AttrValue::from_bytes`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AttrValue<'a> {
+    /// The attribute is set to true, e.g. `diff` in `*.c diff`.
+    True,
+    /// The attribute is unset (set to false), e.g. `-diff` in `*.c -diff`.
+    False,
+    /// The attribute is set to a string value, e.g. `eol=lf`.
+    String(&'a str),
+    /// The attribute is set to a value which is not valid utf-8.
+    Bytes(&'a [u8]),
+    /// The attribute is not specified for the path, or it was explicitly
+    /// returned to the unspecified state, e.g. `!diff`.
+    Unspecified,
+}
+
+impl<'a> AttrValue<'a> {
+    /// Interpret the raw value of an attribute as returned by
+    /// `Repository::get_attr_bytes`.
+    ///
+    /// libgit2 represents the true and false states with special values which
+    /// must be recognized here, rather than by comparing their contents.
+    pub fn from_bytes(value: Option<&'a [u8]>) -> AttrValue<'a> {
+        let value = match value {
+            Some(value) => value,
+            None => return AttrValue::Unspecified,
+        };
+        match unsafe { raw::git_attr_value(value.as_ptr() as *const _) } {
+            raw::GIT_ATTR_VALUE_UNSPECIFIED => AttrValue::Unspecified,
+            raw::GIT_ATTR_VALUE_TRUE => AttrValue::True,
+            raw::GIT_ATTR_VALUE_FALSE => AttrValue::False,
+            raw::GIT_ATTR_VALUE_STRING if value == TRUE => AttrValue::True,
+            raw::GIT_ATTR_VALUE_STRING if value == FALSE => AttrValue::False,
+            raw::GIT_ATTR_VALUE_STRING if value == UNSET => {
+                AttrValue::Unspecified
+            }
+            raw::GIT_ATTR_VALUE_STRING => match str::from_utf8(value) {
+                Ok(s) => AttrValue::String(s),
+                Err(..) => AttrValue::Bytes(value),
+            },
+        }
+    }
+
+    /// Interpret the value of an attribute as returned by
+    /// `Repository::get_attr`.
+    pub fn from_string(value: Option<&'a str>) -> AttrValue<'a> {
+        AttrValue::from_bytes(value.map(|s| s.as_bytes()))
+    }
+}
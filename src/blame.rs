@@ -0,0 +1,363 @@
+use std::ffi;
+use std::iter::Range;
+use std::marker;
+use std::mem;
+use libc::{c_char, size_t};
+
+use {raw, signature, Error, Oid, Signature};
+use util::Binding;
+
+/// The results of blaming a file, describing which commit last changed each
+/// of its lines.
+pub struct Blame<'repo> {
+    raw: *mut raw::git_blame,
+    marker: marker::ContravariantLifetime<'repo>,
+}
+
+/// A range of consecutive lines of a file which were all last changed by the
+/// same commit.
+pub struct BlameHunk<'blame> {
+    raw: *mut raw::git_blame_hunk,
+    marker: marker::ContravariantLifetime<'blame>,
+}
+
+/// An iterator over the hunks of a blame.
+pub struct BlameIter<'blame, 'repo: 'blame> {
+    range: Range<usize>,
+    blame: &'blame Blame<'repo>,
+}
+
+/// Options which can be used to configure how a file is blamed.
+pub struct BlameOptions {
+    raw: raw::git_blame_options,
+}
+
+impl<'repo> Blame<'repo> {
+    /// Get the number of hunks in this blame.
+    pub fn len(&self) -> usize {
+        unsafe { raw::git_blame_get_hunk_count(self.raw) as usize }
+    }
+
+    /// Return whether there are no hunks in this blame.
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Get the hunk at the given index, if it is in bounds.
+    pub fn get_index(&self, index: usize) -> Option<BlameHunk> {
+        unsafe {
+            let ptr = raw::git_blame_get_hunk_byindex(self.raw, index as u32);
+            Binding::from_raw_opt(ptr as *mut _)
+        }
+    }
+
+    /// Get the hunk which contains the given line, counting from 1.
+    pub fn get_line(&self, lineno: usize) -> Option<BlameHunk> {
+        unsafe {
+            let ptr = raw::git_blame_get_hunk_byline(self.raw,
+                                                     lineno as size_t);
+            Binding::from_raw_opt(ptr as *mut _)
+        }
+    }
+
+    /// Iterate over the hunks of this blame.
+    pub fn iter<'a>(&'a self) -> BlameIter<'a, 'repo> {
+        BlameIter { range: range(0, self.len()), blame: self }
+    }
+
+    /// Blame an in-memory version of the file this blame was computed for,
+    /// such as the contents of an editor buffer with unsaved changes.
+    ///
+    /// Lines which differ from the committed file are attributed to a zero
+    /// commit id, while all other lines keep the attribution of this blame.
+    pub fn blame_buffer(&self, buffer: &[u8]) -> Result<Blame<'repo>, Error> {
+        let mut raw = 0 as *mut raw::git_blame;
+        unsafe {
+            try_call!(raw::git_blame_buffer(&mut raw, self.raw,
+                                            buffer.as_ptr() as *const c_char,
+                                            buffer.len() as size_t));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+}
+
+impl<'repo> Binding for Blame<'repo> {
+    type Raw = *mut raw::git_blame;
+    unsafe fn from_raw(raw: *mut raw::git_blame) -> Blame<'repo> {
+        Blame {
+            raw: raw,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+    fn raw(&self) -> *mut raw::git_blame { self.raw }
+}
+
+#[unsafe_destructor]
+impl<'repo> Drop for Blame<'repo> {
+    fn drop(&mut self) {
+        unsafe { raw::git_blame_free(self.raw) }
+    }
+}
+
+impl<'blame> BlameHunk<'blame> {
+    /// Get the number of lines in this hunk.
+    pub fn lines_in_hunk(&self) -> usize {
+        unsafe { (*self.raw).lines_in_hunk as usize }
+    }
+
+    /// Get the id of the commit where these lines were last changed.
+    pub fn final_commit_id(&self) -> Oid {
+        unsafe { Binding::from_raw(&(*self.raw).final_commit_id as *const _) }
+    }
+
+    /// Get the author of the commit where these lines were last changed.
+    ///
+    /// Returns `None` for lines of a `blame_buffer` which are not committed.
+    pub fn final_signature(&self) -> Option<Signature> {
+        unsafe {
+            let sig = (*self.raw).final_signature;
+            if sig.is_null() { return None }
+            Some(signature::from_raw_const(self, sig))
+        }
+    }
+
+    /// Get the line number where this hunk begins in the final version of
+    /// the file, counting from 1.
+    pub fn final_start_line(&self) -> usize {
+        unsafe { (*self.raw).final_start_line_number as usize }
+    }
+
+    /// Get the id of the commit in which these lines were found, which
+    /// differs from `final_commit_id` when they were copied or moved there
+    /// from elsewhere.
+    pub fn orig_commit_id(&self) -> Oid {
+        unsafe { Binding::from_raw(&(*self.raw).orig_commit_id as *const _) }
+    }
+
+    /// Get the author of the commit in which these lines were found.
+    ///
+    /// Returns `None` for lines of a `blame_buffer` which are not committed.
+    pub fn orig_signature(&self) -> Option<Signature> {
+        unsafe {
+            let sig = (*self.raw).orig_signature;
+            if sig.is_null() { return None }
+            Some(signature::from_raw_const(self, sig))
+        }
+    }
+
+    /// Get the line number where this hunk begins in the file in which it
+    /// was found, counting from 1.
+    pub fn orig_start_line(&self) -> usize {
+        unsafe { (*self.raw).orig_start_line_number as usize }
+    }
+
+    /// Get the path of the file in which these lines were found, as of the
+    /// commit given by `orig_commit_id`.
+    ///
+    /// This differs from the path which was blamed when the file has since
+    /// been renamed, or when the lines were copied from another file.
+    pub fn orig_path(&self) -> Option<Path> {
+        unsafe {
+            let ptr = (*self.raw).orig_path;
+            if ptr.is_null() {
+                None
+            } else {
+                Some(Path::new(ffi::c_str_to_bytes(&ptr)))
+            }
+        }
+    }
+
+    /// Check whether this hunk was tracked to a boundary commit, the root
+    /// commit or the `oldest_commit` of the options, rather than to the
+    /// commit which introduced the lines.
+    pub fn is_boundary(&self) -> bool {
+        unsafe { (*self.raw).boundary == 1 }
+    }
+}
+
+impl<'blame> Binding for BlameHunk<'blame> {
+    type Raw = *mut raw::git_blame_hunk;
+    unsafe fn from_raw(raw: *mut raw::git_blame_hunk) -> BlameHunk<'blame> {
+        BlameHunk {
+            raw: raw,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+    fn raw(&self) -> *mut raw::git_blame_hunk { self.raw }
+}
+
+impl<'blame, 'repo> Iterator for BlameIter<'blame, 'repo> {
+    type Item = BlameHunk<'blame>;
+    fn next(&mut self) -> Option<BlameHunk<'blame>> {
+        self.range.next().and_then(|i| self.blame.get_index(i))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) { self.range.size_hint() }
+}
+impl<'blame, 'repo> DoubleEndedIterator for BlameIter<'blame, 'repo> {
+    fn next_back(&mut self) -> Option<BlameHunk<'blame>> {
+        self.range.next_back().and_then(|i| self.blame.get_index(i))
+    }
+}
+impl<'blame, 'repo> ExactSizeIterator for BlameIter<'blame, 'repo> {}
+
+impl BlameOptions {
+    /// Creates a new default set of blame options.
+    pub fn new() -> BlameOptions {
+        let mut opts = BlameOptions { raw: unsafe { mem::zeroed() } };
+        assert_eq!(unsafe {
+            raw::git_blame_init_options(&mut opts.raw,
+                                        raw::GIT_BLAME_OPTIONS_VERSION)
+        }, 0);
+        opts
+    }
+
+    fn flag(&mut self, opt: u32, val: bool) -> &mut BlameOptions {
+        if val {
+            self.raw.flags |= opt;
+        } else {
+            self.raw.flags &= !opt;
+        }
+        self
+    }
+
+    /// Track lines which have moved within a file.
+    pub fn track_copies_same_file(&mut self, opt: bool) -> &mut BlameOptions {
+        self.flag(raw::GIT_BLAME_TRACK_COPIES_SAME_FILE, opt)
+    }
+
+    /// Track lines which have moved across files in the same commit.
+    pub fn track_copies_same_commit_moves(&mut self, opt: bool)
+                                          -> &mut BlameOptions {
+        self.flag(raw::GIT_BLAME_TRACK_COPIES_SAME_COMMIT_MOVES, opt)
+    }
+
+    /// Track lines which have been copied from another file which exists in
+    /// the same commit.
+    pub fn track_copies_same_commit_copies(&mut self, opt: bool)
+                                           -> &mut BlameOptions {
+        self.flag(raw::GIT_BLAME_TRACK_COPIES_SAME_COMMIT_COPIES, opt)
+    }
+
+    /// Track lines which have been copied from another file which exists in
+    /// any commit.
+    pub fn track_copies_any_commit_copies(&mut self, opt: bool)
+                                          -> &mut BlameOptions {
+        self.flag(raw::GIT_BLAME_TRACK_COPIES_ANY_COMMIT_COPIES, opt)
+    }
+
+    /// Restrict the search of commits to those reachable following only the
+    /// first parents.
+    pub fn first_parent(&mut self, opt: bool) -> &mut BlameOptions {
+        self.flag(raw::GIT_BLAME_FIRST_PARENT, opt)
+    }
+
+    /// Set the id of the newest commit to consider, which defaults to HEAD.
+    pub fn newest_commit(&mut self, id: Oid) -> &mut BlameOptions {
+        unsafe { self.raw.newest_commit = *id.raw(); }
+        self
+    }
+
+    /// Set the id of the oldest commit to consider. The default is the first
+    /// commit encountered with no parents.
+    pub fn oldest_commit(&mut self, id: Oid) -> &mut BlameOptions {
+        unsafe { self.raw.oldest_commit = *id.raw(); }
+        self
+    }
+
+    /// Set the first line in the file to blame, counting from 1.
+    pub fn min_line(&mut self, lineno: usize) -> &mut BlameOptions {
+        self.raw.min_line = lineno as size_t;
+        self
+    }
+
+    /// Set the last line in the file to blame, counting from 1.
+    pub fn max_line(&mut self, lineno: usize) -> &mut BlameOptions {
+        self.raw.max_line = lineno as size_t;
+        self
+    }
+}
+
+/// Acquire a pointer to the underlying raw options.
+pub fn raw_options(opts: &mut BlameOptions) -> *mut raw::git_blame_options {
+    &mut opts.raw as *mut _
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::{fs, File};
+    use BlameOptions;
+
+    #[test]
+    fn smoke() {
+        let (td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let commit = |path: &str, contents: &str| {
+            File::create(&td.path().join(path)).write_str(contents).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(&Path::new(path)).unwrap();
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let head = repo.head().unwrap().target().unwrap();
+            let parent = repo.find_commit(head).unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, path, &tree,
+                        &[&parent]).unwrap()
+        };
+        let first = commit("foo", "a\nb\nc\n");
+        let second = commit("foo", "a\nb\nc\nd\n");
+
+        let blame = repo.blame_file(&Path::new("foo"), None).unwrap();
+        assert_eq!(blame.len(), 2);
+        let hunks = blame.iter().collect::<Vec<_>>();
+        assert_eq!(hunks[0].final_commit_id(), first);
+        assert_eq!(hunks[0].lines_in_hunk(), 3);
+        assert_eq!(hunks[0].final_start_line(), 1);
+        assert_eq!(hunks[0].orig_path(), Some(Path::new("foo")));
+        assert_eq!(hunks[0].final_signature().unwrap().name(), Some("name"));
+        assert_eq!(hunks[1].final_commit_id(), second);
+        assert_eq!(hunks[1].final_start_line(), 4);
+        assert_eq!(blame.get_line(4).unwrap().orig_commit_id(), second);
+        assert!(blame.get_line(5).is_none());
+
+        let buffer = blame.blame_buffer(b"a\nb\nc\nd\ne\n").unwrap();
+        assert_eq!(buffer.len(), 3);
+        let uncommitted = buffer.get_line(5).unwrap();
+        assert!(uncommitted.final_commit_id().is_zero());
+        assert!(uncommitted.final_signature().is_none());
+        assert!(uncommitted.orig_signature().is_none());
+
+        let mut opts = BlameOptions::new();
+        opts.min_line(4).max_line(4);
+        let blame = repo.blame_file(&Path::new("foo"), Some(&mut opts))
+                        .unwrap();
+        assert_eq!(blame.len(), 1);
+        assert_eq!(blame.get_index(0).unwrap().final_commit_id(), second);
+    }
+
+    #[test]
+    fn renames() {
+        let (td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let parent = repo.find_commit(head).unwrap();
+
+        File::create(&td.path().join("old")).write_str("a\nb\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("old")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let first = repo.commit(Some("HEAD"), &sig, &sig, "add", &tree,
+                                &[&parent]).unwrap();
+
+        fs::rename(&td.path().join("old"), &td.path().join("new")).unwrap();
+        index.remove_path(&Path::new("old")).unwrap();
+        index.add_path(&Path::new("new")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = repo.find_commit(first).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "rename", &tree,
+                    &[&parent]).unwrap();
+
+        let blame = repo.blame_file(&Path::new("new"), None).unwrap();
+        assert_eq!(blame.len(), 1);
+        let hunk = blame.get_index(0).unwrap();
+        assert_eq!(hunk.final_commit_id(), first);
+        assert_eq!(hunk.orig_path(), Some(Path::new("old")));
+        assert_eq!(hunk.orig_signature().unwrap().email(), Some("email"));
+    }
+}
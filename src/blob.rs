@@ -1,7 +1,7 @@
 use std::marker;
 use std::slice;
 
-use {raw, Oid};
+use {raw, LfsPointer, Oid};
 use util::Binding;
 
 /// A structure to represent a git [blob][1]
@@ -24,6 +24,9 @@ impl<'repo> Blob<'repo> {
     }
 
     /// Get the content of this blob.
+    ///
+    /// The slice borrows the object libgit2 keeps in its cache, so no copy is
+    /// made; use `to_vec` to keep the content beyond the lifetime of the blob.
     pub fn content(&self) -> &[u8] {
         unsafe {
             let data = raw::git_blob_rawcontent(&*self.raw) as *const u8;
@@ -31,6 +34,11 @@ impl<'repo> Blob<'repo> {
             slice::from_raw_parts(data, len)
         }
     }
+
+    /// Parse this blob as a Git LFS pointer, as `LfsPointer::parse` does.
+    pub fn lfs_pointer(&self) -> Option<LfsPointer> {
+        LfsPointer::parse(self.content())
+    }
 }
 
 impl<'repo> Binding for Blob<'repo> {
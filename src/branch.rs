@@ -1,9 +1,11 @@
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::marker;
 use std::str;
 use libc;
 
-use {raw, Error, Reference, Signature, BranchType};
+use {raw, Buf, Error, ErrorCode, Oid, Reference, Repository};
+use {BranchType, CommitGraphUsage};
 use util::Binding;
 
 /// A structure to represent a git [branch][1]
@@ -22,6 +24,17 @@ pub struct Branches<'repo> {
     marker: marker::ContravariantLifetime<'repo>,
 }
 
+/// The state of a local branch relative to its upstream, as returned by
+/// `Repository::branch_summaries`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BranchSummary {
+    name: String,
+    target: Oid,
+    upstream: Option<String>,
+    ahead_behind: Option<(usize, usize)>,
+    is_head: bool,
+}
+
 impl<'repo> Branch<'repo> {
     /// Creates a new branch from a reference
     pub fn wrap(reference: Reference) -> Branch { Branch { inner: reference } }
@@ -32,6 +45,19 @@ impl<'repo> Branch<'repo> {
     /// Take ownership of the underlying reference.
     pub fn into_reference(self) -> Reference<'repo> { self.inner }
 
+    /// Determine whether a branch name is valid, meaning that
+    /// `refs/heads/<name>` is a valid reference name and that it does not
+    /// start with a `-` or is `HEAD`.
+    pub fn name_is_valid(name: &str) -> Result<bool, Error> {
+        ::init();
+        let name = CString::from_slice(name.as_bytes());
+        let mut valid: libc::c_int = 0;
+        unsafe {
+            try_call!(raw::git_branch_name_is_valid(&mut valid, name));
+        }
+        Ok(valid == 1)
+    }
+
     /// Delete an existing branch reference.
     pub fn delete(&mut self) -> Result<(), Error> {
         unsafe { try_call!(raw::git_branch_delete(self.get().raw())); }
@@ -44,17 +70,13 @@ impl<'repo> Branch<'repo> {
     }
 
     /// Move/rename an existing local branch reference.
-    pub fn rename(&mut self, new_branch_name: &str, force: bool,
-                  signature: Option<&Signature>,
-                  log_message: &str) -> Result<Branch<'repo>, Error> {
+    pub fn rename(&mut self, new_branch_name: &str, force: bool)
+                  -> Result<Branch<'repo>, Error> {
         let mut ret = 0 as *mut raw::git_reference;
         let new_branch_name = CString::from_slice(new_branch_name.as_bytes());
-        let log_message = CString::from_slice(log_message.as_bytes());
         unsafe {
             try_call!(raw::git_branch_move(&mut ret, self.get().raw(),
-                                           new_branch_name, force,
-                                           signature.map(|s| s.raw()),
-                                           log_message));
+                                           new_branch_name, force));
             Ok(Branch::wrap(Binding::from_raw(ret)))
         }
     }
@@ -85,6 +107,22 @@ impl<'repo> Branch<'repo> {
         }
     }
 
+    /// Return the full name of the remote tracking branch of this local
+    /// branch, as configured by `branch.<name>.remote` and
+    /// `branch.<name>.merge`, such as `refs/remotes/origin/master`.
+    ///
+    /// Unlike `upstream`, the tracking branch does not need to exist.
+    pub fn upstream_name(&self) -> Result<Buf, Error> {
+        let buf = Buf::new();
+        unsafe {
+            let raw = self.get().raw();
+            try_call!(raw::git_branch_upstream_name(
+                buf.raw(), raw::git_reference_owner(&*raw),
+                raw::git_reference_name(&*raw)));
+        }
+        Ok(buf)
+    }
+
     /// Set the upstream configuration for a given local branch.
     ///
     /// If `None` is specified, then the upstream branch is unset. The name
@@ -135,6 +173,77 @@ impl<'repo> Iterator for Branches<'repo> {
     }
 }
 
+impl BranchSummary {
+    /// Get the name of the branch, such as `master`.
+    pub fn name(&self) -> &str { self.name.as_slice() }
+
+    /// Get the id of the commit the branch points to.
+    pub fn target(&self) -> Oid { self.target }
+
+    /// Get the full name of the remote tracking branch configured as the
+    /// upstream of the branch, such as `refs/remotes/origin/master`.
+    pub fn upstream(&self) -> Option<&str> {
+        self.upstream.as_ref().map(|s| s.as_slice())
+    }
+
+    /// Get the number of commits the branch is ahead and behind of its
+    /// upstream, or `None` if it has no upstream or the upstream does not
+    /// exist.
+    pub fn ahead_behind(&self) -> Option<(usize, usize)> { self.ahead_behind }
+
+    /// Determine whether `HEAD` points at the branch.
+    pub fn is_head(&self) -> bool { self.is_head }
+}
+
+pub fn summaries(repo: &Repository) -> Result<Vec<BranchSummary>, Error> {
+    let mut counts = HashMap::new();
+    let mut ret = Vec::new();
+    for (branch, _) in try!(repo.branches(Some(BranchType::Local))) {
+        let target = match try!(branch.get().resolve()).target() {
+            Some(id) => id,
+            None => continue,
+        };
+        let upstream = match branch.upstream_name() {
+            Ok(buf) => buf.as_str().map(|s| s.to_string()),
+            Err(ref e) if e.code() == ErrorCode::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let upstream_id = match upstream {
+            Some(ref name) => match repo.refname_to_id(name.as_slice()) {
+                Ok(id) => Some(id),
+                Err(ref e) if e.code() == ErrorCode::NotFound => None,
+                Err(e) => return Err(e),
+            },
+            None => None,
+        };
+        let ahead_behind = match upstream_id {
+            Some(id) if id == target => Some((0, 0)),
+            Some(id) => {
+                // many branches typically share an upstream and sit at the
+                // same commit, so each pair is only counted once
+                let key = (target, id);
+                if !counts.contains_key(&key) {
+                    let n = try!(repo.graph_ahead_behind_with(
+                        target, id, CommitGraphUsage::Auto));
+                    counts.insert(key, n);
+                }
+                counts.get(&key).map(|n| *n)
+            }
+            None => None,
+        };
+        ret.push(BranchSummary {
+            name: String::from_utf8_lossy(try!(branch.name_bytes()))
+                         .into_owned(),
+            target: target,
+            upstream: upstream,
+            ahead_behind: ahead_behind,
+            is_head: branch.is_head(),
+        });
+    }
+    ret.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(ret)
+}
+
 #[unsafe_destructor]
 impl<'repo> Drop for Branches<'repo> {
     fn drop(&mut self) {
@@ -144,7 +253,18 @@ impl<'repo> Drop for Branches<'repo> {
 
 #[cfg(test)]
 mod tests {
-    use BranchType;
+    use {Branch, BranchType};
+
+    #[test]
+    fn name_is_valid() {
+        assert!(Branch::name_is_valid("foo").unwrap());
+        assert!(Branch::name_is_valid("foo/bar").unwrap());
+        assert!(!Branch::name_is_valid("").unwrap());
+        assert!(!Branch::name_is_valid("-foo").unwrap());
+        assert!(!Branch::name_is_valid("HEAD").unwrap());
+        assert!(!Branch::name_is_valid("foo..bar").unwrap());
+        assert!(!Branch::name_is_valid("foo.lock").unwrap());
+    }
 
     #[test]
     fn smoke() {
@@ -153,20 +273,89 @@ mod tests {
         let target = head.target().unwrap();
         let commit = repo.find_commit(target).unwrap();
 
-        let sig = repo.signature().unwrap();
-        let mut b1 = repo.branch("foo", &commit, false, None, Some("bar")).unwrap();
+        let mut b1 = repo.branch("foo", &commit, false).unwrap();
         assert!(!b1.is_head());
-        repo.branch("foo2", &commit, false, None, None).unwrap();
+        repo.branch("foo2", &commit, false).unwrap();
 
         assert_eq!(repo.branches(None).unwrap().count(), 3);
         repo.find_branch("foo", BranchType::Local).unwrap();
-        let mut b1 = b1.rename("bar", false, Some(&sig), "bar2").unwrap();
+        let mut b1 = b1.rename("bar", false).unwrap();
         assert_eq!(b1.name().unwrap(), Some("bar"));
         assert!(b1.upstream().is_err());
+        assert!(b1.upstream_name().is_err());
         b1.set_upstream(Some("master")).unwrap();
         b1.upstream().unwrap();
+        assert_eq!(&*b1.upstream_name().unwrap(), b"refs/heads/master");
         b1.set_upstream(None).unwrap();
 
         b1.delete().unwrap();
     }
+
+    #[test]
+    fn tracking() {
+        let (_td, repo) = ::test::repo_init();
+        repo.remote("origin", "https://example.com/foo").unwrap();
+        let name = repo.branch_remote_name("refs/remotes/origin/master");
+        assert_eq!(name.unwrap().as_str(), Some("origin"));
+        assert!(repo.branch_remote_name("refs/remotes/nope/master").is_err());
+
+        let mut cfg = repo.config().unwrap();
+        cfg.set_str("branch.master.remote", "origin").unwrap();
+        cfg.set_str("branch.master.merge", "refs/heads/trunk").unwrap();
+        let remote = repo.branch_upstream_remote("refs/heads/master");
+        assert_eq!(remote.unwrap().as_str(), Some("origin"));
+        let merge = repo.branch_upstream_merge("refs/heads/master");
+        assert_eq!(merge.unwrap().as_str(), Some("refs/heads/trunk"));
+        let upstream = repo.branch_upstream_name("refs/heads/master");
+        assert_eq!(upstream.unwrap().as_str(),
+                   Some("refs/remotes/origin/trunk"));
+        assert!(repo.branch_upstream_name("refs/heads/nope").is_err());
+    }
+
+    #[test]
+    fn summaries() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let initial = repo.find_commit(head).unwrap();
+        let tree = initial.tree().unwrap();
+        let sig = repo.signature().unwrap();
+        repo.remote("origin", "https://example.com/repo").unwrap();
+        repo.reference("refs/remotes/origin/master", head, false, "fetch")
+            .unwrap();
+
+        let mut master = repo.find_branch("master", BranchType::Local)
+                             .unwrap();
+        master.set_upstream(Some("origin/master")).unwrap();
+        let mut topic = repo.branch("topic", &initial, false).unwrap();
+        topic.set_upstream(Some("origin/master")).unwrap();
+        repo.branch("other", &initial, false).unwrap();
+        let second = repo.commit(Some("HEAD"), &sig, &sig, "second", &tree,
+                                 &[&initial]).unwrap();
+
+        let summaries = repo.branch_summaries().unwrap();
+        let names = summaries.iter().map(|s| s.name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["master", "other", "topic"]);
+
+        let master = &summaries[0];
+        assert_eq!(master.target(), second);
+        assert_eq!(master.upstream(), Some("refs/remotes/origin/master"));
+        assert_eq!(master.ahead_behind(), Some((1, 0)));
+        assert!(master.is_head());
+
+        let other = &summaries[1];
+        assert_eq!(other.target(), head);
+        assert_eq!(other.upstream(), None);
+        assert_eq!(other.ahead_behind(), None);
+        assert!(!other.is_head());
+
+        assert_eq!(summaries[2].ahead_behind(), Some((0, 0)));
+
+        // an upstream which has gone away has nothing to count against
+        repo.find_reference("refs/remotes/origin/master").unwrap()
+            .delete().unwrap();
+        let summaries = repo.branch_summaries().unwrap();
+        assert_eq!(summaries[0].upstream(),
+                   Some("refs/remotes/origin/master"));
+        assert_eq!(summaries[0].ahead_behind(), None);
+    }
 }
@@ -1,3 +1,5 @@
+use std::old_io::{self, IoError, IoResult};
+use std::ptr;
 use std::slice;
 use std::str;
 use std::ops::{Deref, DerefMut};
@@ -9,11 +11,17 @@ use util::Binding;
 /// A structure to wrap an intermediate buffer used by libgit2.
 ///
 /// A buffer can be thought of a `Vec<u8>`, but the `Vec` type is not used to
-/// avoid copying data back and forth.
+/// avoid copying data back and forth. Its contents are available through
+/// `Deref<Target=[u8]>`, and more data can be appended through `Writer`.
 pub struct Buf {
     raw: raw::git_buf,
 }
 
+// A `Buf` exclusively owns the memory it points to, so it can be treated like
+// a `Vec<u8>` with respect to threads.
+unsafe impl Send for Buf {}
+unsafe impl Sync for Buf {}
+
 impl Buf {
     /// Creates a new empty buffer.
     pub fn new() -> Buf {
@@ -27,10 +35,71 @@ impl Buf {
         }
     }
 
+    /// Creates a new empty buffer which can hold at least `capacity` bytes
+    /// without reallocating.
+    pub fn with_capacity(capacity: usize) -> Buf {
+        let mut buf = Buf::new();
+        buf.reserve(capacity);
+        buf
+    }
+
     /// Attempt to view this buffer as a string slice.
     ///
     /// Returns `None` if the buffer is not valid utf-8.
     pub fn as_str(&self) -> Option<&str> { str::from_utf8(&**self).ok() }
+
+    /// Copy the contents of this buffer into a new vector.
+    pub fn to_vec(&self) -> Vec<u8> { (&**self).to_vec() }
+
+    /// Consume this buffer, returning its contents as a vector.
+    pub fn into_bytes(self) -> Vec<u8> { self.to_vec() }
+
+    /// Remove all data from this buffer, keeping its allocation.
+    pub fn clear(&mut self) {
+        self.raw.size = 0;
+        if self.raw.asize > 0 {
+            unsafe { *self.raw.ptr = 0 }
+        }
+    }
+
+    /// Ensure that at least `additional` more bytes can be appended to this
+    /// buffer without reallocating.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory could not be allocated.
+    pub fn reserve(&mut self, additional: usize) {
+        // leave room for the nul terminator libgit2 maintains
+        let target = self.raw.size as usize + additional + 1;
+        unsafe {
+            assert_eq!(raw::git_buf_grow(&mut self.raw,
+                                         target as libc::size_t), 0);
+        }
+    }
+}
+
+impl Writer for Buf {
+    fn write_all(&mut self, data: &[u8]) -> IoResult<()> {
+        let size = self.raw.size as usize;
+        let rc = unsafe {
+            raw::git_buf_grow(&mut self.raw,
+                              (size + data.len() + 1) as libc::size_t)
+        };
+        if rc < 0 {
+            return Err(IoError {
+                kind: old_io::OtherIoError,
+                desc: "failed to grow buffer",
+                detail: None,
+            })
+        }
+        unsafe {
+            let dst = (self.raw.ptr as *mut u8).offset(size as isize);
+            ptr::copy_nonoverlapping_memory(dst, data.as_ptr(), data.len());
+            *dst.offset(data.len() as isize) = 0;
+        }
+        self.raw.size = (size + data.len()) as libc::size_t;
+        Ok(())
+    }
 }
 
 impl Deref for Buf {
@@ -65,3 +134,30 @@ impl Drop for Buf {
         unsafe { raw::git_buf_free(&mut self.raw) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Buf;
+
+    #[test]
+    fn smoke() {
+        let mut buf = Buf::new();
+        assert_eq!(&*buf, b"");
+        assert_eq!(buf.as_str(), Some(""));
+        buf.write_str("hello").unwrap();
+        buf.write_all(b", world").unwrap();
+        assert_eq!(buf.as_str(), Some("hello, world"));
+        assert_eq!(buf.to_vec(), b"hello, world".to_vec());
+
+        buf.clear();
+        assert_eq!(&*buf, b"");
+        buf.write_all(&[0xff]).unwrap();
+        assert_eq!(buf.as_str(), None);
+        assert_eq!(buf.into_bytes(), vec![0xff]);
+
+        let mut buf = Buf::with_capacity(100);
+        assert_eq!(buf.len(), 0);
+        buf.write_str("foo").unwrap();
+        assert_eq!(buf.into_bytes(), b"foo".to_vec());
+    }
+}
@@ -5,7 +5,8 @@ use std::old_io;
 use std::mem;
 use libc::{c_char, size_t, c_void, c_uint, c_int};
 
-use {raw, Signature, Error, Repository, RemoteCallbacks, panic, IntoCString};
+use {raw, Error, Repository, RemoteCallbacks, panic, IntoCString};
+use {CheckoutNotificationType, DiffFile, EolOptions};
 use util::Binding;
 
 /// A builder struct which is used to build configuration for cloning a new git
@@ -13,7 +14,6 @@ use util::Binding;
 pub struct RepoBuilder<'cb> {
     bare: bool,
     branch: Option<CString>,
-    sig: Option<Signature<'static>>,
     local: bool,
     hardlinks: bool,
     checkout: Option<CheckoutBuilder<'cb>>,
@@ -31,15 +31,31 @@ pub struct CheckoutBuilder<'cb> {
     file_perm: Option<old_io::FilePermission>,
     dir_perm: Option<old_io::FilePermission>,
     disable_filters: bool,
+    eol: Option<EolOptions>,
     checkout_opts: u32,
     progress: Option<Box<Progress<'cb>>>,
+    notify: Option<Box<Notify<'cb>>>,
+    notify_flags: CheckoutNotificationType,
 }
 
 /// Checkout progress notification callback.
 ///
-/// The first argument is the path for the notification, the next is the numver
-/// of completed steps so far, and the final is the total number of steps.
-pub type Progress<'a> = FnMut(&[u8], usize, usize) + 'a;
+/// The first argument is the path which was just checked out, the next is the
+/// number of completed steps so far, and the final is the total number of
+/// steps. The path is `None` for the report made before any files have been
+/// checked out.
+pub type Progress<'a> = FnMut(Option<&Path>, usize, usize) + 'a;
+
+/// Checkout notifications callback.
+///
+/// The first argument is the notification type, the next is the path for
+/// the notification, followed by the baseline diff, target diff, and workdir
+/// diff.
+///
+/// Returning `false` cancels the checkout before any files are modified.
+pub type Notify<'a> = FnMut(CheckoutNotificationType, Option<&Path>,
+                            Option<DiffFile>, Option<DiffFile>,
+                            Option<DiffFile>) -> bool + 'a;
 
 impl<'cb> RepoBuilder<'cb> {
     /// Creates a new repository builder with all of the default configuration.
@@ -51,7 +67,6 @@ impl<'cb> RepoBuilder<'cb> {
         RepoBuilder {
             bare: false,
             branch: None,
-            sig: None,
             local: true,
             hardlinks: true,
             checkout: None,
@@ -74,19 +89,16 @@ impl<'cb> RepoBuilder<'cb> {
         self
     }
 
-    /// Specify the identity that will be used when updating the reflog.
-    ///
-    /// If not specified, the default signature will be used.
-    pub fn signature(&mut self, sig: Signature<'static>) -> &mut RepoBuilder<'cb> {
-        self.sig = Some(sig);
-        self
-    }
-
     /// Set the flag for bypassing the git aware transport mechanism for local
     /// paths.
     ///
     /// If `true`, the git-aware transport will be bypassed for local paths. If
     /// `false`, the git-aware transport will not be bypassed.
+    ///
+    /// Bypassing the transport copies or hardlinks the object files of the
+    /// source repository directly, so environments which prohibit hardlinks
+    /// should either disable `hardlinks` or pass `false` here to always
+    /// stream the objects through a pack.
     pub fn local(&mut self, local: bool) -> &mut RepoBuilder<'cb> {
         self.local = local;
         self
@@ -94,6 +106,8 @@ impl<'cb> RepoBuilder<'cb> {
 
     /// Set the flag for whether hardlinks are used when using a local git-aware
     /// transport mechanism.
+    ///
+    /// If `false`, object files are copied rather than linked.
     pub fn hardlinks(&mut self, links: bool) -> &mut RepoBuilder<'cb> {
         self.hardlinks = links;
         self
@@ -128,9 +142,6 @@ impl<'cb> RepoBuilder<'cb> {
         opts.checkout_branch = self.branch.as_ref().map(|s| {
             s.as_ptr()
         }).unwrap_or(0 as *const _);
-        opts.signature = self.sig.as_ref().map(|s| {
-            s.raw()
-        }).unwrap_or(0 as *mut _);
 
         opts.local = match (self.local, self.hardlinks) {
             (true, false) => raw::GIT_CLONE_LOCAL_NO_LINKS,
@@ -138,11 +149,11 @@ impl<'cb> RepoBuilder<'cb> {
             (true, _) => raw::GIT_CLONE_LOCAL_AUTO,
         };
         opts.checkout_opts.checkout_strategy =
-            raw::GIT_CHECKOUT_SAFE_CREATE as c_uint;
+            raw::GIT_CHECKOUT_SAFE as c_uint;
 
         match self.callbacks {
             Some(ref mut cbs) => {
-                opts.remote_callbacks = cbs.raw();
+                opts.fetch_opts.callbacks = cbs.raw();
             },
             None => {}
         }
@@ -169,6 +180,7 @@ impl<'cb> CheckoutBuilder<'cb> {
         ::init();
         CheckoutBuilder {
             disable_filters: false,
+            eol: None,
             dir_perm: None,
             file_perm: None,
             path_ptrs: Vec::new(),
@@ -177,8 +189,10 @@ impl<'cb> CheckoutBuilder<'cb> {
             ancestor_label: None,
             our_label: None,
             their_label: None,
-            checkout_opts: raw::GIT_CHECKOUT_SAFE_CREATE as u32,
+            checkout_opts: raw::GIT_CHECKOUT_SAFE as u32,
             progress: None,
+            notify: None,
+            notify_flags: CheckoutNotificationType::empty(),
         }
     }
 
@@ -204,10 +218,19 @@ impl<'cb> CheckoutBuilder<'cb> {
     /// This is the default.
     pub fn safe(&mut self) -> &mut CheckoutBuilder<'cb> {
         self.checkout_opts &= !((1 << 4) - 1);
-        self.checkout_opts |= raw::GIT_CHECKOUT_SAFE_CREATE as u32;
+        self.checkout_opts |= raw::GIT_CHECKOUT_SAFE as u32;
         self
     }
 
+    /// In safe mode, create files that are missing from the working directory
+    /// even if they are unmodified in the index.
+    ///
+    /// Defaults to false.
+    pub fn recreate_missing(&mut self, allow: bool)
+                            -> &mut CheckoutBuilder<'cb> {
+        self.flag(raw::GIT_CHECKOUT_RECREATE_MISSING, allow)
+    }
+
     fn flag(&mut self, bit: raw::git_checkout_strategy_t,
             on: bool) -> &mut CheckoutBuilder<'cb> {
         if on {
@@ -314,6 +337,16 @@ impl<'cb> CheckoutBuilder<'cb> {
         self.flag(raw::GIT_CHECKOUT_CONFLICT_STYLE_DIFF3, on)
     }
 
+    /// Indicates whether to include common ancestor data in zdiff3 format
+    /// files for conflicts, moving common lines at the edges of a conflict
+    /// outside of the conflict markers.
+    ///
+    /// Defaults to false.
+    pub fn conflict_style_zdiff3(&mut self, on: bool)
+                                 -> &mut CheckoutBuilder<'cb> {
+        self.flag(raw::GIT_CHECKOUT_CONFLICT_STYLE_ZDIFF3, on)
+    }
+
     /// Indicate whether to apply filters like CRLF conversion.
     pub fn disable_filters(&mut self, disable: bool)
                            -> &mut CheckoutBuilder<'cb> {
@@ -321,6 +354,23 @@ impl<'cb> CheckoutBuilder<'cb> {
         self
     }
 
+    /// Convert the line endings of the files which are written according to
+    /// `opts` rather than the configuration of the repository.
+    ///
+    /// The options only take effect for the checkouts of
+    /// `Repository::checkout_head`, `checkout_index` and `checkout_tree`, and
+    /// cannot be combined with an explicit index for `checkout_index`.
+    pub fn eol_options(&mut self, opts: &EolOptions)
+                       -> &mut CheckoutBuilder<'cb> {
+        self.eol = Some(opts.clone());
+        self
+    }
+
+    /// Get the line ending options set with `eol_options`, if any.
+    pub fn get_eol_options(&self) -> Option<&EolOptions> {
+        self.eol.as_ref()
+    }
+
     /// Set the mode with which new directories are created.
     ///
     /// Default is 0755
@@ -376,12 +426,38 @@ impl<'cb> CheckoutBuilder<'cb> {
     }
 
     /// Set a callback to receive notifications of checkout progress.
+    ///
+    /// The callback is also invoked for checkouts performed as part of
+    /// `RepoBuilder::clone` and `Repository::merge`.
     pub fn progress<F>(&mut self, cb: F) -> &mut CheckoutBuilder<'cb>
-                       where F: FnMut(&[u8], usize, usize) + 'cb {
+                       where F: FnMut(Option<&Path>, usize, usize) + 'cb {
         self.progress = Some(Box::new(cb) as Box<Progress<'cb>>);
         self
     }
 
+    /// Set a callback to receive checkout notifications.
+    ///
+    /// Callbacks are invoked prior to modifying any files on disk, for each
+    /// of the kinds of notification selected with `notify_on`. Returning
+    /// `false` from the callback will cancel the checkout.
+    pub fn notify<F>(&mut self, cb: F) -> &mut CheckoutBuilder<'cb>
+                     where F: FnMut(CheckoutNotificationType, Option<&Path>,
+                                    Option<DiffFile>, Option<DiffFile>,
+                                    Option<DiffFile>) -> bool + 'cb {
+        self.notify = Some(Box::new(cb) as Box<Notify<'cb>>);
+        self
+    }
+
+    /// Indicates which notifications should be passed to the callback set
+    /// with `notify`.
+    ///
+    /// Defaults to none.
+    pub fn notify_on(&mut self, notification_types: CheckoutNotificationType)
+                     -> &mut CheckoutBuilder<'cb> {
+        self.notify_flags = notification_types;
+        self
+    }
+
     /// Configure a raw checkout options based on this configuration.
     ///
     /// This method is unsafe as there is no guarantee that this structure will
@@ -418,6 +494,12 @@ impl<'cb> CheckoutBuilder<'cb> {
             opts.progress_cb = Some(f);
             opts.progress_payload = self as *mut _ as *mut _;
         }
+        if self.notify.is_some() {
+            let f: raw::git_checkout_notify_cb = notify_cb;
+            opts.notify_cb = Some(f);
+            opts.notify_payload = self as *mut _ as *mut _;
+            opts.notify_flags = self.notify_flags.bits() as c_uint;
+        }
         opts.checkout_strategy = self.checkout_opts as c_uint;
     }
 }
@@ -432,18 +514,51 @@ extern fn progress_cb(path: *const c_char,
             Some(ref mut c) => c,
             None => return,
         };
-        let path = ffi::c_str_to_bytes(&path);
+        let path = if path.is_null() {
+            None
+        } else {
+            Some(Path::new(ffi::c_str_to_bytes(&path)))
+        };
         panic::wrap(|| {
-            callback(path, completed as usize, total as usize);
+            callback(path.as_ref(), completed as usize, total as usize);
+        });
+    }
+}
+
+extern fn notify_cb(why: raw::git_checkout_notify_t,
+                    path: *const c_char,
+                    baseline: *const raw::git_diff_file,
+                    target: *const raw::git_diff_file,
+                    workdir: *const raw::git_diff_file,
+                    data: *mut c_void) -> c_int {
+    unsafe {
+        let payload: &mut CheckoutBuilder = &mut *(data as *mut CheckoutBuilder);
+        let callback = match payload.notify {
+            Some(ref mut c) => c,
+            None => return 0,
+        };
+        let path = if path.is_null() {
+            None
+        } else {
+            Some(Path::new(ffi::c_str_to_bytes(&path)))
+        };
+        let why = CheckoutNotificationType::from_bits_truncate(why as u32);
+        let keep_going = panic::wrap(|| {
+            callback(why, path.as_ref(),
+                     Binding::from_raw_opt(baseline),
+                     Binding::from_raw_opt(target),
+                     Binding::from_raw_opt(workdir))
         });
+        if keep_going == Some(true) {0} else {-1}
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::old_io::{fs, TempDir};
-    use super::RepoBuilder;
-    use Repository;
+    use std::old_io::{fs, File, TempDir};
+    use std::old_io::fs::PathExtensions;
+    use super::{CheckoutBuilder, RepoBuilder};
+    use {Repository, CHECKOUT_NOTIFICATION_DIRTY};
 
     #[test]
     fn smoke() {
@@ -474,4 +589,127 @@ mod tests {
                                   .clone(url.as_slice(), &dst).is_err());
     }
 
+    #[test]
+    fn clone_progress() {
+        let (td, _repo) = checkout_repo_init();
+        let dst = td.path().join("clone");
+        let mut paths = Vec::new();
+        let mut last = (0, 0);
+        {
+            let mut co = CheckoutBuilder::new();
+            co.progress(|path, cur, total| {
+                paths.extend(path.cloned().into_iter());
+                last = (cur, total);
+            });
+            let url = td.path().as_str().unwrap();
+            RepoBuilder::new().with_checkout(co).clone(url, &dst).unwrap();
+        }
+        paths.sort();
+        assert_eq!(paths, vec![Path::new("bar"), Path::new("foo")]);
+        assert_eq!(last, (2, 2));
+    }
+
+
+    fn checkout_repo_init() -> (TempDir, Repository) {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join("foo")).write_str("foo\n").unwrap();
+        File::create(&td.path().join("bar")).write_str("bar\n").unwrap();
+        {
+            let mut index = repo.index().unwrap();
+            index.add_path(&Path::new("foo")).unwrap();
+            index.add_path(&Path::new("bar")).unwrap();
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let sig = repo.signature().unwrap();
+            let head = repo.head().unwrap().target().unwrap();
+            let parent = repo.find_commit(head).unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, "files",
+                        &tree, &[&parent]).unwrap();
+        }
+        (td, repo)
+    }
+
+    #[test]
+    fn checkout_strategies() {
+        let (td, repo) = checkout_repo_init();
+        let foo = td.path().join("foo");
+        let bar = td.path().join("bar");
+
+        // a safe checkout does not recreate missing files by default
+        fs::unlink(&foo).unwrap();
+        repo.checkout_head(None).unwrap();
+        assert!(!foo.exists());
+        repo.checkout_head(Some(CheckoutBuilder::new()
+                                    .recreate_missing(true))).unwrap();
+        assert!(foo.exists());
+
+        // nor does it overwrite modified files, unlike a forced checkout
+        File::create(&bar).write_str("modified\n").unwrap();
+        repo.checkout_head(None).unwrap();
+        assert_eq!(File::open(&bar).read_to_string().unwrap(), "modified\n");
+        let mut paths = Vec::new();
+        {
+            let mut opts = CheckoutBuilder::new();
+            opts.force().progress(|p, cur, total| {
+                assert!(cur <= total);
+                paths.extend(p.cloned().into_iter());
+            });
+            repo.checkout_head(Some(&mut opts)).unwrap();
+        }
+        assert_eq!(File::open(&bar).read_to_string().unwrap(), "bar\n");
+        assert!(paths.contains(&Path::new("bar")));
+    }
+
+    #[test]
+    fn checkout_paths_and_target_dir() {
+        let (td, repo) = checkout_repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let tree = repo.find_object(head, None).unwrap();
+
+        File::create(&td.path().join("foo")).write_str("1\n").unwrap();
+        File::create(&td.path().join("bar")).write_str("2\n").unwrap();
+        repo.checkout_tree(&tree, Some(CheckoutBuilder::new().force()
+                                           .path("foo"))).unwrap();
+        assert_eq!(File::open(&td.path().join("foo")).read_to_string().unwrap(),
+                   "foo\n");
+        assert_eq!(File::open(&td.path().join("bar")).read_to_string().unwrap(),
+                   "2\n");
+
+        let dst = td.path().join("export");
+        repo.checkout_index(None, Some(CheckoutBuilder::new().force()
+                                           .target_dir(dst.clone()))).unwrap();
+        assert!(dst.join("foo").exists());
+        assert!(dst.join("bar").exists());
+    }
+
+    #[test]
+    fn checkout_notify() {
+        let (td, repo) = checkout_repo_init();
+        let bar = td.path().join("bar");
+        File::create(&bar).write_str("modified\n").unwrap();
+
+        // vetoing a dirty file cancels the whole checkout
+        {
+            let mut opts = CheckoutBuilder::new();
+            opts.force().notify_on(CHECKOUT_NOTIFICATION_DIRTY)
+                .notify(|_, _, _, _, _| false);
+            assert!(repo.checkout_head(Some(&mut opts)).is_err());
+        }
+        assert_eq!(File::open(&bar).read_to_string().unwrap(), "modified\n");
+
+        let mut dirty = Vec::new();
+        {
+            let mut opts = CheckoutBuilder::new();
+            opts.force().notify_on(CHECKOUT_NOTIFICATION_DIRTY)
+                .notify(|why, path, _baseline, target, workdir| {
+                assert_eq!(why, CHECKOUT_NOTIFICATION_DIRTY);
+                assert!(target.is_some() && workdir.is_some());
+                dirty.push(path.unwrap().clone());
+                true
+            });
+            repo.checkout_head(Some(&mut opts)).unwrap();
+        }
+        assert_eq!(dirty, vec![Path::new("bar")]);
+        assert_eq!(File::open(&bar).read_to_string().unwrap(), "bar\n");
+    }
 }
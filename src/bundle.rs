@@ -0,0 +1,311 @@
+use std::old_io::{BufferedReader, File, IoErrorKind, SeekStyle};
+use std::str;
+
+use {Error, Oid, ObjectType, Repository, Revwalk};
+use build::CheckoutBuilder;
+use odb::Indexer;
+use util::io_error;
+
+static SIGNATURE: &'static str = "# v2 git bundle\n";
+
+/// A builder for a bundle file, carrying a pack of the commits in a range
+/// along with the references pointing into it, as `git bundle create` would
+/// produce.
+///
+/// Bundles allow history to be moved between repositories without any
+/// network access, and are read back with `Bundle`.
+pub struct BundleBuilder<'repo> {
+    repo: &'repo Repository,
+    walk: Revwalk<'repo>,
+    refs: Vec<(String, Oid)>,
+    tags: Vec<Oid>,
+    prerequisites: Vec<Oid>,
+}
+
+/// A bundle file, as created by `git bundle create` or `BundleBuilder`.
+///
+/// Only the header of the bundle is kept in memory, the pack it carries is
+/// read from the file as it is unbundled.
+pub struct Bundle {
+    refs: Vec<(String, Oid)>,
+    prerequisites: Vec<Oid>,
+    path: Path,
+    pack: u64,
+}
+
+impl<'repo> BundleBuilder<'repo> {
+    /// Create a new builder for a bundle of objects in the given repository.
+    pub fn new(repo: &'repo Repository) -> Result<BundleBuilder<'repo>, Error> {
+        Ok(BundleBuilder {
+            repo: repo,
+            walk: try!(repo.revwalk()),
+            refs: Vec::new(),
+            tags: Vec::new(),
+            prerequisites: Vec::new(),
+        })
+    }
+
+    /// Include the reference with the given full name, such as
+    /// `refs/heads/master` or `HEAD`, in the bundle along with the history
+    /// it points to.
+    ///
+    /// The reference must point to a committish.
+    pub fn reference(&mut self, name: &str) -> Result<(), Error> {
+        let id = try!(self.repo.refname_to_id(name));
+        let obj = try!(self.repo.find_object(id, None));
+        if obj.kind() == Some(ObjectType::Tag) {
+            self.tags.push(id);
+        }
+        try!(self.walk.push(id));
+        self.refs.push((name.to_string(), id));
+        Ok(())
+    }
+
+    /// Exclude the given commit and its ancestors from the bundle.
+    ///
+    /// The commit is recorded as a prerequisite of the bundle, which a
+    /// repository must already have in order to read the bundle.
+    pub fn hide(&mut self, id: Oid) -> Result<(), Error> {
+        try!(self.walk.hide(id));
+        self.prerequisites.push(id);
+        Ok(())
+    }
+
+    /// Write the bundle to the given path.
+    ///
+    /// The revwalk over the included history is consumed in the process, and
+    /// the pack is written out as it is generated.
+    pub fn write(&mut self, path: &Path) -> Result<(), Error> {
+        let mut builder = try!(self.repo.packbuilder());
+        for id in self.tags.iter() {
+            try!(builder.insert_object(*id, None));
+        }
+        try!(builder.insert_walk(&mut self.walk));
+
+        let mut header = String::from_str(SIGNATURE);
+        for id in self.prerequisites.iter() {
+            header.push_str(format!("-{}\n", id).as_slice());
+        }
+        for &(ref name, id) in self.refs.iter() {
+            header.push_str(format!("{} {}\n", id, name).as_slice());
+        }
+        header.push('\n');
+        let mut out = try!(File::create(path).map_err(io_error));
+        try!(out.write_str(header.as_slice()).map_err(io_error));
+
+        let mut err = None;
+        let res = builder.foreach(|data| {
+            match out.write_all(data) {
+                Ok(()) => true,
+                Err(e) => { err = Some(e); false }
+            }
+        });
+        match err {
+            Some(e) => Err(io_error(e)),
+            None => res,
+        }
+    }
+}
+
+impl Bundle {
+    /// Read the header of the bundle at the given path.
+    pub fn open(path: &Path) -> Result<Bundle, Error> {
+        let file = try!(File::open(path).map_err(io_error));
+        let mut file = BufferedReader::new(file);
+        let mut refs = Vec::new();
+        let mut prerequisites = Vec::new();
+        let signature = try!(read_line(&mut file));
+        if signature.as_slice() != SIGNATURE.as_bytes() {
+            return Err(Error::from_str("not a v2 git bundle"))
+        }
+        let mut pos = signature.len();
+        loop {
+            let bytes = try!(read_line(&mut file));
+            if bytes.last() != Some(&b'\n') {
+                return Err(Error::from_str("truncated bundle header"))
+            }
+            pos += bytes.len();
+            let bytes = &bytes[..bytes.len() - 1];
+            let line = try!(str::from_utf8(bytes).map_err(|_| {
+                Error::from_str("bundle header is not valid utf-8")
+            }));
+            if line.is_empty() { break }
+
+            if line.starts_with("-") {
+                let id = line[1..].splitn(1, ' ').next().unwrap();
+                prerequisites.push(try!(Oid::from_str(id)));
+            } else {
+                let mut parts = line.splitn(1, ' ');
+                let id = try!(Oid::from_str(parts.next().unwrap()));
+                let name = try!(parts.next().ok_or_else(|| {
+                    Error::from_str("malformed reference in bundle header")
+                }));
+                refs.push((name.to_string(), id));
+            }
+        }
+        Ok(Bundle {
+            refs: refs,
+            prerequisites: prerequisites,
+            path: path.clone(),
+            pack: pos as u64,
+        })
+    }
+
+    /// Get the references carried by the bundle, as pairs of their full
+    /// name and target.
+    pub fn refs(&self) -> &[(String, Oid)] { self.refs.as_slice() }
+
+    /// Get the commits which a repository must already have in order to
+    /// read the bundle.
+    pub fn prerequisites(&self) -> &[Oid] { self.prerequisites.as_slice() }
+
+    /// Add the objects of the bundle to the given repository, like
+    /// `git fetch` from a bundle file.
+    ///
+    /// No references are updated; the caller decides where the references
+    /// returned by `refs` end up. This fails if the repository lacks any of
+    /// the prerequisites of the bundle.
+    pub fn unbundle(&self, repo: &Repository) -> Result<(), Error> {
+        let odb = try!(repo.odb());
+        for id in self.prerequisites.iter() {
+            if !odb.exists(*id) {
+                let msg = format!("repository lacks the prerequisite {}", id);
+                return Err(Error::from_str(msg.as_slice()))
+            }
+        }
+
+        let mut file = try!(File::open(&self.path).map_err(io_error));
+        try!(file.seek(self.pack as i64, SeekStyle::SeekSet)
+                 .map_err(io_error));
+        let dir = match repo.commondir() {
+            Some(dir) => dir.join("objects/pack"),
+            None => return Err(Error::from_str("repository has no path")),
+        };
+        let mut indexer = try!(Indexer::new(&odb, &dir));
+        let mut buf = [0; 64 * 1024];
+        loop {
+            match file.read(&mut buf) {
+                Ok(n) => try!(indexer.append(&buf[..n])),
+                Err(ref e) if e.kind == IoErrorKind::EndOfFile => break,
+                Err(e) => return Err(io_error(e)),
+            }
+        }
+        try!(indexer.commit());
+        Ok(())
+    }
+
+    /// Create a new repository at the given path out of this bundle, like
+    /// `git clone` from a bundle file.
+    ///
+    /// All references of the bundle are created as they are and the branch
+    /// its `HEAD` points to, if any, is checked out. The bundle must not
+    /// have any prerequisites.
+    pub fn clone_into(&self, path: &Path) -> Result<Repository, Error> {
+        if !self.prerequisites.is_empty() {
+            return Err(Error::from_str("cannot clone from a bundle with \
+                                        prerequisites"))
+        }
+        let repo = try!(Repository::init(path));
+        try!(self.unbundle(&repo));
+
+        let msg = "clone: from bundle";
+        let mut head = None;
+        for &(ref name, id) in self.refs.iter() {
+            if name.as_slice() == "HEAD" {
+                head = Some(id);
+            } else {
+                try!(repo.reference(name.as_slice(), id, false, msg));
+            }
+        }
+        match head {
+            Some(id) => {
+                // a bundle only records the target of `HEAD`, so pick the
+                // branch pointing at the same commit
+                let branch = self.refs.iter().find(|&&(ref name, other)| {
+                    other == id && name.starts_with("refs/heads/")
+                });
+                match branch {
+                    Some(&(ref name, _)) => {
+                        try!(repo.set_head(name.as_slice()));
+                    }
+                    None => try!(repo.set_head_detached(id)),
+                }
+                try!(repo.checkout_head(Some(CheckoutBuilder::new().force())));
+            }
+            None => {}
+        }
+        Ok(repo)
+    }
+}
+
+fn read_line(file: &mut BufferedReader<File>) -> Result<Vec<u8>, Error> {
+    match file.read_until(b'\n') {
+        Ok(line) => Ok(line),
+        Err(ref e) if e.kind == IoErrorKind::EndOfFile => Ok(Vec::new()),
+        Err(e) => Err(io_error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::{File, TempDir};
+    use {Bundle, BundleBuilder, Repository};
+
+    #[test]
+    fn smoke() {
+        let (td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+
+        let td2 = TempDir::new("test").unwrap();
+        let full = td2.path().join("full.bundle");
+        let mut builder = BundleBuilder::new(&repo).unwrap();
+        builder.reference("refs/heads/master").unwrap();
+        builder.reference("HEAD").unwrap();
+        builder.write(&full).unwrap();
+
+        let bundle = Bundle::open(&full).unwrap();
+        assert_eq!(bundle.prerequisites(), [].as_slice());
+        assert_eq!(bundle.refs(),
+                   [("refs/heads/master".to_string(), head),
+                    ("HEAD".to_string(), head)].as_slice());
+        let clone = bundle.clone_into(&td2.path().join("clone")).unwrap();
+        assert_eq!(clone.head().unwrap().name(), Some("refs/heads/master"));
+        assert_eq!(clone.head().unwrap().target(), Some(head));
+
+        // an incremental bundle on top of the initial commit
+        let sig = repo.signature().unwrap();
+        let base = repo.find_commit(head).unwrap();
+        File::create(&td.path().join("foo")).write_str("foo\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("foo")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let second = repo.commit(Some("HEAD"), &sig, &sig, "second",
+                                 &tree, &[&base]).unwrap();
+
+        let incremental = td2.path().join("incremental.bundle");
+        let mut builder = BundleBuilder::new(&repo).unwrap();
+        builder.reference("refs/heads/master").unwrap();
+        builder.hide(head).unwrap();
+        builder.write(&incremental).unwrap();
+        let bundle = Bundle::open(&incremental).unwrap();
+        assert_eq!(bundle.prerequisites(), [head].as_slice());
+        assert!(bundle.clone_into(&td2.path().join("nope")).is_err());
+
+        let empty = Repository::init(&td2.path().join("empty")).unwrap();
+        assert!(bundle.unbundle(&empty).is_err());
+        bundle.unbundle(&clone).unwrap();
+        let commit = clone.find_commit(second).unwrap();
+        assert_eq!(commit.message(), Some("second"));
+        assert!(commit.tree().unwrap().get_name("foo").is_some());
+
+        assert!(Bundle::open(&td.path().join("foo")).is_err());
+
+        // the header is read on its own, the pack only when unbundling
+        let truncated = td2.path().join("truncated.bundle");
+        let header = format!("# v2 git bundle\n{} HEAD\n\n", second);
+        File::create(&truncated).write_str(header.as_slice()).unwrap();
+        let bundle = Bundle::open(&truncated).unwrap();
+        assert_eq!(bundle.refs(), [("HEAD".to_string(), second)].as_slice());
+        assert!(bundle.unbundle(&empty).is_err());
+    }
+}
@@ -27,15 +27,15 @@ pub fn convert<T, U: Convert<T>>(u: &U) -> T { u.convert() }
 
 pub fn try(ret: libc::c_int) -> Result<libc::c_int, Error> {
     match ret {
-        n if n < 0 => Err(last_error()),
+        n if n < 0 => Err(last_error(n)),
         n => Ok(n),
     }
 }
 
-fn last_error() -> Error {
+fn last_error(code: libc::c_int) -> Error {
     // Apparently libgit2 isn't necessarily guaranteed to set the last error
     // whenever a function returns a negative value!
-    Error::last_error().unwrap_or_else(|| {
+    Error::last_error(code).unwrap_or_else(|| {
         Error::from_str("an unknown error occurred")
     })
 }
@@ -45,7 +45,7 @@ mod impls {
     use libc;
 
     use {raw, ConfigLevel, ResetType, ObjectType, BranchType, Direction};
-    use {DiffFormat};
+    use {DiffFormat, ApplyLocation, SubmoduleIgnore};
     use call::Convert;
 
     impl<T: Copy> Convert<T> for T {
@@ -91,6 +91,16 @@ mod impls {
         }
     }
 
+    impl Convert<raw::git_apply_location_t> for ApplyLocation {
+        fn convert(&self) -> raw::git_apply_location_t {
+            match *self {
+                ApplyLocation::WorkDir => raw::GIT_APPLY_LOCATION_WORKDIR,
+                ApplyLocation::Index => raw::GIT_APPLY_LOCATION_INDEX,
+                ApplyLocation::Both => raw::GIT_APPLY_LOCATION_BOTH,
+            }
+        }
+    }
+
     impl Convert<raw::git_direction> for Direction {
         fn convert(&self) -> raw::git_direction {
             match *self {
@@ -136,16 +146,32 @@ mod impls {
     impl Convert<raw::git_config_level_t> for ConfigLevel {
         fn convert(&self) -> raw::git_config_level_t {
             match *self {
+                ConfigLevel::ProgramData => raw::GIT_CONFIG_LEVEL_PROGRAMDATA,
                 ConfigLevel::System => raw::GIT_CONFIG_LEVEL_SYSTEM,
                 ConfigLevel::XDG => raw::GIT_CONFIG_LEVEL_XDG,
                 ConfigLevel::Global => raw::GIT_CONFIG_LEVEL_GLOBAL,
                 ConfigLevel::Local => raw::GIT_CONFIG_LEVEL_LOCAL,
+                ConfigLevel::Worktree => raw::GIT_CONFIG_LEVEL_WORKTREE,
                 ConfigLevel::App => raw::GIT_CONFIG_LEVEL_APP,
                 ConfigLevel::Highest => raw::GIT_CONFIG_HIGHEST_LEVEL,
             }
         }
     }
 
+    impl Convert<raw::git_submodule_ignore_t> for SubmoduleIgnore {
+        fn convert(&self) -> raw::git_submodule_ignore_t {
+            match *self {
+                SubmoduleIgnore::Default =>
+                    raw::GIT_SUBMODULE_IGNORE_UNSPECIFIED,
+                SubmoduleIgnore::None => raw::GIT_SUBMODULE_IGNORE_NONE,
+                SubmoduleIgnore::Untracked =>
+                    raw::GIT_SUBMODULE_IGNORE_UNTRACKED,
+                SubmoduleIgnore::Dirty => raw::GIT_SUBMODULE_IGNORE_DIRTY,
+                SubmoduleIgnore::All => raw::GIT_SUBMODULE_IGNORE_ALL,
+            }
+        }
+    }
+
     impl Convert<raw::git_diff_format_t> for DiffFormat {
         fn convert(&self) -> raw::git_diff_format_t {
             match *self {
@@ -1,12 +1,20 @@
 //! Certificate types which are passed to `CertificateCheck` in
 //! `RemoteCallbacks`.
 
+use std::ascii::AsciiExt;
+use std::iter::range;
 use std::marker;
 use std::mem;
+use std::old_io::File;
+use std::old_io::fs::PathExtensions;
 use std::slice;
+use std::str;
 
-use raw;
-use util::Binding;
+use {raw, Error};
+use util::{Binding, io_error, sha1};
+
+#[cfg(feature = "x509")]
+pub use x509::X509Info;
 
 /// A certificate for a remote connection, viewable as one of `CertHostkey` or
 /// `CertX509` currently.
@@ -21,6 +29,19 @@ pub struct CertHostkey<'a> {
     marker: marker::ContravariantLifetime<'a>,
 }
 
+/// The outcome of looking up a host key in a `known_hosts` file with
+/// `CertHostkey::verify_known_hosts`.
+#[derive(PartialEq, Eq, Copy, Debug)]
+pub enum KnownHostStatus {
+    /// The host is listed with the presented key.
+    Match,
+    /// The host is listed, but only with other keys, or the presented key
+    /// has been revoked. The connection should not be trusted.
+    Mismatch,
+    /// The host is not listed at all.
+    NotFound,
+}
+
 /// X.509 certificate information
 pub struct CertX509<'a> {
     raw: *mut raw::git_cert_x509,
@@ -76,6 +97,63 @@ impl<'a> CertHostkey<'a> {
             }
         }
     }
+
+    /// Check this hostkey against the entries for `hostname` in an OpenSSH
+    /// `known_hosts` file, such as `~/.ssh/known_hosts`.
+    ///
+    /// Both plain and hashed hostnames are understood, along with `*` and
+    /// `?` wildcards and negated patterns. Keys are compared by their SHA-1
+    /// hash, so an error is returned if libssh2 did not provide one. A file
+    /// which does not exist lists no hosts.
+    pub fn verify_known_hosts(&self, path: &Path, hostname: &str)
+                              -> Result<KnownHostStatus, Error> {
+        let expected = match self.hash_sha1() {
+            Some(hash) => hash,
+            None => return Err(Error::from_str("the SHA-1 hash of the \
+                                                hostkey is not available")),
+        };
+        if !path.exists() { return Ok(KnownHostStatus::NotFound) }
+        let contents = try!(File::open(path).read_to_end().map_err(io_error));
+        let mut status = KnownHostStatus::NotFound;
+        for line in contents.as_slice().split(|b| *b == b'\n') {
+            let line = match str::from_utf8(line) {
+                Ok(line) => line.trim(),
+                Err(..) => continue,
+            };
+            if line.is_empty() || line.starts_with("#") { continue }
+            let mut fields = line.words();
+            let mut hosts = match fields.next() {
+                Some(field) => field,
+                None => continue,
+            };
+            let revoked = hosts == "@revoked";
+            if hosts.starts_with("@") {
+                // certificate authorities sign keys rather than list them
+                if !revoked { continue }
+                hosts = match fields.next() {
+                    Some(field) => field,
+                    None => continue,
+                };
+            }
+            let key = match (fields.next(), fields.next()) {
+                (Some(_), Some(key)) => key,
+                _ => continue,
+            };
+            if !hosts_match(hosts, hostname) { continue }
+            let same = match ::util::base64_decode(key) {
+                Some(key) => sha1(key.as_slice()) == *expected,
+                None => false,
+            };
+            if revoked && same { return Ok(KnownHostStatus::Mismatch) }
+            if revoked { continue }
+            if same {
+                status = KnownHostStatus::Match;
+            } else if status == KnownHostStatus::NotFound {
+                status = KnownHostStatus::Mismatch;
+            }
+        }
+        Ok(status)
+    }
 }
 
 impl<'a> CertX509<'a> {
@@ -88,6 +166,65 @@ impl<'a> CertX509<'a> {
     }
 }
 
+// Whether a comma-separated list of host patterns from a known_hosts file
+// matches the given hostname.
+fn hosts_match(patterns: &str, hostname: &str) -> bool {
+    if patterns.starts_with("|1|") {
+        let mut parts = patterns[3..].split('|');
+        return match (parts.next().and_then(::util::base64_decode),
+                      parts.next().and_then(::util::base64_decode)) {
+            (Some(salt), Some(hash)) => {
+                hmac_sha1(salt.as_slice(), hostname.as_bytes()).as_slice() ==
+                    hash.as_slice()
+            }
+            _ => false,
+        }
+    }
+    let mut matched = false;
+    for pattern in patterns.split(',') {
+        if pattern.starts_with("!") {
+            if wildcard_match(pattern[1..].as_bytes(), hostname.as_bytes()) {
+                return false
+            }
+        } else if wildcard_match(pattern.as_bytes(), hostname.as_bytes()) {
+            matched = true;
+        }
+    }
+    matched
+}
+
+fn wildcard_match(pattern: &[u8], s: &[u8]) -> bool {
+    match pattern.first() {
+        None => s.is_empty(),
+        Some(&b'*') => {
+            range(0, s.len() + 1).any(|i| {
+                wildcard_match(&pattern[1..], &s[i..])
+            })
+        }
+        Some(&c) => match s.first() {
+            Some(&d) if c == b'?' || c.to_ascii_lowercase() ==
+                                     d.to_ascii_lowercase() => {
+                wildcard_match(&pattern[1..], &s[1..])
+            }
+            _ => false,
+        },
+    }
+}
+
+fn hmac_sha1(key: &[u8], msg: &[u8]) -> [u8; 20] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        slice::bytes::copy_memory(&mut block, &sha1(key));
+    } else {
+        slice::bytes::copy_memory(&mut block, key);
+    }
+    let mut inner = block.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>();
+    inner.push_all(msg);
+    let mut outer = block.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>();
+    outer.push_all(&sha1(inner.as_slice()));
+    sha1(outer.as_slice())
+}
+
 impl<'a> Binding for Cert<'a> {
     type Raw = *mut raw::git_cert;
     fn from_raw(raw: *mut raw::git_cert) -> Cert<'a> {
@@ -95,3 +232,62 @@ impl<'a> Binding for Cert<'a> {
     }
     fn raw(&self) -> *mut raw::git_cert { self.raw }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::{File, TempDir};
+    use raw;
+    use util::Binding;
+    use super::{Cert, KnownHostStatus};
+
+    #[test]
+    fn sha1() {
+        assert_eq!(::util::sha1(b"abc").as_slice(),
+                   b"\xa9\x99\x3e\x36\x47\x06\x81\x6a\xba\x3e\
+                     \x25\x71\x78\x50\xc2\x6c\x9c\xd0\xd8\x9d");
+    }
+
+    #[test]
+    fn known_hosts() {
+        let key = ::util::base64_decode("AAAAB3NzaC1yc2EAAAADa2V5").unwrap();
+        assert_eq!(key.as_slice(), b"\0\0\0\x07ssh-rsa\0\0\0\x03key");
+        let mut raw = raw::git_cert_hostkey {
+            cert_type: raw::GIT_CERT_HOSTKEY_LIBSSH2,
+            kind: raw::GIT_CERT_SSH_SHA1,
+            hash_md5: [0; 16],
+            hash_sha1: ::util::sha1(key.as_slice()),
+            hash_sha256: [0; 32],
+            raw_type: raw::GIT_CERT_SSH_RAW_TYPE_UNKNOWN,
+            hostkey: 0 as *const _,
+            hostkey_len: 0,
+        };
+        let cert = Cert::from_raw(&mut raw as *mut _ as *mut raw::git_cert);
+        let hostkey = cert.as_hostkey().unwrap();
+
+        let td = TempDir::new("test").unwrap();
+        let path = td.path().join("known_hosts");
+        assert_eq!(hostkey.verify_known_hosts(&path, "example.com").unwrap(),
+                   KnownHostStatus::NotFound);
+        File::create(&path).write_str("\
+# comment
+example.com,192.0.2.1 ssh-rsa AAAAB3NzaC1yc2EAAAADa2V5
+other.com ssh-rsa AAAAB3NzaC1yc2EAAAAFb3RoZXI=
+*.wild.com,!bad.wild.com ssh-rsa AAAAB3NzaC1yc2EAAAADa2V5
+|1|MDEyMzQ1Njc4OWFiY2RlZmdoaWo=|gzfxEI74iflku6CWHlY6D9H4tKY= ssh-rsa AAAAB3NzaC1yc2EAAAADa2V5
+@revoked revoked.com ssh-rsa AAAAB3NzaC1yc2EAAAADa2V5
+revoked.com ssh-rsa AAAAB3NzaC1yc2EAAAADa2V5
+").unwrap();
+
+        let check = |host: &str| {
+            hostkey.verify_known_hosts(&path, host).unwrap()
+        };
+        assert_eq!(check("example.com"), KnownHostStatus::Match);
+        assert_eq!(check("192.0.2.1"), KnownHostStatus::Match);
+        assert_eq!(check("other.com"), KnownHostStatus::Mismatch);
+        assert_eq!(check("a.wild.com"), KnownHostStatus::Match);
+        assert_eq!(check("bad.wild.com"), KnownHostStatus::NotFound);
+        assert_eq!(check("hashed.example.com"), KnownHostStatus::Match);
+        assert_eq!(check("revoked.com"), KnownHostStatus::Mismatch);
+        assert_eq!(check("unknown.com"), KnownHostStatus::NotFound);
+    }
+}
@@ -146,22 +146,28 @@ impl<'repo> Commit<'repo> {
 
     /// Creates a new iterator over the parents of this commit.
     pub fn parents<'a>(&'a self) -> Parents<'a, 'repo> {
-        let max = unsafe { raw::git_commit_parentcount(&*self.raw) as usize };
         Parents {
-            range: range(0, max),
+            range: range(0, self.parent_count()),
             commit: self,
         }
     }
 
     /// Creates a new iterator over the parents of this commit.
     pub fn parent_ids(&self) -> ParentIds {
-        let max = unsafe { raw::git_commit_parentcount(&*self.raw) as usize };
         ParentIds {
-            range: range(0, max),
+            range: range(0, self.parent_count()),
             commit: self,
         }
     }
 
+    /// Get the number of parents of this commit.
+    ///
+    /// This is read from the commit itself, so none of the parents are
+    /// loaded from the ODB.
+    pub fn parent_count(&self) -> usize {
+        unsafe { raw::git_commit_parentcount(&*self.raw) as usize }
+    }
+
     /// Get the author of this commit.
     pub fn author(&self) -> Signature {
         unsafe {
@@ -318,6 +324,15 @@ mod tests {
         let id = repo.commit(Some("HEAD"), &sig, &sig, "bar", &tree,
                              &[&commit]).unwrap();
         let head = repo.find_commit(id).unwrap();
+        assert_eq!(head.parent_count(), 1);
+        assert_eq!(head.parent_id(0).unwrap(), target);
+        assert!(head.parent_id(1).is_err());
+        assert_eq!(head.parent_ids().collect::<Vec<_>>(), vec![target]);
+
+        let short = target.to_string();
+        let found = repo.find_commit_by_prefix(&short[..7]).unwrap();
+        assert_eq!(found.id(), target);
+        assert!(repo.find_commit_by_prefix(&id.to_string()[..3]).is_err());
 
         let new_head = head.amend(Some("HEAD"), None, None, None,
                                   Some("new message"), None).unwrap();
@@ -0,0 +1,438 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::ffi::CString;
+use std::old_io::File;
+
+use {raw, Error, Oid, Revwalk};
+use util::{Binding, io_error};
+
+macro_rules! try_opt( ($e:expr) => (
+    match $e { Some(e) => e, None => return None }
+) );
+
+const PARENT_NONE: u32 = 0x70000000;
+const EDGE_EXTENDED: u32 = 0x80000000;
+const GENERATION_MAX: u32 = 0x3fffffff;
+
+const ONE: u8 = 1 << 0;
+const TWO: u8 = 1 << 1;
+const STALE: u8 = 1 << 2;
+
+/// A writer for the commit-graph file of a repository.
+///
+/// The commit-graph file caches the parents, root tree and generation number
+/// of commits, allowing revision walks to skip parsing commit objects. It is
+/// read by libgit2 when `core.commitGraph` is enabled in the configuration.
+pub struct CommitGraphWriter {
+    raw: *mut raw::git_commit_graph_writer,
+}
+
+/// A commit-graph file read into memory.
+///
+/// Queries answered from the graph are ordered by the generation numbers it
+/// stores, so they stop as soon as the remaining commits are known to be
+/// common to both sides instead of walking on to the roots or relying on
+/// commit dates. A graph written by git or `CommitGraphWriter` contains every
+/// commit reachable from the commits in it.
+pub struct CommitGraph {
+    data: Vec<u8>,
+    len: usize,
+    fanout: usize,
+    oids: usize,
+    commits: usize,
+    edges: Option<usize>,
+}
+
+impl CommitGraphWriter {
+    /// Create a new writer which will write the commit-graph file to the
+    /// given `objects/info` directory.
+    pub fn new(objects_info_dir: &Path) -> Result<CommitGraphWriter, Error> {
+        ::init();
+        let dir = CString::from_slice(objects_info_dir.as_vec());
+        let mut raw = 0 as *mut raw::git_commit_graph_writer;
+        unsafe {
+            try_call!(raw::git_commit_graph_writer_new(&mut raw, dir));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
+    /// Add all of the commits which the given revwalk yields to the graph.
+    ///
+    /// The revwalk is consumed in the process.
+    pub fn add_revwalk(&mut self, walk: &mut Revwalk) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_commit_graph_writer_add_revwalk(self.raw,
+                                                               walk.raw()));
+        }
+        Ok(())
+    }
+
+    /// Write the commit-graph file, replacing any existing one.
+    pub fn commit(&mut self) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_commit_graph_writer_commit(self.raw,
+                                                          0 as *mut _));
+        }
+        Ok(())
+    }
+}
+
+impl CommitGraph {
+    /// Read the commit-graph file in the given `objects/info` directory.
+    ///
+    /// Split commit-graphs, stored as a chain of files in
+    /// `objects/info/commit-graphs`, are not supported.
+    pub fn open(objects_info_dir: &Path) -> Result<CommitGraph, Error> {
+        let path = objects_info_dir.join("commit-graph");
+        let data = try!(File::open(&path).read_to_end().map_err(io_error));
+        CommitGraph::parse(data).ok_or_else(|| {
+            Error::from_str("invalid or unsupported commit-graph file")
+        })
+    }
+
+    fn parse(data: Vec<u8>) -> Option<CommitGraph> {
+        if data.len() < 8 || &data[..4] != &b"CGPH"[..] || data[4] != 1 ||
+           data[5] != 1 || data[7] != 0 {
+            return None
+        }
+        let mut fanout = None;
+        let mut oids = None;
+        let mut commits = None;
+        let mut edges = None;
+        for i in range(0, data[6] as usize) {
+            let at = 8 + i * 12;
+            if at + 12 > data.len() { return None }
+            let id = &data[at..at + 4];
+            let offset = try_opt!(be64(&data, at + 4));
+            if id == &b"OIDF"[..] {
+                fanout = Some(offset);
+            } else if id == &b"OIDL"[..] {
+                oids = Some(offset);
+            } else if id == &b"CDAT"[..] {
+                commits = Some(offset);
+            } else if id == &b"EDGE"[..] {
+                edges = Some(offset);
+            }
+        }
+        let fanout = try_opt!(fanout);
+        let len = try_opt!(be32(&data, fanout + 255 * 4)) as usize;
+        let oids = try_opt!(oids);
+        let commits = try_opt!(commits);
+        if oids + len * 20 > data.len() ||
+           commits + len * 36 > data.len() {
+            return None
+        }
+        Some(CommitGraph {
+            data: data,
+            len: len,
+            fanout: fanout,
+            oids: oids,
+            commits: commits,
+            edges: edges,
+        })
+    }
+
+    /// Get the number of commits in the graph.
+    pub fn len(&self) -> usize { self.len }
+
+    /// Get the generation number of a commit, one more than the largest
+    /// generation number of its parents, if the commit is in the graph.
+    pub fn generation(&self, id: Oid) -> Option<u32> {
+        self.position(id).and_then(|pos| self.generation_at(pos))
+    }
+
+    /// Count the unique commits on either side of two commits, as
+    /// `Repository::graph_ahead_behind` does.
+    ///
+    /// Returns `None` if either commit is not in the graph.
+    pub fn ahead_behind(&self, local: Oid, upstream: Oid)
+                        -> Option<(usize, usize)> {
+        let painted = try_opt!(self.paint(local, upstream));
+        let count = |side| {
+            painted.iter().filter(|&&(_, f)| f & (ONE | TWO) == side).count()
+        };
+        Some((count(ONE), count(TWO)))
+    }
+
+    /// Find all merge bases of two commits, the best common ancestors which
+    /// are not ancestors of one another, from the most recent generation to
+    /// the oldest.
+    ///
+    /// Returns `None` if either commit is not in the graph.
+    pub fn merge_bases(&self, one: Oid, two: Oid) -> Option<Vec<Oid>> {
+        let painted = try_opt!(self.paint(one, two));
+        Some(painted.iter().filter(|&&(_, f)| f == ONE | TWO)
+                    .map(|&(pos, _)| self.oid_at(pos)).collect())
+    }
+
+    // Walk down from both commits in order of decreasing generation, marking
+    // which of them each commit is reachable from, until only commits
+    // reachable from a common ancestor are left. As a commit's parents always
+    // have a smaller generation, each commit is visited after all of its
+    // children and with its final marks, which are returned in order.
+    fn paint(&self, one: Oid, two: Oid) -> Option<Vec<(u32, u8)>> {
+        let one = try_opt!(self.position(one));
+        let two = try_opt!(self.position(two));
+        let mut flags = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        let mut nonstale = 0;
+        for &(pos, flag) in [(one, ONE), (two, TWO)].iter() {
+            let old = flags.get(&pos).map(|f| *f).unwrap_or(0);
+            if old == 0 {
+                queue.push((try_opt!(self.generation_at(pos)), pos));
+                nonstale += 1;
+            }
+            flags.insert(pos, old | flag);
+        }
+
+        let mut painted = Vec::new();
+        while nonstale > 0 {
+            let (_, pos) = queue.pop().unwrap();
+            let mut flag = *flags.get(&pos).unwrap();
+            painted.push((pos, flag));
+            if flag & STALE == 0 {
+                nonstale -= 1;
+            }
+            if flag & (ONE | TWO) == ONE | TWO {
+                flag |= STALE;
+            }
+            for parent in try_opt!(self.parents_at(pos)).into_iter() {
+                let old = flags.get(&parent).map(|f| *f).unwrap_or(0);
+                let new = old | flag;
+                if new == old { continue }
+                if old == 0 {
+                    queue.push((try_opt!(self.generation_at(parent)), parent));
+                    if new & STALE == 0 {
+                        nonstale += 1;
+                    }
+                } else if old & STALE == 0 && new & STALE != 0 {
+                    nonstale -= 1;
+                }
+                flags.insert(parent, new);
+            }
+        }
+        Some(painted)
+    }
+
+    fn position(&self, id: Oid) -> Option<u32> {
+        let id = id.as_bytes();
+        let first = id[0] as usize;
+        let mut lo = if first == 0 {0} else {self.fanout_at(first - 1)};
+        let mut hi = self.fanout_at(first);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let at = self.oids + mid * 20;
+            match self.data[at..at + 20].cmp(id) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return Some(mid as u32),
+            }
+        }
+        None
+    }
+
+    fn fanout_at(&self, i: usize) -> usize {
+        let n = be32(&self.data, self.fanout + i * 4).unwrap() as usize;
+        if n < self.len {n} else {self.len}
+    }
+
+    fn oid_at(&self, pos: u32) -> Oid {
+        let at = self.oids + pos as usize * 20;
+        Oid::from_bytes(&self.data[at..at + 20]).unwrap()
+    }
+
+    // Generation numbers which have not been computed are zero, and those
+    // which are too large to be stored are clamped. Neither can be used to
+    // order commits, so the graph can't answer queries involving them.
+    fn generation_at(&self, pos: u32) -> Option<u32> {
+        let at = self.commits + pos as usize * 36 + 32;
+        let generation = try_opt!(be32(&self.data, at)) >> 2;
+        if generation == 0 || generation == GENERATION_MAX {
+            None
+        } else {
+            Some(generation)
+        }
+    }
+
+    fn parents_at(&self, pos: u32) -> Option<Vec<u32>> {
+        let at = self.commits + pos as usize * 36 + 20;
+        let mut parents = Vec::new();
+        let first = try_opt!(be32(&self.data, at));
+        let second = try_opt!(be32(&self.data, at + 4));
+        if first != PARENT_NONE {
+            parents.push(first);
+        }
+        if second & EDGE_EXTENDED != 0 {
+            // the remaining parents of octopus merges are listed separately
+            let mut edge = try_opt!(self.edges) +
+                           (second & !EDGE_EXTENDED) as usize * 4;
+            loop {
+                let parent = try_opt!(be32(&self.data, edge));
+                parents.push(parent & !EDGE_EXTENDED);
+                if parent & EDGE_EXTENDED != 0 { break }
+                edge += 4;
+            }
+        } else if second != PARENT_NONE {
+            parents.push(second);
+        }
+        if parents.iter().any(|p| *p as usize >= self.len) {
+            return None
+        }
+        Some(parents)
+    }
+}
+
+fn be32(data: &[u8], at: usize) -> Option<u32> {
+    if at > data.len() || data.len() - at < 4 { return None }
+    Some(data[at..at + 4].iter().fold(0, |n, b| (n << 8) | (*b as u32)))
+}
+
+fn be64(data: &[u8], at: usize) -> Option<usize> {
+    if at > data.len() || data.len() - at < 8 { return None }
+    let n = data[at..at + 8].iter().fold(0, |n, b| (n << 8) | (*b as u64));
+    Some(n as usize)
+}
+
+impl Binding for CommitGraphWriter {
+    type Raw = *mut raw::git_commit_graph_writer;
+    unsafe fn from_raw(raw: *mut raw::git_commit_graph_writer)
+                       -> CommitGraphWriter {
+        CommitGraphWriter { raw: raw }
+    }
+    fn raw(&self) -> *mut raw::git_commit_graph_writer { self.raw }
+}
+
+impl Drop for CommitGraphWriter {
+    fn drop(&mut self) {
+        unsafe { raw::git_commit_graph_writer_free(self.raw) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::TempDir;
+    use std::old_io::fs::PathExtensions;
+    use {CommitGraphUsage, CommitGraphWriter, Repository};
+    use fixture::FixtureBuilder;
+
+    #[test]
+    fn write() {
+        let (_td, repo) = ::test::repo_init();
+        let file = repo.path().join("objects/info/commit-graph");
+        assert!(!file.exists());
+        repo.write_commit_graph().unwrap();
+        assert!(file.exists());
+
+        let mut walk = repo.revwalk().unwrap();
+        walk.push_head().unwrap();
+        let info = repo.path().join("objects/info");
+        let mut writer = CommitGraphWriter::new(&info).unwrap();
+        writer.add_revwalk(&mut walk).unwrap();
+        writer.commit().unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_bool("core.commitGraph", true).unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let mut walk = repo.revwalk().unwrap();
+        walk.push_head().unwrap();
+        assert_eq!(walk.collect::<Vec<_>>(), vec![head]);
+    }
+
+    #[test]
+    fn ahead_behind_and_merge_bases() {
+        let fixture = FixtureBuilder::new()
+                                     .file("a", b"a").commit("a")
+                                     .file("b", b"b").commit("b")
+                                     .branch("side")
+                                     .file("c", b"c").commit("c")
+                                     .file("d", b"d").commit("d")
+                                     .checkout("side")
+                                     .file("e", b"e").commit("e")
+                                     .build().unwrap();
+        let repo = fixture.repo();
+        let master = repo.refname_to_id("refs/heads/master").unwrap();
+        let side = repo.refname_to_id("refs/heads/side").unwrap();
+        let base = repo.find_commit(side).unwrap().parent_id(0).unwrap();
+
+        assert!(repo.commit_graph().unwrap().is_none());
+        assert!(repo.graph_ahead_behind_with(master, side,
+                                             CommitGraphUsage::Require)
+                    .is_err());
+        assert_eq!(repo.graph_ahead_behind_with(master, side,
+                                                CommitGraphUsage::Auto)
+                       .unwrap(), (2, 1));
+
+        repo.write_commit_graph().unwrap();
+        let graph = repo.commit_graph().unwrap().unwrap();
+        assert_eq!(graph.len(), 5);
+        assert_eq!(graph.generation(base), Some(2));
+        assert_eq!(graph.generation(master), Some(4));
+        for usage in [CommitGraphUsage::Require,
+                      CommitGraphUsage::Forbid].iter() {
+            assert_eq!(repo.graph_ahead_behind_with(master, side, *usage)
+                           .unwrap(), (2, 1));
+            assert_eq!(repo.graph_ahead_behind_with(base, master, *usage)
+                           .unwrap(), (0, 2));
+            assert_eq!(repo.graph_ahead_behind_with(side, side, *usage)
+                           .unwrap(), (0, 0));
+            assert_eq!(repo.merge_bases_with(master, side, *usage).unwrap(),
+                       vec![base]);
+            assert_eq!(repo.merge_bases_with(base, master, *usage).unwrap(),
+                       vec![base]);
+        }
+
+        // commits made after the graph was written are not in it
+        let sig = repo.signature().unwrap();
+        let parent = repo.find_commit(side).unwrap();
+        let tree = parent.tree().unwrap();
+        let new = repo.commit(None, &sig, &sig, "f", &tree,
+                              &[&parent]).unwrap();
+        assert!(graph.generation(new).is_none());
+        assert!(repo.merge_bases_with(new, master, CommitGraphUsage::Require)
+                    .is_err());
+        assert_eq!(repo.merge_bases_with(new, master, CommitGraphUsage::Auto)
+                       .unwrap(), vec![base]);
+    }
+
+    #[test]
+    fn cache_worktrees_and_replacements() {
+        let fixture = FixtureBuilder::new()
+                                     .file("a", b"a").commit("a")
+                                     .file("b", b"b").commit("b")
+                                     .build().unwrap();
+        let repo = fixture.repo();
+        let head = repo.head().unwrap().target().unwrap();
+        let first = repo.find_commit(head).unwrap().parent_id(0).unwrap();
+        let require = CommitGraphUsage::Require;
+        repo.write_commit_graph().unwrap();
+        assert_eq!(repo.graph_ahead_behind_with(head, first, require)
+                       .unwrap(), (1, 0));
+
+        // a rewritten graph is picked up by the next query
+        let sig = repo.signature().unwrap();
+        let parent = repo.find_commit(head).unwrap();
+        let tree = parent.tree().unwrap();
+        let new = repo.commit(Some("HEAD"), &sig, &sig, "c", &tree,
+                              &[&parent]).unwrap();
+        assert!(repo.graph_ahead_behind_with(new, first, require).is_err());
+        repo.write_commit_graph().unwrap();
+        assert_eq!(repo.graph_ahead_behind_with(new, first, require)
+                       .unwrap(), (2, 0));
+
+        // linked worktrees share the graph of the main repository
+        let td = TempDir::new("test").unwrap();
+        repo.worktree("wt", &td.path().join("wt")).unwrap();
+        let wt = Repository::open(&td.path().join("wt")).unwrap();
+        assert_eq!(wt.commondir(), repo.commondir());
+        assert!(wt.commit_graph().unwrap().is_some());
+        assert_eq!(wt.graph_ahead_behind_with(new, first, require).unwrap(),
+                   (2, 0));
+
+        // the graph records the original parents, not the replaced ones
+        repo.replace_object(new, first, false).unwrap();
+        assert!(repo.graph_ahead_behind_with(new, first, require).is_err());
+        assert_eq!(repo.graph_ahead_behind_with(new, first,
+                                                CommitGraphUsage::Auto)
+                       .unwrap(), (2, 0));
+    }
+}
@@ -1,9 +1,10 @@
+use std::collections::BTreeMap;
 use std::ffi::CString;
 use std::marker;
 use std::str;
 use libc;
 
-use {raw, Error, ConfigLevel, Buf};
+use {raw, Direction, Error, ConfigLevel, Buf};
 use util::Binding;
 
 /// A structure representing a git configuration key/value store
@@ -16,6 +17,7 @@ pub struct Config {
 /// An entry has a name, a value, and a level it applies to.
 pub struct ConfigEntry<'cfg> {
     raw: *const raw::git_config_entry,
+    owned: bool,
     marker: marker::ContravariantLifetime<'cfg>,
 }
 
@@ -117,7 +119,7 @@ impl Config {
         let path = CString::from_slice(path.as_vec());
         unsafe {
             try_call!(raw::git_config_add_file_ondisk(self.raw, path, level,
-                                                      force));
+                                                      0 as *const _, force));
             Ok(())
         }
     }
@@ -196,16 +198,43 @@ impl Config {
         }
     }
 
+    /// Get the value of a path config variable.
+    ///
+    /// A leading `~/` is expanded to the home directory of the user, as git
+    /// does for options holding paths.
+    pub fn get_path(&self, name: &str) -> Result<Path, Error> {
+        let buf = Buf::new();
+        let name = CString::from_slice(name.as_bytes());
+        unsafe {
+            try_call!(raw::git_config_get_path(buf.raw(), &*self.raw, name));
+        }
+        Ok(Path::new(&*buf))
+    }
+
     /// Get the ConfigEntry for a config variable.
     pub fn get_entry(&self, name: &str) -> Result<ConfigEntry, Error> {
-        let mut ret = 0 as *const raw::git_config_entry;
+        let mut ret = 0 as *mut raw::git_config_entry;
         let name = CString::from_slice(name.as_bytes());
         unsafe {
             try_call!(raw::git_config_get_entry(&mut ret, &*self.raw, name));
-            Ok(Binding::from_raw(ret))
+            let mut entry: ConfigEntry = Binding::from_raw(ret as *const _);
+            entry.owned = true;
+            Ok(entry)
         }
     }
 
+    /// Get the level of a config variable along with the path of the file
+    /// its value was read from.
+    ///
+    /// The path is that of the file actually defining the variable, which
+    /// may be one included by the file of the given level. It is `None` if
+    /// the value does not come from a file.
+    pub fn get_entry_with_origin(&self, name: &str)
+                                 -> Result<(ConfigLevel, Option<Path>), Error> {
+        let entry = try!(self.get_entry(name));
+        Ok((entry.level(), entry.origin_path()))
+    }
+
     /// Iterate over all the config variables
     ///
     /// If `glob` is `Some`, then the iterator will only iterate over all
@@ -241,6 +270,82 @@ impl Config {
         }
     }
 
+    /// Get all of the variables within a section, keyed by the remainder of
+    /// their name after `prefix` and the following `.`.
+    ///
+    /// For example a prefix of `remote.origin` will return a map with keys
+    /// such as `url` and `fetch`. If a variable is set more than once, then
+    /// the value with the highest priority is returned. Call this on a
+    /// `snapshot` of the configuration to get a consistent view of it.
+    pub fn get_section(&self, prefix: &str)
+                       -> Result<BTreeMap<String, String>, Error> {
+        let mut glob = String::from_str("^");
+        for c in prefix.chars() {
+            if !c.is_alphanumeric() && c != '-' && c != '_' {
+                glob.push('\\');
+            }
+            glob.push(c);
+        }
+        glob.push_str("\\.");
+        let mut ret = BTreeMap::new();
+        for entry in &try!(self.entries(Some(glob.as_slice()))) {
+            let name = &entry.name_bytes()[prefix.len() + 1..];
+            let name = try!(str::from_utf8(name).map_err(|_| {
+                Error::from_str("configuration name is not valid utf8")
+            }));
+            let value = try!(entry.value().ok_or_else(|| {
+                Error::from_str("configuration value is not valid utf8")
+            }));
+            // entries are yielded from the lowest priority level to the
+            // highest, so later values replace earlier ones
+            ret.insert(name.to_string(), value.to_string());
+        }
+        Ok(ret)
+    }
+
+    /// Rewrite a url with the `url.<base>.insteadOf` variables of this
+    /// configuration, and first with the `url.<base>.pushInsteadOf` ones when
+    /// pushing, as git does before connecting to a remote.
+    ///
+    /// The longest prefix of the url which matches is replaced by its base.
+    /// Returns `None` if none matches. Libgit2 already applies these rewrites
+    /// to the urls of remotes as it creates or loads them, so this is for
+    /// urls which don't come from a remote, or for applying a configuration
+    /// other than the repository's.
+    pub fn rewrite_url(&self, url: &str, direction: Direction)
+                       -> Result<Option<String>, Error> {
+        match direction {
+            Direction::Push => {
+                match try!(self.rewrite_url_with(url, "pushinsteadof")) {
+                    Some(url) => return Ok(Some(url)),
+                    None => {}
+                }
+            }
+            Direction::Fetch => {}
+        }
+        self.rewrite_url_with(url, "insteadof")
+    }
+
+    fn rewrite_url_with(&self, url: &str, key: &str)
+                        -> Result<Option<String>, Error> {
+        let glob = format!("^url\\..*\\.{}$", key);
+        let mut best: Option<(usize, String)> = None;
+        for entry in &try!(self.entries(Some(glob.as_slice()))) {
+            let (name, prefix) = match (entry.name(), entry.value()) {
+                (Some(name), Some(prefix)) => (name, prefix),
+                _ => continue,
+            };
+            let longer = best.as_ref().map(|&(len, _)| prefix.len() > len)
+                             .unwrap_or(true);
+            if url.starts_with(prefix) && longer {
+                let base = &name[4..name.len() - key.len() - 1];
+                best = Some((prefix.len(),
+                             format!("{}{}", base, &url[prefix.len()..])));
+            }
+        }
+        Ok(best.map(|(_, url)| url))
+    }
+
     /// Open the global/XDG configuration file according to git's rules
     ///
     /// Git allows you to store your global configuration at `$HOME/.config` or
@@ -361,6 +466,21 @@ impl<'cfg> ConfigEntry<'cfg> {
     pub fn level(&self) -> ConfigLevel {
         unsafe { ConfigLevel::from_raw((*self.raw).level) }
     }
+
+    /// Gets the path of the file this entry was read from.
+    ///
+    /// Returns `None` if the entry does not come from a file.
+    pub fn origin_path(&self) -> Option<Path> {
+        unsafe {
+            ::opt_bytes(self, (*self.raw).origin_path).map(Path::new)
+        }
+    }
+
+    /// Gets how many levels of `include` directives were followed to reach
+    /// the file this entry was read from, 0 if it was not included.
+    pub fn include_depth(&self) -> u32 {
+        unsafe { (*self.raw).include_depth as u32 }
+    }
 }
 
 impl<'cfg> Binding for ConfigEntry<'cfg> {
@@ -370,12 +490,24 @@ impl<'cfg> Binding for ConfigEntry<'cfg> {
                            -> ConfigEntry<'cfg> {
         ConfigEntry {
             raw: raw,
+            owned: false,
             marker: marker::ContravariantLifetime,
         }
     }
     fn raw(&self) -> *const raw::git_config_entry { self.raw }
 }
 
+#[unsafe_destructor]
+impl<'cfg> Drop for ConfigEntry<'cfg> {
+    fn drop(&mut self) {
+        // entries looked up by name are copies owned by the caller, while
+        // those of an iterator belong to the iterator
+        if self.owned {
+            unsafe { raw::git_config_entry_free(self.raw as *mut _) }
+        }
+    }
+}
+
 impl<'cfg> Binding for ConfigEntries<'cfg> {
     type Raw = *mut raw::git_config_iterator;
 
@@ -418,7 +550,7 @@ impl<'cfg> Drop for ConfigEntries<'cfg> {
 #[cfg(test)]
 mod tests {
     use std::old_io::{TempDir, File};
-    use Config;
+    use {Config, ConfigLevel, Direction};
 
     #[test]
     fn smoke() {
@@ -455,4 +587,85 @@ mod tests {
             entry.level();
         }
     }
+
+    #[test]
+    fn section() {
+        let td = TempDir::new("test").unwrap();
+        let path = td.path().join("foo");
+        File::create(&path).write_str("\
+[remote \"origin\"]
+    url = https://example.com/foo
+    fetch = +refs/heads/*:refs/remotes/origin/*
+[remote \"origin2\"]
+    url = https://example.com/bar
+[remote \"a.b\"]
+    url = https://example.com/baz
+").unwrap();
+        let mut cfg = Config::open(&path).unwrap();
+
+        let section = cfg.get_section("remote.origin").unwrap();
+        assert_eq!(section.len(), 2);
+        let get = |k: &str| section.get(&k.to_string()).map(|s| s.as_slice());
+        assert_eq!(get("url"), Some("https://example.com/foo"));
+        assert_eq!(get("fetch"), Some("+refs/heads/*:refs/remotes/origin/*"));
+        let section = cfg.snapshot().unwrap().get_section("remote.a.b");
+        assert_eq!(section.unwrap().keys().collect::<Vec<_>>(),
+                   vec![&"url".to_string()]);
+        assert!(cfg.get_section("nope").unwrap().is_empty());
+    }
+
+    #[test]
+    fn origin() {
+        let td = TempDir::new("test").unwrap();
+        let path = td.path().join("foo");
+        let included = td.path().join("bar");
+        File::create(&path).write_str("\
+[foo]
+    k1 = a
+[include]
+    path = bar
+").unwrap();
+        File::create(&included).write_str("[foo]\n    k2 = b\n").unwrap();
+        let cfg = Config::open(&path).unwrap();
+
+        assert_eq!(cfg.get_entry_with_origin("foo.k1").unwrap(),
+                   (ConfigLevel::Local, Some(path)));
+        assert_eq!(cfg.get_entry_with_origin("foo.k2").unwrap(),
+                   (ConfigLevel::Local, Some(included.clone())));
+        let entry = cfg.get_entry("foo.k2").unwrap();
+        assert_eq!(entry.origin_path(), Some(included));
+        assert_eq!(entry.include_depth(), 1);
+        assert!(cfg.get_entry_with_origin("foo.k3").is_err());
+    }
+
+    #[test]
+    fn rewrite_url() {
+        let td = TempDir::new("test").unwrap();
+        let path = td.path().join("foo");
+        File::create(&path).write_str("\
+[url \"git@github.com:\"]
+    insteadOf = gh:
+    insteadOf = https://github.com/
+[url \"git@github.com:rust-lang/\"]
+    insteadOf = https://github.com/rust-lang/
+[url \"ssh://push.example.com/\"]
+    pushInsteadOf = https://example.com/
+").unwrap();
+        let cfg = Config::open(&path).unwrap();
+        let rewrite = |url: &str, dir: Direction| {
+            cfg.rewrite_url(url, dir).unwrap()
+        };
+
+        assert_eq!(rewrite("gh:foo/bar", Direction::Fetch),
+                   Some("git@github.com:foo/bar".to_string()));
+        assert_eq!(rewrite("https://github.com/foo/bar", Direction::Push),
+                   Some("git@github.com:foo/bar".to_string()));
+        assert_eq!(rewrite("https://github.com/rust-lang/cargo",
+                           Direction::Fetch),
+                   Some("git@github.com:rust-lang/cargo".to_string()));
+        assert_eq!(rewrite("https://example.com/a", Direction::Fetch), None);
+        assert_eq!(rewrite("https://example.com/a", Direction::Push),
+                   Some("ssh://push.example.com/a".to_string()));
+        assert_eq!(rewrite("/path/to/repo", Direction::Fetch), None);
+    }
 }
@@ -11,6 +11,9 @@ pub struct Cred {
     raw: *mut raw::git_cred,
 }
 
+// Credentials are plain data which is only ever freed by its owner.
+unsafe impl Send for Cred {}
+
 /// Management of the gitcredentials(7) interface.
 pub struct CredentialHelper {
     /// A public field representing the currently discovered username from
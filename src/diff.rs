@@ -5,8 +5,10 @@ use std::mem;
 use std::slice;
 use libc::{c_char, size_t, c_void, c_int};
 
-use {raw, panic, Buf, Delta, Oid, Repository, Tree, Error, Index, DiffFormat};
-use {DiffStatsFormat, IntoCString};
+use {raw, panic, Blob, Buf, Delta, Oid, Repository, Tree, Error, Index};
+use {Config, DiffAlgorithm, ErrorCode};
+use {DiffFormat, DiffStatsFormat, DiffBinaryKind, DiffFlags, FileMode};
+use {IntoCString};
 use util::Binding;
 
 /// The diff object that contains all individual file deltas.
@@ -34,14 +36,24 @@ pub struct DiffFile<'a> {
 }
 
 /// Structure describing options about how the diff should be executed.
-pub struct DiffOptions {
+pub struct DiffOptions<'cb> {
     pathspec: Vec<CString>,
     pathspec_ptrs: Vec<*const c_char>,
     old_prefix: Option<CString>,
     new_prefix: Option<CString>,
+    notify: Option<Box<NotifyCb<'cb>>>,
+    progress: Option<Box<ProgressCb<'cb>>>,
     raw: raw::git_diff_options,
 }
 
+/// Callback invoked with each delta before it is added to a diff, along with
+/// the pathspec that matched it. Returning `false` skips the delta.
+pub type NotifyCb<'a> = FnMut(DiffDelta, Option<&[u8]>) -> bool + 'a;
+
+/// Callback invoked with the old and new paths as a diff is being generated.
+/// Returning `false` aborts the diff.
+pub type ProgressCb<'a> = FnMut(Option<&[u8]>, Option<&[u8]>) -> bool + 'a;
+
 /// Control behavior of rename and copy detection
 pub struct DiffFindOptions {
     raw: raw::git_diff_find_options,
@@ -65,11 +77,59 @@ pub struct DiffHunk<'a> {
     marker: marker::ContravariantLifetime<'a>,
 }
 
+/// Structure describing the binary contents of a diff.
+pub struct DiffBinary<'a> {
+    raw: *const raw::git_diff_binary,
+    marker: marker::ContravariantLifetime<'a>,
+}
+
+/// The contents of one of the files in a binary diff.
+pub struct DiffBinaryFile<'a> {
+    raw: *const raw::git_diff_binary_file,
+    marker: marker::ContravariantLifetime<'a>,
+}
+
 /// Structure describing a hunk of a diff.
 pub struct DiffStats {
     raw: *mut raw::git_diff_stats,
 }
 
+/// Callback for each file in a diff, receiving the delta and the progress
+/// through the diff (a value between 0 and 1).
+pub type FileCb<'a> = FnMut(DiffDelta, f32) -> bool + 'a;
+/// Callback for the binary contents of a file in a diff.
+pub type BinaryCb<'a> = FnMut(DiffDelta, DiffBinary) -> bool + 'a;
+/// Callback for each hunk of a file in a diff.
+pub type HunkCb<'a> = FnMut(DiffDelta, DiffHunk) -> bool + 'a;
+/// Callback for each line of a file in a diff.
+pub type LineCb<'a> = FnMut(DiffDelta, Option<DiffHunk>, DiffLine) -> bool + 'a;
+
+struct ForeachCallbacks<'a, 'b: 'a, 'c, 'd: 'c, 'e, 'f: 'e, 'g, 'h: 'g> {
+    file: Option<&'a mut FileCb<'b>>,
+    binary: Option<&'c mut BinaryCb<'d>>,
+    hunk: Option<&'e mut HunkCb<'f>>,
+    line: Option<&'g mut LineCb<'h>>,
+}
+
+impl<'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h> ForeachCallbacks<'a, 'b, 'c, 'd,
+                                                      'e, 'f, 'g, 'h> {
+    /// Returns the raw callbacks to hand to libgit2, only registering those
+    /// for which a closure was provided.
+    fn raw(&self) -> (Option<raw::git_diff_file_cb>,
+                      Option<raw::git_diff_binary_cb>,
+                      Option<raw::git_diff_hunk_cb>,
+                      Option<raw::git_diff_line_cb>) {
+        let file: raw::git_diff_file_cb = file_cb_c;
+        let binary: raw::git_diff_binary_cb = binary_cb_c;
+        let hunk: raw::git_diff_hunk_cb = hunk_cb_c;
+        let line: raw::git_diff_line_cb = line_cb_c;
+        (if self.file.is_some() {Some(file)} else {None},
+         if self.binary.is_some() {Some(binary)} else {None},
+         if self.hunk.is_some() {Some(hunk)} else {None},
+         if self.line.is_some() {Some(line)} else {None})
+    }
+}
+
 impl Diff {
     /// Create a diff with the difference between two tree objects.
     ///
@@ -199,6 +259,24 @@ impl Diff {
         }
     }
 
+    /// Read the contents of a git patch file into a `Diff` object.
+    ///
+    /// The diff object produced is similar to the one that would be produced
+    /// if you actually produced it computationally by comparing two trees,
+    /// however there may be subtle differences. For example, a patch file
+    /// likely contains abbreviated object IDs, so the object IDs parsed by
+    /// this function will also be abbreviated.
+    pub fn from_buffer(buffer: &[u8]) -> Result<Diff, Error> {
+        ::init();
+        let mut ret = 0 as *mut raw::git_diff;
+        unsafe {
+            try_call!(raw::git_diff_from_buffer(&mut ret,
+                                                buffer.as_ptr() as *const c_char,
+                                                buffer.len() as size_t));
+            Ok(Binding::from_raw(ret))
+        }
+    }
+
     /// Merge one diff into another.
     ///
     /// This merges items from the "from" list into the "self" list.  The
@@ -264,6 +342,217 @@ impl Diff {
         }
     }
 
+    /// Loop over all deltas in a diff issuing callbacks.
+    ///
+    /// Each of the callbacks is only invoked if it is provided: the file
+    /// callback once per delta, and the binary, hunk and line callbacks as
+    /// the content of the delta is binary or text respectively. Returning
+    /// `false` from any callback will terminate the iteration and return an
+    /// error from this function.
+    ///
+    /// As each callback is a separate closure, they cannot all mutably borrow
+    /// the same state; see `foreach_with` for that.
+    pub fn foreach(&self,
+                   file_cb: Option<&mut FileCb>,
+                   binary_cb: Option<&mut BinaryCb>,
+                   hunk_cb: Option<&mut HunkCb>,
+                   line_cb: Option<&mut LineCb>) -> Result<(), Error> {
+        let mut cbs = ForeachCallbacks {
+            file: file_cb,
+            binary: binary_cb,
+            hunk: hunk_cb,
+            line: line_cb,
+        };
+        let (file_cb_c, binary_cb_c, hunk_cb_c, line_cb_c) = cbs.raw();
+        let ptr = &mut cbs as *mut _;
+        unsafe {
+            try_call!(raw::git_diff_foreach(self.raw, file_cb_c, binary_cb_c,
+                                            hunk_cb_c, line_cb_c,
+                                            ptr as *mut c_void));
+            Ok(())
+        }
+    }
+
+    /// Loop over all deltas in a diff issuing callbacks which share some
+    /// mutable state.
+    ///
+    /// This is the same as `foreach`, except that each callback is handed
+    /// `state` as its first argument. This allows, for example, the file
+    /// callback to record the current file and the line callback to update
+    /// counts for it, without wrapping the state in a `RefCell`.
+    pub fn foreach_with<S>(&self,
+                           state: &mut S,
+                           file_cb: Option<&mut FnMut(&mut S, DiffDelta, f32)
+                                                      -> bool>,
+                           binary_cb: Option<&mut FnMut(&mut S, DiffDelta,
+                                                        DiffBinary) -> bool>,
+                           hunk_cb: Option<&mut FnMut(&mut S, DiffDelta,
+                                                      DiffHunk) -> bool>,
+                           line_cb: Option<&mut FnMut(&mut S, DiffDelta,
+                                                      Option<DiffHunk>,
+                                                      DiffLine) -> bool>)
+                           -> Result<(), Error> {
+        // libgit2 only ever invokes one callback at a time, so each of them
+        // can be handed a unique borrow of the state in turn.
+        let state = state as *mut S;
+        let mut file = file_cb.map(|cb| {
+            move |delta: DiffDelta, progress: f32| {
+                cb(unsafe { &mut *state }, delta, progress)
+            }
+        });
+        let mut binary = binary_cb.map(|cb| {
+            move |delta: DiffDelta, binary: DiffBinary| {
+                cb(unsafe { &mut *state }, delta, binary)
+            }
+        });
+        let mut hunk = hunk_cb.map(|cb| {
+            move |delta: DiffDelta, hunk: DiffHunk| {
+                cb(unsafe { &mut *state }, delta, hunk)
+            }
+        });
+        let mut line = line_cb.map(|cb| {
+            move |delta: DiffDelta, hunk: Option<DiffHunk>, line: DiffLine| {
+                cb(unsafe { &mut *state }, delta, hunk, line)
+            }
+        });
+        self.foreach(file.as_mut().map(|f| f as &mut FileCb),
+                     binary.as_mut().map(|f| f as &mut BinaryCb),
+                     hunk.as_mut().map(|f| f as &mut HunkCb),
+                     line.as_mut().map(|f| f as &mut LineCb))
+    }
+
+    /// Directly run a diff on two blobs, issuing callbacks for the result.
+    ///
+    /// Compared to a file, a blob lacks some contextual information. As such,
+    /// the `DiffFile` given to the callbacks will have an invalid value for
+    /// its mode. The paths given are recorded in the deltas as though the
+    /// blobs lived at those paths.
+    ///
+    /// `None` is allowed for either blob and will be treated as an empty blob,
+    /// with the delta being reported as added or deleted accordingly. The
+    /// callbacks behave as they do for `Diff::foreach`, except that the file
+    /// callback is also optional.
+    pub fn blobs(old_blob: Option<&Blob>,
+                 old_as_path: Option<&str>,
+                 new_blob: Option<&Blob>,
+                 new_as_path: Option<&str>,
+                 opts: Option<&mut DiffOptions>,
+                 file_cb: Option<&mut FileCb>,
+                 binary_cb: Option<&mut BinaryCb>,
+                 hunk_cb: Option<&mut HunkCb>,
+                 line_cb: Option<&mut LineCb>) -> Result<(), Error> {
+        let old_as_path = old_as_path.map(|s| s.into_c_string());
+        let new_as_path = new_as_path.map(|s| s.into_c_string());
+        let mut cbs = ForeachCallbacks {
+            file: file_cb,
+            binary: binary_cb,
+            hunk: hunk_cb,
+            line: line_cb,
+        };
+        let (file_cb_c, binary_cb_c, hunk_cb_c, line_cb_c) = cbs.raw();
+        let ptr = &mut cbs as *mut _;
+        unsafe {
+            try_call!(raw::git_diff_blobs(old_blob.map(|s| s.raw()),
+                                          old_as_path,
+                                          new_blob.map(|s| s.raw()),
+                                          new_as_path,
+                                          opts.map(|s| s.raw()),
+                                          file_cb_c, binary_cb_c,
+                                          hunk_cb_c, line_cb_c,
+                                          ptr as *mut c_void));
+            Ok(())
+        }
+    }
+
+    /// Directly run a diff between a blob and a buffer, issuing callbacks for
+    /// the result.
+    ///
+    /// This is the same as `Diff::blobs` except that the new side of the diff
+    /// is given as raw data instead of a blob.
+    pub fn blob_to_buffer(old_blob: Option<&Blob>,
+                          old_as_path: Option<&str>,
+                          buffer: Option<&[u8]>,
+                          buffer_as_path: Option<&str>,
+                          opts: Option<&mut DiffOptions>,
+                          file_cb: Option<&mut FileCb>,
+                          binary_cb: Option<&mut BinaryCb>,
+                          hunk_cb: Option<&mut HunkCb>,
+                          line_cb: Option<&mut LineCb>) -> Result<(), Error> {
+        let old_as_path = old_as_path.map(|s| s.into_c_string());
+        let buffer_as_path = buffer_as_path.map(|s| s.into_c_string());
+        let (ptr, len) = match buffer {
+            Some(b) => (b.as_ptr() as *const c_char, b.len()),
+            None => (0 as *const c_char, 0),
+        };
+        let mut cbs = ForeachCallbacks {
+            file: file_cb,
+            binary: binary_cb,
+            hunk: hunk_cb,
+            line: line_cb,
+        };
+        let (file_cb_c, binary_cb_c, hunk_cb_c, line_cb_c) = cbs.raw();
+        let cbs = &mut cbs as *mut _;
+        unsafe {
+            try_call!(raw::git_diff_blob_to_buffer(old_blob.map(|s| s.raw()),
+                                                   old_as_path,
+                                                   ptr,
+                                                   len as size_t,
+                                                   buffer_as_path,
+                                                   opts.map(|s| s.raw()),
+                                                   file_cb_c, binary_cb_c,
+                                                   hunk_cb_c, line_cb_c,
+                                                   cbs as *mut c_void));
+            Ok(())
+        }
+    }
+
+    /// Directly run a diff between two buffers, issuing callbacks for the
+    /// result.
+    ///
+    /// This is the same as `Diff::blobs` except that both sides of the diff
+    /// are given as raw data, so nothing needs to be written to the object
+    /// database first.
+    pub fn buffers(old_buffer: Option<&[u8]>,
+                   old_as_path: Option<&str>,
+                   new_buffer: Option<&[u8]>,
+                   new_as_path: Option<&str>,
+                   opts: Option<&mut DiffOptions>,
+                   file_cb: Option<&mut FileCb>,
+                   binary_cb: Option<&mut BinaryCb>,
+                   hunk_cb: Option<&mut HunkCb>,
+                   line_cb: Option<&mut LineCb>) -> Result<(), Error> {
+        ::init();
+        let old_as_path = old_as_path.map(|s| s.into_c_string());
+        let new_as_path = new_as_path.map(|s| s.into_c_string());
+        let (old_ptr, old_len) = match old_buffer {
+            Some(b) => (b.as_ptr() as *const c_void, b.len()),
+            None => (0 as *const c_void, 0),
+        };
+        let (new_ptr, new_len) = match new_buffer {
+            Some(b) => (b.as_ptr() as *const c_void, b.len()),
+            None => (0 as *const c_void, 0),
+        };
+        let mut cbs = ForeachCallbacks {
+            file: file_cb,
+            binary: binary_cb,
+            hunk: hunk_cb,
+            line: line_cb,
+        };
+        let (file_cb_c, binary_cb_c, hunk_cb_c, line_cb_c) = cbs.raw();
+        let cbs = &mut cbs as *mut _;
+        unsafe {
+            try_call!(raw::git_diff_buffers(old_ptr, old_len as size_t,
+                                            old_as_path,
+                                            new_ptr, new_len as size_t,
+                                            new_as_path,
+                                            opts.map(|s| s.raw()),
+                                            file_cb_c, binary_cb_c,
+                                            hunk_cb_c, line_cb_c,
+                                            cbs as *mut c_void));
+            Ok(())
+        }
+    }
+
     /// Accumulate diff statistics for all patches.
     pub fn stats(&self) -> Result<DiffStats, Error> {
         let mut ret = 0 as *mut raw::git_diff_stats;
@@ -273,6 +562,20 @@ impl Diff {
         }
     }
 
+    /// Calculate the stable patch id of this diff, as `git patch-id` would.
+    ///
+    /// The patch id only depends on the changed lines of the diff and not on
+    /// line numbers or whitespace, so it can be used to find commits which
+    /// introduce the same change, such as a commit and its cherry-pick.
+    pub fn patchid(&self) -> Result<Oid, Error> {
+        let mut raw = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        unsafe {
+            try_call!(raw::git_diff_patchid(&mut raw, self.raw,
+                                            0 as *mut _));
+            Ok(Binding::from_raw(&raw as *const _))
+        }
+    }
+
     /// Transform a diff marking file renames, copies, etc.
     ///
     /// This modifies a diff in place, replacing old entries that look like
@@ -286,7 +589,76 @@ impl Diff {
         Ok(())
     }
 
-    // TODO: num_deltas_of_type, foreach, format_email, find_similar
+    // TODO: num_deltas_of_type
+}
+
+extern fn file_cb_c(delta: *const raw::git_diff_delta,
+                    progress: f32,
+                    data: *mut c_void) -> c_int {
+    unsafe {
+        let delta = Binding::from_raw(delta as *mut _);
+        let r = panic::wrap(|| {
+            let cbs = data as *mut ForeachCallbacks;
+            match (*cbs).file {
+                Some(ref mut cb) => cb(delta, progress),
+                None => false,
+            }
+        });
+        if r == Some(true) {0} else {-1}
+    }
+}
+
+extern fn binary_cb_c(delta: *const raw::git_diff_delta,
+                      binary: *const raw::git_diff_binary,
+                      data: *mut c_void) -> c_int {
+    unsafe {
+        let delta = Binding::from_raw(delta as *mut _);
+        let binary = Binding::from_raw(binary);
+        let r = panic::wrap(|| {
+            let cbs = data as *mut ForeachCallbacks;
+            match (*cbs).binary {
+                Some(ref mut cb) => cb(delta, binary),
+                None => false,
+            }
+        });
+        if r == Some(true) {0} else {-1}
+    }
+}
+
+extern fn hunk_cb_c(delta: *const raw::git_diff_delta,
+                    hunk: *const raw::git_diff_hunk,
+                    data: *mut c_void) -> c_int {
+    unsafe {
+        let delta = Binding::from_raw(delta as *mut _);
+        let hunk = Binding::from_raw(hunk);
+        let r = panic::wrap(|| {
+            let cbs = data as *mut ForeachCallbacks;
+            match (*cbs).hunk {
+                Some(ref mut cb) => cb(delta, hunk),
+                None => false,
+            }
+        });
+        if r == Some(true) {0} else {-1}
+    }
+}
+
+extern fn line_cb_c(delta: *const raw::git_diff_delta,
+                    hunk: *const raw::git_diff_hunk,
+                    line: *const raw::git_diff_line,
+                    data: *mut c_void) -> c_int {
+    unsafe {
+        let delta = Binding::from_raw(delta as *mut _);
+        let hunk = Binding::from_raw_opt(hunk);
+        let line = Binding::from_raw(line);
+        let r = panic::wrap(|| {
+            let cbs = data as *mut ForeachCallbacks;
+            match (*cbs).line {
+                Some(ref mut cb) => cb(delta, hunk, line),
+                None => false,
+            }
+        });
+        if r == Some(true) {0} else {-1}
+    }
 }
 
 impl Binding for Diff {
@@ -305,16 +677,25 @@ impl Drop for Diff {
 }
 
 impl<'a> DiffDelta<'a> {
-    // TODO: expose when diffs are more exposed
-    // pub fn similarity(&self) -> u16 {
-    //     unsafe { (*self.raw).similarity }
-    // }
+    /// Returns the similarity score of this delta, between 0 and 100.
+    ///
+    /// This is only filled in for renamed and copied deltas after
+    /// `Diff::find_similar` has been run (the `100` in `R100`).
+    pub fn similarity(&self) -> u16 {
+        unsafe { (*self.raw).similarity }
+    }
 
     /// Returns the number of files in this delta.
     pub fn nfiles(&self) -> u16 {
         unsafe { (*self.raw).nfiles }
     }
 
+    /// Returns the flags describing the content of this delta, such as
+    /// whether it is binary.
+    pub fn flags(&self) -> DiffFlags {
+        DiffFlags::from_bits_truncate(unsafe { (*self.raw).flags })
+    }
+
     /// Returns the status of this entry
     ///
     /// For more information, see `Delta`'s documentation
@@ -330,6 +711,7 @@ impl<'a> DiffDelta<'a> {
             raw::GIT_DELTA_UNTRACKED => Delta::Untracked,
             raw::GIT_DELTA_TYPECHANGE => Delta::Typechange,
             raw::GIT_DELTA_UNREADABLE => Delta::Unreadable,
+            raw::GIT_DELTA_CONFLICTED => Delta::Conflicted,
         }
     }
 
@@ -385,7 +767,50 @@ impl<'a> DiffFile<'a> {
     /// Returns the size of this entry, in bytes
     pub fn size(&self) -> u64 { unsafe { (*self.raw).size as u64 } }
 
-    // TODO: expose flags/mode
+    /// Returns the raw file mode of this entry, such as `0o100644`, or 0 if
+    /// this entry represents an absent side of a diff.
+    pub fn filemode_raw(&self) -> i32 { unsafe { (*self.raw).mode as i32 } }
+
+    /// Returns the flags describing this entry.
+    pub fn flags(&self) -> DiffFlags {
+        DiffFlags::from_bits_truncate(unsafe { (*self.raw).flags })
+    }
+
+    /// Returns `true` if this entry is treated as binary data.
+    pub fn is_binary(&self) -> bool {
+        self.flags().contains(::DIFF_FLAG_BINARY)
+    }
+
+    /// Returns `true` if this entry is treated as text data.
+    pub fn is_not_binary(&self) -> bool {
+        self.flags().contains(::DIFF_FLAG_NOT_BINARY)
+    }
+
+    /// Returns `true` if the id of this entry is known.
+    ///
+    /// The id of a file in the working directory is only computed when it is
+    /// needed, so it may be zeroes even though the file exists.
+    pub fn is_valid_id(&self) -> bool {
+        self.flags().contains(::DIFF_FLAG_VALID_ID)
+    }
+
+    /// Returns `true` if this entry exists on this side of the delta.
+    pub fn exists(&self) -> bool {
+        self.flags().contains(::DIFF_FLAG_EXISTS)
+    }
+
+    /// Returns the mode of this entry.
+    pub fn mode(&self) -> FileMode {
+        match unsafe { (*self.raw).mode } as u32 {
+            0o040000 => FileMode::Tree,
+            // group-writable blobs are a legacy mode git reads as a blob
+            0o100644 | 0o100664 => FileMode::Blob,
+            0o100755 => FileMode::BlobExecutable,
+            0o120000 => FileMode::Link,
+            0o160000 => FileMode::Commit,
+            _ => FileMode::Unreadable,
+        }
+    }
 }
 
 impl<'a> Binding for DiffFile<'a> {
@@ -399,18 +824,20 @@ impl<'a> Binding for DiffFile<'a> {
     fn raw(&self) -> *const raw::git_diff_file { self.raw }
 }
 
-impl DiffOptions {
+impl<'cb> DiffOptions<'cb> {
     /// Creates a new set of empty diff options.
     ///
     /// All flags and other options are defaulted to false or their otherwise
     /// zero equivalents.
-    pub fn new() -> DiffOptions {
+    pub fn new() -> DiffOptions<'cb> {
         let mut opts = DiffOptions {
             pathspec: Vec::new(),
             pathspec_ptrs: Vec::new(),
             raw: unsafe { mem::zeroed() },
             old_prefix: None,
             new_prefix: None,
+            notify: None,
+            progress: None,
         };
         assert_eq!(unsafe {
             raw::git_diff_init_options(&mut opts.raw, 1)
@@ -418,7 +845,7 @@ impl DiffOptions {
         opts
     }
 
-    fn flag(&mut self, opt: u32, val: bool) -> &mut DiffOptions {
+    fn flag(&mut self, opt: u32, val: bool) -> &mut DiffOptions<'cb> {
         if val {
             self.raw.flags |= opt;
         } else {
@@ -428,38 +855,38 @@ impl DiffOptions {
     }
 
     /// Flag indicating whether the sides of the diff will be reversed.
-    pub fn reverse(&mut self, reverse: bool) -> &mut DiffOptions {
+    pub fn reverse(&mut self, reverse: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_REVERSE, reverse)
     }
 
     /// Flag indicating whether ignored files are included.
-    pub fn include_ignored(&mut self, include: bool) -> &mut DiffOptions {
+    pub fn include_ignored(&mut self, include: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_INCLUDE_IGNORED, include)
     }
 
     /// Flag indicating whether ignored directories are traversed deeply or not.
-    pub fn recurse_ignored_dirs(&mut self, recurse: bool) -> &mut DiffOptions {
+    pub fn recurse_ignored_dirs(&mut self, recurse: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_RECURSE_IGNORED_DIRS, recurse)
     }
 
     /// Flag indicating whether untracked files are in the diff
-    pub fn include_untracked(&mut self, include: bool) -> &mut DiffOptions {
+    pub fn include_untracked(&mut self, include: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_INCLUDE_UNTRACKED, include)
     }
 
     /// Flag indicating whether untracked directories are deeply traversed or
     /// not.
-    pub fn recurse_untracked_dirs(&mut self, recurse: bool) -> &mut DiffOptions {
+    pub fn recurse_untracked_dirs(&mut self, recurse: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_RECURSE_UNTRACKED_DIRS, recurse)
     }
 
     /// Flag indicating whether unmodified files are in the diff.
-    pub fn include_unmodified(&mut self, include: bool) -> &mut DiffOptions {
+    pub fn include_unmodified(&mut self, include: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_INCLUDE_UNMODIFIED, include)
     }
 
     /// If entrabled, then Typechange delta records are generated.
-    pub fn include_typechange(&mut self, include: bool) -> &mut DiffOptions {
+    pub fn include_typechange(&mut self, include: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_INCLUDE_TYPECHANGE, include)
     }
 
@@ -468,35 +895,35 @@ impl DiffOptions {
     /// typechange record with the `new_file`'s mode set to tree.
     ///
     /// Note that the tree SHA will not be available.
-    pub fn include_typechange_trees(&mut self, include: bool) -> &mut DiffOptions {
+    pub fn include_typechange_trees(&mut self, include: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_INCLUDE_TYPECHANGE_TREES, include)
     }
 
     /// Flag indicating whether file mode changes are ignored.
-    pub fn ignore_filemode(&mut self, ignore: bool) -> &mut DiffOptions {
+    pub fn ignore_filemode(&mut self, ignore: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_IGNORE_FILEMODE, ignore)
     }
 
     /// Flag indicating whether all submodules should be treated as unmodified.
-    pub fn ignore_submodules(&mut self, ignore: bool) -> &mut DiffOptions {
+    pub fn ignore_submodules(&mut self, ignore: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_IGNORE_SUBMODULES, ignore)
     }
 
     /// Flag indicating whether case insensitive filenames should be used.
-    pub fn ignore_case(&mut self, ignore: bool) -> &mut DiffOptions {
+    pub fn ignore_case(&mut self, ignore: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_IGNORE_CASE, ignore)
     }
 
     /// If pathspecs are specified, this flag means that they should be applied
     /// as an exact match instead of a fnmatch pattern.
-    pub fn disable_pathspec_match(&mut self, disable: bool) -> &mut DiffOptions {
+    pub fn disable_pathspec_match(&mut self, disable: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_DISABLE_PATHSPEC_MATCH, disable)
     }
 
     /// Disable updating the `binary` flag in delta records. This is useful when
     /// iterating over a diff if you don't need hunk and data callbacks and want
     /// to avoid having to load a file completely.
-    pub fn skip_binary_check(&mut self, skip: bool) -> &mut DiffOptions {
+    pub fn skip_binary_check(&mut self, skip: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_SKIP_BINARY_CHECK, skip)
     }
 
@@ -509,7 +936,7 @@ impl DiffOptions {
     /// This flag turns off that scan and immediately labels an untracked
     /// directory as untracked (changing the behavior to not match core git).
     pub fn enable_fast_untracked_dirs(&mut self, enable: bool)
-                                      -> &mut DiffOptions {
+                                      -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_ENABLE_FAST_UNTRACKED_DIRS, enable)
     }
 
@@ -517,43 +944,43 @@ impl DiffOptions {
     /// different from the index, but the OID ends up being the same, write the
     /// correct stat information into the index. Note: without this flag, diff
     /// will always leave the index untouched.
-    pub fn update_index(&mut self, update: bool) -> &mut DiffOptions {
+    pub fn update_index(&mut self, update: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_UPDATE_INDEX, update)
     }
 
     /// Include unreadable files in the diff
-    pub fn include_unreadable(&mut self, include: bool) -> &mut DiffOptions {
+    pub fn include_unreadable(&mut self, include: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_INCLUDE_UNREADABLE, include)
     }
 
     /// Include unreadable files in the diff
     pub fn include_unreadable_as_untracked(&mut self, include: bool)
-                                           -> &mut DiffOptions {
+                                           -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_INCLUDE_UNREADABLE_AS_UNTRACKED, include)
     }
 
     /// Treat all files as text, disabling binary attributes and detection.
-    pub fn force_text(&mut self, force: bool) -> &mut DiffOptions {
+    pub fn force_text(&mut self, force: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_FORCE_TEXT, force)
     }
 
     /// Treat all files as binary, disabling text diffs
-    pub fn force_binary(&mut self, force: bool) -> &mut DiffOptions {
+    pub fn force_binary(&mut self, force: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_FORCE_TEXT, force)
     }
 
     /// Ignore all whitespace
-    pub fn ignore_whitespace(&mut self, ignore: bool) -> &mut DiffOptions {
+    pub fn ignore_whitespace(&mut self, ignore: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_IGNORE_WHITESPACE, ignore)
     }
 
     /// Ignore changes in the amount of whitespace
-    pub fn ignore_whitespace_change(&mut self, ignore: bool) -> &mut DiffOptions {
+    pub fn ignore_whitespace_change(&mut self, ignore: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_IGNORE_WHITESPACE_CHANGE, ignore)
     }
 
     /// Ignore whitespace at tend of line
-    pub fn ignore_whitespace_eol(&mut self, ignore: bool) -> &mut DiffOptions {
+    pub fn ignore_whitespace_eol(&mut self, ignore: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_IGNORE_WHITESPACE_EOL, ignore)
     }
 
@@ -562,7 +989,7 @@ impl DiffOptions {
     /// This automatically turns on `include_untracked` but it does not turn on
     /// `recurse_untracked_dirs`. Add that flag if you want the content of every
     /// single untracked file.
-    pub fn show_untracked_content(&mut self, show: bool) -> &mut DiffOptions {
+    pub fn show_untracked_content(&mut self, show: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_SHOW_UNTRACKED_CONTENT, show)
     }
 
@@ -570,23 +997,69 @@ impl DiffOptions {
     /// are included in the `Diff`. Normally these are skipped in the formats
     /// that list files (e.g. name-only, name-status, raw). Even with this these
     /// will not be included in the patch format.
-    pub fn show_unmodified(&mut self, show: bool) -> &mut DiffOptions {
+    pub fn show_unmodified(&mut self, show: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_SHOW_UNMODIFIED, show)
     }
 
     /// Use the "patience diff" algorithm
-    pub fn patience(&mut self, patience: bool) -> &mut DiffOptions {
+    pub fn patience(&mut self, patience: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_PATIENCE, patience)
     }
 
     /// Take extra time to find the minimal diff
-    pub fn minimal(&mut self, minimal: bool) -> &mut DiffOptions {
+    pub fn minimal(&mut self, minimal: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_MINIMAL, minimal)
     }
 
+    /// Set the algorithm used to generate the diff, replacing any earlier
+    /// `patience` or `minimal` setting.
+    pub fn algorithm(&mut self, algorithm: DiffAlgorithm)
+                     -> &mut DiffOptions<'cb> {
+        self.flag(raw::GIT_DIFF_PATIENCE, algorithm == DiffAlgorithm::Patience);
+        self.flag(raw::GIT_DIFF_MINIMAL, algorithm == DiffAlgorithm::Minimal)
+    }
+
+    /// Shift the boundaries of changed blocks of lines to where they are
+    /// easiest to read, based on the indentation of the surrounding lines,
+    /// as with `git diff --indent-heuristic`.
+    pub fn indent_heuristic(&mut self, on: bool) -> &mut DiffOptions<'cb> {
+        self.flag(raw::GIT_DIFF_INDENT_HEURISTIC, on)
+    }
+
+    /// Set the algorithm and the indent heuristic from the `diff.algorithm`
+    /// and `diff.indentHeuristic` configuration, so that patches are laid out
+    /// like those of `git diff`.
+    ///
+    /// As with git, the indent heuristic is on unless it is disabled. The
+    /// histogram algorithm is not implemented by libgit2, so
+    /// `diff.algorithm=histogram` selects the patience algorithm, which it
+    /// extends and agrees with on most inputs.
+    pub fn algorithm_from_config(&mut self, config: &Config)
+                                 -> Result<&mut DiffOptions<'cb>, Error> {
+        let algorithm = match config.get_str("diff.algorithm") {
+            Ok("default") | Ok("myers") => DiffAlgorithm::Myers,
+            Ok("minimal") => DiffAlgorithm::Minimal,
+            Ok("patience") | Ok("histogram") => DiffAlgorithm::Patience,
+            Ok(s) => {
+                return Err(Error::from_str(format!("unknown diff algorithm \
+                                                    '{}'", s).as_slice()))
+            }
+            Err(ref e) if e.code() == ErrorCode::NotFound => {
+                DiffAlgorithm::Myers
+            }
+            Err(e) => return Err(e),
+        };
+        let indent = match config.get_bool("diff.indentHeuristic") {
+            Ok(b) => b,
+            Err(ref e) if e.code() == ErrorCode::NotFound => true,
+            Err(e) => return Err(e),
+        };
+        Ok(self.algorithm(algorithm).indent_heuristic(indent))
+    }
+
     /// Include the necessary deflate/delta information so that `git-apply` can
     /// apply given diff information to binary files.
-    pub fn show_binary(&mut self, show: bool) -> &mut DiffOptions {
+    pub fn show_binary(&mut self, show: bool) -> &mut DiffOptions<'cb> {
         self.flag(raw::GIT_DIFF_SHOW_BINARY, show)
     }
 
@@ -594,7 +1067,7 @@ impl DiffOptions {
     /// (and to display before and after).
     ///
     /// The default value for this is 3.
-    pub fn context_lines(&mut self, lines: u32) -> &mut DiffOptions {
+    pub fn context_lines(&mut self, lines: u32) -> &mut DiffOptions<'cb> {
         self.raw.context_lines = lines;
         self
     }
@@ -603,13 +1076,13 @@ impl DiffOptions {
     /// the hunks will be merged into one.
     ///
     /// The default value for this is 0.
-    pub fn interhunk_lines(&mut self, lines: u32) -> &mut DiffOptions {
+    pub fn interhunk_lines(&mut self, lines: u32) -> &mut DiffOptions<'cb> {
         self.raw.interhunk_lines = lines;
         self
     }
 
     /// The default value for this is `core.abbrev` or 7 if unset.
-    pub fn id_abbrev(&mut self, abbrev: u16) -> &mut DiffOptions {
+    pub fn id_abbrev(&mut self, abbrev: u16) -> &mut DiffOptions<'cb> {
         self.raw.id_abbrev = abbrev;
         self
     }
@@ -620,7 +1093,7 @@ impl DiffOptions {
     /// A negative value will disable this entirely.
     ///
     /// The default value for this is 512MB.
-    pub fn max_size(&mut self, size: i64) -> &mut DiffOptions {
+    pub fn max_size(&mut self, size: i64) -> &mut DiffOptions<'cb> {
         self.raw.max_size = size as raw::git_off_t;
         self
     }
@@ -628,7 +1101,7 @@ impl DiffOptions {
     /// The virtual "directory" to prefix old file names with in hunk headers.
     ///
     /// The default value for this is "a".
-    pub fn old_prefix<T: IntoCString>(&mut self, t: T) -> &mut DiffOptions {
+    pub fn old_prefix<T: IntoCString>(&mut self, t: T) -> &mut DiffOptions<'cb> {
         self.old_prefix = Some(t.into_c_string());
         self
     }
@@ -636,20 +1109,44 @@ impl DiffOptions {
     /// The virtual "directory" to prefix new file names with in hunk headers.
     ///
     /// The default value for this is "b".
-    pub fn new_prefix<T: IntoCString>(&mut self, t: T) -> &mut DiffOptions {
+    pub fn new_prefix<T: IntoCString>(&mut self, t: T) -> &mut DiffOptions<'cb> {
         self.new_prefix = Some(t.into_c_string());
         self
     }
 
     /// Add to the array of paths/fnmatch patterns to constrain the diff.
     pub fn pathspec<T: IntoCString>(&mut self, pathspec: T)
-                                       -> &mut DiffOptions {
+                                       -> &mut DiffOptions<'cb> {
         let s = pathspec.into_c_string();
         self.pathspec_ptrs.push(s.as_ptr());
         self.pathspec.push(s);
         self
     }
 
+    /// Set a callback which is invoked as each delta is about to be added to
+    /// the diff.
+    ///
+    /// The callback receives the delta along with the pathspec which matched
+    /// it, if any. Returning `false` will skip the delta so it does not appear
+    /// in the generated diff, while `true` will include it.
+    pub fn notify<F>(&mut self, cb: F) -> &mut DiffOptions<'cb>
+                     where F: FnMut(DiffDelta, Option<&[u8]>) -> bool + 'cb {
+        self.notify = Some(Box::new(cb) as Box<NotifyCb<'cb>>);
+        self
+    }
+
+    /// Set a callback which is invoked periodically as the diff is being
+    /// generated, receiving the old and new paths currently being examined.
+    ///
+    /// Returning `false` will abort the generation of the diff and cause an
+    /// error to be returned from the function generating it.
+    pub fn progress<F>(&mut self, cb: F) -> &mut DiffOptions<'cb>
+                       where F: FnMut(Option<&[u8]>, Option<&[u8]>) -> bool
+                                + 'cb {
+        self.progress = Some(Box::new(cb) as Box<ProgressCb<'cb>>);
+        self
+    }
+
     /// Acquire a pointer to the underlying raw options.
     ///
     /// This function is unsafe as the pointer is only valid so long as this
@@ -661,10 +1158,58 @@ impl DiffOptions {
                                   .unwrap_or(0 as *const _);
         self.raw.pathspec.count = self.pathspec_ptrs.len() as size_t;
         self.raw.pathspec.strings = self.pathspec_ptrs.as_ptr() as *mut _;
+        if self.notify.is_some() {
+            let f: raw::git_diff_notify_cb = notify_cb;
+            self.raw.notify_cb = Some(f);
+        }
+        if self.progress.is_some() {
+            let f: raw::git_diff_progress_cb = progress_cb;
+            self.raw.progress_cb = Some(f);
+        }
+        self.raw.payload = self as *mut _ as *mut _;
         &self.raw as *const _
     }
 
-    // TODO: expose ignore_submodules, notify_cb/notify_payload
+    // TODO: expose ignore_submodules
+}
+
+extern fn notify_cb(_diff_so_far: *const raw::git_diff,
+                    delta: *const raw::git_diff_delta,
+                    matched_pathspec: *const c_char,
+                    data: *mut c_void) -> c_int {
+    unsafe {
+        let payload = &mut *(data as *mut DiffOptions);
+        let callback = match payload.notify {
+            Some(ref mut c) => c,
+            None => return 0,
+        };
+        let delta = Binding::from_raw(delta as *mut _);
+        let pathspec = ::opt_bytes(&data, matched_pathspec);
+        match panic::wrap(|| callback(delta, pathspec)) {
+            Some(true) => 0,
+            Some(false) => 1,
+            None => -1,
+        }
+    }
+}
+
+extern fn progress_cb(_diff_so_far: *const raw::git_diff,
+                      old_path: *const c_char,
+                      new_path: *const c_char,
+                      data: *mut c_void) -> c_int {
+    unsafe {
+        let payload = &mut *(data as *mut DiffOptions);
+        let callback = match payload.progress {
+            Some(ref mut c) => c,
+            None => return 0,
+        };
+        let old_path = ::opt_bytes(&data, old_path);
+        let new_path = ::opt_bytes(&data, new_path);
+        match panic::wrap(|| callback(old_path, new_path)) {
+            Some(true) => 0,
+            _ => -1,
+        }
+    }
 }
 
 impl<'diff> Iterator for Deltas<'diff> {
@@ -792,6 +1337,75 @@ impl<'a> Binding for DiffHunk<'a> {
     fn raw(&self) -> *const raw::git_diff_hunk { self.raw }
 }
 
+impl<'a> DiffBinary<'a> {
+    /// Returns whether there is data in this binary structure or not.
+    ///
+    /// If this is `true`, then this was produced and included binary content.
+    /// If this is `false` then this was generated knowing only that a binary
+    /// file changed but without providing the data, probably from a patch
+    /// that said `Binary files a/file.txt and b/file.txt differ`.
+    pub fn contains_data(&self) -> bool {
+        unsafe { (*self.raw).contains_data == 1 }
+    }
+
+    /// The contents of the old file.
+    pub fn old_file(&self) -> DiffBinaryFile<'a> {
+        unsafe { Binding::from_raw(&(*self.raw).old_file as *const _) }
+    }
+
+    /// The contents of the new file.
+    pub fn new_file(&self) -> DiffBinaryFile<'a> {
+        unsafe { Binding::from_raw(&(*self.raw).new_file as *const _) }
+    }
+}
+
+impl<'a> Binding for DiffBinary<'a> {
+    type Raw = *const raw::git_diff_binary;
+    unsafe fn from_raw(raw: *const raw::git_diff_binary) -> DiffBinary<'a> {
+        DiffBinary {
+            raw: raw,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+    fn raw(&self) -> *const raw::git_diff_binary { self.raw }
+}
+
+impl<'a> DiffBinaryFile<'a> {
+    /// The type of binary data for this file
+    pub fn kind(&self) -> DiffBinaryKind {
+        match unsafe { (*self.raw).kind } {
+            raw::GIT_DIFF_BINARY_NONE => DiffBinaryKind::None,
+            raw::GIT_DIFF_BINARY_LITERAL => DiffBinaryKind::Literal,
+            raw::GIT_DIFF_BINARY_DELTA => DiffBinaryKind::Delta,
+        }
+    }
+
+    /// The binary data, deflated
+    pub fn data(&self) -> &[u8] {
+        unsafe {
+            slice::from_raw_parts((*self.raw).data as *const u8,
+                                  (*self.raw).datalen as usize)
+        }
+    }
+
+    /// The length of the binary data after inflation
+    pub fn inflated_len(&self) -> usize {
+        unsafe { (*self.raw).inflatedlen as usize }
+    }
+}
+
+impl<'a> Binding for DiffBinaryFile<'a> {
+    type Raw = *const raw::git_diff_binary_file;
+    unsafe fn from_raw(raw: *const raw::git_diff_binary_file)
+                       -> DiffBinaryFile<'a> {
+        DiffBinaryFile {
+            raw: raw,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+    fn raw(&self) -> *const raw::git_diff_binary_file { self.raw }
+}
+
 impl DiffStats {
     /// Get the total number of files chaned in a diff.
     pub fn files_changed(&self) -> usize {
@@ -898,10 +1512,16 @@ impl DiffFindOptions {
     }
 
     /// Actually split large rewrites into delete/add pairs
-    pub fn break_rewries(&mut self, find: bool) -> &mut DiffFindOptions {
+    pub fn break_rewrites(&mut self, find: bool) -> &mut DiffFindOptions {
         self.flag(raw::GIT_DIFF_BREAK_REWRITES, find)
     }
 
+    /// Misspelled former name of `break_rewrites`.
+    #[deprecated]
+    pub fn break_rewries(&mut self, find: bool) -> &mut DiffFindOptions {
+        self.break_rewrites(find)
+    }
+
     /// Find renames/copies for untracked items in working directory.
     ///
     /// For this to work correctly use the `include_untracked` option when the
@@ -996,12 +1616,79 @@ impl DiffFindOptions {
         self
     }
 
+    /// Acquire a pointer to the underlying raw options.
+    ///
+    /// This function is unsafe as the pointer is only valid so long as this
+    /// structure is not moved, modified, or used elsewhere.
+    pub unsafe fn raw(&mut self) -> *const raw::git_diff_find_options {
+        &self.raw as *const _
+    }
+
     // TODO: expose git_diff_similarity_metric
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Diff;
+    use std::old_io::{fs, File};
+    use {Delta, DiffBinaryKind, DiffFormat, DIFF_STATS_FULL, DIFF_STATS_SHORT};
+    use std::str;
+    use {raw, DiffAlgorithm, FileMode, Patch};
+    use super::{Diff, DiffOptions, DiffFindOptions};
+
+    #[test]
+    fn algorithm() {
+        let (_td, repo) = ::test::repo_init();
+        let algorithm = |opts: &DiffOptions| {
+            let flags = opts.raw.flags;
+            (flags & raw::GIT_DIFF_PATIENCE != 0,
+             flags & raw::GIT_DIFF_MINIMAL != 0,
+             flags & raw::GIT_DIFF_INDENT_HEURISTIC != 0)
+        };
+        let mut opts = DiffOptions::new();
+        opts.algorithm(DiffAlgorithm::Patience);
+        assert_eq!(algorithm(&opts), (true, false, false));
+        opts.algorithm(DiffAlgorithm::Minimal).indent_heuristic(true);
+        assert_eq!(algorithm(&opts), (false, true, true));
+
+        let mut config = repo.config().unwrap();
+        let mut opts = DiffOptions::new();
+        opts.patience(true);
+        opts.algorithm_from_config(&config).unwrap();
+        assert_eq!(algorithm(&opts), (false, false, true));
+        config.set_str("diff.algorithm", "histogram").unwrap();
+        config.set_bool("diff.indentHeuristic", false).unwrap();
+        opts.algorithm_from_config(&config).unwrap();
+        assert_eq!(algorithm(&opts), (true, false, false));
+        config.set_str("diff.algorithm", "minimal").unwrap();
+        opts.algorithm_from_config(&config).unwrap();
+        assert_eq!(algorithm(&opts), (false, true, false));
+        config.set_str("diff.algorithm", "bogus").unwrap();
+        assert!(opts.algorithm_from_config(&config).is_err());
+
+        // the options are honored when generating a diff
+        opts.algorithm(DiffAlgorithm::Patience).indent_heuristic(true);
+        Diff::tree_to_workdir(&repo, None, Some(&mut opts)).unwrap();
+    }
+
+    #[test]
+    fn algorithm_output() {
+        // moving a rule past another one is where patience, anchored on the
+        // unique selector lines, and myers lay out the hunk differently
+        let old = b".foo1 {\n    margin: 0;\n}\n\n.bar {\n    margin: 0;\n}\n";
+        let new = b".bar {\n    margin: 0;\n}\n\n.foo1 {\n    margin: 0;\n\
+                    \x20   color: green;\n}\n";
+        let patch = |algorithm| {
+            let mut opts = DiffOptions::new();
+            opts.algorithm(algorithm);
+            let mut patch = Patch::from_buffers(old, None, new, None,
+                                                Some(&mut opts)).unwrap();
+            patch.to_buf().unwrap().to_vec()
+        };
+        let myers = patch(DiffAlgorithm::Myers);
+        assert!(str::from_utf8(myers.as_slice()).unwrap()
+                    .contains("-.foo1 {\n+.bar {\n"));
+        assert!(myers != patch(DiffAlgorithm::Patience));
+    }
 
     #[test]
     fn smoke() {
@@ -1013,4 +1700,353 @@ mod tests {
         assert_eq!(stats.deletions(), 0);
         assert_eq!(stats.files_changed(), 0);
     }
+
+    #[test]
+    fn workdir_and_index() {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join("foo")).write_str("foo\n").unwrap();
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true).context_lines(1);
+
+        let diff = repo.diff_index_to_workdir(None, Some(&mut opts)).unwrap();
+        assert_eq!(diff.deltas().len(), 1);
+        assert_eq!(diff.get_delta(0).unwrap().new_file().path(),
+                   Some(Path::new("foo")));
+
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("foo")).unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let head = repo.find_commit(head).unwrap().tree().unwrap();
+        let diff = repo.diff_tree_to_index(Some(&head), Some(&index),
+                                           None).unwrap();
+        assert_eq!(diff.deltas().len(), 1);
+        let diff = repo.diff_tree_to_workdir_with_index(Some(&head),
+                                                        None).unwrap();
+        assert_eq!(diff.deltas().len(), 1);
+
+        let id = index.write_tree().unwrap();
+        let tree = repo.find_tree(id).unwrap();
+        let mut opts = DiffOptions::new();
+        opts.pathspec("bar");
+        let diff = repo.diff_tree_to_tree(Some(&head), Some(&tree),
+                                          Some(&mut opts)).unwrap();
+        assert_eq!(diff.deltas().len(), 0);
+        let diff = repo.diff_tree_to_tree(Some(&head), Some(&tree),
+                                          None).unwrap();
+        assert_eq!(diff.deltas().len(), 1);
+    }
+
+    #[test]
+    fn patchid() {
+        let (td, repo) = ::test::repo_init();
+        let mut index = repo.index().unwrap();
+        let mut tree = |name: &str, contents: &str| {
+            File::create(&td.path().join(name)).write_str(contents).unwrap();
+            index.add_path(&Path::new(name)).unwrap();
+            repo.find_tree(index.write_tree().unwrap()).unwrap()
+        };
+        let t1 = tree("foo", "a\nb\nc\nd\n");
+        let t2 = tree("foo", "a\nb\nc\nd\ne\n");
+        let t3 = tree("foo", "x\na\nb\nc\nd\n");
+        let t4 = tree("foo", "x\na\nb\nc\nd\ne\n");
+        let id = |a: &::Tree, b: &::Tree| {
+            repo.diff_tree_to_tree(Some(a), Some(b), None).unwrap()
+                .patchid().unwrap()
+        };
+
+        // the same change at a different offset in the file
+        assert_eq!(id(&t1, &t2), id(&t3, &t4));
+        assert!(id(&t1, &t2) != id(&t2, &t4));
+    }
+
+    #[test]
+    fn print_and_stats() {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join("foo")).write_str("a\nb\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("foo")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let head = repo.find_commit(head).unwrap().tree().unwrap();
+        let diff = repo.diff_tree_to_tree(Some(&head), Some(&tree),
+                                          None).unwrap();
+
+        let mut lines = Vec::new();
+        diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
+            lines.push(line.origin());
+            true
+        }).unwrap();
+        assert_eq!(lines.iter().filter(|c| **c == '+').count(), 2);
+
+        let mut names = Vec::new();
+        diff.print(DiffFormat::NameOnly, |_delta, hunk, line| {
+            assert!(hunk.is_none());
+            names.extend(line.content().iter().cloned());
+            true
+        }).unwrap();
+        assert_eq!(names, b"foo\n");
+
+        let mut status = Vec::new();
+        diff.print(DiffFormat::NameStatus, |_delta, _hunk, line| {
+            status.extend(line.content().iter().cloned());
+            true
+        }).unwrap();
+        assert_eq!(status, b"A\tfoo\n");
+
+        let delta = diff.get_delta(0).unwrap();
+        assert_eq!(delta.status(), Delta::Added);
+        let (old, new) = (delta.old_file(), delta.new_file());
+        assert!(!old.exists() && new.exists());
+        assert!(old.id().is_zero());
+        assert_eq!(old.mode(), FileMode::Unreadable);
+        assert_eq!(new.mode(), FileMode::Blob);
+        assert!(new.is_valid_id());
+        assert_eq!(new.id(), index.get_path(&Path::new("foo"), 0).unwrap().id);
+        assert!(new.is_not_binary() && !new.is_binary());
+
+        for format in [DiffFormat::PatchHeader, DiffFormat::Raw].iter() {
+            let mut n = 0;
+            diff.print(*format, |_, _, _| { n += 1; true }).unwrap();
+            assert!(n > 0);
+        }
+        assert!(diff.print(DiffFormat::Patch, |_, _, _| false).is_err());
+
+        let stats = diff.stats().unwrap();
+        assert_eq!(stats.files_changed(), 1);
+        assert_eq!(stats.insertions(), 2);
+        assert_eq!(stats.deletions(), 0);
+        let buf = stats.to_buf(DIFF_STATS_SHORT, 80).unwrap();
+        assert_eq!(buf.as_str().unwrap().trim(),
+                   "1 file changed, 2 insertions(+)");
+        let buf = stats.to_buf(DIFF_STATS_FULL, 80).unwrap();
+        assert!(buf.as_str().unwrap().contains("foo | 2 ++"));
+    }
+
+    #[test]
+    fn find_similar() {
+        let (td, repo) = ::test::repo_init();
+        let contents = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        File::create(&td.path().join("foo")).write_str(contents).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("foo")).unwrap();
+        let old = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        index.remove_path(&Path::new("foo")).unwrap();
+        File::create(&td.path().join("bar")).write_str(contents).unwrap();
+        index.add_path(&Path::new("bar")).unwrap();
+        let new = repo.find_tree(index.write_tree().unwrap()).unwrap();
+
+        let mut diff = repo.diff_tree_to_tree(Some(&old), Some(&new),
+                                              None).unwrap();
+        assert_eq!(diff.deltas().len(), 2);
+        let mut opts = DiffFindOptions::new();
+        opts.renames(true).rename_threshold(90);
+        diff.find_similar(Some(&mut opts)).unwrap();
+        assert_eq!(diff.deltas().len(), 1);
+        let delta = diff.get_delta(0).unwrap();
+        assert_eq!(delta.status(), Delta::Renamed);
+        assert_eq!(delta.similarity(), 100);
+        assert_eq!(delta.old_file().path(), Some(Path::new("foo")));
+        assert_eq!(delta.new_file().path(), Some(Path::new("bar")));
+
+        fs::unlink(&td.path().join("foo")).unwrap();
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true);
+        let mut diff = repo.diff_tree_to_workdir(Some(&old),
+                                                 Some(&mut opts)).unwrap();
+        assert_eq!(diff.deltas().len(), 2);
+        let mut opts = DiffFindOptions::new();
+        opts.renames(true).for_untracked(true);
+        diff.find_similar(Some(&mut opts)).unwrap();
+        assert_eq!(diff.deltas().len(), 1);
+        assert_eq!(diff.get_delta(0).unwrap().status(), Delta::Renamed);
+    }
+
+    #[test]
+    fn from_buffer() {
+        let patch = b"diff --git a/foo b/foo
+index 7898192..6178079 100644
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-a
++b
+diff --git a/bar b/bar
+new file mode 100644
+index 0000000..e69de29
+";
+        let diff = Diff::from_buffer(patch).unwrap();
+        assert_eq!(diff.deltas().len(), 2);
+        let delta = diff.get_delta(0).unwrap();
+        assert_eq!(delta.status(), Delta::Modified);
+        assert_eq!(delta.old_file().path(), Some(Path::new("foo")));
+        assert_eq!(diff.get_delta(1).unwrap().status(), Delta::Added);
+        let stats = diff.stats().unwrap();
+        assert_eq!(stats.insertions(), 1);
+        assert_eq!(stats.deletions(), 1);
+    }
+
+    #[test]
+    fn foreach_binary() {
+        let (td, repo) = ::test::repo_init();
+        let data = [0u8, 1, 2, 3, 0, 4, 5];
+        File::create(&td.path().join("foo")).write_all(&data).unwrap();
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true).show_untracked_content(true)
+            .show_binary(true);
+        let diff = repo.diff_tree_to_workdir(None, Some(&mut opts)).unwrap();
+
+        let mut files = 0;
+        let mut binaries = Vec::new();
+        let mut hunks = 0;
+        diff.foreach(Some(&mut |_delta, _progress| { files += 1; true }),
+                     Some(&mut |delta, binary| {
+                         assert!(delta.new_file().path().is_some());
+                         assert!(delta.flags().contains(::DIFF_FLAG_BINARY));
+                         assert!(binary.contains_data());
+                         let new = binary.new_file();
+                         binaries.push((new.kind(), new.inflated_len(),
+                                        new.data().len()));
+                         true
+                     }),
+                     Some(&mut |_delta, _hunk| { hunks += 1; true }),
+                     None).unwrap();
+        assert_eq!(files, 1);
+        assert_eq!(hunks, 0);
+        assert_eq!(binaries.len(), 1);
+        assert_eq!(binaries[0].0, DiffBinaryKind::Literal);
+        assert_eq!(binaries[0].1, data.len());
+        assert!(binaries[0].2 > 0);
+
+        let mut text = Vec::new();
+        diff.print(DiffFormat::Patch, |_, _, line| {
+            text.extend(line.content().iter().cloned());
+            true
+        }).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("GIT binary patch"));
+
+        assert!(diff.foreach(Some(&mut |_, _| false), None, None, None)
+                    .is_err());
+    }
+
+    #[test]
+    fn foreach_with_state() {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join("foo")).write_str("a\nb\nc\n").unwrap();
+        File::create(&td.path().join("bar")).write_str("a\n").unwrap();
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true).show_untracked_content(true);
+        let diff = repo.diff_tree_to_workdir(None, Some(&mut opts)).unwrap();
+
+        // (path, lines) for each file, with lines counted into the last one
+        let mut counts: Vec<(Path, usize)> = Vec::new();
+        diff.foreach_with(&mut counts,
+                          Some(&mut |counts, delta, _| {
+                              let path = delta.new_file().path().unwrap();
+                              counts.push((path, 0));
+                              true
+                          }),
+                          None, None,
+                          Some(&mut |counts, _, _, _| {
+                              counts.last_mut().unwrap().1 += 1;
+                              true
+                          })).unwrap();
+        assert_eq!(counts, vec![(Path::new("bar"), 1), (Path::new("foo"), 3)]);
+
+        let mut files = 0;
+        assert!(diff.foreach_with(&mut files,
+                                  Some(&mut |files, _, _| {
+                                      *files += 1;
+                                      false
+                                  }),
+                                  None, None, None).is_err());
+        assert_eq!(files, 1);
+    }
+
+    #[test]
+    fn blobs_and_buffers() {
+        let (_td, repo) = ::test::repo_init();
+        let old = repo.find_blob(repo.blob(b"a\nb\n").unwrap()).unwrap();
+        let new = repo.find_blob(repo.blob(b"a\nc\n").unwrap()).unwrap();
+
+        let mut paths = Vec::new();
+        let mut lines = Vec::new();
+        Diff::blobs(Some(&old), Some("foo"), Some(&new), Some("foo"), None,
+                    Some(&mut |delta, _| {
+                        paths.push(delta.new_file().path().unwrap());
+                        true
+                    }),
+                    None, None,
+                    Some(&mut |_, _, line| {
+                        lines.push(line.origin());
+                        true
+                    })).unwrap();
+        assert_eq!(paths, vec![Path::new("foo")]);
+        assert_eq!(lines, vec![' ', '-', '+']);
+
+        let mut status = None;
+        Diff::blobs(None, None, Some(&new), Some("bar"), None,
+                    Some(&mut |delta, _| {
+                        status = Some(delta.status());
+                        true
+                    }),
+                    None, None, None).unwrap();
+        assert_eq!(status, Some(Delta::Added));
+
+        let mut hunks = 0;
+        Diff::blob_to_buffer(Some(&old), None, Some(b"a\nb\nc\n"), None,
+                             None, None, None,
+                             Some(&mut |_, hunk| {
+                                 assert_eq!(hunk.new_lines(), 3);
+                                 hunks += 1;
+                                 true
+                             }), None).unwrap();
+        assert_eq!(hunks, 1);
+
+        let mut opts = DiffOptions::new();
+        opts.context_lines(0);
+        let mut lines = Vec::new();
+        Diff::buffers(Some(b"1\n2\n3\n"), None, Some(b"1\n3\n"), None,
+                      Some(&mut opts), None, None, None,
+                      Some(&mut |_, _, line| {
+                          lines.push((line.origin(), line.old_lineno()));
+                          true
+                      })).unwrap();
+        assert_eq!(lines, vec![('-', Some(2))]);
+    }
+
+    #[test]
+    fn notify_and_progress() {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join("foo")).write_str("foo\n").unwrap();
+        File::create(&td.path().join("bar")).write_str("bar\n").unwrap();
+
+        let mut seen = Vec::new();
+        let mut progress = 0;
+        {
+            let mut opts = DiffOptions::new();
+            opts.include_untracked(true).pathspec("*o*").pathspec("bar");
+            opts.notify(|delta, pathspec| {
+                seen.push(pathspec.unwrap().to_vec());
+                delta.new_file().path() != Some(Path::new("bar"))
+            });
+            opts.progress(|_old, new| {
+                assert!(new.is_some());
+                progress += 1;
+                true
+            });
+            let diff = repo.diff_index_to_workdir(None,
+                                                  Some(&mut opts)).unwrap();
+            assert_eq!(diff.deltas().len(), 1);
+            assert_eq!(diff.get_delta(0).unwrap().new_file().path(),
+                       Some(Path::new("foo")));
+        }
+        seen.sort();
+        assert_eq!(seen, vec![b"*o*".to_vec(), b"bar".to_vec()]);
+        assert!(progress > 0);
+
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true).progress(|_, _| false);
+        assert!(repo.diff_index_to_workdir(None, Some(&mut opts)).is_err());
+    }
 }
@@ -0,0 +1,630 @@
+use std::ascii::AsciiExt;
+use std::ffi::CString;
+use std::mem;
+use std::ptr;
+use libc::size_t;
+
+use {raw, Buf, Commit, Diff, DiffFindOptions, DiffOptions, Error, IntoCString};
+use {Oid, Signature, Time};
+use util::{self, Binding};
+
+macro_rules! try_opt( ($e:expr) => (
+    match $e { Some(e) => e, None => return None }
+) );
+
+static MONTHS: [&'static str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+    "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A patch formatted as an RFC 2822 style email, as produced by
+/// `git format-patch`.
+pub struct Email {
+    email: Buf,
+}
+
+/// A patch parsed out of an email, such as one produced by
+/// `git format-patch`, along with the metadata of the commit it describes.
+///
+/// Like `git am`, the diff can be applied with `Repository::apply` or
+/// `Repository::apply_to_tree` and then committed with the author and
+/// message of the email.
+pub struct EmailPatch {
+    author_name: String,
+    author_email: String,
+    when: Time,
+    summary: String,
+    body: String,
+    diff: Vec<u8>,
+}
+
+/// Options for controlling the formatting of the generated e-mail.
+pub struct EmailCreateOptions {
+    diff_options: DiffOptions<'static>,
+    diff_find_options: DiffFindOptions,
+    subject_prefix: Option<CString>,
+    flags: u32,
+    start_number: usize,
+    reroll_number: usize,
+}
+
+impl EmailCreateOptions {
+    /// Creates a new set of email create options.
+    ///
+    /// By default, options include rename detection, patches are numbered
+    /// starting from 1 and the subject is prefixed with `PATCH`.
+    pub fn new() -> EmailCreateOptions {
+        EmailCreateOptions {
+            diff_options: DiffOptions::new(),
+            diff_find_options: DiffFindOptions::new(),
+            subject_prefix: None,
+            flags: raw::GIT_EMAIL_CREATE_DEFAULT,
+            start_number: 1,
+            reroll_number: 0,
+        }
+    }
+
+    fn flag(&mut self, opt: u32, val: bool) -> &mut EmailCreateOptions {
+        if val {
+            self.flags |= opt;
+        } else {
+            self.flags &= !opt;
+        }
+        self
+    }
+
+    /// Flag indicating whether patch numbers are included in the subject
+    /// prefix.
+    pub fn omit_numbers(&mut self, omit: bool) -> &mut EmailCreateOptions {
+        self.flag(raw::GIT_EMAIL_CREATE_OMIT_NUMBERS, omit)
+    }
+
+    /// Flag indicating whether numbers included in the subject prefix even
+    /// when the patch is for a single commit (1/1).
+    pub fn always_number(&mut self, always: bool) -> &mut EmailCreateOptions {
+        self.flag(raw::GIT_EMAIL_CREATE_ALWAYS_NUMBER, always)
+    }
+
+    /// Flag indicating whether rename or similarity detection are ignored.
+    pub fn ignore_renames(&mut self, ignore: bool) -> &mut EmailCreateOptions {
+        self.flag(raw::GIT_EMAIL_CREATE_NO_RENAMES, ignore)
+    }
+
+    /// Get mutable access to `DiffOptions` that are used for creating diffs.
+    pub fn diff_options(&mut self) -> &mut DiffOptions<'static> {
+        &mut self.diff_options
+    }
+
+    /// Get mutable access to `DiffFindOptions` that are used for finding
+    /// similarities within diffs.
+    pub fn diff_find_options(&mut self) -> &mut DiffFindOptions {
+        &mut self.diff_find_options
+    }
+
+    /// Set the subject prefix, `PATCH` by default.
+    ///
+    /// The prefix is placed inside the square brackets of the subject line,
+    /// for example `[RFC 1/2]`.
+    pub fn subject_prefix<T: IntoCString>(&mut self, t: T)
+                                          -> &mut EmailCreateOptions {
+        self.subject_prefix = Some(t.into_c_string());
+        self
+    }
+
+    /// Set the starting patch number; this cannot be 0, the default is 1.
+    pub fn start_number(&mut self, number: usize) -> &mut EmailCreateOptions {
+        self.start_number = number;
+        self
+    }
+
+    /// Set the "re-roll" number, used to produce `[PATCH v2]` style
+    /// prefixes. A value of 0 (the default) omits the version.
+    pub fn reroll_number(&mut self, number: usize) -> &mut EmailCreateOptions {
+        self.reroll_number = number;
+        self
+    }
+
+    /// Fill in a raw set of options from this configuration.
+    ///
+    /// This function is unsafe as the pointers stored in the resulting
+    /// structure are only valid so long as this structure is not moved,
+    /// modified, or used elsewhere.
+    unsafe fn raw(&mut self) -> raw::git_email_create_options {
+        let mut opts: raw::git_email_create_options = mem::zeroed();
+        opts.version = raw::GIT_EMAIL_CREATE_OPTIONS_VERSION;
+        opts.flags = self.flags;
+        opts.diff_opts = ptr::read(self.diff_options.raw());
+        opts.diff_find_opts = ptr::read(self.diff_find_options.raw());
+        opts.subject_prefix = self.subject_prefix.as_ref()
+                                  .map(|s| s.as_ptr())
+                                  .unwrap_or(0 as *const _);
+        opts.start_number = self.start_number as size_t;
+        opts.reroll_number = self.reroll_number as size_t;
+        opts
+    }
+}
+
+impl Email {
+    /// Returns a byte slice with stored e-mail patch in. `Email` could be
+    /// created by one of the `from_*` functions.
+    pub fn as_slice(&self) -> &[u8] {
+        &*self.email
+    }
+
+    /// Create a diff for a commit in mbox format for sending via email.
+    ///
+    /// The `patch_idx` is the 1-based number of this patch in a series of
+    /// `patch_count` patches.
+    pub fn from_diff<T: IntoCString>(diff: &Diff,
+                                     patch_idx: usize,
+                                     patch_count: usize,
+                                     commit_id: &Oid,
+                                     summary: T,
+                                     body: T,
+                                     author: &Signature,
+                                     opts: &mut EmailCreateOptions)
+                                     -> Result<Email, Error> {
+        let buf = Buf::new();
+        let summary = summary.into_c_string();
+        let body = body.into_c_string();
+        unsafe {
+            let opts = opts.raw();
+            try_call!(raw::git_email_create_from_diff(buf.raw(),
+                                                      diff.raw(),
+                                                      patch_idx as size_t,
+                                                      patch_count as size_t,
+                                                      commit_id.raw(),
+                                                      summary,
+                                                      body,
+                                                      author.raw(),
+                                                      &opts));
+        }
+        Ok(Email { email: buf })
+    }
+
+    /// Create a diff for a commit in mbox format for sending via email.
+    ///
+    /// The commit must not be a merge commit.
+    pub fn from_commit(commit: &Commit, opts: &mut EmailCreateOptions)
+                       -> Result<Email, Error> {
+        let buf = Buf::new();
+        unsafe {
+            let opts = opts.raw();
+            try_call!(raw::git_email_create_from_commit(buf.raw(),
+                                                        commit.raw(),
+                                                        &opts));
+        }
+        Ok(Email { email: buf })
+    }
+}
+
+impl EmailPatch {
+    /// Parse all of the patches in a mailbox in mbox format, in the order
+    /// they appear.
+    ///
+    /// A single email without the leading `From ` line of mbox is accepted
+    /// as well. Only emails whose body is not further encoded, as is the
+    /// case for those produced by `git format-patch`, are supported.
+    pub fn from_mbox(data: &[u8]) -> Result<Vec<EmailPatch>, Error> {
+        let lines = data.split(|b| *b == b'\n').collect::<Vec<_>>();
+        let mut ret = Vec::new();
+        let mut start = 0;
+        for i in range(1, lines.len()) {
+            if lines[i - 1].is_empty() && lines[i].starts_with(b"From ") &&
+               lines.get(i + 1).map(|l| is_header(*l)).unwrap_or(false) {
+                ret.push(try!(EmailPatch::parse(&lines[start..i])));
+                start = i;
+            }
+        }
+        ret.push(try!(EmailPatch::parse(&lines[start..])));
+        Ok(ret)
+    }
+
+    fn parse(lines: &[&[u8]]) -> Result<EmailPatch, Error> {
+        let mut lines = lines;
+        if lines.first().map(|l| l.starts_with(b"From ")).unwrap_or(false) {
+            lines = &lines[1..];
+        }
+
+        // headers, with folded lines joined back together
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let mut rest = &lines[lines.len()..];
+        for (i, line) in lines.iter().enumerate() {
+            let line = trim_cr(*line);
+            if line.is_empty() {
+                rest = &lines[i + 1..];
+                break
+            }
+            if line[0] == b' ' || line[0] == b'\t' {
+                match headers.last_mut() {
+                    Some(&mut (_, ref mut value)) => {
+                        value.push(' ');
+                        value.push_str(lossy(line).trim());
+                    }
+                    None => {}
+                }
+                continue
+            }
+            match line.iter().position(|b| *b == b':') {
+                Some(i) => headers.push((lossy(&line[..i]),
+                                         lossy(&line[i + 1..]).trim()
+                                                              .to_string())),
+                None => {}
+            }
+        }
+        let header = |name: &str| {
+            headers.iter().find(|&&(ref k, _)| k.eq_ignore_ascii_case(name))
+                   .map(|&(_, ref v)| decode_header(v.as_slice()))
+        };
+
+        let from = try!(header("from").ok_or_else(|| {
+            Error::from_str("email has no From header")
+        }));
+        let (name, email) = parse_address(from.as_slice());
+        let date = try!(header("date").ok_or_else(|| {
+            Error::from_str("email has no Date header")
+        }));
+        let when = try!(parse_date(date.as_slice()).ok_or_else(|| {
+            Error::from_str("failed to parse the Date header of the email")
+        }));
+        let subject = try!(header("subject").ok_or_else(|| {
+            Error::from_str("email has no Subject header")
+        }));
+
+        // the message runs up to the `---` line preceding the diffstat, and
+        // the diff up to the signature of the email, if any
+        let is_diff = |l: &[u8]| l.starts_with(b"diff --git ");
+        let end = rest.iter().position(|l| is_separator(*l) || is_diff(*l))
+                      .unwrap_or(rest.len());
+        let start = try!(rest[end..].iter().position(|l| is_diff(*l))
+                                    .ok_or_else(|| {
+            Error::from_str("email does not contain a patch")
+        })) + end;
+        let diff = match rest[start..].iter().rposition(|l| *l == &b"-- "[..]) {
+            Some(i) => &rest[start..start + i],
+            None => &rest[start..],
+        };
+        let mut diff_bytes = Vec::new();
+        for line in diff.iter() {
+            diff_bytes.push_all(*line);
+            diff_bytes.push(b'\n');
+        }
+
+        let body = rest[..end].iter().map(|l| lossy(trim_cr(*l)))
+                              .collect::<Vec<_>>().connect("\n");
+        Ok(EmailPatch {
+            author_name: name,
+            author_email: email,
+            when: when,
+            summary: clean_subject(subject.as_slice()),
+            body: body.trim().to_string(),
+            diff: diff_bytes,
+        })
+    }
+
+    /// Get the author of the patch, as a signature dated with the date of
+    /// the email.
+    pub fn author(&self) -> Result<Signature<'static>, Error> {
+        Signature::new(self.author_name.as_slice(),
+                       self.author_email.as_slice(), &self.when)
+    }
+
+    /// Get the summary of the patch, the subject of the email without any
+    /// `[PATCH]` style prefixes.
+    pub fn summary(&self) -> &str { self.summary.as_slice() }
+
+    /// Get the body of the commit message, which may be empty.
+    pub fn body(&self) -> &str { self.body.as_slice() }
+
+    /// Get the full commit message of the patch, made up of its summary
+    /// and body.
+    pub fn message(&self) -> String {
+        if self.body.is_empty() {
+            format!("{}\n", self.summary)
+        } else {
+            format!("{}\n\n{}\n", self.summary, self.body)
+        }
+    }
+
+    /// Get the text of the diff carried by the email.
+    pub fn diff_bytes(&self) -> &[u8] { self.diff.as_slice() }
+
+    /// Parse the diff carried by the email, ready to be applied.
+    pub fn diff(&self) -> Result<Diff, Error> {
+        Diff::from_buffer(self.diff.as_slice())
+    }
+}
+
+fn lossy(data: &[u8]) -> String {
+    String::from_utf8_lossy(data).into_owned()
+}
+
+fn trim_cr(line: &[u8]) -> &[u8] {
+    if line.ends_with(b"\r") { &line[..line.len() - 1] } else { line }
+}
+
+fn is_separator(line: &[u8]) -> bool {
+    let line = trim_cr(line);
+    line.starts_with(b"---") && line[3..].iter().all(|b| *b == b' ')
+}
+
+fn is_header(line: &[u8]) -> bool {
+    match line.iter().position(|b| *b == b':') {
+        Some(i) => i > 0 && !line[..i].iter().any(|b| *b == b' '),
+        None => false,
+    }
+}
+
+// Strip any number of leading `Re:` and `[PATCH n/m]` style prefixes from the
+// subject, as `git am` does.
+fn clean_subject(subject: &str) -> String {
+    let mut s = subject.trim();
+    loop {
+        if s.starts_with("[") {
+            match s.find(']') {
+                Some(i) => { s = s[i + 1..].trim_left(); continue }
+                None => {}
+            }
+        }
+        if s.len() >= 3 && s.as_bytes()[..3].eq_ignore_ascii_case(b"re:") {
+            s = s[3..].trim_left();
+            continue
+        }
+        return s.to_string()
+    }
+}
+
+// Split a `Name <email>` address, falling back to the address itself as the
+// name if there is none.
+fn parse_address(from: &str) -> (String, String) {
+    match (from.find('<'), from.rfind('>')) {
+        (Some(a), Some(b)) if a < b => {
+            let name = from[..a].trim().trim_matches('"');
+            let email = &from[a + 1..b];
+            let name = if name.is_empty() { email } else { name };
+            (name.to_string(), email.to_string())
+        }
+        _ => (from.trim().to_string(), from.trim().to_string()),
+    }
+}
+
+// Decode the RFC 2047 encoded words in a header, assuming utf-8 contents.
+fn decode_header(header: &str) -> String {
+    let mut ret = Vec::new();
+    let mut rest = header;
+    let mut prev_encoded = false;
+    while let Some(start) = rest.find("=?") {
+        let (decoded, len) = match decode_word(&rest[start + 2..]) {
+            Some(pair) => pair,
+            None => {
+                ret.push_all(rest[..start + 2].as_bytes());
+                rest = &rest[start + 2..];
+                prev_encoded = false;
+                continue
+            }
+        };
+        // whitespace between two encoded words is not part of the text
+        let between = &rest[..start];
+        if !prev_encoded || !between.trim().is_empty() {
+            ret.push_all(between.as_bytes());
+        }
+        ret.push_all(decoded.as_slice());
+        rest = &rest[start + 2 + len..];
+        prev_encoded = true;
+    }
+    ret.push_all(rest.as_bytes());
+    lossy(ret.as_slice())
+}
+
+// Decode a `charset?encoding?text?=` word, returning its contents and length.
+fn decode_word(word: &str) -> Option<(Vec<u8>, usize)> {
+    let charset = try_opt!(word.find('?'));
+    let encoding = try_opt!(word[charset + 1..].find('?')) + charset + 1;
+    let end = try_opt!(word[encoding + 1..].find("?=")) + encoding + 1;
+    let text = &word[encoding + 1..end];
+    let decoded = match &word[charset + 1..encoding] {
+        "B" | "b" => try_opt!(util::base64_decode(text)),
+        "Q" | "q" => {
+            let mut ret = Vec::new();
+            let mut bytes = text.bytes();
+            while let Some(b) = bytes.next() {
+                ret.push(match b {
+                    b'_' => b' ',
+                    b'=' => {
+                        let hi = try_opt!(bytes.next().and_then(hex));
+                        let lo = try_opt!(bytes.next().and_then(hex));
+                        hi << 4 | lo
+                    }
+                    b => b,
+                });
+            }
+            ret
+        }
+        _ => return None,
+    };
+    Some((decoded, end + 2))
+}
+
+fn hex(b: u8) -> Option<u8> {
+    match b {
+        b'0'...b'9' => Some(b - b'0'),
+        b'a'...b'f' => Some(b - b'a' + 10),
+        b'A'...b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+// Parse an RFC 2822 date such as `Thu, 1 Jan 1970 00:00:00 +0000`.
+fn parse_date(date: &str) -> Option<Time> {
+    let mut parts = date.split(' ').filter(|s| !s.is_empty());
+    let mut part = try_opt!(parts.next());
+    if part.ends_with(",") {
+        part = try_opt!(parts.next());
+    }
+    let day: i64 = try_opt!(part.parse().ok());
+    let month = try_opt!(parts.next());
+    let month = try_opt!(MONTHS.iter().position(|m| *m == month)) as i64 + 1;
+    let year: i64 = try_opt!(try_opt!(parts.next()).parse().ok());
+    let mut hms = [0i64; 3];
+    for (i, field) in try_opt!(parts.next()).split(':').enumerate() {
+        if i >= 3 { return None }
+        hms[i] = try_opt!(field.parse().ok());
+    }
+    let zone = try_opt!(parts.next());
+    if zone.len() != 5 || !zone.is_ascii() { return None }
+    let hours: i32 = try_opt!(zone[1..3].parse().ok());
+    let minutes: i32 = try_opt!(zone[3..].parse().ok());
+    let offset = match &zone[..1] {
+        "+" => hours * 60 + minutes,
+        "-" => -(hours * 60 + minutes),
+        _ => return None,
+    };
+
+    let days = util::days_from_civil(year, month, day);
+    let seconds = days * 86400 + hms[0] * 3600 + hms[1] * 60 + hms[2];
+    Some(Time::new(seconds - offset as i64 * 60, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::File;
+    use std::str;
+    use {Email, EmailCreateOptions, EmailPatch};
+    use super::parse_date;
+
+    #[test]
+    fn smoke() {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join("foo")).write_str("foo\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("foo")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let parent = repo.find_commit(head).unwrap();
+        let id = repo.commit(Some("HEAD"), &sig, &sig, "add foo\n\nbody\n",
+                             &tree, &[&parent]).unwrap();
+        let commit = repo.find_commit(id).unwrap();
+
+        let mut opts = EmailCreateOptions::new();
+        let email = Email::from_commit(&commit, &mut opts).unwrap();
+        let email = str::from_utf8(email.as_slice()).unwrap();
+        assert!(email.starts_with(format!("From {} ", id).as_slice()));
+        assert!(email.contains("Subject: [PATCH] add foo\n"));
+        assert!(email.contains("\nbody\n"));
+        assert!(email.contains(" foo | 1 +\n"));
+        assert!(email.contains(" 1 file changed, 1 insertion(+)\n"));
+        assert!(email.contains("+++ b/foo\n"));
+
+        let parent_tree = parent.tree().unwrap();
+        let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&tree),
+                                          None).unwrap();
+        opts.subject_prefix("RFC").reroll_number(2);
+        let email = Email::from_diff(&diff, 2, 3, &id, "add foo", "body",
+                                     &sig, &mut opts).unwrap();
+        let email = str::from_utf8(email.as_slice()).unwrap();
+        assert!(email.contains("Subject: [RFC v2 2/3] add foo\n"));
+
+        opts.omit_numbers(true);
+        let email = Email::from_diff(&diff, 2, 3, &id, "add foo", "body",
+                                     &sig, &mut opts).unwrap();
+        let email = str::from_utf8(email.as_slice()).unwrap();
+        assert!(email.contains("Subject: [RFC v2] add foo\n"));
+    }
+
+    #[test]
+    fn parse_mbox() {
+        let (td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let base = repo.find_commit(head).unwrap();
+        let mut parent = repo.find_commit(head).unwrap();
+        let mut mbox = Vec::new();
+        for &(file, msg) in [("foo", "add foo\n\nbody\n"),
+                             ("bar", "add bar\n")].iter() {
+            File::create(&td.path().join(file)).write_str("bar\n").unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(&Path::new(file)).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let id = repo.commit(Some("HEAD"), &sig, &sig, msg,
+                                 &tree, &[&parent]).unwrap();
+            parent = repo.find_commit(id).unwrap();
+            let mut opts = EmailCreateOptions::new();
+            let email = Email::from_commit(&parent, &mut opts).unwrap();
+            mbox.push_all(email.as_slice());
+        }
+
+        let patches = EmailPatch::from_mbox(mbox.as_slice()).unwrap();
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].summary(), "add foo");
+        assert_eq!(patches[0].message(), "add foo\n\nbody\n");
+        assert_eq!(patches[1].message(), "add bar\n");
+        assert_eq!(patches[1].body(), "");
+
+        // replay the series on top of the initial commit
+        let mut tree = base.tree().unwrap();
+        for patch in patches.iter() {
+            let diff = patch.diff().unwrap();
+            let mut index = repo.apply_to_tree(&tree, &diff, None).unwrap();
+            let id = index.write_tree_to(&repo).unwrap();
+            tree = repo.find_tree(id).unwrap();
+            let author = patch.author().unwrap();
+            assert_eq!(author.name(), Some("name"));
+            assert_eq!(author.email(), Some("email"));
+            assert_eq!(author.when().seconds(),
+                       parent.author().when().seconds());
+        }
+        assert_eq!(tree.id(), parent.tree_id());
+    }
+
+    #[test]
+    fn parse_headers() {
+        let email = b"From: =?UTF-8?q?J=C3=B6rg?= =?UTF-8?q?_M?= <j@example.com>
+Date: Mon, 2 Mar 2015 10:30:00 +0100
+Subject: Re: [PATCH v2 1/2] fix the
+ frobnicator
+
+Longer description.
+---
+ foo | 2 +-
+
+diff --git a/foo b/foo
+index 7898192..6178079 100644
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-a
++b
+-- 
+2.3.0
+";
+        let patches = EmailPatch::from_mbox(email).unwrap();
+        assert_eq!(patches.len(), 1);
+        let patch = &patches[0];
+        assert_eq!(patch.summary(), "fix the frobnicator");
+        assert_eq!(patch.body(), "Longer description.");
+        let author = patch.author().unwrap();
+        assert_eq!(author.name(), Some("J\u{f6}rg M"));
+        assert_eq!(author.email(), Some("j@example.com"));
+        assert_eq!(author.when().seconds(), 1425288600);
+        assert_eq!(author.when().offset_minutes(), 60);
+        assert!(patch.diff_bytes().starts_with(b"diff --git a/foo b/foo\n"));
+        assert!(patch.diff_bytes().ends_with(b"+b\n"));
+        assert_eq!(patch.diff().unwrap().deltas().len(), 1);
+
+        assert!(EmailPatch::from_mbox(b"Subject: no patch\n\nbody\n").is_err());
+    }
+
+    #[test]
+    fn date() {
+        let t = parse_date("Thu, 1 Jan 1970 01:00:00 +0100").unwrap();
+        assert_eq!(t.seconds(), 0);
+        assert_eq!(t.offset_minutes(), 60);
+        let t = parse_date("2 Jan 1970 00:00:00 -0030").unwrap();
+        assert_eq!(t.seconds(), 86400 + 30 * 60);
+
+        assert!(parse_date("Thu, 1 Jan 1970 00:00:00 \u{e9}000").is_none());
+        assert!(parse_date("Thu, 1 Jan 1970 00:00:00 +00\u{e9}").is_none());
+        assert!(parse_date("Thu, 1 Jan 1970 00:00:00 UTC").is_none());
+    }
+}
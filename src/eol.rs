@@ -0,0 +1,164 @@
+use std::old_io::{File, TempDir};
+
+use {raw, AutoCrlf, ConfigLevel, Eol, Error, Repository, SafeCrlf};
+use util::{Binding, io_error};
+
+/// Line ending conversion settings which take precedence over the
+/// `core.autocrlf`, `core.eol` and `core.safecrlf` configuration of the user
+/// for a single operation, see `CheckoutBuilder::eol_options` and
+/// `Repository::blob_path_with_eol`.
+///
+/// Settings which are not given still come from the configuration.
+#[derive(Clone, Debug)]
+pub struct EolOptions {
+    autocrlf: Option<AutoCrlf>,
+    eol: Option<Eol>,
+    safecrlf: Option<SafeCrlf>,
+}
+
+impl EolOptions {
+    /// Create a new set of options which override no settings.
+    pub fn new() -> EolOptions {
+        EolOptions { autocrlf: None, eol: None, safecrlf: None }
+    }
+
+    /// Set how the line endings of text files are converted, overriding
+    /// `core.autocrlf`.
+    pub fn autocrlf(&mut self, autocrlf: AutoCrlf) -> &mut EolOptions {
+        self.autocrlf = Some(autocrlf);
+        self
+    }
+
+    /// Set the line endings text files are checked out with when
+    /// `core.autocrlf` is not set, overriding `core.eol`.
+    pub fn eol(&mut self, eol: Eol) -> &mut EolOptions {
+        self.eol = Some(eol);
+        self
+    }
+
+    /// Set what happens when a conversion is not reversible, overriding
+    /// `core.safecrlf`.
+    pub fn safecrlf(&mut self, safecrlf: SafeCrlf) -> &mut EolOptions {
+        self.safecrlf = Some(safecrlf);
+        self
+    }
+
+    fn to_config(&self) -> String {
+        let mut ret = String::from_str("[core]\n");
+        match self.autocrlf {
+            Some(AutoCrlf::False) => ret.push_str("\tautocrlf = false\n"),
+            Some(AutoCrlf::True) => ret.push_str("\tautocrlf = true\n"),
+            Some(AutoCrlf::Input) => ret.push_str("\tautocrlf = input\n"),
+            None => {}
+        }
+        match self.eol {
+            Some(Eol::Lf) => ret.push_str("\teol = lf\n"),
+            Some(Eol::Crlf) => ret.push_str("\teol = crlf\n"),
+            Some(Eol::Native) => ret.push_str("\teol = native\n"),
+            None => {}
+        }
+        match self.safecrlf {
+            Some(SafeCrlf::Allow) => ret.push_str("\tsafecrlf = false\n"),
+            Some(SafeCrlf::Warn) => ret.push_str("\tsafecrlf = warn\n"),
+            Some(SafeCrlf::Fail) => ret.push_str("\tsafecrlf = true\n"),
+            None => {}
+        }
+        ret
+    }
+}
+
+/// Open a separate handle on `repo` which reads the usual configuration of
+/// the repository with the given options layered on top.
+///
+/// Operations run through the handle convert line endings according to
+/// `opts`, while the configuration of `repo` itself is left untouched.
+pub fn repository(repo: &Repository, opts: &EolOptions)
+                  -> Result<Repository, Error> {
+    let ret = try!(Repository::open(&repo.workdir()
+                                         .unwrap_or_else(|| repo.path())));
+    let td = try!(TempDir::new("git2-eol").map_err(io_error));
+    let path = td.path().join("config");
+    try!(File::create(&path).write_str(opts.to_config().as_slice())
+                            .map_err(io_error));
+    let mut config = try!(ret.config());
+    try!(config.add_file(&path, ConfigLevel::App, false));
+
+    // the snapshot keeps the settings in memory once the file is removed, and
+    // setting it also drops anything libgit2 cached from the configuration
+    let snapshot = try!(config.snapshot());
+    unsafe {
+        raw::git_repository_set_config(ret.raw(), snapshot.raw());
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::{fs, File};
+    use {AutoCrlf, EolOptions, ErrorCode, SafeCrlf};
+    use build::CheckoutBuilder;
+    use fixture::FixtureBuilder;
+
+    #[test]
+    fn checkin() {
+        let (td, repo) = ::test::repo_init();
+        let path = td.path().join("crlf.txt");
+        File::create(&path).write_str("a\r\nb\r\n").unwrap();
+        let content = |opts: &EolOptions| {
+            let id = try!(repo.blob_path_with_eol(&path, opts));
+            Ok(try!(repo.find_blob(id)).content().to_vec())
+        };
+
+        let mut opts = EolOptions::new();
+        opts.autocrlf(AutoCrlf::True);
+        assert_eq!(content(&opts).unwrap(), b"a\nb\n".to_vec());
+        opts.autocrlf(AutoCrlf::False);
+        assert_eq!(content(&opts).unwrap(), b"a\r\nb\r\n".to_vec());
+
+        // with autocrlf=input the line endings are never restored
+        opts.autocrlf(AutoCrlf::Input).safecrlf(SafeCrlf::Fail);
+        assert!(content(&opts).is_err());
+        opts.safecrlf(SafeCrlf::Allow);
+        assert_eq!(content(&opts).unwrap(), b"a\nb\n".to_vec());
+
+        // the configuration of the repository is left alone
+        let config = repo.config().unwrap();
+        assert_eq!(config.get_entry("core.safecrlf").err().unwrap().code(),
+                   ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn checkout() {
+        let fixture = FixtureBuilder::new().file("f.txt", b"x\ny\n")
+                                           .commit("f").build().unwrap();
+        let repo = fixture.repo();
+        let path = fixture.path().join("f.txt");
+        let mut opts = EolOptions::new();
+        opts.autocrlf(AutoCrlf::True);
+
+        fs::unlink(&path).unwrap();
+        repo.checkout_head(Some(CheckoutBuilder::new().force()
+                                                      .eol_options(&opts)))
+            .unwrap();
+        assert_eq!(File::open(&path).read_to_end().unwrap(),
+                   b"x\r\ny\r\n".to_vec());
+
+        fs::unlink(&path).unwrap();
+        let head = repo.revparse_single("HEAD").unwrap();
+        repo.checkout_tree(&head, Some(CheckoutBuilder::new().force()
+                                                         .eol_options(&opts)))
+            .unwrap();
+        assert_eq!(File::open(&path).read_to_end().unwrap(),
+                   b"x\r\ny\r\n".to_vec());
+        let mut index = repo.index().unwrap();
+        assert!(repo.checkout_index(Some(&mut index),
+                                    Some(CheckoutBuilder::new()
+                                                         .eol_options(&opts)))
+                    .is_err());
+
+        fs::unlink(&path).unwrap();
+        repo.checkout_head(Some(CheckoutBuilder::new().force())).unwrap();
+        assert_eq!(File::open(&path).read_to_end().unwrap(),
+                   b"x\ny\n".to_vec());
+    }
+}
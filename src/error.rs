@@ -1,45 +1,118 @@
 use std::ffi;
 use std::error;
 use std::fmt;
-use std::str;
 use libc::c_int;
 
-use {raw, ErrorCode};
+use {raw, ErrorClass, ErrorCode};
 
 /// A structure to represent errors coming out of libgit2.
 #[derive(Debug)]
 pub struct Error {
+    code: c_int,
     klass: c_int,
     message: String,
 }
 
 impl Error {
-    /// Returns the last error, or `None` if one is not available.
-    pub fn last_error() -> Option<Error> {
+    /// Creates a new error with the given code, class and message.
+    ///
+    /// This is mostly useful for returning errors from callbacks which
+    /// libgit2 should see with a particular code, such as `ErrorCode::User`.
+    pub fn new(code: ErrorCode, class: ErrorClass, message: &str) -> Error {
+        let mut err = Error::from_str(message);
+        err.code = match code {
+            ErrorCode::GenericError => raw::GIT_ERROR,
+            ErrorCode::NotFound => raw::GIT_ENOTFOUND,
+            ErrorCode::Exists => raw::GIT_EEXISTS,
+            ErrorCode::Ambiguous => raw::GIT_EAMBIGUOUS,
+            ErrorCode::BufSize => raw::GIT_EBUFS,
+            ErrorCode::User => raw::GIT_EUSER,
+            ErrorCode::BareRepo => raw::GIT_EBAREREPO,
+            ErrorCode::UnbornBranch => raw::GIT_EUNBORNBRANCH,
+            ErrorCode::Unmerged => raw::GIT_EUNMERGED,
+            ErrorCode::NotFastForward => raw::GIT_ENONFASTFORWARD,
+            ErrorCode::InvalidSpec => raw::GIT_EINVALIDSPEC,
+            ErrorCode::Conflict => raw::GIT_ECONFLICT,
+            ErrorCode::Locked => raw::GIT_ELOCKED,
+            ErrorCode::Modified => raw::GIT_EMODIFIED,
+            ErrorCode::Auth => raw::GIT_EAUTH,
+            ErrorCode::Certificate => raw::GIT_ECERTIFICATE,
+            ErrorCode::Applied => raw::GIT_EAPPLIED,
+            ErrorCode::Peel => raw::GIT_EPEEL,
+        } as c_int;
+        err.klass = match class {
+            ErrorClass::None => raw::GITERR_NONE,
+            ErrorClass::NoMemory => raw::GITERR_NOMEMORY,
+            ErrorClass::Os => raw::GITERR_OS,
+            ErrorClass::Invalid => raw::GITERR_INVALID,
+            ErrorClass::Reference => raw::GITERR_REFERENCE,
+            ErrorClass::Zlib => raw::GITERR_ZLIB,
+            ErrorClass::Repository => raw::GITERR_REPOSITORY,
+            ErrorClass::Config => raw::GITERR_CONFIG,
+            ErrorClass::Regex => raw::GITERR_REGEX,
+            ErrorClass::Odb => raw::GITERR_ODB,
+            ErrorClass::Index => raw::GITERR_INDEX,
+            ErrorClass::Object => raw::GITERR_OBJECT,
+            ErrorClass::Net => raw::GITERR_NET,
+            ErrorClass::Tag => raw::GITERR_TAG,
+            ErrorClass::Tree => raw::GITERR_TREE,
+            ErrorClass::Indexer => raw::GITERR_INDEXER,
+            ErrorClass::Ssl => raw::GITERR_SSL,
+            ErrorClass::Submodule => raw::GITERR_SUBMODULE,
+            ErrorClass::Thread => raw::GITERR_THREAD,
+            ErrorClass::Stash => raw::GITERR_STASH,
+            ErrorClass::Checkout => raw::GITERR_CHECKOUT,
+            ErrorClass::FetchHead => raw::GITERR_FETCHHEAD,
+            ErrorClass::Merge => raw::GITERR_MERGE,
+            ErrorClass::Ssh => raw::GITERR_SSH,
+            ErrorClass::Filter => raw::GITERR_FILTER,
+            ErrorClass::Revert => raw::GITERR_REVERT,
+            ErrorClass::Callback => raw::GITERR_CALLBACK,
+            ErrorClass::CherryPick => raw::GITERR_CHERRYPICK,
+            ErrorClass::Describe => raw::GITERR_DESCRIBE,
+            ErrorClass::Rebase => raw::GITERR_REBASE,
+        } as c_int;
+        err
+    }
+
+    /// Returns the last error which happened, tagged with the given return
+    /// code of the function which failed, or `None` if one is not
+    /// available.
+    pub fn last_error(code: c_int) -> Option<Error> {
         ::init();
         unsafe {
-            let ptr = raw::giterr_last();
+            let ptr = raw::git_error_last();
             if ptr.is_null() {
                 None
             } else {
-                Some(Error::from_raw(ptr))
+                Some(Error::from_raw(code, ptr))
             }
         }
     }
 
-    unsafe fn from_raw(ptr: *const raw::git_error) -> Error {
+    unsafe fn from_raw(code: c_int, ptr: *const raw::git_error) -> Error {
         let msg = (*ptr).message as *const _;
         let msg = ffi::c_str_to_bytes(&msg);
-        let msg = str::from_utf8(msg).unwrap();
-        Error { klass: (*ptr).klass, message: msg.to_string() }
+        let msg = String::from_utf8_lossy(msg).into_owned();
+        Error { code: code, klass: (*ptr).klass, message: msg }
     }
 
     /// Creates a new error from the given string as the error.
+    ///
+    /// The error returned will have the code `GenericError` and the class
+    /// `None`.
     pub fn from_str(s: &str) -> Error {
-        Error { klass: raw::GIT_ERROR as c_int, message: s.to_string() }
+        Error {
+            code: raw::GIT_ERROR as c_int,
+            klass: raw::GITERR_NONE as c_int,
+            message: s.to_string(),
+        }
     }
 
     /// Return the error code associated with this error.
+    ///
+    /// Codes which are not represented by `ErrorCode` are reported as
+    /// `GenericError`; see `raw_code` for the exact value.
     pub fn code(&self) -> ErrorCode {
         match self.raw_code() {
             raw::GIT_OK => super::ErrorCode::GenericError,
@@ -54,18 +127,79 @@ impl Error {
             raw::GIT_EUNMERGED => super::ErrorCode::Unmerged,
             raw::GIT_ENONFASTFORWARD => super::ErrorCode::NotFastForward,
             raw::GIT_EINVALIDSPEC => super::ErrorCode::InvalidSpec,
-            raw::GIT_EMERGECONFLICT => super::ErrorCode::MergeConflict,
+            raw::GIT_ECONFLICT => super::ErrorCode::Conflict,
             raw::GIT_ELOCKED => super::ErrorCode::Locked,
             raw::GIT_EMODIFIED => super::ErrorCode::Modified,
+            raw::GIT_EAUTH => super::ErrorCode::Auth,
+            raw::GIT_ECERTIFICATE => super::ErrorCode::Certificate,
+            raw::GIT_EAPPLIED => super::ErrorCode::Applied,
+            raw::GIT_EPEEL => super::ErrorCode::Peel,
+            raw::GIT_EEOF => super::ErrorCode::GenericError,
+            raw::GIT_EINVALID => super::ErrorCode::GenericError,
+            raw::GIT_EUNCOMMITTED => super::ErrorCode::GenericError,
+            raw::GIT_EDIRECTORY => super::ErrorCode::GenericError,
+            raw::GIT_EMERGECONFLICT => super::ErrorCode::GenericError,
             raw::GIT_PASSTHROUGH => super::ErrorCode::GenericError,
             raw::GIT_ITEROVER => super::ErrorCode::GenericError,
+            raw::GIT_RETRY => super::ErrorCode::GenericError,
+            raw::GIT_EMISMATCH => super::ErrorCode::GenericError,
+            raw::GIT_EINDEXDIRTY => super::ErrorCode::GenericError,
+            raw::GIT_EAPPLYFAIL => super::ErrorCode::GenericError,
+            raw::GIT_EOWNER => super::ErrorCode::GenericError,
+            raw::GIT_TIMEOUT => super::ErrorCode::GenericError,
+        }
+    }
+
+    /// Return the subsystem of libgit2 which this error originated from.
+    ///
+    /// Classes which are not represented by `ErrorClass` are reported as
+    /// `None`; see `raw_class` for the exact value.
+    pub fn class(&self) -> ErrorClass {
+        match self.raw_class() {
+            raw::GITERR_NONE => super::ErrorClass::None,
+            raw::GITERR_NOMEMORY => super::ErrorClass::NoMemory,
+            raw::GITERR_OS => super::ErrorClass::Os,
+            raw::GITERR_INVALID => super::ErrorClass::Invalid,
+            raw::GITERR_REFERENCE => super::ErrorClass::Reference,
+            raw::GITERR_ZLIB => super::ErrorClass::Zlib,
+            raw::GITERR_REPOSITORY => super::ErrorClass::Repository,
+            raw::GITERR_CONFIG => super::ErrorClass::Config,
+            raw::GITERR_REGEX => super::ErrorClass::Regex,
+            raw::GITERR_ODB => super::ErrorClass::Odb,
+            raw::GITERR_INDEX => super::ErrorClass::Index,
+            raw::GITERR_OBJECT => super::ErrorClass::Object,
+            raw::GITERR_NET => super::ErrorClass::Net,
+            raw::GITERR_TAG => super::ErrorClass::Tag,
+            raw::GITERR_TREE => super::ErrorClass::Tree,
+            raw::GITERR_INDEXER => super::ErrorClass::Indexer,
+            raw::GITERR_SSL => super::ErrorClass::Ssl,
+            raw::GITERR_SUBMODULE => super::ErrorClass::Submodule,
+            raw::GITERR_THREAD => super::ErrorClass::Thread,
+            raw::GITERR_STASH => super::ErrorClass::Stash,
+            raw::GITERR_CHECKOUT => super::ErrorClass::Checkout,
+            raw::GITERR_FETCHHEAD => super::ErrorClass::FetchHead,
+            raw::GITERR_MERGE => super::ErrorClass::Merge,
+            raw::GITERR_SSH => super::ErrorClass::Ssh,
+            raw::GITERR_FILTER => super::ErrorClass::Filter,
+            raw::GITERR_REVERT => super::ErrorClass::Revert,
+            raw::GITERR_CALLBACK => super::ErrorClass::Callback,
+            raw::GITERR_CHERRYPICK => super::ErrorClass::CherryPick,
+            raw::GITERR_DESCRIBE => super::ErrorClass::Describe,
+            raw::GITERR_REBASE => super::ErrorClass::Rebase,
+            raw::GITERR_FILESYSTEM => super::ErrorClass::None,
+            raw::GITERR_PATCH => super::ErrorClass::None,
+            raw::GITERR_WORKTREE => super::ErrorClass::None,
+            raw::GITERR_SHA => super::ErrorClass::None,
+            raw::GITERR_HTTP => super::ErrorClass::None,
+            raw::GITERR_INTERNAL => super::ErrorClass::None,
+            raw::GITERR_GRAFTS => super::ErrorClass::None,
         }
     }
 
     /// Return the raw error code associated with this error.
     pub fn raw_code(&self) -> raw::git_error_code {
         macro_rules! check( ($($e:ident),*) => (
-            $(if self.klass == raw::$e as c_int { raw::$e }) else *
+            $(if self.code == raw::$e as c_int { raw::$e }) else *
             else {
                 raw::GIT_ERROR
             }
@@ -83,11 +217,75 @@ impl Error {
             GIT_EUNMERGED,
             GIT_ENONFASTFORWARD,
             GIT_EINVALIDSPEC,
-            GIT_EMERGECONFLICT,
+            GIT_ECONFLICT,
             GIT_ELOCKED,
             GIT_EMODIFIED,
+            GIT_EAUTH,
+            GIT_ECERTIFICATE,
+            GIT_EAPPLIED,
+            GIT_EPEEL,
+            GIT_EEOF,
+            GIT_EINVALID,
+            GIT_EUNCOMMITTED,
+            GIT_EDIRECTORY,
+            GIT_EMERGECONFLICT,
             GIT_PASSTHROUGH,
-            GIT_ITEROVER
+            GIT_ITEROVER,
+            GIT_RETRY,
+            GIT_EMISMATCH,
+            GIT_EINDEXDIRTY,
+            GIT_EAPPLYFAIL,
+            GIT_EOWNER,
+            GIT_TIMEOUT
+        )
+    }
+
+    /// Return the raw error class associated with this error.
+    pub fn raw_class(&self) -> raw::git_error_t {
+        macro_rules! check( ($($e:ident),*) => (
+            $(if self.klass == raw::$e as c_int { raw::$e }) else *
+            else {
+                raw::GITERR_NONE
+            }
+        ) );
+        check!(
+            GITERR_NONE,
+            GITERR_NOMEMORY,
+            GITERR_OS,
+            GITERR_INVALID,
+            GITERR_REFERENCE,
+            GITERR_ZLIB,
+            GITERR_REPOSITORY,
+            GITERR_CONFIG,
+            GITERR_REGEX,
+            GITERR_ODB,
+            GITERR_INDEX,
+            GITERR_OBJECT,
+            GITERR_NET,
+            GITERR_TAG,
+            GITERR_TREE,
+            GITERR_INDEXER,
+            GITERR_SSL,
+            GITERR_SUBMODULE,
+            GITERR_THREAD,
+            GITERR_STASH,
+            GITERR_CHECKOUT,
+            GITERR_FETCHHEAD,
+            GITERR_MERGE,
+            GITERR_SSH,
+            GITERR_FILTER,
+            GITERR_REVERT,
+            GITERR_CALLBACK,
+            GITERR_CHERRYPICK,
+            GITERR_DESCRIBE,
+            GITERR_REBASE,
+            GITERR_FILESYSTEM,
+            GITERR_PATCH,
+            GITERR_WORKTREE,
+            GITERR_SHA,
+            GITERR_HTTP,
+            GITERR_INTERNAL,
+            GITERR_GRAFTS
         )
     }
 
@@ -101,7 +299,33 @@ impl error::Error for Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(write!(f, "[{}] ", self.klass));
-        f.write_str(self.message.as_slice())
+        try!(f.write_str(self.message.as_slice()));
+        try!(write!(f, "; class={:?} ({})", self.class(), self.klass));
+        write!(f, "; code={:?} ({})", self.code(), self.code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Repository, Oid, ErrorClass, ErrorCode, Error};
+
+    #[test]
+    fn smoke() {
+        let (_td, repo) = ::test::repo_init();
+        let err = repo.find_commit(Oid::zero()).err().unwrap();
+        assert_eq!(err.code(), ErrorCode::NotFound);
+        assert_eq!(err.class(), ErrorClass::Odb);
+
+        let err = Repository::open(&Path::new("/path/to/nowhere")).err();
+        assert_eq!(err.unwrap().code(), ErrorCode::NotFound);
+
+        let err = Error::new(ErrorCode::User, ErrorClass::Callback, "nope");
+        assert_eq!(err.code(), ErrorCode::User);
+        assert_eq!(err.class(), ErrorClass::Callback);
+        assert_eq!(err.message(), "nope");
+
+        let err = Error::from_str("foo");
+        assert_eq!(err.code(), ErrorCode::GenericError);
+        assert_eq!(err.class(), ErrorClass::None);
     }
 }
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use {Delta, Error, Oid, Repository, Revwalk, Signature};
+use {SORT_TOPOLOGICAL, SORT_REVERSE};
+use util::io_error;
+
+/// An iterator over the commits of a revwalk in the stream format of
+/// `git fast-export`, which `git fast-import` and other tools can read.
+///
+/// Each item holds the commands for a single commit: the blobs it introduces
+/// followed by the commit itself, which modifies the files that changed
+/// relative to its first parent. Blobs and commits are given marks as they
+/// are exported, and parents which were not part of the stream are referred
+/// to by their id.
+pub struct FastExport<'repo> {
+    repo: &'repo Repository,
+    walk: Revwalk<'repo>,
+    refname: String,
+    marks: HashMap<Oid, usize>,
+}
+
+impl<'repo> FastExport<'repo> {
+    /// Create a stream of the commits of the given revwalk, all of which are
+    /// committed to the reference `refname`, such as `refs/heads/master`.
+    ///
+    /// The revwalk is sorted so that parents are exported before their
+    /// children.
+    pub fn new(repo: &'repo Repository, mut walk: Revwalk<'repo>,
+               refname: &str) -> FastExport<'repo> {
+        walk.set_sorting(SORT_TOPOLOGICAL | SORT_REVERSE);
+        FastExport {
+            repo: repo,
+            walk: walk,
+            refname: refname.to_string(),
+            marks: HashMap::new(),
+        }
+    }
+
+    /// Get the mark a blob or commit was exported with, if it has been
+    /// exported already.
+    pub fn mark(&self, id: Oid) -> Option<usize> {
+        self.marks.get(&id).map(|m| *m)
+    }
+
+    /// Write the remainder of the stream to `out`, terminated with the
+    /// `done` command.
+    pub fn write_to(&mut self, out: &mut Writer) -> Result<(), Error> {
+        while let Some(chunk) = self.next() {
+            let chunk = try!(chunk);
+            try!(out.write_all(chunk.as_slice()).map_err(io_error));
+        }
+        out.write_all(b"done\n").map_err(io_error)
+    }
+
+    fn dataref(&self, id: Oid) -> String {
+        match self.marks.get(&id) {
+            Some(mark) => format!(":{}", mark),
+            None => id.to_string(),
+        }
+    }
+
+    fn mark_new(&mut self, id: Oid) -> usize {
+        let mark = self.marks.len() + 1;
+        self.marks.insert(id, mark);
+        mark
+    }
+
+    fn export(&mut self, id: Oid) -> Result<Vec<u8>, Error> {
+        let commit = try!(self.repo.find_commit(id));
+        let tree = try!(commit.tree());
+        let parent = match commit.parent_count() {
+            0 => None,
+            _ => Some(try!(try!(commit.parent(0)).tree())),
+        };
+        let diff = try!(self.repo.diff_tree_to_tree(parent.as_ref(),
+                                                    Some(&tree), None));
+
+        let mut out = Vec::new();
+        let mut changes = Vec::new();
+        let mut deletes = Vec::new();
+        for delta in diff.deltas() {
+            if delta.status() == Delta::Deleted {
+                deletes.push_all(b"D ");
+                quote(delta.old_file().path_bytes().unwrap(), &mut deletes);
+                deletes.push(b'\n');
+                continue
+            }
+            let file = delta.new_file();
+            let mode = file.filemode_raw();
+            // submodules are referred to by the id of their commit
+            let known = self.marks.contains_key(&file.id());
+            let dataref = if mode == 0o160000 || known {
+                self.dataref(file.id())
+            } else {
+                let blob = try!(self.repo.find_blob(file.id()));
+                let mark = self.mark_new(file.id());
+                out.push_all(format!("blob\nmark :{}\ndata {}\n", mark,
+                                     blob.content().len()).as_bytes());
+                out.push_all(blob.content());
+                out.push(b'\n');
+                format!(":{}", mark)
+            };
+            changes.push_all(format!("M {:o} {} ", mode, dataref).as_bytes());
+            quote(file.path_bytes().unwrap(), &mut changes);
+            changes.push(b'\n');
+        }
+
+        // a root commit must not pick up the commits exported before it
+        if commit.parent_count() == 0 {
+            out.push_all(format!("reset {}\n", self.refname).as_bytes());
+        }
+        let mark = self.mark_new(id);
+        let message = commit.message_bytes();
+        out.push_all(format!("commit {}\nmark :{}\nauthor {}\ncommitter {}\n\
+                              data {}\n", self.refname, mark,
+                             ident(&commit.author()),
+                             ident(&commit.committer()),
+                             message.len()).as_bytes());
+        out.push_all(message);
+        out.push(b'\n');
+        for (i, parent) in commit.parent_ids().enumerate() {
+            let kind = if i == 0 { "from" } else { "merge" };
+            out.push_all(format!("{} {}\n", kind, self.dataref(parent))
+                             .as_bytes());
+        }
+        out.push_all(deletes.as_slice());
+        out.push_all(changes.as_slice());
+        out.push(b'\n');
+        Ok(out)
+    }
+}
+
+impl<'repo> Iterator for FastExport<'repo> {
+    type Item = Result<Vec<u8>, Error>;
+    fn next(&mut self) -> Option<Result<Vec<u8>, Error>> {
+        match self.walk.next() {
+            Some(id) => Some(self.export(id)),
+            None => None,
+        }
+    }
+}
+
+// Format a signature as `Name <email> time offset`.
+fn ident(sig: &Signature) -> String {
+    let when = sig.when();
+    let offset = when.offset_minutes();
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = if offset < 0 { -offset } else { offset };
+    format!("{} <{}> {} {}{:02}{:02}",
+            String::from_utf8_lossy(sig.name_bytes()),
+            String::from_utf8_lossy(sig.email_bytes()),
+            when.seconds(), sign, offset / 60, offset % 60)
+}
+
+// Paths only need quoting if they would otherwise be ambiguous to
+// `git fast-import`.
+fn quote(path: &[u8], out: &mut Vec<u8>) {
+    if !path.starts_with(b"\"") && !path.contains(&b'\n') {
+        return out.push_all(path)
+    }
+    out.push(b'"');
+    for b in path.iter() {
+        match *b {
+            b'"' => out.push_all(b"\\\""),
+            b'\\' => out.push_all(b"\\\\"),
+            b'\n' => out.push_all(b"\\n"),
+            b => out.push(b),
+        }
+    }
+    out.push(b'"');
+}
+
+#[cfg(test)]
+mod tests {
+    use FastExport;
+
+    #[test]
+    fn smoke() {
+        let (_td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let base = repo.find_commit(head).unwrap();
+        let foo = repo.blob(b"foo\n").unwrap();
+        let mut builder = repo.treebuilder(None).unwrap();
+        builder.insert("foo", foo, 0o100644).unwrap();
+        builder.insert("run", foo, 0o100755).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let second = repo.commit(Some("HEAD"), &sig, &sig, "second",
+                                 &tree, &[&base]).unwrap();
+        let second = repo.find_commit(second).unwrap();
+        let mut builder = repo.treebuilder(Some(&tree)).unwrap();
+        builder.remove("run").unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "third", &tree,
+                    &[&second]).unwrap();
+
+        let mut walk = repo.revwalk().unwrap();
+        walk.push_head().unwrap();
+        let mut export = FastExport::new(&repo, walk, "refs/heads/master");
+        let chunks = export.by_ref().map(|c| {
+            String::from_utf8(c.unwrap()).unwrap()
+        }).collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[0].starts_with("reset refs/heads/master\n\
+                                       commit refs/heads/master\n\
+                                       mark :1\n"));
+        assert_eq!(export.mark(head), Some(1));
+        assert_eq!(export.mark(foo), Some(2));
+
+        let ident = super::ident(&sig);
+        assert_eq!(chunks[1], format!("blob\nmark :2\ndata 4\nfoo\n\n\
+                                       commit refs/heads/master\nmark :3\n\
+                                       author {0}\ncommitter {0}\n\
+                                       data 6\nsecond\nfrom :1\n\
+                                       M 100644 :2 foo\nM 100755 :2 run\n\n",
+                                      ident));
+        assert_eq!(chunks[2], format!("commit refs/heads/master\nmark :4\n\
+                                       author {0}\ncommitter {0}\n\
+                                       data 5\nthird\nfrom :3\nD run\n\n",
+                                      ident));
+
+        // parents left out of the stream are referred to by their id
+        let mut walk = repo.revwalk().unwrap();
+        walk.push_head().unwrap();
+        walk.hide(head).unwrap();
+        let mut out = Vec::new();
+        FastExport::new(&repo, walk, "refs/heads/master")
+                   .write_to(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("blob\nmark :1\n"));
+        assert!(out.contains(format!("from {}\n", head).as_slice()));
+        assert!(out.ends_with("\ndone\n"));
+    }
+}
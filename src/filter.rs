@@ -0,0 +1,465 @@
+//! Interfaces for adding custom filters to libgit2
+//!
+//! Filters transform the contents of files as they move between the object
+//! database and the working directory, such as when a file is checked out
+//! ("smudge") or staged ("clean"). A filter registered here participates in
+//! checkouts, `Index::add_path`, `Repository::blob_path` and anything else
+//! which runs the filters configured through `.gitattributes`.
+
+use std::ffi::{self, CString};
+use std::iter::range;
+use std::marker;
+use std::mem;
+use std::old_io::IoError;
+use std::slice;
+use std::str;
+use libc::{c_int, c_void, c_char, size_t};
+
+use {raw, panic, AttrValue, Blob, Error, Oid, Repository};
+use util::{Binding, io_error};
+
+/// The priority at which filters are usually registered, matching that of
+/// the filter drivers configured by `filter.<name>.clean` and
+/// `filter.<name>.smudge`.
+pub const DRIVER_PRIORITY: i32 = raw::GIT_FILTER_DRIVER_PRIORITY as i32;
+
+/// A filter which can transform the contents of files as they are checked
+/// out or checked in.
+pub trait Filter: Send + Sync {
+    /// Decide whether this filter should be applied to the given source.
+    ///
+    /// The `attrs` slice contains the values of the attributes named when the
+    /// filter was registered, in the same order, as they are set for the path
+    /// being filtered. Returning `false` skips this filter for the file.
+    ///
+    /// By default the filter is always applied.
+    fn check(&self, src: &FilterSource, attrs: &[AttrValue])
+             -> Result<bool, Error> {
+        let _ = (src, attrs);
+        Ok(true)
+    }
+
+    /// Filter the contents of a file, in the direction given by
+    /// `src.mode()`.
+    ///
+    /// Returning `None` passes the input through untouched, otherwise the
+    /// returned data replaces the contents of the file.
+    fn apply(&self, src: &FilterSource, input: &[u8])
+             -> Result<Option<Vec<u8>>, Error>;
+}
+
+/// The direction in which a filter is being applied.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum FilterMode {
+    /// The file is being written to the working directory ("smudge").
+    ToWorktree,
+    /// The file is being read into the object database ("clean").
+    ToOdb,
+}
+
+/// Information about the file being filtered.
+pub struct FilterSource<'a> {
+    raw: *const raw::git_filter_source,
+    marker: marker::ContravariantLifetime<'a>,
+}
+
+/// The list of filters which apply to a particular path, loaded with
+/// `FilterList::load`.
+///
+/// The filters can be run over data while writing the results out to any
+/// `Writer` as they are produced, so the filtered contents never need to be
+/// held in memory in full.
+pub struct FilterList<'repo> {
+    raw: *mut raw::git_filter_list,
+    repo: &'repo Repository,
+}
+
+/// Instance of a `git_writestream` which forwards to a `Writer`, must use
+/// `#[repr(C)]` to ensure that the C fields come first.
+#[repr(C)]
+struct WriteStream<'a> {
+    raw: raw::git_writestream,
+    out: &'a mut (Writer + 'a),
+    err: Option<IoError>,
+}
+
+/// Instance of a `git_filter`, must use `#[repr(C)]` to ensure that the C
+/// fields come first.
+#[repr(C)]
+struct RawFilter {
+    raw: raw::git_filter,
+    attributes: Option<CString>,
+    nattrs: usize,
+    obj: Box<Filter>,
+}
+
+/// Register a filter under the given name.
+///
+/// The `attributes` are a whitespace-separated list of the attributes the
+/// filter is interested in, such as `"filter=lfs"` or `"text eol"`; a filter
+/// is only run for files where the attributes match, and their values are
+/// handed to `Filter::check`. Filters with higher `priority` run first when
+/// checking files in, and last when checking them out.
+///
+/// This function is unsafe as it needs to be externally synchronized with
+/// any other use of filters.
+pub unsafe fn register<F>(name: &str,
+                          attributes: Option<&str>,
+                          priority: i32,
+                          filter: F) -> Result<(), Error>
+    where F: Filter + 'static
+{
+    ::init();
+    let mut data = Box::new(RawFilter {
+        raw: mem::zeroed(),
+        attributes: attributes.map(|s| CString::from_slice(s.as_bytes())),
+        nattrs: attributes.map(|s| s.words().count()).unwrap_or(0),
+        obj: Box::new(filter),
+    });
+    data.raw.version = raw::GIT_FILTER_VERSION;
+    data.raw.attributes = data.attributes.as_ref().map(|s| s.as_ptr())
+                              .unwrap_or(0 as *const _);
+    let check: raw::git_filter_check_fn = filter_check;
+    let apply: raw::git_filter_apply_fn = filter_apply;
+    data.raw.check = Some(check);
+    data.raw.apply = Some(apply);
+
+    let name = CString::from_slice(name.as_bytes());
+    let datap = (&mut *data) as *mut RawFilter as *mut raw::git_filter;
+    try_call!(raw::git_filter_register(name, datap, priority as c_int));
+    mem::forget(data); // ownership transferred to libgit2
+    Ok(())
+}
+
+/// Remove a filter which was previously registered with `register`.
+///
+/// This function is unsafe as it needs to be externally synchronized with
+/// any other use of filters, and the filter must have been registered from
+/// Rust.
+pub unsafe fn unregister(name: &str) -> Result<(), Error> {
+    let name = CString::from_slice(name.as_bytes());
+    let filter = raw::git_filter_lookup(name.as_ptr());
+    try_call!(raw::git_filter_unregister(name));
+    mem::transmute::<_, Box<RawFilter>>(filter);
+    Ok(())
+}
+
+impl<'a> FilterSource<'a> {
+    /// Get the path that the source data is coming from, as a byte slice.
+    pub fn path_bytes(&self) -> Option<&[u8]> {
+        unsafe { ::opt_bytes(self, raw::git_filter_source_path(self.raw)) }
+    }
+
+    /// Get the path that the source data is coming from.
+    ///
+    /// Returns `None` if the path is not valid utf-8.
+    pub fn path(&self) -> Option<&str> {
+        self.path_bytes().and_then(|s| str::from_utf8(s).ok())
+    }
+
+    /// Get the file mode of the source file, or 0 if it is not known.
+    pub fn filemode(&self) -> u16 {
+        unsafe { raw::git_filter_source_filemode(self.raw) }
+    }
+
+    /// Get the id of the source object, if it is known.
+    pub fn id(&self) -> Option<Oid> {
+        unsafe {
+            Binding::from_raw_opt(raw::git_filter_source_id(self.raw))
+        }
+    }
+
+    /// Get the direction in which the file is being filtered.
+    pub fn mode(&self) -> FilterMode {
+        match unsafe { raw::git_filter_source_mode(self.raw) } {
+            raw::GIT_FILTER_TO_WORKTREE => FilterMode::ToWorktree,
+            raw::GIT_FILTER_TO_ODB => FilterMode::ToOdb,
+        }
+    }
+}
+
+impl<'repo> FilterList<'repo> {
+    /// Load the filters which apply to the given path in the repository,
+    /// as configured through `.gitattributes`, in the given direction.
+    ///
+    /// Returns `None` if no filters need to be applied to the path.
+    pub fn load(repo: &'repo Repository, path: &Path, mode: FilterMode)
+                -> Result<Option<FilterList<'repo>>, Error> {
+        let path = CString::from_slice(path.as_vec());
+        let mode = match mode {
+            FilterMode::ToWorktree => raw::GIT_FILTER_TO_WORKTREE,
+            FilterMode::ToOdb => raw::GIT_FILTER_TO_ODB,
+        };
+        let mut raw = 0 as *mut raw::git_filter_list;
+        unsafe {
+            try_call!(raw::git_filter_list_load(&mut raw, repo.raw(),
+                                                0 as *mut raw::git_blob,
+                                                path, mode,
+                                                raw::GIT_FILTER_DEFAULT));
+        }
+        if raw.is_null() {
+            Ok(None)
+        } else {
+            Ok(Some(FilterList { raw: raw, repo: repo }))
+        }
+    }
+
+    /// Run the filters over the given data, writing the result to `out`.
+    pub fn stream_data<W: Writer>(&self, data: &[u8], out: &mut W)
+                                  -> Result<(), Error> {
+        let mut buf = raw::git_buf {
+            ptr: data.as_ptr() as *mut c_char,
+            asize: 0,
+            size: data.len() as size_t,
+        };
+        stream(out, |target| unsafe {
+            raw::git_filter_list_stream_data(self.raw, &mut buf, target)
+        })
+    }
+
+    /// Run the filters over the contents of a blob, writing the result to
+    /// `out`.
+    pub fn stream_blob<W: Writer>(&self, blob: &Blob, out: &mut W)
+                                  -> Result<(), Error> {
+        stream(out, |target| unsafe {
+            raw::git_filter_list_stream_blob(self.raw, blob.raw(), target)
+        })
+    }
+
+    /// Run the filters over a file in the working directory, writing the
+    /// result to `out`.
+    ///
+    /// The file is read in chunks, so neither it nor the result are ever
+    /// loaded into memory in full. Relative paths are taken relative to the
+    /// working directory of the repository.
+    pub fn stream_file<W: Writer>(&self, path: &Path, out: &mut W)
+                                  -> Result<(), Error> {
+        let path = CString::from_slice(path.as_vec());
+        stream(out, |target| unsafe {
+            raw::git_filter_list_stream_file(self.raw, self.repo.raw(),
+                                             path.as_ptr(), target)
+        })
+    }
+}
+
+#[unsafe_destructor]
+impl<'repo> Drop for FilterList<'repo> {
+    fn drop(&mut self) {
+        unsafe { raw::git_filter_list_free(self.raw) }
+    }
+}
+
+fn stream<F>(out: &mut Writer, f: F) -> Result<(), Error>
+    where F: FnOnce(*mut raw::git_writestream) -> c_int
+{
+    let mut stream = WriteStream {
+        raw: raw::git_writestream {
+            write: stream_write,
+            close: stream_close,
+            free: stream_free,
+        },
+        out: out,
+        err: None,
+    };
+    let rc = f(&mut stream.raw);
+    match ::call::try(rc) {
+        Ok(..) => Ok(()),
+        Err(e) => {
+            panic::check();
+            Err(stream.err.take().map(io_error).unwrap_or(e))
+        }
+    }
+}
+
+extern fn stream_write(stream: *mut raw::git_writestream,
+                       buffer: *const c_char,
+                       len: size_t) -> c_int {
+    unsafe {
+        let stream = &mut *(stream as *mut WriteStream);
+        let data = slice::from_raw_parts(buffer as *const u8, len as usize);
+        match panic::wrap(|| stream.out.write_all(data)) {
+            Some(Ok(())) => 0,
+            Some(Err(e)) => { stream.err = Some(e); -1 }
+            None => -1,
+        }
+    }
+}
+
+extern fn stream_close(stream: *mut raw::git_writestream) -> c_int {
+    unsafe {
+        let stream = &mut *(stream as *mut WriteStream);
+        match panic::wrap(|| stream.out.flush()) {
+            Some(Ok(())) => 0,
+            Some(Err(e)) => { stream.err = Some(e); -1 }
+            None => -1,
+        }
+    }
+}
+
+// the stream lives on the stack of `stream`, so there is nothing to free
+extern fn stream_free(_stream: *mut raw::git_writestream) {}
+
+impl<'a> Binding for FilterSource<'a> {
+    type Raw = *const raw::git_filter_source;
+    unsafe fn from_raw(raw: *const raw::git_filter_source) -> FilterSource<'a> {
+        FilterSource {
+            raw: raw,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+    fn raw(&self) -> *const raw::git_filter_source { self.raw }
+}
+
+unsafe fn set_err(e: &Error) {
+    let s = CString::from_slice(e.message().as_bytes());
+    raw::git_error_set_str(raw::GITERR_FILTER as c_int, s.as_ptr());
+}
+
+// callback used by libgit2 to decide whether a `Filter` applies to a file
+extern fn filter_check(filter: *mut raw::git_filter,
+                       _payload: *mut *mut c_void,
+                       src: *const raw::git_filter_source,
+                       attr_values: *mut *const c_char) -> c_int {
+    unsafe {
+        let filter = &*(filter as *const RawFilter);
+        let src: FilterSource = Binding::from_raw(src);
+        let attrs = if attr_values.is_null() {
+            Vec::new()
+        } else {
+            range(0, filter.nattrs).map(|i| {
+                let value = attr_values.offset(i as isize);
+                let value = if (*value).is_null() {
+                    None
+                } else {
+                    Some(ffi::c_str_to_bytes(&*value))
+                };
+                AttrValue::from_bytes(value)
+            }).collect::<Vec<_>>()
+        };
+        match panic::wrap(|| filter.obj.check(&src, attrs.as_slice())) {
+            Some(Ok(true)) => 0,
+            Some(Ok(false)) => raw::GIT_PASSTHROUGH as c_int,
+            Some(Err(e)) => { set_err(&e); -1 }
+            None => -1,
+        }
+    }
+}
+
+// callback used by libgit2 to run a `Filter` over the contents of a file
+extern fn filter_apply(filter: *mut raw::git_filter,
+                       _payload: *mut *mut c_void,
+                       to: *mut raw::git_buf,
+                       from: *const raw::git_buf,
+                       src: *const raw::git_filter_source) -> c_int {
+    unsafe {
+        let filter = &*(filter as *const RawFilter);
+        let src: FilterSource = Binding::from_raw(src);
+        let input = if (*from).ptr.is_null() {
+            &[][]
+        } else {
+            slice::from_raw_parts((*from).ptr as *const u8,
+                                  (*from).size as usize)
+        };
+        match panic::wrap(|| filter.obj.apply(&src, input)) {
+            Some(Ok(Some(data))) => {
+                raw::git_buf_set(to, data.as_ptr() as *const c_void,
+                                 data.len() as size_t)
+            }
+            Some(Ok(None)) => raw::GIT_PASSTHROUGH as c_int,
+            Some(Err(e)) => { set_err(&e); -1 }
+            None => -1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ascii::AsciiExt;
+    use std::old_io::File;
+    use std::sync::{StaticMutex, MUTEX_INIT};
+    use {AttrValue, Error};
+    use super::{Filter, FilterList, FilterMode, FilterSource};
+
+    static LOCK: StaticMutex = MUTEX_INIT;
+
+    struct Upcase;
+
+    impl Filter for Upcase {
+        fn check(&self, _src: &FilterSource, attrs: &[AttrValue])
+                 -> Result<bool, Error> {
+            assert_eq!(attrs.len(), 1);
+            Ok(attrs[0] == AttrValue::String("upcase"))
+        }
+
+        fn apply(&self, src: &FilterSource, input: &[u8])
+                 -> Result<Option<Vec<u8>>, Error> {
+            Ok(match src.mode() {
+                FilterMode::ToOdb => Some(input.to_ascii_uppercase()),
+                FilterMode::ToWorktree => None,
+            })
+        }
+    }
+
+    #[test]
+    fn clean() {
+        let _g = LOCK.lock();
+        let (td, repo) = ::test::repo_init();
+        unsafe {
+            super::register("upcase", Some("filter"), super::DRIVER_PRIORITY,
+                            Upcase).unwrap();
+        }
+        File::create(&td.path().join(".gitattributes"))
+            .write_str("*.up filter=upcase\n").unwrap();
+        File::create(&td.path().join("a.up")).write_str("hello\n").unwrap();
+        File::create(&td.path().join("a.txt")).write_str("hello\n").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("a.up")).unwrap();
+        index.add_path(&Path::new("a.txt")).unwrap();
+        let up = index.get_path(&Path::new("a.up"), 0).unwrap().id;
+        let txt = index.get_path(&Path::new("a.txt"), 0).unwrap().id;
+        assert_eq!(repo.find_blob(up).unwrap().content(), b"HELLO\n");
+        assert_eq!(repo.find_blob(txt).unwrap().content(), b"hello\n");
+
+        unsafe { super::unregister("upcase").unwrap(); }
+        File::create(&td.path().join("a.up")).write_str("bye\n").unwrap();
+        index.add_path(&Path::new("a.up")).unwrap();
+        let up = index.get_path(&Path::new("a.up"), 0).unwrap().id;
+        assert_eq!(repo.find_blob(up).unwrap().content(), b"bye\n");
+    }
+
+    #[test]
+    fn stream() {
+        let _g = LOCK.lock();
+        let (td, repo) = ::test::repo_init();
+        unsafe {
+            super::register("upcase", Some("filter"), super::DRIVER_PRIORITY,
+                            Upcase).unwrap();
+        }
+        File::create(&td.path().join(".gitattributes"))
+            .write_str("*.up filter=upcase\n").unwrap();
+        File::create(&td.path().join("a.up")).write_str("hello\n").unwrap();
+
+        assert!(FilterList::load(&repo, &Path::new("a.txt"), FilterMode::ToOdb)
+                           .unwrap().is_none());
+        let list = FilterList::load(&repo, &Path::new("a.up"),
+                                    FilterMode::ToOdb).unwrap().unwrap();
+        let mut out = Vec::new();
+        list.stream_data(b"foo\n", &mut out).unwrap();
+        assert_eq!(out, b"FOO\n");
+        let mut out = Vec::new();
+        list.stream_file(&Path::new("a.up"), &mut out).unwrap();
+        assert_eq!(out, b"HELLO\n");
+
+        let id = repo.blob(b"bar\n").unwrap();
+        let blob = repo.find_blob(id).unwrap();
+        let list = FilterList::load(&repo, &Path::new("a.up"),
+                                    FilterMode::ToWorktree).unwrap().unwrap();
+        let mut out = Vec::new();
+        list.stream_blob(&blob, &mut out).unwrap();
+        assert_eq!(out, b"bar\n");
+        drop(list);
+
+        unsafe { super::unregister("upcase").unwrap(); }
+    }
+}
@@ -0,0 +1,357 @@
+//! Temporary repositories populated from a declarative description, for
+//! writing tests against repositories without a long series of plumbing
+//! calls.
+//!
+//! ```no_run
+//! # #![allow(unstable)]
+//! use git2::fixture::FixtureBuilder;
+//!
+//! let fixture = FixtureBuilder::new()
+//!                              .file("README", b"hello\n")
+//!                              .commit("initial")
+//!                              .tag("v1", Some("first release"))
+//!                              // branches off the initial commit on master
+//!                              .checkout("feature")
+//!                              .file("src/lib.rs", b"")
+//!                              .remove("README")
+//!                              .commit("add a library")
+//!                              .build().unwrap();
+//! let repo = fixture.repo();
+//! ```
+
+use std::old_io::{self, File, TempDir};
+use std::old_io::fs::{self, PathExtensions};
+
+use {build, Commit, Error, ErrorCode, Repository, Signature, Time, TreeUpdate};
+use util::io_error;
+
+/// A builder for a temporary repository, described as a sequence of steps
+/// which are replayed in order by `build`.
+///
+/// Files are added to and removed from the next commit, which is made on
+/// the branch `HEAD` points to. All commits are authored and committed by
+/// "name <email>" at fixed, increasing times, so building the same
+/// description always produces the same object ids. Repositories are created
+/// with `user.name` and `user.email` set to the same identity.
+pub struct FixtureBuilder {
+    bare: bool,
+    steps: Vec<Step>,
+}
+
+/// A temporary repository created by `FixtureBuilder`, which is deleted
+/// along with its directory when dropped.
+pub struct Fixture {
+    repo: Repository,
+    td: TempDir,
+}
+
+enum Step {
+    File(String, Vec<u8>),
+    Remove(String),
+    Commit(String),
+    Branch(String),
+    Checkout(String),
+    Tag(String, Option<String>),
+    Config(String, String),
+}
+
+impl FixtureBuilder {
+    /// Create a new builder for a repository with no commits.
+    pub fn new() -> FixtureBuilder {
+        FixtureBuilder { bare: false, steps: Vec::new() }
+    }
+
+    /// Create a bare repository rather than one with a working directory.
+    pub fn bare(&mut self, bare: bool) -> &mut FixtureBuilder {
+        self.bare = bare;
+        self
+    }
+
+    /// Add a regular file with the given contents, or replace the contents of
+    /// an existing file, in the next commit.
+    ///
+    /// `path` is relative to the root of the repository and separated by
+    /// `/`. Files added after the last commit are only written to the
+    /// working directory, as uncommitted changes.
+    pub fn file(&mut self, path: &str, contents: &[u8])
+                -> &mut FixtureBuilder {
+        self.steps.push(Step::File(path.to_string(), contents.to_vec()));
+        self
+    }
+
+    /// Remove a file from the next commit.
+    ///
+    /// Files removed after the last commit are only removed from the working
+    /// directory, as uncommitted changes.
+    pub fn remove(&mut self, path: &str) -> &mut FixtureBuilder {
+        self.steps.push(Step::Remove(path.to_string()));
+        self
+    }
+
+    /// Commit the files added and removed since the previous commit on the
+    /// branch `HEAD` points to.
+    pub fn commit(&mut self, message: &str) -> &mut FixtureBuilder {
+        self.steps.push(Step::Commit(message.to_string()));
+        self
+    }
+
+    /// Create a branch pointing at the current `HEAD` commit.
+    pub fn branch(&mut self, name: &str) -> &mut FixtureBuilder {
+        self.steps.push(Step::Branch(name.to_string()));
+        self
+    }
+
+    /// Point `HEAD` at the branch with the given name, on which the following
+    /// commits are made.
+    ///
+    /// A branch which does not exist yet is created at the current `HEAD`
+    /// commit, like `git checkout -b`, or starts out as an orphan branch if
+    /// nothing has been committed yet. The last branch checked out is the
+    /// one checked out in the working directory of the repository.
+    pub fn checkout(&mut self, name: &str) -> &mut FixtureBuilder {
+        self.steps.push(Step::Checkout(name.to_string()));
+        self
+    }
+
+    /// Tag the current `HEAD` commit, with an annotated tag if a message is
+    /// given or a lightweight tag otherwise.
+    pub fn tag(&mut self, name: &str, message: Option<&str>)
+               -> &mut FixtureBuilder {
+        self.steps.push(Step::Tag(name.to_string(),
+                                  message.map(|s| s.to_string())));
+        self
+    }
+
+    /// Set a configuration variable in the repository.
+    pub fn config(&mut self, name: &str, value: &str) -> &mut FixtureBuilder {
+        self.steps.push(Step::Config(name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Create the repository in a new temporary directory and replay the
+    /// steps of the description in it.
+    pub fn build(&self) -> Result<Fixture, Error> {
+        let td = try!(TempDir::new("git2-fixture").map_err(io_error));
+        let repo = if self.bare {
+            try!(Repository::init_bare(td.path()))
+        } else {
+            try!(Repository::init(td.path()))
+        };
+        {
+            let mut config = try!(repo.config());
+            try!(config.set_str("user.name", "name"));
+            try!(config.set_str("user.email", "email"));
+        }
+
+        let mut pending = Vec::new();
+        let mut time = 1234567890;
+        for step in self.steps.iter() {
+            match *step {
+                Step::File(ref path, ref contents) => {
+                    pending.push((path.as_slice(), Some(contents.as_slice())));
+                }
+                Step::Remove(ref path) => {
+                    pending.push((path.as_slice(), None));
+                }
+                Step::Commit(ref message) => {
+                    let parent = try!(head(&repo));
+                    let base = match parent {
+                        Some(ref parent) => try!(parent.tree()),
+                        None => {
+                            let empty = try!(try!(repo.treebuilder(None))
+                                                 .write());
+                            try!(repo.find_tree(empty))
+                        }
+                    };
+                    let mut updates = Vec::new();
+                    for &(path, contents) in pending.iter() {
+                        updates.push(match contents {
+                            Some(contents) => {
+                                let id = try!(repo.blob(contents));
+                                TreeUpdate::upsert(path, id, 0o100644)
+                            }
+                            None => TreeUpdate::remove(path),
+                        });
+                    }
+                    pending.truncate(0);
+                    let tree = try!(base.create_updated(&repo,
+                                                        updates.as_slice()));
+                    let tree = try!(repo.find_tree(tree));
+                    let sig = try!(signature(&mut time));
+                    let parents = parent.iter().collect::<Vec<_>>();
+                    try!(repo.commit(Some("HEAD"), &sig, &sig,
+                                     message.as_slice(), &tree,
+                                     parents.as_slice()));
+                }
+                Step::Branch(ref name) => {
+                    let target = try!(try!(head(&repo)).ok_or_else(|| {
+                        Error::from_str("cannot branch before the first commit")
+                    }));
+                    try!(repo.branch(name.as_slice(), &target, false));
+                }
+                Step::Checkout(ref name) => {
+                    let refname = format!("refs/heads/{}", name);
+                    if repo.find_reference(refname.as_slice()).is_err() {
+                        match try!(head(&repo)) {
+                            Some(target) => {
+                                try!(repo.branch(name.as_slice(), &target,
+                                                 false));
+                            }
+                            None => {}
+                        }
+                    }
+                    try!(repo.set_head(refname.as_slice()));
+                }
+                Step::Tag(ref name, ref message) => {
+                    let target = try!(try!(head(&repo)).ok_or_else(|| {
+                        Error::from_str("cannot tag before the first commit")
+                    }));
+                    let target = try!(repo.find_object(target.id(), None));
+                    match *message {
+                        Some(ref message) => {
+                            let sig = try!(signature(&mut time));
+                            try!(repo.tag(name.as_slice(), &target, &sig,
+                                          message.as_slice(), false));
+                        }
+                        None => {
+                            try!(repo.tag_lightweight(name.as_slice(), &target,
+                                                      false));
+                        }
+                    }
+                }
+                Step::Config(ref name, ref value) => {
+                    let mut config = try!(repo.config());
+                    try!(config.set_str(name.as_slice(), value.as_slice()));
+                }
+            }
+        }
+
+        let workdir = match repo.workdir() {
+            Some(workdir) => workdir,
+            None => return Ok(Fixture { repo: repo, td: td }),
+        };
+        if try!(head(&repo)).is_some() {
+            let mut checkout = build::CheckoutBuilder::new();
+            try!(repo.checkout_head(Some(checkout.force())));
+        }
+        for &(path, contents) in pending.iter() {
+            let file = workdir.join(path);
+            match contents {
+                Some(contents) => {
+                    try!(fs::mkdir_recursive(&file.dir_path(),
+                                             old_io::USER_DIR)
+                            .map_err(io_error));
+                    try!(File::create(&file).write_all(contents)
+                                            .map_err(io_error));
+                }
+                None if file.exists() => {
+                    try!(fs::unlink(&file).map_err(io_error));
+                }
+                None => {}
+            }
+        }
+        Ok(Fixture { repo: repo, td: td })
+    }
+}
+
+fn head(repo: &Repository) -> Result<Option<Commit>, Error> {
+    match repo.refname_to_id("HEAD") {
+        Ok(id) => repo.find_commit(id).map(Some),
+        Err(ref e) if e.code() == ErrorCode::NotFound ||
+                      e.code() == ErrorCode::UnbornBranch => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn signature(time: &mut i64) -> Result<Signature<'static>, Error> {
+    *time += 60;
+    Signature::new("name", "email", &Time::new(*time, 0))
+}
+
+impl Fixture {
+    /// Get the repository of this fixture.
+    pub fn repo(&self) -> &Repository { &self.repo }
+
+    /// Get the temporary directory holding the repository, which is its
+    /// working directory unless it is bare.
+    pub fn path(&self) -> &Path { self.td.path() }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::File;
+    use std::old_io::fs::PathExtensions;
+    use ObjectType;
+    use super::FixtureBuilder;
+
+    #[test]
+    fn smoke() {
+        let fixture = FixtureBuilder::new()
+                                     .file("README", b"hello\n")
+                                     .file("src/lib.rs", b"")
+                                     .commit("initial")
+                                     .tag("v1", Some("first"))
+                                     .branch("old")
+                                     .checkout("feature")
+                                     .remove("README")
+                                     .file("src/lib.rs", b"fn main() {}\n")
+                                     .commit("second")
+                                     .tag("light", None)
+                                     .config("core.foo", "bar")
+                                     .file("dirty", b"x")
+                                     .build().unwrap();
+        let repo = fixture.repo();
+
+        let head = repo.head().unwrap();
+        assert_eq!(head.name(), Some("refs/heads/feature"));
+        let second = repo.find_commit(head.target().unwrap()).unwrap();
+        assert_eq!(second.message(), Some("second"));
+        assert_eq!(second.author().name(), Some("name"));
+        let tree = second.tree().unwrap();
+        assert!(tree.get_name("README").is_none());
+        assert!(tree.get_path(&Path::new("src/lib.rs")).is_ok());
+
+        let initial = second.parent(0).unwrap();
+        assert_eq!(initial.parent_count(), 0);
+        assert!(initial.tree().unwrap().get_name("README").is_some());
+        assert_eq!(repo.refname_to_id("refs/heads/old").unwrap(),
+                   initial.id());
+        assert_eq!(repo.refname_to_id("refs/heads/master").unwrap(),
+                   initial.id());
+        let tag = repo.revparse_single("refs/tags/v1").unwrap();
+        assert_eq!(tag.kind(), Some(ObjectType::Tag));
+        assert_eq!(repo.refname_to_id("refs/tags/light").unwrap(),
+                   second.id());
+        assert_eq!(repo.config().unwrap().get_str("core.foo").unwrap(),
+                   "bar");
+
+        let lib = fixture.path().join("src/lib.rs");
+        assert_eq!(File::open(&lib).read_to_string().unwrap(),
+                   "fn main() {}\n");
+        assert!(!fixture.path().join("README").exists());
+        assert!(fixture.path().join("dirty").exists());
+
+        // the same description builds the same history
+        let again = FixtureBuilder::new()
+                                   .file("README", b"hello\n")
+                                   .file("src/lib.rs", b"")
+                                   .commit("initial")
+                                   .build().unwrap();
+        let id = again.repo().head().unwrap().target().unwrap();
+        assert_eq!(id, initial.id());
+
+        let path = fixture.path().clone();
+        drop(fixture);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn bare() {
+        let fixture = FixtureBuilder::new().bare(true)
+                                     .file("a", b"a").commit("a")
+                                     .build().unwrap();
+        assert!(fixture.repo().is_bare());
+        assert!(FixtureBuilder::new().branch("x").build().is_err());
+        assert!(FixtureBuilder::new().tag("x", None).build().is_err());
+    }
+}
@@ -1,12 +1,14 @@
 use std::ffi::{self, CString};
-use std::iter::Range;
+use std::iter::{range, Range};
+use std::old_io::{self, fs, File};
 use std::old_path::PosixPath;
 
 use libc::{c_int, c_uint, size_t, c_void, c_char, c_ushort};
 
 use {raw, panic, Repository, Error, Tree, Oid, IndexAddOption, IndexTime};
+use {Blob, Buf, IndexCapabilities, Patch};
 use IntoCString;
-use util::Binding;
+use util::{Binding, io_error};
 
 /// A structure to represent a git [index][1]
 ///
@@ -48,6 +50,27 @@ pub struct IndexEntry {
     pub path: Vec<u8>,
 }
 
+/// An entry of the resolve undo (REUC) extension of an index, which records
+/// the ancestor, our and their side of a path whose conflict was resolved.
+///
+/// A side with a mode of 0 did not exist, and its id is zero.
+#[allow(missing_docs)]
+pub struct ResolveUndoEntry {
+    pub path: Vec<u8>,
+    pub ancestor_mode: u32,
+    pub ancestor_id: Oid,
+    pub our_mode: u32,
+    pub our_id: Oid,
+    pub their_mode: u32,
+    pub their_id: Oid,
+}
+
+/// An iterator over the resolve undo entries in an index
+pub struct ResolveUndoEntries<'index> {
+    range: Range<usize>,
+    index: &'index Index,
+}
+
 impl Index {
     /// Creates a new in-memory index.
     ///
@@ -86,13 +109,46 @@ impl Index {
     /// given 'source_entry', it will be replaced. Otherwise, the 'source_entry'
     /// will be added.
     pub fn add(&mut self, source_entry: &IndexEntry) -> Result<(), Error> {
-        let entry = source_entry.raw();
+        let path = CString::from_slice(&source_entry.path);
+        let mut entry = source_entry.raw();
+        entry.path = path.as_ptr();
         unsafe {
             try_call!(raw::git_index_add(self.raw, &entry));
             Ok(())
         }
     }
 
+    /// Add or update an index entry from a buffer in memory
+    ///
+    /// This method will create a blob in the repository that owns the index and
+    /// then add the index entry to the index. The `path` of the entry
+    /// represents the position of the blob relative to the repository's root
+    /// folder.
+    ///
+    /// If a previous index entry exists that has the same path as the given
+    /// 'entry', it will be replaced. Otherwise, the 'entry' will be added.
+    /// The `id` and `file_size` of the 'entry' are updated with the real value
+    /// of the blob.
+    ///
+    /// This forces the file to be added to the index, not looking at gitignore
+    /// rules.
+    ///
+    /// If this file currently is the result of a merge conflict, this file will
+    /// no longer be marked as conflicting. The data about the conflict will be
+    /// moved to the "resolve undo" (REUC) section.
+    pub fn add_frombuffer(&mut self, entry: &IndexEntry, data: &[u8])
+                          -> Result<(), Error> {
+        let path = CString::from_slice(&entry.path);
+        let mut raw = entry.raw();
+        raw.path = path.as_ptr();
+        unsafe {
+            try_call!(raw::git_index_add_frombuffer(self.raw, &raw,
+                                                    data.as_ptr() as *const c_void,
+                                                    data.len() as size_t));
+        }
+        Ok(())
+    }
+
     /// Add or update an index entry from a file on disk
     ///
     /// The file path must be relative to the repository's working folder and
@@ -176,6 +232,65 @@ impl Index {
         return Ok(());
     }
 
+    /// Read the index's capability flags.
+    ///
+    /// The capabilities describe how the index treats the filesystem, for
+    /// example whether paths are matched case insensitively.
+    pub fn caps(&self) -> IndexCapabilities {
+        let caps = unsafe { raw::git_index_caps(&*self.raw) };
+        IndexCapabilities::from_bits_truncate(caps as u32)
+    }
+
+    /// Set the index's capability flags.
+    ///
+    /// If the index is associated with a repository, the repository's
+    /// `core.ignorecase`, `core.filemode` and `core.symlinks` configuration
+    /// determine the capabilities by default; this overrides those values.
+    pub fn set_caps(&mut self, caps: IndexCapabilities) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_index_set_caps(self.raw, caps.bits() as c_int));
+        }
+        Ok(())
+    }
+
+    /// Reset the index's capabilities to those dictated by the configuration
+    /// of the owning repository.
+    pub fn set_caps_from_owner(&mut self) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_index_set_caps(self.raw,
+                                              raw::GIT_INDEXCAP_FROM_OWNER));
+        }
+        Ok(())
+    }
+
+    /// Get the on-disk version of the index format.
+    ///
+    /// Valid versions are 2, 3 and 4.
+    pub fn version(&self) -> u32 {
+        unsafe { raw::git_index_version(self.raw) as u32 }
+    }
+
+    /// Set the on-disk version of the index format.
+    ///
+    /// Version 4 enables path prefix compression which makes the index
+    /// significantly smaller for repositories with deep directory structures.
+    /// The new version takes effect the next time the index is written.
+    pub fn set_version(&mut self, version: u32) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_index_set_version(self.raw, version as c_uint));
+        }
+        Ok(())
+    }
+
+    /// Get the checksum of the index file as it was last read or written.
+    ///
+    /// This can be used to cheaply determine whether the index on disk has
+    /// changed. An in-memory index which has never been written has a zero
+    /// checksum.
+    pub fn checksum(&self) -> Oid {
+        unsafe { Binding::from_raw(raw::git_index_checksum(self.raw)) }
+    }
+
     /// Clear the contents (all the entries) of an index object.
     ///
     /// This clears the index object in memory; changes must be explicitly
@@ -198,6 +313,11 @@ impl Index {
         }
     }
 
+    /// Determine if the index contains entries representing file conflicts.
+    pub fn has_conflicts(&self) -> bool {
+        unsafe { raw::git_index_has_conflicts(&*self.raw) == 1 }
+    }
+
     /// Get an iterator over the entries in this index.
     pub fn iter(&self) -> IndexEntries {
         IndexEntries { range: range(0, self.len()), index: self }
@@ -213,6 +333,167 @@ impl Index {
         }
     }
 
+    /// Add the ancestor, our and their side of a conflicted path to the
+    /// index, at stages 1, 2 and 3.
+    ///
+    /// Any of the sides may be `None` if the path does not exist on that
+    /// side. The stages of the entries are ignored, and the entry of the path
+    /// at stage 0 is removed.
+    pub fn conflict_add(&mut self,
+                        ancestor: Option<&IndexEntry>,
+                        ours: Option<&IndexEntry>,
+                        theirs: Option<&IndexEntry>) -> Result<(), Error> {
+        let entries = [ancestor, ours, theirs];
+        let paths = entries.iter().map(|e| {
+            (*e).map(|e| CString::from_slice(&e.path))
+        }).collect::<Vec<_>>();
+        let raws = entries.iter().zip(paths.iter()).map(|(e, path)| {
+            (*e).map(|e| {
+                let mut raw = e.raw();
+                raw.path = path.as_ref().unwrap().as_ptr();
+                raw
+            })
+        }).collect::<Vec<_>>();
+        unsafe {
+            try_call!(raw::git_index_conflict_add(self.raw, raws[0].as_ref(),
+                                                  raws[1].as_ref(),
+                                                  raws[2].as_ref()));
+        }
+        Ok(())
+    }
+
+    /// Get the ancestor, our and their side of a conflicted path.
+    ///
+    /// A side is `None` if the path does not exist on that side. Returns an
+    /// error with the code `NotFound` if the path is not conflicted.
+    pub fn conflict_get(&self, path: &Path)
+                        -> Result<(Option<IndexEntry>, Option<IndexEntry>,
+                                   Option<IndexEntry>), Error> {
+        let path = CString::from_slice(path.as_vec());
+        let mut ancestor = 0 as *const raw::git_index_entry;
+        let mut ours = 0 as *const raw::git_index_entry;
+        let mut theirs = 0 as *const raw::git_index_entry;
+        unsafe {
+            try_call!(raw::git_index_conflict_get(&mut ancestor, &mut ours,
+                                                  &mut theirs, self.raw,
+                                                  path));
+            let entry = |ptr: *const raw::git_index_entry| {
+                if ptr.is_null() {None} else {Some(Binding::from_raw(*ptr))}
+            };
+            Ok((entry(ancestor), entry(ours), entry(theirs)))
+        }
+    }
+
+    /// Remove all sides of a conflicted path from the index.
+    pub fn conflict_remove(&mut self, path: &Path) -> Result<(), Error> {
+        let path = CString::from_slice(path.as_vec());
+        unsafe {
+            try_call!(raw::git_index_conflict_remove(self.raw, path));
+        }
+        Ok(())
+    }
+
+    /// Get the count of entries in the resolve undo extension of the index.
+    pub fn resolve_undo_len(&self) -> usize {
+        unsafe { raw::git_index_reuc_entrycount(self.raw) as usize }
+    }
+
+    /// Get one of the resolve undo entries in the index by its position.
+    pub fn resolve_undo_get(&self, n: usize) -> Option<ResolveUndoEntry> {
+        unsafe {
+            let ptr = raw::git_index_reuc_get_byindex(self.raw, n as size_t);
+            if ptr.is_null() {None} else {Some(resolve_undo_entry(ptr))}
+        }
+    }
+
+    /// Get the resolve undo entry of a path.
+    pub fn resolve_undo_get_path(&self, path: &Path)
+                                 -> Option<ResolveUndoEntry> {
+        let path = CString::from_slice(path.as_vec());
+        unsafe {
+            let ptr = call!(raw::git_index_reuc_get_bypath(self.raw, path));
+            if ptr.is_null() {None} else {Some(resolve_undo_entry(ptr))}
+        }
+    }
+
+    /// Get an iterator over the resolve undo entries in this index.
+    pub fn resolve_undo_iter(&self) -> ResolveUndoEntries {
+        ResolveUndoEntries { range: range(0, self.resolve_undo_len()),
+                             index: self }
+    }
+
+    /// Add or replace the resolve undo entry of a path.
+    pub fn add_resolve_undo(&mut self, entry: &ResolveUndoEntry)
+                            -> Result<(), Error> {
+        let path = CString::from_slice(&entry.path);
+        unsafe {
+            try_call!(raw::git_index_reuc_add(self.raw, path,
+                                              entry.ancestor_mode as c_int,
+                                              entry.ancestor_id.raw(),
+                                              entry.our_mode as c_int,
+                                              entry.our_id.raw(),
+                                              entry.their_mode as c_int,
+                                              entry.their_id.raw()));
+        }
+        Ok(())
+    }
+
+    /// Remove one of the resolve undo entries in the index by its position.
+    pub fn remove_resolve_undo(&mut self, n: usize) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_index_reuc_remove(self.raw, n as size_t));
+        }
+        Ok(())
+    }
+
+    /// Remove all resolve undo entries from the index.
+    pub fn clear_resolve_undo(&mut self) {
+        unsafe { raw::git_index_reuc_clear(self.raw) }
+    }
+
+    /// Bring back the conflict of a path which was resolved, from its resolve
+    /// undo entry.
+    ///
+    /// The entry of the path at stage 0 is replaced with the sides recorded
+    /// when the conflict was resolved, and the resolve undo entry is removed,
+    /// as with `git update-index --unresolve`. Checking out the path from the
+    /// index with `CheckoutBuilder::conflict_style_merge` afterwards writes
+    /// it with conflict markers again, as with `git checkout --conflict=merge`.
+    ///
+    /// Returns an error with the code `NotFound` if the path has no resolve
+    /// undo entry.
+    pub fn unresolve(&mut self, path: &Path) -> Result<(), Error> {
+        let cpath = CString::from_slice(path.as_vec());
+        let mut pos = 0 as size_t;
+        unsafe {
+            try_call!(raw::git_index_reuc_find(&mut pos, self.raw, cpath));
+        }
+        let reuc = self.resolve_undo_get(pos as usize).unwrap();
+        let side = |mode: u32, id: Oid| {
+            if mode == 0 { return None }
+            Some(IndexEntry {
+                ctime: IndexTime::new(0, 0),
+                mtime: IndexTime::new(0, 0),
+                dev: 0,
+                ino: 0,
+                mode: mode,
+                uid: 0,
+                gid: 0,
+                file_size: 0,
+                id: id,
+                flags: 0,
+                flags_extended: 0,
+                path: reuc.path.clone(),
+            })
+        };
+        let ancestor = side(reuc.ancestor_mode, reuc.ancestor_id);
+        let ours = side(reuc.our_mode, reuc.our_id);
+        let theirs = side(reuc.their_mode, reuc.their_id);
+        try!(self.conflict_add(ancestor.as_ref(), ours.as_ref(),
+                               theirs.as_ref()));
+        self.remove_resolve_undo(pos as usize)
+    }
+
     /// Get the full path to the index file on disk.
     ///
     /// Returns `None` if this is an in-memory index.
@@ -381,6 +662,151 @@ impl Index {
             Ok(Binding::from_raw(&raw as *const _))
         }
     }
+
+    /// Stage only some of the changes made to a file in the working
+    /// directory, like `git add -p`.
+    ///
+    /// The `hunks` are indices into the hunks of the patch from the index to
+    /// the working directory for `path`, as computed by `Patch::from_buffers`
+    /// with default options. The selected hunks are applied to the contents
+    /// of the file in the index, and the result is written as a new blob
+    /// which replaces the index entry. Other hunks remain unstaged.
+    ///
+    /// The working directory file is passed through its clean filters, such
+    /// as line ending conversion, before it is compared. The mode of an
+    /// existing entry is kept, while a new entry takes the mode of the file.
+    /// Symbolic links cannot be staged this way.
+    ///
+    /// The index must belong to a repository with a working directory, and
+    /// is not written back to disk.
+    pub fn stage_hunks(&mut self, path: &Path, hunks: &[usize])
+                       -> Result<(), Error> {
+        let repo = unsafe { raw::git_index_owner(self.raw) };
+        if repo.is_null() {
+            return Err(Error::from_str("index is not owned by a repository"))
+        }
+        let workdir = unsafe { raw::git_repository_workdir(repo) };
+        if workdir.is_null() {
+            return Err(Error::from_str("cannot stage hunks in a bare \
+                                        repository"))
+        }
+        let workdir = Path::new(unsafe { ffi::c_str_to_bytes(&workdir) });
+        let stat = try!(fs::lstat(&workdir.join(path)).map_err(io_error));
+        if stat.kind == old_io::FileType::Symlink {
+            return Err(Error::from_str("cannot stage hunks of a symbolic \
+                                        link"))
+        }
+        let new = try!(read_clean(repo, path));
+
+        let (entry, blob) = match self.get_path(path, 0) {
+            Some(entry) => {
+                let mut blob = 0 as *mut raw::git_blob;
+                let blob: Blob = unsafe {
+                    try_call!(raw::git_blob_lookup(&mut blob, repo,
+                                                   entry.id.raw()));
+                    Binding::from_raw(blob)
+                };
+                (entry, Some(blob))
+            }
+            None => {
+                let entry = IndexEntry {
+                    ctime: IndexTime::new(0, 0),
+                    mtime: IndexTime::new(0, 0),
+                    dev: 0,
+                    ino: 0,
+                    mode: if stat.perm.contains(old_io::USER_EXECUTE) {
+                        0o100755
+                    } else {
+                        0o100644
+                    },
+                    uid: 0,
+                    gid: 0,
+                    file_size: 0,
+                    id: Oid::zero(),
+                    flags: 0,
+                    flags_extended: 0,
+                    path: path.as_vec().to_vec(),
+                };
+                (entry, None)
+            }
+        };
+        let old = blob.as_ref().map(|b| b.content()).unwrap_or(&[]);
+        let patch = try!(Patch::from_buffers(old, Some(path), new.as_slice(),
+                                             Some(path), None));
+        if hunks.iter().any(|h| *h >= patch.num_hunks()) {
+            return Err(Error::from_str("hunk index out of range"))
+        }
+
+        let old_lines = lines(old);
+        let mut data = Vec::new();
+        let mut next = 0;
+        for i in range(0, patch.num_hunks()) {
+            let (hunk, nlines) = try!(patch.hunk(i));
+            let start = hunk.old_start() as usize;
+            let start = if hunk.old_lines() == 0 { start } else { start - 1 };
+            for line in old_lines[next..start].iter() {
+                data.push_all(*line);
+            }
+            next = start + hunk.old_lines() as usize;
+
+            let staged = hunks.contains(&i);
+            for j in range(0, nlines) {
+                let line = try!(patch.line_in_hunk(i, j));
+                match line.origin() {
+                    ' ' => data.push_all(line.content()),
+                    '+' if staged => data.push_all(line.content()),
+                    '-' if !staged => data.push_all(line.content()),
+                    _ => {}
+                }
+            }
+        }
+        for line in old_lines[next..].iter() {
+            data.push_all(*line);
+        }
+        self.add_frombuffer(&entry, data.as_slice())
+    }
+}
+
+// Read a file in the working directory as it would be added to the index,
+// with the clean filters of its attributes applied.
+fn read_clean(repo: *mut raw::git_repository, path: &Path)
+              -> Result<Vec<u8>, Error> {
+    let cpath = CString::from_slice(path.as_vec());
+    let mut filters = 0 as *mut raw::git_filter_list;
+    unsafe {
+        try_call!(raw::git_filter_list_load(&mut filters, repo,
+                                            0 as *mut raw::git_blob, cpath,
+                                            raw::GIT_FILTER_TO_ODB,
+                                            raw::GIT_FILTER_DEFAULT));
+        if filters.is_null() {
+            let workdir = raw::git_repository_workdir(repo);
+            let workdir = Path::new(ffi::c_str_to_bytes(&workdir));
+            return File::open(&workdir.join(path)).read_to_end()
+                        .map_err(io_error)
+        }
+        let buf = Buf::new();
+        let rc = raw::git_filter_list_apply_to_file(buf.raw(), filters, repo,
+                                                    cpath.as_ptr());
+        raw::git_filter_list_free(filters);
+        try!(::call::try(rc));
+        Ok(buf.to_vec())
+    }
+}
+
+// Split data into lines, keeping their line endings.
+fn lines(data: &[u8]) -> Vec<&[u8]> {
+    let mut ret = Vec::new();
+    let mut start = 0;
+    for (i, b) in data.iter().enumerate() {
+        if *b == b'\n' {
+            ret.push(&data[start..i + 1]);
+            start = i + 1;
+        }
+    }
+    if start < data.len() {
+        ret.push(&data[start..]);
+    }
+    ret
 }
 
 impl Binding for Index {
@@ -417,6 +843,27 @@ impl<'index> Iterator for IndexEntries<'index> {
     }
 }
 
+impl<'index> Iterator for ResolveUndoEntries<'index> {
+    type Item = ResolveUndoEntry;
+    fn next(&mut self) -> Option<ResolveUndoEntry> {
+        self.range.next().map(|i| self.index.resolve_undo_get(i).unwrap())
+    }
+}
+
+unsafe fn resolve_undo_entry(raw: *const raw::git_index_reuc_entry)
+                             -> ResolveUndoEntry {
+    let raw = &*raw;
+    ResolveUndoEntry {
+        path: ffi::c_str_to_bytes(&(raw.path as *const c_char)).to_vec(),
+        ancestor_mode: raw.mode[0],
+        ancestor_id: Binding::from_raw(&raw.oid[0] as *const _),
+        our_mode: raw.mode[1],
+        our_id: Binding::from_raw(&raw.oid[1] as *const _),
+        their_mode: raw.mode[2],
+        their_id: Binding::from_raw(&raw.oid[2] as *const _),
+    }
+}
+
 impl Binding for IndexEntry {
     type Raw = raw::git_index_entry;
 
@@ -448,17 +895,17 @@ impl Binding for IndexEntry {
             mode: self.mode as c_uint,
             uid: self.uid as c_uint,
             gid: self.gid as c_uint,
-            file_size: self.file_size as raw::git_off_t,
+            file_size: self.file_size as u32,
             id: unsafe { *self.id.raw() },
             flags: self.flags as c_ushort,
             flags_extended: self.flags_extended as c_ushort,
             path: self.path.as_ptr() as *const _,
             mtime: raw::git_index_time {
-                seconds: self.mtime.seconds() as raw::git_time_t,
+                seconds: self.mtime.seconds() as i32,
                 nanoseconds: self.mtime.nanoseconds() as c_uint,
             },
             ctime: raw::git_index_time {
-                seconds: self.ctime.seconds() as raw::git_time_t,
+                seconds: self.ctime.seconds() as i32,
                 nanoseconds: self.ctime.nanoseconds() as c_uint,
             },
         }
@@ -467,10 +914,13 @@ impl Binding for IndexEntry {
 
 #[cfg(test)]
 mod tests {
+    use std::iter::range;
     use std::old_io::{self, fs, File, TempDir};
     use url::Url;
 
-    use {Index, Repository, ResetType};
+    use {Index, IndexEntry, IndexTime, Repository, ResetType};
+    use {ErrorCode, Oid, ResolveUndoEntry};
+    use build::CheckoutBuilder;
 
     #[test]
     fn smoke() {
@@ -483,6 +933,31 @@ mod tests {
         assert!(index.read(true).is_err());
     }
 
+    #[test]
+    fn in_memory_read_tree() {
+        let (_td, repo) = ::test::repo_init();
+        let blob = repo.blob(b"data").unwrap();
+        let mut builder = repo.treebuilder(None).unwrap();
+        builder.insert("a", blob, 0o100644).unwrap();
+        builder.insert("b", blob, 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+
+        let mut index = Index::new().unwrap();
+        index.read_tree(&tree).unwrap();
+        assert_eq!(index.len(), 2);
+        index.remove(&Path::new("a"), 0).unwrap();
+        let id = index.write_tree_to(&repo).unwrap();
+        let written = repo.find_tree(id).unwrap();
+        assert_eq!(written.len(), 1);
+        assert_eq!(written.get(0).unwrap().name(), Some("b"));
+
+        // The repository's own index is left untouched
+        assert_eq!(repo.index().unwrap().len(), 0);
+
+        index.clear().unwrap();
+        assert_eq!(index.len(), 0);
+    }
+
     #[test]
     fn smoke_from_repo() {
         let (_td, repo) = ::test::repo_init();
@@ -497,6 +972,24 @@ mod tests {
         index.write_tree_to(&repo).unwrap();
     }
 
+    #[test]
+    fn version_and_caps() {
+        let (_td, repo) = ::test::repo_init();
+        let mut index = repo.index().unwrap();
+        assert!(index.version() >= 2);
+        index.set_version(4).unwrap();
+        assert_eq!(index.version(), 4);
+        assert!(index.set_version(1).is_err());
+        index.write().unwrap();
+        assert!(!index.checksum().is_zero());
+
+        index.set_caps(::INDEXCAP_IGNORE_CASE | ::INDEXCAP_NO_SYMLINKS).unwrap();
+        assert!(index.caps().contains(::INDEXCAP_IGNORE_CASE));
+        assert!(index.caps().contains(::INDEXCAP_NO_SYMLINKS));
+        assert!(!index.caps().contains(::INDEXCAP_NO_FILEMODE));
+        index.set_caps_from_owner().unwrap();
+    }
+
     #[test]
     fn add_all() {
         let (_td, repo) = ::test::repo_init();
@@ -527,6 +1020,35 @@ mod tests {
         assert!(called);
     }
 
+    #[test]
+    fn add_all_skip_and_update_all() {
+        let (_td, repo) = ::test::repo_init();
+        let mut index = repo.index().unwrap();
+
+        let root = repo.path().dir_path();
+        fs::mkdir(&root.join("vendor"), old_io::USER_DIR).unwrap();
+        File::create(&root.join("vendor/lib")).unwrap();
+        File::create(&root.join("main")).unwrap();
+        index.add_all(["*"].iter(), ::ADD_DEFAULT,
+                      Some(&mut |a: &[u8], _b: &[u8]| {
+            if a.starts_with(b"vendor/") {1} else {0}
+        })).unwrap();
+        assert_eq!(index.len(), 1);
+        assert!(index.get_path(&Path::new("main"), 0).is_some());
+        assert!(index.get_path(&Path::new("vendor/lib"), 0).is_none());
+
+        fs::unlink(&root.join("main")).unwrap();
+        let mut called = false;
+        index.update_all(["*"].iter(), Some(&mut |a: &[u8], _b: &[u8]| {
+            assert!(!called);
+            called = true;
+            assert_eq!(a, b"main");
+            0
+        })).unwrap();
+        assert!(called);
+        assert_eq!(index.len(), 0);
+    }
+
     #[test]
     fn smoke_add() {
         let (_td, repo) = ::test::repo_init();
@@ -548,14 +1070,182 @@ mod tests {
         let commit = repo.commit(Some("HEAD"), &sig, &sig, "commit",
                                  &tree, &[&parent]).unwrap();
         let obj = repo.find_object(commit, None).unwrap();
-        repo.reset(&obj, ResetType::Hard, None, None, None).unwrap();
+        repo.reset(&obj, ResetType::Hard, None).unwrap();
 
         let td2 = TempDir::new("git").unwrap();
         let url = Url::from_file_path(&root).ok().unwrap();
         let url = url.to_string();
         let repo = Repository::clone(url.as_slice(), td2.path()).unwrap();
         let obj = repo.find_object(commit, None).unwrap();
-        repo.reset(&obj, ResetType::Hard, None, None, None).unwrap();
+        repo.reset(&obj, ResetType::Hard, None).unwrap();
     }
-}
 
+    #[test]
+    fn add_frombuffer() {
+        let (_td, repo) = ::test::repo_init();
+        let mut index = repo.index().unwrap();
+
+        let entry = IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: 0o100644,
+            uid: 0,
+            gid: 0,
+            file_size: 0,
+            id: repo.blob(b"").unwrap(),
+            flags: 0,
+            flags_extended: 0,
+            path: b"foo/bar".to_vec(),
+        };
+        index.add_frombuffer(&entry, b"contents").unwrap();
+        assert_eq!(index.len(), 1);
+
+        let added = index.get_path(&Path::new("foo/bar"), 0).unwrap();
+        assert_eq!(added.path, b"foo/bar".to_vec());
+        assert_eq!(added.file_size, 8);
+        let blob = repo.find_blob(added.id).unwrap();
+        assert_eq!(blob.content(), b"contents");
+
+        index.remove(&Path::new("foo/bar"), 0).unwrap();
+        assert_eq!(index.len(), 0);
+        index.add(&added).unwrap();
+        assert_eq!(index.iter().count(), 1);
+    }
+
+    #[test]
+    fn stage_hunks() {
+        let (td, repo) = ::test::repo_init();
+        let path = Path::new("foo");
+        let contents = |changed: &[usize]| {
+            range(0, 20).map(|i| {
+                if changed.contains(&i) { format!("x{}\n", i) }
+                else { format!("{}\n", i) }
+            }).collect::<String>()
+        };
+        let orig = contents(&[]);
+        File::create(&td.path().join("foo")).write_str(orig.as_slice())
+                                            .unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(&path).unwrap();
+
+        let modified = contents(&[1, 18]);
+        File::create(&td.path().join("foo")).write_str(modified.as_slice())
+                                            .unwrap();
+        assert!(index.stage_hunks(&path, &[2]).is_err());
+        index.stage_hunks(&path, &[1]).unwrap();
+        let id = index.get_path(&path, 0).unwrap().id;
+        assert_eq!(repo.find_blob(id).unwrap().content(),
+                   contents(&[18]).as_bytes());
+
+        // the remaining hunk is now the only one left to stage
+        index.stage_hunks(&path, &[0]).unwrap();
+        let id = index.get_path(&path, 0).unwrap().id;
+        assert_eq!(repo.find_blob(id).unwrap().content(), modified.as_bytes());
+
+        // new files consist of a single hunk
+        File::create(&td.path().join("bar")).write_str("a\nb\n").unwrap();
+        index.stage_hunks(&Path::new("bar"), &[0]).unwrap();
+        let id = index.get_path(&Path::new("bar"), 0).unwrap().id;
+        assert_eq!(repo.find_blob(id).unwrap().content(), b"a\nb\n");
+
+        // the clean filters apply and new entries take the mode of the file
+        File::create(&td.path().join(".gitattributes")).write_str("*.sh text\n")
+                                                      .unwrap();
+        let script = td.path().join("run.sh");
+        File::create(&script).write_str("a\r\nb\r\n").unwrap();
+        fs::chmod(&script, old_io::USER_RWX).unwrap();
+        index.stage_hunks(&Path::new("run.sh"), &[0]).unwrap();
+        let entry = index.get_path(&Path::new("run.sh"), 0).unwrap();
+        assert_eq!(entry.mode, 0o100755);
+        assert_eq!(repo.find_blob(entry.id).unwrap().content(), b"a\nb\n");
+
+        // which is kept once the entry exists
+        File::create(&script).write_str("a\r\nc\r\n").unwrap();
+        fs::chmod(&script, old_io::USER_RW).unwrap();
+        index.stage_hunks(&Path::new("run.sh"), &[0]).unwrap();
+        let entry = index.get_path(&Path::new("run.sh"), 0).unwrap();
+        assert_eq!(entry.mode, 0o100755);
+        assert_eq!(repo.find_blob(entry.id).unwrap().content(), b"a\nc\n");
+    }
+
+    #[test]
+    fn resolve_undo() {
+        let (td, repo) = ::test::repo_init();
+        let path = Path::new("f");
+        let entry = |data: &[u8]| IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: 0o100644,
+            uid: 0,
+            gid: 0,
+            file_size: 0,
+            id: repo.blob(data).unwrap(),
+            flags: 0,
+            flags_extended: 0,
+            path: b"f".to_vec(),
+        };
+        let (base, ours) = (entry(b"a\n"), entry(b"b\n"));
+        let theirs = entry(b"c\n");
+        let mut index = repo.index().unwrap();
+        index.conflict_add(Some(&base), Some(&ours), Some(&theirs)).unwrap();
+        assert!(index.has_conflicts());
+        let (a, o, t) = index.conflict_get(&path).unwrap();
+        assert_eq!(a.unwrap().id, base.id);
+        assert_eq!(o.unwrap().id, ours.id);
+        assert_eq!(t.unwrap().id, theirs.id);
+        assert_eq!(index.resolve_undo_len(), 0);
+
+        // resolving the conflict records its sides
+        index.add_frombuffer(&ours, b"resolved\n").unwrap();
+        assert!(!index.has_conflicts());
+        let reuc = index.resolve_undo_get_path(&path).unwrap();
+        assert_eq!(reuc.path, b"f".to_vec());
+        assert_eq!((reuc.ancestor_id, reuc.our_id, reuc.their_id),
+                   (base.id, ours.id, theirs.id));
+        assert_eq!(reuc.their_mode, 0o100644);
+        index.write().unwrap();
+        let on_disk = Index::open(&repo.path().join("index")).unwrap();
+        assert_eq!(on_disk.resolve_undo_iter().count(), 1);
+
+        index.unresolve(&path).unwrap();
+        assert!(index.get_path(&path, 0).is_none());
+        assert_eq!(index.get_path(&path, 1).unwrap().id, base.id);
+        assert_eq!(index.get_path(&path, 3).unwrap().id, theirs.id);
+        assert_eq!(index.resolve_undo_len(), 0);
+        assert_eq!(index.unresolve(&path).err().unwrap().code(),
+                   ErrorCode::NotFound);
+
+        // the file can be checked out with conflict markers again
+        let mut opts = CheckoutBuilder::new();
+        opts.force().allow_conflicts(true).conflict_style_merge(true);
+        repo.checkout_index(Some(&mut index), Some(&mut opts)).unwrap();
+        let content = File::open(&td.path().join("f")).read_to_string()
+                          .unwrap();
+        assert!(content.contains("<<<<<<<"));
+        assert!(content.contains("b\n") && content.contains("c\n"));
+
+        index.conflict_remove(&path).unwrap();
+        assert!(!index.has_conflicts());
+        assert!(index.conflict_get(&path).is_err());
+
+        index.add_resolve_undo(&ResolveUndoEntry {
+            path: b"g".to_vec(),
+            ancestor_mode: 0,
+            ancestor_id: Oid::zero(),
+            our_mode: 0o100644,
+            our_id: ours.id,
+            their_mode: 0o100644,
+            their_id: theirs.id,
+        }).unwrap();
+        assert_eq!(index.resolve_undo_get(0).unwrap().path, b"g".to_vec());
+        index.remove_resolve_undo(0).unwrap();
+        assert_eq!(index.resolve_undo_len(), 0);
+        index.add_resolve_undo(&reuc).unwrap();
+        index.clear_resolve_undo();
+        assert!(index.resolve_undo_get_path(&path).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+use std::str;
+
+macro_rules! try_opt( ($e:expr) => (
+    match $e { Some(e) => e, None => return None }
+) );
+
+/// The largest blob which Git LFS considers as a possible pointer.
+const MAX_POINTER_SIZE: usize = 1024;
+
+static VERSIONS: &'static [&'static str] = &[
+    "https://git-lfs.github.com/spec/v1",
+    "https://hawser.github.com/spec/v1",
+    "http://git-media.io/v/2",
+];
+
+/// A [Git LFS][1] pointer, the small blob committed in place of a file whose
+/// content is kept in LFS storage.
+///
+/// [1]: https://git-lfs.github.com/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LfsPointer {
+    version: String,
+    oid: String,
+    size: u64,
+}
+
+impl LfsPointer {
+    /// Parse the content of a blob as an LFS pointer.
+    ///
+    /// Returns `None` if the content is not a valid pointer, in which case
+    /// it is the real content of the file.
+    pub fn parse(data: &[u8]) -> Option<LfsPointer> {
+        if data.len() > MAX_POINTER_SIZE || !data.ends_with(b"\n") {
+            return None
+        }
+        let data = try_opt!(str::from_utf8(&data[..data.len() - 1]).ok());
+        let mut lines = data.split('\n');
+
+        // the version always comes first, the remaining keys are sorted
+        let version = try_opt!(lines.next().and_then(|l| value(l, "version")));
+        if !VERSIONS.contains(&version) {
+            return None
+        }
+        let mut oid = None;
+        let mut size = None;
+        let mut prev = "";
+        for line in lines {
+            let key = try_opt!(line.splitn(1, ' ').next());
+            if key <= prev { return None }
+            prev = key;
+            match key {
+                "oid" => {
+                    let hex = try_opt!(value(line, "oid sha256:"));
+                    if hex.len() != 64 || !hex.chars().all(|c| {
+                        (c >= '0' && c <= '9') || (c >= 'a' && c <= 'f')
+                    }) {
+                        return None
+                    }
+                    oid = Some(hex);
+                }
+                "size" => {
+                    let n = try_opt!(value(line, "size"));
+                    size = Some(try_opt!(n.parse().ok()));
+                }
+                _ if key.starts_with("ext-") => {}
+                _ => return None,
+            }
+        }
+        Some(LfsPointer {
+            version: version.to_string(),
+            oid: try_opt!(oid).to_string(),
+            size: try_opt!(size),
+        })
+    }
+
+    /// Get the URL of the version of the pointer format.
+    pub fn version(&self) -> &str { self.version.as_slice() }
+
+    /// Get the hex-encoded SHA-256 hash of the content the pointer stands
+    /// for, as used to fetch it from LFS storage.
+    pub fn oid(&self) -> &str { self.oid.as_slice() }
+
+    /// Get the size in bytes of the content the pointer stands for.
+    pub fn size(&self) -> u64 { self.size }
+}
+
+fn value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    if !line.starts_with(key) { return None }
+    let rest = &line[key.len()..];
+    if key.ends_with(":") {
+        Some(rest)
+    } else if rest.starts_with(" ") && rest.len() > 1 {
+        Some(&rest[1..])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::File;
+    use LfsPointer;
+
+    static OID: &'static str =
+        "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+    #[test]
+    fn parse() {
+        let data = format!("version https://git-lfs.github.com/spec/v1\n\
+                            oid sha256:{}\n\
+                            size 12345\n", OID);
+        let pointer = LfsPointer::parse(data.as_bytes()).unwrap();
+        assert_eq!(pointer.version(), "https://git-lfs.github.com/spec/v1");
+        assert_eq!(pointer.oid(), OID);
+        assert_eq!(pointer.size(), 12345);
+
+        let ext = format!("version https://git-lfs.github.com/spec/v1\n\
+                           ext-0-foo sha256:{}\n\
+                           oid sha256:{}\n\
+                           size 1\n", OID, OID);
+        assert!(LfsPointer::parse(ext.as_bytes()).is_some());
+
+        let bad = [
+            format!("oid sha256:{}\nsize 1\n", OID),
+            format!("version https://example.com\noid sha256:{}\nsize 1\n",
+                    OID),
+            format!("version https://git-lfs.github.com/spec/v1\n\
+                     size 1\noid sha256:{}\n", OID),
+            format!("version https://git-lfs.github.com/spec/v1\n\
+                     oid sha256:{}\nsize 1", OID),
+            format!("version https://git-lfs.github.com/spec/v1\n\
+                     oid sha256:{}\nsize -1\n", OID),
+            format!("version https://git-lfs.github.com/spec/v1\n\
+                     oid sha1:{}\nsize 1\n", OID),
+            "version https://git-lfs.github.com/spec/v1\nsize 1\n"
+                .to_string(),
+            "real content\n".to_string(),
+        ];
+        for data in bad.iter() {
+            assert!(LfsPointer::parse(data.as_bytes()).is_none(), "{}", data);
+        }
+    }
+
+    #[test]
+    fn tracked() {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join(".gitattributes"))
+             .write_str("*.bin filter=lfs diff=lfs merge=lfs -text\n")
+             .unwrap();
+        assert!(repo.is_lfs_tracked(&Path::new("foo.bin")).unwrap());
+        assert!(repo.is_lfs_tracked(&Path::new("a/b.bin")).unwrap());
+        assert!(!repo.is_lfs_tracked(&Path::new("foo.txt")).unwrap());
+
+        let data = format!("version https://git-lfs.github.com/spec/v1\n\
+                            oid sha256:{}\nsize 3\n", OID);
+        let blob = repo.find_blob(repo.blob(data.as_bytes()).unwrap())
+                       .unwrap();
+        assert_eq!(blob.lfs_pointer().unwrap().size(), 3);
+        let blob = repo.find_blob(repo.blob(b"foo").unwrap()).unwrap();
+        assert!(blob.lfs_pointer().is_none());
+    }
+}
@@ -65,6 +65,20 @@
 //! All deriviative objects, references, etc are attached to the lifetime of the
 //! source `Repository`, to ensure that they do not outlive the repository
 //! itself.
+//!
+//! ## Threads
+//!
+//! libgit2 is built with thread support, and its rule is that an object may
+//! be used by any one thread at a time, while distinct objects (even ones
+//! referring to the same repository on disk) may be used concurrently. This
+//! is reflected in the types of this library: a `Repository` is `Send`, so it
+//! can be moved into a worker thread or shared between threads behind a
+//! `Mutex`, but it is not `Sync`. The same goes for standalone objects such as
+//! `Cred`, `Buf` and owned `Signature`s, while `Oid` and `Error` are plain
+//! data. Objects which borrow from a `Repository` are tied to the thread which
+//! owns the repository. This includes `Config` and `Index`: the ones returned
+//! by a repository are shared with it, so they stay on its thread even though
+//! they do not borrow it.
 
 #![feature(unsafe_destructor, hash, std_misc, core, path, os)]
 #![feature(io, libc)]
@@ -83,38 +97,71 @@ use std::mem;
 use std::str;
 use std::sync::{Once, ONCE_INIT};
 
+pub use apply::ApplyOptions;
+pub use archive::ArchiveOptions;
+pub use attr::AttrValue;
+pub use blame::{Blame, BlameHunk, BlameIter, BlameOptions};
 pub use blob::Blob;
-pub use branch::{Branch, Branches};
+pub use branch::{Branch, Branches, BranchSummary};
 pub use buf::Buf;
+pub use bundle::{Bundle, BundleBuilder};
 pub use commit::{Commit, Parents};
+pub use commit_graph::{CommitGraph, CommitGraphWriter};
 pub use config::{Config, ConfigEntry, ConfigEntries};
 pub use cred::{Cred, CredentialHelper};
 pub use diff::{Diff, DiffDelta, DiffFile, DiffOptions, Deltas};
 pub use diff::{DiffLine, DiffHunk, DiffStats, DiffFindOptions};
+pub use diff::{DiffBinary, DiffBinaryFile};
+pub use email::{Email, EmailCreateOptions, EmailPatch};
+pub use eol::EolOptions;
 pub use error::Error;
+pub use fast_export::FastExport;
 pub use index::{Index, IndexEntry, IndexEntries, IndexMatchedPath};
+pub use index::{ResolveUndoEntry, ResolveUndoEntries};
+pub use lfs::LfsPointer;
+pub use mempack::Mempack;
+pub use merge::{AnnotatedCommit, MergeOptions};
+pub use merge::{merge_file, MergeFileInput, MergeFileOptions, MergeFileResult};
+pub use message::{message_prettify, message_trailers};
+pub use message::{MessageTrailers, MessageTrailersIter};
+pub use trace::{trace_set, trace_clear};
 pub use note::{Note, Notes};
 pub use object::Object;
+pub use object_cache::ObjectCache;
+pub use odb::{Odb, OdbObject};
 pub use oid::Oid;
+pub use packbuilder::PackBuilder;
+pub use patch::Patch;
 pub use pathspec::{Pathspec, PathspecMatchList, PathspecFailedEntries};
 pub use pathspec::{PathspecDiffEntries, PathspecEntries};
-pub use push::{Push, PushStatus};
+pub use push::{Push, PushOptions, PushStatus};
+pub use rebase::{Rebase, RebaseOperation, RebaseOptions};
+pub use rebase::CommitSigningCallback;
 pub use reference::{Reference, References, ReferenceNames};
 pub use reflog::{Reflog, ReflogEntry, ReflogIter};
 pub use refspec::Refspec;
 pub use remote::{Remote, Refspecs, RemoteHead};
 pub use remote_callbacks::{RemoteCallbacks, Credentials, TransferProgress};
 pub use remote_callbacks::{TransportMessage, Progress, UpdateTips};
+pub use remote_callbacks::{ResolveUrl, PackProgress};
 pub use repo::{Repository, RepositoryInitOptions};
+pub use rerere::Rerere;
 pub use revspec::Revspec;
 pub use revwalk::Revwalk;
+pub use sequencer::SequencerStep;
 pub use signature::Signature;
+pub use stash::StashApplyOptions;
 pub use status::{StatusOptions, Statuses, StatusIter, StatusEntry, StatusShow};
 pub use submodule::Submodule;
 pub use tag::Tag;
 pub use time::{Time, IndexTime};
-pub use tree::{Tree, TreeEntry, TreeIter};
+pub use tree::{Tree, TreeEntry, TreeIter, TreeUpdate};
+pub use treebuilder::TreeBuilder;
 pub use util::IntoCString;
+pub use verify::SignatureVerification;
+#[cfg(feature = "ssh-keygen")]
+pub use ssh_keygen::ssh_keygen_verify;
+pub use worktree::{Worktree, WorktreePruneOptions};
 
 /// An enumeration of possible errors that can happen when working with a git
 /// repository.
@@ -142,12 +189,57 @@ pub enum ErrorCode {
     NotFastForward,
     /// Name/ref spec was not in a valid format
     InvalidSpec,
-    /// Merge conflicts prevented operation
-    MergeConflict,
+    /// Checkout or merge conflicts prevented operation
+    Conflict,
     /// Lock file prevented operation
     Locked,
     /// Reference value does not match expected
     Modified,
+    /// Authentication error
+    Auth,
+    /// Server certificate is invalid
+    Certificate,
+    /// Patch or merge has already been applied
+    Applied,
+    /// The requested peel operation is not possible
+    Peel,
+}
+
+/// An enumeration of the subsystems of libgit2 which an error can originate
+/// from.
+#[derive(PartialEq, Eq, Clone, Debug, Copy)]
+#[allow(missing_docs)]
+pub enum ErrorClass {
+    None,
+    NoMemory,
+    Os,
+    Invalid,
+    Reference,
+    Zlib,
+    Repository,
+    Config,
+    Regex,
+    Odb,
+    Index,
+    Object,
+    Net,
+    Tag,
+    Tree,
+    Indexer,
+    Ssl,
+    Submodule,
+    Thread,
+    Stash,
+    Checkout,
+    FetchHead,
+    Merge,
+    Ssh,
+    Filter,
+    Revert,
+    Callback,
+    CherryPick,
+    Describe,
+    Rebase,
 }
 
 /// A listing of the possible states that a repository can be in.
@@ -175,6 +267,88 @@ pub enum Direction {
     Push,
 }
 
+/// The actions which can be listed in the todo list of an interrupted
+/// multi-commit cherry-pick or revert, see `Repository::sequencer_todo`.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum SequencerAction {
+    /// The commit is to be cherry-picked.
+    Pick,
+    /// The commit is to be reverted.
+    Revert,
+}
+
+/// Stages of building a pack, as reported to `RemoteCallbacks::pack_progress`.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum PackBuilderStage {
+    /// Objects are being collected into the pack, the "Counting objects"
+    /// phase of `git push`.
+    AddingObjects,
+    /// Deltas are being computed between the objects of the pack, the
+    /// "Compressing objects" phase of `git push`.
+    Deltafication,
+}
+
+/// Whether a query may be answered from the commit-graph file of a
+/// repository, see `Repository::graph_ahead_behind_with`.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum CommitGraphUsage {
+    /// Answer from the commit-graph if the repository has one containing the
+    /// commits, and fall back to libgit2 otherwise.
+    Auto,
+    /// Fail unless the query can be answered from the commit-graph.
+    Require,
+    /// Leave the query to libgit2, which only reads the commit-graph when
+    /// `core.commitGraph` is enabled.
+    Forbid,
+}
+
+/// How the line endings of text files are converted between the repository
+/// and the working directory, as with `core.autocrlf`.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum AutoCrlf {
+    /// Do not convert line endings, unless the `text` and `eol` attributes
+    /// ask for it.
+    False,
+    /// Convert CRLF to LF when adding files and LF to CRLF when checking them
+    /// out.
+    True,
+    /// Convert CRLF to LF when adding files, but do not convert them when
+    /// checking them out.
+    Input,
+}
+
+/// The line endings text files are checked out with, as with `core.eol`.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum Eol {
+    /// Check out text files with LF line endings.
+    Lf,
+    /// Check out text files with CRLF line endings.
+    Crlf,
+    /// Check out text files with the line endings of the platform.
+    Native,
+}
+
+/// What happens when converting the line endings of a file being added would
+/// not be undone by checking it out again, as with `core.safecrlf`.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum SafeCrlf {
+    /// Convert the file anyway.
+    Allow,
+    /// Convert the file anyway, which git would warn about.
+    Warn,
+    /// Fail to add the file.
+    Fail,
+}
+
+/// The formats `Repository::archive` can write archives in.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum ArchiveFormat {
+    /// A POSIX tar archive, using pax headers for long paths.
+    Tar,
+    /// A zip archive, with its entries stored uncompressed.
+    Zip,
+}
+
 /// An enumeration of the operations that can be performed for the `reset`
 /// method on a `Repository`.
 #[derive(Copy)]
@@ -187,6 +361,106 @@ pub enum ResetType {
     Hard,
 }
 
+/// Possible locations to which a diff can be applied by
+/// `Repository::apply`.
+#[derive(PartialEq, Eq, Copy, Debug)]
+pub enum ApplyLocation {
+    /// Apply the patch to the working directory.
+    WorkDir,
+    /// Apply the patch to the index.
+    Index,
+    /// Apply the patch to both the working directory and the index.
+    Both,
+}
+
+/// Which side to favor when resolving conflicting hunks in a merge.
+#[derive(PartialEq, Eq, Copy, Debug)]
+pub enum FileFavor {
+    /// When a region of a file is changed in both branches, a conflict will be
+    /// recorded in the index so that git_checkout can produce a merge file
+    /// with conflict markers in the working directory. This is the default.
+    Normal,
+    /// When a region of a file is changed in both branches, the file created
+    /// in the index will contain the "ours" side of any conflicting region.
+    /// The index will not record a conflict.
+    Ours,
+    /// When a region of a file is changed in both branches, the file created
+    /// in the index will contain the "theirs" side of any conflicting region.
+    /// The index will not record a conflict.
+    Theirs,
+    /// When a region of a file is changed in both branches, the file created
+    /// in the index will contain each unique line from each side, which has
+    /// the result of combining both files. The index will not record a
+    /// conflict.
+    Union,
+}
+
+/// A type of rebase operation, as determined by `RebaseOperation::kind`.
+#[derive(PartialEq, Eq, Copy, Debug)]
+pub enum RebaseOperationType {
+    /// The given commit is to be cherry-picked. The client should commit the
+    /// changes and continue if there are no conflicts.
+    Pick,
+    /// The given commit is to be cherry-picked, but the client should prompt
+    /// the user to provide an updated commit message.
+    Reword,
+    /// The given commit is to be cherry-picked, but the client should stop to
+    /// allow the user to edit the changes before committing them.
+    Edit,
+    /// The given commit is to be squashed into the previous commit. The
+    /// commit message will be merged with the previous message.
+    Squash,
+    /// The given commit is to be squashed into the previous commit. The
+    /// commit message from this commit will be discarded.
+    Fixup,
+    /// No commit will be cherry-picked. The client should run the given
+    /// command and (if successful) continue.
+    Exec,
+}
+
+/// The stages reported to the progress callback of `StashApplyOptions` while
+/// a stash is being applied.
+#[derive(PartialEq, Eq, Copy, Debug)]
+pub enum StashApplyProgress {
+    /// None
+    None,
+    /// Loading the stashed data from the object database
+    LoadingStash,
+    /// The stored index is being analyzed
+    AnalyzeIndex,
+    /// The modified files are being analyzed
+    AnalyzeModified,
+    /// The untracked and ignored files are being analyzed
+    AnalyzeUntracked,
+    /// The untracked files are being written to disk
+    CheckoutUntracked,
+    /// The modified files are being written to disk
+    CheckoutModified,
+    /// The stash was applied successfully
+    Done,
+}
+
+/// The severity of a message traced by libgit2, see `trace_set`.
+///
+/// Levels are ordered from least to most verbose.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Debug)]
+pub enum TraceLevel {
+    /// No tracing will be performed
+    None,
+    /// Severe errors that may impact the program's execution
+    Fatal,
+    /// Errors that do not impact the program's execution
+    Error,
+    /// Warnings that suggest abnormal data
+    Warn,
+    /// Informational messages about program execution
+    Info,
+    /// Detailed data that allows for debugging
+    Debug,
+    /// Exceptionally detailed debugging data
+    Trace,
+}
+
 /// An enumeration all possible kinds objects may have.
 #[derive(PartialEq, Eq, Copy, Debug)]
 pub enum ObjectType {
@@ -217,6 +491,9 @@ pub enum BranchType {
 /// searching for config entries.
 #[derive(PartialEq, Eq, Debug, Copy)]
 pub enum ConfigLevel {
+    /// Platform-wide configuration file, e.g. %PROGRAMDATA%\Git\config on
+    /// Windows
+    ProgramData,
     /// System-wide configuration file, e.g. /etc/gitconfig
     System,
     /// XDG-compatible configuration file, e.g. ~/.config/git/config
@@ -225,12 +502,32 @@ pub enum ConfigLevel {
     Global,
     /// Reopsitory specific config, e.g. $PWD/.git/config
     Local,
+    /// Worktree specific config, e.g. $PWD/.git/config.worktree
+    Worktree,
     /// Application specific configuration file
     App,
     /// Highest level available
     Highest,
 }
 
+/// An enumeration of the rules which control how much of a submodule's
+/// working directory is examined when computing its status.
+#[derive(PartialEq, Eq, Debug, Copy)]
+pub enum SubmoduleIgnore {
+    /// Use the rule configured for the submodule in `.gitmodules` or the
+    /// repository's config.
+    Default,
+    /// Examine everything, including untracked files in the submodule.
+    None,
+    /// Ignore untracked files in the submodule's working directory.
+    Untracked,
+    /// Ignore all changes in the submodule's working directory, only
+    /// reporting when its checked out commit differs.
+    Dirty,
+    /// Ignore the submodule entirely, only reporting where it is recorded.
+    All,
+}
+
 bitflags! {
     #[doc = "
 Orderings that may be specified for Revwalk iteration.
@@ -253,6 +550,8 @@ Types of credentials that can be requested by a credential callback.
         const SSH_CUSTOM = raw::GIT_CREDTYPE_SSH_CUSTOM as u32,
         const DEFAULT = raw::GIT_CREDTYPE_DEFAULT as u32,
         const SSH_INTERACTIVE = raw::GIT_CREDTYPE_SSH_INTERACTIVE as u32,
+        const USERNAME = raw::GIT_CREDTYPE_USERNAME as u32,
+        const SSH_MEMORY = raw::GIT_CREDTYPE_SSH_MEMORY as u32,
     }
 }
 
@@ -269,6 +568,138 @@ Flags for APIs that add files matching pathspec
     }
 }
 
+bitflags! {
+    #[doc = "
+Capabilities of an index, describing how it treats the filesystem it is
+checked out on.
+"]
+    flags IndexCapabilities: u32 {
+        #[doc = "Paths are compared case insensitively"]
+        const INDEXCAP_IGNORE_CASE = raw::GIT_INDEXCAP_IGNORE_CASE as u32,
+        #[doc = "The executable bit of files is not trusted"]
+        const INDEXCAP_NO_FILEMODE = raw::GIT_INDEXCAP_NO_FILEMODE as u32,
+        #[doc = "Symbolic links are stored as plain files"]
+        const INDEXCAP_NO_SYMLINKS = raw::GIT_INDEXCAP_NO_SYMLINKS as u32,
+    }
+}
+
+bitflags! {
+    #[doc = "
+The results of `Repository::merge_analysis` indicating the merge
+opportunities.
+"]
+    flags MergeAnalysis: u32 {
+        #[doc = "No merge is possible."]
+        const MERGE_ANALYSIS_NONE = raw::GIT_MERGE_ANALYSIS_NONE as u32,
+        #[doc = "
+A \"normal\" merge; both HEAD and the given merge input have diverged from
+their common ancestor. The divergent commits must be merged.
+"]
+        const MERGE_ANALYSIS_NORMAL = raw::GIT_MERGE_ANALYSIS_NORMAL as u32,
+        #[doc = "
+All given merge inputs are reachable from HEAD, meaning the repository is
+up-to-date and no merge needs to be performed.
+"]
+        const MERGE_ANALYSIS_UP_TO_DATE =
+            raw::GIT_MERGE_ANALYSIS_UP_TO_DATE as u32,
+        #[doc = "
+The given merge input is a fast-forward from HEAD and no merge needs to be
+performed. Instead, the client can check out the given merge input.
+"]
+        const MERGE_ANALYSIS_FASTFORWARD =
+            raw::GIT_MERGE_ANALYSIS_FASTFORWARD as u32,
+        #[doc = "
+The HEAD of the current repository is \"unborn\" and does not point to a
+valid commit. No merge can be performed, but the caller may wish to simply
+set HEAD to the target commit(s).
+"]
+        const MERGE_ANALYSIS_UNBORN = raw::GIT_MERGE_ANALYSIS_UNBORN as u32,
+    }
+}
+
+bitflags! {
+    #[doc = "
+The user's stated preference for merges, as returned by
+`Repository::merge_analysis`.
+"]
+    flags MergePreference: u32 {
+        #[doc = "No configuration was found that suggests a preferred behavior."]
+        const MERGE_PREFERENCE_NONE = raw::GIT_MERGE_PREFERENCE_NONE as u32,
+        #[doc = "
+There is a `merge.ff=false` configuration setting, suggesting that the user
+does not want to allow a fast-forward merge.
+"]
+        const MERGE_PREFERENCE_NO_FAST_FORWARD =
+            raw::GIT_MERGE_PREFERENCE_NO_FASTFORWARD as u32,
+        #[doc = "
+There is a `merge.ff=only` configuration setting, suggesting that the user
+only wants fast-forward merges.
+"]
+        const MERGE_PREFERENCE_FASTFORWARD_ONLY =
+            raw::GIT_MERGE_PREFERENCE_FASTFORWARD_ONLY as u32,
+    }
+}
+
+bitflags! {
+    #[doc = "
+Flags controlling what is stashed by `Repository::stash_save`.
+"]
+    flags StashFlags: u32 {
+        #[doc = "No option, default"]
+        const STASH_DEFAULT = raw::GIT_STASH_DEFAULT as u32,
+        #[doc = "
+All changes already added to the index are left intact in the working
+directory
+"]
+        const STASH_KEEP_INDEX = raw::GIT_STASH_KEEP_INDEX as u32,
+        #[doc = "
+All untracked files are also stashed and then cleaned up from the working
+directory
+"]
+        const STASH_INCLUDE_UNTRACKED =
+            raw::GIT_STASH_INCLUDE_UNTRACKED as u32,
+        #[doc = "
+All ignored files are also stashed and then cleaned up from the working
+directory
+"]
+        const STASH_INCLUDE_IGNORED = raw::GIT_STASH_INCLUDE_IGNORED as u32,
+    }
+}
+
+bitflags! {
+    #[doc = "
+Flags controlling how a stash is applied by `Repository::stash_apply`.
+"]
+    flags StashApplyFlags: u32 {
+        const STASH_APPLY_DEFAULT = raw::GIT_STASH_APPLY_DEFAULT as u32,
+        #[doc = "
+Try to reinstate not only the working tree's changes, but also the index's
+changes
+"]
+        const STASH_APPLY_REINSTATE_INDEX =
+            raw::GIT_STASH_APPLY_REINSTATE_INDEX as u32,
+    }
+}
+
+bitflags! {
+    #[doc = "
+Flags controlling where `Repository::get_attr` looks for attributes and in
+which order.
+"]
+    flags AttrCheckFlags: u32 {
+        #[doc = "Check the working directory, then the index (the default)"]
+        const ATTR_CHECK_FILE_THEN_INDEX =
+            raw::GIT_ATTR_CHECK_FILE_THEN_INDEX as u32,
+        #[doc = "Check the index, then the working directory"]
+        const ATTR_CHECK_INDEX_THEN_FILE =
+            raw::GIT_ATTR_CHECK_INDEX_THEN_FILE as u32,
+        #[doc = "Check the index only"]
+        const ATTR_CHECK_INDEX_ONLY = raw::GIT_ATTR_CHECK_INDEX_ONLY as u32,
+        #[doc = "Do not use the system gitattributes file"]
+        const ATTR_CHECK_NO_SYSTEM = raw::GIT_ATTR_CHECK_NO_SYSTEM as u32,
+    }
+}
+
 bitflags! {
     #[doc = "
 Flags for the return value of `Repository::revparse`
@@ -280,43 +711,97 @@ Flags for the return value of `Repository::revparse`
     }
 }
 
+bitflags! {
+    #[doc = "
+Flags controlling how `Reference::normalize_name` treats a reference name
+"]
+    flags ReferenceFormat: u32 {
+        #[doc = "No particular normalization"]
+        const REFERENCE_FORMAT_NORMAL = raw::GIT_REF_FORMAT_NORMAL as u32,
+        #[doc = "Allow names made of a single component, such as `HEAD` or \
+                 `foo`, rather than requiring a `refs/` prefix"]
+        const REFERENCE_FORMAT_ALLOW_ONELEVEL =
+            raw::GIT_REF_FORMAT_ALLOW_ONELEVEL as u32,
+        #[doc = "Allow a single `*` in the name, as used in refspec patterns"]
+        const REFERENCE_FORMAT_REFSPEC_PATTERN =
+            raw::GIT_REF_FORMAT_REFSPEC_PATTERN as u32,
+        #[doc = "Allow shorthand names such as `master`, as used in the \
+                 source of refspecs"]
+        const REFERENCE_FORMAT_REFSPEC_SHORTHAND =
+            raw::GIT_REF_FORMAT_REFSPEC_SHORTHAND as u32,
+    }
+}
+
 mod call;
 mod panic;
 mod util;
 
 pub mod build;
 pub mod cert;
+pub mod filter;
+pub mod fixture;
+pub mod oid_array;
+pub mod opts;
 pub mod string_array;
 pub mod transport;
 
+mod apply;
+mod archive;
+mod attr;
+mod blame;
 mod blob;
 mod branch;
 mod buf;
+mod bundle;
 mod commit;
+mod commit_graph;
 mod config;
 mod cred;
 mod diff;
+mod email;
+mod eol;
 mod error;
+mod fast_export;
 mod index;
+mod lfs;
+mod mempack;
+mod merge;
+mod message;
 mod note;
 mod object;
+mod object_cache;
+mod odb;
 mod oid;
+mod packbuilder;
+mod patch;
 mod pathspec;
 mod push;
+mod rebase;
 mod reference;
 mod reflog;
 mod refspec;
 mod remote;
 mod remote_callbacks;
 mod repo;
+mod rerere;
 mod revspec;
 mod revwalk;
+mod sequencer;
 mod signature;
+mod stash;
 mod status;
 mod submodule;
 mod tag;
 mod time;
+mod trace;
 mod tree;
+mod treebuilder;
+mod verify;
+mod worktree;
+#[cfg(feature = "ssh-keygen")]
+mod ssh_keygen;
+#[cfg(feature = "x509")]
+mod x509;
 
 #[cfg(test)] mod test;
 
@@ -329,7 +814,24 @@ fn init() {
                 "couldn't initialize the libgit2 library: {}", r);
         assert_eq!(libc::atexit(shutdown), 0);
     });
-    extern fn shutdown() { unsafe { raw::git_libgit2_shutdown() } }
+    extern fn shutdown() { unsafe { raw::git_libgit2_shutdown(); } }
+}
+
+/// Returns the version of libgit2 which this library is linked against, as a
+/// `(major, minor, revision)` tuple.
+pub fn version() -> (i32, i32, i32) {
+    let (mut major, mut minor, mut rev) = (0, 0, 0);
+    unsafe { raw::git_libgit2_version(&mut major, &mut minor, &mut rev); }
+    (major as i32, minor as i32, rev as i32)
+}
+
+/// Returns the optional features which the linked libgit2 was compiled with.
+///
+/// Programs can use this to avoid offering functionality which will fail at
+/// runtime, such as cloning over SSH when libssh2 support is missing.
+pub fn features() -> Features {
+    ::init();
+    Features::from_bits_truncate(unsafe { raw::git_libgit2_features() } as u32)
 }
 
 unsafe fn opt_bytes<'a, T>(anchor: &'a T,
@@ -395,10 +897,12 @@ impl ConfigLevel {
     /// Converts a raw configuration level to a ConfigLevel
     pub fn from_raw(raw: raw::git_config_level_t) -> ConfigLevel {
         match raw {
+            raw::GIT_CONFIG_LEVEL_PROGRAMDATA => ConfigLevel::ProgramData,
             raw::GIT_CONFIG_LEVEL_SYSTEM => ConfigLevel::System,
             raw::GIT_CONFIG_LEVEL_XDG => ConfigLevel::XDG,
             raw::GIT_CONFIG_LEVEL_GLOBAL => ConfigLevel::Global,
             raw::GIT_CONFIG_LEVEL_LOCAL => ConfigLevel::Local,
+            raw::GIT_CONFIG_LEVEL_WORKTREE => ConfigLevel::Worktree,
             raw::GIT_CONFIG_LEVEL_APP => ConfigLevel::App,
             raw::GIT_CONFIG_HIGHEST_LEVEL => ConfigLevel::Highest,
         }
@@ -425,6 +929,36 @@ Flags for repository status
         const STATUS_WT_RENAMED = raw::GIT_STATUS_WT_RENAMED as u32,
 
         const STATUS_IGNORED = raw::GIT_STATUS_IGNORED as u32,
+        const STATUS_CONFLICTED = raw::GIT_STATUS_CONFLICTED as u32,
+    }
+}
+
+bitflags! {
+    #[doc = "
+Flags for Repository::open_ext
+"]
+    flags RepositoryOpenFlags: u32 {
+        #[doc = "Only open the repository at the path given, without looking \
+                 in its parent directories."]
+        const REPOSITORY_OPEN_NO_SEARCH =
+                    raw::GIT_REPOSITORY_OPEN_NO_SEARCH as u32,
+        #[doc = "Keep looking in parent directories across filesystem \
+                 boundaries."]
+        const REPOSITORY_OPEN_CROSS_FS =
+                    raw::GIT_REPOSITORY_OPEN_CROSS_FS as u32,
+        #[doc = "Open the repository as bare, even if it has a working \
+                 directory."]
+        const REPOSITORY_OPEN_BARE = raw::GIT_REPOSITORY_OPEN_BARE as u32,
+        #[doc = "Do not look for a `.git` directory within the path given."]
+        const REPOSITORY_OPEN_NO_DOTGIT =
+                    raw::GIT_REPOSITORY_OPEN_NO_DOTGIT as u32,
+        #[doc = "Honor the `GIT_DIR`, `GIT_CEILING_DIRECTORIES` and related \
+                 environment variables, as git does."]
+        const REPOSITORY_OPEN_FROM_ENV =
+                    raw::GIT_REPOSITORY_OPEN_FROM_ENV as u32,
+        #[doc = "Ignore replacement refs, as with `git --no-replace-objects`, \
+                 so that `Repository::replacement_of` never follows them."]
+        const REPOSITORY_OPEN_NO_REPLACE_OBJECTS = 1 << 16,
     }
 }
 
@@ -446,8 +980,25 @@ Mode options for RepositoryInitOptions
     }
 }
 
+/// File modes of entries in trees and diffs.
+#[derive(PartialEq, Eq, Copy, Debug)]
+pub enum FileMode {
+    /// Unreadable, or the file does not exist on this side of a diff
+    Unreadable,
+    /// A directory
+    Tree,
+    /// A regular file
+    Blob,
+    /// An executable file
+    BlobExecutable,
+    /// A symbolic link
+    Link,
+    /// A submodule
+    Commit,
+}
+
 /// What type of change is described by a `DiffDelta`?
-#[derive(Copy, Debug)]
+#[derive(PartialEq, Eq, Copy, Debug)]
 pub enum Delta {
     /// No changes
     Unmodified,
@@ -469,6 +1020,8 @@ pub enum Delta {
     Typechange,
     /// Entry is unreadable
     Unreadable,
+    /// Entry in the index is conflicted
+    Conflicted,
 }
 
 bitflags! {
@@ -561,6 +1114,19 @@ bitflags! {
     }
 }
 
+/// The algorithms a diff can be generated with, see
+/// `DiffOptions::algorithm`.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum DiffAlgorithm {
+    /// The default Myers algorithm, as with `diff.algorithm=myers`.
+    Myers,
+    /// The Myers algorithm, taking extra time to find the smallest diff, as
+    /// with `diff.algorithm=minimal`.
+    Minimal,
+    /// The patience algorithm, as with `diff.algorithm=patience`.
+    Patience,
+}
+
 /// Possible output formats for diff data
 #[derive(Copy)]
 pub enum DiffFormat {
@@ -576,6 +1142,75 @@ pub enum DiffFormat {
     NameStatus,
 }
 
+/// When producing a binary diff, the binary data returned will be either the
+/// deflated full ("literal") contents of the file, or the deflated binary
+/// delta between the two sides (whichever is smaller).
+#[derive(PartialEq, Eq, Copy, Debug)]
+pub enum DiffBinaryKind {
+    /// There is no binary delta
+    None,
+    /// The binary data is the literal contents of the file
+    Literal,
+    /// The binary data is the delta from one side to the other
+    Delta,
+}
+
+bitflags! {
+    #[doc = "
+Types of notifications emitted from checkouts, see `CheckoutBuilder::notify`
+"]
+    flags CheckoutNotificationType: u32 {
+        #[doc = "Notification about a conflict"]
+        const CHECKOUT_NOTIFICATION_CONFLICT =
+            raw::GIT_CHECKOUT_NOTIFY_CONFLICT as u32,
+        #[doc = "Notification about a dirty file, one which is modified \
+                 in the working directory"]
+        const CHECKOUT_NOTIFICATION_DIRTY =
+            raw::GIT_CHECKOUT_NOTIFY_DIRTY as u32,
+        #[doc = "Notification about an updated file"]
+        const CHECKOUT_NOTIFICATION_UPDATED =
+            raw::GIT_CHECKOUT_NOTIFY_UPDATED as u32,
+        #[doc = "Notification about an untracked file"]
+        const CHECKOUT_NOTIFICATION_UNTRACKED =
+            raw::GIT_CHECKOUT_NOTIFY_UNTRACKED as u32,
+        #[doc = "Notification about an ignored file"]
+        const CHECKOUT_NOTIFICATION_IGNORED =
+            raw::GIT_CHECKOUT_NOTIFY_IGNORED as u32,
+    }
+}
+
+bitflags! {
+    #[doc = "
+Flags describing a `DiffDelta` or one of its `DiffFile`s
+"]
+    flags DiffFlags: u32 {
+        #[doc = "The file(s) are treated as binary data"]
+        const DIFF_FLAG_BINARY = raw::GIT_DIFF_FLAG_BINARY,
+        #[doc = "The file(s) are treated as text data"]
+        const DIFF_FLAG_NOT_BINARY = raw::GIT_DIFF_FLAG_NOT_BINARY,
+        #[doc = "The id of the file is known"]
+        const DIFF_FLAG_VALID_ID = raw::GIT_DIFF_FLAG_VALID_ID,
+        #[doc = "The file exists on this side of the delta"]
+        const DIFF_FLAG_EXISTS = raw::GIT_DIFF_FLAG_EXISTS,
+    }
+}
+
+bitflags! {
+    #[doc = "
+Optional features which libgit2 may have been compiled with, see `features`
+"]
+    flags Features: u32 {
+        #[doc = "libgit2 is thread safe"]
+        const FEATURE_THREADS = raw::GIT_FEATURE_THREADS as u32,
+        #[doc = "HTTPS remotes are supported"]
+        const FEATURE_HTTPS = raw::GIT_FEATURE_HTTPS as u32,
+        #[doc = "SSH remotes are supported"]
+        const FEATURE_SSH = raw::GIT_FEATURE_SSH as u32,
+        #[doc = "File timestamps are tracked with nanosecond precision"]
+        const FEATURE_NSEC = raw::GIT_FEATURE_NSEC as u32,
+    }
+}
+
 bitflags! {
     /// Formatting options for diff stats
     flags DiffStatsFormat: u32 {
@@ -599,4 +1234,32 @@ mod tests {
         assert!(ObjectType::Blob.is_loose());
     }
 
+    #[test]
+    fn version_and_features() {
+        let (major, minor, _) = ::version();
+        assert!(major > 0 || minor > 0);
+        // which features are present depends on how libgit2 was built
+        ::features();
+    }
+
+    #[test]
+    fn send() {
+        use std::thread;
+        use {Repository, Signature, Buf, Oid, Error};
+
+        fn assert_send<T: Send>() {}
+        assert_send::<Repository>();
+        assert_send::<Signature<'static>>();
+        assert_send::<Buf>();
+        assert_send::<Oid>();
+        assert_send::<Error>();
+
+        let (td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let id = thread::scoped(move || {
+            repo.find_commit(head).unwrap().id()
+        }).join();
+        assert_eq!(id, head);
+        drop(td);
+    }
 }
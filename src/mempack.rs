@@ -0,0 +1,146 @@
+use std::ffi::CString;
+
+use {raw, odb, Buf, Error, Odb, Oid, Repository};
+use util::Binding;
+
+/// An in-memory object backend for writing many objects into a repository
+/// at once, as a converter or repository generator would.
+///
+/// While a `Mempack` is alive, every object written through its repository,
+/// whether with `blob`, a `TreeBuilder` or `commit`, is kept in memory
+/// rather than written as a loose object. Calling `flush` then writes all of
+/// them out as a single pack. Objects which have not been flushed are lost
+/// once the `Mempack` is dropped.
+pub struct Mempack<'repo> {
+    raw: *mut raw::git_odb_backend,
+    repo: &'repo Repository,
+    original: Odb,
+}
+
+impl<'repo> Mempack<'repo> {
+    /// Start buffering the objects written to the given repository in
+    /// memory.
+    pub fn new(repo: &'repo Repository) -> Result<Mempack<'repo>, Error> {
+        let original = try!(repo.odb());
+        let objects = match repo.commondir() {
+            Some(dir) => dir.join("objects"),
+            None => return Err(Error::from_str("repository has no path")),
+        };
+        let objects = CString::from_slice(objects.as_vec());
+        let mut odb = 0 as *mut raw::git_odb;
+        let mut backend = 0 as *mut raw::git_odb_backend;
+        unsafe {
+            try_call!(raw::git_odb_open(&mut odb, objects));
+            let odb: Odb = Binding::from_raw(odb);
+            try_call!(raw::git_mempack_new(&mut backend));
+            // the object database owns the backend from here on, and the
+            // highest priority makes it receive all writes
+            try_call!(raw::git_odb_add_backend(odb.raw(), backend, 1000));
+            try_call!(raw::git_repository_set_odb(repo.raw(), odb.raw()));
+        }
+        Ok(Mempack { raw: backend, repo: repo, original: original })
+    }
+
+    /// Get the objects written so far as the contents of a pack file,
+    /// without writing it anywhere.
+    pub fn dump(&self) -> Result<Buf, Error> {
+        let buf = Buf::new();
+        unsafe {
+            try_call!(raw::git_mempack_dump(buf.raw(), self.repo.raw(),
+                                            self.raw));
+        }
+        Ok(buf)
+    }
+
+    /// Write the objects written so far to a single pack in the repository
+    /// and release them from memory.
+    ///
+    /// Returns the hash of the new pack, or `None` if there were no objects
+    /// to write.
+    pub fn flush(&mut self) -> Result<Option<Oid>, Error> {
+        let pack = try!(self.dump());
+        // the pack header stores the number of objects after the signature
+        // and the version
+        let count = pack[8..12].iter().fold(0u32, |n, b| {
+            (n << 8) | (*b as u32)
+        });
+        if count == 0 {
+            return Ok(None)
+        }
+        let dir = match self.repo.commondir() {
+            Some(dir) => dir.join("objects/pack"),
+            None => return Err(Error::from_str("repository has no path")),
+        };
+        let id = try!(odb::index_pack(&self.original, &dir, &pack));
+        self.reset();
+        Ok(Some(id))
+    }
+
+    /// Forget all objects written so far without writing them out.
+    pub fn reset(&mut self) {
+        unsafe { raw::git_mempack_reset(self.raw); }
+    }
+}
+
+#[unsafe_destructor]
+impl<'repo> Drop for Mempack<'repo> {
+    fn drop(&mut self) {
+        unsafe {
+            raw::git_repository_set_odb(self.repo.raw(), self.original.raw());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::fs::PathExtensions;
+    use {Mempack, ObjectType};
+
+    #[test]
+    fn smoke() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let parent = repo.find_commit(head).unwrap();
+        let sig = repo.signature().unwrap();
+        let objects = repo.path().join("objects");
+
+        let mut mempack = Mempack::new(&repo).unwrap();
+        assert_eq!(mempack.flush().unwrap(), None);
+        let mut ids = Vec::new();
+        let mut builder = repo.treebuilder(None).unwrap();
+        for i in range(0, 10) {
+            let blob = repo.blob(format!("{}\n", i).as_bytes()).unwrap();
+            builder.insert(format!("file{}", i), blob, 0o100644).unwrap();
+            ids.push(blob);
+        }
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let commit = repo.commit(None, &sig, &sig, "bulk", &tree,
+                                 &[&parent]).unwrap();
+        ids.push(tree.id());
+        ids.push(commit);
+
+        let loose = |id: ::Oid| {
+            let hex = id.to_string();
+            objects.join(&hex[..2]).join(&hex[2..]).exists()
+        };
+        assert!(!ids.iter().any(|id| loose(*id)));
+        assert!(mempack.dump().unwrap().len() > 0);
+
+        let pack = mempack.flush().unwrap().unwrap();
+        let name = format!("pack/pack-{}.pack", pack);
+        assert!(objects.join(name).exists());
+        assert_eq!(mempack.flush().unwrap(), None);
+        drop(mempack);
+
+        assert!(!ids.iter().any(|id| loose(*id)));
+        for id in ids.iter() {
+            repo.find_object(*id, None).unwrap();
+        }
+        assert_eq!(repo.find_object(commit, None).unwrap().kind(),
+                   Some(ObjectType::Commit));
+
+        // without a mempack objects are loose again
+        let blob = repo.blob(b"loose").unwrap();
+        assert!(loose(blob));
+    }
+}
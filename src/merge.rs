@@ -0,0 +1,646 @@
+use std::ffi::CString;
+use std::marker;
+use std::mem;
+use std::slice;
+use std::str;
+use libc::{c_char, c_uint, c_ushort, size_t};
+
+use {raw, Error, FileFavor, IntoCString, Oid};
+use util::Binding;
+
+/// A structure to represent an annotated commit, the input to merge and rebase.
+///
+/// An annotated commit contains information about how it was looked up, which
+/// may be useful for functions like merge or rebase to provide context to the
+/// operation.
+pub struct AnnotatedCommit<'repo> {
+    raw: *mut raw::git_annotated_commit,
+    marker: marker::ContravariantLifetime<'repo>,
+}
+
+/// Options to specify when merging.
+pub struct MergeOptions {
+    raw: raw::git_merge_options,
+}
+
+/// One side of a file-level merge performed by `merge_file`.
+pub struct MergeFileInput<'a> {
+    content: &'a [u8],
+    path: Option<CString>,
+    mode: u32,
+}
+
+/// Options for merging the contents of a file with `merge_file` or
+/// `Repository::merge_file_from_index`.
+pub struct MergeFileOptions {
+    ancestor_label: Option<CString>,
+    our_label: Option<CString>,
+    their_label: Option<CString>,
+    raw: raw::git_merge_file_options,
+}
+
+/// The result of a file-level merge.
+pub struct MergeFileResult {
+    raw: raw::git_merge_file_result,
+}
+
+/// Merge the contents of three versions of a file, returning the merged
+/// contents.
+///
+/// If the merge could not be resolved automatically the result will not be
+/// automergeable and its content will contain conflict markers.
+pub fn merge_file(ancestor: &MergeFileInput,
+                  ours: &MergeFileInput,
+                  theirs: &MergeFileInput,
+                  opts: Option<&mut MergeFileOptions>)
+                  -> Result<MergeFileResult, Error> {
+    ::init();
+    let ancestor = ancestor.raw();
+    let ours = ours.raw();
+    let theirs = theirs.raw();
+    unsafe {
+        let mut ret: raw::git_merge_file_result = mem::zeroed();
+        try_call!(raw::git_merge_file(&mut ret, &ancestor, &ours, &theirs,
+                                      opts.map(|o| o.raw())));
+        Ok(Binding::from_raw(ret))
+    }
+}
+
+impl<'a> MergeFileInput<'a> {
+    /// Creates a new input with the given file contents, no path and the
+    /// default mode.
+    pub fn new(content: &'a [u8]) -> MergeFileInput<'a> {
+        MergeFileInput { content: content, path: None, mode: 0 }
+    }
+
+    /// Set the path of this side of the merge, used when determining the
+    /// path of the result.
+    pub fn path<T: IntoCString>(&mut self, path: T) -> &mut MergeFileInput<'a> {
+        self.path = Some(path.into_c_string());
+        self
+    }
+
+    /// Set the file mode of this side of the merge.
+    pub fn mode(&mut self, mode: u32) -> &mut MergeFileInput<'a> {
+        self.mode = mode;
+        self
+    }
+
+    fn raw(&self) -> raw::git_merge_file_input {
+        unsafe {
+            let mut raw = mem::zeroed();
+            assert_eq!(raw::git_merge_file_init_input(&mut raw,
+                                          raw::GIT_MERGE_FILE_INPUT_VERSION), 0);
+            raw.ptr = self.content.as_ptr() as *const c_char;
+            raw.size = self.content.len() as size_t;
+            raw.path = self.path.as_ref().map(|s| s.as_ptr())
+                           .unwrap_or(0 as *const _);
+            raw.mode = self.mode as c_uint;
+            raw
+        }
+    }
+}
+
+impl MergeFileOptions {
+    /// Creates a default set of file merge options.
+    pub fn new() -> MergeFileOptions {
+        let mut opts = MergeFileOptions {
+            ancestor_label: None,
+            our_label: None,
+            their_label: None,
+            raw: unsafe { mem::zeroed() },
+        };
+        assert_eq!(unsafe {
+            raw::git_merge_file_init_options(&mut opts.raw,
+                                             raw::GIT_MERGE_FILE_OPTIONS_VERSION)
+        }, 0);
+        opts
+    }
+
+    fn flag(&mut self, opt: u32, val: bool) -> &mut MergeFileOptions {
+        if val {
+            self.raw.flags |= opt;
+        } else {
+            self.raw.flags &= !opt;
+        }
+        self
+    }
+
+    /// Label for the ancestor file side of the conflict which will be
+    /// prepended to labels in diff3-format merge files.
+    pub fn ancestor_label<T: IntoCString>(&mut self, t: T)
+                                          -> &mut MergeFileOptions {
+        self.ancestor_label = Some(t.into_c_string());
+        self
+    }
+
+    /// Label for our file side of the conflict which will be prepended to
+    /// labels in merge files.
+    pub fn our_label<T: IntoCString>(&mut self, t: T) -> &mut MergeFileOptions {
+        self.our_label = Some(t.into_c_string());
+        self
+    }
+
+    /// Label for their file side of the conflict which will be prepended to
+    /// labels in merge files.
+    pub fn their_label<T: IntoCString>(&mut self, t: T)
+                                       -> &mut MergeFileOptions {
+        self.their_label = Some(t.into_c_string());
+        self
+    }
+
+    /// Specify a side to favor for resolving conflicts
+    pub fn favor(&mut self, favor: FileFavor) -> &mut MergeFileOptions {
+        self.raw.favor = match favor {
+            FileFavor::Normal => raw::GIT_MERGE_FILE_FAVOR_NORMAL,
+            FileFavor::Ours => raw::GIT_MERGE_FILE_FAVOR_OURS,
+            FileFavor::Theirs => raw::GIT_MERGE_FILE_FAVOR_THEIRS,
+            FileFavor::Union => raw::GIT_MERGE_FILE_FAVOR_UNION,
+        };
+        self
+    }
+
+    /// Create standard conflicted merge files
+    pub fn style_standard(&mut self, standard: bool) -> &mut MergeFileOptions {
+        self.flag(raw::GIT_MERGE_FILE_STYLE_MERGE, standard)
+    }
+
+    /// Create diff3-style conflicted merge files, which include the ancestor
+    /// version of each conflicting region.
+    pub fn style_diff3(&mut self, diff3: bool) -> &mut MergeFileOptions {
+        self.flag(raw::GIT_MERGE_FILE_STYLE_DIFF3, diff3)
+    }
+
+    /// Create zdiff3-style conflicted merge files, which are like diff3 but
+    /// move common lines at the edges of a conflict outside of the markers.
+    pub fn style_zdiff3(&mut self, zdiff3: bool) -> &mut MergeFileOptions {
+        self.flag(raw::GIT_MERGE_FILE_STYLE_ZDIFF3, zdiff3)
+    }
+
+    /// Condense non-alphanumeric regions for simplified diff file
+    pub fn simplify_alnum(&mut self, simplify: bool) -> &mut MergeFileOptions {
+        self.flag(raw::GIT_MERGE_FILE_SIMPLIFY_ALNUM, simplify)
+    }
+
+    /// Ignore all whitespace
+    pub fn ignore_whitespace(&mut self, ignore: bool) -> &mut MergeFileOptions {
+        self.flag(raw::GIT_MERGE_FILE_IGNORE_WHITESPACE, ignore)
+    }
+
+    /// Ignore changes in amount of whitespace
+    pub fn ignore_whitespace_change(&mut self, ignore: bool)
+                                    -> &mut MergeFileOptions {
+        self.flag(raw::GIT_MERGE_FILE_IGNORE_WHITESPACE_CHANGE, ignore)
+    }
+
+    /// Ignore whitespace at end of line
+    pub fn ignore_whitespace_eol(&mut self, ignore: bool)
+                                 -> &mut MergeFileOptions {
+        self.flag(raw::GIT_MERGE_FILE_IGNORE_WHITESPACE_EOL, ignore)
+    }
+
+    /// Use the "patience diff" algorithm
+    pub fn patience(&mut self, patience: bool) -> &mut MergeFileOptions {
+        self.flag(raw::GIT_MERGE_FILE_DIFF_PATIENCE, patience)
+    }
+
+    /// Take extra time to find minimal diff
+    pub fn minimal(&mut self, minimal: bool) -> &mut MergeFileOptions {
+        self.flag(raw::GIT_MERGE_FILE_DIFF_MINIMAL, minimal)
+    }
+
+    /// The size of conflict markers (eg, "<<<<<<<"). Default is 7.
+    pub fn marker_size(&mut self, size: u16) -> &mut MergeFileOptions {
+        self.raw.marker_size = size as c_ushort;
+        self
+    }
+
+    /// Acquire a pointer to the underlying raw options.
+    ///
+    /// This function is unsafe as the pointer is only valid so long as this
+    /// structure is not moved, modified, or used elsewhere.
+    pub unsafe fn raw(&mut self) -> *const raw::git_merge_file_options {
+        self.raw.ancestor_label = self.ancestor_label.as_ref()
+                                      .map(|s| s.as_ptr())
+                                      .unwrap_or(0 as *const _);
+        self.raw.our_label = self.our_label.as_ref().map(|s| s.as_ptr())
+                                 .unwrap_or(0 as *const _);
+        self.raw.their_label = self.their_label.as_ref().map(|s| s.as_ptr())
+                                   .unwrap_or(0 as *const _);
+        &self.raw as *const _
+    }
+}
+
+impl MergeFileResult {
+    /// True if the output was automerged, false if the output contains
+    /// conflict markers.
+    pub fn is_automergeable(&self) -> bool {
+        self.raw.automergeable != 0
+    }
+
+    /// The path that the resultant merge file should use.
+    ///
+    /// Returns `None` if a filename conflict would occur, or if the path is
+    /// not valid utf-8.
+    pub fn path(&self) -> Option<&str> {
+        self.path_bytes().and_then(|s| str::from_utf8(s).ok())
+    }
+
+    /// Gets the path as a byte slice.
+    pub fn path_bytes(&self) -> Option<&[u8]> {
+        unsafe { ::opt_bytes(self, self.raw.path) }
+    }
+
+    /// The mode that the resultant merge file should use.
+    pub fn mode(&self) -> u32 {
+        self.raw.mode as u32
+    }
+
+    /// The contents of the merge.
+    pub fn content(&self) -> &[u8] {
+        unsafe {
+            slice::from_raw_parts(self.raw.ptr as *const u8,
+                                  self.raw.len as usize)
+        }
+    }
+}
+
+impl Binding for MergeFileResult {
+    type Raw = raw::git_merge_file_result;
+    unsafe fn from_raw(raw: raw::git_merge_file_result) -> MergeFileResult {
+        MergeFileResult { raw: raw }
+    }
+    fn raw(&self) -> raw::git_merge_file_result {
+        unsafe { ::std::ptr::read(&self.raw) }
+    }
+}
+
+impl Drop for MergeFileResult {
+    fn drop(&mut self) {
+        unsafe { raw::git_merge_file_result_free(&mut self.raw) }
+    }
+}
+
+impl<'repo> AnnotatedCommit<'repo> {
+    /// Gets the commit ID that the given AnnotatedCommit refers to
+    pub fn id(&self) -> Oid {
+        unsafe { Binding::from_raw(raw::git_annotated_commit_id(self.raw)) }
+    }
+
+    /// Get the refname that the given AnnotatedCommit refers to
+    ///
+    /// Returns `None` if it is not valid utf8 or if the commit was not looked
+    /// up through a reference.
+    pub fn refname(&self) -> Option<&str> {
+        self.refname_bytes().and_then(|s| str::from_utf8(s).ok())
+    }
+
+    /// Get the refname that the given AnnotatedCommit refers to.
+    ///
+    /// Returns `None` if the commit was not looked up through a reference.
+    pub fn refname_bytes(&self) -> Option<&[u8]> {
+        unsafe { ::opt_bytes(self, raw::git_annotated_commit_ref(&*self.raw)) }
+    }
+}
+
+impl<'repo> Binding for AnnotatedCommit<'repo> {
+    type Raw = *mut raw::git_annotated_commit;
+    unsafe fn from_raw(raw: *mut raw::git_annotated_commit)
+                       -> AnnotatedCommit<'repo> {
+        AnnotatedCommit {
+            raw: raw,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+    fn raw(&self) -> *mut raw::git_annotated_commit { self.raw }
+}
+
+#[unsafe_destructor]
+impl<'repo> Drop for AnnotatedCommit<'repo> {
+    fn drop(&mut self) {
+        unsafe { raw::git_annotated_commit_free(self.raw) }
+    }
+}
+
+impl MergeOptions {
+    /// Creates a default set of merge options.
+    pub fn new() -> MergeOptions {
+        let mut opts = MergeOptions {
+            raw: unsafe { mem::zeroed() },
+        };
+        assert_eq!(unsafe {
+            raw::git_merge_init_options(&mut opts.raw,
+                                        raw::GIT_MERGE_OPTIONS_VERSION)
+        }, 0);
+        opts
+    }
+
+    fn flag(&mut self, opt: u32, val: bool) -> &mut MergeOptions {
+        if val {
+            self.raw.flags |= opt;
+        } else {
+            self.raw.flags &= !opt;
+        }
+        self
+    }
+
+    fn file_flag(&mut self, opt: u32, val: bool) -> &mut MergeOptions {
+        if val {
+            self.raw.file_flags |= opt;
+        } else {
+            self.raw.file_flags &= !opt;
+        }
+        self
+    }
+
+    /// Detect file renames
+    pub fn find_renames(&mut self, find: bool) -> &mut MergeOptions {
+        self.flag(raw::GIT_MERGE_FIND_RENAMES, find)
+    }
+
+    /// If a conflict occurs, exit immediately instead of attempting to
+    /// continue resolving conflicts
+    pub fn fail_on_conflict(&mut self, fail: bool) -> &mut MergeOptions {
+        self.flag(raw::GIT_MERGE_FAIL_ON_CONFLICT, fail)
+    }
+
+    /// Do not write the REUC extension on the generated index
+    pub fn skip_reuc(&mut self, skip: bool) -> &mut MergeOptions {
+        self.flag(raw::GIT_MERGE_SKIP_REUC, skip)
+    }
+
+    /// If the commits being merged have multiple merge bases, do not build a
+    /// recursive merge base (by merging the multiple merge bases), instead
+    /// simply use the first base.
+    pub fn no_recursive(&mut self, disable: bool) -> &mut MergeOptions {
+        self.flag(raw::GIT_MERGE_NO_RECURSIVE, disable)
+    }
+
+    /// Similarity to consider a file renamed (default 50)
+    pub fn rename_threshold(&mut self, thresh: u32) -> &mut MergeOptions {
+        self.raw.rename_threshold = thresh as c_uint;
+        self
+    }
+
+    /// Maximum similarity sources to examine for renames (default 200).
+    ///
+    /// If the number of rename candidates (add / delete pairs) is greater
+    /// than this value, inexact rename detection is aborted. This setting
+    /// overrides the `merge.renameLimit` configuration value.
+    pub fn target_limit(&mut self, limit: u32) -> &mut MergeOptions {
+        self.raw.target_limit = limit as c_uint;
+        self
+    }
+
+    /// Maximum number of times to merge common ancestors to build a
+    /// virtual merge base when faced with criss-cross merges.
+    ///
+    /// When this limit is reached, the next ancestor will simply be used
+    /// instead of attempting to merge it. The default is unlimited.
+    pub fn recursion_limit(&mut self, limit: u32) -> &mut MergeOptions {
+        self.raw.recursion_limit = limit as c_uint;
+        self
+    }
+
+    /// Specify a side to favor for resolving conflicts
+    pub fn file_favor(&mut self, favor: FileFavor) -> &mut MergeOptions {
+        self.raw.file_favor = match favor {
+            FileFavor::Normal => raw::GIT_MERGE_FILE_FAVOR_NORMAL,
+            FileFavor::Ours => raw::GIT_MERGE_FILE_FAVOR_OURS,
+            FileFavor::Theirs => raw::GIT_MERGE_FILE_FAVOR_THEIRS,
+            FileFavor::Union => raw::GIT_MERGE_FILE_FAVOR_UNION,
+        };
+        self
+    }
+
+    /// Create standard conflicted merge files
+    pub fn standard_style(&mut self, standard: bool) -> &mut MergeOptions {
+        self.file_flag(raw::GIT_MERGE_FILE_STYLE_MERGE, standard)
+    }
+
+    /// Create diff3-style file
+    pub fn diff3_style(&mut self, diff3: bool) -> &mut MergeOptions {
+        self.file_flag(raw::GIT_MERGE_FILE_STYLE_DIFF3, diff3)
+    }
+
+    /// Condense non-alphanumeric regions for simplified diff file
+    pub fn simplify_alnum(&mut self, simplify: bool) -> &mut MergeOptions {
+        self.file_flag(raw::GIT_MERGE_FILE_SIMPLIFY_ALNUM, simplify)
+    }
+
+    /// Ignore all whitespace
+    pub fn ignore_whitespace(&mut self, ignore: bool) -> &mut MergeOptions {
+        self.file_flag(raw::GIT_MERGE_FILE_IGNORE_WHITESPACE, ignore)
+    }
+
+    /// Ignore changes in amount of whitespace
+    pub fn ignore_whitespace_change(&mut self, ignore: bool)
+                                    -> &mut MergeOptions {
+        self.file_flag(raw::GIT_MERGE_FILE_IGNORE_WHITESPACE_CHANGE, ignore)
+    }
+
+    /// Ignore whitespace at end of line
+    pub fn ignore_whitespace_eol(&mut self, ignore: bool) -> &mut MergeOptions {
+        self.file_flag(raw::GIT_MERGE_FILE_IGNORE_WHITESPACE_EOL, ignore)
+    }
+
+    /// Use the "patience diff" algorithm
+    pub fn patience(&mut self, patience: bool) -> &mut MergeOptions {
+        self.file_flag(raw::GIT_MERGE_FILE_DIFF_PATIENCE, patience)
+    }
+
+    /// Take extra time to find minimal diff
+    pub fn minimal(&mut self, minimal: bool) -> &mut MergeOptions {
+        self.file_flag(raw::GIT_MERGE_FILE_DIFF_MINIMAL, minimal)
+    }
+
+    /// Acquire a pointer to the underlying raw options.
+    ///
+    /// This function is unsafe as the pointer is only valid so long as this
+    /// structure is not moved, modified, or used elsewhere.
+    pub unsafe fn raw(&self) -> *const raw::git_merge_options {
+        &self.raw as *const _
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::File;
+    use std::old_io::fs::PathExtensions;
+    use {CheckoutBuilder, FileFavor, MergeOptions, Oid, Repository};
+    use {RepositoryState};
+    use {merge_file, MergeFileInput, MergeFileOptions};
+    use {MERGE_ANALYSIS_FASTFORWARD, MERGE_ANALYSIS_NORMAL};
+    use {MERGE_ANALYSIS_UP_TO_DATE};
+
+    fn commit(repo: &Repository, contents: &str, parent: Option<Oid>) -> Oid {
+        let root = repo.workdir().unwrap();
+        File::create(&root.join("foo")).write_str(contents).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("foo")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = repo.signature().unwrap();
+        let parent = match parent {
+            Some(id) => repo.find_commit(id).unwrap(),
+            None => {
+                let head = repo.head().unwrap().target().unwrap();
+                repo.find_commit(head).unwrap()
+            }
+        };
+        repo.commit(None, &sig, &sig, contents, &tree, &[&parent]).unwrap()
+    }
+
+    #[test]
+    fn merge_commits_and_trees() {
+        let (_td, repo) = ::test::repo_init();
+        let base = commit(&repo, "a\nb\nc\n", None);
+        let ours = commit(&repo, "A\nb\nc\n", Some(base));
+        let theirs = commit(&repo, "a\nb\nC\n", Some(base));
+        let conflict = commit(&repo, "a\nb\nX\n", Some(base));
+
+        let ours = repo.find_commit(ours).unwrap();
+        let theirs = repo.find_commit(theirs).unwrap();
+        let conflict = repo.find_commit(conflict).unwrap();
+
+        let mut index = repo.merge_commits(&ours, &theirs, None).unwrap();
+        assert!(!index.has_conflicts());
+        let id = index.write_tree_to(&repo).unwrap();
+        let tree = repo.find_tree(id).unwrap();
+        let entry = tree.get_name("foo").unwrap();
+        let blob = entry.to_object(&repo).unwrap();
+        assert_eq!(blob.as_blob().unwrap().content(), b"A\nb\nC\n");
+
+        let index = repo.merge_commits(&theirs, &conflict, None).unwrap();
+        assert!(index.has_conflicts());
+
+        let mut opts = MergeOptions::new();
+        opts.fail_on_conflict(true);
+        assert!(repo.merge_commits(&theirs, &conflict,
+                                   Some(&opts)).is_err());
+
+        let mut opts = MergeOptions::new();
+        opts.file_favor(FileFavor::Ours);
+        let base = repo.find_commit(base).unwrap().tree().unwrap();
+        let index = repo.merge_trees(&base, &theirs.tree().unwrap(),
+                                     &conflict.tree().unwrap(),
+                                     Some(&opts)).unwrap();
+        assert!(!index.has_conflicts());
+    }
+
+    #[test]
+    fn analysis_and_merge() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let base = commit(&repo, "a\nb\nc\n", None);
+
+        let annotated = repo.find_annotated_commit(base).unwrap();
+        assert_eq!(annotated.id(), base);
+        let (analysis, _) = repo.merge_analysis(&[&annotated]).unwrap();
+        assert_eq!(analysis,
+                   MERGE_ANALYSIS_NORMAL | MERGE_ANALYSIS_FASTFORWARD);
+
+        let initial = repo.find_annotated_commit(head).unwrap();
+        let (analysis, _) = repo.merge_analysis(&[&initial]).unwrap();
+        assert_eq!(analysis, MERGE_ANALYSIS_UP_TO_DATE);
+
+        let sig = repo.signature().unwrap();
+        let base_commit = repo.find_commit(base).unwrap();
+        let tree = base_commit.tree().unwrap();
+        let parent = repo.find_commit(head).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "other", &tree,
+                    &[&parent]).unwrap();
+        let (analysis, _) = repo.merge_analysis(&[&annotated]).unwrap();
+        assert_eq!(analysis, MERGE_ANALYSIS_NORMAL);
+
+        // the merged tree matches the working directory, so only the initial
+        // report is made, without a path
+        let mut reports = Vec::new();
+        {
+            let mut co = CheckoutBuilder::new();
+            co.progress(|path, cur, total| {
+                reports.push((path.cloned(), cur, total));
+            });
+            repo.merge(&[&annotated], None, Some(&mut co)).unwrap();
+        }
+        assert_eq!(reports, vec![(None, 0, 0)]);
+        assert_eq!(repo.state(), RepositoryState::Merge);
+        let merge_head = repo.path().join("MERGE_HEAD");
+        let contents = File::open(&merge_head).read_to_string().unwrap();
+        assert_eq!(contents.trim(), base.to_string());
+        assert!(repo.path().join("MERGE_MSG").exists());
+    }
+
+    #[test]
+    fn annotated_commit_constructors() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap();
+        let id = head.target().unwrap();
+
+        let annotated = repo.reference_to_annotated_commit(&head).unwrap();
+        assert_eq!(annotated.id(), id);
+        assert_eq!(annotated.refname(), Some("refs/heads/master"));
+
+        let annotated = repo.annotated_commit_from_revspec("HEAD").unwrap();
+        assert_eq!(annotated.id(), id);
+        let annotated = repo.annotated_commit_from_revspec("HEAD~10");
+        assert!(annotated.is_err());
+
+        let annotated = repo.annotated_commit_from_fetchhead(
+            "main", "https://example.com/repo.git", &id).unwrap();
+        assert_eq!(annotated.id(), id);
+        assert_eq!(annotated.refname(), Some("main"));
+
+        let annotated = repo.find_annotated_commit(id).unwrap();
+        assert_eq!(annotated.refname(), None);
+    }
+
+    #[test]
+    fn merge_file_contents() {
+        let ancestor = MergeFileInput::new(b"a\nb\nc\n");
+        let mut ours = MergeFileInput::new(b"A\nb\nc\n");
+        ours.path("foo").mode(0o100644);
+        let theirs = MergeFileInput::new(b"a\nb\nC\n");
+        let result = merge_file(&ancestor, &ours, &theirs, None).unwrap();
+        assert!(result.is_automergeable());
+        assert_eq!(result.content(), b"A\nb\nC\n");
+        assert_eq!(result.path(), Some("foo"));
+        assert_eq!(result.mode(), 0o100644);
+
+        let theirs = MergeFileInput::new(b"X\nb\nc\n");
+        let mut opts = MergeFileOptions::new();
+        opts.our_label("ours").their_label("theirs").ancestor_label("base")
+            .style_diff3(true);
+        let result = merge_file(&ancestor, &ours, &theirs,
+                                Some(&mut opts)).unwrap();
+        assert!(!result.is_automergeable());
+        assert_eq!(result.content(), b"<<<<<<< ours\nA\n||||||| base\na\n\
+                                       =======\nX\n>>>>>>> theirs\nb\nc\n");
+
+        let mut opts = MergeFileOptions::new();
+        opts.favor(FileFavor::Theirs);
+        let result = merge_file(&ancestor, &ours, &theirs,
+                                Some(&mut opts)).unwrap();
+        assert!(result.is_automergeable());
+        assert_eq!(result.content(), b"X\nb\nc\n");
+    }
+
+    #[test]
+    fn merge_file_from_index() {
+        let (_td, repo) = ::test::repo_init();
+        let base = commit(&repo, "a\nb\nc\n", None);
+        let ours = commit(&repo, "A\nb\nc\n", Some(base));
+        let theirs = commit(&repo, "a\nb\nC\n", Some(base));
+        let entry = |id: Oid| {
+            let commit = repo.find_commit(id).unwrap();
+            let mut index = repo.index().unwrap();
+            index.read_tree(&commit.tree().unwrap()).unwrap();
+            index.get_path(&Path::new("foo"), 0).unwrap()
+        };
+        let (base, ours, theirs) = (entry(base), entry(ours), entry(theirs));
+        let result = repo.merge_file_from_index(&base, &ours, &theirs,
+                                                None).unwrap();
+        assert!(result.is_automergeable());
+        assert_eq!(result.path(), Some("foo"));
+        assert_eq!(result.content(), b"A\nb\nC\n");
+    }
+}
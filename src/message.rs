@@ -0,0 +1,138 @@
+use std::ffi::CString;
+use std::iter::{range, Range};
+use std::mem;
+use std::str;
+use libc::{c_char, c_int};
+
+use {raw, Buf, Error};
+
+/// Clean up a message, the way git does before writing it to a commit or
+/// tag.
+///
+/// Whitespace is trimmed from the end of each line, runs of empty lines are
+/// collapsed into one and leading and trailing empty lines are removed. If a
+/// `comment_char` is given then lines starting with it are removed as
+/// comments. The result always ends with a newline, unless it is empty.
+pub fn message_prettify(message: &str, comment_char: Option<u8>)
+                        -> Result<String, Error> {
+    ::init();
+    let message = CString::from_slice(message.as_bytes());
+    let ret = Buf::new();
+    unsafe {
+        try_call!(raw::git_message_prettify(ret.raw(), message,
+                                            comment_char.is_some() as c_int,
+                                            comment_char.unwrap_or(0)
+                                                as c_char));
+    }
+    Ok(ret.as_str().unwrap().to_string())
+}
+
+/// A collection of the trailers of a message, such as `Signed-off-by` and
+/// `Co-authored-by` lines, as returned by `message_trailers`.
+pub struct MessageTrailers {
+    raw: raw::git_message_trailer_array,
+}
+
+/// An iterator over the key/value pairs of a `MessageTrailers`.
+pub struct MessageTrailersIter<'a> {
+    trailers: &'a MessageTrailers,
+    range: Range<usize>,
+}
+
+/// Parse the trailers out of a message.
+///
+/// Trailers are the `Key: value` lines in the last paragraph of a message,
+/// as understood by `git interpret-trailers`.
+pub fn message_trailers(message: &str) -> Result<MessageTrailers, Error> {
+    ::init();
+    let message = CString::from_slice(message.as_bytes());
+    unsafe {
+        let mut ret: raw::git_message_trailer_array = mem::zeroed();
+        try_call!(raw::git_message_trailers(&mut ret, message));
+        Ok(MessageTrailers { raw: ret })
+    }
+}
+
+impl MessageTrailers {
+    /// Gets the number of trailers found in the message.
+    pub fn len(&self) -> usize { self.raw.count as usize }
+
+    /// Gets the key and value of the trailer at the given index, or `None`
+    /// if the index is out of bounds or either is not valid utf-8.
+    pub fn get(&self, index: usize) -> Option<(&str, &str)> {
+        self.get_bytes(index).and_then(|(k, v)| {
+            match (str::from_utf8(k), str::from_utf8(v)) {
+                (Ok(k), Ok(v)) => Some((k, v)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Gets the key and value of the trailer at the given index as byte
+    /// slices, or `None` if the index is out of bounds.
+    pub fn get_bytes(&self, index: usize) -> Option<(&[u8], &[u8])> {
+        if index >= self.len() {
+            return None
+        }
+        unsafe {
+            let trailer = self.raw.trailers.offset(index as isize);
+            Some((::opt_bytes(self, (*trailer).key).unwrap(),
+                  ::opt_bytes(self, (*trailer).value).unwrap()))
+        }
+    }
+
+    /// Returns an iterator over the key/value pairs of the trailers.
+    pub fn iter(&self) -> MessageTrailersIter {
+        MessageTrailersIter { trailers: self, range: range(0, self.len()) }
+    }
+}
+
+impl Drop for MessageTrailers {
+    fn drop(&mut self) {
+        unsafe { raw::git_message_trailer_array_free(&mut self.raw) }
+    }
+}
+
+impl<'a> Iterator for MessageTrailersIter<'a> {
+    type Item = (&'a str, &'a str);
+    fn next(&mut self) -> Option<(&'a str, &'a str)> {
+        self.range.next().and_then(|i| self.trailers.get(i))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) { self.range.size_hint() }
+}
+impl<'a> DoubleEndedIterator for MessageTrailersIter<'a> {
+    fn next_back(&mut self) -> Option<(&'a str, &'a str)> {
+        self.range.next_back().and_then(|i| self.trailers.get(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {message_prettify, message_trailers};
+
+    #[test]
+    fn prettify() {
+        let msg = "\n\nsummary  \n\n\n# a comment\nbody\t\n\n";
+        assert_eq!(message_prettify(msg, None).unwrap(),
+                   "summary\n\n# a comment\nbody\n");
+        assert_eq!(message_prettify(msg, Some(b'#')).unwrap(),
+                   "summary\n\nbody\n");
+        assert_eq!(message_prettify("", None).unwrap(), "");
+    }
+
+    #[test]
+    fn trailers() {
+        let msg = "summary\n\nbody\n\n\
+                   Signed-off-by: a <a@example.com>\n\
+                   Co-authored-by: b <b@example.com>\n";
+        let trailers = message_trailers(msg).unwrap();
+        assert_eq!(trailers.len(), 2);
+        assert_eq!(trailers.get(0),
+                   Some(("Signed-off-by", "a <a@example.com>")));
+        assert!(trailers.get(2).is_none());
+        assert_eq!(trailers.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+                   vec!["Signed-off-by", "Co-authored-by"]);
+
+        assert_eq!(message_trailers("summary\n\nbody\n").unwrap().len(), 0);
+    }
+}
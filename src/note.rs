@@ -123,4 +123,61 @@ mod tests {
 
         assert_eq!(repo.note_default_ref().unwrap(), "refs/notes/commits");
     }
+
+    #[test]
+    fn custom_ref() {
+        let (_td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let notes_ref = Some("refs/notes/review");
+
+        repo.note(&sig, &sig, notes_ref, head, "lgtm", false).unwrap();
+        assert!(repo.find_note(None, head).is_err());
+        assert!(repo.note(&sig, &sig, notes_ref, head, "nope", false).is_err());
+        repo.note(&sig, &sig, notes_ref, head, "r+", true).unwrap();
+        let note = repo.find_note(notes_ref, head).unwrap();
+        assert_eq!(note.message(), Some("r+"));
+        assert_eq!(note.author().name(), Some("name"));
+
+        repo.config().unwrap().set_str("core.notesRef", "refs/notes/review")
+            .unwrap();
+        assert_eq!(repo.note_default_ref().unwrap(), "refs/notes/review");
+        assert_eq!(repo.find_note(None, head).unwrap().message(), Some("r+"));
+
+        repo.note_delete(head, notes_ref, &sig, &sig).unwrap();
+        assert!(repo.find_note(notes_ref, head).is_err());
+        assert!(repo.note_delete(head, notes_ref, &sig, &sig).is_err());
+    }
+
+    #[test]
+    fn commits() {
+        let (_td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+
+        let (id, blob) = repo.note_commit_create(None, &sig, &sig, head, "foo",
+                                                 false).unwrap();
+        let notes = repo.find_commit(id).unwrap();
+        assert_eq!(notes.parents().count(), 0);
+        assert_eq!(repo.find_blob(blob).unwrap().content(), b"foo");
+        assert!(repo.find_note(None, head).is_err());
+        let note = repo.note_commit_read(&notes, head).unwrap();
+        assert_eq!(note.id(), blob);
+        assert_eq!(note.message(), Some("foo"));
+
+        assert!(repo.note_commit_create(Some(&notes), &sig, &sig, head, "bar",
+                                        false).is_err());
+        let (id, _) = repo.note_commit_create(Some(&notes), &sig, &sig, head,
+                                              "bar", true).unwrap();
+        let first = notes.id();
+        let notes = repo.find_commit(id).unwrap();
+        assert_eq!(notes.parent_id(0).unwrap(), first);
+        assert_eq!(repo.note_commit_read(&notes, head).unwrap().message(),
+                   Some("bar"));
+
+        let id = repo.note_commit_remove(&notes, &sig, &sig, head).unwrap();
+        let removed = repo.find_commit(id).unwrap();
+        assert!(repo.note_commit_read(&removed, head).is_err());
+        assert!(repo.note_commit_read(&notes, head).is_ok());
+    }
 }
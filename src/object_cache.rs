@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi;
+use std::mem;
+use libc::{c_char, size_t};
+
+use {raw, ObjectType, Oid};
+
+// A rough allowance for the memory libgit2 uses for a parsed object besides
+// its variable length data.
+const OBJECT_OVERHEAD: usize = 64;
+const TREE_ENTRY_OVERHEAD: usize = 48;
+
+/// A cache of parsed commits and trees which can be attached to a
+/// `Repository` with `set_object_cache`.
+///
+/// Libgit2 keeps a cache of parsed objects of its own, but it is limited for
+/// the whole process and evicts objects at random once it is full. While an
+/// `ObjectCache` is attached, the commits and trees looked up with
+/// `find_commit` and `find_tree` are kept until their estimated size exceeds
+/// the budget of the cache, and the least recently used ones are evicted
+/// first, so repeated traversals find them again without another lookup.
+pub struct ObjectCache {
+    limit: usize,
+    size: usize,
+    tick: u64,
+    hits: usize,
+    misses: usize,
+    entries: HashMap<Oid, Entry>,
+    lru: BTreeMap<u64, Oid>,
+}
+
+struct Entry {
+    raw: *mut raw::git_object,
+    size: usize,
+    tick: u64,
+}
+
+// The cached objects are only handed out through the repository the cache
+// is attached to, which may itself be sent among threads.
+unsafe impl Send for ObjectCache {}
+
+impl ObjectCache {
+    /// Create a new, empty cache which holds objects of an estimated size of
+    /// up to `limit` bytes.
+    pub fn new(limit: usize) -> ObjectCache {
+        ObjectCache {
+            limit: limit,
+            size: 0,
+            tick: 0,
+            hits: 0,
+            misses: 0,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+        }
+    }
+
+    /// Get the budget of this cache in bytes.
+    pub fn limit(&self) -> usize { self.limit }
+
+    /// Get the estimated size in bytes of the objects in this cache.
+    pub fn size(&self) -> usize { self.size }
+
+    /// Get the number of objects in this cache.
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    /// Get the number of lookups which found their object in this cache.
+    pub fn hits(&self) -> usize { self.hits }
+
+    /// Get the number of lookups which did not find their object in this
+    /// cache.
+    pub fn misses(&self) -> usize { self.misses }
+
+    /// Remove all objects from this cache.
+    pub fn clear(&mut self) {
+        let entries = mem::replace(&mut self.entries, HashMap::new());
+        for (_, entry) in entries.into_iter() {
+            unsafe { raw::git_object_free(entry.raw) }
+        }
+        self.lru.clear();
+        self.size = 0;
+    }
+}
+
+impl Drop for ObjectCache {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Look up an object of the given type in the cache, returning a new
+/// reference to it which the caller owns.
+pub fn get(cache: &mut ObjectCache, id: Oid, kind: ObjectType)
+           -> Option<*mut raw::git_object> {
+    cache.tick += 1;
+    let tick = cache.tick;
+    match cache.entries.get_mut(&id) {
+        Some(entry) => unsafe {
+            if ObjectType::from_raw(raw::git_object_type(&*entry.raw)) !=
+               Some(kind) {
+                return None
+            }
+            cache.lru.remove(&entry.tick);
+            cache.lru.insert(tick, id);
+            entry.tick = tick;
+            cache.hits += 1;
+            let mut ret = 0 as *mut raw::git_object;
+            raw::git_object_dup(&mut ret, entry.raw);
+            Some(ret)
+        },
+        None => {
+            cache.misses += 1;
+            None
+        }
+    }
+}
+
+/// Add an object to the cache, which takes a reference of its own, evicting
+/// the least recently used objects to stay within its budget.
+pub fn insert(cache: &mut ObjectCache, id: Oid, obj: *mut raw::git_object) {
+    let size = unsafe { estimate(obj) };
+    if size > cache.limit || cache.entries.contains_key(&id) {
+        return
+    }
+    cache.tick += 1;
+    let mut raw = 0 as *mut raw::git_object;
+    unsafe { raw::git_object_dup(&mut raw, obj); }
+    cache.entries.insert(id, Entry { raw: raw, size: size, tick: cache.tick });
+    cache.lru.insert(cache.tick, id);
+    cache.size += size;
+
+    while cache.size > cache.limit {
+        let (tick, id) = match cache.lru.iter().next() {
+            Some((tick, id)) => (*tick, *id),
+            None => break,
+        };
+        cache.lru.remove(&tick);
+        let entry = cache.entries.remove(&id).unwrap();
+        cache.size -= entry.size;
+        unsafe { raw::git_object_free(entry.raw) }
+    }
+}
+
+unsafe fn estimate(obj: *mut raw::git_object) -> usize {
+    let len = |p: *const c_char| ffi::c_str_to_bytes(&p).len();
+    match ObjectType::from_raw(raw::git_object_type(&*obj)) {
+        Some(ObjectType::Commit) => {
+            let commit = obj as *const raw::git_commit;
+            OBJECT_OVERHEAD + len(raw::git_commit_raw_header(commit)) +
+                len(raw::git_commit_message_raw(commit))
+        }
+        Some(ObjectType::Tree) => {
+            let tree = obj as *const raw::git_tree;
+            let count = raw::git_tree_entrycount(tree) as usize;
+            range(0, count).fold(OBJECT_OVERHEAD, |size, i| {
+                let entry = raw::git_tree_entry_byindex(tree, i as size_t);
+                size + TREE_ENTRY_OVERHEAD +
+                    len(raw::git_tree_entry_name(entry))
+            })
+        }
+        _ => OBJECT_OVERHEAD,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ObjectCache;
+    use util::Binding;
+
+    #[test]
+    fn smoke() {
+        let (_td, mut repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let tree = repo.find_commit(head).unwrap().tree_id();
+        assert!(repo.set_object_cache(Some(ObjectCache::new(1 << 20)))
+                    .is_none());
+
+        for _ in range(0, 3) {
+            let commit = repo.find_commit(head).unwrap();
+            assert_eq!(commit.message(), Some("initial"));
+            assert_eq!(repo.find_tree(tree).unwrap().len(), 0);
+        }
+        // the type of a cached object is still checked
+        assert!(repo.find_tree(head).is_err());
+        assert!(repo.find_commit(tree).is_err());
+
+        let cache = repo.set_object_cache(None).unwrap();
+        assert_eq!((cache.len(), cache.size()), (0, 0));
+        assert_eq!((cache.hits(), cache.misses()), (4, 2));
+
+        // a budget too small for both objects keeps the most recent one
+        let mut cache = ObjectCache::new(1 << 20);
+        {
+            let commit = repo.find_commit(head).unwrap();
+            let tree = repo.find_tree(tree).unwrap();
+            ::object_cache::insert(&mut cache, head, commit.raw() as *mut _);
+            ::object_cache::insert(&mut cache, tree.id(), tree.raw() as *mut _);
+        }
+        let small = ObjectCache::new(cache.size() - 1);
+        repo.set_object_cache(Some(small));
+        repo.find_commit(head).unwrap();
+        repo.find_tree(tree).unwrap();
+        repo.find_tree(tree).unwrap();
+        repo.find_commit(head).unwrap();
+        let small = repo.set_object_cache(None).unwrap();
+        assert_eq!((small.hits(), small.misses()), (1, 3));
+    }
+}
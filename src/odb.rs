@@ -0,0 +1,247 @@
+use std::ffi::CString;
+use std::marker;
+use std::mem;
+use std::slice;
+use libc::{c_void, size_t};
+
+use {raw, Error, ObjectType, Oid};
+use util::Binding;
+
+/// A structure to represent the object database of a repository, the set of
+/// loose object directories, packs and alternates its objects are read from.
+pub struct Odb {
+    raw: *mut raw::git_odb,
+}
+
+/// An object read from an object database, holding its inflated content.
+pub struct OdbObject<'odb> {
+    raw: *mut raw::git_odb_object,
+    marker: marker::ContravariantLifetime<'odb>,
+}
+
+impl Odb {
+    /// Create a new object database with no backends.
+    ///
+    /// Objects can only be read from it once backends, such as an alternate
+    /// added with `add_disk_alternate`, have been added.
+    pub fn new() -> Result<Odb, Error> {
+        ::init();
+        let mut raw = 0 as *mut raw::git_odb;
+        unsafe {
+            try_call!(raw::git_odb_new(&mut raw));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
+    /// Determine whether an object with the given id can be found in this
+    /// object database, including any of its alternates.
+    pub fn exists(&self, id: Oid) -> bool {
+        unsafe { raw::git_odb_exists(self.raw, id.raw()) != 0 }
+    }
+
+    /// Add an on-disk object directory, such as the `objects` directory of
+    /// another repository, as an alternate of this object database.
+    ///
+    /// Objects in the alternate can then be read, but new objects are never
+    /// written to it. The alternate is only added in memory; to make it
+    /// persistent it must also be listed in `objects/info/alternates`.
+    pub fn add_disk_alternate(&self, path: &Path) -> Result<(), Error> {
+        let path = CString::from_slice(path.as_vec());
+        unsafe { try_call!(raw::git_odb_add_disk_alternate(self.raw, path)); }
+        Ok(())
+    }
+
+    /// Read an object from this object database.
+    ///
+    /// Unlike looking the object up through a repository, the content is not
+    /// parsed, so this works the same for objects of any type.
+    pub fn read(&self, id: Oid) -> Result<OdbObject, Error> {
+        let mut raw = 0 as *mut raw::git_odb_object;
+        unsafe {
+            try_call!(raw::git_odb_read(&mut raw, self.raw, id.raw()));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+}
+
+impl<'odb> OdbObject<'odb> {
+    /// Get the id of this object.
+    pub fn id(&self) -> Oid {
+        unsafe { Binding::from_raw(raw::git_odb_object_id(self.raw)) }
+    }
+
+    /// Get the type of this object.
+    pub fn kind(&self) -> Option<ObjectType> {
+        ObjectType::from_raw(unsafe { raw::git_odb_object_type(self.raw) })
+    }
+
+    /// Get the size in bytes of the content of this object.
+    pub fn len(&self) -> usize {
+        unsafe { raw::git_odb_object_size(self.raw) as usize }
+    }
+
+    /// Get the content of this object.
+    ///
+    /// The slice borrows the buffer libgit2 inflated the object into, so no
+    /// copy is made; use `into_owned` to keep the content beyond the
+    /// lifetime of the object.
+    pub fn data(&self) -> &[u8] {
+        unsafe {
+            let data = raw::git_odb_object_data(self.raw) as *const u8;
+            slice::from_raw_parts(data, self.len())
+        }
+    }
+
+    /// Copy the content of this object into a new vector and release the
+    /// object.
+    pub fn into_owned(self) -> Vec<u8> { self.data().to_vec() }
+}
+
+/// A pack being written along with its index as its data arrives, resolving
+/// the bases of the pack against an object database.
+pub struct Indexer {
+    raw: *mut raw::git_indexer,
+    stats: raw::git_transfer_progress,
+}
+
+impl Indexer {
+    /// Create an indexer writing the pack and its index to `dir`.
+    pub fn new(odb: &Odb, dir: &Path) -> Result<Indexer, Error> {
+        let dir = CString::from_slice(dir.as_vec());
+        let mut ret = Indexer {
+            raw: 0 as *mut raw::git_indexer,
+            stats: unsafe { mem::zeroed() },
+        };
+        unsafe {
+            let mut opts: raw::git_indexer_options = mem::zeroed();
+            try_call!(raw::git_indexer_options_init(
+                &mut opts, raw::GIT_INDEXER_OPTIONS_VERSION));
+            try_call!(raw::git_indexer_new(&mut ret.raw, dir, 0, odb.raw,
+                                           &opts));
+        }
+        Ok(ret)
+    }
+
+    /// Add the next chunk of pack data.
+    pub fn append(&mut self, data: &[u8]) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_indexer_append(self.raw,
+                                              data.as_ptr() as *const c_void,
+                                              data.len() as size_t,
+                                              &mut self.stats));
+        }
+        Ok(())
+    }
+
+    /// Finish the pack once all of its data has been added, and return its
+    /// hash.
+    pub fn commit(&mut self) -> Result<Oid, Error> {
+        unsafe {
+            try_call!(raw::git_indexer_commit(self.raw, &mut self.stats));
+            Ok(Binding::from_raw(raw::git_indexer_hash(self.raw)))
+        }
+    }
+}
+
+impl Drop for Indexer {
+    fn drop(&mut self) {
+        unsafe { raw::git_indexer_free(self.raw) }
+    }
+}
+
+impl Binding for Odb {
+    type Raw = *mut raw::git_odb;
+    unsafe fn from_raw(raw: *mut raw::git_odb) -> Odb {
+        Odb { raw: raw }
+    }
+    fn raw(&self) -> *mut raw::git_odb { self.raw }
+}
+
+impl Drop for Odb {
+    fn drop(&mut self) {
+        unsafe { raw::git_odb_free(self.raw) }
+    }
+}
+
+impl<'odb> Binding for OdbObject<'odb> {
+    type Raw = *mut raw::git_odb_object;
+    unsafe fn from_raw(raw: *mut raw::git_odb_object) -> OdbObject<'odb> {
+        OdbObject { raw: raw, marker: marker::ContravariantLifetime }
+    }
+    fn raw(&self) -> *mut raw::git_odb_object { self.raw }
+}
+
+#[unsafe_destructor]
+impl<'odb> Drop for OdbObject<'odb> {
+    fn drop(&mut self) {
+        unsafe { raw::git_odb_object_free(self.raw) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::{File, TempDir};
+    use {Odb, ObjectType, Repository};
+
+    #[test]
+    fn alternates() {
+        let (td, repo) = ::test::repo_init();
+        let id = repo.head().unwrap().target().unwrap();
+        let objects = td.path().join(".git/objects");
+
+        let td2 = TempDir::new("test").unwrap();
+        let repo2 = Repository::init(td2.path()).unwrap();
+        let odb = repo2.odb().unwrap();
+        assert!(!odb.exists(id));
+        assert_eq!(repo2.alternates().unwrap(), Vec::<Path>::new());
+
+        odb.add_disk_alternate(&objects).unwrap();
+        assert!(odb.exists(id));
+        repo2.find_commit(id).unwrap();
+
+        let info = td2.path().join(".git/objects/info/alternates");
+        File::create(&info).write_str(format!("# shared\n{}\n\nrel\n",
+                                              objects.display())
+                                          .as_slice()).unwrap();
+        assert_eq!(repo2.alternates().unwrap(),
+                   vec![objects, repo2.path().join("objects/rel")]);
+    }
+
+    #[test]
+    fn wrap() {
+        let (td, repo) = ::test::repo_init();
+        let id = repo.head().unwrap().target().unwrap();
+
+        let odb = Odb::new().unwrap();
+        assert!(!odb.exists(id));
+        odb.add_disk_alternate(&td.path().join(".git/objects")).unwrap();
+        let repo = Repository::from_odb(&odb).unwrap();
+        let commit = repo.find_commit(id).unwrap();
+        assert_eq!(commit.message(), Some("initial"));
+        repo.find_tree(commit.tree_id()).unwrap();
+        assert!(repo.head().is_err());
+        assert!(repo.find_reference("refs/heads/master").is_err());
+        assert_eq!(repo.index().unwrap().len(), 0);
+        assert!(repo.config().unwrap().get_str("user.name").is_err());
+    }
+
+    #[test]
+    fn read() {
+        let (_td, repo) = ::test::repo_init();
+        let id = repo.blob(b"foo\n").unwrap();
+        let odb = repo.odb().unwrap();
+        let obj = odb.read(id).unwrap();
+        assert_eq!(obj.id(), id);
+        assert_eq!(obj.kind(), Some(ObjectType::Blob));
+        assert_eq!(obj.len(), 4);
+        assert_eq!(obj.data(), b"foo\n");
+        assert_eq!(obj.into_owned(), b"foo\n".to_vec());
+
+        let head = repo.head().unwrap().target().unwrap();
+        let obj = odb.read(head).unwrap();
+        assert_eq!(obj.kind(), Some(ObjectType::Commit));
+        assert!(obj.data().starts_with(b"tree "));
+        let missing = "0123456789012345678901234567890123456789";
+        assert!(odb.read(::Oid::from_str(missing).unwrap()).is_err());
+    }
+}
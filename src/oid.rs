@@ -16,8 +16,9 @@ pub struct Oid {
 impl Oid {
     /// Parse a hex-formatted object id into an Oid structure.
     ///
-    /// If the string is not a valid 40-character hex string, an error is
-    /// returned.
+    /// The string may be an abbreviated hex id, in which case the remaining
+    /// digits are filled in with zeros. If the string is not valid hex or is
+    /// longer than 40 characters, an error is returned.
     pub fn from_str(s: &str) -> Result<Oid, Error> {
         ::init();
         let mut raw = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
@@ -39,11 +40,16 @@ impl Oid {
         if bytes.len() != raw::GIT_OID_RAWSZ {
             Err(Error::from_str("raw byte array must be 20 bytes"))
         } else {
-            unsafe { raw::git_oid_fromraw(&mut raw, bytes.as_ptr()) }
+            unsafe { try_call!(raw::git_oid_fromraw(&mut raw, bytes.as_ptr())); }
             Ok(Oid { raw: raw })
         }
     }
 
+    /// Creates an all zero Oid structure.
+    pub fn zero() -> Oid {
+        Oid { raw: raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] } }
+    }
+
     /// View this OID as a byte-slice 20 bytes in length.
     pub fn as_bytes(&self) -> &[u8] { self.raw.id.as_slice() }
 
@@ -125,4 +131,17 @@ mod tests {
         assert!(Oid::from_bytes(b"foo").is_err());
         assert!(Oid::from_bytes(b"00000000000000000000").is_ok());
     }
+
+    #[test]
+    fn zero_and_prefix() {
+        assert!(Oid::zero().is_zero());
+        assert_eq!(Oid::zero(), Oid::from_bytes(&[0; 20]).unwrap());
+        let short = Oid::from_str("decbf2b").unwrap();
+        assert!(!short.is_zero());
+        assert_eq!(short.to_string(),
+                   "decbf2b000000000000000000000000000000000");
+        assert!(short < Oid::from_str("decbf2c").unwrap());
+        assert!(Oid::from_str("decbf2be529ab6557d5429922251e5ee365198170")
+                    .is_err());
+    }
 }
@@ -0,0 +1,44 @@
+//! Bindings to libgit2's raw git_oidarray type
+
+use std::ops::Deref;
+use std::mem;
+use std::slice;
+
+use {raw, Oid};
+use util::Binding;
+
+/// An oid array structure used by libgit2
+///
+/// Some apis return arrays of oids which originate from libgit2. This
+/// wrapper type behaves like a `&[Oid]` but does so without copying the
+/// underlying oids.
+pub struct OidArray {
+    raw: raw::git_oidarray,
+}
+
+impl Deref for OidArray {
+    type Target = [Oid];
+
+    fn deref(&self) -> &[Oid] {
+        unsafe {
+            debug_assert_eq!(mem::size_of::<Oid>(),
+                             mem::size_of::<raw::git_oid>());
+            slice::from_raw_parts(self.raw.ids as *const Oid,
+                                  self.raw.count as usize)
+        }
+    }
+}
+
+impl Binding for OidArray {
+    type Raw = raw::git_oidarray;
+    unsafe fn from_raw(raw: raw::git_oidarray) -> OidArray {
+        OidArray { raw: raw }
+    }
+    fn raw(&self) -> raw::git_oidarray { self.raw }
+}
+
+impl Drop for OidArray {
+    fn drop(&mut self) {
+        unsafe { raw::git_oidarray_free(&mut self.raw) }
+    }
+}
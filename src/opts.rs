@@ -0,0 +1,252 @@
+//! Bindings to libgit2's global options, set through `git_libgit2_opts`
+//!
+//! These options affect every repository opened by the process, and allow
+//! tuning how much memory libgit2 will use for mapping pack files and caching
+//! objects, where it looks for configuration files, and which certificates
+//! it trusts.
+//!
+//! The setters in this module are unsafe as they modify global state which
+//! is not synchronized by libgit2; they must not race with each other or
+//! with any other use of libgit2.
+
+use std::ptr;
+use libc::{c_int, c_char, size_t, ssize_t};
+
+use {raw, call, Buf, ConfigLevel, Error, IntoCString, ObjectType};
+use util::Binding;
+
+/// Get the maximum size, in bytes, of a single window mapped into memory
+/// when reading pack files.
+pub fn get_mwindow_size() -> Result<usize, Error> {
+    ::init();
+    let mut size = 0 as size_t;
+    unsafe {
+        try!(call::try(raw::git_libgit2_opts(
+            raw::GIT_OPT_GET_MWINDOW_SIZE as c_int, &mut size)));
+    }
+    Ok(size as usize)
+}
+
+/// Set the maximum size, in bytes, of a single window mapped into memory
+/// when reading pack files.
+pub unsafe fn set_mwindow_size(size: usize) -> Result<(), Error> {
+    ::init();
+    try!(call::try(raw::git_libgit2_opts(
+        raw::GIT_OPT_SET_MWINDOW_SIZE as c_int, size as size_t)));
+    Ok(())
+}
+
+/// Get the maximum amount of memory, in bytes, which may be mapped in total
+/// while reading pack files.
+pub fn get_mwindow_mapped_limit() -> Result<usize, Error> {
+    ::init();
+    let mut limit = 0 as size_t;
+    unsafe {
+        try!(call::try(raw::git_libgit2_opts(
+            raw::GIT_OPT_GET_MWINDOW_MAPPED_LIMIT as c_int, &mut limit)));
+    }
+    Ok(limit as usize)
+}
+
+/// Set the maximum amount of memory, in bytes, which may be mapped in total
+/// while reading pack files.
+///
+/// Once the limit is reached libgit2 will unmap windows which are not in use
+/// before mapping new ones.
+pub unsafe fn set_mwindow_mapped_limit(limit: usize) -> Result<(), Error> {
+    ::init();
+    try!(call::try(raw::git_libgit2_opts(
+        raw::GIT_OPT_SET_MWINDOW_MAPPED_LIMIT as c_int, limit as size_t)));
+    Ok(())
+}
+
+/// Get the paths which are searched for configuration files of the given
+/// level.
+///
+/// Multiple paths are separated by `:` (`;` on Windows). Only the `System`,
+/// `XDG` and `Global` levels have search paths.
+pub fn get_search_path(level: ConfigLevel) -> Result<Buf, Error> {
+    ::init();
+    let buf = Buf::new();
+    let level: raw::git_config_level_t = call::convert(&level);
+    unsafe {
+        try!(call::try(raw::git_libgit2_opts(
+            raw::GIT_OPT_GET_SEARCH_PATH as c_int, level as c_int,
+            buf.raw())));
+    }
+    Ok(buf)
+}
+
+/// Set the paths which are searched for configuration files of the given
+/// level.
+///
+/// Multiple paths are separated by `:` (`;` on Windows), and the string
+/// `$PATH` is expanded to the previous value of the search path, allowing
+/// paths to be prepended or appended.
+pub unsafe fn set_search_path<T>(level: ConfigLevel, paths: T)
+                                 -> Result<(), Error>
+    where T: IntoCString
+{
+    ::init();
+    let paths = paths.into_c_string();
+    let level: raw::git_config_level_t = call::convert(&level);
+    try!(call::try(raw::git_libgit2_opts(
+        raw::GIT_OPT_SET_SEARCH_PATH as c_int, level as c_int,
+        paths.as_ptr())));
+    Ok(())
+}
+
+/// Reset the search path for configuration files of the given level to the
+/// default, which is determined from the environment.
+pub unsafe fn reset_search_path(level: ConfigLevel) -> Result<(), Error> {
+    ::init();
+    let level: raw::git_config_level_t = call::convert(&level);
+    try!(call::try(raw::git_libgit2_opts(
+        raw::GIT_OPT_SET_SEARCH_PATH as c_int, level as c_int,
+        ptr::null::<c_char>())));
+    Ok(())
+}
+
+/// Set the largest size, in bytes, of an object of the given type which will
+/// be kept in the object cache.
+///
+/// A limit of 0 prevents objects of that type from being cached at all. By
+/// default only commits, trees and tags are cached.
+pub unsafe fn set_cache_object_limit(kind: ObjectType, limit: usize)
+                                     -> Result<(), Error> {
+    ::init();
+    try!(call::try(raw::git_libgit2_opts(
+        raw::GIT_OPT_SET_CACHE_OBJECT_LIMIT as c_int, kind.raw() as c_int,
+        limit as size_t)));
+    Ok(())
+}
+
+/// Set the maximum amount of memory, in bytes, which the object cache may
+/// use in total.
+pub unsafe fn set_cache_max_size(size: isize) -> Result<(), Error> {
+    ::init();
+    try!(call::try(raw::git_libgit2_opts(
+        raw::GIT_OPT_SET_CACHE_MAX_SIZE as c_int, size as ssize_t)));
+    Ok(())
+}
+
+/// Enable or disable caching of objects entirely.
+///
+/// Caching is enabled by default.
+pub unsafe fn enable_caching(enabled: bool) -> Result<(), Error> {
+    ::init();
+    try!(call::try(raw::git_libgit2_opts(
+        raw::GIT_OPT_ENABLE_CACHING as c_int, enabled as c_int)));
+    Ok(())
+}
+
+/// Get the amount of memory, in bytes, currently used by the object cache
+/// along with the maximum amount it may use.
+pub fn get_cached_memory() -> Result<(isize, isize), Error> {
+    ::init();
+    let mut current = 0 as ssize_t;
+    let mut allowed = 0 as ssize_t;
+    unsafe {
+        try!(call::try(raw::git_libgit2_opts(
+            raw::GIT_OPT_GET_CACHED_MEMORY as c_int, &mut current,
+            &mut allowed)));
+    }
+    Ok((current as isize, allowed as isize))
+}
+
+/// Set the locations from which the certificates used to verify SSL
+/// connections are loaded.
+///
+/// `file` names a file of concatenated PEM certificates, and `dir` a
+/// directory of certificates named by their hashes; either may be omitted.
+/// This has no effect if libgit2 was not built against OpenSSL.
+pub unsafe fn set_ssl_cert_locations<F, D>(file: Option<F>, dir: Option<D>)
+                                           -> Result<(), Error>
+    where F: IntoCString, D: IntoCString
+{
+    ::init();
+    let file = file.map(|f| f.into_c_string());
+    let dir = dir.map(|d| d.into_c_string());
+    try!(call::try(raw::git_libgit2_opts(
+        raw::GIT_OPT_SET_SSL_CERT_LOCATIONS as c_int,
+        file.as_ref().map(|s| s.as_ptr()).unwrap_or(ptr::null()),
+        dir.as_ref().map(|s| s.as_ptr()).unwrap_or(ptr::null()))));
+    Ok(())
+}
+
+/// Set the value which is sent in the User-Agent header of HTTP requests.
+///
+/// The given string is appended to libgit2's own identifier.
+pub unsafe fn set_user_agent(agent: &str) -> Result<(), Error> {
+    ::init();
+    let agent = agent.into_c_string();
+    try!(call::try(raw::git_libgit2_opts(
+        raw::GIT_OPT_SET_USER_AGENT as c_int, agent.as_ptr())));
+    Ok(())
+}
+
+/// Enable or disable validation of the objects referenced when creating new
+/// objects, such as checking that the tree and parents of a new commit
+/// exist.
+///
+/// Validation is enabled by default.
+pub unsafe fn enable_strict_object_creation(enabled: bool)
+                                            -> Result<(), Error> {
+    ::init();
+    try!(call::try(raw::git_libgit2_opts(
+        raw::GIT_OPT_ENABLE_STRICT_OBJECT_CREATION as c_int,
+        enabled as c_int)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::TempDir;
+    use std::sync::{StaticMutex, MUTEX_INIT};
+    use ConfigLevel;
+
+    static LOCK: StaticMutex = MUTEX_INIT;
+
+    #[test]
+    fn mwindow() {
+        let _g = LOCK.lock();
+        let size = super::get_mwindow_size().unwrap();
+        unsafe { super::set_mwindow_size(size * 2).unwrap(); }
+        assert_eq!(super::get_mwindow_size().unwrap(), size * 2);
+        unsafe { super::set_mwindow_size(size).unwrap(); }
+
+        let limit = super::get_mwindow_mapped_limit().unwrap();
+        unsafe { super::set_mwindow_mapped_limit(limit + 1).unwrap(); }
+        assert_eq!(super::get_mwindow_mapped_limit().unwrap(), limit + 1);
+        unsafe { super::set_mwindow_mapped_limit(limit).unwrap(); }
+    }
+
+    #[test]
+    fn search_path() {
+        let _g = LOCK.lock();
+        let td = TempDir::new("test").unwrap();
+        let orig = super::get_search_path(ConfigLevel::Global).unwrap();
+        let orig = orig.to_vec();
+        unsafe {
+            super::set_search_path(ConfigLevel::Global, td.path().clone())
+                  .unwrap();
+        }
+        let path = super::get_search_path(ConfigLevel::Global).unwrap();
+        assert_eq!(&*path, td.path().as_vec());
+        unsafe {
+            super::set_search_path(ConfigLevel::Global, String::from_utf8(orig)
+                                                            .unwrap())
+                  .unwrap();
+        }
+        assert!(super::get_search_path(ConfigLevel::Local).is_err());
+    }
+
+    #[test]
+    fn cache() {
+        let _g = LOCK.lock();
+        let (_, max) = super::get_cached_memory().unwrap();
+        unsafe { super::set_cache_max_size(max + 1).unwrap(); }
+        assert_eq!(super::get_cached_memory().unwrap().1, max + 1);
+        unsafe { super::set_cache_max_size(max).unwrap(); }
+    }
+}
@@ -0,0 +1,203 @@
+use std::ffi::CString;
+use std::marker;
+use std::slice;
+use libc::{c_int, c_uint, c_void, size_t};
+
+use {raw, panic, Error, Oid, Revwalk};
+use util::Binding;
+
+/// A builder for creating a pack file out of objects in a repository.
+///
+/// Objects are first inserted into the builder, which then computes deltas
+/// between them when the pack is written out.
+pub struct PackBuilder<'repo> {
+    raw: *mut raw::git_packbuilder,
+    marker: marker::ContravariantLifetime<'repo>,
+}
+
+impl<'repo> PackBuilder<'repo> {
+    /// Insert a single object into the pack.
+    ///
+    /// The `name` is the path the object was found at, if any, and is used
+    /// as a hint to find good delta bases.
+    pub fn insert_object(&mut self, id: Oid, name: Option<&str>)
+                         -> Result<(), Error> {
+        let name = name.map(|s| CString::from_slice(s.as_bytes()));
+        unsafe {
+            try_call!(raw::git_packbuilder_insert(self.raw, id.raw(), name));
+        }
+        Ok(())
+    }
+
+    /// Insert a tree into the pack, along with all of the trees and blobs
+    /// it references.
+    pub fn insert_tree(&mut self, id: Oid) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_packbuilder_insert_tree(self.raw, id.raw()));
+        }
+        Ok(())
+    }
+
+    /// Insert a commit into the pack, along with its tree and everything the
+    /// tree references.
+    pub fn insert_commit(&mut self, id: Oid) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_packbuilder_insert_commit(self.raw, id.raw()));
+        }
+        Ok(())
+    }
+
+    /// Insert all of the commits which the given revwalk yields into the
+    /// pack, along with their trees and everything the trees reference.
+    ///
+    /// The revwalk is consumed in the process.
+    pub fn insert_walk(&mut self, walk: &mut Revwalk) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_packbuilder_insert_walk(self.raw, walk.raw()));
+        }
+        Ok(())
+    }
+
+    /// Write the pack and its index to the given directory, which will
+    /// usually be `objects/pack` of a repository.
+    ///
+    /// The files are named after the pack's hash and created with the given
+    /// mode, or `0644` if `mode` is 0.
+    pub fn write(&mut self, path: &Path, mode: u32) -> Result<(), Error> {
+        let path = CString::from_slice(path.as_vec());
+        unsafe {
+            try_call!(raw::git_packbuilder_write(self.raw, path,
+                                                 mode as c_uint, None,
+                                                 0 as *mut _));
+        }
+        Ok(())
+    }
+
+    /// Generate the pack in memory, passing its contents to `cb` chunk by
+    /// chunk instead of writing it to disk.
+    ///
+    /// Returning `false` from the callback aborts generating the pack with an
+    /// error.
+    pub fn foreach<F>(&mut self, mut cb: F) -> Result<(), Error>
+                      where F: FnMut(&[u8]) -> bool {
+        unsafe {
+            try_call!(raw::git_packbuilder_foreach(self.raw, foreach_cb::<F>,
+                                                   &mut cb as *mut _
+                                                           as *mut c_void));
+            return Ok(())
+        }
+
+        extern fn foreach_cb<F>(buf: *mut c_void,
+                                size: size_t,
+                                data: *mut c_void) -> c_int
+                                where F: FnMut(&[u8]) -> bool
+        {
+            unsafe {
+                let data = data as *mut F;
+                let buf = buf as *const u8;
+                let buf = slice::from_raw_parts(buf, size as usize);
+                let ok = panic::wrap(move || (*data)(buf)).unwrap_or(false);
+                if ok {0} else {-1}
+            }
+        }
+    }
+
+    /// Get the hash which the pack is named after.
+    ///
+    /// Returns `None` if the pack has not been written yet.
+    pub fn hash(&self) -> Option<Oid> {
+        if self.written() == 0 { return None }
+        unsafe {
+            Binding::from_raw_opt(raw::git_packbuilder_hash(self.raw))
+        }
+    }
+
+    /// Get the total number of objects which have been inserted.
+    pub fn object_count(&self) -> usize {
+        unsafe { raw::git_packbuilder_object_count(self.raw) as usize }
+    }
+
+    /// Get the number of objects which have been written to the pack.
+    pub fn written(&self) -> usize {
+        unsafe { raw::git_packbuilder_written(self.raw) as usize }
+    }
+}
+
+impl<'repo> Binding for PackBuilder<'repo> {
+    type Raw = *mut raw::git_packbuilder;
+    unsafe fn from_raw(raw: *mut raw::git_packbuilder) -> PackBuilder<'repo> {
+        PackBuilder {
+            raw: raw,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+    fn raw(&self) -> *mut raw::git_packbuilder { self.raw }
+}
+
+#[unsafe_destructor]
+impl<'repo> Drop for PackBuilder<'repo> {
+    fn drop(&mut self) {
+        unsafe { raw::git_packbuilder_free(self.raw) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::fs::{self, PathExtensions};
+
+    #[test]
+    fn smoke() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let pack = repo.path().join("objects/pack");
+
+        let mut builder = repo.packbuilder().unwrap();
+        builder.insert_commit(head).unwrap();
+        assert_eq!(builder.object_count(), 2);
+        assert!(builder.hash().is_none());
+        builder.write(&pack, 0).unwrap();
+        assert_eq!(builder.written(), 2);
+        let hash = builder.hash().unwrap();
+        let name = format!("pack-{}.pack", hash);
+        assert!(pack.join(name).exists());
+
+        let packs = fs::readdir(&pack).unwrap();
+        assert_eq!(packs.len(), 2);
+    }
+
+    #[test]
+    fn foreach() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+
+        let mut builder = repo.packbuilder().unwrap();
+        builder.insert_commit(head).unwrap();
+        let mut data = Vec::new();
+        builder.foreach(|buf| { data.push_all(buf); true }).unwrap();
+        assert!(data.starts_with(b"PACK"));
+        assert_eq!(builder.written(), 2);
+
+        let mut builder = repo.packbuilder().unwrap();
+        builder.insert_commit(head).unwrap();
+        let mut calls = 0;
+        assert!(builder.foreach(|_| { calls += 1; false }).is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn pack_objects() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let loose = |id: ::Oid| {
+            let hex = id.to_string();
+            repo.path().join("objects").join(&hex[..2]).join(&hex[2..])
+        };
+        assert!(loose(head).exists());
+
+        repo.pack_objects(false).unwrap();
+        assert!(loose(head).exists());
+        repo.pack_objects(true).unwrap();
+        assert!(!loose(head).exists());
+        assert_eq!(repo.find_commit(head).unwrap().message(), Some("initial"));
+    }
+}
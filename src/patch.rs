@@ -0,0 +1,310 @@
+use std::marker;
+use libc::{c_char, c_int, c_void, size_t};
+
+use {raw, panic, Blob, Buf, Diff, DiffDelta, DiffHunk, DiffLine, DiffOptions};
+use {Error, IntoCString};
+use util::Binding;
+
+/// A structure representing the text changes in a single diff delta.
+///
+/// This is an opaque structure which is created through one of the
+/// constructors below (such as `Patch::from_diff`) and it provides access to
+/// the individual hunks and lines of the delta.
+pub struct Patch<'buffers> {
+    raw: *mut raw::git_patch,
+    marker: marker::ContravariantLifetime<'buffers>,
+}
+
+impl Patch<'static> {
+    /// Return a Patch for one file in a Diff.
+    ///
+    /// Returns `Ok(None)` for an unchanged or binary file.
+    pub fn from_diff(diff: &Diff, idx: usize)
+                     -> Result<Option<Patch<'static>>, Error> {
+        let mut ret = 0 as *mut raw::git_patch;
+        unsafe {
+            try_call!(raw::git_patch_from_diff(&mut ret, diff.raw(),
+                                               idx as size_t));
+            Ok(Binding::from_raw_opt(ret))
+        }
+    }
+}
+
+impl<'buffers> Patch<'buffers> {
+    /// Generate a Patch by diffing two blobs.
+    ///
+    /// The paths given are only used for the file names recorded in the
+    /// patch, they need not exist.
+    pub fn from_blobs(old_blob: &'buffers Blob,
+                      old_path: Option<&Path>,
+                      new_blob: &'buffers Blob,
+                      new_path: Option<&Path>,
+                      opts: Option<&mut DiffOptions>)
+                      -> Result<Patch<'buffers>, Error> {
+        let mut ret = 0 as *mut raw::git_patch;
+        let old_path = old_path.map(|p| p.into_c_string());
+        let new_path = new_path.map(|p| p.into_c_string());
+        unsafe {
+            try_call!(raw::git_patch_from_blobs(&mut ret,
+                                                old_blob.raw(),
+                                                old_path,
+                                                new_blob.raw(),
+                                                new_path,
+                                                opts.map(|s| s.raw())));
+            Ok(Binding::from_raw(ret))
+        }
+    }
+
+    /// Generate a Patch by diffing a blob and a buffer.
+    pub fn from_blob_and_buffer(old_blob: &'buffers Blob,
+                                old_path: Option<&Path>,
+                                new_buffer: &'buffers [u8],
+                                new_path: Option<&Path>,
+                                opts: Option<&mut DiffOptions>)
+                                -> Result<Patch<'buffers>, Error> {
+        let mut ret = 0 as *mut raw::git_patch;
+        let old_path = old_path.map(|p| p.into_c_string());
+        let new_path = new_path.map(|p| p.into_c_string());
+        unsafe {
+            try_call!(raw::git_patch_from_blob_and_buffer(
+                &mut ret,
+                old_blob.raw(),
+                old_path,
+                new_buffer.as_ptr() as *const c_char,
+                new_buffer.len() as size_t,
+                new_path,
+                opts.map(|s| s.raw())));
+            Ok(Binding::from_raw(ret))
+        }
+    }
+
+    /// Generate a Patch by diffing two buffers.
+    pub fn from_buffers(old_buffer: &'buffers [u8],
+                        old_path: Option<&Path>,
+                        new_buffer: &'buffers [u8],
+                        new_path: Option<&Path>,
+                        opts: Option<&mut DiffOptions>)
+                        -> Result<Patch<'buffers>, Error> {
+        let mut ret = 0 as *mut raw::git_patch;
+        let old_path = old_path.map(|p| p.into_c_string());
+        let new_path = new_path.map(|p| p.into_c_string());
+        unsafe {
+            try_call!(raw::git_patch_from_buffers(
+                &mut ret,
+                old_buffer.as_ptr() as *const c_void,
+                old_buffer.len() as size_t,
+                old_path,
+                new_buffer.as_ptr() as *const c_char,
+                new_buffer.len() as size_t,
+                new_path,
+                opts.map(|s| s.raw())));
+            Ok(Binding::from_raw(ret))
+        }
+    }
+
+    /// Get the DiffDelta associated with the Patch.
+    pub fn delta(&self) -> DiffDelta {
+        unsafe {
+            Binding::from_raw(raw::git_patch_get_delta(self.raw) as *mut _)
+        }
+    }
+
+    /// Get the number of hunks in the Patch.
+    pub fn num_hunks(&self) -> usize {
+        unsafe { raw::git_patch_num_hunks(self.raw) as usize }
+    }
+
+    /// Get the number of lines of context, additions, and deletions in the
+    /// Patch, in that order.
+    pub fn line_stats(&self) -> Result<(usize, usize, usize), Error> {
+        let mut context = 0;
+        let mut additions = 0;
+        let mut deletions = 0;
+        unsafe {
+            try_call!(raw::git_patch_line_stats(&mut context,
+                                                &mut additions,
+                                                &mut deletions,
+                                                self.raw));
+        }
+        Ok((context as usize, additions as usize, deletions as usize))
+    }
+
+    /// Get a DiffHunk and its total line count from the Patch.
+    pub fn hunk(&self, hunk_idx: usize) -> Result<(DiffHunk, usize), Error> {
+        let mut ret = 0 as *const raw::git_diff_hunk;
+        let mut lines = 0;
+        unsafe {
+            try_call!(raw::git_patch_get_hunk(&mut ret, &mut lines, self.raw,
+                                              hunk_idx as size_t));
+            Ok((Binding::from_raw(ret), lines as usize))
+        }
+    }
+
+    /// Get the number of lines in a hunk.
+    pub fn num_lines_in_hunk(&self, hunk_idx: usize) -> Result<usize, Error> {
+        unsafe {
+            Ok(try_call!(raw::git_patch_num_lines_in_hunk(self.raw,
+                                                          hunk_idx as size_t))
+               as usize)
+        }
+    }
+
+    /// Get a DiffLine from a hunk of the Patch.
+    pub fn line_in_hunk(&self, hunk_idx: usize, line_of_hunk: usize)
+                        -> Result<DiffLine, Error> {
+        let mut ret = 0 as *const raw::git_diff_line;
+        unsafe {
+            try_call!(raw::git_patch_get_line_in_hunk(&mut ret,
+                                                      self.raw,
+                                                      hunk_idx as size_t,
+                                                      line_of_hunk as size_t));
+            Ok(Binding::from_raw(ret))
+        }
+    }
+
+    /// Get the size of a Patch's diff data in bytes.
+    ///
+    /// The flags control whether context lines, hunk headers and file headers
+    /// are counted towards the total.
+    pub fn size(&self,
+                include_context: bool,
+                include_hunk_headers: bool,
+                include_file_headers: bool) -> usize {
+        unsafe {
+            raw::git_patch_size(self.raw,
+                                include_context as c_int,
+                                include_hunk_headers as c_int,
+                                include_file_headers as c_int) as usize
+        }
+    }
+
+    /// Serialize the Patch, invoking the callback for each line of output.
+    ///
+    /// Returning `false` from the callback will terminate the iteration and
+    /// return an error from this function.
+    pub fn print<F>(&mut self, mut cb: F) -> Result<(), Error>
+                    where F: FnMut(DiffDelta,
+                                   Option<DiffHunk>,
+                                   DiffLine) -> bool {
+        unsafe {
+            try_call!(raw::git_patch_print(self.raw, print::<F>,
+                                           &mut cb as *mut _ as *mut _));
+            return Ok(())
+        }
+        extern fn print<F>(delta: *const raw::git_diff_delta,
+                           hunk: *const raw::git_diff_hunk,
+                           line: *const raw::git_diff_line,
+                           data: *mut c_void) -> c_int
+                           where F: FnMut(DiffDelta, Option<DiffHunk>,
+                                          DiffLine) -> bool
+        {
+            unsafe {
+                let delta = Binding::from_raw(delta as *mut _);
+                let hunk = Binding::from_raw_opt(hunk);
+                let line = Binding::from_raw(line);
+                let data = data as *mut F;
+                let ok = panic::wrap(move || {
+                    (*data)(delta, hunk, line)
+                }).unwrap_or(false);
+                if ok {0} else {-1}
+            }
+        }
+    }
+
+    /// Get the content of a Patch as a single diff text.
+    pub fn to_buf(&mut self) -> Result<Buf, Error> {
+        let buf = Buf::new();
+        unsafe {
+            try_call!(raw::git_patch_to_buf(buf.raw(), self.raw));
+        }
+        Ok(buf)
+    }
+}
+
+impl<'buffers> Binding for Patch<'buffers> {
+    type Raw = *mut raw::git_patch;
+
+    unsafe fn from_raw(raw: *mut raw::git_patch) -> Patch<'buffers> {
+        Patch {
+            raw: raw,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+    fn raw(&self) -> *mut raw::git_patch { self.raw }
+}
+
+#[unsafe_destructor]
+impl<'buffers> Drop for Patch<'buffers> {
+    fn drop(&mut self) {
+        unsafe { raw::git_patch_free(self.raw) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::File;
+    use Patch;
+
+    #[test]
+    fn buffers() {
+        let old = b"a\nb\nc\n";
+        let new = b"a\nB\nc\nd\n";
+        let mut patch = Patch::from_buffers(old, Some(&Path::new("foo")),
+                                            new, Some(&Path::new("foo")),
+                                            None).unwrap();
+        assert_eq!(patch.delta().new_file().path(), Some(Path::new("foo")));
+        assert_eq!(patch.num_hunks(), 1);
+        assert_eq!(patch.line_stats().unwrap(), (2, 2, 1));
+
+        let (hunk, lines) = patch.hunk(0).unwrap();
+        assert_eq!(hunk.old_start(), 1);
+        assert_eq!(hunk.old_lines(), 3);
+        assert_eq!(hunk.new_lines(), 4);
+        assert_eq!(lines, 5);
+        assert_eq!(patch.num_lines_in_hunk(0).unwrap(), 5);
+        assert!(patch.hunk(1).is_err());
+
+        let line = patch.line_in_hunk(0, 1).unwrap();
+        assert_eq!(line.origin(), '-');
+        assert_eq!(line.old_lineno(), Some(2));
+        assert_eq!(line.new_lineno(), None);
+        assert_eq!(line.content(), b"b\n");
+        let line = patch.line_in_hunk(0, 4).unwrap();
+        assert_eq!(line.origin(), '+');
+        assert_eq!(line.new_lineno(), Some(4));
+        assert!(patch.line_in_hunk(0, 5).is_err());
+
+        assert!(patch.size(true, true, true) > patch.size(false, false, false));
+        let buf = patch.to_buf().unwrap();
+        let text = buf.as_str().unwrap();
+        assert!(text.contains("--- a/foo\n+++ b/foo\n"));
+        assert!(text.contains("@@ -1,3 +1,4 @@\n"));
+
+        let mut n = 0;
+        patch.print(|_, _, _| { n += 1; true }).unwrap();
+        assert!(n > 5);
+    }
+
+    #[test]
+    fn blobs_and_diff() {
+        let (td, repo) = ::test::repo_init();
+        let old = repo.blob(b"foo\n").unwrap();
+        let old = repo.find_blob(old).unwrap();
+        let new = repo.blob(b"bar\n").unwrap();
+        let new = repo.find_blob(new).unwrap();
+        let patch = Patch::from_blobs(&old, None, &new, None, None).unwrap();
+        assert_eq!(patch.line_stats().unwrap(), (0, 1, 1));
+        let patch = Patch::from_blob_and_buffer(&old, None, b"foo\nbar\n",
+                                                None, None).unwrap();
+        assert_eq!(patch.line_stats().unwrap(), (1, 1, 0));
+
+        File::create(&td.path().join("foo")).write_str("foo\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("foo")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let diff = repo.diff_tree_to_tree(None, Some(&tree), None).unwrap();
+        let patch = Patch::from_diff(&diff, 0).unwrap().unwrap();
+        assert_eq!(patch.delta().new_file().path(), Some(Path::new("foo")));
+        assert_eq!(patch.line_stats().unwrap(), (0, 1, 0));
+    }
+}
@@ -1,9 +1,10 @@
-use std::ffi::{self, CString};
+use std::ffi::CString;
 use std::marker;
-use std::str;
+use std::mem;
 use libc;
 
-use {raw, Error, Signature};
+use {raw, Error, IntoCString, RemoteCallbacks};
+use remote_callbacks;
 use util::Binding;
 
 /// A structure to represent a pending push operation to a remote.
@@ -11,7 +12,11 @@ use util::Binding;
 /// Remotes can create a `Push` which is then used to push data to the upstream
 /// repository.
 pub struct Push<'remote> {
-    raw: *mut raw::git_push,
+    remote: *mut raw::git_remote,
+    callbacks: Option<*mut RemoteCallbacks<'remote>>,
+    refspecs: Vec<CString>,
+    options: PushOptions,
+    statuses: Vec<PushStatus>,
     marker: marker::ContravariantLifetime<'remote>,
 }
 
@@ -24,14 +29,32 @@ pub struct PushStatus {
     pub message: Option<String>,
 }
 
+/// Options which can be specified to configure a push operation.
+pub struct PushOptions {
+    raw: raw::git_push_options,
+    push_options: Vec<CString>,
+    push_option_ptrs: Vec<*const libc::c_char>,
+}
+
 impl<'remote> Push<'remote> {
+    /// Set the options to use for this push.
+    ///
+    /// The options are copied, later changes to `opts` do not affect this
+    /// push.
+    pub fn set_options(&mut self, opts: &mut PushOptions) -> Result<(), Error> {
+        let mut options = PushOptions::new();
+        options.raw.pb_parallelism = opts.raw.pb_parallelism;
+        for option in opts.push_options.iter() {
+            options.push_option(option.clone());
+        }
+        self.options = options;
+        Ok(())
+    }
+
     /// Add a refspec to be pushed
     pub fn add_refspec(&mut self, refspec: &str) -> Result<(), Error> {
-        let refspec = CString::from_slice(refspec.as_bytes());
-        unsafe {
-            try_call!(raw::git_push_add_refspec(self.raw, refspec));
-            Ok(())
-        }
+        self.refspecs.push(CString::from_slice(refspec.as_bytes()));
+        Ok(())
     }
 
     /// Actually push all given refspecs
@@ -41,82 +64,152 @@ impl<'remote> Push<'remote> {
     /// `statuses`. The remote repository might have refused to
     /// update some or all of the references.
     pub fn finish(&mut self) -> Result<(), Error> {
+        let ptrs = self.refspecs.iter().map(|s| s.as_ptr())
+                       .collect::<Vec<_>>();
+        let refspecs = raw::git_strarray {
+            strings: ptrs.as_ptr() as *mut _,
+            count: ptrs.len() as libc::size_t,
+        };
+        let mut default = RemoteCallbacks::new();
         unsafe {
-            try_call!(raw::git_push_finish(self.raw));
-            Ok(())
+            let callbacks = match self.callbacks {
+                Some(cbs) => &mut *cbs,
+                None => &mut default,
+            };
+            self.options.raw.callbacks = callbacks.raw();
+            let rc = raw::git_remote_upload(self.remote, &refspecs,
+                                            self.options.raw());
+            self.statuses = remote_callbacks::take_push_statuses(callbacks);
+            match ::call::try(rc) {
+                Ok(..) => {}
+                Err(e) => { ::panic::check(); return Err(e) }
+            }
         }
+        Ok(())
     }
 
     /// Update remote tips after a push
-    pub fn update_tips(&mut self, signature: Option<&Signature>,
-                       reflog_message: Option<&str>) -> Result<(), Error> {
+    pub fn update_tips(&mut self, reflog_message: Option<&str>)
+                       -> Result<(), Error> {
         let msg = reflog_message.map(|s| CString::from_slice(s.as_bytes()));
+        let default = RemoteCallbacks::new();
         unsafe {
-            try_call!(raw::git_push_update_tips(self.raw,
-                                                signature.map(|s| s.raw()),
-                                                msg));
+            let callbacks = match self.callbacks {
+                Some(cbs) => (*cbs).raw(),
+                None => default.raw(),
+            };
+            try_call!(raw::git_remote_update_tips(
+                self.remote,
+                &callbacks,
+                raw::GIT_REMOTE_UPDATE_FETCHHEAD,
+                raw::GIT_REMOTE_DOWNLOAD_TAGS_UNSPECIFIED,
+                msg));
             Ok(())
         }
     }
 
-    /// Return each status entry
+    /// Return the status the remote reported for each reference of the last
+    /// call to `finish`.
     pub fn statuses(&mut self) -> Result<Vec<PushStatus>, Error> {
-        let mut ret: Vec<PushStatus> = Vec::new();
-        unsafe {
-            try_call!(raw::git_push_status_foreach(self.raw, cb,
-                                                   &mut ret as *mut _
-                                                            as *mut libc::c_void));
-        }
-        return Ok(ret);
-
-        extern fn cb(git_ref: *const libc::c_char,
-                     msg: *const libc::c_char,
-                     data: *mut libc::c_void) -> libc::c_int {
-            unsafe {
-                let git_ref = match str::from_utf8(ffi::c_str_to_bytes(&git_ref)) {
-                    Ok(s) => s.to_string(),
-                    Err(_) => return 0,
-                };
-                let msg = if !msg.is_null() {
-                    match str::from_utf8(ffi::c_str_to_bytes(&msg)) {
-                        Ok(s) => Some(s.to_string()),
-                        Err(_) => return 0,
-                    }
-                } else {
-                    None
-                };
-
-                let data = &mut *(data as *mut Vec<PushStatus>);
-                data.push(PushStatus { reference: git_ref, message: msg });
-                return 0;
-            }
-        }
+        Ok(mem::replace(&mut self.statuses, Vec::new()))
     }
 }
 
-impl<'remote> Binding for Push<'remote> {
-    type Raw = *mut raw::git_push;
-    unsafe fn from_raw(raw: *mut raw::git_push) -> Push<'remote> {
-        Push {
-            raw: raw,
-            marker: marker::ContravariantLifetime,
-        }
+/// Create a push to the given remote, invoking the given callbacks while it is
+/// in progress.
+pub fn new<'remote>(remote: *mut raw::git_remote,
+                    callbacks: Option<*mut RemoteCallbacks<'remote>>)
+                    -> Push<'remote> {
+    Push {
+        remote: remote,
+        callbacks: callbacks,
+        refspecs: Vec::new(),
+        options: PushOptions::new(),
+        statuses: Vec::new(),
+        marker: marker::ContravariantLifetime,
     }
-    fn raw(&self) -> *mut raw::git_push { self.raw }
 }
 
-#[unsafe_destructor]
-impl<'a> Drop for Push<'a> {
-    fn drop(&mut self) {
-        unsafe { raw::git_push_free(self.raw) }
+impl PushOptions {
+    /// Creates a new default set of push options.
+    pub fn new() -> PushOptions {
+        let mut opts = PushOptions {
+            raw: unsafe { mem::zeroed() },
+            push_options: Vec::new(),
+            push_option_ptrs: Vec::new(),
+        };
+        assert_eq!(unsafe {
+            raw::git_push_init_options(&mut opts.raw,
+                                       raw::GIT_PUSH_OPTIONS_VERSION)
+        }, 0);
+        opts
+    }
+
+    /// Set the number of worker threads used to build the pack which is
+    /// sent to the remote.
+    ///
+    /// If set to 0 the number of threads is autodetected. Defaults to 1.
+    pub fn packbuilder_parallelism(&mut self, threads: u32)
+                                   -> &mut PushOptions {
+        self.raw.pb_parallelism = threads as libc::c_uint;
+        self
+    }
+
+    /// Add a push option to send to the remote, as with `git push -o`.
+    ///
+    /// Push options are made available to the hooks of the receiving
+    /// repository, such as `pre-receive`, and are commonly used to pass
+    /// flags like `ci.skip` to the server. The remote must advertise support
+    /// for them, otherwise the push fails.
+    pub fn push_option<T: IntoCString>(&mut self, option: T)
+                                       -> &mut PushOptions {
+        let s = option.into_c_string();
+        self.push_option_ptrs.push(s.as_ptr());
+        self.push_options.push(s);
+        self
+    }
+
+    /// Add each of the given push options, see `push_option`.
+    pub fn push_options<T: IntoCString + Clone>(&mut self, options: &[T])
+                                                -> &mut PushOptions {
+        for option in options.iter() {
+            self.push_option(option.clone());
+        }
+        self
+    }
+
+    /// Acquire a pointer to the underlying raw options.
+    ///
+    /// This function is unsafe as the pointer is only valid so long as this
+    /// structure is not moved, modified, or used elsewhere.
+    pub unsafe fn raw(&mut self) -> *const raw::git_push_options {
+        self.raw.remote_push_options.count =
+            self.push_option_ptrs.len() as libc::size_t;
+        self.raw.remote_push_options.strings =
+            self.push_option_ptrs.as_ptr() as *mut _;
+        &self.raw as *const _
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
     use std::old_io::TempDir;
     use url::Url;
-    use Repository;
+    use {Repository, PushOptions, RemoteCallbacks, PackBuilderStage};
+
+    #[test]
+    fn options() {
+        let mut opts = PushOptions::new();
+        opts.packbuilder_parallelism(0).push_option("ci.skip")
+            .push_options(&["a=b", "c"]);
+        assert_eq!(opts.push_options.len(), 3);
+        unsafe {
+            let raw = &*opts.raw();
+            assert_eq!(raw.pb_parallelism, 0);
+            assert_eq!(raw.remote_push_options.count, 3);
+        }
+    }
 
     #[test]
     fn smoke() {
@@ -132,10 +225,72 @@ mod tests {
         let mut push = remote.push().unwrap();
         push.add_refspec("refs/heads/master").unwrap();
         push.finish().unwrap();
-        push.update_tips(None, None).unwrap();
+        push.update_tips(None).unwrap();
         let v = push.statuses().unwrap();
         assert!(v.len() > 0);
         assert_eq!(v[0].reference.as_slice(), "refs/heads/master");
         assert!(v[0].message.is_none());
     }
+
+    #[test]
+    fn pack_progress() {
+        let td = TempDir::new("test").unwrap();
+        let remote = td.path().join("remote");
+        Repository::init_bare(&remote).unwrap();
+
+        let (_td, repo) = ::test::repo_init();
+        let url = Url::from_file_path(&remote).ok().unwrap();
+        let url = url.to_string();
+        let calls = Cell::new(0);
+        let deltafication = Cell::new(false);
+        {
+            let mut cbs = RemoteCallbacks::new();
+            cbs.pack_progress(|stage, current, total| {
+                calls.set(calls.get() + 1);
+                if stage == PackBuilderStage::Deltafication {
+                    deltafication.set(true);
+                    assert!(current <= total);
+                }
+            });
+            let mut remote = repo.remote("origin", url.as_slice()).unwrap();
+            remote.set_callbacks(&mut cbs);
+
+            let mut push = remote.push().unwrap();
+            push.add_refspec("refs/heads/master").unwrap();
+            push.finish().unwrap();
+        }
+        assert!(calls.get() > 0);
+        assert!(deltafication.get());
+    }
+
+    #[test]
+    fn options_and_statuses() {
+        let td = TempDir::new("test").unwrap();
+        let path = td.path().join("remote");
+        let bare = Repository::init_bare(&path).unwrap();
+
+        let (_td, repo) = ::test::repo_init();
+        let url = Url::from_file_path(&path).ok().unwrap();
+        let url = url.to_string();
+        let mut remote = repo.remote("origin", url.as_slice()).unwrap();
+
+        // the statuses are reported even if the remote has no callbacks
+        let mut opts = PushOptions::new();
+        opts.packbuilder_parallelism(0);
+        let mut push = remote.push().unwrap();
+        push.set_options(&mut opts).unwrap();
+        push.add_refspec("refs/heads/master:refs/heads/other").unwrap();
+        push.finish().unwrap();
+        let v = push.statuses().unwrap();
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].reference.as_slice(), "refs/heads/other");
+        assert!(v[0].message.is_none());
+        assert!(push.statuses().unwrap().is_empty());
+
+        let head = repo.refname_to_id("HEAD").unwrap();
+        assert_eq!(bare.refname_to_id("refs/heads/other").unwrap(), head);
+        push.update_tips(Some("push")).unwrap();
+        assert_eq!(repo.refname_to_id("refs/remotes/origin/other").unwrap(),
+                   head);
+    }
 }
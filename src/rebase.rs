@@ -0,0 +1,531 @@
+use std::ffi::CString;
+use std::marker;
+use std::mem;
+use std::ptr;
+use std::str;
+use libc::{self, c_char, c_int, c_void, size_t};
+
+use {raw, panic, Error, Index, MergeOptions, Oid, RebaseOperationType};
+use Signature;
+use build::CheckoutBuilder;
+use util::Binding;
+
+/// Options to specify when rebasing with `Repository::rebase` or
+/// `Repository::open_rebase`.
+pub struct RebaseOptions<'cb> {
+    raw: raw::git_rebase_options,
+    rewrite_notes_ref: Option<CString>,
+    merge_options: Option<MergeOptions>,
+    checkout_options: Option<CheckoutBuilder<'cb>>,
+    signing: Option<Box<CommitSigningCallback<'cb>>>,
+}
+
+/// Callback used to sign the commits created during a rebase.
+///
+/// The callback is given the content of the commit to be created and returns
+/// the signature to add in its "gpgsig" header, or `None` to create the
+/// commit unsigned.
+pub type CommitSigningCallback<'a> =
+    FnMut(&str) -> Result<Option<String>, Error> + 'a;
+
+/// Representation of a rebase in progress.
+///
+/// Iterating over a `Rebase` applies each patch in turn, yielding the
+/// operation which was just performed. Once a patch has been applied it
+/// should be committed with `commit` before moving on to the next one.
+pub struct Rebase<'repo> {
+    raw: *mut raw::git_rebase,
+    marker: marker::ContravariantLifetime<'repo>,
+}
+
+/// A single rebase operation, to be (or which was) applied during the rebase.
+pub struct RebaseOperation<'rebase> {
+    raw: *const raw::git_rebase_operation,
+    marker: marker::ContravariantLifetime<'rebase>,
+}
+
+impl<'cb> RebaseOptions<'cb> {
+    /// Creates a new default set of rebase options.
+    pub fn new() -> RebaseOptions<'cb> {
+        let mut opts = RebaseOptions {
+            raw: unsafe { mem::zeroed() },
+            rewrite_notes_ref: None,
+            merge_options: None,
+            checkout_options: None,
+            signing: None,
+        };
+        assert_eq!(unsafe {
+            raw::git_rebase_init_options(&mut opts.raw,
+                                         raw::GIT_REBASE_OPTIONS_VERSION)
+        }, 0);
+        opts
+    }
+
+    /// Used by `Repository::rebase`, this will instruct other clients working
+    /// on this rebase that you want a quiet rebase experience, which they may
+    /// choose to provide in an application-specific manner. This has no
+    /// effect upon libgit2 directly, but is provided for interoperability
+    /// between Git tools.
+    pub fn quiet(&mut self, quiet: bool) -> &mut RebaseOptions<'cb> {
+        self.raw.quiet = quiet as libc::c_int;
+        self
+    }
+
+    /// Used by `Repository::rebase`, this will begin an in-memory rebase,
+    /// which will allow callers to step through the rebase operations and
+    /// commit the rebased changes, but will not rewind HEAD or update the
+    /// repository to be in a rebasing state. This will not interfere with
+    /// the working directory (if there is one).
+    pub fn inmemory(&mut self, inmemory: bool) -> &mut RebaseOptions<'cb> {
+        self.raw.inmemory = inmemory as libc::c_int;
+        self
+    }
+
+    /// Used by `Rebase::finish`, this is the name of the notes reference used
+    /// to rewrite notes for rebased commits when finishing the rebase; if not
+    /// provided, the contents of the configuration option
+    /// `notes.rewriteRef` are examined, unless the configuration option
+    /// `notes.rewrite.rebase` is set to false.
+    pub fn rewrite_notes_ref(&mut self, rewrite_notes_ref: &str)
+                             -> &mut RebaseOptions<'cb> {
+        self.rewrite_notes_ref =
+            Some(CString::from_slice(rewrite_notes_ref.as_bytes()));
+        self
+    }
+
+    /// Options to control how trees are merged during `next`.
+    pub fn merge_options(&mut self, opts: MergeOptions)
+                         -> &mut RebaseOptions<'cb> {
+        self.merge_options = Some(opts);
+        self
+    }
+
+    /// Options to control how files are written during `Repository::rebase`,
+    /// `next` and `abort`. Note that a minimum strategy of `safe` is
+    /// defaulted in `init` and `next`, and a minimum strategy of `force` is
+    /// defaulted in `abort` to match git semantics.
+    pub fn checkout_options(&mut self, opts: CheckoutBuilder<'cb>)
+                            -> &mut RebaseOptions<'cb> {
+        self.checkout_options = Some(opts);
+        self
+    }
+
+    /// Set a callback to sign each commit created by `Rebase::commit`, for
+    /// example with GPG or SSH.
+    pub fn commit_signing_callback<F>(&mut self, cb: F)
+                                      -> &mut RebaseOptions<'cb>
+        where F: FnMut(&str) -> Result<Option<String>, Error> + 'cb
+    {
+        self.signing = Some(Box::new(cb) as Box<CommitSigningCallback<'cb>>);
+        self
+    }
+
+    /// Acquire a pointer to the underlying raw options.
+    ///
+    /// This function is unsafe as the pointer is only valid so long as this
+    /// structure is not moved, modified, or used elsewhere.
+    pub unsafe fn raw(&mut self) -> *const raw::git_rebase_options {
+        self.raw.rewrite_notes_ref = self.rewrite_notes_ref.as_ref()
+                                         .map(|s| s.as_ptr())
+                                         .unwrap_or(0 as *const _);
+        match self.merge_options {
+            Some(ref opts) => {
+                self.raw.merge_options = ptr::read(opts.raw());
+            }
+            None => {}
+        }
+        match self.checkout_options {
+            Some(ref mut opts) => {
+                opts.configure(&mut self.raw.checkout_options);
+            }
+            None => {}
+        }
+        if self.signing.is_some() {
+            let f: raw::git_commit_signing_cb = signing_cb;
+            self.raw.signing_cb = Some(f);
+            self.raw.payload = self as *mut _ as *mut _;
+        }
+        &self.raw as *const _
+    }
+}
+
+extern fn signing_cb(signature: *mut raw::git_buf,
+                     _signature_field: *mut raw::git_buf,
+                     commit_content: *const c_char,
+                     data: *mut c_void) -> c_int {
+    unsafe {
+        let payload = &mut *(data as *mut RebaseOptions);
+        let callback = match payload.signing {
+            Some(ref mut c) => c,
+            None => return raw::GIT_PASSTHROUGH as c_int,
+        };
+        let content = match ::opt_bytes(&data, commit_content)
+                             .and_then(|s| str::from_utf8(s).ok()) {
+            Some(s) => s,
+            None => {
+                set_err(&Error::from_str("commit content is not valid utf-8"));
+                return -1
+            }
+        };
+        match panic::wrap(|| callback(content)) {
+            Some(Ok(Some(sig))) => {
+                raw::git_buf_set(signature, sig.as_ptr() as *const c_void,
+                                 sig.len() as size_t)
+            }
+            Some(Ok(None)) => raw::GIT_PASSTHROUGH as c_int,
+            Some(Err(e)) => {
+                set_err(&e);
+                match e.raw_code() as c_int {
+                    n if n < 0 => n,
+                    _ => -1,
+                }
+            }
+            None => -1,
+        }
+    }
+}
+
+unsafe fn set_err(e: &Error) {
+    let s = CString::from_slice(e.message().as_bytes());
+    raw::git_error_set_str(e.raw_class() as c_int, s.as_ptr());
+}
+
+impl<'repo> Rebase<'repo> {
+    /// Gets the count of rebase operations that are to be applied.
+    pub fn len(&self) -> usize {
+        unsafe { raw::git_rebase_operation_entrycount(self.raw) as usize }
+    }
+
+    /// Gets the index of the rebase operation that is currently being
+    /// applied, or `None` if no operations have been applied yet.
+    pub fn operation_current(&mut self) -> Option<usize> {
+        let cur = unsafe { raw::git_rebase_operation_current(self.raw) };
+        if cur == raw::GIT_REBASE_NO_OPERATION {
+            None
+        } else {
+            Some(cur as usize)
+        }
+    }
+
+    /// Gets the rebase operation specified by the given index, or `None` if
+    /// the index is out of bounds.
+    pub fn get(&mut self, idx: usize) -> Option<RebaseOperation> {
+        unsafe {
+            let op = raw::git_rebase_operation_byindex(self.raw,
+                                                       idx as libc::size_t);
+            Binding::from_raw_opt(op as *const _)
+        }
+    }
+
+    /// Gets the original `HEAD` ref name for merge rebases.
+    ///
+    /// Returns `None` if the original `HEAD` was detached or the name is not
+    /// valid utf-8.
+    pub fn orig_head_name(&self) -> Option<&str> {
+        unsafe {
+            ::opt_bytes(self, raw::git_rebase_orig_head_name(self.raw))
+                .and_then(|s| str::from_utf8(s).ok())
+        }
+    }
+
+    /// Gets the original HEAD id for merge rebases.
+    pub fn orig_head_id(&self) -> Option<Oid> {
+        unsafe {
+            Binding::from_raw_opt(raw::git_rebase_orig_head_id(self.raw))
+        }
+    }
+
+    /// Gets the onto ref name for merge rebases.
+    ///
+    /// Returns `None` if the name is not valid utf-8.
+    pub fn onto_name(&self) -> Option<&str> {
+        unsafe {
+            ::opt_bytes(self, raw::git_rebase_onto_name(self.raw))
+                .and_then(|s| str::from_utf8(s).ok())
+        }
+    }
+
+    /// Gets the onto id for merge rebases.
+    pub fn onto_id(&self) -> Oid {
+        unsafe { Binding::from_raw(raw::git_rebase_onto_id(self.raw)) }
+    }
+
+    /// Gets the index produced by the last operation, which is the result of
+    /// `next` and which will be committed by the next invocation of
+    /// `commit`. This is useful for resolving conflicts in an in-memory
+    /// rebase before committing them.
+    ///
+    /// This is only applicable for in-memory rebases; for rebases within a
+    /// working directory, the changes were applied to the repository's
+    /// index.
+    pub fn inmemory_index(&mut self) -> Result<Index, Error> {
+        let mut idx = 0 as *mut raw::git_index;
+        unsafe {
+            try_call!(raw::git_rebase_inmemory_index(&mut idx, self.raw));
+            Ok(Binding::from_raw(idx))
+        }
+    }
+
+    /// Commits the current patch. You must have resolved any conflicts that
+    /// were introduced during the patch application from the `next`
+    /// invocation.
+    ///
+    /// If `author` is `None` the author of the original commit is kept, and
+    /// if `message` is `None` the original commit message is kept.
+    pub fn commit(&mut self,
+                  author: Option<&Signature>,
+                  committer: &Signature,
+                  message: Option<&str>) -> Result<Oid, Error> {
+        let mut id = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        let message = message.map(|s| CString::from_slice(s.as_bytes()));
+        unsafe {
+            try_call!(raw::git_rebase_commit(&mut id,
+                                             self.raw,
+                                             author.map(|a| a.raw()),
+                                             committer.raw(),
+                                             0 as *const libc::c_char,
+                                             message));
+            Ok(Binding::from_raw(&id as *const _))
+        }
+    }
+
+    /// Aborts a rebase that is currently in progress, resetting the
+    /// repository and working directory to their state before rebase began.
+    pub fn abort(&mut self) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_rebase_abort(self.raw));
+        }
+        Ok(())
+    }
+
+    /// Finishes a rebase that is currently in progress once all patches have
+    /// been applied.
+    pub fn finish(&mut self, signature: Option<&Signature>)
+                  -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_rebase_finish(self.raw,
+                                             signature.map(|s| s.raw())));
+        }
+        Ok(())
+    }
+}
+
+impl<'repo> Iterator for Rebase<'repo> {
+    type Item = Result<RebaseOperation<'repo>, Error>;
+
+    /// Performs the next rebase operation and returns the information about
+    /// it. If the operation is one that applies a patch (which is any
+    /// operation except `RebaseOperationType::Exec`) then the patch will be
+    /// applied and the index and working directory will be updated with the
+    /// changes. If there are conflicts, you will need to address those
+    /// before committing the changes.
+    fn next(&mut self) -> Option<Result<RebaseOperation<'repo>, Error>> {
+        let mut out = 0 as *mut raw::git_rebase_operation;
+        unsafe {
+            let rc = raw::git_rebase_next(&mut out, self.raw);
+            if rc == raw::GIT_ITEROVER as libc::c_int {
+                return None
+            }
+            match ::call::try(rc) {
+                Ok(..) => Some(Ok(Binding::from_raw(out as *const _))),
+                Err(e) => Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl<'repo> Binding for Rebase<'repo> {
+    type Raw = *mut raw::git_rebase;
+    unsafe fn from_raw(raw: *mut raw::git_rebase) -> Rebase<'repo> {
+        Rebase {
+            raw: raw,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+    fn raw(&self) -> *mut raw::git_rebase { self.raw }
+}
+
+#[unsafe_destructor]
+impl<'repo> Drop for Rebase<'repo> {
+    fn drop(&mut self) {
+        unsafe { raw::git_rebase_free(self.raw) }
+    }
+}
+
+impl<'rebase> RebaseOperation<'rebase> {
+    /// The type of rebase operation.
+    pub fn kind(&self) -> RebaseOperationType {
+        match unsafe { (*self.raw).kind } {
+            raw::GIT_REBASE_OPERATION_PICK => RebaseOperationType::Pick,
+            raw::GIT_REBASE_OPERATION_REWORD => RebaseOperationType::Reword,
+            raw::GIT_REBASE_OPERATION_EDIT => RebaseOperationType::Edit,
+            raw::GIT_REBASE_OPERATION_SQUASH => RebaseOperationType::Squash,
+            raw::GIT_REBASE_OPERATION_FIXUP => RebaseOperationType::Fixup,
+            raw::GIT_REBASE_OPERATION_EXEC => RebaseOperationType::Exec,
+        }
+    }
+
+    /// The commit ID being cherry-picked. This will be populated for all
+    /// operations except those of type `RebaseOperationType::Exec`.
+    pub fn id(&self) -> Oid {
+        unsafe { Binding::from_raw(&(*self.raw).id as *const _) }
+    }
+
+    /// The executable the user has requested be run. This will only be
+    /// populated for operations of type `RebaseOperationType::Exec`.
+    ///
+    /// Returns `None` if there is no command or it is not valid utf-8.
+    pub fn exec(&self) -> Option<&str> {
+        unsafe {
+            ::opt_bytes(self, (*self.raw).exec)
+                .and_then(|s| str::from_utf8(s).ok())
+        }
+    }
+}
+
+impl<'rebase> Binding for RebaseOperation<'rebase> {
+    type Raw = *const raw::git_rebase_operation;
+    unsafe fn from_raw(raw: *const raw::git_rebase_operation)
+                       -> RebaseOperation<'rebase> {
+        RebaseOperation {
+            raw: raw,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+    fn raw(&self) -> *const raw::git_rebase_operation { self.raw }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Error, Oid, RebaseOperationType, RebaseOptions, Repository};
+    use RepositoryState;
+
+    // Creates a commit on top of `parent` which adds a file called `name`,
+    // without touching the index or working directory.
+    fn commit(repo: &Repository, name: &str, parent: Oid) -> Oid {
+        let parent = repo.find_commit(parent).unwrap();
+        let blob = repo.blob(name.as_bytes()).unwrap();
+        let mut builder = repo.treebuilder(Some(&parent.tree().unwrap()))
+                              .unwrap();
+        builder.insert(name, blob, 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let sig = repo.signature().unwrap();
+        repo.commit(None, &sig, &sig, name, &tree, &[&parent]).unwrap()
+    }
+
+    #[test]
+    fn inmemory() {
+        let (_td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let base = repo.head().unwrap().target().unwrap();
+        let upstream = commit(&repo, "upstream", base);
+        let side1 = commit(&repo, "side1", base);
+        let side2 = commit(&repo, "side2", side1);
+
+        let branch = repo.find_annotated_commit(side2).unwrap();
+        let upstream = repo.find_annotated_commit(upstream).unwrap();
+        let mut opts = RebaseOptions::new();
+        opts.inmemory(true);
+        let mut rebase = repo.rebase(Some(&branch), Some(&upstream), None,
+                                     Some(&mut opts)).unwrap();
+        assert_eq!(rebase.len(), 2);
+        assert_eq!(rebase.operation_current(), None);
+        assert_eq!(rebase.onto_id(), upstream.id());
+        assert_eq!(rebase.get(0).unwrap().id(), side1);
+        assert!(rebase.get(2).is_none());
+
+        let mut parent = upstream.id();
+        for (i, expected) in [side1, side2].iter().enumerate() {
+            let op = rebase.next().unwrap().unwrap();
+            assert_eq!(op.kind(), RebaseOperationType::Pick);
+            assert_eq!(op.id(), *expected);
+            assert!(op.exec().is_none());
+            assert_eq!(rebase.operation_current(), Some(i));
+            assert!(!rebase.inmemory_index().unwrap().has_conflicts());
+
+            let id = rebase.commit(None, &sig, None).unwrap();
+            let commit = repo.find_commit(id).unwrap();
+            assert_eq!(commit.parent_id(0).unwrap(), parent);
+            assert_eq!(commit.message(),
+                       repo.find_commit(*expected).unwrap().message());
+            parent = id;
+        }
+        assert!(rebase.next().is_none());
+        rebase.finish(None).unwrap();
+
+        // an in-memory rebase leaves the repository untouched
+        assert_eq!(repo.state(), RepositoryState::Clean);
+        assert_eq!(repo.head().unwrap().target().unwrap(), base);
+    }
+
+    #[test]
+    fn signing() {
+        let (_td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let base = repo.head().unwrap().target().unwrap();
+        let upstream = commit(&repo, "upstream", base);
+        let side1 = commit(&repo, "side1", base);
+        let side2 = commit(&repo, "side2", side1);
+
+        let branch = repo.find_annotated_commit(side2).unwrap();
+        let upstream = repo.find_annotated_commit(upstream).unwrap();
+        let mut signed = 0;
+        let mut ids = Vec::new();
+        {
+            let mut opts = RebaseOptions::new();
+            opts.inmemory(true).commit_signing_callback(|content| {
+                signed += 1;
+                assert!(content.starts_with("tree "));
+                // only sign the first commit
+                Ok(if signed == 1 {Some("sig".to_string())} else {None})
+            });
+            let mut rebase = repo.rebase(Some(&branch), Some(&upstream), None,
+                                         Some(&mut opts)).unwrap();
+            while let Some(op) = rebase.next() {
+                op.unwrap();
+                ids.push(rebase.commit(None, &sig, None).unwrap());
+            }
+            rebase.finish(None).unwrap();
+        }
+        assert_eq!(signed, 2);
+        let first = repo.find_commit(ids[0]).unwrap();
+        assert!(first.raw_header().unwrap().contains("gpgsig sig"));
+        let second = repo.find_commit(ids[1]).unwrap();
+        assert!(!second.raw_header().unwrap().contains("gpgsig"));
+
+        // errors of the callback are reported by `commit`
+        let mut opts = RebaseOptions::new();
+        opts.inmemory(true).commit_signing_callback(|_| {
+            Err(Error::from_str("no signing key"))
+        });
+        let mut rebase = repo.rebase(Some(&branch), Some(&upstream), None,
+                                     Some(&mut opts)).unwrap();
+        rebase.next().unwrap().unwrap();
+        let err = rebase.commit(None, &sig, None).err().unwrap();
+        assert_eq!(err.message(), "no signing key");
+    }
+
+    #[test]
+    fn abort() {
+        let (_td, repo) = ::test::repo_init();
+        let base = repo.head().unwrap().target().unwrap();
+        let upstream = commit(&repo, "upstream", base);
+        let side = commit(&repo, "side", base);
+
+        let branch = repo.find_annotated_commit(side).unwrap();
+        let upstream = repo.find_annotated_commit(upstream).unwrap();
+        {
+            let mut rebase = repo.rebase(Some(&branch), Some(&upstream), None,
+                                         None).unwrap();
+            assert_eq!(rebase.len(), 1);
+            assert_eq!(repo.state(), RepositoryState::RebaseMerge);
+            rebase.next().unwrap().unwrap();
+            rebase.abort().unwrap();
+        }
+        assert_eq!(repo.state(), RepositoryState::Clean);
+        assert_eq!(repo.head().unwrap().target().unwrap(), base);
+
+        // nothing to resume once the rebase has been aborted
+        assert!(repo.open_rebase(None).is_err());
+    }
+}
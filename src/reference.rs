@@ -1,11 +1,12 @@
 use std::cmp::Ordering;
-use std::ffi::CString;
+use std::ffi::{self, CString};
 use std::marker;
 use std::mem;
 use std::str;
 use libc;
 
-use {raw, Error, Oid, Signature};
+use {raw, Blob, Commit, Error, Object, ObjectType, Oid, ReferenceFormat};
+use {Tag, Tree};
 use util::Binding;
 
 /// A structure to represent a git [reference][1].
@@ -35,6 +36,28 @@ impl<'repo> Reference<'repo> {
         unsafe { raw::git_reference_is_valid_name(refname.as_ptr()) == 1 }
     }
 
+    /// Normalize a reference name, removing any leading slash and collapsing
+    /// runs of adjacent slashes, and validate it according to `flags`.
+    ///
+    /// An error describing why the name is not acceptable is returned if it
+    /// is invalid, which makes this suitable for checking names entered by a
+    /// user before creating a reference with them.
+    pub fn normalize_name(refname: &str, flags: ReferenceFormat)
+                          -> Result<String, Error> {
+        ::init();
+        let refname = CString::from_slice(refname.as_bytes());
+        let mut buf = [0 as libc::c_char; raw::GIT_REFNAME_MAX];
+        let len = buf.len() as libc::size_t;
+        let flags = flags.bits() as libc::c_uint;
+        unsafe {
+            try_call!(raw::git_reference_normalize_name(buf.as_mut_ptr(), len,
+                                                        refname, flags));
+            let ptr = buf.as_ptr();
+            let bytes = ffi::c_str_to_bytes(&ptr);
+            Ok(str::from_utf8(bytes).unwrap().to_string())
+        }
+    }
+
     /// Get access to the underlying raw pointer.
     pub fn raw(&self) -> *mut raw::git_reference { self.raw }
 
@@ -111,12 +134,64 @@ impl<'repo> Reference<'repo> {
     ///
     /// This peeled OID only applies to direct references that point to a hard
     /// Tag object: it is the result of peeling such Tag.
+    ///
+    /// It is only known for references read from `packed-refs`, where it is
+    /// recorded alongside the tag, and avoids looking up the tag object. Use
+    /// `peel` to find the target of other references.
     pub fn target_peel(&self) -> Option<Oid> {
         unsafe {
             Binding::from_raw_opt(raw::git_reference_target_peel(&*self.raw))
         }
     }
 
+    /// Recursively peel this reference until an object of the specified type
+    /// is found.
+    ///
+    /// Symbolic references are resolved first. If you pass `Any` as the
+    /// target type, then the object will be peeled until a non-tag object is
+    /// met.
+    pub fn peel(&self, kind: ObjectType) -> Result<Object<'repo>, Error> {
+        let raw = try!(self.peel_raw(kind));
+        unsafe { Ok(Binding::from_raw(raw)) }
+    }
+
+    /// Peel this reference until a commit is found, returning an error if
+    /// it does not eventually point to one.
+    pub fn peel_to_commit(&self) -> Result<Commit<'repo>, Error> {
+        let raw = try!(self.peel_raw(ObjectType::Commit));
+        unsafe { Ok(Binding::from_raw(raw as *mut raw::git_commit)) }
+    }
+
+    /// Peel this reference until a tree is found, returning an error if it
+    /// does not eventually point to one.
+    pub fn peel_to_tree(&self) -> Result<Tree<'repo>, Error> {
+        let raw = try!(self.peel_raw(ObjectType::Tree));
+        unsafe { Ok(Binding::from_raw(raw as *mut raw::git_tree)) }
+    }
+
+    /// Peel this reference until a blob is found, returning an error if it
+    /// does not eventually point to one.
+    pub fn peel_to_blob(&self) -> Result<Blob<'repo>, Error> {
+        let raw = try!(self.peel_raw(ObjectType::Blob));
+        unsafe { Ok(Binding::from_raw(raw as *mut raw::git_blob)) }
+    }
+
+    /// Peel this reference to the tag object it points to, returning an
+    /// error if it is not a reference to an annotated tag.
+    pub fn peel_to_tag(&self) -> Result<Tag<'repo>, Error> {
+        let raw = try!(self.peel_raw(ObjectType::Tag));
+        unsafe { Ok(Binding::from_raw(raw as *mut raw::git_tag)) }
+    }
+
+    fn peel_raw(&self, kind: ObjectType)
+                -> Result<*mut raw::git_object, Error> {
+        let mut raw = 0 as *mut raw::git_object;
+        unsafe {
+            try_call!(raw::git_reference_peel(&mut raw, self.raw, kind));
+        }
+        Ok(raw)
+    }
+
     /// Get full name to the reference pointed to by a symbolic reference.
     ///
     /// May return `None` if the reference is either not symbolic or not a
@@ -155,15 +230,13 @@ impl<'repo> Reference<'repo> {
     /// If the force flag is not enabled, and there's already a reference with
     /// the given name, the renaming will fail.
     pub fn rename(&mut self, new_name: &str, force: bool,
-                  sig: Option<&Signature>,
                   msg: &str) -> Result<Reference<'repo>, Error> {
         let mut raw = 0 as *mut raw::git_reference;
         let new_name = CString::from_slice(new_name.as_bytes());
         let msg = CString::from_slice(msg.as_bytes());
         unsafe {
             try_call!(raw::git_reference_rename(&mut raw, self.raw, new_name,
-                                                force, sig.map(|s| s.raw()),
-                                                msg));
+                                                force, msg));
         }
         Ok(Reference {
             raw: raw,
@@ -277,7 +350,8 @@ impl<'repo> Iterator for ReferenceNames<'repo> {
 
 #[cfg(test)]
 mod tests {
-    use {Reference};
+    use {Reference, REFERENCE_FORMAT_NORMAL, REFERENCE_FORMAT_ALLOW_ONELEVEL};
+    use {REFERENCE_FORMAT_REFSPEC_PATTERN, ObjectType};
 
     #[test]
     fn smoke() {
@@ -285,6 +359,50 @@ mod tests {
         assert!(!Reference::is_valid_name("foo"));
     }
 
+    #[test]
+    fn normalize_name() {
+        assert_eq!(Reference::normalize_name("refs//heads///foo",
+                                             REFERENCE_FORMAT_NORMAL).unwrap(),
+                   "refs/heads/foo");
+        assert!(Reference::normalize_name("foo",
+                                          REFERENCE_FORMAT_NORMAL).is_err());
+        assert_eq!(Reference::normalize_name("foo",
+                                             REFERENCE_FORMAT_ALLOW_ONELEVEL)
+                       .unwrap(), "foo");
+        assert!(Reference::normalize_name("refs/heads/*",
+                                          REFERENCE_FORMAT_NORMAL).is_err());
+        Reference::normalize_name("refs/heads/*",
+                                  REFERENCE_FORMAT_REFSPEC_PATTERN).unwrap();
+        let err = Reference::normalize_name("refs/heads/a..b",
+                                            REFERENCE_FORMAT_NORMAL);
+        assert!(err.unwrap_err().message().contains("a..b"));
+    }
+
+    #[test]
+    fn peel() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap();
+        let id = head.target().unwrap();
+        let commit = repo.find_commit(id).unwrap();
+        let sig = repo.signature().unwrap();
+        let obj = repo.find_object(id, None).unwrap();
+        let tag_id = repo.tag("v1", &obj, &sig, "msg", false).unwrap();
+
+        let tag = repo.find_reference("refs/tags/v1").unwrap();
+        assert_eq!(tag.peel_to_tag().unwrap().id(), tag_id);
+        assert_eq!(tag.peel_to_commit().unwrap().id(), id);
+        assert_eq!(tag.peel_to_tree().unwrap().id(), commit.tree_id());
+        assert!(tag.peel_to_blob().is_err());
+        assert_eq!(tag.peel(ObjectType::Any).unwrap().id(), id);
+        assert!(head.peel_to_tag().is_err());
+        assert!(tag.target_peel().is_none());
+
+        repo.pack_refs().unwrap();
+        let tag = repo.find_reference("refs/tags/v1").unwrap();
+        assert_eq!(tag.target(), Some(tag_id));
+        assert_eq!(tag.target_peel(), Some(id));
+    }
+
     #[test]
     fn smoke2() {
         let (_td, repo) = ::test::repo_init();
@@ -307,17 +425,15 @@ mod tests {
         assert_eq!(head.shorthand(), Some("master"));
         assert!(head.resolve().unwrap() == head);
 
-        let sig = repo.signature().unwrap();
         let mut tag1 = repo.reference("refs/tags/tag1",
                                       head.target().unwrap(),
-                                      false,
-                                      None, "test").unwrap();
+                                      false, "test").unwrap();
         assert!(tag1.is_tag());
         tag1.delete().unwrap();
 
         let mut sym1 = repo.reference_symbolic("refs/tags/tag1",
                                                "refs/heads/master", false,
-                                               Some(&sig), "test").unwrap();
+                                               "test").unwrap();
         sym1.delete().unwrap();
 
         {
@@ -330,7 +446,7 @@ mod tests {
             assert!(repo.references_glob("refs/heads/*").unwrap().count() == 1);
         }
 
-        let mut head = head.rename("refs/foo", true, None, "test").unwrap();
+        let mut head = head.rename("refs/foo", true, "test").unwrap();
         head.delete().unwrap();
 
     }
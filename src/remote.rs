@@ -6,8 +6,9 @@ use std::slice;
 use std::str;
 use libc;
 
-use {raw, Direction, Error, Refspec, Oid, IntoCString};
-use {Signature, Push, RemoteCallbacks, Progress};
+use {raw, Direction, Error, Refspec, Oid, Reference};
+use {Push, RemoteCallbacks, Progress};
+use {push, remote_callbacks};
 use util::Binding;
 
 /// A structure representing a [remote][1] of a git repository.
@@ -20,6 +21,8 @@ pub struct Remote<'repo, 'cb> {
     raw: *mut raw::git_remote,
     marker: marker::ContravariantLifetime<'repo>,
     callbacks: Option<&'cb mut RemoteCallbacks<'cb>>,
+    connected_url: Option<String>,
+    update_fetchhead: bool,
 }
 
 /// An iterator over the refspecs that a remote contains.
@@ -82,108 +85,85 @@ impl<'repo, 'cb> Remote<'repo, 'cb> {
         unsafe { ::opt_bytes(self, raw::git_remote_pushurl(&*self.raw)) }
     }
 
-    /// Open a connection to a remote.
-    pub fn connect(&mut self, dir: Direction) -> Result<(), Error> {
-        unsafe {
-            try!(self.set_raw_callbacks());
-            try_call!(raw::git_remote_connect(self.raw, dir));
-        }
-        Ok(())
-    }
-
-    /// Check whether the remote is connected
-    pub fn connected(&mut self) -> bool {
-        unsafe { raw::git_remote_connected(self.raw) == 1 }
-    }
-
-    /// Disconnect from the remote
-    pub fn disconnect(&mut self) {
-        unsafe { raw::git_remote_disconnect(self.raw) }
+    /// Get the url this remote fetches from, with the `url.<base>.insteadOf`
+    /// rewrites of the repository's configuration applied.
+    ///
+    /// Libgit2 applies the rewrites when the remote is created or loaded, so
+    /// this is the same as `url`, which is already rewritten.
+    pub fn url_resolved(&self) -> Result<String, Error> {
+        self.url().map(|s| s.to_string()).ok_or_else(|| {
+            Error::from_str("remote url is not valid utf-8")
+        })
     }
 
-    /// Save a remote to its repository's configuration
+    /// Get the url this remote pushes to, with the rewrites of the
+    /// repository's configuration applied.
     ///
-    /// Anonymous remotes cannot be saved
-    pub fn save(&self) -> Result<(), Error> {
-        unsafe { try_call!(raw::git_remote_save(&*self.raw)); }
-        Ok(())
+    /// This is the pushurl rewritten by `url.<base>.insteadOf` if one is
+    /// set, and otherwise the url rewritten by `url.<base>.pushInsteadOf`,
+    /// or by `url.<base>.insteadOf` if no `pushInsteadOf` prefix matches.
+    /// Libgit2 applies these rewrites when the remote is created or loaded,
+    /// and reports the result through `pushurl` if it differs from `url`.
+    pub fn pushurl_resolved(&self) -> Result<String, Error> {
+        let url = self.pushurl_bytes().unwrap_or(self.url_bytes());
+        str::from_utf8(url).map(|s| s.to_string()).map_err(|_| {
+            Error::from_str("remote url is not valid utf-8")
+        })
     }
 
-    /// Add a fetch refspec to the remote
-    pub fn add_fetch(&mut self, spec: &str) -> Result<(), Error> {
-        let spec = CString::from_slice(spec.as_bytes());
+    /// Open a connection to a remote.
+    pub fn connect(&mut self, dir: Direction) -> Result<(), Error> {
+        self.connected_url = None;
+        self.take_resolved_url();
+        let callbacks = self.callbacks.as_ref().map(|cbs| cbs.raw());
         unsafe {
-            try_call!(raw::git_remote_add_fetch(self.raw, spec));
+            try_call!(raw::git_remote_connect(self.raw, dir,
+                                              callbacks.as_ref(),
+                                              0 as *const raw::git_proxy_options,
+                                              0 as *const raw::git_strarray));
         }
+        let url = match self.take_resolved_url() {
+            Some(url) => Some(url),
+            None => {
+                let url = match dir {
+                    Direction::Push => self.pushurl_bytes()
+                                           .unwrap_or(self.url_bytes()),
+                    Direction::Fetch => self.url_bytes(),
+                };
+                str::from_utf8(url).ok().map(|s| s.to_string())
+            }
+        };
+        self.connected_url = url;
         Ok(())
     }
 
-    /// Add a push refspec to the remote
-    pub fn add_push(&mut self, spec: &str) -> Result<(), Error> {
-        let spec = CString::from_slice(spec.as_bytes());
-        unsafe {
-            try_call!(raw::git_remote_add_push(self.raw, spec));
-        }
-        Ok(())
+    /// Get the url which the last call to `connect` actually connected to.
+    ///
+    /// This is the pushurl when connecting for a push, if one is configured,
+    /// and reflects any rewriting done by `RemoteCallbacks::resolve_url`.
+    /// Redirects followed by the http transport are not visible here.
+    ///
+    /// Returns `None` if the remote has not been connected with `connect`, has
+    /// since been disconnected, or if the url is not valid utf-8.
+    pub fn connected_url(&self) -> Option<&str> {
+        self.connected_url.as_ref().map(|s| s.as_slice())
     }
 
-    /// Set the remote's url
-    ///
-    /// Existing connections will not be updated.
-    pub fn set_url(&mut self, url: &str) -> Result<(), Error> {
-        let url = CString::from_slice(url.as_bytes());
-        unsafe { try_call!(raw::git_remote_set_url(self.raw, url)); }
-        Ok(())
+    /// Check whether the remote is connected
+    pub fn connected(&mut self) -> bool {
+        unsafe { raw::git_remote_connected(self.raw) == 1 }
     }
 
-    /// Set the remote's pushurl.
-    ///
-    /// `None` indicates that it should be cleared.
-    ///
-    /// Existing connections will not be updated.
-    pub fn set_pushurl(&mut self, pushurl: Option<&str>) -> Result<(), Error> {
-        let pushurl = pushurl.map(|s| CString::from_slice(s.as_bytes()));
-        unsafe {
-            try_call!(raw::git_remote_set_pushurl(self.raw, pushurl));
-        }
-        Ok(())
+    /// Disconnect from the remote
+    pub fn disconnect(&mut self) {
+        self.connected_url = None;
+        unsafe { raw::git_remote_disconnect(self.raw); }
     }
 
     /// Sets the update FETCH_HEAD setting. By default, FETCH_HEAD will be
     /// updated on every fetch.
     pub fn set_update_fetchhead(&mut self, update: bool) {
-        unsafe {
-            raw::git_remote_set_update_fetchhead(self.raw, update as libc::c_int)
-        }
-    }
-
-    /// Set the remote's list of fetch refspecs
-    pub fn set_fetch_refspecs<T, I>(&mut self, i: I) -> Result<(), Error>
-        where T: IntoCString, I: Iterator<Item=T>
-    {
-        let (_a, _b, mut arr) = ::util::iter2cstrs(i);
-        unsafe {
-            try_call!(raw::git_remote_set_fetch_refspecs(self.raw, &mut arr));
-        }
-        Ok(())
-    }
-
-    /// Set the remote's list of push refspecs
-    pub fn set_push_refspecs<T, I>(&mut self, i: I) -> Result<(), Error>
-        where T: IntoCString, I: Iterator<Item=T>
-    {
-        let (_a, _b, mut arr) = ::util::iter2cstrs(i);
-        unsafe {
-            try_call!(raw::git_remote_set_push_refspecs(self.raw, &mut arr));
-        }
-        Ok(())
-    }
-
-    /// Clear the refspecs
-    ///
-    /// Remove all configured fetch and push refspecs from the remote.
-    pub fn clear_refspecs(&mut self) {
-        unsafe { raw::git_remote_clear_refspecs(self.raw) }
+        self.update_fetchhead = update;
     }
 
     /// Download and index the packfile
@@ -196,15 +176,48 @@ impl<'repo, 'cb> Remote<'repo, 'cb> {
     ///
     /// The `specs` argument is a list of refspecs to use for this negotiation
     /// and download. Use an empty array to use the base refspecs.
+    ///
+    /// During negotiation libgit2 advertises the tips of all local references
+    /// as the commits it already has, see `download_with_haves` to add to
+    /// them.
     pub fn download(&mut self, specs: &[&str]) -> Result<(), Error> {
         let (_a, _b, arr) = ::util::iter2cstrs(specs.iter());
+        let opts = self.fetch_options();
         unsafe {
-            try!(self.set_raw_callbacks());
-            try_call!(raw::git_remote_download(self.raw, &arr));
+            try_call!(raw::git_remote_download(self.raw, &arr, &opts));
         }
         Ok(())
     }
 
+    /// Like `download`, but also tell the remote that the commits in `haves`
+    /// are available locally, such as those of an alternate or a shared
+    /// cache, so that it can leave them and their history out of the pack.
+    ///
+    /// libgit2 offers no hook into the negotiation, so each commit is
+    /// advertised through a temporary reference under `refs/git2-haves/`,
+    /// which is deleted again once the download finishes or fails. These
+    /// references are visible to other processes using the repository while
+    /// the download runs. If one of them already exists, for example because
+    /// another download is in progress, this fails with `ErrorCode::Exists`
+    /// and leaves the existing reference alone.
+    pub fn download_with_haves(&mut self, specs: &[&str], haves: &[Oid])
+                               -> Result<(), Error> {
+        let mut refs = HaveRefs { refs: Vec::new() };
+        for have in haves.iter() {
+            let name = CString::from_slice(format!("refs/git2-haves/{}", have)
+                                               .as_bytes());
+            let msg = CString::from_slice(b"download: have");
+            let mut raw = 0 as *mut raw::git_reference;
+            unsafe {
+                let repo = raw::git_remote_owner(&*self.raw);
+                try_call!(raw::git_reference_create(&mut raw, repo, name,
+                                                    have.raw(), false, msg));
+                refs.refs.push(Binding::from_raw(raw));
+            }
+        }
+        self.download(specs)
+    }
+
     /// Get the number of refspecs for a remote
     pub fn refspecs<'a>(&'a self) -> Refspecs<'a, 'cb> {
         let cnt = unsafe { raw::git_remote_refspec_count(&*self.raw) as usize };
@@ -228,46 +241,44 @@ impl<'repo, 'cb> Remote<'repo, 'cb> {
     /// disconnect and update the remote-tracking branches.
     pub fn fetch(&mut self,
                  refspecs: &[&str],
-                 signature: Option<&Signature>,
                  msg: Option<&str>) -> Result<(), Error> {
         let (_a, _b, arr) = ::util::iter2cstrs(refspecs.iter());
         let msg = msg.map(|s| CString::from_slice(s.as_bytes()));
+        let opts = self.fetch_options();
         unsafe {
-            try!(self.set_raw_callbacks());
-            try_call!(raw::git_remote_fetch(self.raw,
-                                            &arr,
-                                            signature.map(|s| s.raw()),
-                                            msg));
+            try_call!(raw::git_remote_fetch(self.raw, &arr, &opts, msg));
         }
         Ok(())
     }
 
     /// Update the tips to the new state
-    pub fn update_tips(&mut self, signature: Option<&Signature>,
-                       msg: Option<&str>) -> Result<(), Error> {
+    ///
+    /// FETCH_HEAD is written as well and tags are downloaded according to the
+    /// remote's configuration.
+    pub fn update_tips(&mut self, msg: Option<&str>) -> Result<(), Error> {
         let msg = msg.map(|s| CString::from_slice(s.as_bytes()));
+        let callbacks = self.callbacks.as_ref().map(|cbs| cbs.raw());
         unsafe {
-            try_call!(raw::git_remote_update_tips(self.raw,
-                                                  signature.map(|s| s.raw()),
-                                                  msg));
+            try_call!(raw::git_remote_update_tips(
+                self.raw,
+                callbacks.as_ref(),
+                self.update_flags(),
+                raw::GIT_REMOTE_DOWNLOAD_TAGS_UNSPECIFIED,
+                msg));
         }
         Ok(())
     }
 
-    /// Retrieve the update FETCH_HEAD setting.
-    pub fn update_fetchhead(&mut self) -> Result<(), Error> {
-        unsafe { try_call!(raw::git_remote_update_fetchhead(self.raw)); }
-        Ok(())
-    }
-
     /// Create a new push object
+    ///
+    /// The callbacks of this remote are invoked while the push is in
+    /// progress.
     pub fn push(&mut self) -> Result<Push, Error> {
-        let mut ret = 0 as *mut raw::git_push;
-        try!(self.set_raw_callbacks());
-        unsafe {
-            try_call!(raw::git_push_new(&mut ret, self.raw));
-            Ok(Binding::from_raw(ret))
-        }
+        let callbacks = match self.callbacks {
+            Some(ref mut cbs) => Some(&mut **cbs as *mut RemoteCallbacks),
+            None => None,
+        };
+        Ok(push::new(self.raw, callbacks))
     }
 
     /// Set the callbacks to be invoked when the transfer is in-progress.
@@ -277,15 +288,31 @@ impl<'repo, 'cb> Remote<'repo, 'cb> {
         self.callbacks = Some(callbacks);
     }
 
-    fn set_raw_callbacks(&mut self) -> Result<(), Error> {
+    fn take_resolved_url(&mut self) -> Option<String> {
         match self.callbacks {
-            Some(ref mut cbs) => unsafe {
-                let raw = cbs.raw();
-                try_call!(raw::git_remote_set_callbacks(self.raw, &raw));
-            },
+            Some(ref mut cbs) => {
+                remote_callbacks::take_resolved_url(&mut **cbs)
+            }
+            None => None,
+        }
+    }
+
+    fn update_flags(&self) -> libc::c_uint {
+        if self.update_fetchhead {raw::GIT_REMOTE_UPDATE_FETCHHEAD} else {0}
+    }
+
+    fn fetch_options(&self) -> raw::git_fetch_options {
+        let mut opts: raw::git_fetch_options = unsafe { mem::zeroed() };
+        assert_eq!(unsafe {
+            raw::git_fetch_options_init(&mut opts,
+                                        raw::GIT_FETCH_OPTIONS_VERSION)
+        }, 0);
+        match self.callbacks {
+            Some(ref cbs) => opts.callbacks = cbs.raw(),
             None => {}
         }
-        Ok(())
+        opts.update_fetchhead = self.update_flags();
+        opts
     }
 
     /// Get the statistics structure that is filled in by the fetch operation.
@@ -317,6 +344,20 @@ impl<'repo, 'cb> Remote<'repo, 'cb> {
     }
 }
 
+// The temporary references of `download_with_haves`, deleted when dropped.
+struct HaveRefs<'repo> {
+    refs: Vec<Reference<'repo>>,
+}
+
+#[unsafe_destructor]
+impl<'repo> Drop for HaveRefs<'repo> {
+    fn drop(&mut self) {
+        for r in self.refs.iter_mut() {
+            let _ = r.delete();
+        }
+    }
+}
+
 impl<'a, 'b> Clone for Remote<'a, 'b> {
     fn clone(&self) -> Remote<'a, 'b> {
         let mut ret = 0 as *mut raw::git_remote;
@@ -326,6 +367,8 @@ impl<'a, 'b> Clone for Remote<'a, 'b> {
             raw: ret,
             marker: marker::ContravariantLifetime,
             callbacks: None,
+            connected_url: None,
+            update_fetchhead: self.update_fetchhead,
         }
     }
 }
@@ -338,6 +381,8 @@ impl<'repo, 'cb> Binding for Remote<'repo, 'cb> {
             raw: raw,
             marker: marker::ContravariantLifetime,
             callbacks: None,
+            connected_url: None,
+            update_fetchhead: true,
         }
     }
     fn raw(&self) -> *mut raw::git_remote { self.raw }
@@ -394,7 +439,7 @@ mod tests {
     use std::old_io::TempDir;
     use std::cell::Cell;
     use url::Url;
-    use {Repository, Remote, RemoteCallbacks, Direction};
+    use {Repository, Remote, RemoteCallbacks, Direction, Oid, ErrorCode};
 
     #[test]
     fn smoke() {
@@ -452,20 +497,13 @@ mod tests {
         origin.download(&[]).unwrap();
         origin.disconnect();
 
-        origin.save().unwrap();
-
-        origin.add_fetch("foo").unwrap();
-        origin.add_fetch("bar").unwrap();
-        origin.clear_refspecs();
+        repo.remote_add_fetch("origin", "foo").unwrap();
+        repo.remote_add_push("origin", "foo").unwrap();
 
-        origin.set_fetch_refspecs(["foo"].iter().map(|a| *a)).unwrap();
-        origin.set_push_refspecs(["foo"].iter().map(|a| *a)).unwrap();
-
-        let sig = repo.signature().unwrap();
-        origin.fetch(&[], Some(&sig), None).unwrap();
-        origin.fetch(&[], None, Some("foo")).unwrap();
-        origin.update_tips(Some(&sig), None).unwrap();
-        origin.update_tips(None, Some("foo")).unwrap();
+        origin.fetch(&[], None).unwrap();
+        origin.fetch(&[], Some("foo")).unwrap();
+        origin.update_tips(None).unwrap();
+        origin.update_tips(Some("foo")).unwrap();
     }
 
     #[test]
@@ -511,8 +549,148 @@ mod tests {
                 true
             });
             origin.set_callbacks(&mut callbacks);
-            origin.fetch(&[], None, None).unwrap();
+            origin.fetch(&[], None).unwrap();
         }
         assert!(progress_hit.get());
     }
+
+    #[test]
+    fn download_with_haves() {
+        let (td, origin_repo) = ::test::repo_init();
+        let url = Url::from_file_path(td.path()).ok().unwrap().to_string();
+        let first = origin_repo.head().unwrap().target().unwrap();
+
+        // copy the objects of the first commit without keeping a ref to it
+        let seeded = || {
+            let td = TempDir::new("git").unwrap();
+            let repo = Repository::init(td.path()).unwrap();
+            repo.remote_anonymous(url.as_slice(),
+                                  Some("refs/heads/master:refs/tmp")).unwrap()
+                .fetch(&[], None).unwrap();
+            repo.find_reference("refs/tmp").unwrap().delete().unwrap();
+            repo.remote("origin", url.as_slice()).unwrap();
+            (td, repo)
+        };
+        let (_td1, repo1) = seeded();
+        let (_td2, repo2) = seeded();
+
+        let sig = origin_repo.signature().unwrap();
+        let mut tree = origin_repo.treebuilder(None).unwrap();
+        tree.insert("a", origin_repo.blob(b"a").unwrap(), 0o100644).unwrap();
+        let tree = origin_repo.find_tree(tree.write().unwrap()).unwrap();
+        let parent = origin_repo.find_commit(first).unwrap();
+        origin_repo.commit(Some("HEAD"), &sig, &sig, "second", &tree,
+                           &[&parent]).unwrap();
+
+        let total = |repo: &Repository, haves: &[Oid]| {
+            let total = Cell::new(0);
+            {
+                let mut callbacks = RemoteCallbacks::new();
+                callbacks.transfer_progress(|progress| {
+                    total.set(progress.total_objects());
+                    true
+                });
+                let mut origin = repo.find_remote("origin").unwrap();
+                origin.set_callbacks(&mut callbacks);
+                origin.download_with_haves(&[], haves).unwrap();
+            }
+            total.get()
+        };
+        // the first commit and its tree are left out
+        assert_eq!(total(&repo1, &[first]), 3);
+        assert!(repo1.references_glob("refs/git2-haves/*").unwrap()
+                     .next().is_none());
+        assert_eq!(total(&repo2, &[]), 5);
+
+        // references left by someone else are neither reused nor deleted
+        let name = format!("refs/git2-haves/{}", first);
+        repo1.reference(name.as_slice(), first, false, "test").unwrap();
+        let mut origin = repo1.find_remote("origin").unwrap();
+        let err = origin.download_with_haves(&[], &[first]).err().unwrap();
+        assert_eq!(err.code(), ErrorCode::Exists);
+        assert!(repo1.find_reference(name.as_slice()).is_ok());
+    }
+
+    #[test]
+    fn resolve_url() {
+        let (td, _repo) = ::test::repo_init();
+        let td2 = TempDir::new("git").unwrap();
+        let url = Url::from_file_path(td.path()).ok().unwrap();
+        let url = url.to_string();
+
+        let repo = Repository::init(td2.path()).unwrap();
+        let mut seen = Vec::new();
+        {
+            let mut callbacks = RemoteCallbacks::new();
+            let mut origin = repo.remote("origin", "/path/to/nowhere").unwrap();
+            callbacks.resolve_url(|u, dir| {
+                assert!(match dir { Direction::Fetch => true, _ => false });
+                seen.push(u.to_string());
+                Some(url.clone())
+            });
+            origin.set_callbacks(&mut callbacks);
+            origin.fetch(&[], None).unwrap();
+        }
+        assert!(seen.len() > 0);
+        assert!(seen.iter().all(|u| u.as_slice() == "/path/to/nowhere"));
+        let origin = repo.find_remote("origin").unwrap();
+        assert_eq!(origin.url(), Some("/path/to/nowhere"));
+    }
+
+    #[test]
+    fn connected_url() {
+        let (td, _repo) = ::test::repo_init();
+        let td2 = TempDir::new("git").unwrap();
+        let url = Url::from_file_path(td.path()).ok().unwrap();
+        let url = url.to_string();
+
+        let repo = Repository::init(td2.path()).unwrap();
+        let mut origin = repo.remote("origin", url.as_slice()).unwrap();
+        assert!(origin.connected_url().is_none());
+        origin.connect(Direction::Fetch).unwrap();
+        assert_eq!(origin.connected_url(), Some(url.as_slice()));
+        origin.disconnect();
+        assert!(origin.connected_url().is_none());
+
+        let mut callbacks = RemoteCallbacks::new();
+        let mut origin = repo.remote("mirror", "/path/to/nowhere").unwrap();
+        callbacks.resolve_url(|_, _| Some(url.clone()));
+        origin.set_callbacks(&mut callbacks);
+        origin.connect(Direction::Fetch).unwrap();
+        assert_eq!(origin.connected_url(), Some(url.as_slice()));
+        assert_eq!(origin.url(), Some("/path/to/nowhere"));
+    }
+
+    #[test]
+    fn url_resolved() {
+        let (td, _repo) = ::test::repo_init();
+        let url = Url::from_file_path(td.path()).ok().unwrap().to_string();
+        let td2 = TempDir::new("git").unwrap();
+        let repo = Repository::init(td2.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str(format!("url.{}.insteadOf", url).as_slice(), "local:")
+              .unwrap();
+        config.set_str("url.ssh://push/.pushInsteadOf", "local:").unwrap();
+
+        // libgit2 rewrites the urls as it creates the remote
+        let mut origin = repo.remote("origin", "local:").unwrap();
+        assert_eq!(origin.url(), Some(url.as_slice()));
+        assert_eq!(origin.pushurl(), Some("ssh://push/"));
+        assert_eq!(origin.url_resolved().unwrap(), url);
+        assert_eq!(origin.pushurl_resolved().unwrap(), "ssh://push/");
+
+        // and as it loads one, where an explicit pushurl only takes insteadOf
+        config.set_str("remote.third.url", "/path/to/nowhere").unwrap();
+        config.set_str("remote.third.pushurl", "local:").unwrap();
+        let third = repo.find_remote("third").unwrap();
+        assert_eq!(third.url_resolved().unwrap(), "/path/to/nowhere");
+        assert_eq!(third.pushurl_resolved().unwrap(), url);
+        let other = repo.remote("other", "/path/to/nowhere").unwrap();
+        assert_eq!(other.url_resolved().unwrap(), "/path/to/nowhere");
+        assert_eq!(other.pushurl_resolved().unwrap(), "/path/to/nowhere");
+
+        // so connecting needs no callbacks
+        origin.connect(Direction::Fetch).unwrap();
+        assert_eq!(origin.connected_url(), Some(url.as_slice()));
+    }
 }
@@ -3,9 +3,10 @@ use std::marker;
 use std::mem;
 use std::slice;
 use std::str;
-use libc::{c_void, c_int, c_char, c_uint};
+use libc::{c_void, c_int, c_char, c_uint, size_t};
 
-use {raw, panic, Error, Cred, CredentialType, Oid};
+use {raw, panic, Error, Cred, CredentialType, Direction, Oid};
+use {PackBuilderStage, PushStatus};
 use cert::Cert;
 use util::Binding;
 
@@ -20,6 +21,10 @@ pub struct RemoteCallbacks<'a> {
     sideband_progress: Option<Box<TransportMessage<'a>>>,
     update_tips: Option<Box<UpdateTips<'a>>>,
     certificate_check: Option<Box<CertificateCheck<'a>>>,
+    resolve_url: Option<Box<ResolveUrl<'a>>>,
+    pack_progress: Option<Box<PackProgress<'a>>>,
+    resolved_url: Option<String>,
+    push_statuses: Vec<PushStatus>,
 }
 
 /// Struct representing the progress by an in-flight transfer.
@@ -68,6 +73,19 @@ pub type UpdateTips<'a> = FnMut(&str, Oid, Oid) -> bool + 'a;
 /// argument.
 pub type CertificateCheck<'a> = FnMut(&Cert, &str) -> bool + 'a;
 
+/// Callback to rewrite the url of a remote when connecting to it.
+///
+/// The arguments are the url configured for the remote and the direction of
+/// the connection. Returning `None` connects to the url as is, otherwise the
+/// returned url is used for this connection only.
+pub type ResolveUrl<'a> = FnMut(&str, Direction) -> Option<String> + 'a;
+
+/// Callback for the progress of building the pack which is sent when pushing.
+///
+/// The arguments are the current stage, the number of objects processed in
+/// that stage so far and the total number of objects.
+pub type PackProgress<'a> = FnMut(PackBuilderStage, usize, usize) + 'a;
+
 impl<'a> RemoteCallbacks<'a> {
     /// Creates a new set of empty callbacks
     pub fn new() -> RemoteCallbacks<'a> {
@@ -77,6 +95,10 @@ impl<'a> RemoteCallbacks<'a> {
             sideband_progress: None,
             update_tips: None,
             certificate_check: None,
+            resolve_url: None,
+            pack_progress: None,
+            resolved_url: None,
+            push_statuses: Vec::new(),
         }
     }
 
@@ -123,6 +145,39 @@ impl<'a> RemoteCallbacks<'a> {
         self.certificate_check = Some(Box::new(cb) as Box<CertificateCheck<'a>>);
         self
     }
+
+    /// The callback through which the url of a remote may be rewritten each
+    /// time it is connected to, for example to redirect it to a mirror,
+    /// without modifying the repository's configuration.
+    pub fn resolve_url<F>(&mut self, cb: F) -> &mut RemoteCallbacks<'a>
+        where F: FnMut(&str, Direction) -> Option<String> + 'a
+    {
+        self.resolve_url = Some(Box::new(cb) as Box<ResolveUrl<'a>>);
+        self
+    }
+
+    /// The callback through which progress of building the pack is reported
+    /// during a push, before any data is sent.
+    ///
+    /// This is separate from the network transfer, which matches the
+    /// "Counting objects" and "Compressing objects" output of `git push`.
+    pub fn pack_progress<F>(&mut self, cb: F) -> &mut RemoteCallbacks<'a>
+        where F: FnMut(PackBuilderStage, usize, usize) + 'a
+    {
+        self.pack_progress = Some(Box::new(cb) as Box<PackProgress<'a>>);
+        self
+    }
+}
+
+/// Take the url which the `resolve_url` callback last rewrote a remote's url
+/// to, if any.
+pub fn take_resolved_url(callbacks: &mut RemoteCallbacks) -> Option<String> {
+    callbacks.resolved_url.take()
+}
+
+/// Take the statuses the remote reported for each reference of the last push.
+pub fn take_push_statuses(callbacks: &mut RemoteCallbacks) -> Vec<PushStatus> {
+    mem::replace(&mut callbacks.push_statuses, Vec::new())
 }
 
 impl<'a> Binding for RemoteCallbacks<'a> {
@@ -159,6 +214,17 @@ impl<'a> Binding for RemoteCallbacks<'a> {
                                 = update_tips_cb;
                 callbacks.update_tips = Some(f);
             }
+            if self.resolve_url.is_some() {
+                let f: raw::git_url_resolve_cb = resolve_url_cb;
+                callbacks.resolve_url = Some(f);
+            }
+            if self.pack_progress.is_some() {
+                let f: raw::git_packbuilder_progress = pack_progress_cb;
+                callbacks.pack_progress = Some(f);
+            }
+            let f: extern fn(*const c_char, *const c_char,
+                             *mut c_void) -> c_int = push_update_reference_cb;
+            callbacks.push_update_reference = Some(f);
             callbacks.payload = self as *const _ as *mut _;
             return callbacks;
         }
@@ -338,3 +404,82 @@ extern fn certificate_check_cb(cert: *mut raw::git_cert,
         if ok {0} else {-1}
     }
 }
+
+extern fn resolve_url_cb(url_resolved: *mut raw::git_buf,
+                         url: *const c_char,
+                         direction: c_int,
+                         data: *mut c_void) -> c_int {
+    unsafe {
+        let payload: &mut RemoteCallbacks = &mut *(data as *mut RemoteCallbacks);
+        let callback = match payload.resolve_url {
+            Some(ref mut c) => c,
+            None => return raw::GIT_PASSTHROUGH as c_int,
+        };
+        let url = match str::from_utf8(ffi::c_str_to_bytes(&url)) {
+            Ok(url) => url,
+            Err(_) => return raw::GIT_PASSTHROUGH as c_int,
+        };
+        let direction = if direction == raw::GIT_DIRECTION_PUSH as c_int {
+            Direction::Push
+        } else {
+            Direction::Fetch
+        };
+        match panic::wrap(|| callback(url, direction)) {
+            Some(Some(url)) => {
+                let rc = raw::git_buf_set(url_resolved,
+                                          url.as_ptr() as *const c_void,
+                                          url.len() as size_t);
+                payload.resolved_url = Some(url);
+                rc
+            }
+            Some(None) => raw::GIT_PASSTHROUGH as c_int,
+            None => -1,
+        }
+    }
+}
+
+extern fn pack_progress_cb(stage: c_int,
+                           current: c_uint,
+                           total: c_uint,
+                           data: *mut c_void) -> c_int {
+    unsafe {
+        let payload: &mut RemoteCallbacks = &mut *(data as *mut RemoteCallbacks);
+        let callback = match payload.pack_progress {
+            Some(ref mut c) => c,
+            None => return 0,
+        };
+        let stage = match stage {
+            raw::GIT_PACKBUILDER_ADDING_OBJECTS => {
+                PackBuilderStage::AddingObjects
+            }
+            _ => PackBuilderStage::Deltafication,
+        };
+        let ok = panic::wrap(|| {
+            callback(stage, current as usize, total as usize);
+        }).is_some();
+        if ok {0} else {-1}
+    }
+}
+
+extern fn push_update_reference_cb(refname: *const c_char,
+                                   status: *const c_char,
+                                   data: *mut c_void) -> c_int {
+    unsafe {
+        let payload: &mut RemoteCallbacks = &mut *(data as *mut RemoteCallbacks);
+        let refname = match str::from_utf8(ffi::c_str_to_bytes(&refname)) {
+            Ok(s) => s.to_string(),
+            Err(_) => return 0,
+        };
+        let message = if status.is_null() {
+            None
+        } else {
+            let bytes = ffi::c_str_to_bytes(&status);
+            Some(String::from_utf8_lossy(bytes).into_owned())
+        };
+        payload.push_statuses.push(PushStatus {
+            reference: refname,
+            message: message,
+        });
+        0
+    }
+}
@@ -1,16 +1,39 @@
+use std::cell::RefCell;
+use std::env;
 use std::ffi::{self, CString};
 use std::mem;
+use std::old_io::{self, File, Command};
+use std::old_io::fs::{self, PathExtensions};
+use std::old_io::process::ProcessOutput;
 use std::str;
+use std::thread;
 use libc::{c_int, c_char, size_t, c_void, c_uint};
 
-use {raw, Revspec, Error, init, Object, RepositoryState, Remote, Buf};
+use {raw, panic, Revspec, Error, init, Object, RepositoryState, Remote, Buf};
 use {ResetType, Signature, Reference, References, Submodule};
 use {Branches, BranchType, Index, Config, Oid, Blob, Branch, Commit, Tree};
 use {ObjectType, Tag, Note, Notes, StatusOptions, Statuses, Status, Revwalk};
-use {RevparseMode, RepositoryInitMode, Reflog, IntoCString};
+use {RevparseMode, RepositoryInitMode, Reflog, IntoCString, TreeBuilder};
+use {RepositoryOpenFlags, REPOSITORY_OPEN_NO_REPLACE_OBJECTS};
+use {Diff, DiffOptions, ApplyLocation, ApplyOptions, MergeOptions};
+use {AnnotatedCommit, MergeAnalysis, MergePreference};
+use {IndexEntry, MergeFileOptions, MergeFileResult, Rebase, RebaseOptions};
+use {StashFlags, StashApplyOptions, AttrCheckFlags};
+use {SubmoduleIgnore, SubmoduleStatus, Worktree, Odb, ErrorCode, ErrorClass};
+use {CommitGraph, CommitGraphUsage, CommitGraphWriter, PackBuilder};
+use {SequencerStep, Blame, BlameOptions, EolOptions};
+use {ArchiveOptions, BranchSummary, ObjectCache, Rerere, SignatureVerification};
+use blame;
+use branch;
+use eol;
+use object_cache;
+use status;
 use build::{RepoBuilder, CheckoutBuilder};
+use oid_array::OidArray;
 use string_array::StringArray;
-use util::Binding;
+use util::{Binding, io_error};
+
+static REPLACE_REF_BASE: &'static str = "refs/replace/";
 
 /// An owned git repository, representing all state associated with the
 /// underlying filesystem.
@@ -23,10 +46,19 @@ use util::Binding;
 /// from the filesystem.
 pub struct Repository {
     raw: *mut raw::git_repository,
+    object_cache: RefCell<Option<ObjectCache>>,
+    commit_graph: RefCell<Option<(CommitGraphKey, CommitGraph)>>,
+    no_replace_objects: bool,
 }
 
-// It is the current belief that a `Repository` can be sent among threads, or
-// even shared among threads in a mutex.
+// The file a cached commit-graph was read from along with its size and
+// modification time, to notice when it has been rewritten.
+type CommitGraphKey = (Path, u64, u64);
+
+// libgit2 guarantees that distinct `git_repository` handles may be used
+// concurrently, and a single handle may be used from any one thread at a time,
+// so a `Repository` can be sent among threads, or even shared among threads in
+// a mutex. It is not `Sync` as some of its caches are not internally locked.
 unsafe impl Send for Repository {}
 
 /// Options which can be used to configure how a repository is initialized
@@ -54,6 +86,38 @@ impl Repository {
         }
     }
 
+    /// Find and open a repository at or above `path` according to `flags`.
+    ///
+    /// The search for a repository through the parent directories of `path`
+    /// does not go up into any of the `ceiling_dirs`.
+    pub fn open_ext(path: &Path, flags: RepositoryOpenFlags,
+                    ceiling_dirs: &[&Path]) -> Result<Repository, Error> {
+        init();
+        let path = CString::from_slice(path.as_vec());
+        let sep = if cfg!(windows) { b';' } else { b':' };
+        let mut ceiling = Vec::new();
+        for (i, dir) in ceiling_dirs.iter().enumerate() {
+            if i > 0 { ceiling.push(sep) }
+            ceiling.push_all(dir.as_vec());
+        }
+        let ceiling = if ceiling_dirs.is_empty() {
+            None
+        } else {
+            Some(CString::from_vec(ceiling))
+        };
+        let libgit2_flags = flags - REPOSITORY_OPEN_NO_REPLACE_OBJECTS;
+        let mut ret = 0 as *mut raw::git_repository;
+        unsafe {
+            try_call!(raw::git_repository_open_ext(&mut ret, path,
+                                                   libgit2_flags.bits(),
+                                                   ceiling));
+            let mut repo: Repository = Binding::from_raw(ret);
+            repo.no_replace_objects =
+                flags.contains(REPOSITORY_OPEN_NO_REPLACE_OBJECTS);
+            Ok(repo)
+        }
+    }
+
     /// Attempt to open an already-existing repository at or above `path`
     ///
     /// This starts at `path` and looks up the filesystem hierarchy
@@ -70,6 +134,36 @@ impl Repository {
         Repository::open(&Path::new(&*buf))
     }
 
+    /// Create a "fake" repository to wrap an object database.
+    ///
+    /// The repository has no working directory and no path on disk, and its
+    /// references, index and configuration start out empty and are kept in
+    /// memory, so methods such as `head` return an error. Objects can still
+    /// be looked up, created and inspected through the usual commit, tree
+    /// and blob APIs, which makes this suitable for tools which only operate
+    /// on a bare store of objects.
+    pub fn from_odb(odb: &Odb) -> Result<Repository, Error> {
+        init();
+        let mut ret = 0 as *mut raw::git_repository;
+        unsafe {
+            try_call!(raw::git_repository_wrap_odb(&mut ret, odb.raw()));
+            let repo: Repository = Binding::from_raw(ret);
+
+            // libgit2 would otherwise load these from the repository
+            // directory, which a wrapped odb does not have
+            let mut refdb = 0 as *mut raw::git_refdb;
+            try_call!(raw::git_refdb_new(&mut refdb, ret));
+            let rc = raw::git_repository_set_refdb(ret, refdb);
+            raw::git_refdb_free(refdb);
+            try!(::call::try(rc));
+            let index = try!(Index::new());
+            try_call!(raw::git_repository_set_index(ret, index.raw()));
+            let config = try!(Config::new());
+            try_call!(raw::git_repository_set_config(ret, config.raw()));
+            Ok(repo)
+        }
+    }
+
     /// Creates a new repository in the specified folder.
     ///
     /// This by default will create any necessary directories to create the
@@ -151,6 +245,58 @@ impl Repository {
         unsafe { raw::git_repository_is_shallow(self.raw) == 1 }
     }
 
+    /// Read the commits at the boundary of a shallow clone from the
+    /// `shallow` file of the repository.
+    ///
+    /// The history behind these commits was not fetched, so they are treated
+    /// as having no parents. The list is empty if the repository is not
+    /// shallow.
+    pub fn shallow_roots(&self) -> Result<Vec<Oid>, Error> {
+        let file = match self.commondir() {
+            Some(dir) => dir.join("shallow"),
+            None => return Err(Error::from_str("repository has no path")),
+        };
+        let lines = try!(self.read_oid_lines(&file));
+        Ok(lines.into_iter().map(|ids| ids[0]).collect())
+    }
+
+    /// Read the grafts listed in the `info/grafts` file of the repository,
+    /// as pairs of a commit and the parents it is treated as having instead
+    /// of those it records.
+    ///
+    /// Grafts are deprecated in favor of replacement refs, see
+    /// `replace_object`, but are still honored by git.
+    pub fn grafts(&self) -> Result<Vec<(Oid, Vec<Oid>)>, Error> {
+        let file = match self.commondir() {
+            Some(dir) => dir.join("info/grafts"),
+            None => return Err(Error::from_str("repository has no path")),
+        };
+        let lines = try!(self.read_oid_lines(&file));
+        Ok(lines.into_iter().map(|mut ids| {
+            let commit = ids.remove(0);
+            (commit, ids)
+        }).collect())
+    }
+
+    // Parse lines of object ids separated by spaces, skipping blank lines
+    // and comments, as found in `shallow` and `info/grafts`.
+    fn read_oid_lines(&self, file: &Path) -> Result<Vec<Vec<Oid>>, Error> {
+        if !file.exists() { return Ok(Vec::new()) }
+        let contents = try!(File::open(file).read_to_string()
+                                .map_err(io_error));
+        let mut ret = Vec::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("#") { continue }
+            let mut ids = Vec::new();
+            for hex in line.split(' ').filter(|s| !s.is_empty()) {
+                ids.push(try!(Oid::from_str(hex)));
+            }
+            ret.push(ids);
+        }
+        Ok(ret)
+    }
+
     /// Tests whether this repository is empty.
     pub fn is_empty(&self) -> Result<bool, Error> {
         let empty = unsafe {
@@ -161,6 +307,11 @@ impl Repository {
 
     /// Returns the path to the `.git` folder for normal repositories or the
     /// repository itself for bare repositories.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the repository was created with `from_odb` and so has no
+    /// path.
     pub fn path(&self) -> Path {
         unsafe {
             let ptr = raw::git_repository_path(self.raw);
@@ -169,6 +320,23 @@ impl Repository {
         }
     }
 
+    /// Returns the path to the directory shared by all worktrees of the
+    /// repository, holding its objects and references.
+    ///
+    /// This is the same as `path` except for linked worktrees, for which it
+    /// is the `.git` folder of the main worktree. Returns `None` if the
+    /// repository was created with `from_odb`.
+    pub fn commondir(&self) -> Option<Path> {
+        unsafe {
+            let ptr = raw::git_repository_commondir(self.raw);
+            if ptr.is_null() {
+                None
+            } else {
+                Some(Path::new(ffi::c_str_to_bytes(&ptr)))
+            }
+        }
+    }
+
     /// Returns the current state of this repository
     pub fn state(&self) -> RepositoryState {
         let state = unsafe { raw::git_repository_state(self.raw) };
@@ -195,6 +363,133 @@ impl Repository {
         )
     }
 
+    /// Remove all the metadata associated with an ongoing command like
+    /// merge, revert, cherry-pick, etc. For example: MERGE_HEAD, MERGE_MSG,
+    /// etc.
+    pub fn cleanup_state(&self) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_repository_state_cleanup(self.raw));
+        }
+        Ok(())
+    }
+
+    /// Release the memory held by this repository's caches.
+    ///
+    /// This drops the cached objects, attributes and configuration of this
+    /// repository along with its handles to the object database, unmapping
+    /// any pack file windows which are no longer in use elsewhere. Everything
+    /// is lazily reloaded when next needed, so this can be called at any time
+    /// to bound the memory use of a long running process.
+    ///
+    /// Object caching can be tuned or disabled for all repositories through
+    /// the `opts` module.
+    pub fn free_unused_memory(&self) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_repository__cleanup(self.raw));
+        }
+        Ok(())
+    }
+
+    /// Get the ids of the commits being merged in, as listed in MERGE_HEAD.
+    ///
+    /// If no merge is in progress then an empty list is returned.
+    pub fn merge_heads(&self) -> Result<Vec<Oid>, Error> {
+        let mut ids = Vec::new();
+        unsafe {
+            let rc = raw::git_repository_mergehead_foreach(
+                self.raw, mergehead_cb, &mut ids as *mut _ as *mut c_void);
+            if rc == raw::GIT_ENOTFOUND as c_int {
+                return Ok(Vec::new())
+            }
+            try!(::call::try(rc));
+        }
+        return Ok(ids);
+
+        extern fn mergehead_cb(id: *const raw::git_oid,
+                               data: *mut c_void) -> c_int {
+            unsafe {
+                let ids = &mut *(data as *mut Vec<Oid>);
+                ids.push(Binding::from_raw(id));
+                0
+            }
+        }
+    }
+
+    /// Get the id of the commit being cherry-picked, as recorded in
+    /// CHERRY_PICK_HEAD.
+    ///
+    /// Returns `None` if no cherry-pick is in progress.
+    pub fn cherrypick_head(&self) -> Result<Option<Oid>, Error> {
+        self.state_head("CHERRY_PICK_HEAD")
+    }
+
+    /// Get the id of the commit being reverted, as recorded in REVERT_HEAD.
+    ///
+    /// Returns `None` if no revert is in progress.
+    pub fn revert_head(&self) -> Result<Option<Oid>, Error> {
+        self.state_head("REVERT_HEAD")
+    }
+
+    fn state_head(&self, name: &str) -> Result<Option<Oid>, Error> {
+        match self.refname_to_id(name) {
+            Ok(id) => Ok(Some(id)),
+            Err(ref e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read the steps which remain to be applied by an interrupted
+    /// multi-commit cherry-pick or revert, as written by `git cherry-pick` or
+    /// `git revert` to `sequencer/todo`.
+    ///
+    /// The commit currently being applied is not included, and is available
+    /// through `cherrypick_head` or `revert_head` instead. An empty list is
+    /// returned if no such operation is in progress.
+    pub fn sequencer_todo(&self) -> Result<Vec<SequencerStep>, Error> {
+        let file = self.path().join("sequencer/todo");
+        if !file.exists() { return Ok(Vec::new()) }
+        let todo = try!(File::open(&file).read_to_string().map_err(io_error));
+        ::sequencer::parse_todo(self, todo.as_slice())
+    }
+
+    /// Loop over the entries of FETCH_HEAD, as written by the last fetch,
+    /// and issue a callback for each one.
+    ///
+    /// The callback is given the name of the fetched reference, the url of
+    /// the remote it was fetched from, its id, and whether it was marked to
+    /// be merged. Returning `false` from the callback will terminate the
+    /// iteration.
+    pub fn fetchhead_foreach<F>(&self, mut callback: F) -> Result<(), Error>
+        where F: FnMut(&str, &[u8], &Oid, bool) -> bool
+    {
+        unsafe {
+            try_call!(raw::git_repository_fetchhead_foreach(
+                self.raw, fetchhead_cb::<F>,
+                &mut callback as *mut _ as *mut c_void));
+            return Ok(())
+        }
+
+        extern fn fetchhead_cb<F>(ref_name: *const c_char,
+                                  remote_url: *const c_char,
+                                  id: *const raw::git_oid,
+                                  is_merge: c_uint,
+                                  data: *mut c_void) -> c_int
+            where F: FnMut(&str, &[u8], &Oid, bool) -> bool
+        {
+            unsafe {
+                let data = data as *mut F;
+                let ref_name = ffi::c_str_to_bytes(&ref_name);
+                let ref_name = str::from_utf8(ref_name).unwrap();
+                let remote_url = ffi::c_str_to_bytes(&remote_url);
+                let id: Oid = Binding::from_raw(id);
+                let ok = panic::wrap(move || {
+                    (*data)(ref_name, remote_url, &id, is_merge == 1)
+                }).unwrap_or(false);
+                if ok {0} else {1}
+            }
+        }
+    }
+
     /// Get the path of the working directory for this repository.
     ///
     /// If this repository is bare, then `None` is returned.
@@ -270,8 +565,13 @@ impl Repository {
         let url = CString::from_slice(url.as_bytes());
         let fetch = fetch.map(|t| CString::from_slice(t.as_bytes()));
         unsafe {
-            try_call!(raw::git_remote_create_anonymous(&mut ret, self.raw, url,
-                                                       fetch));
+            let mut opts: raw::git_remote_create_options = mem::zeroed();
+            try_call!(raw::git_remote_create_options_init(
+                &mut opts, raw::GIT_REMOTE_CREATE_OPTIONS_VERSION));
+            opts.repository = self.raw;
+            opts.fetchspec = fetch.as_ref().map(|s| s.as_ptr())
+                                  .unwrap_or(0 as *const _);
+            try_call!(raw::git_remote_create_with_opts(&mut ret, url, &opts));
             Ok(Binding::from_raw(ret))
         }
     }
@@ -314,6 +614,59 @@ impl Repository {
         Ok(())
     }
 
+    /// Add a fetch refspec to the configuration of the remote `name`.
+    ///
+    /// Loaded instances of the remote are not updated.
+    pub fn remote_add_fetch(&self, name: &str, spec: &str)
+                            -> Result<(), Error> {
+        let name = CString::from_slice(name.as_bytes());
+        let spec = CString::from_slice(spec.as_bytes());
+        unsafe {
+            try_call!(raw::git_remote_add_fetch(self.raw, name, spec));
+        }
+        Ok(())
+    }
+
+    /// Add a push refspec to the configuration of the remote `name`.
+    ///
+    /// Loaded instances of the remote are not updated.
+    pub fn remote_add_push(&self, name: &str, spec: &str)
+                           -> Result<(), Error> {
+        let name = CString::from_slice(name.as_bytes());
+        let spec = CString::from_slice(spec.as_bytes());
+        unsafe {
+            try_call!(raw::git_remote_add_push(self.raw, name, spec));
+        }
+        Ok(())
+    }
+
+    /// Set the url of the remote `name` in the configuration.
+    ///
+    /// Loaded instances of the remote and existing connections are not
+    /// updated.
+    pub fn remote_set_url(&self, name: &str, url: &str) -> Result<(), Error> {
+        let name = CString::from_slice(name.as_bytes());
+        let url = CString::from_slice(url.as_bytes());
+        unsafe { try_call!(raw::git_remote_set_url(self.raw, name, url)); }
+        Ok(())
+    }
+
+    /// Set the pushurl of the remote `name` in the configuration.
+    ///
+    /// `None` indicates that it should be cleared.
+    ///
+    /// Loaded instances of the remote and existing connections are not
+    /// updated.
+    pub fn remote_set_pushurl(&self, name: &str, pushurl: Option<&str>)
+                              -> Result<(), Error> {
+        let name = CString::from_slice(name.as_bytes());
+        let pushurl = pushurl.map(|s| CString::from_slice(s.as_bytes()));
+        unsafe {
+            try_call!(raw::git_remote_set_pushurl(self.raw, name, pushurl));
+        }
+        Ok(())
+    }
+
     /// Sets the current head to the specified object and optionally resets
     /// the index and working tree to match.
     ///
@@ -334,18 +687,39 @@ impl Repository {
     pub fn reset(&self,
                  target: &Object,
                  kind: ResetType,
-                 checkout: Option<&mut CheckoutBuilder>,
-                 signature: Option<&Signature>,
-                 log_message: Option<&str>)
+                 checkout: Option<&mut CheckoutBuilder>)
                  -> Result<(), Error> {
-        let msg = log_message.map(|s| CString::from_slice(s.as_bytes()));
         unsafe {
             let mut opts: raw::git_checkout_options = mem::zeroed();
             let opts = checkout.map(|c| {
                 c.configure(&mut opts); &mut opts
             });
-            try_call!(raw::git_reset(self.raw, target.raw(), kind,
-                                     opts, signature.map(|s| s.raw()), msg));
+            try_call!(raw::git_reset(self.raw, target.raw(), kind, opts));
+        }
+        Ok(())
+    }
+
+    /// Sets the current head to the specified annotated commit and optionally
+    /// resets the index and working tree to match.
+    ///
+    /// This behaves like `reset`, but the reflog entry for HEAD records how
+    /// the annotated commit was looked up (e.g. the branch name or revspec)
+    /// instead of just its id.
+    pub fn reset_from_annotated(&self,
+                                commit: &AnnotatedCommit,
+                                kind: ResetType,
+                                checkout: Option<&mut CheckoutBuilder>)
+                                -> Result<(), Error> {
+        unsafe {
+            let mut opts = mem::zeroed();
+            try_call!(raw::git_checkout_init_options(&mut opts,
+                                raw::GIT_CHECKOUT_OPTIONS_VERSION));
+            match checkout {
+                Some(c) => c.configure(&mut opts),
+                None => {}
+            }
+            try_call!(raw::git_reset_from_annotated(self.raw, commit.raw(),
+                                                    kind, &opts));
         }
         Ok(())
     }
@@ -379,6 +753,56 @@ impl Repository {
         }
     }
 
+    /// Make the repository HEAD point to the specified reference.
+    ///
+    /// If the provided reference points to a tree or a blob, the HEAD is
+    /// unaltered and an error is returned.
+    ///
+    /// If the provided reference points to a branch, the HEAD will point to
+    /// that branch, staying attached, or become attached if it isn't yet. If
+    /// the branch doesn't exist yet, no error will be returned. The HEAD will
+    /// then be attached to an unborn branch.
+    ///
+    /// Otherwise, the HEAD will be detached and will directly point to the
+    /// commit.
+    ///
+    /// The change is recorded in the reflog of HEAD with the identity
+    /// configured for the repository.
+    pub fn set_head(&self, refname: &str) -> Result<(), Error> {
+        self.set_head_bytes(refname.as_bytes())
+    }
+
+    /// Make the repository HEAD point to the specified reference, given as a
+    /// byte slice.
+    ///
+    /// Reference names are not required to be valid utf-8, and this allows
+    /// names returned by `Reference::name_bytes` to be used directly.
+    pub fn set_head_bytes(&self, refname: &[u8]) -> Result<(), Error> {
+        let refname = CString::from_slice(refname);
+        unsafe {
+            try_call!(raw::git_repository_set_head(self.raw, refname));
+        }
+        Ok(())
+    }
+
+    /// Make the repository HEAD directly point to the commit.
+    ///
+    /// If the provided commitish cannot be found in the repository, the HEAD
+    /// is unaltered and an error is returned.
+    ///
+    /// If the provided commitish cannot be peeled into a commit, the HEAD is
+    /// unaltered and an error is returned.
+    ///
+    /// Otherwise, the HEAD will eventually be detached and will directly point
+    /// to the peeled commit.
+    pub fn set_head_detached(&self, commitish: Oid) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_repository_set_head_detached(self.raw,
+                                                            commitish.raw()));
+        }
+        Ok(())
+    }
+
     /// Create an iterator for the repo's references
     pub fn references(&self) -> Result<References, Error> {
         let mut ret = 0 as *mut raw::git_reference_iterator;
@@ -401,6 +825,102 @@ impl Repository {
         }
     }
 
+    /// Replace the object `original` with `replacement`, like
+    /// `git replace`.
+    ///
+    /// This creates the reference `refs/replace/<original>` pointing at
+    /// `replacement`, which must be an object of the same type. An existing
+    /// replacement of `original` is only overwritten if `force` is set.
+    pub fn replace_object(&self, original: Oid, replacement: Oid, force: bool)
+                          -> Result<Reference, Error> {
+        if original == replacement {
+            return Err(Error::from_str("an object cannot replace itself"))
+        }
+        let a = try!(self.find_object(original, None));
+        let b = try!(self.find_object(replacement, None));
+        if a.kind() != b.kind() {
+            return Err(Error::from_str("an object can only be replaced by an \
+                                        object of the same type"))
+        }
+        let name = format!("{}{}", REPLACE_REF_BASE, original);
+        let msg = format!("replace: {} with {}", original, replacement);
+        self.reference(name.as_slice(), replacement, force, msg.as_slice())
+    }
+
+    /// Remove the replacement of the object `original`.
+    pub fn delete_replacement(&self, original: Oid) -> Result<(), Error> {
+        let name = format!("{}{}", REPLACE_REF_BASE, original);
+        try!(self.find_reference(name.as_slice())).delete()
+    }
+
+    /// List the objects which are replaced along with their replacements,
+    /// as pairs of the original and the replacement.
+    ///
+    /// References under `refs/replace/` which are not named after an object
+    /// id are ignored, as git does.
+    pub fn replacements(&self) -> Result<Vec<(Oid, Oid)>, Error> {
+        let glob = format!("{}*", REPLACE_REF_BASE);
+        let mut ret = Vec::new();
+        for r in try!(self.references_glob(glob.as_slice())) {
+            let original = r.name().and_then(|name| {
+                let hex = &name[REPLACE_REF_BASE.len()..];
+                if hex.len() == 40 { Oid::from_str(hex).ok() } else { None }
+            });
+            match (original, r.target()) {
+                (Some(original), Some(replacement)) => {
+                    ret.push((original, replacement));
+                }
+                _ => {}
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Get the id of the object which replaces the object `id`, following
+    /// chains of replacements, or `id` itself if it is not replaced.
+    ///
+    /// libgit2 never looks at replacements on its own, so lookups such as
+    /// `find_commit` and revwalks always see the original objects; ids must
+    /// be passed through this to see the grafted history instead. As with
+    /// git, replacements are not followed if `core.useReplaceRefs` is false
+    /// or `GIT_NO_REPLACE_OBJECTS` is set in the environment, nor if the
+    /// repository was opened with `REPOSITORY_OPEN_NO_REPLACE_OBJECTS`.
+    pub fn replacement_of(&self, id: Oid) -> Result<Oid, Error> {
+        if !try!(self.uses_replace_refs()) {
+            return Ok(id)
+        }
+
+        let mut cur = id;
+        // the same limit on the length of a chain as git
+        for _ in range(0, 5) {
+            let name = format!("{}{}", REPLACE_REF_BASE, cur);
+            match self.find_reference(name.as_slice()) {
+                Ok(r) => match r.target() {
+                    Some(next) => cur = next,
+                    None => return Ok(cur),
+                },
+                Err(ref e) if e.code() == ErrorCode::NotFound => {
+                    return Ok(cur)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(Error::from_str("replacement chain is too long"))
+    }
+
+    fn uses_replace_refs(&self) -> Result<bool, Error> {
+        if self.no_replace_objects ||
+           env::var("GIT_NO_REPLACE_OBJECTS").is_ok() {
+            return Ok(false)
+        }
+        let config = try!(self.config());
+        match config.get_bool("core.useReplaceRefs") {
+            Ok(b) => Ok(b),
+            Err(ref e) if e.code() == ErrorCode::NotFound => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Load all submodules for this repository and return them.
     pub fn submodules(&self) -> Result<Vec<Submodule>, Error> {
         struct Data<'a, 'b:'a> {
@@ -444,13 +964,7 @@ impl Repository {
     /// with no pathspec so that all files can be considered.
     pub fn statuses(&self, options: Option<&mut StatusOptions>)
                     -> Result<Statuses, Error> {
-        let mut ret = 0 as *mut raw::git_status_list;
-        unsafe {
-            try_call!(raw::git_status_list_new(&mut ret, self.raw,
-                                               options.map(|s| s.raw())
-                                                      .unwrap_or(0 as *const _)));
-            Ok(Binding::from_raw(ret))
-        }
+        status::list(self, options)
     }
 
     /// Test if the ignore rules apply to a given file.
@@ -471,6 +985,106 @@ impl Repository {
         Ok(ret != 0)
     }
 
+    /// Add ignore rules for a repository.
+    ///
+    /// The format of the rules is the same one of the .gitignore file, with
+    /// multiple rules separated by newlines. These rules are only kept in
+    /// memory for this `Repository` and are not written to disk; they apply
+    /// in addition to the rules from any .gitignore files.
+    pub fn add_ignore_rule(&self, rules: &str) -> Result<(), Error> {
+        let rules = CString::from_slice(rules.as_bytes());
+        unsafe {
+            try_call!(raw::git_ignore_add_rule(self.raw, rules));
+        }
+        Ok(())
+    }
+
+    /// Clear ignore rules that were explicitly added with `add_ignore_rule`.
+    ///
+    /// This resets the internal rules to the defaults, which ignore ".",
+    /// ".." and ".git".
+    pub fn clear_ignore_rules(&self) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_ignore_clear_internal_rules(self.raw));
+        }
+        Ok(())
+    }
+
+    /// Test if the ignore rules apply to a given path.
+    ///
+    /// This function checks the ignore rules (including those added with
+    /// `add_ignore_rule`) to see if they would apply to the given file. This
+    /// indicates if the file would be ignored regardless of whether the file
+    /// is already in the index or committed to the repository.
+    pub fn is_path_ignored(&self, path: &Path) -> Result<bool, Error> {
+        let mut ignored = 0 as c_int;
+        let path = CString::from_slice(path.as_vec());
+        unsafe {
+            try_call!(raw::git_ignore_path_is_ignored(&mut ignored, self.raw,
+                                                      path));
+        }
+        Ok(ignored != 0)
+    }
+
+    /// Look up the value of one git attribute for a path.
+    ///
+    /// Returns `None` if the attribute is not specified for the path, or if
+    /// its value is not valid utf-8. Boolean attributes are returned as
+    /// special values, which can be told apart with `AttrValue::from_string`.
+    ///
+    /// The value is copied out of the attribute cache of libgit2, which
+    /// frees it whenever the attributes files are reloaded.
+    pub fn get_attr(&self,
+                    path: &Path,
+                    name: &str,
+                    flags: AttrCheckFlags) -> Result<Option<String>, Error> {
+        Ok(try!(self.get_attr_bytes(path, name, flags))
+               .and_then(|a| String::from_utf8(a).ok()))
+    }
+
+    /// Look up the value of one git attribute for a path as bytes.
+    ///
+    /// Returns `None` if the attribute is not specified for the path.
+    pub fn get_attr_bytes(&self,
+                          path: &Path,
+                          name: &str,
+                          flags: AttrCheckFlags)
+                          -> Result<Option<Vec<u8>>, Error> {
+        let mut ret = 0 as *const c_char;
+        let path = CString::from_slice(path.as_vec());
+        let name = CString::from_slice(name.as_bytes());
+        unsafe {
+            try_call!(raw::git_attr_get(&mut ret, self.raw, flags.bits(),
+                                        path, name));
+            Ok(::opt_bytes(self, ret).map(|s| s.to_vec()))
+        }
+    }
+
+    /// Determine whether the given path is tracked by Git LFS, that is
+    /// whether its `filter` attribute is set to `lfs`.
+    ///
+    /// Files committed before the attribute was set may still hold their
+    /// real content, so their blobs should be checked with
+    /// `Blob::lfs_pointer` as well.
+    pub fn is_lfs_tracked(&self, path: &Path) -> Result<bool, Error> {
+        let flags = ::ATTR_CHECK_FILE_THEN_INDEX;
+        let filter = try!(self.get_attr(path, "filter", flags));
+        Ok(filter.as_ref().map(|s| s.as_slice()) == Some("lfs"))
+    }
+
+    /// Flush the gitattributes cache.
+    ///
+    /// Call this if you have reason to believe that the attributes files on
+    /// disk no longer match the cached contents of memory. This will cause
+    /// the attributes files to be reloaded the next time that an attribute
+    /// access function is called.
+    pub fn attr_cache_flush(&self) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_attr_cache_flush(self.raw));
+        }
+        Ok(())
+    }
+
     /// Get file status for a single file.
     ///
     /// This tries to get status for the filename that you give. If no files
@@ -507,6 +1121,20 @@ impl Repository {
         }
     }
 
+    /// Summarize every local branch, sorted by name: the commit it points
+    /// to, its upstream and how far ahead and behind of it it is, and
+    /// whether it is `HEAD`.
+    ///
+    /// Counting commits only walks the history between a branch and its
+    /// upstream, down to their merge base, and is done once for all branches
+    /// pointing at the same commit with the same upstream. The commit-graph
+    /// is used when it contains the commits, see `graph_ahead_behind_with`.
+    /// This stays cheap for repositories with many branches and a long
+    /// history.
+    pub fn branch_summaries(&self) -> Result<Vec<BranchSummary>, Error> {
+        branch::summaries(self)
+    }
+
     /// Get the Index file for this repository.
     ///
     /// If a custom index has not been set, the default index for the repository
@@ -519,6 +1147,95 @@ impl Repository {
         }
     }
 
+    /// Get the object database for this repository.
+    pub fn odb(&self) -> Result<Odb, Error> {
+        let mut raw = 0 as *mut raw::git_odb;
+        unsafe {
+            try_call!(raw::git_repository_odb(&mut raw, self.raw()));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
+    /// Read the object directories listed in `objects/info/alternates`,
+    /// from which this repository borrows objects.
+    ///
+    /// Relative entries are resolved against the repository's own `objects`
+    /// directory. Alternates added with `Odb::add_disk_alternate` are not
+    /// included, as they are not recorded on disk.
+    pub fn alternates(&self) -> Result<Vec<Path>, Error> {
+        let objects = match self.commondir() {
+            Some(dir) => dir.join("objects"),
+            None => return Ok(Vec::new()),
+        };
+        let file = objects.join("info/alternates");
+        if !file.exists() { return Ok(Vec::new()) }
+        let contents = try!(File::open(&file).read_to_end().map_err(io_error));
+        Ok(contents.as_slice().split(|b| *b == b'\n').filter(|line| {
+            !line.is_empty() && line[0] != b'#'
+        }).map(|line| objects.join(line)).collect())
+    }
+
+    /// Get the path at which the hook with the given name, such as
+    /// `pre-commit`, would be found.
+    ///
+    /// This is the `hooks` directory of the common directory, shared by all
+    /// worktrees, unless the `core.hooksPath` configuration option is set.
+    /// Like git, a leading `~/` in `core.hooksPath` is expanded to the home
+    /// directory, and a relative path is taken relative to the working
+    /// directory, or to the repository itself if it is bare. The hook itself
+    /// may not exist.
+    pub fn hook_path(&self, name: &str) -> Result<Path, Error> {
+        let config = try!(self.config());
+        let dir = match config.get_path("core.hooksPath") {
+            Ok(dir) => self.hook_cwd().join(dir),
+            Err(ref e) if e.code() == ErrorCode::NotFound => {
+                match self.commondir() {
+                    Some(dir) => dir.join("hooks"),
+                    None => {
+                        return Err(Error::from_str("repository has no path"))
+                    }
+                }
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(dir.join(name))
+    }
+
+    /// Run the hook with the given name the same way git would, passing it
+    /// `args` and feeding it `stdin`, if any.
+    ///
+    /// The hook is run from the working directory, or from the repository
+    /// itself if it is bare, and its output is captured. Returns `None` if
+    /// the hook does not exist or is not executable, in which case git
+    /// silently skips it. A hook exiting unsuccessfully is not an error; the
+    /// caller is expected to inspect the status of the returned output.
+    pub fn run_hook(&self, name: &str, args: &[&str], stdin: Option<&[u8]>)
+                    -> Result<Option<ProcessOutput>, Error> {
+        let path = try!(self.hook_path(name));
+        match path.stat() {
+            Ok(ref stat) if stat.kind == old_io::FileType::RegularFile &&
+                            stat.perm.contains(old_io::USER_EXECUTE) => {}
+            _ => return Ok(None),
+        }
+        let mut p = try!(Command::new(&path).args(args).cwd(&self.hook_cwd())
+                                            .spawn().map_err(io_error));
+        // Feed stdin from another thread while the output is read, so that a
+        // hook writing a lot before reading its input cannot deadlock
+        let mut input = p.stdin.take().unwrap();
+        let _writer = thread::scoped(move || {
+            // Ignore write errors as the hook may exit without reading stdin
+            match stdin {
+                Some(data) => { let _ = input.write_all(data); }
+                None => {}
+            }
+        });
+        p.wait_with_output().map(Some).map_err(io_error)
+    }
+
+    fn hook_cwd(&self) -> Path {
+        self.workdir().unwrap_or_else(|| self.path())
+    }
+
     /// Get the configuration file for this repository.
     ///
     /// If a configuration file has not been set, the default config set for the
@@ -562,6 +1279,57 @@ impl Repository {
         }
     }
 
+    /// Like `blob_path`, but convert the line endings of the file according
+    /// to `opts` rather than the configuration of the repository.
+    ///
+    /// With `SafeCrlf::Fail`, a file whose conversion is not reversible
+    /// returns an error. The `text` and `eol` attributes still take
+    /// precedence, as they do in git.
+    pub fn blob_path_with_eol(&self, path: &Path, opts: &EolOptions)
+                              -> Result<Oid, Error> {
+        try!(eol::repository(self, opts)).blob_path(path)
+    }
+
+    /// Calculate the id an object of type `kind` would have with the contents
+    /// of the file at `path`, without writing anything to the object database.
+    ///
+    /// The filters which apply to `as_path`, or to `path` itself if `None`, are
+    /// applied first, such as CRLF conversion, so the result can be compared
+    /// against the id of a blob in the index to tell whether a file in the
+    /// working directory has been modified. An empty `as_path` applies no
+    /// filters at all.
+    ///
+    /// A relative `path` is taken relative to the working directory.
+    pub fn hashfile(&self, path: &Path, kind: ObjectType,
+                    as_path: Option<&Path>) -> Result<Oid, Error> {
+        let path = CString::from_slice(path.as_vec());
+        let as_path = as_path.map(|p| CString::from_slice(p.as_vec()));
+        let mut raw = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        unsafe {
+            try_call!(raw::git_repository_hashfile(&mut raw, self.raw(), path,
+                                                   kind, as_path));
+            Ok(Binding::from_raw(&raw as *const _))
+        }
+    }
+
+    /// Find which commit last changed each line of the file at `path`, given
+    /// relative to the working directory.
+    ///
+    /// The file is blamed as of the newest commit of the options, which is
+    /// HEAD by default. Lines are followed across renames, and the path each
+    /// line had in the commit it was found in is reported by
+    /// `BlameHunk::orig_path`.
+    pub fn blame_file(&self, path: &Path, opts: Option<&mut BlameOptions>)
+                      -> Result<Blame, Error> {
+        let path = CString::from_slice(path.as_vec());
+        let mut raw = 0 as *mut raw::git_blame;
+        unsafe {
+            try_call!(raw::git_blame_file(&mut raw, self.raw(), path,
+                                          opts.map(blame::raw_options)));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
     /// Lookup a reference to one of the objects in a repository.
     pub fn find_blob(&self, oid: Oid) -> Result<Blob, Error> {
         let mut raw = 0 as *mut raw::git_blob;
@@ -579,20 +1347,15 @@ impl Repository {
     pub fn branch(&self,
                   branch_name: &str,
                   target: &Commit,
-                  force: bool,
-                  signature: Option<&Signature>,
-                  log_message: Option<&str>) -> Result<Branch, Error> {
+                  force: bool) -> Result<Branch, Error> {
         let branch_name = CString::from_slice(branch_name.as_bytes());
-        let log_message = log_message.map(|s| CString::from_slice(s.as_bytes()));
         let mut raw = 0 as *mut raw::git_reference;
         unsafe {
             try_call!(raw::git_branch_create(&mut raw,
                                              self.raw(),
                                              branch_name,
                                              target.raw(),
-                                             force,
-                                             signature.map(|s| s.raw()),
-                                             log_message));
+                                             force));
             Ok(Branch::wrap(Binding::from_raw(raw)))
         }
     }
@@ -609,12 +1372,66 @@ impl Repository {
         }
     }
 
-    /// Create new commit in the repository
+    /// Get the full name of the remote tracking branch of the local branch
+    /// with the given full reference name, such as `refs/remotes/origin/foo`
+    /// for `refs/heads/foo`.
+    pub fn branch_upstream_name(&self, refname: &str) -> Result<Buf, Error> {
+        let refname = CString::from_slice(refname.as_bytes());
+        unsafe {
+            let buf = Buf::new();
+            try_call!(raw::git_branch_upstream_name(buf.raw(), self.raw,
+                                                    refname));
+            Ok(buf)
+        }
+    }
+
+    /// Get the name of the remote which the remote tracking branch with the
+    /// given full reference name belongs to, such as `origin` for
+    /// `refs/remotes/origin/foo`.
     ///
-    /// If the `update_ref` is not `None`, name of the reference that will be
-    /// updated to point to this commit. If the reference is not direct, it will
-    /// be resolved to a direct reference. Use "HEAD" to update the HEAD of the
-    /// current branch and make it point to this commit. If the reference
+    /// An error is returned if no remote, or more than one, has a fetch
+    /// refspec matching the reference.
+    pub fn branch_remote_name(&self, refname: &str) -> Result<Buf, Error> {
+        let refname = CString::from_slice(refname.as_bytes());
+        unsafe {
+            let buf = Buf::new();
+            try_call!(raw::git_branch_remote_name(buf.raw(), self.raw,
+                                                  refname));
+            Ok(buf)
+        }
+    }
+
+    /// Get the name of the remote which the local branch with the given full
+    /// reference name tracks, as configured by `branch.<name>.remote`.
+    pub fn branch_upstream_remote(&self, refname: &str) -> Result<Buf, Error> {
+        let refname = CString::from_slice(refname.as_bytes());
+        unsafe {
+            let buf = Buf::new();
+            try_call!(raw::git_branch_upstream_remote(buf.raw(), self.raw,
+                                                      refname));
+            Ok(buf)
+        }
+    }
+
+    /// Get the name of the reference on the remote which the local branch
+    /// with the given full reference name tracks, as configured by
+    /// `branch.<name>.merge`.
+    pub fn branch_upstream_merge(&self, refname: &str) -> Result<Buf, Error> {
+        let refname = CString::from_slice(refname.as_bytes());
+        unsafe {
+            let buf = Buf::new();
+            try_call!(raw::git_branch_upstream_merge(buf.raw(), self.raw,
+                                                     refname));
+            Ok(buf)
+        }
+    }
+
+    /// Create new commit in the repository
+    ///
+    /// If the `update_ref` is not `None`, name of the reference that will be
+    /// updated to point to this commit. If the reference is not direct, it will
+    /// be resolved to a direct reference. Use "HEAD" to update the HEAD of the
+    /// current branch and make it point to this commit. If the reference
     /// doesn't exist yet, it will be created. If it does exist, the first
     /// parent must be the tip of this branch.
     pub fn commit(&self,
@@ -645,9 +1462,131 @@ impl Repository {
         }
     }
 
+    /// Create a commit object and return its raw content, without writing it
+    /// to the object database.
+    ///
+    /// The arguments are the same as for `commit`. The returned buffer can be
+    /// signed and then written with `commit_signed`.
+    pub fn commit_create_buffer(&self,
+                                author: &Signature,
+                                committer: &Signature,
+                                message: &str,
+                                tree: &Tree,
+                                parents: &[&Commit]) -> Result<Buf, Error> {
+        let parent_ptrs: Vec<*const raw::git_commit> =  parents.iter().map(|p| {
+            p.raw() as *const raw::git_commit
+        }).collect();
+        let message = CString::from_slice(message.as_bytes());
+        let buf = Buf::new();
+        unsafe {
+            try_call!(raw::git_commit_create_buffer(buf.raw(),
+                                                    self.raw(),
+                                                    author.raw(),
+                                                    committer.raw(),
+                                                    0 as *const c_char,
+                                                    message,
+                                                    tree.raw(),
+                                                    parents.len() as size_t,
+                                                    parent_ptrs.as_ptr()));
+        }
+        Ok(buf)
+    }
+
+    /// Create a commit object from the given buffer and signature.
+    ///
+    /// The `commit_content` is typically created with
+    /// `commit_create_buffer`, and the `signature` is inserted into the
+    /// commit in the header named by `signature_field`, which defaults to
+    /// "gpgsig". No references are updated.
+    pub fn commit_signed(&self,
+                         commit_content: &str,
+                         signature: &str,
+                         signature_field: Option<&str>) -> Result<Oid, Error> {
+        let commit_content = CString::from_slice(commit_content.as_bytes());
+        let signature = CString::from_slice(signature.as_bytes());
+        let signature_field = signature_field.map(|s| {
+            CString::from_slice(s.as_bytes())
+        });
+        let mut raw = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        unsafe {
+            try_call!(raw::git_commit_create_with_signature(&mut raw,
+                                                            self.raw(),
+                                                            commit_content,
+                                                            signature,
+                                                            signature_field));
+            Ok(Binding::from_raw(&raw as *const _))
+        }
+    }
+
+    /// Create a new signed commit in the repository.
+    ///
+    /// This creates the commit content as `commit_create_buffer` does, hands
+    /// it to `signer` to produce a signature (for example an ASCII-armored
+    /// GPG or SSH signature), and writes the signed commit with
+    /// `commit_signed` using the default "gpgsig" header. No references are
+    /// updated.
+    pub fn commit_signed_with<F>(&self,
+                                 author: &Signature,
+                                 committer: &Signature,
+                                 message: &str,
+                                 tree: &Tree,
+                                 parents: &[&Commit],
+                                 mut signer: F) -> Result<Oid, Error>
+        where F: FnMut(&str) -> Result<String, Error>
+    {
+        let buf = try!(self.commit_create_buffer(author, committer, message,
+                                                 tree, parents));
+        let content = try!(buf.as_str().ok_or_else(|| {
+            Error::from_str("commit content is not valid utf-8")
+        }));
+        let signature = try!(signer(content));
+        self.commit_signed(content, signature.as_slice(), None)
+    }
+
+    /// Extract the signature of a commit along with the data it signs, the
+    /// commit without its signature header.
+    ///
+    /// The signature is read from the header named by `signature_field`,
+    /// which defaults to "gpgsig". An error with the code
+    /// `ErrorCode::NotFound` is returned if the commit is not signed.
+    pub fn extract_signature(&self, id: Oid, signature_field: Option<&str>)
+                             -> Result<(Buf, Buf), Error> {
+        let signature_field = signature_field.map(|s| {
+            CString::from_slice(s.as_bytes())
+        });
+        let signature = Buf::new();
+        let signed_data = Buf::new();
+        unsafe {
+            try_call!(raw::git_commit_extract_signature(signature.raw(),
+                                                        signed_data.raw(),
+                                                        self.raw(),
+                                                        id.raw() as *mut _,
+                                                        signature_field));
+        }
+        Ok((signature, signed_data))
+    }
+
+    /// Verify the signature of a commit.
+    ///
+    /// The signature and the data it signs are extracted as with
+    /// `extract_signature` and handed to `verifier`, which checks them, for
+    /// example with `gpg` or `ssh_keygen_verify`, and reports the outcome.
+    /// An error with the code `ErrorCode::NotFound` is returned if the commit
+    /// is not signed.
+    pub fn verify_commit_signature<F>(&self, id: Oid, mut verifier: F)
+                                      -> Result<SignatureVerification, Error>
+        where F: FnMut(&[u8], &[u8]) -> Result<SignatureVerification, Error>
+    {
+        let (signature, signed_data) = try!(self.extract_signature(id, None));
+        verifier(&*signature, &*signed_data)
+    }
 
     /// Lookup a reference to one of the commits in a repository.
     pub fn find_commit(&self, oid: Oid) -> Result<Commit, Error> {
+        if self.object_cache.borrow().is_some() {
+            let raw = try!(self.find_cached(oid, ObjectType::Commit));
+            return Ok(unsafe { Binding::from_raw(raw as *mut raw::git_commit) })
+        }
         let mut raw = 0 as *mut raw::git_commit;
         unsafe {
             try_call!(raw::git_commit_lookup(&mut raw, self.raw(), oid.raw()));
@@ -655,6 +1594,59 @@ impl Repository {
         }
     }
 
+    /// Attach a cache of parsed commits and trees to this repository, which
+    /// `find_commit` and `find_tree` look objects up in first.
+    ///
+    /// The cache attached before, if any, is detached and returned. The
+    /// objects in it are released, but its statistics are kept.
+    pub fn set_object_cache(&mut self, cache: Option<ObjectCache>)
+                            -> Option<ObjectCache> {
+        let mut old = mem::replace(&mut *self.object_cache.borrow_mut(),
+                                   cache);
+        match old {
+            Some(ref mut old) => old.clear(),
+            None => {}
+        }
+        old
+    }
+
+    // Look an object up in the attached object cache, adding it to the cache
+    // if it is not there yet.
+    fn find_cached(&self, oid: Oid, kind: ObjectType)
+                   -> Result<*mut raw::git_object, Error> {
+        let mut cache = self.object_cache.borrow_mut();
+        let cache = cache.as_mut().unwrap();
+        match object_cache::get(cache, oid, kind) {
+            Some(raw) => return Ok(raw),
+            None => {}
+        }
+        let mut raw = 0 as *mut raw::git_object;
+        unsafe {
+            try_call!(raw::git_object_lookup(&mut raw, self.raw(), oid.raw(),
+                                             kind));
+        }
+        object_cache::insert(cache, oid, raw);
+        Ok(raw)
+    }
+
+    /// Lookup a reference to one of the commits in a repository by an
+    /// abbreviated hex id.
+    ///
+    /// The `prefix` must be at least 4 characters long, and it must uniquely
+    /// identify a single object in the repository, otherwise an error is
+    /// returned.
+    pub fn find_commit_by_prefix(&self, prefix: &str)
+                                 -> Result<Commit, Error> {
+        let id = try!(Oid::from_str(prefix));
+        let mut raw = 0 as *mut raw::git_commit;
+        unsafe {
+            try_call!(raw::git_commit_lookup_prefix(&mut raw, self.raw(),
+                                                    id.raw(),
+                                                    prefix.len() as size_t));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
     /// Lookup a reference to one of the objects in a repository.
     pub fn find_object(&self, oid: Oid,
                        kind: Option<ObjectType>) -> Result<Object, Error> {
@@ -666,13 +1658,31 @@ impl Repository {
         }
     }
 
+    /// Lookup an object in the repository by an abbreviated hex id.
+    ///
+    /// The `prefix` must be at least 4 characters long, and it must uniquely
+    /// identify a single object in the repository, otherwise an error is
+    /// returned.
+    pub fn find_object_by_prefix(&self, prefix: &str,
+                                 kind: Option<ObjectType>)
+                                 -> Result<Object, Error> {
+        let id = try!(Oid::from_str(prefix));
+        let mut raw = 0 as *mut raw::git_object;
+        unsafe {
+            try_call!(raw::git_object_lookup_prefix(&mut raw, self.raw(),
+                                                    id.raw(),
+                                                    prefix.len() as size_t,
+                                                    kind));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
     /// Create a new direct reference.
     ///
     /// This function will return an error if a reference already exists with
     /// the given name unless force is true, in which case it will be
     /// overwritten.
     pub fn reference(&self, name: &str, id: Oid, force: bool,
-                     sig: Option<&Signature>,
                      log_message: &str) -> Result<Reference, Error> {
         let name = CString::from_slice(name.as_bytes());
         let log_message = CString::from_slice(log_message.as_bytes());
@@ -680,7 +1690,6 @@ impl Repository {
         unsafe {
             try_call!(raw::git_reference_create(&mut raw, self.raw(), name,
                                                 id.raw(), force,
-                                                sig.map(|s| s.raw()),
                                                 log_message));
             Ok(Binding::from_raw(raw))
         }
@@ -692,8 +1701,7 @@ impl Repository {
     /// the given name unless force is true, in which case it will be
     /// overwritten.
     pub fn reference_symbolic(&self, name: &str, target: &str,
-                              force: bool, sig: Option<&Signature>,
-                              log_message: &str)
+                              force: bool, log_message: &str)
                               -> Result<Reference, Error> {
         let name = CString::from_slice(name.as_bytes());
         let target = CString::from_slice(target.as_bytes());
@@ -702,7 +1710,6 @@ impl Repository {
         unsafe {
             try_call!(raw::git_reference_symbolic_create(&mut raw, self.raw(),
                                                          name, target, force,
-                                                         sig.map(|s| s.raw()),
                                                          log_message));
             Ok(Binding::from_raw(raw))
         }
@@ -710,7 +1717,13 @@ impl Repository {
 
     /// Lookup a reference to one of the objects in a repository.
     pub fn find_reference(&self, name: &str) -> Result<Reference, Error> {
-        let name = CString::from_slice(name.as_bytes());
+        self.find_reference_bytes(name.as_bytes())
+    }
+
+    /// Lookup a reference by a name which is not necessarily valid utf-8.
+    pub fn find_reference_bytes(&self, name: &[u8])
+                                -> Result<Reference, Error> {
+        let name = CString::from_slice(name);
         let mut raw = 0 as *mut raw::git_reference;
         unsafe {
             try_call!(raw::git_reference_lookup(&mut raw, self.raw(), name));
@@ -724,7 +1737,13 @@ impl Repository {
     /// through to the object id that it refers to. This avoids having to
     /// allocate or free any `Reference` objects for simple situations.
     pub fn refname_to_id(&self, name: &str) -> Result<Oid, Error> {
-        let name = CString::from_slice(name.as_bytes());
+        self.refname_to_id_bytes(name.as_bytes())
+    }
+
+    /// Lookup a reference by a name which is not necessarily valid utf-8 and
+    /// resolve it immediately to an OID.
+    pub fn refname_to_id_bytes(&self, name: &[u8]) -> Result<Oid, Error> {
+        let name = CString::from_slice(name);
         let mut ret = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
         unsafe {
             try_call!(raw::git_reference_name_to_id(&mut ret, self.raw(), name));
@@ -783,8 +1802,67 @@ impl Repository {
         }
     }
 
+    /// Get the status of the submodule with the given name or path.
+    ///
+    /// The `ignore` rule overrides the one configured for the submodule for
+    /// this query only, and controls how much of the submodule's working
+    /// directory is examined; see `SubmoduleStatus` for which flags can be
+    /// returned under each rule.
+    pub fn submodule_status(&self, name: &str, ignore: SubmoduleIgnore)
+                            -> Result<SubmoduleStatus, Error> {
+        let name = CString::from_slice(name.as_bytes());
+        let mut ret = 0;
+        unsafe {
+            try_call!(raw::git_submodule_status(&mut ret, self.raw, name,
+                                                ignore));
+        }
+        Ok(SubmoduleStatus::from_bits_truncate(ret as u32))
+    }
+
+    /// List the names of the linked worktrees of this repository.
+    pub fn worktrees(&self) -> Result<StringArray, Error> {
+        let mut arr = raw::git_strarray {
+            strings: 0 as *mut *mut c_char,
+            count: 0,
+        };
+        unsafe {
+            try_call!(raw::git_worktree_list(&mut arr, self.raw));
+            Ok(Binding::from_raw(arr))
+        }
+    }
+
+    /// Lookup a linked worktree of this repository by its name.
+    pub fn find_worktree(&self, name: &str) -> Result<Worktree, Error> {
+        let name = CString::from_slice(name.as_bytes());
+        let mut raw = 0 as *mut raw::git_worktree;
+        unsafe {
+            try_call!(raw::git_worktree_lookup(&mut raw, self.raw, name));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
+    /// Add a new linked worktree with the given name, checking out a new
+    /// branch of the same name at `path`.
+    ///
+    /// This is the equivalent of `git worktree add -b <name> <path>`.
+    pub fn worktree(&self, name: &str, path: &Path)
+                    -> Result<Worktree, Error> {
+        let name = CString::from_slice(name.as_bytes());
+        let path = CString::from_slice(path.as_vec());
+        let mut raw = 0 as *mut raw::git_worktree;
+        unsafe {
+            try_call!(raw::git_worktree_add(&mut raw, self.raw, name, path,
+                                            0 as *const _));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
     /// Lookup a reference to one of the objects in a repository.
     pub fn find_tree(&self, oid: Oid) -> Result<Tree, Error> {
+        if self.object_cache.borrow().is_some() {
+            let raw = try!(self.find_cached(oid, ObjectType::Tree));
+            return Ok(unsafe { Binding::from_raw(raw as *mut raw::git_tree) })
+        }
         let mut raw = 0 as *mut raw::git_tree;
         unsafe {
             try_call!(raw::git_tree_lookup(&mut raw, self.raw(), oid.raw()));
@@ -792,6 +1870,23 @@ impl Repository {
         }
     }
 
+    /// Create a new TreeBuilder, optionally initialized with the
+    /// entries of the given Tree.
+    ///
+    /// The tree builder can be used to create or modify trees in memory and
+    /// write them as tree objects to the database.
+    pub fn treebuilder(&self, tree: Option<&Tree>) -> Result<TreeBuilder, Error> {
+        unsafe {
+            let mut ret = 0 as *mut raw::git_treebuilder;
+            let tree = match tree {
+                Some(tree) => tree.raw() as *const _,
+                None => 0 as *const raw::git_tree,
+            };
+            try_call!(raw::git_treebuilder_new(&mut ret, self.raw, tree));
+            Ok(Binding::from_raw(ret))
+        }
+    }
+
     /// Create a new tag in the repository from an object
     ///
     /// A new reference will also be created pointing to this tag object. If
@@ -880,6 +1975,8 @@ impl Repository {
     /// the commit pointed at by HEAD.
     pub fn checkout_head(&self, opts: Option<&mut CheckoutBuilder>)
                          -> Result<(), Error> {
+        let eol = try!(self.checkout_repo(&opts));
+        let repo = eol.as_ref().unwrap_or(self);
         unsafe {
             let mut raw_opts = mem::zeroed();
             try_call!(raw::git_checkout_init_options(&mut raw_opts,
@@ -889,17 +1986,24 @@ impl Repository {
                 None => {}
             }
 
-            try_call!(raw::git_checkout_head(self.raw, &raw_opts));
+            try_call!(raw::git_checkout_head(repo.raw, &raw_opts));
         }
         Ok(())
     }
 
     /// Updates files in the working tree to match the content of the index.
     ///
-    /// If the index is `None`, the repository's index will be used.
+    /// If the index is `None`, the repository's index will be used. An
+    /// explicit index cannot be combined with `CheckoutBuilder::eol_options`.
     pub fn checkout_index(&self,
                           index: Option<&mut Index>,
                           opts: Option<&mut CheckoutBuilder>) -> Result<(), Error> {
+        let eol = try!(self.checkout_repo(&opts));
+        if eol.is_some() && index.is_some() {
+            return Err(Error::from_str("line ending options cannot be used \
+                                        with an explicit index"))
+        }
+        let repo = eol.as_ref().unwrap_or(self);
         unsafe {
             let mut raw_opts = mem::zeroed();
             try_call!(raw::git_checkout_init_options(&mut raw_opts,
@@ -909,7 +2013,7 @@ impl Repository {
                 None => {}
             }
 
-            try_call!(raw::git_checkout_index(self.raw,
+            try_call!(raw::git_checkout_index(repo.raw,
                                               index.map(|i| &mut *i.raw()),
                                               &raw_opts));
         }
@@ -921,6 +2025,15 @@ impl Repository {
     pub fn checkout_tree(&self,
                          treeish: &Object,
                          opts: Option<&mut CheckoutBuilder>) -> Result<(), Error> {
+        let eol = try!(self.checkout_repo(&opts));
+        // libgit2 requires the treeish to come from the repository it checks
+        // out, so look it up again in the handle with the line ending options
+        let other = match eol {
+            Some(ref repo) => Some(try!(repo.find_object(treeish.id(), None))),
+            None => None,
+        };
+        let repo = eol.as_ref().unwrap_or(self);
+        let treeish = other.as_ref().unwrap_or(treeish);
         unsafe {
             let mut raw_opts = mem::zeroed();
             try_call!(raw::git_checkout_init_options(&mut raw_opts,
@@ -930,12 +2043,20 @@ impl Repository {
                 None => {}
             }
 
-            try_call!(raw::git_checkout_tree(self.raw, &*treeish.raw(),
+            try_call!(raw::git_checkout_tree(repo.raw, &*treeish.raw(),
                                              &raw_opts));
         }
         Ok(())
     }
 
+    fn checkout_repo(&self, opts: &Option<&mut CheckoutBuilder>)
+                     -> Result<Option<Repository>, Error> {
+        match opts.as_ref().and_then(|c| c.get_eol_options()) {
+            Some(eol) => eol::repository(self, eol).map(Some),
+            None => Ok(None),
+        }
+    }
+
     /// Add a note for an object
     ///
     /// The `notes_ref` argument is the canonical name of the reference to use,
@@ -965,12 +2086,15 @@ impl Repository {
     }
 
     /// Get the default notes reference for this repository
-    pub fn note_default_ref(&self) -> Result<&str, Error> {
-        let mut ret = 0 as *const c_char;
+    ///
+    /// This is the value of the `core.notesRef` configuration option, or
+    /// "refs/notes/commits" if it is not set.
+    pub fn note_default_ref(&self) -> Result<String, Error> {
+        let ret = Buf::new();
         unsafe {
-            try_call!(raw::git_note_default_ref(&mut ret, self.raw));
-            Ok(str::from_utf8(::opt_bytes(self, ret).unwrap()).unwrap())
+            try_call!(raw::git_note_default_ref(ret.raw(), self.raw));
         }
+        Ok(ret.as_str().unwrap().to_string())
     }
 
     /// Creates a new iterator for notes in this repository.
@@ -1026,6 +2150,202 @@ impl Repository {
         }
     }
 
+    /// Add a note for an object, creating a new notes commit on top of
+    /// `parent` without updating any reference.
+    ///
+    /// If `parent` is `None` the notes commit is a root commit. The ids of
+    /// the new notes commit and of the note's blob are returned, in that
+    /// order; it is up to the caller to point a notes reference at the
+    /// commit, for example as part of its own reference transaction.
+    pub fn note_commit_create(&self,
+                              parent: Option<&Commit>,
+                              author: &Signature,
+                              committer: &Signature,
+                              oid: Oid,
+                              note: &str,
+                              force: bool) -> Result<(Oid, Oid), Error> {
+        let note = CString::from_slice(note.as_bytes());
+        let parent = parent.map(|c| c.raw()).unwrap_or(0 as *mut _);
+        let mut commit = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        let mut blob = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        unsafe {
+            try_call!(raw::git_note_commit_create(&mut commit,
+                                                  &mut blob,
+                                                  self.raw,
+                                                  parent,
+                                                  author.raw(),
+                                                  committer.raw(),
+                                                  oid.raw(),
+                                                  note,
+                                                  force));
+            Ok((Binding::from_raw(&commit as *const _),
+                Binding::from_raw(&blob as *const _)))
+        }
+    }
+
+    /// Read the note for an object from the given notes commit rather than
+    /// from a notes reference.
+    pub fn note_commit_read(&self, notes_commit: &Commit, id: Oid)
+                            -> Result<Note, Error> {
+        let mut ret = 0 as *mut raw::git_note;
+        unsafe {
+            try_call!(raw::git_note_commit_read(&mut ret, self.raw,
+                                                notes_commit.raw(),
+                                                id.raw()));
+            Ok(Binding::from_raw(ret))
+        }
+    }
+
+    /// Remove the note for an object by creating a new notes commit on top
+    /// of `notes_commit`, without updating any reference.
+    ///
+    /// The id of the new notes commit is returned.
+    pub fn note_commit_remove(&self,
+                              notes_commit: &Commit,
+                              author: &Signature,
+                              committer: &Signature,
+                              id: Oid) -> Result<Oid, Error> {
+        let mut ret = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        unsafe {
+            try_call!(raw::git_note_commit_remove(&mut ret, self.raw,
+                                                  notes_commit.raw(),
+                                                  author.raw(),
+                                                  committer.raw(),
+                                                  id.raw()));
+            Ok(Binding::from_raw(&ret as *const _))
+        }
+    }
+
+    /// Pack all loose references of this repository into the `packed-refs`
+    /// file, like `git pack-refs --all`.
+    ///
+    /// This keeps the number of files under `refs` manageable for
+    /// repositories with many references.
+    pub fn pack_refs(&self) -> Result<(), Error> {
+        let mut refdb = 0 as *mut raw::git_refdb;
+        unsafe {
+            try_call!(raw::git_repository_refdb(&mut refdb, self.raw()));
+            let rc = raw::git_refdb_compress(refdb);
+            raw::git_refdb_free(refdb);
+            try!(::call::try(rc));
+        }
+        Ok(())
+    }
+
+    /// Create a new pack builder which packs objects of this repository.
+    pub fn packbuilder(&self) -> Result<PackBuilder, Error> {
+        let mut raw = 0 as *mut raw::git_packbuilder;
+        unsafe {
+            try_call!(raw::git_packbuilder_new(&mut raw, self.raw()));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
+    /// Write an archive of a tree to `out`, like `git archive`.
+    ///
+    /// Entries with the `export-ignore` attribute are left out of the
+    /// archive, and if a commit is set in the options the `$Format:...$`
+    /// placeholders of files with the `export-subst` attribute are expanded.
+    /// Passing `None` writes a tar archive without any prefix.
+    pub fn archive(&self, tree: &Tree, out: &mut Writer,
+                   opts: Option<&mut ArchiveOptions>) -> Result<(), Error> {
+        let mut default = ArchiveOptions::new();
+        let opts = opts.unwrap_or(&mut default);
+        ::archive::archive(self, tree, out, &*opts)
+    }
+
+    /// Pack all objects reachable from the references of this repository and
+    /// `HEAD` into a single new pack in `objects/pack`, similar to a basic
+    /// `git repack -a`.
+    ///
+    /// If `prune_loose` is set, loose objects which are contained in the new
+    /// pack are deleted afterwards, like `git prune-packed`. Other loose
+    /// objects and existing packs are left alone.
+    ///
+    /// Returns the hash which the new pack is named after, or `None` if there
+    /// was nothing to pack.
+    pub fn pack_objects(&self, prune_loose: bool)
+                        -> Result<Option<Oid>, Error> {
+        let mut walk = try!(self.revwalk());
+        try!(walk.push_glob("*"));
+        match walk.push_head() {
+            Ok(()) => {}
+            Err(ref e) if e.code() == ErrorCode::UnbornBranch ||
+                          e.code() == ErrorCode::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let mut builder = try!(self.packbuilder());
+        for reference in try!(self.references()) {
+            let id = match reference.target() {
+                Some(id) => id,
+                None => continue,
+            };
+            let obj = try!(self.find_object(id, None));
+            if obj.kind() == Some(ObjectType::Tag) {
+                try!(builder.insert_object(id, None));
+            }
+        }
+        try!(builder.insert_walk(&mut walk));
+
+        let dir = match self.commondir() {
+            Some(dir) => dir.join("objects/pack"),
+            None => return Err(Error::from_str("repository has no path")),
+        };
+        try!(builder.write(&dir, 0));
+        let hash = match builder.hash() {
+            Some(hash) => hash,
+            None => return Ok(None),
+        };
+        if prune_loose {
+            let idx = dir.join(format!("pack-{}.idx", hash));
+            try!(self.prune_packed(&idx));
+        }
+        Ok(Some(hash))
+    }
+
+    // Delete the loose objects which are also stored in the given pack
+    fn prune_packed(&self, idx: &Path) -> Result<(), Error> {
+        let idx = CString::from_slice(idx.as_vec());
+        let mut odb = 0 as *mut raw::git_odb;
+        let mut backend = 0 as *mut raw::git_odb_backend;
+        let odb: Odb = unsafe {
+            try_call!(raw::git_odb_new(&mut odb));
+            let odb: Odb = Binding::from_raw(odb);
+            try_call!(raw::git_odb_backend_one_pack(&mut backend, idx));
+            try_call!(raw::git_odb_add_backend(odb.raw(), backend, 1));
+            odb
+        };
+
+        let objects = match self.commondir() {
+            Some(dir) => dir.join("objects"),
+            None => return Err(Error::from_str("repository has no path")),
+        };
+        for dir in try!(fs::readdir(&objects).map_err(io_error)).iter() {
+            let prefix = match dir.filename_str() {
+                Some(s) if s.len() == 2 && dir.is_dir() => s,
+                _ => continue,
+            };
+            for file in try!(fs::readdir(dir).map_err(io_error)).iter() {
+                let id = match file.filename_str() {
+                    Some(s) if s.len() == 38 => {
+                        match Oid::from_str(format!("{}{}", prefix, s)
+                                                .as_slice()) {
+                            Ok(id) => id,
+                            Err(..) => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                if odb.exists(id) {
+                    try!(fs::unlink(file).map_err(io_error));
+                }
+            }
+            // only succeeds once the directory is empty
+            let _ = fs::rmdir(dir);
+        }
+        Ok(())
+    }
+
     /// Create a revwalk that can be used to traverse the commit graph.
     pub fn revwalk(&self) -> Result<Revwalk, Error> {
         let mut raw = 0 as *mut raw::git_revwalk;
@@ -1035,6 +2355,98 @@ impl Repository {
         }
     }
 
+    /// Write the commit-graph file of this repository, covering all commits
+    /// reachable from its references and `HEAD`.
+    ///
+    /// The file is used to speed up revision walks once `core.commitGraph`
+    /// is enabled in the repository's configuration.
+    pub fn write_commit_graph(&self) -> Result<(), Error> {
+        let mut walk = try!(self.revwalk());
+        try!(walk.push_glob("*"));
+        match walk.push_head() {
+            Ok(()) => {}
+            Err(ref e) if e.code() == ErrorCode::UnbornBranch ||
+                          e.code() == ErrorCode::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let dir = match self.commondir() {
+            Some(dir) => dir.join("objects/info"),
+            None => return Err(Error::from_str("repository has no path")),
+        };
+        let mut writer = try!(CommitGraphWriter::new(&dir));
+        try!(writer.add_revwalk(&mut walk));
+        writer.commit()
+    }
+
+    /// Read the commit-graph file of this repository, if it has one.
+    ///
+    /// As with git, the graph is looked for in the object directory shared
+    /// by all worktrees, and then in those of the alternates.
+    pub fn commit_graph(&self) -> Result<Option<CommitGraph>, Error> {
+        match try!(self.commit_graph_file()) {
+            Some(file) => CommitGraph::open(&file.dir_path()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn commit_graph_file(&self) -> Result<Option<Path>, Error> {
+        let objects = match self.commondir() {
+            Some(dir) => dir.join("objects"),
+            None => return Ok(None),
+        };
+        let mut dirs = vec![objects];
+        dirs.extend(try!(self.alternates()).into_iter());
+        Ok(dirs.into_iter().map(|dir| dir.join("info/commit-graph"))
+                           .find(|file| file.exists()))
+    }
+
+    // Answer a query from the commit-graph as the usage allows, or return
+    // `None` to leave it to libgit2.
+    //
+    // The graph is read once and kept until the file changes, so that many
+    // queries in a row, as for `branch_summaries`, don't read it again.
+    fn with_commit_graph<T, F>(&self, usage: CommitGraphUsage, f: F)
+                               -> Result<Option<T>, Error>
+        where F: FnOnce(&CommitGraph) -> Option<T>
+    {
+        if usage == CommitGraphUsage::Forbid {
+            return Ok(None)
+        }
+        // the graph records the parents from before any grafts or
+        // replacements
+        let usable = !self.is_shallow() &&
+                     !(try!(self.uses_replace_refs()) &&
+                       !try!(self.replacements()).is_empty());
+        let key = match try!(self.commit_graph_file()) {
+            Some(ref file) if usable => {
+                let stat = try!(file.stat().map_err(io_error));
+                Some((file.clone(), stat.size, stat.modified))
+            }
+            _ => None,
+        };
+        let mut cache = self.commit_graph.borrow_mut();
+        let fresh = match (&*cache, &key) {
+            (&Some((ref cached, _)), &Some(ref key)) => cached == key,
+            _ => false,
+        };
+        if !fresh {
+            *cache = match key {
+                Some(key) => {
+                    let graph = try!(CommitGraph::open(&key.0.dir_path()));
+                    Some((key, graph))
+                }
+                None => None,
+            };
+        }
+        match cache.as_ref().and_then(|&(_, ref graph)| f(graph)) {
+            Some(ret) => Ok(Some(ret)),
+            None if usage == CommitGraphUsage::Require => {
+                Err(Error::from_str("the commits are not in a commit-graph"))
+            }
+            None => Ok(None),
+        }
+    }
+
     /// Find a merge base between two commits
     pub fn merge_base(&self, one: Oid, two: Oid) -> Result<Oid, Error> {
         let mut raw = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
@@ -1045,6 +2457,316 @@ impl Repository {
         }
     }
 
+    /// Find a merge base given a list of commits
+    pub fn merge_base_many(&self, oids: &[Oid]) -> Result<Oid, Error> {
+        let mut raw = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        let oids = oids.iter().map(|o| unsafe { *o.raw() })
+                       .collect::<Vec<_>>();
+        unsafe {
+            try_call!(raw::git_merge_base_many(&mut raw, self.raw,
+                                               oids.len() as size_t,
+                                               oids.as_ptr()));
+            Ok(Binding::from_raw(&raw as *const _))
+        }
+    }
+
+    /// Find a merge base in preparation for an octopus merge
+    pub fn merge_base_octopus(&self, oids: &[Oid]) -> Result<Oid, Error> {
+        let mut raw = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        let oids = oids.iter().map(|o| unsafe { *o.raw() })
+                       .collect::<Vec<_>>();
+        unsafe {
+            try_call!(raw::git_merge_base_octopus(&mut raw, self.raw,
+                                                  oids.len() as size_t,
+                                                  oids.as_ptr()));
+            Ok(Binding::from_raw(&raw as *const _))
+        }
+    }
+
+    /// Find all merge bases between two commits
+    pub fn merge_bases(&self, one: Oid, two: Oid) -> Result<OidArray, Error> {
+        let mut arr = raw::git_oidarray {
+            ids: 0 as *mut raw::git_oid,
+            count: 0,
+        };
+        unsafe {
+            try_call!(raw::git_merge_bases(&mut arr, self.raw,
+                                           one.raw(), two.raw()));
+            Ok(Binding::from_raw(arr))
+        }
+    }
+
+    /// Find all merge bases between two commits, using the commit-graph as
+    /// the usage allows.
+    ///
+    /// Answered from the commit-graph, the walk stops as soon as the
+    /// remaining commits are known to be common to both sides, and the merge
+    /// bases are ordered from the most recent generation to the oldest.
+    pub fn merge_bases_with(&self, one: Oid, two: Oid,
+                            usage: CommitGraphUsage)
+                            -> Result<Vec<Oid>, Error> {
+        match try!(self.with_commit_graph(usage, |graph| {
+            graph.merge_bases(one, two)
+        })) {
+            Some(bases) => return Ok(bases),
+            None => {}
+        }
+        match self.merge_bases(one, two) {
+            Ok(bases) => Ok(bases.iter().map(|id| *id).collect()),
+            Err(ref e) if e.code() == ErrorCode::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Find all merge bases given a list of commits
+    pub fn merge_bases_many(&self, oids: &[Oid]) -> Result<OidArray, Error> {
+        let mut arr = raw::git_oidarray {
+            ids: 0 as *mut raw::git_oid,
+            count: 0,
+        };
+        let oids = oids.iter().map(|o| unsafe { *o.raw() })
+                       .collect::<Vec<_>>();
+        unsafe {
+            try_call!(raw::git_merge_bases_many(&mut arr, self.raw,
+                                                oids.len() as size_t,
+                                                oids.as_ptr()));
+            Ok(Binding::from_raw(arr))
+        }
+    }
+
+    /// Creates an `AnnotatedCommit` from the given commit id.
+    pub fn find_annotated_commit(&self, id: Oid)
+                                 -> Result<AnnotatedCommit, Error> {
+        let mut raw = 0 as *mut raw::git_annotated_commit;
+        unsafe {
+            try_call!(raw::git_annotated_commit_lookup(&mut raw, self.raw,
+                                                       id.raw()));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
+    /// Creates an `AnnotatedCommit` from the given reference.
+    ///
+    /// The reference name is remembered so that operations like merge and
+    /// rebase can record it, e.g. in reflog messages.
+    pub fn reference_to_annotated_commit(&self, reference: &Reference)
+                                         -> Result<AnnotatedCommit, Error> {
+        let mut raw = 0 as *mut raw::git_annotated_commit;
+        unsafe {
+            try_call!(raw::git_annotated_commit_from_ref(&mut raw, self.raw,
+                                                         reference.raw()));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
+    /// Creates an `AnnotatedCommit` from the given fetch head data.
+    ///
+    /// The branch name and remote url are those of the fetched branch, as
+    /// recorded in `FETCH_HEAD`.
+    pub fn annotated_commit_from_fetchhead(&self,
+                                           branch_name: &str,
+                                           remote_url: &str,
+                                           id: &Oid)
+                                           -> Result<AnnotatedCommit, Error> {
+        let branch_name = CString::from_slice(branch_name.as_bytes());
+        let remote_url = CString::from_slice(remote_url.as_bytes());
+        let mut raw = 0 as *mut raw::git_annotated_commit;
+        unsafe {
+            try_call!(raw::git_annotated_commit_from_fetchhead(&mut raw,
+                                                               self.raw,
+                                                               branch_name,
+                                                               remote_url,
+                                                               id.raw()));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
+    /// Creates an `AnnotatedCommit` from a revision string.
+    ///
+    /// See `man gitrevisions`, or http://git-scm.com/docs/git-rev-parse.html
+    /// for information on the syntax accepted.
+    pub fn annotated_commit_from_revspec(&self, spec: &str)
+                                         -> Result<AnnotatedCommit, Error> {
+        let spec = CString::from_slice(spec.as_bytes());
+        let mut raw = 0 as *mut raw::git_annotated_commit;
+        unsafe {
+            try_call!(raw::git_annotated_commit_from_revspec(&mut raw,
+                                                             self.raw,
+                                                             spec));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
+    /// Analyzes the given branch(es) and determines the opportunities for
+    /// merging them into the HEAD of the repository.
+    ///
+    /// Returns the analysis along with the merge preference configured by the
+    /// user (via `merge.ff`).
+    pub fn merge_analysis(&self, their_heads: &[&AnnotatedCommit])
+                          -> Result<(MergeAnalysis, MergePreference), Error> {
+        let mut analysis: raw::git_merge_analysis_t = 0;
+        let mut preference: raw::git_merge_preference_t = 0;
+        let mut heads = their_heads.iter().map(|a| a.raw() as *const _)
+                                   .collect::<Vec<_>>();
+        unsafe {
+            try_call!(raw::git_merge_analysis(&mut analysis,
+                                              &mut preference,
+                                              self.raw,
+                                              heads.as_mut_ptr(),
+                                              heads.len() as size_t));
+        }
+        Ok((MergeAnalysis::from_bits_truncate(analysis as u32),
+            MergePreference::from_bits_truncate(preference as u32)))
+    }
+
+    /// Merges the given commit(s) into HEAD, writing the results into the
+    /// working directory.
+    ///
+    /// Any changes are staged for commit and any conflicts are written to the
+    /// index. Callers should inspect the repository's index after this
+    /// completes, resolve any conflicts and prepare a commit. The
+    /// `MERGE_HEAD` and `MERGE_MSG` files are written so that the merge can be
+    /// concluded (or aborted) in the same way as with `git merge`.
+    pub fn merge(&self,
+                 annotated_commits: &[&AnnotatedCommit],
+                 merge_opts: Option<&MergeOptions>,
+                 checkout_opts: Option<&mut CheckoutBuilder>)
+                 -> Result<(), Error> {
+        let mut heads = annotated_commits.iter().map(|a| a.raw() as *const _)
+                                         .collect::<Vec<_>>();
+        unsafe {
+            let mut raw_checkout = mem::zeroed();
+            try_call!(raw::git_checkout_init_options(&mut raw_checkout,
+                                raw::GIT_CHECKOUT_OPTIONS_VERSION));
+            match checkout_opts {
+                Some(c) => c.configure(&mut raw_checkout),
+                None => {}
+            }
+            try_call!(raw::git_merge(self.raw,
+                                     heads.as_mut_ptr(),
+                                     heads.len() as size_t,
+                                     merge_opts.map(|o| o.raw()),
+                                     &raw_checkout));
+        }
+        Ok(())
+    }
+
+    /// Merge two files as they exist in the index, using the given common
+    /// ancestor as the baseline.
+    ///
+    /// The contents of the entries are looked up in the object database, and
+    /// the result contains the merged contents along with whether they could
+    /// be merged automatically.
+    pub fn merge_file_from_index(&self,
+                                 ancestor: &IndexEntry,
+                                 ours: &IndexEntry,
+                                 theirs: &IndexEntry,
+                                 opts: Option<&mut MergeFileOptions>)
+                                 -> Result<MergeFileResult, Error> {
+        let ancestor_path = CString::from_slice(&ancestor.path);
+        let ours_path = CString::from_slice(&ours.path);
+        let theirs_path = CString::from_slice(&theirs.path);
+        let mut ancestor = ancestor.raw();
+        let mut ours = ours.raw();
+        let mut theirs = theirs.raw();
+        ancestor.path = ancestor_path.as_ptr();
+        ours.path = ours_path.as_ptr();
+        theirs.path = theirs_path.as_ptr();
+        unsafe {
+            let mut ret: raw::git_merge_file_result = mem::zeroed();
+            try_call!(raw::git_merge_file_from_index(&mut ret, self.raw,
+                                                     &ancestor, &ours, &theirs,
+                                                     opts.map(|o| o.raw())));
+            Ok(Binding::from_raw(ret))
+        }
+    }
+
+    /// Get a handle for recording and reusing the resolutions of conflicts
+    /// in this repository, like `git rerere`.
+    pub fn rerere(&self) -> Rerere {
+        Rerere::new(self)
+    }
+
+    /// Initializes a rebase operation to rebase the changes in `branch`
+    /// relative to `upstream` onto another branch. To begin the rebase
+    /// process, iterate over the returned `Rebase`.
+    ///
+    /// `branch` defaults to `HEAD` when `None`, `upstream` defaults to the
+    /// upstream configured for the branch, and `onto` defaults to
+    /// `upstream`.
+    ///
+    /// The options stay borrowed for as long as the rebase, as libgit2 keeps
+    /// using them, and their callbacks, in `next`, `commit` and `finish`.
+    pub fn rebase<'a>(&'a self,
+                      branch: Option<&AnnotatedCommit>,
+                      upstream: Option<&AnnotatedCommit>,
+                      onto: Option<&AnnotatedCommit>,
+                      opts: Option<&'a mut RebaseOptions>)
+                      -> Result<Rebase<'a>, Error> {
+        let mut rebase = 0 as *mut raw::git_rebase;
+        unsafe {
+            try_call!(raw::git_rebase_init(&mut rebase,
+                                           self.raw,
+                                           branch.map(|c| c.raw()),
+                                           upstream.map(|c| c.raw()),
+                                           onto.map(|c| c.raw()),
+                                           opts.map(|o| o.raw())));
+            Ok(Binding::from_raw(rebase))
+        }
+    }
+
+    /// Opens an existing rebase that was previously started by either an
+    /// invocation of `rebase` or by another client.
+    ///
+    /// As with `rebase`, the options stay borrowed for as long as the rebase.
+    pub fn open_rebase<'a>(&'a self, opts: Option<&'a mut RebaseOptions>)
+                           -> Result<Rebase<'a>, Error> {
+        let mut rebase = 0 as *mut raw::git_rebase;
+        unsafe {
+            try_call!(raw::git_rebase_open(&mut rebase, self.raw,
+                                           opts.map(|o| o.raw())));
+            Ok(Binding::from_raw(rebase))
+        }
+    }
+
+    /// Merge two commits, producing an index that reflects the result of the
+    /// merge.
+    ///
+    /// The index may be written as-is to the working directory or checked
+    /// out. If the index is to be converted to a tree, the caller should
+    /// resolve any conflicts that arose as part of the merge.
+    pub fn merge_commits(&self, our_commit: &Commit, their_commit: &Commit,
+                         opts: Option<&MergeOptions>) -> Result<Index, Error> {
+        let mut raw = 0 as *mut raw::git_index;
+        unsafe {
+            try_call!(raw::git_merge_commits(&mut raw, self.raw,
+                                             our_commit.raw(),
+                                             their_commit.raw(),
+                                             opts.map(|o| o.raw())));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
+    /// Merge two trees, producing an index that reflects the result of the
+    /// merge.
+    ///
+    /// The index may be written as-is to the working directory or checked
+    /// out. If the index is to be converted to a tree, the caller should
+    /// resolve any conflicts that arose as part of the merge.
+    pub fn merge_trees(&self, ancestor_tree: &Tree, our_tree: &Tree,
+                       their_tree: &Tree, opts: Option<&MergeOptions>)
+                       -> Result<Index, Error> {
+        let mut raw = 0 as *mut raw::git_index;
+        unsafe {
+            try_call!(raw::git_merge_trees(&mut raw, self.raw,
+                                           ancestor_tree.raw(),
+                                           our_tree.raw(),
+                                           their_tree.raw(),
+                                           opts.map(|o| o.raw())));
+            Ok(Binding::from_raw(raw))
+        }
+    }
+
     /// Count the number of unique commits between two commit objects
     ///
     /// There is no need for branches containing the commits to have any
@@ -1063,6 +2785,23 @@ impl Repository {
         }
     }
 
+    /// Count the number of unique commits between two commit objects, using
+    /// the commit-graph as the usage allows.
+    ///
+    /// Answered from the commit-graph, the walk stops as soon as the
+    /// remaining commits are known to be common to both sides, rather than
+    /// when commit dates suggest so.
+    pub fn graph_ahead_behind_with(&self, local: Oid, upstream: Oid,
+                                   usage: CommitGraphUsage)
+                                   -> Result<(usize, usize), Error> {
+        match try!(self.with_commit_graph(usage, |graph| {
+            graph.ahead_behind(local, upstream)
+        })) {
+            Some(counts) => Ok(counts),
+            None => self.graph_ahead_behind(local, upstream),
+        }
+    }
+
     /// Determine if a commit is the descendant of another commit
     pub fn graph_descendant_of(&self, commit: Oid, ancestor: Oid)
                                -> Result<bool, Error> {
@@ -1074,6 +2813,95 @@ impl Repository {
         }
     }
 
+    /// Create a diff with the difference between two tree objects.
+    ///
+    /// This is equivalent to `git diff <old-tree> <new-tree>`. See
+    /// `Diff::tree_to_tree` for more information.
+    pub fn diff_tree_to_tree(&self,
+                             old_tree: Option<&Tree>,
+                             new_tree: Option<&Tree>,
+                             opts: Option<&mut DiffOptions>)
+                             -> Result<Diff, Error> {
+        Diff::tree_to_tree(self, old_tree, new_tree, opts)
+    }
+
+    /// Create a diff between a tree and repository index.
+    ///
+    /// This is equivalent to `git diff --cached <treeish>`. See
+    /// `Diff::tree_to_index` for more information.
+    pub fn diff_tree_to_index(&self,
+                              old_tree: Option<&Tree>,
+                              index: Option<&Index>,
+                              opts: Option<&mut DiffOptions>)
+                              -> Result<Diff, Error> {
+        Diff::tree_to_index(self, old_tree, index, opts)
+    }
+
+    /// Create a diff between the repository index and the workdir directory.
+    ///
+    /// This matches the `git diff` command. See `Diff::index_to_workdir` for
+    /// more information.
+    pub fn diff_index_to_workdir(&self,
+                                 index: Option<&Index>,
+                                 opts: Option<&mut DiffOptions>)
+                                 -> Result<Diff, Error> {
+        Diff::index_to_workdir(self, index, opts)
+    }
+
+    /// Create a diff between a tree and the working directory.
+    ///
+    /// Note that this does not consult the index, see `Diff::tree_to_workdir`
+    /// for more information.
+    pub fn diff_tree_to_workdir(&self,
+                                old_tree: Option<&Tree>,
+                                opts: Option<&mut DiffOptions>)
+                                -> Result<Diff, Error> {
+        Diff::tree_to_workdir(self, old_tree, opts)
+    }
+
+    /// Create a diff between a tree and the working directory using index data
+    /// to account for staged deletes, tracked files, etc.
+    ///
+    /// This emulates `git diff <tree>`. See
+    /// `Diff::tree_to_workdir_with_index` for more information.
+    pub fn diff_tree_to_workdir_with_index(&self,
+                                           old_tree: Option<&Tree>,
+                                           opts: Option<&mut DiffOptions>)
+                                           -> Result<Diff, Error> {
+        Diff::tree_to_workdir_with_index(self, old_tree, opts)
+    }
+
+    /// Apply a Diff to the given repo, making changes directly in the working
+    /// directory, the index, or both.
+    pub fn apply(&self,
+                 diff: &Diff,
+                 location: ApplyLocation,
+                 options: Option<&mut ApplyOptions>)
+                 -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_apply(self.raw, diff.raw(), location,
+                                     options.map(|s| s.raw())));
+        }
+        Ok(())
+    }
+
+    /// Apply a Diff to the provided tree, and return the resulting Index.
+    ///
+    /// Neither the repository's index nor its working directory is modified.
+    pub fn apply_to_tree(&self,
+                         tree: &Tree,
+                         diff: &Diff,
+                         options: Option<&mut ApplyOptions>)
+                         -> Result<Index, Error> {
+        let mut ret = 0 as *mut raw::git_index;
+        unsafe {
+            try_call!(raw::git_apply_to_tree(&mut ret, self.raw, tree.raw(),
+                                             diff.raw(),
+                                             options.map(|s| s.raw())));
+            Ok(Binding::from_raw(ret))
+        }
+    }
+
     /// Read the reflog for the given reference
     ///
     /// If there is no reflog file for the given reference yet, an empty reflog
@@ -1106,12 +2934,129 @@ impl Repository {
         }
         Ok(())
     }
+
+    /// Save the local modifications to a new stash.
+    ///
+    /// The `stasher` is the identity of the person performing the stashing,
+    /// and `message` is an optional description along with the stashed
+    /// state. Returns the id of the commit containing the stashed state,
+    /// which is also the target of the direct reference `refs/stash`.
+    pub fn stash_save(&self,
+                      stasher: &Signature,
+                      message: Option<&str>,
+                      flags: StashFlags) -> Result<Oid, Error> {
+        let message = message.map(|s| CString::from_slice(s.as_bytes()));
+        let mut ret = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        unsafe {
+            try_call!(raw::git_stash_save(&mut ret, self.raw, stasher.raw(),
+                                          message, flags.bits()));
+            Ok(Binding::from_raw(&ret as *const _))
+        }
+    }
+
+    /// Apply a single stashed state from the stash list.
+    ///
+    /// The `index` is the position within the stash list, where 0 is the
+    /// most recent stash. If local changes in the working directory
+    /// conflict with changes in the stash then this will return an error
+    /// and leave the working directory untouched.
+    pub fn stash_apply(&self,
+                       index: usize,
+                       opts: Option<&mut StashApplyOptions>)
+                       -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_stash_apply(self.raw, index as size_t,
+                                           opts.map(|o| o.raw())));
+        }
+        Ok(())
+    }
+
+    /// Loop over all the stashed states and issue a callback for each one.
+    ///
+    /// The callback is given the position of the stash within the stash
+    /// list, its message and the id of the stash commit. Returning `false`
+    /// from the callback will terminate the iteration.
+    pub fn stash_foreach<F>(&self, mut callback: F) -> Result<(), Error>
+                            where F: FnMut(usize, &str, &Oid) -> bool {
+        unsafe {
+            try_call!(raw::git_stash_foreach(self.raw, stash_cb::<F>,
+                                             &mut callback as *mut _
+                                                           as *mut c_void));
+            return Ok(())
+        }
+
+        extern fn stash_cb<F>(index: size_t,
+                              message: *const c_char,
+                              stash_id: *const raw::git_oid,
+                              data: *mut c_void) -> c_int
+                              where F: FnMut(usize, &str, &Oid) -> bool
+        {
+            unsafe {
+                let data = data as *mut F;
+                let message = ffi::c_str_to_bytes(&message);
+                let message = str::from_utf8(message).unwrap();
+                let id: Oid = Binding::from_raw(stash_id);
+                let ok = panic::wrap(move || {
+                    (*data)(index as usize, message, &id)
+                }).unwrap_or(false);
+                if ok {0} else {1}
+            }
+        }
+    }
+
+    /// Look up the commit recording the stashed state at the given position
+    /// in the stash list, without applying it.
+    ///
+    /// The first parent of the commit is the commit which was checked out
+    /// when the stash was made, and the second records the state of the
+    /// index. If untracked files were stashed they are recorded in a third
+    /// parent. The tree of the commit itself is the stashed working
+    /// directory, so it can be diffed against the first parent to inspect
+    /// the stash.
+    pub fn find_stash(&self, index: usize) -> Result<Commit, Error> {
+        let mut found = None;
+        try!(self.stash_foreach(|i, _, id| {
+            if i == index { found = Some(*id); }
+            found.is_none()
+        }));
+        match found {
+            Some(id) => self.find_commit(id),
+            None => Err(Error::new(ErrorCode::NotFound, ErrorClass::Stash,
+                                   "no stashed state at the given index")),
+        }
+    }
+
+    /// Remove a single stashed state from the stash list.
+    pub fn stash_drop(&self, index: usize) -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_stash_drop(self.raw, index as size_t));
+        }
+        Ok(())
+    }
+
+    /// Apply a single stashed state from the stash list and remove it from
+    /// the list if successful.
+    pub fn stash_pop(&self,
+                     index: usize,
+                     opts: Option<&mut StashApplyOptions>)
+                     -> Result<(), Error> {
+        unsafe {
+            try_call!(raw::git_stash_pop(self.raw, index as size_t,
+                                         opts.map(|o| o.raw())));
+        }
+        Ok(())
+    }
 }
 
 impl Binding for Repository {
     type Raw = *mut raw::git_repository;
     unsafe fn from_raw(ptr: *mut raw::git_repository) -> Repository {
-        Repository { raw: ptr }
+        Repository {
+            raw: ptr,
+            object_cache: RefCell::new(None),
+            commit_graph: RefCell::new(None),
+            no_replace_objects: false,
+        }
     }
     fn raw(&self) -> *mut raw::git_repository { self.raw }
 }
@@ -1119,6 +3064,8 @@ impl Binding for Repository {
 #[unsafe_destructor]
 impl Drop for Repository {
     fn drop(&mut self) {
+        // cached objects must be freed before the repository
+        *self.object_cache.borrow_mut() = None;
         unsafe { raw::git_repository_free(self.raw) }
     }
 }
@@ -1277,8 +3224,12 @@ impl RepositoryInitOptions {
 
 #[cfg(test)]
 mod tests {
-    use std::old_io::TempDir;
-    use {Repository, ObjectType, ResetType};
+    use std::env;
+    use std::old_io::{self, File, TempDir};
+    use std::old_io::fs::{self, PathExtensions};
+    use {Repository, ObjectType, ResetType, AttrValue, Error};
+    use {ErrorCode, Oid, SignatureVerification, RepositoryOpenFlags};
+    use {REPOSITORY_OPEN_NO_SEARCH, REPOSITORY_OPEN_NO_REPLACE_OBJECTS};
 
     #[test]
     fn smoke_init() {
@@ -1325,6 +3276,49 @@ mod tests {
                    ::test::realpath(td.path()));
     }
 
+    #[test]
+    fn set_head() {
+        let (_td, repo) = ::test::repo_init();
+        let id = repo.refname_to_id_bytes(b"refs/heads/master").unwrap();
+        repo.reference("refs/heads/other", id, false, "create").unwrap();
+
+        repo.set_head_bytes(b"refs/heads/other").unwrap();
+        let head = repo.head().unwrap();
+        assert_eq!(head.name_bytes(), b"refs/heads/other");
+        assert!(head.is_branch());
+        let other = repo.find_reference_bytes(head.name_bytes()).unwrap();
+        assert_eq!(other.target(), Some(id));
+
+        repo.set_head_detached(id).unwrap();
+        let head = repo.head().unwrap();
+        assert_eq!(head.name(), Some("HEAD"));
+        assert_eq!(head.target(), Some(id));
+
+        repo.set_head("refs/heads/master").unwrap();
+        assert_eq!(repo.head().unwrap().name(), Some("refs/heads/master"));
+        assert!(repo.set_head_detached(::Oid::zero()).is_err());
+    }
+
+    #[test]
+    fn smoke_hashfile() {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join(".gitattributes"))
+             .write_str("*.txt text\n").unwrap();
+        File::create(&td.path().join("foo.txt"))
+             .write_str("a\r\nb\r\n").unwrap();
+
+        let id = repo.hashfile(&Path::new("foo.txt"), ObjectType::Blob,
+                               None).unwrap();
+        assert!(!repo.odb().unwrap().exists(id));
+        assert_eq!(id, repo.blob(b"a\nb\n").unwrap());
+
+        let raw = repo.hashfile(&td.path().join("foo.txt"), ObjectType::Blob,
+                                Some(&Path::new(""))).unwrap();
+        assert_eq!(raw, repo.blob(b"a\r\nb\r\n").unwrap());
+        assert!(repo.hashfile(&Path::new("missing"), ObjectType::Blob,
+                              None).is_err());
+    }
+
     #[test]
     fn smoke_checkout() {
         let (_td, repo) = ::test::repo_init();
@@ -1343,9 +3337,128 @@ mod tests {
         let obj = repo.find_object(from.id(), None).unwrap().clone();
         obj.peel(ObjectType::Any).unwrap();
         obj.short_id().unwrap();
+        repo.reset(&obj, ResetType::Hard, None).unwrap();
+        repo.reset(&obj, ResetType::Soft, None).unwrap();
+    }
+
+    #[test]
+    fn smoke_find_object_by_prefix() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let obj = repo.find_object(head, None).unwrap();
+        let short = obj.short_id().unwrap();
+        let short = short.as_str().unwrap();
+        let found = repo.find_object_by_prefix(short, None).unwrap();
+        assert_eq!(found.id(), head);
+        let found = repo.find_object_by_prefix(short,
+                                               Some(ObjectType::Commit));
+        assert_eq!(found.unwrap().id(), head);
+        assert!(repo.find_object_by_prefix(short,
+                                           Some(ObjectType::Blob)).is_err());
+        assert!(repo.find_object_by_prefix("zzzz", None).is_err());
+    }
+
+    #[test]
+    fn smoke_free_unused_memory() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let commit = repo.find_commit(head).unwrap();
+        repo.free_unused_memory().unwrap();
+        assert_eq!(commit.id(), head);
+        assert_eq!(repo.find_commit(head).unwrap().id(), head);
+        assert_eq!(repo.config().unwrap().get_str("user.name").unwrap(),
+                   "name");
+    }
+
+    #[test]
+    fn smoke_pack_refs() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        repo.reference("refs/tags/a", head, false, "").unwrap();
+        assert!(repo.path().join("refs/tags/a").exists());
+        repo.pack_refs().unwrap();
+        assert!(!repo.path().join("refs/tags/a").exists());
+        assert!(repo.path().join("packed-refs").exists());
+        assert_eq!(repo.refname_to_id("refs/tags/a").unwrap(), head);
+    }
+
+    #[test]
+    fn smoke_merge_heads() {
+        let (_td, repo) = ::test::repo_init();
+        assert_eq!(repo.merge_heads().unwrap(), Vec::new());
+
+        let head = repo.head().unwrap().target().unwrap();
+        File::create(&repo.path().join("MERGE_HEAD"))
+            .write_str(format!("{}\n", head).as_slice()).unwrap();
+        assert_eq!(repo.merge_heads().unwrap(), vec![head]);
+        repo.cleanup_state().unwrap();
+        assert_eq!(repo.merge_heads().unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn smoke_fetchhead_foreach() {
+        let (_td, repo) = ::test::repo_init();
+        assert!(repo.fetchhead_foreach(|_, _, _, _| true).is_err());
+
+        let head = repo.head().unwrap().target().unwrap();
+        File::create(&repo.path().join("FETCH_HEAD")).write_str(format!(
+            "{id}\t\tbranch 'master' of https://example.com/foo\n\
+             {id}\tnot-for-merge\tbranch 'bar' of https://example.com/foo\n",
+            id = head).as_slice()).unwrap();
+        let mut entries = Vec::new();
+        repo.fetchhead_foreach(|name, url, id, is_merge| {
+            assert_eq!(url, b"https://example.com/foo");
+            assert_eq!(*id, head);
+            entries.push((name.to_string(), is_merge));
+            true
+        }).unwrap();
+        assert_eq!(entries, vec![("refs/heads/master".to_string(), true),
+                                 ("refs/heads/bar".to_string(), false)]);
+
+        let mut n = 0;
+        repo.fetchhead_foreach(|_, _, _, _| { n += 1; false }).unwrap();
+        assert_eq!(n, 1);
+    }
+
+    #[test]
+    fn smoke_reset() {
+        let (td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let initial = repo.find_object(head, None).unwrap();
         let sig = repo.signature().unwrap();
-        repo.reset(&obj, ResetType::Hard, None, None, None).unwrap();
-        repo.reset(&obj, ResetType::Soft, None, Some(&sig), Some("foo")).unwrap();
+
+        File::create(&td.path().join("foo")).write_str("foo\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("foo")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = repo.find_commit(head).unwrap();
+        let second = repo.commit(Some("HEAD"), &sig, &sig, "second",
+                                 &tree, &[&parent]).unwrap();
+
+        // unstage `foo` without touching HEAD or the working directory
+        repo.reset_default(Some(&initial), vec!["foo"].into_iter()).unwrap();
+        assert_eq!(repo.index().unwrap().len(), 0);
+        assert_eq!(repo.head().unwrap().target().unwrap(), second);
+
+        // a mixed reset moves HEAD and resets the index, keeping the file
+        let second = repo.find_object(second, None).unwrap();
+        repo.reset(&second, ResetType::Mixed, None).unwrap();
+        assert_eq!(repo.index().unwrap().len(), 1);
+        repo.reset(&initial, ResetType::Mixed, None).unwrap();
+        assert_eq!(repo.head().unwrap().target().unwrap(), head);
+        assert_eq!(repo.index().unwrap().len(), 0);
+        assert!(td.path().join("foo").exists());
+
+        // a hard reset to a commit also updates the working directory
+        let annotated = repo.reference_to_annotated_commit(
+            &repo.find_reference("HEAD").unwrap().resolve().unwrap()).unwrap();
+        repo.reset(&second, ResetType::Hard, None).unwrap();
+        assert_eq!(repo.index().unwrap().len(), 1);
+        repo.reset_from_annotated(&annotated, ResetType::Hard, None).unwrap();
+        assert_eq!(repo.head().unwrap().target().unwrap(), head);
+        assert_eq!(repo.index().unwrap().len(), 0);
+        assert!(!td.path().join("foo").exists());
     }
 
     #[test]
@@ -1364,6 +3477,217 @@ mod tests {
                    ::test::realpath(td.path()));
     }
 
+    #[test]
+    fn smoke_ignore_rules() {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join("foo.o")).unwrap();
+        assert!(!repo.is_path_ignored(&Path::new("foo.o")).unwrap());
+        assert_eq!(repo.statuses(None).unwrap().len(), 1);
+
+        repo.add_ignore_rule("*.o\ntarget/\n").unwrap();
+        assert!(repo.is_path_ignored(&Path::new("foo.o")).unwrap());
+        assert!(repo.is_path_ignored(&Path::new("target/debug")).unwrap());
+        assert!(!repo.is_path_ignored(&Path::new("foo.c")).unwrap());
+        assert_eq!(repo.statuses(None).unwrap().len(), 0);
+
+        repo.clear_ignore_rules().unwrap();
+        assert!(!repo.is_path_ignored(&Path::new("foo.o")).unwrap());
+        assert!(repo.is_path_ignored(&Path::new(".git")).unwrap());
+    }
+
+    #[test]
+    fn smoke_get_attr() {
+        let (td, repo) = ::test::repo_init();
+        File::create(&td.path().join(".gitattributes"))
+            .write_str("*.bin -diff\n*.txt diff eol=lf\n").unwrap();
+        let flags = ::ATTR_CHECK_FILE_THEN_INDEX;
+
+        let get = |path: &str, name: &str| {
+            repo.get_attr(&Path::new(path), name, flags).unwrap()
+        };
+        let diff = get("a.bin", "diff");
+        let diff = diff.as_ref().map(|s| s.as_slice());
+        assert_eq!(AttrValue::from_string(diff), AttrValue::False);
+        let diff = get("a.txt", "diff");
+        let diff = diff.as_ref().map(|s| s.as_slice());
+        assert_eq!(AttrValue::from_string(diff), AttrValue::True);
+        let lf = get("a.txt", "eol");
+        let eol = lf.as_ref().map(|s| s.as_slice());
+        assert_eq!(eol, Some("lf"));
+        assert_eq!(AttrValue::from_string(eol), AttrValue::String("lf"));
+        let eol = repo.get_attr_bytes(&Path::new("a.bin"), "eol",
+                                      flags).unwrap();
+        let eol = eol.as_ref().map(|s| s.as_slice());
+        assert_eq!(AttrValue::from_bytes(eol), AttrValue::Unspecified);
+
+        // nothing is staged, so only looking at the index finds nothing
+        let diff = repo.get_attr(&Path::new("a.bin"), "diff",
+                                 ::ATTR_CHECK_INDEX_ONLY).unwrap();
+        assert!(diff.is_none());
+
+        File::create(&td.path().join(".gitattributes"))
+            .write_str("*.bin diff\n").unwrap();
+        repo.attr_cache_flush().unwrap();
+        let diff = repo.get_attr(&Path::new("a.bin"), "diff", flags).unwrap();
+        // the value looked up before the flush is still valid
+        assert_eq!(lf, Some("lf".to_string()));
+        let diff = diff.as_ref().map(|s| s.as_slice());
+        assert_eq!(AttrValue::from_string(diff), AttrValue::True);
+    }
+
+    #[test]
+    fn smoke_commit_signed() {
+        let (_td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let parent = repo.find_commit(head).unwrap();
+        let tree = parent.tree().unwrap();
+
+        let buf = repo.commit_create_buffer(&sig, &sig, "signed\n", &tree,
+                                            &[&parent]).unwrap();
+        let content = buf.as_str().unwrap();
+        assert!(content.starts_with("tree "));
+        assert!(content.ends_with("\nsigned\n"));
+
+        let id = repo.commit_signed_with(&sig, &sig, "signed\n", &tree,
+                                         &[&parent], |c| {
+            assert_eq!(c, content);
+            Ok("magic signature".to_string())
+        }).unwrap();
+        let commit = repo.find_commit(id).unwrap();
+        assert!(commit.raw_header().unwrap()
+                      .contains("gpgsig magic signature"));
+        assert_eq!(commit.message(), Some("signed\n"));
+        assert_eq!(commit.parent_id(0).unwrap(), head);
+        // creating a signed commit does not move HEAD
+        assert_eq!(repo.head().unwrap().target().unwrap(), head);
+
+        let id = repo.commit_signed(content, "other", Some("x-sig")).unwrap();
+        let commit = repo.find_commit(id).unwrap();
+        assert!(commit.raw_header().unwrap().contains("x-sig other"));
+
+        assert!(repo.commit_signed_with(&sig, &sig, "x", &tree, &[&parent],
+                                        |_| Err(Error::from_str("no key")))
+                    .is_err());
+    }
+
+    #[test]
+    fn grafts_and_shallow() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let a = Oid::from_str("8496071c1b46c854b31185ea97743be6a8774479")
+                    .unwrap();
+        let b = Oid::from_str("a4a7dce85cf63874e984719f4fdd239f5145052f")
+                    .unwrap();
+        assert_eq!(repo.shallow_roots().unwrap(), vec![]);
+        assert_eq!(repo.grafts().unwrap(), vec![]);
+
+        File::create(&repo.path().join("shallow"))
+             .write_str(format!("{}\n{}\n", head, a).as_slice()).unwrap();
+        assert_eq!(repo.shallow_roots().unwrap(), vec![head, a]);
+
+        fs::mkdir_recursive(&repo.path().join("info"), old_io::USER_DIR)
+           .unwrap();
+        let grafts = repo.path().join("info/grafts");
+        File::create(&grafts).write_str(format!("# grafts\n{} {} {}\n\n{}\n",
+                                                head, a, b, a)
+                                            .as_slice()).unwrap();
+        assert_eq!(repo.grafts().unwrap(),
+                   vec![(head, vec![a, b]), (a, vec![])]);
+
+        File::create(&grafts).write_str("nope\n").unwrap();
+        assert!(repo.grafts().is_err());
+    }
+
+    #[test]
+    fn open_ext() {
+        let (td, _repo) = ::test::repo_init();
+        let sub = td.path().join("a/b");
+        fs::mkdir_recursive(&sub, old_io::USER_DIR).unwrap();
+        let repo = Repository::open_ext(&sub, RepositoryOpenFlags::empty(),
+                                        &[]).unwrap();
+        assert_eq!(::test::realpath(&repo.workdir().unwrap()).unwrap(),
+                   ::test::realpath(td.path()).unwrap());
+        assert!(Repository::open_ext(&sub, REPOSITORY_OPEN_NO_SEARCH,
+                                     &[]).is_err());
+        assert!(Repository::open_ext(&sub, RepositoryOpenFlags::empty(),
+                                     &[&td.path().join("a")]).is_err());
+    }
+
+    #[test]
+    fn replace_objects() {
+        let (_td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let tree = repo.find_commit(head).unwrap().tree().unwrap();
+        let a = repo.commit(None, &sig, &sig, "a", &tree, &[]).unwrap();
+        let b = repo.commit(None, &sig, &sig, "b", &tree, &[]).unwrap();
+
+        assert_eq!(repo.replacements().unwrap(), vec![]);
+        assert_eq!(repo.replacement_of(head).unwrap(), head);
+        assert!(repo.replace_object(head, head, false).is_err());
+        assert!(repo.replace_object(head, tree.id(), false).is_err());
+
+        let r = repo.replace_object(head, a, false).unwrap();
+        assert_eq!(r.name(), Some(format!("refs/replace/{}", head).as_slice()));
+        assert_eq!(repo.replacements().unwrap(), vec![(head, a)]);
+        assert_eq!(repo.replacement_of(head).unwrap(), a);
+        assert!(repo.replace_object(head, b, false).is_err());
+
+        // chains of replacements are followed
+        repo.replace_object(a, b, false).unwrap();
+        assert_eq!(repo.replacement_of(head).unwrap(), b);
+        assert_eq!(repo.replacement_of(a).unwrap(), b);
+
+        repo.config().unwrap().set_bool("core.useReplaceRefs", false)
+            .unwrap();
+        assert_eq!(repo.replacement_of(head).unwrap(), head);
+        repo.config().unwrap().set_bool("core.useReplaceRefs", true)
+            .unwrap();
+
+        let other = Repository::open_ext(&repo.path(),
+                                         REPOSITORY_OPEN_NO_REPLACE_OBJECTS,
+                                         &[]).unwrap();
+        assert_eq!(other.replacement_of(head).unwrap(), head);
+        assert_eq!(other.replacements().unwrap().len(), 2);
+
+        repo.delete_replacement(a).unwrap();
+        assert_eq!(repo.replacements().unwrap(), vec![(head, a)]);
+        assert_eq!(repo.replacement_of(head).unwrap(), a);
+        assert!(repo.delete_replacement(a).is_err());
+    }
+
+    #[test]
+    fn verify_commit_signature() {
+        let (_td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let parent = repo.find_commit(head).unwrap();
+        let tree = parent.tree().unwrap();
+        let buf = repo.commit_create_buffer(&sig, &sig, "signed\n", &tree,
+                                            &[&parent]).unwrap();
+        let content = buf.as_str().unwrap();
+        let id = repo.commit_signed(content, "magic signature", None).unwrap();
+
+        let (signature, data) = repo.extract_signature(id, None).unwrap();
+        assert_eq!(signature.as_str(), Some("magic signature"));
+        assert_eq!(data.as_str(), Some(content));
+        assert!(repo.extract_signature(id, Some("x-sig")).is_err());
+
+        let verification = repo.verify_commit_signature(id, |s, d| {
+            assert_eq!(s, b"magic signature");
+            assert_eq!(d, content.as_bytes());
+            Ok(SignatureVerification::new(true, Some("name <email>")))
+        }).unwrap();
+        assert!(verification.is_valid());
+        assert_eq!(verification.signer(), Some("name <email>"));
+
+        let err = repo.verify_commit_signature(head, |_, _| {
+            panic!("unsigned commits are not verified")
+        }).err().unwrap();
+        assert_eq!(err.code(), ErrorCode::NotFound);
+    }
+
     fn graph_repo_init() -> (TempDir, Repository) {
         let (_td, repo) = ::test::repo_init();
         {
@@ -1408,4 +3732,76 @@ mod tests {
         assert!(repo.graph_descendant_of(head_id, head_parent_id).unwrap());
         assert!(!repo.graph_descendant_of(head_parent_id, head_id).unwrap());
     }
+
+    #[test]
+    fn smoke_merge_base() {
+        let (_td, repo) = graph_repo_init();
+        let sig = repo.signature().unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let head = repo.find_commit(head).unwrap();
+        let base = head.parent(0).unwrap();
+        let tree = head.tree().unwrap();
+
+        // two more commits branching off from `base`
+        let side1 = repo.commit(None, &sig, &sig, "side1",
+                                &tree, &[&base]).unwrap();
+        let side2 = repo.commit(None, &sig, &sig, "side2",
+                                &tree, &[&base]).unwrap();
+        let ids = [head.id(), side1, side2];
+
+        assert_eq!(repo.merge_base(head.id(), side1).unwrap(), base.id());
+        assert_eq!(repo.merge_base_many(&ids).unwrap(), base.id());
+        assert_eq!(repo.merge_base_octopus(&ids).unwrap(), base.id());
+
+        let bases = repo.merge_bases(head.id(), side1).unwrap();
+        assert_eq!(bases.len(), 1);
+        assert_eq!(bases[0], base.id());
+        let bases = repo.merge_bases_many(&ids).unwrap();
+        assert_eq!(bases.iter().collect::<Vec<_>>(), vec![&base.id()]);
+    }
+
+    #[test]
+    fn hooks() {
+        let (_td, repo) = ::test::repo_init();
+        let path = repo.path().join("hooks/commit-msg");
+        assert_eq!(repo.hook_path("commit-msg").unwrap(), path);
+        assert!(repo.run_hook("commit-msg", &[], None).unwrap().is_none());
+
+        File::create(&path).write_str("#!/bin/sh\necho \"$1\"\ncat\nexit 1\n")
+                           .unwrap();
+        assert!(repo.run_hook("commit-msg", &[], None).unwrap().is_none());
+        fs::chmod(&path, old_io::USER_EXEC).unwrap();
+        let out = repo.run_hook("commit-msg", &["msg"], Some(b"foo"))
+                      .unwrap().unwrap();
+        assert!(!out.status.success());
+        assert_eq!(out.output.as_slice(), b"msg\nfoo");
+
+        repo.config().unwrap().set_str("core.hooksPath", "custom").unwrap();
+        assert_eq!(repo.hook_path("pre-commit").unwrap(),
+                   repo.workdir().unwrap().join("custom/pre-commit"));
+        assert!(repo.run_hook("commit-msg", &[], None).unwrap().is_none());
+
+        repo.config().unwrap().set_str("core.hooksPath", "~/hooks").unwrap();
+        assert_eq!(repo.hook_path("pre-commit").unwrap(),
+                   env::home_dir().unwrap().join("hooks/pre-commit"));
+    }
+
+    #[test]
+    fn hook_large_io() {
+        let (_td, repo) = ::test::repo_init();
+        let path = repo.path().join("hooks/pre-receive");
+        // the hook fills the output pipe before it reads any input
+        File::create(&path).write_str("#!/bin/sh
+head -c 1048576 \
+                                       /dev/zero
+cat
+").unwrap();
+        fs::chmod(&path, old_io::USER_EXEC).unwrap();
+        let input = vec![1u8; 1 << 20];
+        let out = repo.run_hook("pre-receive", &[], Some(input.as_slice()))
+                      .unwrap().unwrap();
+        assert!(out.status.success());
+        assert_eq!(out.output.len(), 2 << 20);
+        assert!(&out.output[1 << 20..] == input.as_slice());
+    }
 }
@@ -0,0 +1,423 @@
+use std::mem;
+use std::old_io::{self, File};
+use std::old_io::fs::{self, PathExtensions};
+use std::str;
+
+use {merge_file, Error, ErrorCode, MergeFileInput, Oid, Repository};
+use util::{self, io_error};
+
+/// The length of the conflict markers which are recognized.
+const MARKER_SIZE: usize = 7;
+
+/// Support for reusing recorded resolutions of conflicts, like `git rerere`.
+///
+/// A conflict is identified by the hunks between its conflict markers, so a
+/// resolution recorded while resolving a merge is reused when the same
+/// conflict shows up again, for instance when the merge is redone or the
+/// branch rebased. Conflicts and resolutions are kept in the `rr-cache`
+/// directory shared by all worktrees, laid out the same way git does, while
+/// the conflicts of the ongoing merge are tracked in `MERGE_RR`.
+pub struct Rerere<'repo> {
+    repo: &'repo Repository,
+}
+
+impl<'repo> Rerere<'repo> {
+    /// Create a handle for recording and replaying the resolutions of
+    /// conflicts in the given repository.
+    pub fn new(repo: &'repo Repository) -> Rerere<'repo> {
+        Rerere { repo: repo }
+    }
+
+    /// Determine whether rerere is enabled for the repository.
+    ///
+    /// Like git, this honors `rerere.enabled` and, if it is not set, treats
+    /// rerere as enabled if the `rr-cache` directory exists.
+    pub fn enabled(&self) -> Result<bool, Error> {
+        let config = try!(self.repo.config());
+        match config.get_bool("rerere.enabled") {
+            Ok(enabled) => Ok(enabled),
+            Err(ref e) if e.code() == ErrorCode::NotFound => {
+                Ok(self.cache().is_dir())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Enable or disable rerere for the repository by setting
+    /// `rerere.enabled` in its configuration.
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), Error> {
+        let mut config = try!(self.repo.config());
+        config.set_bool("rerere.enabled", enabled)
+    }
+
+    /// Record the conflicts in the index, and the resolutions of previously
+    /// recorded conflicts which have since been resolved.
+    ///
+    /// A conflict seen for the first time has the conflicted file recorded
+    /// as its preimage. A file recorded earlier which no longer contains any
+    /// conflict markers is taken to be resolved and recorded as the
+    /// postimage of its conflict. This does nothing if rerere is disabled.
+    pub fn record(&self) -> Result<(), Error> {
+        if !try!(self.enabled()) { return Ok(()) }
+        let workdir = try!(self.workdir());
+        let mut merge_rr = try!(self.read_merge_rr());
+
+        for path in try!(self.conflicts()).into_iter() {
+            let file = workdir.join(&path);
+            if !file.exists() ||
+               merge_rr.iter().any(|&(_, ref p)| *p == path) {
+                continue
+            }
+            let data = try!(read(&file));
+            let (id, preimage) = match normalize(data.as_slice()) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let file = self.image(id.as_slice(), "preimage");
+            if !file.exists() {
+                try!(write(&file, preimage.as_slice()));
+            }
+            merge_rr.push((id, path));
+        }
+
+        let mut remaining = Vec::new();
+        for (id, path) in merge_rr.into_iter() {
+            let file = workdir.join(&path);
+            // a file removed while resolving has nothing to record
+            if !file.exists() { continue }
+            let data = try!(read(&file));
+            if has_markers(data.as_slice()) {
+                remaining.push((id, path));
+            } else {
+                try!(write(&self.image(id.as_slice(), "postimage"),
+                           data.as_slice()));
+            }
+        }
+        self.write_merge_rr(remaining.as_slice())
+    }
+
+    /// Resolve the conflicts in the working directory for which a resolution
+    /// has been recorded, returning the paths of the files resolved.
+    ///
+    /// The recorded resolution is merged into each conflicted file, which is
+    /// left alone if this itself conflicts. The index is not updated, so the
+    /// resolved files still need to be added to it. This does nothing if
+    /// rerere is disabled.
+    pub fn apply(&self) -> Result<Vec<Path>, Error> {
+        let mut resolved = Vec::new();
+        if !try!(self.enabled()) { return Ok(resolved) }
+        let workdir = try!(self.workdir());
+
+        for path in try!(self.conflicts()).into_iter() {
+            let file = workdir.join(&path);
+            if !file.exists() { continue }
+            let data = try!(read(&file));
+            let (id, thisimage) = match normalize(data.as_slice()) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let preimage = self.image(id.as_slice(), "preimage");
+            let postimage = self.image(id.as_slice(), "postimage");
+            if !preimage.exists() || !postimage.exists() { continue }
+            let preimage = try!(read(&preimage));
+            let postimage = try!(read(&postimage));
+
+            let ancestor = MergeFileInput::new(preimage.as_slice());
+            let ours = MergeFileInput::new(thisimage.as_slice());
+            let theirs = MergeFileInput::new(postimage.as_slice());
+            let result = try!(merge_file(&ancestor, &ours, &theirs, None));
+            if !result.is_automergeable() { continue }
+            try!(write(&file, result.content()));
+            resolved.push(path);
+        }
+        Ok(resolved)
+    }
+
+    /// Forget the conflicts of the ongoing merge, like `git rerere clear`
+    /// when a merge is aborted.
+    ///
+    /// Conflicts which have not been resolved are removed from `rr-cache`,
+    /// while recorded resolutions are kept.
+    pub fn clear(&self) -> Result<(), Error> {
+        for (id, _) in try!(self.read_merge_rr()).into_iter() {
+            let preimage = self.image(id.as_slice(), "preimage");
+            if self.image(id.as_slice(), "postimage").exists() ||
+               !preimage.exists() {
+                continue
+            }
+            try!(fs::unlink(&preimage).map_err(io_error));
+            // other variants of the conflict may still live in the directory
+            let _ = fs::rmdir(&preimage.dir_path());
+        }
+        self.write_merge_rr(&[])
+    }
+
+    fn workdir(&self) -> Result<&Path, Error> {
+        self.repo.workdir().ok_or_else(|| {
+            Error::from_str("cannot use rerere in a bare repository")
+        })
+    }
+
+    // the cache is shared by all worktrees, unlike `MERGE_RR`
+    fn cache(&self) -> Path {
+        let dir = self.repo.commondir().unwrap_or_else(|| self.repo.path());
+        dir.join("rr-cache")
+    }
+
+    // git records further variants of a conflict with the same id as
+    // `<id>.<n>`, whose images are suffixed the same way
+    fn image(&self, id: &str, name: &str) -> Path {
+        match id.find('.') {
+            Some(i) => {
+                let name = format!("{}{}", name, &id[i..]);
+                self.cache().join(&id[..i]).join(name)
+            }
+            None => self.cache().join(id).join(name),
+        }
+    }
+
+    /// Paths with both our and their side of a conflict in the index.
+    fn conflicts(&self) -> Result<Vec<Path>, Error> {
+        let index = try!(self.repo.index());
+        let mut ret = Vec::new();
+        for entry in index.iter() {
+            // the stage of an entry is kept in bits 12 and 13 of its flags
+            if (entry.flags >> 12) & 3 != 3 { continue }
+            let path = Path::new(entry.path);
+            if index.get_path(&path, 2).is_some() {
+                ret.push(path);
+            }
+        }
+        Ok(ret)
+    }
+
+    fn read_merge_rr(&self) -> Result<Vec<(String, Path)>, Error> {
+        let file = self.repo.path().join("MERGE_RR");
+        if !file.exists() { return Ok(Vec::new()) }
+        let data = try!(read(&file));
+        let mut ret = Vec::new();
+        for record in data.split(|b| *b == 0).filter(|r| !r.is_empty()) {
+            let tab = record.iter().position(|b| *b == b'\t');
+            let id = tab.and_then(|i| str::from_utf8(&record[..i]).ok());
+            let (tab, id) = match (tab, id) {
+                (Some(tab), Some(id)) => (tab, id),
+                _ => return Err(Error::from_str("corrupt MERGE_RR")),
+            };
+            ret.push((id.to_string(), Path::new(&record[tab + 1..])));
+        }
+        Ok(ret)
+    }
+
+    fn write_merge_rr(&self, entries: &[(String, Path)]) -> Result<(), Error> {
+        let file = self.repo.path().join("MERGE_RR");
+        if entries.is_empty() {
+            if file.exists() {
+                try!(fs::unlink(&file).map_err(io_error));
+            }
+            return Ok(())
+        }
+        let mut data = Vec::new();
+        for &(ref id, ref path) in entries.iter() {
+            data.push_all(id.as_bytes());
+            data.push(b'\t');
+            data.push_all(path.as_vec());
+            data.push(0);
+        }
+        write(&file, data.as_slice())
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    File::open(path).read_to_end().map_err(io_error)
+}
+
+fn write(path: &Path, data: &[u8]) -> Result<(), Error> {
+    try!(fs::mkdir_recursive(&path.dir_path(), old_io::USER_RWX)
+            .map_err(io_error));
+    File::create(path).write_all(data).map_err(io_error)
+}
+
+fn is_marker(line: &[u8], c: u8) -> bool {
+    if line.len() < MARKER_SIZE ||
+       line[..MARKER_SIZE].iter().any(|b| *b != c) {
+        return false
+    }
+    let rest = &line[MARKER_SIZE..];
+    rest.iter().all(|b| *b == b'\r' || *b == b'\n') ||
+        (c != b'=' && rest[0] == b' ')
+}
+
+fn has_markers(data: &[u8]) -> bool {
+    lines(data).any(|line| is_marker(line, b'<') || is_marker(line, b'>'))
+}
+
+/// An iterator over the lines of a file, including their line endings.
+struct Lines<'a> {
+    data: &'a [u8],
+}
+
+fn lines(data: &[u8]) -> Lines { Lines { data: data } }
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a [u8];
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if self.data.is_empty() { return None }
+        let end = match self.data.iter().position(|b| *b == b'\n') {
+            Some(i) => i + 1,
+            None => self.data.len(),
+        };
+        let line = &self.data[..end];
+        self.data = &self.data[end..];
+        Some(line)
+    }
+}
+
+/// Normalize the conflicts in a file the way git does, returning the id of
+/// the conflicts along with the normalized file, or `None` if the file
+/// contains no complete conflict.
+///
+/// Each conflict is reduced to its two sides, ordered so that the same
+/// conflict is recognized whichever side of a merge it came from, and any
+/// labels and common ancestor section are dropped.
+fn normalize(data: &[u8]) -> Option<(String, Vec<u8>)> {
+    #[derive(PartialEq)]
+    enum State { Outside, One, Base, Two }
+
+    let mut state = State::Outside;
+    let mut out = Vec::new();
+    let mut hash = Vec::new();
+    let mut one = Vec::new();
+    let mut two = Vec::new();
+    let mut hunks = 0;
+    for line in lines(data) {
+        if state == State::Outside && is_marker(line, b'<') {
+            state = State::One;
+        } else if state == State::One && is_marker(line, b'|') {
+            state = State::Base;
+        } else if (state == State::One || state == State::Base) &&
+                  is_marker(line, b'=') {
+            state = State::Two;
+        } else if state == State::Two && is_marker(line, b'>') {
+            if one > two {
+                mem::swap(&mut one, &mut two);
+            }
+            hash.push_all(one.as_slice());
+            hash.push(0);
+            hash.push_all(two.as_slice());
+            hash.push(0);
+            out.push_all(b"<<<<<<<\n");
+            out.push_all(one.as_slice());
+            out.push_all(b"=======\n");
+            out.push_all(two.as_slice());
+            out.push_all(b">>>>>>>\n");
+            one.truncate(0);
+            two.truncate(0);
+            hunks += 1;
+            state = State::Outside;
+        } else {
+            match state {
+                State::Outside => out.push_all(line),
+                State::One => one.push_all(line),
+                State::Base => {}
+                State::Two => two.push_all(line),
+            }
+        }
+    }
+    if hunks == 0 || state != State::Outside {
+        return None
+    }
+    let id = Oid::from_bytes(&util::sha1(hash.as_slice())).unwrap();
+    Some((id.to_string(), out))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::File;
+    use std::old_io::fs::{self, PathExtensions};
+    use {Oid, Repository, ResetType};
+
+    fn commit(repo: &Repository, parent: Oid, foo: &str) -> Oid {
+        let sig = repo.signature().unwrap();
+        let parent = repo.find_commit(parent).unwrap();
+        let mut builder = repo.treebuilder(None).unwrap();
+        builder.insert("foo", repo.blob(foo.as_bytes()).unwrap(),
+                       0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        repo.commit(None, &sig, &sig, foo, &tree, &[&parent]).unwrap()
+    }
+
+    fn conflict(repo: &Repository, ours: Oid, theirs: Oid) {
+        let ours = repo.find_object(ours, None).unwrap();
+        repo.reset(&ours, ResetType::Hard, None).unwrap();
+        let theirs = repo.find_annotated_commit(theirs).unwrap();
+        repo.merge(&[&theirs], None, None).unwrap();
+        assert!(repo.index().unwrap().has_conflicts());
+    }
+
+    #[test]
+    fn normalize() {
+        let a = b"x\n<<<<<<< HEAD\none\n=======\ntwo\n>>>>>>> theirs\ny\n";
+        let b = b"x\n<<<<<<< ours\ntwo\n||||||| base\nzero\n=======\none\n\
+                  >>>>>>> b\ny\n";
+        let (id, out) = super::normalize(a).unwrap();
+        assert_eq!(out.as_slice(),
+                   b"x\n<<<<<<<\none\n=======\ntwo\n>>>>>>>\ny\n");
+        assert_eq!(super::normalize(b), Some((id, out)));
+
+        assert!(super::normalize(b"no conflict\n").is_none());
+        assert!(super::normalize(b"<<<<<<<\none\n=======\n").is_none());
+        assert!(super::normalize(b"<<<<<<<<\none\n=======\n\
+                                   >>>>>>>\n").is_none());
+    }
+
+    #[test]
+    fn smoke() {
+        let (td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let base = commit(&repo, head, "a\nb\nc\n");
+        let ours = commit(&repo, base, "a\nours\nc\n");
+        let theirs = commit(&repo, base, "a\ntheirs\nc\n");
+        let foo = td.path().join("foo");
+        let cache = repo.path().join("rr-cache");
+        let merge_rr = repo.path().join("MERGE_RR");
+
+        let rerere = repo.rerere();
+        assert!(!rerere.enabled().unwrap());
+        conflict(&repo, ours, theirs);
+        rerere.record().unwrap();
+        assert!(!cache.exists());
+
+        rerere.set_enabled(true).unwrap();
+        assert!(rerere.enabled().unwrap());
+        rerere.record().unwrap();
+        assert!(merge_rr.exists());
+        assert_eq!(rerere.apply().unwrap(), Vec::<Path>::new());
+
+        File::create(&foo).write_str("a\nresolved\nc\n").unwrap();
+        rerere.record().unwrap();
+        assert!(!merge_rr.exists());
+
+        repo.cleanup_state().unwrap();
+        conflict(&repo, ours, theirs);
+        assert_eq!(rerere.apply().unwrap(), vec![Path::new("foo")]);
+        assert_eq!(File::open(&foo).read_to_string().unwrap(),
+                   "a\nresolved\nc\n");
+
+        // the same conflict the other way around is recognized as well
+        repo.cleanup_state().unwrap();
+        conflict(&repo, theirs, ours);
+        assert_eq!(rerere.apply().unwrap(), vec![Path::new("foo")]);
+        assert_eq!(File::open(&foo).read_to_string().unwrap(),
+                   "a\nresolved\nc\n");
+
+        // an unresolved conflict is dropped again by clear
+        let other = commit(&repo, base, "a\nother\nc\n");
+        repo.cleanup_state().unwrap();
+        conflict(&repo, ours, other);
+        rerere.record().unwrap();
+        assert!(merge_rr.exists());
+        rerere.clear().unwrap();
+        assert!(!merge_rr.exists());
+        assert_eq!(fs::readdir(&cache).unwrap().len(), 1);
+    }
+}
@@ -1,8 +1,8 @@
 use std::marker;
 use std::ffi::CString;
-use libc::c_uint;
+use libc::{c_int, c_uint, c_void};
 
-use {raw, Error, Sort, Oid};
+use {raw, panic, Error, ErrorCode, ObjectType, Oid, Repository, Sort};
 use util::Binding;
 
 /// A revwalk allows traversal of the commit graph defined by including one or
@@ -10,21 +10,28 @@ use util::Binding;
 pub struct Revwalk<'repo> {
     raw: *mut raw::git_revwalk,
     marker: marker::ContravariantLifetime<'repo>,
+    hide_cb: Option<Box<Box<HideCb<'repo>>>>,
 }
 
+/// Callback deciding whether a commit, and its ancestors, should be hidden
+/// from a revwalk.
+///
+/// Returning `true` hides the commit with the given id.
+pub type HideCb<'a> = FnMut(Oid) -> bool + 'a;
+
 impl<'repo> Revwalk<'repo> {
     /// Reset a revwalk to allow re-configuring it.
     ///
     /// The revwalk is automatically reset when iteration of its commits
     /// completes.
     pub fn reset(&mut self) {
-        unsafe { raw::git_revwalk_reset(self.raw()) }
+        unsafe { raw::git_revwalk_reset(self.raw()); }
     }
 
     /// Set the order in which commits are visited.
     pub fn set_sorting(&mut self, sort_mode: Sort) {
         unsafe {
-            raw::git_revwalk_sorting(self.raw(), sort_mode.bits() as c_uint)
+            raw::git_revwalk_sorting(self.raw(), sort_mode.bits() as c_uint);
         }
     }
 
@@ -32,7 +39,7 @@ impl<'repo> Revwalk<'repo> {
     ///
     /// No parents other than the first for each commit will be enqueued.
     pub fn simplify_first_parent(&mut self) {
-        unsafe { raw::git_revwalk_simplify_first_parent(self.raw) }
+        unsafe { raw::git_revwalk_simplify_first_parent(self.raw); }
     }
 
     /// Mark a commit to start traversal from.
@@ -90,6 +97,55 @@ impl<'repo> Revwalk<'repo> {
         Ok(())
     }
 
+    /// Push and hide commits according to revisions given the way
+    /// `git rev-list` and `git log` accept them on the command line.
+    ///
+    /// Each revision is one of:
+    ///
+    /// * `A`, to include `A` and its ancestors,
+    /// * `^A`, to exclude `A` and its ancestors,
+    /// * `A..B`, to include the ancestors of `B` which are not ancestors of
+    ///   `A`,
+    /// * `A...B`, to include the commits reachable from either `A` or `B`
+    ///   but not from both, by excluding their merge bases.
+    ///
+    /// An omitted end of a range defaults to `HEAD`, and each end is in any
+    /// form accepted by `revparse_single` which peels to a commit. Unlike
+    /// `push_range`, this needs the repository being walked, in order to
+    /// resolve the revisions and compute merge bases.
+    pub fn push_revs(&mut self, repo: &Repository, revs: &[&str])
+                     -> Result<(), Error> {
+        for rev in revs.iter() {
+            match (rev.find("..."), rev.find("..")) {
+                (Some(i), _) => {
+                    let a = try!(commit_id(repo, &rev[..i]));
+                    let b = try!(commit_id(repo, &rev[i + 3..]));
+                    try!(self.push(a));
+                    try!(self.push(b));
+                    match repo.merge_bases(a, b) {
+                        Ok(bases) => {
+                            for base in bases.iter() {
+                                try!(self.hide(*base));
+                            }
+                        }
+                        // unrelated histories have no merge base to hide
+                        Err(ref e) if e.code() == ErrorCode::NotFound => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                (None, Some(i)) => {
+                    try!(self.hide(try!(commit_id(repo, &rev[..i]))));
+                    try!(self.push(try!(commit_id(repo, &rev[i + 2..]))));
+                }
+                (None, None) if rev.starts_with("^") => {
+                    try!(self.hide(try!(commit_id(repo, &rev[1..]))));
+                }
+                (None, None) => try!(self.push(try!(commit_id(repo, *rev)))),
+            }
+        }
+        Ok(())
+    }
+
     /// Push the OID pointed to by a reference
     ///
     /// The reference must point to a committish.
@@ -137,6 +193,26 @@ impl<'repo> Revwalk<'repo> {
         Ok(())
     }
 
+    /// Set a callback which decides whether each commit encountered, along
+    /// with all of its ancestors, should be hidden from the walk.
+    ///
+    /// This is consulted in addition to the commits hidden with `hide` and
+    /// its variants, and allows the set of hidden commits to be computed
+    /// while walking, such as all commits older than a given time or those
+    /// which are already recorded elsewhere.
+    pub fn hide_callback<F>(&mut self, cb: F) -> Result<(), Error>
+                            where F: FnMut(Oid) -> bool + 'repo {
+        let mut cb = Box::new(Box::new(cb) as Box<HideCb<'repo>>);
+        let payload = &mut *cb as *mut Box<HideCb<'repo>> as *mut c_void;
+        unsafe {
+            let f: raw::git_revwalk_hide_cb = hide_cb;
+            try_call!(raw::git_revwalk_add_hide_cb(self.raw, Some(f),
+                                                   payload));
+        }
+        self.hide_cb = Some(cb);
+        Ok(())
+    }
+
     /// Hide the OID pointed to by a reference.
     ///
     /// The reference must point to a committish.
@@ -149,12 +225,19 @@ impl<'repo> Revwalk<'repo> {
     }
 }
 
+fn commit_id(repo: &Repository, spec: &str) -> Result<Oid, Error> {
+    let spec = if spec.is_empty() { "HEAD" } else { spec };
+    let obj = try!(repo.revparse_single(spec));
+    Ok(try!(obj.peel(ObjectType::Commit)).id())
+}
+
 impl<'repo> Binding for Revwalk<'repo> {
     type Raw = *mut raw::git_revwalk;
     unsafe fn from_raw(raw: *mut raw::git_revwalk) -> Revwalk<'repo> {
         Revwalk {
             raw: raw,
             marker: marker::ContravariantLifetime,
+            hide_cb: None,
         }
     }
     fn raw(&self) -> *mut raw::git_revwalk { self.raw }
@@ -174,7 +257,7 @@ impl<'repo> Iterator for Revwalk<'repo> {
         unsafe {
             match raw::git_revwalk_next(&mut out, self.raw()) {
                 0 => (),
-                _ => return None,
+                _ => { panic::check(); return None }
             }
 
             Some(Binding::from_raw(&out as *const _))
@@ -182,6 +265,15 @@ impl<'repo> Iterator for Revwalk<'repo> {
     }
 }
 
+extern fn hide_cb(id: *const raw::git_oid, payload: *mut c_void) -> c_int {
+    unsafe {
+        let callback = &mut *(payload as *mut Box<HideCb>);
+        let id = Binding::from_raw(id);
+        let hide = panic::wrap(|| callback(id)).unwrap_or(true);
+        hide as c_int
+    }
+}
+
 #[cfg(test)]
 mod tests {
     extern crate std;
@@ -210,4 +302,72 @@ mod tests {
         walk.hide_head().unwrap();
         assert_eq!(walk.by_ref().count(), 0);
     }
+
+    #[test]
+    fn hide_callback() {
+        let (_td, repo) = ::test::repo_init();
+        let first = repo.head().unwrap().target().unwrap();
+        let parent = repo.find_commit(first).unwrap();
+        let tree = parent.tree().unwrap();
+        let sig = repo.signature().unwrap();
+        let second = repo.commit(Some("HEAD"), &sig, &sig, "second", &tree,
+                                 &[&parent]).unwrap();
+
+        let mut walk = repo.revwalk().unwrap();
+        walk.hide_callback(move |id| id == first).unwrap();
+        walk.push(second).unwrap();
+        assert_eq!(walk.by_ref().collect::<Vec<_>>(), vec![second]);
+
+        let mut walk = repo.revwalk().unwrap();
+        walk.hide_callback(|_| false).unwrap();
+        walk.push(second).unwrap();
+        assert_eq!(walk.by_ref().collect::<Vec<_>>(), vec![second, first]);
+    }
+
+    #[test]
+    #[should_fail]
+    fn hide_callback_panic() {
+        let (_td, repo) = ::test::repo_init();
+        let head = repo.head().unwrap().target().unwrap();
+        let mut walk = repo.revwalk().unwrap();
+        walk.hide_callback(|_| panic!()).unwrap();
+        walk.push(head).unwrap();
+        walk.by_ref().count();
+    }
+
+    #[test]
+    fn push_revs() {
+        let (_td, repo) = ::test::repo_init();
+        let base = repo.head().unwrap().target().unwrap();
+        let parent = repo.find_commit(base).unwrap();
+        let tree = parent.tree().unwrap();
+        let sig = repo.signature().unwrap();
+        let a = repo.commit(Some("refs/heads/a"), &sig, &sig, "a", &tree,
+                            &[&parent]).unwrap();
+        let b = repo.commit(Some("refs/heads/b"), &sig, &sig, "b", &tree,
+                            &[&parent]).unwrap();
+        repo.set_head("refs/heads/a").unwrap();
+
+        let walk = |revs: &[&str]| {
+            let mut walk = repo.revwalk().unwrap();
+            walk.push_revs(&repo, revs).unwrap();
+            let mut ids = walk.collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        let sorted = |mut ids: Vec<::Oid>| { ids.sort(); ids };
+
+        assert_eq!(walk(&["b"]), sorted(vec![b, base]));
+        assert_eq!(walk(&["a..b"]), vec![b]);
+        assert_eq!(walk(&["..b"]), vec![b]);
+        assert_eq!(walk(&["b.."]), vec![a]);
+        assert_eq!(walk(&["b", "^a"]), vec![b]);
+        assert_eq!(walk(&["^master", "a", "b"]), sorted(vec![a, b]));
+        assert_eq!(walk(&["a...b"]), sorted(vec![a, b]));
+        assert_eq!(walk(&["a...master"]), vec![a]);
+        assert_eq!(walk(&["...b"]), sorted(vec![a, b]));
+
+        let mut walk = repo.revwalk().unwrap();
+        assert!(walk.push_revs(&repo, &["nope..b"]).is_err());
+    }
 }
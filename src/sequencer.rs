@@ -0,0 +1,87 @@
+use {Error, Oid, Repository, SequencerAction};
+
+/// A single step which remains to be applied by an interrupted multi-commit
+/// cherry-pick or revert, as listed in `.git/sequencer/todo`.
+pub struct SequencerStep {
+    /// Whether the commit is to be cherry-picked or reverted.
+    pub action: SequencerAction,
+    /// The id of the commit to apply.
+    pub id: Oid,
+    /// The summary of the commit, as recorded in the todo list.
+    pub summary: String,
+}
+
+/// Parse the contents of a sequencer todo list, resolving the abbreviated
+/// commit ids it contains against `repo`.
+pub fn parse_todo(repo: &Repository, todo: &str)
+                  -> Result<Vec<SequencerStep>, Error> {
+    let mut steps = Vec::new();
+    for line in todo.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("#") { continue }
+        let (action, rest) = split_word(line);
+        let action = match action {
+            "p" | "pick" => SequencerAction::Pick,
+            "revert" => SequencerAction::Revert,
+            _ => {
+                let msg = format!("invalid sequencer todo line: {}", line);
+                return Err(Error::from_str(msg.as_slice()))
+            }
+        };
+        let (id, summary) = split_word(rest);
+        let id = if id.len() == 40 {
+            try!(Oid::from_str(id))
+        } else {
+            try!(repo.find_commit_by_prefix(id)).id()
+        };
+        steps.push(SequencerStep {
+            action: action,
+            id: id,
+            summary: summary.to_string(),
+        });
+    }
+    Ok(steps)
+}
+
+fn split_word(s: &str) -> (&str, &str) {
+    match s.find(|c: char| c.is_whitespace()) {
+        Some(i) => (&s[..i], s[i..].trim_left()),
+        None => (s, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::{self, fs, File};
+    use SequencerAction;
+
+    #[test]
+    fn todo() {
+        let (_td, repo) = ::test::repo_init();
+        let id = repo.head().unwrap().target().unwrap();
+        assert!(repo.cherrypick_head().unwrap().is_none());
+        assert!(repo.revert_head().unwrap().is_none());
+        assert_eq!(repo.sequencer_todo().unwrap().len(), 0);
+
+        File::create(&repo.path().join("CHERRY_PICK_HEAD"))
+             .write_str(format!("{}\n", id).as_slice()).unwrap();
+        assert_eq!(repo.cherrypick_head().unwrap(), Some(id));
+
+        let hex = id.to_string();
+        let dir = repo.path().join("sequencer");
+        fs::mkdir(&dir, old_io::USER_DIR).unwrap();
+        File::create(&dir.join("todo")).write_str(format!(
+            "pick {} initial\n# comment\n\nrevert {}  initial\n",
+            &hex[..7], hex).as_slice()).unwrap();
+        let steps = repo.sequencer_todo().unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].action, SequencerAction::Pick);
+        assert_eq!(steps[0].id, id);
+        assert_eq!(steps[0].summary.as_slice(), "initial");
+        assert_eq!(steps[1].action, SequencerAction::Revert);
+        assert_eq!(steps[1].summary.as_slice(), "initial");
+
+        File::create(&dir.join("todo")).write_str("squash 1234567\n").unwrap();
+        assert!(repo.sequencer_todo().is_err());
+    }
+}
@@ -19,6 +19,10 @@ pub struct Signature<'a> {
     owned: bool,
 }
 
+// An owned signature is plain data, but a borrowed one points into another
+// object which may not be shared across threads.
+unsafe impl Send for Signature<'static> {}
+
 impl<'a> Signature<'a> {
     /// Create a new action signature with a timestamp of 'now'.
     ///
@@ -0,0 +1,92 @@
+//! Verification of SSH signatures with `ssh-keygen`, enabled by the
+//! `ssh-keygen` feature.
+
+use std::old_io::{Command, File, TempDir};
+use std::str;
+use std::thread;
+
+use {Error, SignatureVerification};
+use util::io_error;
+
+/// Verify an SSH signature with `ssh-keygen`, the way git does when
+/// `gpg.format` is `ssh`.
+///
+/// The signer is looked up among the principals in the `allowed_signers`
+/// file, in the format used by `gpg.ssh.allowedSignersFile`, and the
+/// signature is only valid if it was made by one of them. This is meant to
+/// be used as the verifier passed to `Repository::verify_commit_signature`.
+/// An error is only returned if `ssh-keygen` cannot be run at all.
+pub fn ssh_keygen_verify(allowed_signers: &Path,
+                         signature: &[u8],
+                         signed_data: &[u8])
+                         -> Result<SignatureVerification, Error> {
+    let td = try!(TempDir::new("git2-rs").map_err(io_error));
+    let sig = td.path().join("signature");
+    try!(File::create(&sig).write_all(signature).map_err(io_error));
+
+    let out = try!(Command::new("ssh-keygen")
+                           .arg("-Y").arg("find-principals")
+                           .arg("-f").arg(allowed_signers)
+                           .arg("-s").arg(&sig)
+                           .output().map_err(io_error));
+    let principal = str::from_utf8(out.output.as_slice()).ok().and_then(|s| {
+        s.lines().next().map(|s| s.trim().to_string())
+    });
+    let principal = match principal {
+        Some(ref p) if out.status.success() && !p.is_empty() => p.clone(),
+        _ => return Ok(SignatureVerification::new(false, None)),
+    };
+
+    let mut p = try!(Command::new("ssh-keygen")
+                             .arg("-Y").arg("verify")
+                             .arg("-f").arg(allowed_signers)
+                             .arg("-I").arg(principal.as_slice())
+                             .arg("-n").arg("git")
+                             .arg("-s").arg(&sig)
+                             .spawn().map_err(io_error));
+    // Feed the data from another thread while the output is read, so that
+    // ssh-keygen cannot block on a full output pipe
+    let mut input = p.stdin.take().unwrap();
+    let _writer = thread::scoped(move || {
+        // Ignore write errors as ssh-keygen exits early on a bad signature
+        let _ = input.write_all(signed_data);
+    });
+    let out = try!(p.wait_with_output().map_err(io_error));
+    Ok(SignatureVerification::new(out.status.success(),
+                                  Some(principal.as_slice())))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::{Command, File, TempDir};
+    use super::ssh_keygen_verify;
+
+    #[test]
+    fn verify() {
+        let td = TempDir::new("test").unwrap();
+        let key = td.path().join("key");
+        assert!(Command::new("ssh-keygen").arg("-q").arg("-t").arg("ed25519")
+                        .arg("-N").arg("").arg("-f").arg(&key)
+                        .status().unwrap().success());
+        let data = vec![b'x'; 1 << 20];
+        let file = td.path().join("data");
+        File::create(&file).write_all(data.as_slice()).unwrap();
+        assert!(Command::new("ssh-keygen").arg("-Y").arg("sign")
+                        .arg("-f").arg(&key).arg("-n").arg("git").arg(&file)
+                        .status().unwrap().success());
+        let sig = File::open(&td.path().join("data.sig")).read_to_end()
+                                                         .unwrap();
+        let public = File::open(&td.path().join("key.pub")).read_to_string()
+                                                           .unwrap();
+        let signers = td.path().join("allowed_signers");
+        File::create(&signers).write_str(format!("me@example.com {}", public)
+                                             .as_slice()).unwrap();
+
+        let res = ssh_keygen_verify(&signers, sig.as_slice(),
+                                    data.as_slice()).unwrap();
+        assert!(res.is_valid());
+        assert_eq!(res.signer(), Some("me@example.com"));
+        let res = ssh_keygen_verify(&signers, sig.as_slice(), b"y").unwrap();
+        assert!(!res.is_valid());
+    }
+}
@@ -0,0 +1,208 @@
+use std::mem;
+use libc::{c_int, c_void};
+
+use {raw, panic, StashApplyFlags, StashApplyProgress};
+use build::CheckoutBuilder;
+
+/// Options to specify when applying a stash with `Repository::stash_apply`
+/// or `Repository::stash_pop`.
+pub struct StashApplyOptions<'cb> {
+    raw: raw::git_stash_apply_options,
+    checkout_options: Option<CheckoutBuilder<'cb>>,
+    progress: Option<Box<StashApplyProgressCb<'cb>>>,
+}
+
+/// Callback invoked as a stash is being applied to report on the stage of
+/// the operation.
+///
+/// Returning `false` aborts the application of the stash.
+pub type StashApplyProgressCb<'a> = FnMut(StashApplyProgress) -> bool + 'a;
+
+impl<'cb> StashApplyOptions<'cb> {
+    /// Creates a new default set of stash application options.
+    pub fn new() -> StashApplyOptions<'cb> {
+        let mut opts = StashApplyOptions {
+            raw: unsafe { mem::zeroed() },
+            checkout_options: None,
+            progress: None,
+        };
+        assert_eq!(unsafe {
+            raw::git_stash_apply_init_options(&mut opts.raw,
+                                raw::GIT_STASH_APPLY_OPTIONS_VERSION)
+        }, 0);
+        opts
+    }
+
+    /// Set the flags which control how the stash is applied.
+    pub fn flags(&mut self, flags: StashApplyFlags)
+                 -> &mut StashApplyOptions<'cb> {
+        self.raw.flags = flags.bits();
+        self
+    }
+
+    /// Try to restore the changes which were staged in the index as well as
+    /// those in the working directory.
+    ///
+    /// Defaults to false.
+    pub fn reinstantiate_index(&mut self, reinstate: bool)
+                               -> &mut StashApplyOptions<'cb> {
+        if reinstate {
+            self.raw.flags |= raw::GIT_STASH_APPLY_REINSTATE_INDEX;
+        } else {
+            self.raw.flags &= !raw::GIT_STASH_APPLY_REINSTATE_INDEX;
+        }
+        self
+    }
+
+    /// Options to control how files are written to the working directory
+    /// when the stash is checked out.
+    pub fn checkout_options(&mut self, opts: CheckoutBuilder<'cb>)
+                            -> &mut StashApplyOptions<'cb> {
+        self.checkout_options = Some(opts);
+        self
+    }
+
+    /// Set a callback to receive notifications of the progress of applying
+    /// the stash.
+    pub fn progress_cb<F>(&mut self, cb: F) -> &mut StashApplyOptions<'cb>
+                          where F: FnMut(StashApplyProgress) -> bool + 'cb {
+        self.progress = Some(Box::new(cb) as Box<StashApplyProgressCb<'cb>>);
+        self
+    }
+
+    /// Acquire a pointer to the underlying raw options.
+    ///
+    /// This function is unsafe as the pointer is only valid so long as this
+    /// structure is not moved, modified, or used elsewhere.
+    pub unsafe fn raw(&mut self) -> *const raw::git_stash_apply_options {
+        match self.checkout_options {
+            Some(ref mut opts) => {
+                opts.configure(&mut self.raw.checkout_options);
+            }
+            None => {}
+        }
+        if self.progress.is_some() {
+            let f: raw::git_stash_apply_progress_cb = progress_cb;
+            self.raw.progress_cb = Some(f);
+            self.raw.progress_payload = self as *mut _ as *mut _;
+        }
+        &self.raw as *const _
+    }
+}
+
+extern fn progress_cb(progress: raw::git_stash_apply_progress_t,
+                      data: *mut c_void) -> c_int {
+    unsafe {
+        let payload = &mut *(data as *mut StashApplyOptions);
+        let callback = match payload.progress {
+            Some(ref mut c) => c,
+            None => return 0,
+        };
+        let progress = match progress {
+            raw::GIT_STASH_APPLY_PROGRESS_NONE => StashApplyProgress::None,
+            raw::GIT_STASH_APPLY_PROGRESS_LOADING_STASH =>
+                StashApplyProgress::LoadingStash,
+            raw::GIT_STASH_APPLY_PROGRESS_ANALYZE_INDEX =>
+                StashApplyProgress::AnalyzeIndex,
+            raw::GIT_STASH_APPLY_PROGRESS_ANALYZE_MODIFIED =>
+                StashApplyProgress::AnalyzeModified,
+            raw::GIT_STASH_APPLY_PROGRESS_ANALYZE_UNTRACKED =>
+                StashApplyProgress::AnalyzeUntracked,
+            raw::GIT_STASH_APPLY_PROGRESS_CHECKOUT_UNTRACKED =>
+                StashApplyProgress::CheckoutUntracked,
+            raw::GIT_STASH_APPLY_PROGRESS_CHECKOUT_MODIFIED =>
+                StashApplyProgress::CheckoutModified,
+            raw::GIT_STASH_APPLY_PROGRESS_DONE => StashApplyProgress::Done,
+        };
+        match panic::wrap(|| callback(progress)) {
+            Some(true) => 0,
+            _ => -1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::File;
+    use std::old_io::fs::PathExtensions;
+    use {Repository, StashApplyOptions, StashApplyProgress};
+    use {STASH_INCLUDE_UNTRACKED, STASH_DEFAULT};
+
+    fn stash_list(repo: &Repository) -> Vec<String> {
+        let mut ret = Vec::new();
+        repo.stash_foreach(|i, msg, _id| {
+            assert_eq!(i, ret.len());
+            ret.push(msg.to_string());
+            true
+        }).unwrap();
+        ret
+    }
+
+    #[test]
+    fn save_apply_drop() {
+        let (td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let foo = td.path().join("foo");
+        File::create(&foo).write_str("foo\n").unwrap();
+
+        // nothing is tracked yet, so untracked files must be asked for
+        assert!(repo.stash_save(&sig, Some("nope"), STASH_DEFAULT).is_err());
+        let first = repo.stash_save(&sig, Some("first"),
+                                    STASH_INCLUDE_UNTRACKED).unwrap();
+        assert!(!foo.exists());
+        File::create(&foo).write_str("bar\n").unwrap();
+        repo.stash_save(&sig, Some("second"), STASH_INCLUDE_UNTRACKED).unwrap();
+        assert_eq!(stash_list(&repo).len(), 2);
+        assert!(stash_list(&repo)[1].as_slice().ends_with("first"));
+
+        // apply the older stash, reporting progress along the way
+        let mut stages = Vec::new();
+        {
+            let mut opts = StashApplyOptions::new();
+            opts.progress_cb(|p| { stages.push(p); true });
+            repo.stash_apply(1, Some(&mut opts)).unwrap();
+        }
+        assert_eq!(stages.last(), Some(&StashApplyProgress::Done));
+        assert_eq!(File::open(&foo).read_to_string().unwrap(), "foo\n");
+        assert_eq!(stash_list(&repo).len(), 2);
+
+        // stashes can be inspected as commits without applying them
+        let stash = repo.find_stash(1).unwrap();
+        assert_eq!(stash.id(), first);
+        assert_eq!(stash.parent_count(), 3);
+        assert_eq!(stash.parent_id(0).unwrap(),
+                   repo.head().unwrap().target().unwrap());
+        let untracked = stash.parent(2).unwrap().tree().unwrap();
+        assert!(untracked.get_name("foo").is_some());
+        assert!(stash.tree().unwrap().get_name("foo").is_none());
+        assert!(repo.find_stash(2).is_err());
+
+        repo.stash_drop(0).unwrap();
+        let mut ids = Vec::new();
+        repo.stash_foreach(|_, _, id| { ids.push(*id); true }).unwrap();
+        assert_eq!(ids, vec![first]);
+    }
+
+    #[test]
+    fn pop() {
+        let (td, repo) = ::test::repo_init();
+        let sig = repo.signature().unwrap();
+        let foo = td.path().join("foo");
+        File::create(&foo).write_str("foo\n").unwrap();
+        repo.stash_save(&sig, None, STASH_INCLUDE_UNTRACKED).unwrap();
+        assert!(!foo.exists());
+
+        // aborting from the progress callback leaves the stash in place
+        {
+            let mut opts = StashApplyOptions::new();
+            opts.progress_cb(|_| false);
+            assert!(repo.stash_pop(0, Some(&mut opts)).is_err());
+        }
+        assert_eq!(stash_list(&repo).len(), 1);
+
+        repo.stash_pop(0, None).unwrap();
+        assert!(foo.exists());
+        assert_eq!(stash_list(&repo).len(), 0);
+        assert!(repo.stash_drop(0).is_err());
+    }
+}
@@ -5,7 +5,8 @@ use std::mem;
 use std::str;
 use libc::{c_char, size_t, c_uint};
 
-use {raw, Status, DiffDelta, IntoCString};
+use {raw, Error, ErrorCode, Pathspec, Repository, Status, DiffDelta};
+use {IntoCString, PATHSPEC_DEFAULT, PATHSPEC_NO_GLOB};
 use util::Binding;
 
 /// Options that can be provided to `repo.statuses()` to control how the status
@@ -14,6 +15,7 @@ pub struct StatusOptions {
     raw: raw::git_status_options,
     pathspec: Vec<CString>,
     ptrs: Vec<*const c_char>,
+    changed: Option<Vec<CString>>,
 }
 
 /// Enumeration of possible methods of what can be shown through a status
@@ -68,6 +70,7 @@ impl StatusOptions {
                 raw: raw,
                 pathspec: Vec::new(),
                 ptrs: Vec::new(),
+                changed: None,
             }
         }
     }
@@ -173,6 +176,16 @@ impl StatusOptions {
         self.flag(raw::GIT_STATUS_OPT_RENAMES_INDEX_TO_WORKDIR, include)
     }
 
+    /// Set the similarity threshold (as a percentage) at which a deleted and
+    /// an added file are considered to be a rename.
+    ///
+    /// This only has an effect if rename detection has been enabled with
+    /// `renames_head_to_index` or `renames_index_to_workdir`. Defaults to 50.
+    pub fn rename_threshold(&mut self, threshold: u16) -> &mut StatusOptions {
+        self.raw.rename_threshold = threshold;
+        self
+    }
+
     /// Override the native case sensitivity for the file system and force the
     /// output to be in case sensitive order.
     pub fn sort_case_sensitively(&mut self, include: bool)
@@ -207,6 +220,24 @@ impl StatusOptions {
         self.flag(raw::GIT_STATUS_OPT_UPDATE_INDEX, include)
     }
 
+    /// Only look at the given paths in the working directory, as reported
+    /// changed by a file system monitor such as watchman since the status was
+    /// last gathered.
+    ///
+    /// All other files in the working directory are assumed to be unchanged,
+    /// so they are neither read nor stat'd, which is what makes status fast
+    /// on large working directories. Changes staged in the index and
+    /// conflicts are still reported for every file. The paths are literal
+    /// paths relative to the root of the working directory, and are further
+    /// limited by any pathspec given. Untracked files among them are always
+    /// reported individually, as with `recurse_untracked_dirs`.
+    pub fn changed_paths<T, I>(&mut self, paths: I) -> &mut StatusOptions
+        where T: IntoCString, I: Iterator<Item=T>
+    {
+        self.changed = Some(paths.map(|p| p.into_c_string()).collect());
+        self
+    }
+
     // erm...
     #[allow(missing_docs)]
     pub fn include_unreadable(&mut self, include: bool) -> &mut StatusOptions {
@@ -231,11 +262,96 @@ impl StatusOptions {
     }
 }
 
+/// Gather the status of a repository, limiting the working directory to the
+/// changed paths of the options if there are any.
+pub fn list<'repo>(repo: &'repo Repository,
+                   options: Option<&mut StatusOptions>)
+                   -> Result<Statuses<'repo>, Error> {
+    let opts = match options {
+        Some(opts) => opts,
+        None => return unsafe { new_list(repo, 0 as *const _) },
+    };
+    if opts.changed.is_none() {
+        return unsafe { new_list(repo, opts.raw()) }
+    }
+
+    // Staged changes and conflicts are found through the index alone, so
+    // they are added to the paths to look at. Libgit2 then only visits
+    // these paths in the working directory.
+    let mut paths = opts.changed.as_ref().unwrap().iter().map(|p| {
+        p.as_bytes().to_vec()
+    }).collect::<Vec<_>>();
+    let index = try!(repo.index());
+    for entry in index.iter() {
+        if (entry.flags >> 12) & 3 != 0 {
+            paths.push(entry.path);
+        }
+    }
+    let head = match repo.refname_to_id("HEAD") {
+        Ok(id) => Some(try!(try!(repo.find_commit(id)).tree())),
+        Err(ref e) if e.code() == ErrorCode::NotFound ||
+                      e.code() == ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e),
+    };
+    let diff = try!(repo.diff_tree_to_index(head.as_ref(), Some(&index),
+                                            None));
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()].iter() {
+            match file.path_bytes() {
+                Some(path) => paths.push(path.to_vec()),
+                None => {}
+            }
+        }
+    }
+    paths.sort();
+    paths.dedup();
+
+    if opts.pathspec.len() > 0 {
+        let literal = raw::GIT_STATUS_OPT_DISABLE_PATHSPEC_MATCH as c_uint;
+        let flags = if opts.raw.flags & literal != 0 {
+            PATHSPEC_NO_GLOB
+        } else {
+            PATHSPEC_DEFAULT
+        };
+        let pathspec = try!(Pathspec::new(opts.pathspec.iter()));
+        paths.retain(|p| {
+            pathspec.matches_path(&Path::new(p.as_slice()), flags)
+        });
+    }
+    if paths.len() == 0 {
+        // an empty pathspec would match everything instead
+        return Ok(unsafe { Binding::from_raw(0 as *mut raw::git_status_list) })
+    }
+
+    // libgit2 does not descend into untracked directories otherwise, which
+    // would hide a changed path inside one
+    let mut limited = StatusOptions::new();
+    limited.raw.show = opts.raw.show;
+    limited.raw.flags = opts.raw.flags |
+                        raw::GIT_STATUS_OPT_RECURSE_UNTRACKED_DIRS as c_uint;
+    limited.raw.baseline = opts.raw.baseline;
+    limited.raw.rename_threshold = opts.raw.rename_threshold;
+    limited.disable_pathspec_match(true);
+    for path in paths.into_iter() {
+        limited.pathspec(CString::from_vec(path));
+    }
+    unsafe { new_list(repo, limited.raw()) }
+}
+
+unsafe fn new_list<'repo>(repo: &'repo Repository,
+                          options: *const raw::git_status_options)
+                          -> Result<Statuses<'repo>, Error> {
+    let mut ret = 0 as *mut raw::git_status_list;
+    try_call!(raw::git_status_list_new(&mut ret, repo.raw(), options));
+    Ok(Binding::from_raw(ret))
+}
+
 impl<'repo> Statuses<'repo> {
     /// Gets a status entry from this list at the specified index.
     ///
     /// Returns `None` if the index is out of bounds.
     pub fn get(&self, index: usize) -> Option<StatusEntry> {
+        if self.raw.is_null() { return None }
         unsafe {
             let p = raw::git_status_byindex(self.raw, index as size_t);
             Binding::from_raw_opt(p)
@@ -247,6 +363,7 @@ impl<'repo> Statuses<'repo> {
     /// If there are no changes in status (at least according the options given
     /// when the status list was created), this can return 0.
     pub fn len(&self) -> usize {
+        if self.raw.is_null() { return 0 }
         unsafe { raw::git_status_list_entrycount(self.raw) as usize }
     }
 
@@ -345,7 +462,9 @@ impl<'statuses> Binding for StatusEntry<'statuses> {
 
 #[cfg(test)]
 mod tests {
-    use std::old_io::File;
+    use std::iter::repeat;
+    use std::old_io::{self, fs, File};
+    use StatusOptions;
 
     #[test]
     fn smoke() {
@@ -363,4 +482,155 @@ mod tests {
         assert_eq!(diff.old_file().path_bytes().unwrap(), b"foo");
         assert_eq!(diff.new_file().path_bytes().unwrap(), b"foo");
     }
+
+    #[test]
+    fn untracked_and_ignored() {
+        let (td, repo) = ::test::repo_init();
+        fs::mkdir(&td.path().join("dir"), old_io::USER_DIR).unwrap();
+        File::create(&td.path().join("dir/a")).unwrap();
+        File::create(&td.path().join("dir/b")).unwrap();
+        File::create(&td.path().join("out.log")).unwrap();
+        File::create(&td.path().join(".gitignore"))
+            .write_str("*.log\n").unwrap();
+
+        // untracked directories are collapsed unless asked to recurse
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true);
+        let paths = repo.statuses(Some(&mut opts)).unwrap().iter()
+                        .map(|s| s.path().unwrap().to_string())
+                        .collect::<Vec<_>>();
+        assert_eq!(paths, vec![".gitignore", "dir/"]);
+        opts.recurse_untracked_dirs(true);
+        assert_eq!(repo.statuses(Some(&mut opts)).unwrap().len(), 3);
+
+        let mut opts = StatusOptions::new();
+        opts.include_ignored(true).include_untracked(false);
+        let statuses = repo.statuses(Some(&mut opts)).unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses.get(0).unwrap().path(), Some("out.log"));
+        assert_eq!(statuses.get(0).unwrap().status(), ::STATUS_IGNORED);
+
+        let mut opts = StatusOptions::new();
+        opts.recurse_untracked_dirs(true).pathspec("dir/a");
+        let statuses = repo.statuses(Some(&mut opts)).unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses.get(0).unwrap().path(), Some("dir/a"));
+
+        assert!(repo.status_should_ignore(&Path::new("foo.log")).unwrap());
+        assert!(!repo.status_should_ignore(&Path::new("dir/a")).unwrap());
+        assert_eq!(repo.status_file(&Path::new("dir/a")).unwrap(),
+                   ::STATUS_WT_NEW);
+        assert!(repo.status_file(&Path::new("missing")).is_err());
+    }
+
+    #[test]
+    fn renames() {
+        let (td, repo) = ::test::repo_init();
+        let contents = repeat("a fairly long line of content\n")
+                           .take(10).collect::<String>();
+        File::create(&td.path().join("foo")).write_str(contents.as_slice())
+                                            .unwrap();
+        {
+            let mut index = repo.index().unwrap();
+            index.add_path(&Path::new("foo")).unwrap();
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let sig = repo.signature().unwrap();
+            let head = repo.head().unwrap().target().unwrap();
+            let parent = repo.find_commit(head).unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, "foo", &tree,
+                        &[&parent]).unwrap();
+        }
+
+        // a rename in the working directory
+        fs::rename(&td.path().join("foo"), &td.path().join("bar")).unwrap();
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).renames_index_to_workdir(true);
+        let statuses = repo.statuses(Some(&mut opts)).unwrap();
+        assert_eq!(statuses.len(), 1);
+        let entry = statuses.get(0).unwrap();
+        assert_eq!(entry.status(), ::STATUS_WT_RENAMED);
+        let delta = entry.index_to_workdir().unwrap();
+        assert_eq!(delta.old_file().path_bytes(), Some(b"foo"));
+        assert_eq!(delta.new_file().path_bytes(), Some(b"bar"));
+
+        // the same rename, staged in the index
+        let mut index = repo.index().unwrap();
+        index.remove_path(&Path::new("foo")).unwrap();
+        index.add_path(&Path::new("bar")).unwrap();
+        index.write().unwrap();
+        let mut opts = StatusOptions::new();
+        opts.renames_head_to_index(true);
+        let statuses = repo.statuses(Some(&mut opts)).unwrap();
+        assert_eq!(statuses.len(), 1);
+        let entry = statuses.get(0).unwrap();
+        assert_eq!(entry.status(), ::STATUS_INDEX_RENAMED);
+        assert!(entry.head_to_index().is_some());
+
+        // without rename detection this is an addition and a deletion
+        assert_eq!(repo.statuses(None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn changed_paths() {
+        let (td, repo) = ::test::repo_init();
+        for name in ["a", "b"].iter() {
+            File::create(&td.path().join(*name)).write_str("foo\n").unwrap();
+        }
+        {
+            let mut index = repo.index().unwrap();
+            index.add_path(&Path::new("a")).unwrap();
+            index.add_path(&Path::new("b")).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let sig = repo.signature().unwrap();
+            let head = repo.head().unwrap().target().unwrap();
+            let parent = repo.find_commit(head).unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, "ab", &tree,
+                        &[&parent]).unwrap();
+        }
+        File::create(&td.path().join("a")).write_str("bar\n").unwrap();
+        File::create(&td.path().join("b")).write_str("bar\n").unwrap();
+        File::create(&td.path().join("c")).write_str("baz\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(&Path::new("c")).unwrap();
+        index.write().unwrap();
+
+        let paths = |opts: &mut StatusOptions| {
+            repo.statuses(Some(opts)).unwrap().iter().map(|s| {
+                (s.path().unwrap().to_string(), s.status())
+            }).collect::<Vec<_>>()
+        };
+        assert_eq!(paths(&mut StatusOptions::new()).len(), 3);
+
+        // only the changed paths are looked at in the working directory, but
+        // staged changes are found everywhere
+        let mut opts = StatusOptions::new();
+        opts.changed_paths(["a"].iter());
+        assert_eq!(paths(&mut opts),
+                   vec![("a".to_string(), ::STATUS_WT_MODIFIED),
+                        ("c".to_string(), ::STATUS_INDEX_NEW)]);
+
+        let mut opts = StatusOptions::new();
+        opts.changed_paths(Vec::<&str>::new().into_iter());
+        assert_eq!(paths(&mut opts),
+                   vec![("c".to_string(), ::STATUS_INDEX_NEW)]);
+
+        // pathspecs still apply
+        let mut opts = StatusOptions::new();
+        opts.changed_paths(["a", "b"].iter()).pathspec("b");
+        assert_eq!(paths(&mut opts),
+                   vec![("b".to_string(), ::STATUS_WT_MODIFIED)]);
+        let mut opts = StatusOptions::new();
+        opts.changed_paths(["a"].iter()).pathspec("b");
+        assert_eq!(paths(&mut opts), vec![]);
+
+        // untracked files are found inside untracked directories
+        fs::mkdir(&td.path().join("d"), old_io::USER_DIR).unwrap();
+        File::create(&td.path().join("d/e")).write_str("e\n").unwrap();
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).changed_paths(["d/e"].iter());
+        assert_eq!(paths(&mut opts),
+                   vec![("c".to_string(), ::STATUS_INDEX_NEW),
+                        ("d/e".to_string(), ::STATUS_WT_NEW)]);
+    }
 }
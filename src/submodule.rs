@@ -129,18 +129,6 @@ impl<'repo> Submodule<'repo> {
         Ok(())
     }
 
-    /// Write submodule settings to .gitmodules file.
-    ///
-    /// This commits any in-memory changes to the submodule to the gitmodules
-    /// file on disk. You may also be interested in `init()` which
-    /// writes submodule info to ".git/config" (which is better for local
-    /// changes to submodule settings) and/or `sync()` which writes
-    /// settings about remotes to the actual submodule repository.
-    pub fn save(&mut self) -> Result<(), Error> {
-        unsafe { try_call!(raw::git_submodule_save(self.raw)); }
-        Ok(())
-    }
-
     /// Copy submodule remote info into submodule repo.
     ///
     /// This copies the information about the submodules URL into the checked
@@ -181,7 +169,13 @@ impl<'repo> Submodule<'repo> {
     /// will return a combination of the `SubmoduleStatus` values.
     pub fn status(&self) -> Result<SubmoduleStatus, Error> {
         let mut ret = 0;
-        unsafe { try_call!(raw::git_submodule_status(&mut ret, self.raw)); }
+        unsafe {
+            try_call!(raw::git_submodule_status(
+                &mut ret,
+                raw::git_submodule_owner(self.raw),
+                raw::git_submodule_name(self.raw),
+                raw::GIT_SUBMODULE_IGNORE_UNSPECIFIED));
+        }
         Ok(SubmoduleStatus::from_bits_truncate(ret as u32))
     }
 }
@@ -207,7 +201,9 @@ impl<'repo> Drop for Submodule<'repo> {
 #[cfg(test)]
 mod tests {
     use std::old_io::TempDir;
-    use Repository;
+    use {Repository, SubmoduleIgnore};
+    use {SUBMODULE_STATUS_IN_CONFIG, SUBMODULE_STATUS_IN_HEAD};
+    use {SUBMODULE_STATUS_WD_UNTRACKED};
 
     #[test]
     fn smoke() {
@@ -235,4 +231,22 @@ mod tests {
         assert!(s.path() == Path::new("bar"));
         s.reload(true).unwrap();
     }
+
+    #[test]
+    fn status() {
+        let (_td, repo) = ::test::repo_init();
+        repo.submodule("/path/to/nowhere", &Path::new("foo"), true).unwrap();
+
+        let status = repo.submodule_status("foo", SubmoduleIgnore::Default)
+                         .unwrap();
+        assert!(status.contains(SUBMODULE_STATUS_IN_CONFIG));
+        assert!(!status.contains(SUBMODULE_STATUS_IN_HEAD));
+
+        let status = repo.submodule_status("foo", SubmoduleIgnore::All)
+                         .unwrap();
+        assert!(status.contains(SUBMODULE_STATUS_IN_CONFIG));
+        assert!(!status.contains(SUBMODULE_STATUS_WD_UNTRACKED));
+
+        assert!(repo.submodule_status("bar", SubmoduleIgnore::None).is_err());
+    }
 }
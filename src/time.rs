@@ -1,4 +1,4 @@
-use libc::{c_int, c_uint};
+use libc::{c_char, c_int, c_uint};
 
 use raw;
 use util::Binding;
@@ -22,6 +22,7 @@ impl Time {
             Binding::from_raw(raw::git_time {
                 time: time as raw::git_time_t,
                 offset: offset as c_int,
+                sign: (if offset < 0 {b'-'} else {b'+'}) as c_char,
             })
         }
     }
@@ -46,7 +47,7 @@ impl IndexTime {
     pub fn new(seconds: i64, nanoseconds: u32) -> IndexTime {
         unsafe {
             Binding::from_raw(raw::git_index_time {
-                seconds: seconds as raw::git_time_t,
+                seconds: seconds as i32,
                 nanoseconds: nanoseconds as c_uint,
             })
         }
@@ -0,0 +1,109 @@
+use std::ffi;
+use std::mem;
+use std::sync::{StaticMutex, MUTEX_INIT};
+use libc::c_char;
+
+use {raw, panic, Error, TraceLevel};
+
+/// Callback invoked with each message traced by libgit2.
+pub type TraceCb = Fn(TraceLevel, &str) + Send + Sync;
+
+static LOCK: StaticMutex = MUTEX_INIT;
+static mut CALLBACK: usize = 0;
+
+/// Route the trace output of libgit2 through the given callback.
+///
+/// Only messages at `level` or below, in terms of verbosity, are reported.
+/// Any callback previously installed is replaced.
+///
+/// The callback is invoked from whichever thread libgit2 traces a message
+/// on, and it must not call `trace_set` or `trace_clear` itself.
+pub fn trace_set<F>(level: TraceLevel, cb: F) -> Result<(), Error>
+    where F: Fn(TraceLevel, &str) + Send + Sync + 'static
+{
+    ::init();
+    let cb = Box::new(Box::new(cb) as Box<TraceCb>);
+    let level = match level {
+        TraceLevel::None => raw::GIT_TRACE_NONE,
+        TraceLevel::Fatal => raw::GIT_TRACE_FATAL,
+        TraceLevel::Error => raw::GIT_TRACE_ERROR,
+        TraceLevel::Warn => raw::GIT_TRACE_WARN,
+        TraceLevel::Info => raw::GIT_TRACE_INFO,
+        TraceLevel::Debug => raw::GIT_TRACE_DEBUG,
+        TraceLevel::Trace => raw::GIT_TRACE_TRACE,
+    };
+    unsafe {
+        let _g = LOCK.lock();
+        let f: raw::git_trace_callback = trace_cb;
+        try_call!(raw::git_trace_set(level, Some(f)));
+        free(mem::replace(&mut CALLBACK, mem::transmute(cb)));
+    }
+    Ok(())
+}
+
+/// Stop routing the trace output of libgit2 through the callback installed
+/// with `trace_set`, if any.
+pub fn trace_clear() -> Result<(), Error> {
+    ::init();
+    unsafe {
+        let _g = LOCK.lock();
+        try_call!(raw::git_trace_set(raw::GIT_TRACE_NONE, None));
+        free(mem::replace(&mut CALLBACK, 0));
+    }
+    Ok(())
+}
+
+unsafe fn free(cb: usize) {
+    if cb != 0 {
+        mem::transmute::<usize, Box<Box<TraceCb>>>(cb);
+    }
+}
+
+extern fn trace_cb(level: raw::git_trace_level_t, msg: *const c_char) {
+    let level = match level {
+        raw::GIT_TRACE_NONE => TraceLevel::None,
+        raw::GIT_TRACE_FATAL => TraceLevel::Fatal,
+        raw::GIT_TRACE_ERROR => TraceLevel::Error,
+        raw::GIT_TRACE_WARN => TraceLevel::Warn,
+        raw::GIT_TRACE_INFO => TraceLevel::Info,
+        raw::GIT_TRACE_DEBUG => TraceLevel::Debug,
+        raw::GIT_TRACE_TRACE => TraceLevel::Trace,
+    };
+    let msg = unsafe { ffi::c_str_to_bytes(&msg) };
+    let msg = String::from_utf8_lossy(msg);
+    let _g = LOCK.lock();
+    let cb = unsafe { CALLBACK };
+    if cb == 0 { return }
+    let cb = unsafe { &*(cb as *const Box<TraceCb>) };
+    panic::wrap(|| cb(level, &*msg));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
+    use TraceLevel;
+
+    static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    struct Counter;
+
+    impl Drop for Counter {
+        fn drop(&mut self) { DROPS.fetch_add(1, Ordering::SeqCst); }
+    }
+
+    #[test]
+    fn smoke() {
+        let counter = Counter;
+        super::trace_set(TraceLevel::Trace, move |_, _| {
+            let _ = &counter;
+        }).unwrap();
+        assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+
+        // replacing and clearing the callback frees the previous one
+        super::trace_set(TraceLevel::Error, |_, _| {}).unwrap();
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+        super::trace_clear().unwrap();
+        super::trace_clear().unwrap();
+        assert!(TraceLevel::Error < TraceLevel::Debug);
+    }
+}
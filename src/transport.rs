@@ -5,7 +5,6 @@ use std::mem;
 use std::old_io::IoError;
 use std::slice;
 use std::str;
-use std::sync::{StaticMutex, MUTEX_INIT};
 use libc::{c_int, c_void, c_uint, c_char, size_t};
 
 use {raw, panic, Error, Remote};
@@ -100,6 +99,11 @@ struct RawSmartSubtransportStream {
 /// Add a custom transport definition, to be used in addition to the built-in
 /// set of transports that come with libgit2.
 ///
+/// Custom transports take precedence over the built-in ones, so registering
+/// a prefix such as `file://` replaces the transport libgit2 would otherwise
+/// use for it. `Transport::local` can be used to create the built-in local
+/// transport from within the factory.
+///
 /// This function is unsafe as it needs to be externally synchronized with calls
 /// to creation of other transports.
 pub unsafe fn register<F>(prefix: &str, factory: F) -> Result<(), Error>
@@ -117,7 +121,36 @@ pub unsafe fn register<F>(prefix: &str, factory: F) -> Result<(), Error>
     Ok(())
 }
 
+/// Remove a custom transport definition which was previously added with
+/// `register`, restoring the built-in transport for the prefix if any.
+///
+/// The factory which was registered is leaked rather than destroyed, as
+/// transports which it created may still be alive.
+///
+/// This function is unsafe as it needs to be externally synchronized with calls
+/// to creation of other transports.
+pub unsafe fn unregister(prefix: &str) -> Result<(), Error> {
+    let prefix = CString::from_slice(prefix.as_bytes());
+    try_call!(raw::git_transport_unregister(prefix));
+    Ok(())
+}
+
 impl Transport {
+    /// Creates a new instance of the built-in transport for repositories on
+    /// the local filesystem.
+    ///
+    /// This transport streams objects through a pack, in the same way as
+    /// remote transports do, and so never creates hardlinks to the objects of
+    /// the source repository.
+    pub fn local(remote: &Remote) -> Result<Transport, Error> {
+        let mut ret = 0 as *mut raw::git_transport;
+        unsafe {
+            try_call!(raw::git_transport_local(&mut ret, remote.raw(),
+                                               0 as *mut c_void));
+        }
+        Ok(Transport { raw: ret, owned: true })
+    }
+
     /// Creates a new transport which will use the "smart" transport protocol
     /// for transferring data.
     ///
@@ -133,16 +166,9 @@ impl Transport {
                     subtransport: S) -> Result<Transport, Error>
         where S: SmartSubtransport
     {
-        static LOCK: StaticMutex = MUTEX_INIT;
-        static mut PTR: usize = 0;
-
-        let mut defn = raw::git_smart_subtransport_definition {
-            callback: smart_factory,
-            rpc: rpc as c_uint,
-        };
         let mut ret = 0 as *mut _;
 
-        let raw = Box::new(RawSmartSubtransport {
+        let mut raw = Box::new(RawSmartSubtransport {
             raw: raw::git_smart_subtransport {
                 action: subtransport_action,
                 close: subtransport_close,
@@ -150,31 +176,24 @@ impl Transport {
             },
             obj: Box::new(subtransport),
         });
+        let mut defn = raw::git_smart_subtransport_definition {
+            callback: smart_factory,
+            rpc: rpc as c_uint,
+            param: &mut *raw as *mut RawSmartSubtransport as *mut c_void,
+        };
 
-        // Currently there's no way to pass a paload via the
-        // git_smart_subtransport_definition structure, but it's only used as a
-        // configuration for the initial creation of the smart transport (verified
-        // by reading the current code, hopefully it doesn't change!).
-        //
-        // We, however, need some state (gotta pass in our
-        // `RawSmartSubtransport`). This also means that this block must be
-        // entirely synchronized with a lock (boo!)
         unsafe {
-            let _g = LOCK.lock();
-            assert!(PTR == 0);
-            PTR = &*raw as *const RawSmartSubtransport as usize;
             try_call!(raw::git_transport_smart(&mut ret, remote.raw(),
                                                &mut defn as *mut _ as *mut _));
             mem::forget(raw); // ownership transport to `ret`
-            PTR = 0;
         }
         return Ok(Transport { raw: ret, owned: true });
 
         extern fn smart_factory(out: *mut *mut raw::git_smart_subtransport,
-                                _owner: *mut raw::git_transport) -> c_int {
+                                _owner: *mut raw::git_transport,
+                                param: *mut c_void) -> c_int {
             unsafe {
-                assert!(PTR as usize != 0);
-                *out = PTR as *mut raw::git_smart_subtransport;
+                *out = param as *mut raw::git_smart_subtransport;
                 0
             }
         }
@@ -305,7 +324,7 @@ extern fn stream_write(stream: *mut raw::git_smart_subtransport_stream,
 
 unsafe fn set_err(e: IoError) {
     let s = CString::from_slice(e.to_string().as_bytes());
-    raw::giterr_set_str(raw::GITERR_NET as c_int, s.as_ptr())
+    raw::git_error_set_str(raw::GITERR_NET as c_int, s.as_ptr());
 }
 
 // callback used by smart transports to free a `SmartSubtransportStream`
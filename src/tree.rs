@@ -6,7 +6,7 @@ use std::marker;
 use std::str;
 use libc;
 
-use {raw, Oid, Repository, Error, Object, ObjectType};
+use {raw, Oid, Repository, Error, Object, ObjectType, IntoCString};
 use util::Binding;
 
 /// A structure to represent a git [tree][1]
@@ -31,6 +31,17 @@ pub struct TreeIter<'tree> {
     tree: &'tree Tree<'tree>,
 }
 
+/// A single modification to apply to a tree through `Tree::create_updated`.
+///
+/// Updates address entries by their full path relative to the root of the
+/// tree, and any intermediate trees are created or pruned as necessary.
+pub struct TreeUpdate {
+    action: raw::git_tree_update_t,
+    id: raw::git_oid,
+    filemode: i32,
+    path: CString,
+}
+
 impl<'repo> Tree<'repo> {
     /// Get the id (SHA1) of a repository object
     pub fn id(&self) -> Oid {
@@ -54,7 +65,7 @@ impl<'repo> Tree<'repo> {
             if ptr.is_null() {
                 None
             } else {
-                Some(TreeEntry::from_raw_const(ptr))
+                Some(entry_from_raw_const(ptr))
             }
         }
     }
@@ -67,7 +78,7 @@ impl<'repo> Tree<'repo> {
             if ptr.is_null() {
                 None
             } else {
-                Some(TreeEntry::from_raw_const(ptr))
+                Some(entry_from_raw_const(ptr))
             }
         }
     }
@@ -80,7 +91,7 @@ impl<'repo> Tree<'repo> {
             if ptr.is_null() {
                 None
             } else {
-                Some(TreeEntry::from_raw_const(ptr))
+                Some(entry_from_raw_const(ptr))
             }
         }
     }
@@ -95,6 +106,60 @@ impl<'repo> Tree<'repo> {
             Ok(Binding::from_raw(ret))
         }
     }
+
+    /// Create a new tree based on this one with the given updates applied.
+    ///
+    /// Each update may insert, replace, or remove an entry at an arbitrary
+    /// path beneath this tree. This is considerably cheaper than building
+    /// nested `TreeBuilder`s by hand for deep paths as every intermediate tree
+    /// is only written once.
+    ///
+    /// The same path should not be specified by more than one update, and the
+    /// updates are not validated against each other.
+    pub fn create_updated(&self, repo: &Repository, updates: &[TreeUpdate])
+                          -> Result<Oid, Error> {
+        let raw_updates = updates.iter().map(|u| {
+            raw::git_tree_update {
+                action: u.action,
+                id: u.id,
+                filemode: u.filemode as libc::c_uint,
+                path: u.path.as_ptr(),
+            }
+        }).collect::<Vec<_>>();
+        let mut raw = raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] };
+        unsafe {
+            try_call!(raw::git_tree_create_updated(&mut raw, repo.raw(),
+                                                   self.raw,
+                                                   raw_updates.len()
+                                                        as libc::size_t,
+                                                   raw_updates.as_ptr()));
+            Ok(Binding::from_raw(&raw as *const _))
+        }
+    }
+}
+
+impl TreeUpdate {
+    /// Insert or replace the entry at `path` with the object `id` and the
+    /// given file mode (e.g. 0o100644 for a regular blob).
+    pub fn upsert<P: IntoCString>(path: P, id: Oid, filemode: i32)
+                                  -> TreeUpdate {
+        TreeUpdate {
+            action: raw::GIT_TREE_UPDATE_UPSERT,
+            id: unsafe { *id.raw() },
+            filemode: filemode,
+            path: path.into_c_string(),
+        }
+    }
+
+    /// Remove the entry at `path` from the tree.
+    pub fn remove<P: IntoCString>(path: P) -> TreeUpdate {
+        TreeUpdate {
+            action: raw::GIT_TREE_UPDATE_REMOVE,
+            id: raw::git_oid { id: [0; raw::GIT_OID_RAWSZ] },
+            filemode: 0,
+            path: path.into_c_string(),
+        }
+    }
 }
 
 impl<'repo> Binding for Tree<'repo> {
@@ -116,20 +181,20 @@ impl<'repo> Drop for Tree<'repo> {
     }
 }
 
-impl<'tree> TreeEntry<'tree> {
-    /// Create a new tree entry from the raw pointer provided.
-    ///
-    /// The lifetime of the entry is tied to the tree provided and the function
-    /// is unsafe because the validity of the pointer cannot be guaranteed.
-    unsafe fn from_raw_const(raw: *const raw::git_tree_entry)
-                                 -> TreeEntry<'tree> {
-        TreeEntry {
-            raw: raw as *mut raw::git_tree_entry,
-            owned: false,
-            marker: marker::ContravariantLifetime,
-        }
+/// Create a new tree entry from the raw pointer provided.
+///
+/// The lifetime of the entry is tied to the tree provided and the function
+/// is unsafe because the validity of the pointer cannot be guaranteed.
+pub unsafe fn entry_from_raw_const<'tree>(raw: *const raw::git_tree_entry)
+                                          -> TreeEntry<'tree> {
+    TreeEntry {
+        raw: raw as *mut raw::git_tree_entry,
+        owned: false,
+        marker: marker::ContravariantLifetime,
     }
+}
 
+impl<'tree> TreeEntry<'tree> {
     /// Get the id of the object pointed by the entry
     pub fn id(&self) -> Oid {
         unsafe { Binding::from_raw(raw::git_tree_entry_id(&*self.raw)) }
@@ -248,6 +313,7 @@ impl<'tree> ExactSizeIterator for TreeIter<'tree> {}
 #[cfg(test)]
 mod tests {
     use std::old_io::File;
+    use super::TreeUpdate;
 
     #[test]
     fn smoke() {
@@ -280,4 +346,28 @@ mod tests {
 
         repo.find_object(commit.tree_id(), None).unwrap().as_tree().unwrap();
     }
+
+    #[test]
+    fn create_updated() {
+        let (_td, repo) = ::test::repo_init();
+        let blob = repo.blob(b"data").unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        let tree = repo.find_commit(head).unwrap().tree().unwrap();
+
+        let id = tree.create_updated(&repo, &[
+            TreeUpdate::upsert("a/b/c", blob, 0o100644),
+            TreeUpdate::upsert("d", blob, 0o100755),
+        ]).unwrap();
+        let updated = repo.find_tree(id).unwrap();
+        assert_eq!(updated.len(), 2);
+        let entry = updated.get_path(&Path::new("a/b/c")).unwrap();
+        assert_eq!(entry.id(), blob);
+
+        let id = updated.create_updated(&repo, &[
+            TreeUpdate::remove("a/b/c"),
+        ]).unwrap();
+        let updated = repo.find_tree(id).unwrap();
+        assert_eq!(updated.len(), 1);
+        assert!(updated.get_name("a").is_none());
+    }
 }
@@ -1,9 +1,11 @@
 use std::ffi::{CString, AsOsStr, OsStr, OsString};
+use std::iter::range;
 use std::path::Path as NewPath;
 use std::path::PathBuf;
+use std::old_io::IoError;
 use libc::{c_char, size_t};
 
-use raw;
+use {raw, Error, ErrorCode, ErrorClass};
 
 #[doc(hidden)]
 pub trait Binding: Sized {
@@ -36,6 +38,110 @@ pub fn iter2cstrs<T, I>(iter: I) -> (Vec<CString>, Vec<*const c_char>,
     (cstrs, ptrs, raw)
 }
 
+pub fn io_error(e: IoError) -> Error {
+    Error::new(ErrorCode::GenericError, ErrorClass::Os, e.desc)
+}
+
+pub fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let mut ret = Vec::new();
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in s.bytes().take_while(|c| *c != b'=') {
+        let val = match c {
+            b'A'...b'Z' => c - b'A',
+            b'a'...b'z' => c - b'a' + 26,
+            b'0'...b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        acc = (acc << 6) | val as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            ret.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(ret)
+}
+
+// Days since the unix epoch of the given date in the proleptic Gregorian
+// calendar.
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// The inverse of `days_from_civil`, returning the year, month and day.
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Compute the SHA-1 hash of `data`.
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h = [0x67452301u32, 0xefcdab89, 0x98badcfe, 0x10325476,
+                 0xc3d2e1f0];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 { msg.push(0) }
+    let bits = (data.len() as u64) * 8;
+    for i in range(0, 8) {
+        msg.push((bits >> (56 - i * 8)) as u8);
+    }
+    for chunk in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in range(0, 16) {
+            w[i] = (chunk[i * 4] as u32) << 24 |
+                   (chunk[i * 4 + 1] as u32) << 16 |
+                   (chunk[i * 4 + 2] as u32) << 8 |
+                   (chunk[i * 4 + 3] as u32);
+        }
+        for i in range(16, 80) {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let (mut a, mut b, mut c) = (h[0], h[1], h[2]);
+        let (mut d, mut e) = (h[3], h[4]);
+        for i in range(0, 80) {
+            let (f, k) = match i {
+                0...19 => ((b & c) | (!b & d), 0x5a827999),
+                20...39 => (b ^ c ^ d, 0x6ed9eba1),
+                40...59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e)
+                     .wrapping_add(k).wrapping_add(w[i]);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e].iter()) {
+            *h = h.wrapping_add(*v);
+        }
+    }
+    let mut ret = [0u8; 20];
+    for i in range(0, 20) {
+        ret[i] = (h[i / 4] >> (24 - (i % 4) * 8)) as u8;
+    }
+    ret
+}
+
 /// A class of types that can be converted to C strings.
 ///
 /// These types are represented internally as byte slices and it is quite rare
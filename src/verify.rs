@@ -0,0 +1,28 @@
+/// The outcome of verifying the signature of a commit, as reported by the
+/// verifier passed to `Repository::verify_commit_signature`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SignatureVerification {
+    valid: bool,
+    signer: Option<String>,
+}
+
+impl SignatureVerification {
+    /// Create a new outcome of a verification, with the identity of the
+    /// signer if it is known.
+    pub fn new(valid: bool, signer: Option<&str>) -> SignatureVerification {
+        SignatureVerification {
+            valid: valid,
+            signer: signer.map(|s| s.to_string()),
+        }
+    }
+
+    /// Whether the signature is a good signature of the commit made by a
+    /// trusted key.
+    pub fn is_valid(&self) -> bool { self.valid }
+
+    /// Get the identity of the signer, such as the principal of an SSH key
+    /// or the user id of a GPG key, if it is known.
+    pub fn signer(&self) -> Option<&str> {
+        self.signer.as_ref().map(|s| s.as_slice())
+    }
+}
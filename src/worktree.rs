@@ -0,0 +1,213 @@
+use std::ffi::{self, CString};
+use std::mem;
+use std::str;
+
+use {raw, Buf, Error};
+use util::Binding;
+
+/// A linked working tree of a repository, as created by `git worktree add`.
+///
+/// Worktrees are found through `Repository::find_worktree` and created with
+/// `Repository::worktree`.
+pub struct Worktree {
+    raw: *mut raw::git_worktree,
+}
+
+/// Options which decide which worktrees `Worktree::is_prunable` accepts and
+/// what `Worktree::prune` removes.
+///
+/// By default only worktrees whose working directory has gone missing, and
+/// which are not locked, are considered prunable.
+pub struct WorktreePruneOptions {
+    raw: raw::git_worktree_prune_options,
+}
+
+impl Worktree {
+    /// Get the name of this worktree, as stored under `.git/worktrees`.
+    ///
+    /// Returns `None` if the name is not valid utf-8.
+    pub fn name(&self) -> Option<&str> {
+        str::from_utf8(self.name_bytes()).ok()
+    }
+
+    /// Get the name of this worktree, as a byte slice.
+    pub fn name_bytes(&self) -> &[u8] {
+        unsafe { ::opt_bytes(self, raw::git_worktree_name(self.raw)).unwrap() }
+    }
+
+    /// Get the path of this worktree's working directory.
+    pub fn path(&self) -> Path {
+        unsafe {
+            let ptr = raw::git_worktree_path(self.raw);
+            assert!(!ptr.is_null());
+            Path::new(ffi::c_str_to_bytes(&ptr))
+        }
+    }
+
+    /// Check that this worktree is still valid, meaning that its gitdir,
+    /// its parent repository and its working directory all exist.
+    ///
+    /// An error describing what is missing is returned otherwise.
+    pub fn validate(&self) -> Result<(), Error> {
+        unsafe { try_call!(raw::git_worktree_validate(self.raw)); }
+        Ok(())
+    }
+
+    /// Lock this worktree, protecting it from being pruned, optionally
+    /// recording the reason why.
+    pub fn lock(&self, reason: Option<&str>) -> Result<(), Error> {
+        let reason = reason.map(|s| CString::from_slice(s.as_bytes()));
+        unsafe { try_call!(raw::git_worktree_lock(self.raw, reason)); }
+        Ok(())
+    }
+
+    /// Unlock this worktree.
+    ///
+    /// Returns `false` if the worktree was not locked in the first place.
+    pub fn unlock(&self) -> Result<bool, Error> {
+        let rc = unsafe { try_call!(raw::git_worktree_unlock(self.raw)) };
+        Ok(rc == 0)
+    }
+
+    /// Check whether this worktree is locked, returning the reason it was
+    /// locked with if so.
+    ///
+    /// The reason is empty if none was given when locking.
+    pub fn is_locked(&self) -> Result<Option<Buf>, Error> {
+        let buf = Buf::new();
+        unsafe {
+            match try_call!(raw::git_worktree_is_locked(buf.raw(), self.raw)) {
+                0 => Ok(None),
+                _ => Ok(Some(buf)),
+            }
+        }
+    }
+
+    /// Check whether this worktree may be pruned with the given options.
+    ///
+    /// Passing `None` uses the default options, under which only invalid
+    /// worktrees which are not locked are prunable.
+    pub fn is_prunable(&self, opts: Option<&mut WorktreePruneOptions>)
+                       -> Result<bool, Error> {
+        let mut default = WorktreePruneOptions::new();
+        let opts = opts.unwrap_or(&mut default);
+        unsafe {
+            let rc = try_call!(raw::git_worktree_is_prunable(self.raw,
+                                                             &mut opts.raw));
+            Ok(rc != 0)
+        }
+    }
+
+    /// Prune this worktree, removing its administrative files from the
+    /// parent repository, and its working directory as well if asked to.
+    ///
+    /// This fails unless the worktree is prunable under the same options.
+    pub fn prune(&self, opts: Option<&mut WorktreePruneOptions>)
+                 -> Result<(), Error> {
+        let mut default = WorktreePruneOptions::new();
+        let opts = opts.unwrap_or(&mut default);
+        unsafe {
+            try_call!(raw::git_worktree_prune(self.raw, &mut opts.raw));
+        }
+        Ok(())
+    }
+}
+
+impl Binding for Worktree {
+    type Raw = *mut raw::git_worktree;
+    unsafe fn from_raw(raw: *mut raw::git_worktree) -> Worktree {
+        Worktree { raw: raw }
+    }
+    fn raw(&self) -> *mut raw::git_worktree { self.raw }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        unsafe { raw::git_worktree_free(self.raw) }
+    }
+}
+
+impl WorktreePruneOptions {
+    /// Creates a new default set of prune options.
+    pub fn new() -> WorktreePruneOptions {
+        let mut opts = WorktreePruneOptions { raw: unsafe { mem::zeroed() } };
+        assert_eq!(unsafe {
+            raw::git_worktree_prune_init_options(&mut opts.raw,
+                                raw::GIT_WORKTREE_PRUNE_OPTIONS_VERSION)
+        }, 0);
+        opts
+    }
+
+    fn flag(&mut self, flag: u32, on: bool) -> &mut WorktreePruneOptions {
+        if on {
+            self.raw.flags |= flag;
+        } else {
+            self.raw.flags &= !flag;
+        }
+        self
+    }
+
+    /// Consider worktrees prunable even if they are still valid, that is
+    /// when their working directory still exists.
+    ///
+    /// Defaults to false.
+    pub fn valid(&mut self, valid: bool) -> &mut WorktreePruneOptions {
+        self.flag(raw::GIT_WORKTREE_PRUNE_VALID, valid)
+    }
+
+    /// Consider worktrees prunable even if they are locked.
+    ///
+    /// Defaults to false.
+    pub fn locked(&mut self, locked: bool) -> &mut WorktreePruneOptions {
+        self.flag(raw::GIT_WORKTREE_PRUNE_LOCKED, locked)
+    }
+
+    /// Remove the working directory of a worktree along with its
+    /// administrative files when pruning it.
+    ///
+    /// Defaults to false.
+    pub fn working_tree(&mut self, working_tree: bool)
+                        -> &mut WorktreePruneOptions {
+        self.flag(raw::GIT_WORKTREE_PRUNE_WORKING_TREE, working_tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::old_io::TempDir;
+    use std::old_io::fs::PathExtensions;
+    use WorktreePruneOptions;
+
+    #[test]
+    fn prune() {
+        let (_td, repo) = ::test::repo_init();
+        let td2 = TempDir::new("test").unwrap();
+        let path = td2.path().join("wt");
+        let wt = repo.worktree("wt", &path).unwrap();
+        assert_eq!(wt.name(), Some("wt"));
+        assert_eq!(repo.worktrees().unwrap().iter().collect::<Vec<_>>(),
+                   vec![Some("wt")]);
+        wt.validate().unwrap();
+
+        // a valid worktree is only prunable when asked for explicitly
+        assert!(!wt.is_prunable(None).unwrap());
+        let mut opts = WorktreePruneOptions::new();
+        opts.valid(true);
+        assert!(wt.is_prunable(Some(&mut opts)).unwrap());
+
+        // locking protects it unless locked worktrees are included too
+        wt.lock(Some("in use")).unwrap();
+        assert_eq!(&*wt.is_locked().unwrap().unwrap(), b"in use");
+        assert!(!wt.is_prunable(Some(&mut opts)).unwrap());
+        opts.locked(true);
+        assert!(wt.is_prunable(Some(&mut opts)).unwrap());
+        assert!(wt.unlock().unwrap());
+        assert!(!wt.unlock().unwrap());
+        assert!(wt.is_locked().unwrap().is_none());
+
+        opts.locked(false).working_tree(true);
+        wt.prune(Some(&mut opts)).unwrap();
+        assert!(!path.exists());
+        assert!(repo.find_worktree("wt").is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! A minimal reader for the DER encoding of X.509 certificates, exposing the
+//! fields which are useful when deciding whether to trust a certificate.
+
+use std::iter::range;
+use std::str;
+
+use {Error, Time};
+use cert::CertX509;
+use util;
+
+macro_rules! try_opt( ($e:expr) => (
+    match $e { Some(e) => e, None => return None }
+) );
+
+static SAN: &'static [u8] = &[0x55, 0x1d, 0x11];
+
+static ATTRIBUTES: &'static [(&'static [u8], &'static str)] = &[
+    (&[0x55, 0x04, 0x03], "CN"),
+    (&[0x55, 0x04, 0x06], "C"),
+    (&[0x55, 0x04, 0x07], "L"),
+    (&[0x55, 0x04, 0x08], "ST"),
+    (&[0x55, 0x04, 0x0a], "O"),
+    (&[0x55, 0x04, 0x0b], "OU"),
+];
+
+/// The parsed contents of an X.509 certificate, as returned by
+/// `CertX509::parse`.
+pub struct X509Info {
+    subject: String,
+    issuer: String,
+    not_before: Time,
+    not_after: Time,
+    alt_names: Vec<String>,
+}
+
+impl<'a> CertX509<'a> {
+    /// Parse the fields of this certificate which are commonly inspected
+    /// when deciding whether to trust it.
+    ///
+    /// libgit2 only provides the certificate of the server itself, not the
+    /// chain which was used to verify it.
+    pub fn parse(&self) -> Result<X509Info, Error> {
+        X509Info::from_der(self.data()).ok_or_else(|| {
+            Error::from_str("failed to parse the X.509 certificate")
+        })
+    }
+}
+
+impl X509Info {
+    /// Parse a DER encoded certificate.
+    ///
+    /// Returns `None` if the data is not a well-formed certificate.
+    pub fn from_der(der: &[u8]) -> Option<X509Info> {
+        let (cert, _) = try_opt!(expect(der, 0x30));
+        let (tbs, _) = try_opt!(expect(cert, 0x30));
+
+        // skip the version, serial number and signature algorithm
+        let mut rest = tbs;
+        if rest.first() == Some(&0xa0) {
+            rest = try_opt!(read(rest)).2;
+        }
+        let rest = try_opt!(expect(rest, 0x02)).1;
+        let rest = try_opt!(expect(rest, 0x30)).1;
+
+        let (issuer, rest) = try_opt!(expect(rest, 0x30));
+        let (validity, rest) = try_opt!(expect(rest, 0x30));
+        let (subject, rest) = try_opt!(expect(rest, 0x30));
+        let (_, rest) = try_opt!(expect(rest, 0x30));
+
+        let (tag, not_before, validity) = try_opt!(read(validity));
+        let not_before = try_opt!(parse_time(tag, not_before));
+        let (tag, not_after, _) = try_opt!(read(validity));
+        let not_after = try_opt!(parse_time(tag, not_after));
+
+        // the extensions are the only optional field we look at
+        let mut alt_names = Vec::new();
+        let mut rest = rest;
+        while !rest.is_empty() {
+            let (tag, field, next) = try_opt!(read(rest));
+            rest = next;
+            if tag != 0xa3 { continue }
+            let (mut exts, _) = try_opt!(expect(field, 0x30));
+            while !exts.is_empty() {
+                let (ext, next) = try_opt!(expect(exts, 0x30));
+                exts = next;
+                let (oid, ext) = try_opt!(expect(ext, 0x06));
+                if oid != SAN { continue }
+                let ext = match ext.first() {
+                    Some(&0x01) => try_opt!(read(ext)).2,
+                    _ => ext,
+                };
+                let (value, _) = try_opt!(expect(ext, 0x04));
+                alt_names = try_opt!(parse_alt_names(value));
+            }
+        }
+
+        Some(X509Info {
+            subject: try_opt!(parse_name(subject)),
+            issuer: try_opt!(parse_name(issuer)),
+            not_before: not_before,
+            not_after: not_after,
+            alt_names: alt_names,
+        })
+    }
+
+    /// The distinguished name of the subject of the certificate, such as
+    /// `C=US, O=Example, CN=example.com`.
+    pub fn subject(&self) -> &str { self.subject.as_slice() }
+
+    /// The distinguished name of the issuer of the certificate.
+    pub fn issuer(&self) -> &str { self.issuer.as_slice() }
+
+    /// The time from which the certificate is valid.
+    pub fn not_before(&self) -> Time { self.not_before }
+
+    /// The time after which the certificate is no longer valid.
+    pub fn not_after(&self) -> Time { self.not_after }
+
+    /// The DNS names and IP addresses listed in the subject alternative
+    /// name extension of the certificate.
+    pub fn subject_alt_names(&self) -> &[String] { self.alt_names.as_slice() }
+}
+
+// Read a single DER element, returning its tag, contents and the remaining
+// data.
+fn read(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    if data.len() < 2 { return None }
+    let tag = data[0];
+    let (len, start) = match data[1] {
+        n if n < 0x80 => (n as usize, 2),
+        n => {
+            let nbytes = (n & 0x7f) as usize;
+            if nbytes == 0 || nbytes > 4 || data.len() < 2 + nbytes {
+                return None
+            }
+            let len = data[2..2 + nbytes].iter().fold(0, |len, b| {
+                (len << 8) | *b as usize
+            });
+            (len, 2 + nbytes)
+        }
+    };
+    if data.len() - start < len { return None }
+    Some((tag, &data[start..start + len], &data[start + len..]))
+}
+
+fn expect(data: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    match read(data) {
+        Some((t, contents, rest)) if t == tag => Some((contents, rest)),
+        _ => None,
+    }
+}
+
+fn parse_name(mut name: &[u8]) -> Option<String> {
+    let mut parts = Vec::new();
+    while !name.is_empty() {
+        let (mut set, next) = try_opt!(expect(name, 0x31));
+        name = next;
+        while !set.is_empty() {
+            let (attr, next) = try_opt!(expect(set, 0x30));
+            set = next;
+            let (oid, attr) = try_opt!(expect(attr, 0x06));
+            let (_, value, _) = try_opt!(read(attr));
+            let key = match ATTRIBUTES.iter().find(|&&(o, _)| o == oid) {
+                Some(&(_, key)) => key.to_string(),
+                None => try_opt!(format_oid(oid)),
+            };
+            let value = String::from_utf8_lossy(value);
+            parts.push(format!("{}={}", key, value));
+        }
+    }
+    Some(parts.connect(", "))
+}
+
+fn format_oid(oid: &[u8]) -> Option<String> {
+    let first = *try_opt!(oid.first());
+    let mut parts = vec![(first / 40) as u64, (first % 40) as u64];
+    let mut cur = 0u64;
+    for b in oid[1..].iter() {
+        cur = (cur << 7) | (*b & 0x7f) as u64;
+        if *b & 0x80 == 0 {
+            parts.push(cur);
+            cur = 0;
+        }
+    }
+    Some(parts.iter().map(|p| p.to_string()).collect::<Vec<_>>().connect("."))
+}
+
+fn parse_alt_names(value: &[u8]) -> Option<Vec<String>> {
+    let (mut names, _) = try_opt!(expect(value, 0x30));
+    let mut ret = Vec::new();
+    while !names.is_empty() {
+        let (tag, name, next) = try_opt!(read(names));
+        names = next;
+        match tag {
+            // dNSName
+            0x82 => ret.push(try_opt!(str::from_utf8(name).ok()).to_string()),
+            // iPAddress
+            0x87 if name.len() == 4 => {
+                ret.push(format!("{}.{}.{}.{}", name[0], name[1], name[2],
+                                 name[3]));
+            }
+            0x87 if name.len() == 16 => {
+                let groups = range(0, 8).map(|i| {
+                    format!("{:x}", (name[i * 2] as u16) << 8 |
+                                    name[i * 2 + 1] as u16)
+                }).collect::<Vec<_>>();
+                ret.push(groups.connect(":"));
+            }
+            _ => {}
+        }
+    }
+    Some(ret)
+}
+
+// Parse a UTCTime or GeneralizedTime, which are always in UTC in
+// certificates.
+fn parse_time(tag: u8, time: &[u8]) -> Option<Time> {
+    // only digits and the trailing `Z` are allowed, which also keeps the
+    // slicing below on character boundaries
+    match time.last() {
+        Some(&b'Z') => {}
+        _ => return None,
+    }
+    if !time[..time.len() - 1].iter().all(|b| b'0' <= *b && *b <= b'9') {
+        return None
+    }
+    let time = try_opt!(str::from_utf8(time).ok());
+    let (year, rest) = match tag {
+        0x17 if time.len() == 13 => {
+            let year: i64 = try_opt!(time[..2].parse().ok());
+            (if year < 50 { 2000 + year } else { 1900 + year }, &time[2..])
+        }
+        0x18 if time.len() == 15 => {
+            (try_opt!(time[..4].parse().ok()), &time[4..])
+        }
+        _ => return None,
+    };
+    let mut fields = [0i64; 5];
+    for i in range(0, 5) {
+        fields[i] = try_opt!(rest[i * 2..i * 2 + 2].parse().ok());
+    }
+    let days = util::days_from_civil(year, fields[0], fields[1]);
+    let (hour, minute, second) = (fields[2], fields[3], fields[4]);
+
+    Some(Time::new(days * 86400 + hour * 3600 + minute * 60 + second, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_time, X509Info};
+
+    // a self-signed certificate for example.com
+    static CERT: &'static str = "\
+        MIIB4jCCAYigAwIBAgIBATAKBggqhkjOPQQDAjA5MQswCQYDVQQGEwJVUzEUMBIGA1UE\
+        CgwLRXhhbXBsZSBPcmcxFDASBgNVBAMMC2V4YW1wbGUuY29tMB4XDTI2MTAxNTIzNDcy\
+        NFoXDTI3MTAxNTIzNDcyNFowOTELMAkGA1UEBhMCVVMxFDASBgNVBAoMC0V4YW1wbGUg\
+        T3JnMRQwEgYDVQQDDAtleGFtcGxlLmNvbTBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IA\
+        BKYZjCjF5SdrsEs2HsdsbnLvRGugmmj/fXTEDLTrN7ykhVYZr3NqMXfCN2/NlplWI3Dc\
+        RnwGo/4D0s2Lyp0niqmjgYAwfjAdBgNVHQ4EFgQUjipqJIMCfpvgX312D+MXNv9er/0w\
+        HwYDVR0jBBgwFoAUjipqJIMCfpvgX312D+MXNv9er/0wDwYDVR0TAQH/BAUwAwEB/zAr\
+        BgNVHREEJDAiggtleGFtcGxlLmNvbYINKi5leGFtcGxlLmNvbYcEwAACATAKBggqhkjO\
+        PQQDAgNIADBFAiEAj0YwL8Yf1bw21Q43xdTRlY9ddlX+etzuIqT9/dseHIUCIB4NdwlZ\
+        QmN2/zyMnxZcuofhUvHwKEdHczfSnEyhvUCl";
+
+    #[test]
+    fn parse() {
+        let der = ::util::base64_decode(CERT).unwrap();
+        let info = X509Info::from_der(der.as_slice()).unwrap();
+        assert_eq!(info.subject(), "C=US, O=Example Org, CN=example.com");
+        assert_eq!(info.issuer(), info.subject());
+        assert_eq!(info.not_before().seconds(), 1792108044);
+        assert_eq!(info.not_after().seconds(), 1823644044);
+        assert_eq!(info.subject_alt_names(),
+                   ["example.com".to_string(), "*.example.com".to_string(),
+                    "192.0.2.1".to_string()].as_slice());
+
+        assert!(X509Info::from_der(&der[..100]).is_none());
+        assert!(X509Info::from_der(b"").is_none());
+    }
+
+    #[test]
+    fn time() {
+        let t = parse_time(0x17, b"261015234724Z").unwrap();
+        assert_eq!(t.seconds(), 1792108044);
+        let t = parse_time(0x18, b"20261015234724Z").unwrap();
+        assert_eq!(t.seconds(), 1792108044);
+
+        assert!(parse_time(0x17, "\u{e9}1015234724Z".as_bytes()).is_none());
+        assert!(parse_time(0x17, b"2610152347+4Z").is_none());
+        assert!(parse_time(0x17, b"2610152347240").is_none());
+        assert!(parse_time(0x17, b"").is_none());
+    }
+}